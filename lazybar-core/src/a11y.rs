@@ -0,0 +1,149 @@
+use anyhow::Result;
+use tokio::sync::mpsc::{
+    unbounded_channel, UnboundedReceiver, UnboundedSender,
+};
+use zbus::{connection, interface, SignalContext};
+
+use crate::Alignment;
+
+/// The object path at which the bar's contents are exported
+pub const PATH: &str = "/org/lazybar/Bar";
+
+/// The text content of one panel
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Update {
+    /// The panel's id
+    pub id: String,
+    /// The panel's role, in AT-SPI terms: `push button` for panels that
+    /// respond to clicks and `label` for everything else
+    pub role: &'static str,
+    /// The text that the panel is showing
+    pub text: String,
+    /// The position of the panel, used to sort panels in reading order
+    pub position: (usize, Alignment, usize),
+}
+
+/// Sends panel updates to the D-Bus service.
+#[derive(Debug, Clone)]
+pub struct Exporter {
+    send: UnboundedSender<Update>,
+}
+
+impl Exporter {
+    /// Starts a D-Bus service named `org.lazybar.Bar.<bar name>` on the
+    /// session bus that exports the contents of the bar. Characters that
+    /// aren't allowed in bus names are replaced with underscores.
+    ///
+    /// The `org.lazybar.Accessibility` interface at [`PATH`] has:
+    /// - a `Panels` method that returns the id, role, and text of each panel
+    ///   in reading order (top to bottom, then left to right)
+    /// - a `Text` property with the text of all panels joined by spaces
+    /// - a `PanelChanged` signal with the id, role, and new text of a panel
+    ///   whenever its text changes
+    #[must_use]
+    pub fn start(bar_name: &str) -> Self {
+        let name = bar_name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        let service = format!("org.lazybar.Bar.b{name}");
+        let (send, recv) = unbounded_channel();
+
+        tokio::spawn(async move {
+            if let Err(e) = serve(service.as_str(), recv).await {
+                log::warn!("Failed to export bar contents over D-Bus: {e}");
+            }
+        });
+
+        Self { send }
+    }
+
+    /// Queues an update to be exported.
+    pub fn update(&self, update: Update) {
+        if self.send.send(update).is_err() {
+            log::debug!("Accessibility service is no longer running");
+        }
+    }
+}
+
+async fn serve(
+    service: &str,
+    mut recv: UnboundedReceiver<Update>,
+) -> Result<()> {
+    let conn = connection::Builder::session()?
+        .name(service)?
+        .serve_at(PATH, Contents::default())?
+        .build()
+        .await?;
+    let iface = conn.object_server().interface::<_, Contents>(PATH).await?;
+    log::info!("Exporting bar contents as {service}");
+
+    while let Some(update) = recv.recv().await {
+        let mut contents = iface.get_mut().await;
+        if !contents.update(update.clone()) {
+            continue;
+        }
+        contents.text_changed(iface.signal_context()).await?;
+        drop(contents);
+
+        Contents::panel_changed(
+            iface.signal_context(),
+            update.id.as_str(),
+            update.role,
+            update.text.as_str(),
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Contents {
+    panels: Vec<Update>,
+}
+
+impl Contents {
+    /// Stores an update, returning whether anything changed.
+    fn update(&mut self, update: Update) -> bool {
+        if let Some(panel) = self.panels.iter_mut().find(|p| p.id == update.id)
+        {
+            if *panel == update {
+                return false;
+            }
+            *panel = update;
+        } else {
+            self.panels.push(update);
+        }
+        self.panels.sort_by_key(|p| p.position);
+        true
+    }
+}
+
+#[interface(name = "org.lazybar.Accessibility")]
+impl Contents {
+    fn panels(&self) -> Vec<(String, String, String)> {
+        self.panels
+            .iter()
+            .map(|p| (p.id.clone(), p.role.to_string(), p.text.clone()))
+            .collect()
+    }
+
+    #[zbus(property)]
+    fn text(&self) -> String {
+        self.panels
+            .iter()
+            .map(|p| p.text.trim())
+            .filter(|t| !t.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[zbus(signal)]
+    async fn panel_changed(
+        ctxt: &SignalContext<'_>,
+        id: &str,
+        role: &str,
+        text: &str,
+    ) -> zbus::Result<()>;
+}
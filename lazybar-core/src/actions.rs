@@ -1,10 +1,13 @@
-use std::collections::HashMap;
+use std::{borrow::Cow, collections::HashMap};
 
 use anyhow::Result;
 use config::Value;
 use derive_builder::Builder;
 
-use crate::remove_string_from_config;
+use crate::{
+    bar::{MouseButton, MouseEvent},
+    remove_string_from_config,
+};
 
 /// A map from mouse buttons to panel events
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Builder)]
@@ -24,9 +27,53 @@ pub struct Actions {
     /// The event that should be run when the panel is scrolled down
     #[builder(default = "String::new()")]
     pub down: String,
+    /// The event that should be run when the panel is double-clicked with
+    /// the left button
+    #[builder(default = "String::new()")]
+    pub double_left: String,
+    /// The event that should be run when the panel is double-clicked with
+    /// the right button
+    #[builder(default = "String::new()")]
+    pub double_right: String,
+    /// The event that should be run when the panel is double-clicked with
+    /// the middle button
+    #[builder(default = "String::new()")]
+    pub double_middle: String,
+    /// The event that should be run when the left button is held down on
+    /// the panel
+    #[builder(default = "String::new()")]
+    pub hold_left: String,
+    /// The event that should be run when the right button is held down on
+    /// the panel
+    #[builder(default = "String::new()")]
+    pub hold_right: String,
+    /// The event that should be run when the middle button is held down on
+    /// the panel
+    #[builder(default = "String::new()")]
+    pub hold_middle: String,
 }
 
 impl Actions {
+    /// Returns whether no events are set, so that mouse events do nothing.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        [
+            &self.left,
+            &self.right,
+            &self.middle,
+            &self.up,
+            &self.down,
+            &self.double_left,
+            &self.double_right,
+            &self.double_middle,
+            &self.hold_left,
+            &self.hold_right,
+            &self.hold_middle,
+        ]
+        .iter()
+        .all(|event| event.is_empty())
+    }
+
     /// Attempts to parse an instance of this type from a subset of tthe global
     /// [`Config`][config::Config].
     ///
@@ -41,6 +88,21 @@ impl Actions {
     ///   up.
     /// - `scroll_down`: The name of the event to run when the panel is scrolled
     ///   down.
+    /// - `double_click_left`, `double_click_right`, `double_click_middle`: The
+    ///   name of the event to run when the panel is double-clicked. The first
+    ///   click of a double-click still runs the single-click event.
+    /// - `hold_left`, `hold_right`, `hold_middle`: The name of the event to
+    ///   run when a button is held down on the panel. Without one, holding a
+    ///   button runs the single-click event.
+    ///
+    /// `%span%` in any event is replaced with the name of the part of the
+    /// panel that was clicked, so that one panel can have several buttons.
+    /// See [`draw_panel`][crate::common::draw_panel] for how to name parts of
+    /// a panel's text.
+    ///
+    /// How quickly clicks have to follow each other to count as a
+    /// double-click, and how long a button has to be held, are set for the
+    /// whole bar. See [`parser::parse`][crate::parser::parse].
     pub fn parse<S: std::hash::BuildHasher>(
         table: &mut HashMap<String, Value, S>,
     ) -> Result<Self> {
@@ -61,7 +123,68 @@ impl Actions {
         if let Some(down) = remove_string_from_config("scroll_down", table) {
             builder.down(down);
         }
+        if let Some(left) =
+            remove_string_from_config("double_click_left", table)
+        {
+            builder.double_left(left);
+        }
+        if let Some(right) =
+            remove_string_from_config("double_click_right", table)
+        {
+            builder.double_right(right);
+        }
+        if let Some(middle) =
+            remove_string_from_config("double_click_middle", table)
+        {
+            builder.double_middle(middle);
+        }
+        if let Some(left) = remove_string_from_config("hold_left", table) {
+            builder.hold_left(left);
+        }
+        if let Some(right) = remove_string_from_config("hold_right", table) {
+            builder.hold_right(right);
+        }
+        if let Some(middle) = remove_string_from_config("hold_middle", table) {
+            builder.hold_middle(middle);
+        }
 
         Ok(builder.build()?)
     }
+
+    /// Returns the event that should be run for a mouse event, taking
+    /// double-clicks and held buttons into account. `%span%` in the event is
+    /// replaced with the name of the span that was clicked, or nothing if
+    /// there isn't one (see [`MouseEvent::span`]).
+    #[must_use]
+    pub fn get(&self, event: &MouseEvent) -> Cow<'_, str> {
+        let action = self.event(event);
+        if action.contains("%span%") {
+            Cow::Owned(action.replace("%span%", event.span.unwrap_or_default()))
+        } else {
+            Cow::Borrowed(action)
+        }
+    }
+
+    fn event(&self, event: &MouseEvent) -> &str {
+        let (single, double, hold) = match event.button {
+            MouseButton::Left => {
+                (&self.left, &self.double_left, &self.hold_left)
+            }
+            MouseButton::Right => {
+                (&self.right, &self.double_right, &self.hold_right)
+            }
+            MouseButton::Middle => {
+                (&self.middle, &self.double_middle, &self.hold_middle)
+            }
+            MouseButton::ScrollUp => return self.up.as_str(),
+            MouseButton::ScrollDown => return self.down.as_str(),
+        };
+        if event.held && !hold.is_empty() {
+            hold
+        } else if event.clicks >= 2 && !double.is_empty() {
+            double
+        } else {
+            single
+        }
+    }
 }
@@ -1,23 +1,39 @@
+use std::{borrow::Cow, collections::HashMap};
+
 use anyhow::{Context, Result};
+use config::Value;
 use csscolorparser::Color;
 use derive_builder::Builder;
 use pango::FontDescription;
 
 use crate::{
-    background::Bg, parser, remove_color_from_config, remove_string_from_config,
+    background::Bg, border::Border, high_contrast, parser,
+    remove_array_from_config, remove_color_from_config,
+    remove_string_from_config, style, ColorRamp,
 };
 
 /// Attributes of a panel, or the defaults for the bar.
 #[derive(Builder, Clone, Default, Debug, PartialEq, PartialOrd)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
+#[non_exhaustive]
 pub struct Attrs {
     #[builder(default, setter(strip_option))]
     font: Option<FontDescription>,
     #[builder(default, setter(strip_option))]
+    font_features: Option<String>,
+    #[builder(default, setter(strip_option))]
     fg: Option<Color>,
     #[builder(default, setter(strip_option))]
+    fg_ramp: Option<ColorRamp>,
+    #[builder(default, setter(strip_option))]
     pub(crate) bg: Option<Bg>,
+    #[builder(default, setter(strip_option))]
+    pub(crate) border: Option<Border>,
+    /// The name this instance was parsed from, used to look it up again when
+    /// the styles are reloaded.
+    #[builder(default, setter(skip))]
+    name: Option<String>,
 }
 
 impl Attrs {
@@ -29,8 +45,12 @@ impl Attrs {
     pub const fn empty() -> Self {
         Self {
             font: None,
+            font_features: None,
             fg: None,
+            fg_ramp: None,
             bg: None,
+            border: None,
+            name: None,
         }
     }
 
@@ -46,13 +66,37 @@ impl Attrs {
     /// - `fg: String`: Specify the foreground (usually text) color. All parsing
     ///   methods from [csscolorparser] are available.
     ///
+    /// - `fg_ramp: String`: The name of a [`ColorRamp`] that sets the
+    ///   foreground color from the panel's value, e.g. so that a CPU panel
+    ///   shifts from green to red as its usage rises. This takes precedence
+    ///   over `fg` in panels that support it, which document the range they
+    ///   use.
+    ///
     /// - `bg`: See [`Bg::parse`].
     ///
+    /// - `border_width: f64`: Draw a border this many pixels wide around the
+    ///   panel's background. `border_top`, `border_right`, `border_bottom`,
+    ///   and `border_left` override the width of a single side.
+    ///
+    /// - `border_color: String`: The color of the border. All parsing methods
+    ///   from [csscolorparser] are available.
+    ///
+    /// - `border_radius: f64`: How rounded the corners of the border are. This
+    ///   is ignored unless all of the sides are the same width.
+    ///
     /// - `font: String`: Specify the font to be used. This will be turned into
     ///   a [`pango::FontDescription`], so it's very configurable. Font family,
     ///   weight, size, and more can be specified.
+    ///
+    /// - `font_fallback: Array of Strings`: Font families to use, in order,
+    ///   for characters that the family in `font` doesn't have, e.g. an icon
+    ///   font after a text font. Without `font`, only the families are set.
+    ///
+    /// - `font_features: Array of Strings`: OpenType features to turn on or
+    ///   off, e.g. `["tnum", "-liga"]` for tabular numbers without
+    ///   ligatures. A feature can also be given a value, like `"ss01=1"`.
     pub fn parse(name: impl AsRef<str>) -> Result<Self> {
-        let attrs_table = parser::ATTRS.get().unwrap();
+        let attrs_table = parser::ATTRS.read().unwrap();
         let name = name.as_ref();
         log::debug!("parsing {name} attrs");
         let mut attr_table = attrs_table
@@ -68,18 +112,84 @@ impl Attrs {
             log::debug!("got fg: {fg}");
             builder.fg(fg);
         }
+        if let Some(ramp) =
+            remove_string_from_config("fg_ramp", &mut attr_table)
+        {
+            match ColorRamp::parse(ramp.as_str()) {
+                Some(ramp) => {
+                    builder.fg_ramp(ramp);
+                }
+                None => log::warn!("couldn't find color ramp {ramp}"),
+            }
+        }
         if let Some(bg) = remove_string_from_config("bg", &mut attr_table) {
             if let Some(bg) = Bg::parse(bg.as_str()) {
                 log::debug!("got bg: {bg:?}");
                 builder.bg(bg);
             }
         }
-        if let Some(font) = remove_string_from_config("font", &mut attr_table) {
-            log::debug!("got font: {font}");
-            builder.font(FontDescription::from_string(font.as_str()));
+        if let Some(border) = Border::parse(&mut attr_table) {
+            log::debug!("got border: {border:?}");
+            builder.border(border);
+        }
+        let strings = |key, table: &mut HashMap<String, Value>| {
+            remove_array_from_config(key, table).map(|array| {
+                array
+                    .into_iter()
+                    .filter_map(|value| value.into_string().ok())
+                    .collect::<Vec<_>>()
+            })
+        };
+        let font = remove_string_from_config("font", &mut attr_table)
+            .map(|font| FontDescription::from_string(font.as_str()));
+        let fallback = strings("font_fallback", &mut attr_table)
+            .filter(|fallback| !fallback.is_empty());
+        let font = match (font, fallback) {
+            (Some(mut font), Some(fallback)) => {
+                let families = font
+                    .family()
+                    .map(|family| family.to_string())
+                    .into_iter()
+                    .chain(fallback)
+                    .collect::<Vec<_>>();
+                font.set_family(families.join(",").as_str());
+                Some(font)
+            }
+            (None, Some(fallback)) => {
+                let mut font = FontDescription::new();
+                font.set_family(fallback.join(",").as_str());
+                Some(font)
+            }
+            (font, None) => font,
+        };
+        if let Some(font) = font {
+            log::debug!("got font: {}", font.to_str());
+            builder.font(font);
+        }
+        if let Some(features) = strings("font_features", &mut attr_table) {
+            log::debug!("got font features: {features:?}");
+            builder.font_features(features.join(","));
         }
 
-        Ok(builder.build()?)
+        let mut attrs = builder.build()?;
+        attrs.name = Some(name.to_owned());
+        Ok(attrs)
+    }
+
+    /// Returns this instance as it would be parsed from the current styles if
+    /// they have been reloaded (see [`style::reload`]). Options that the new
+    /// styles don't set keep their current values.
+    #[must_use]
+    pub fn restyled(&self) -> Cow<'_, Self> {
+        let Some(name) = self.name.as_ref().filter(|_| style::reloaded())
+        else {
+            return Cow::Borrowed(self);
+        };
+        let parsed = style::cached(name, |name| Self::parse(name).ok());
+        parsed.map_or(Cow::Borrowed(self), |mut attrs| {
+            attrs.apply_to(self);
+            Cow::Owned(attrs)
+        })
     }
 
     /// Parses an instance of this type from a subset of the global
@@ -91,16 +201,68 @@ impl Attrs {
         Self::parse(name).unwrap_or_default()
     }
 
-    /// Sets the font of a [`pango::Layout`].
+    /// Returns this instance with its foreground color taken from its
+    /// `fg_ramp` at `value` in the range `[min, max]`. Attrs without a
+    /// `fg_ramp` are returned as they are.
+    ///
+    /// The result is already restyled (see [`Attrs::restyled`]).
+    #[must_use]
+    pub fn ramped(&self, value: f64, min: f64, max: f64) -> Cow<'_, Self> {
+        let attrs = self.restyled();
+        let Some(color) = attrs
+            .fg_ramp
+            .as_ref()
+            .and_then(|ramp| ramp.color(value, min, max))
+        else {
+            return attrs;
+        };
+        let mut attrs = attrs.into_owned();
+        attrs.fg = Some(color);
+        // it was restyled above, and restyling it again would lose the color
+        attrs.name = None;
+        Cow::Owned(attrs)
+    }
+
+    /// Returns these attrs with the options they don't set taken from `base`.
+    /// Unlike [`Attrs::apply_to`], a foreground color here also overrides a
+    /// `fg_ramp` in `base`. The result has no name, so it won't be restyled.
+    #[must_use]
+    pub fn layered_over(&self, base: &Self) -> Self {
+        let mut attrs = self.clone();
+        let has_fg = attrs.fg.is_some();
+        attrs.apply_to(base);
+        if has_fg && self.fg_ramp.is_none() {
+            attrs.fg_ramp = None;
+        }
+        attrs.name = None;
+        attrs
+    }
+
+    /// Sets the font of a [`pango::Layout`], along with any OpenType
+    /// features. Setting the layout's markup replaces the features, so this
+    /// should be called after the text is set.
     pub fn apply_font(&self, layout: &pango::Layout) {
         if let Some(font) = &self.font {
             layout.set_font_description(Some(font));
         }
+        if let Some(features) = &self.font_features {
+            let attrs = layout
+                .attributes()
+                .and_then(|attrs| attrs.copy())
+                .unwrap_or_else(pango::AttrList::new);
+            // the attribute covers the whole text by default
+            attrs.insert(pango::AttrFontFeatures::new(features.as_str()));
+            layout.set_attributes(Some(&attrs));
+        }
     }
 
     /// Sets the foreground (usually text) color of a [`cairo::Context`].
     pub fn apply_fg(&self, cr: &cairo::Context) {
-        if let Some(fg) = &self.fg {
+        if let Some(fg) = self
+            .fg
+            .as_ref()
+            .map(|fg| high_contrast::or(fg, &high_contrast::FG))
+        {
             cr.set_source_rgba(
                 fg.r.into(),
                 fg.g.into(),
@@ -123,11 +285,20 @@ impl Attrs {
         if self.font.is_none() {
             self.font.clone_from(&new.font);
         }
+        if self.font_features.is_none() {
+            self.font_features.clone_from(&new.font_features);
+        }
         if self.fg.is_none() {
             self.fg.clone_from(&new.fg);
         }
+        if self.fg_ramp.is_none() {
+            self.fg_ramp.clone_from(&new.fg_ramp);
+        }
         if self.bg.is_none() {
             self.bg.clone_from(&new.bg);
         }
+        if self.border.is_none() {
+            self.border.clone_from(&new.border);
+        }
     }
 }
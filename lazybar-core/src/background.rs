@@ -1,11 +1,12 @@
-use std::f64::consts::PI;
+use std::{collections::HashMap, f64::consts::PI};
 
 use anyhow::Result;
+use config::{Value, ValueKind};
 use csscolorparser::Color;
 
 use crate::{
-    parser, remove_color_from_config, remove_float_from_config,
-    remove_string_from_config,
+    high_contrast, parser, remove_color_from_config, remove_float_from_config,
+    remove_string_from_config, replace_consts,
 };
 
 /// The configuration options for a panel background
@@ -77,7 +78,7 @@ impl Bg {
     /// - `color`: the background color. See [csscolorparser] for parsing
     ///   options.
     pub fn parse(name: impl AsRef<str>) -> Option<Self> {
-        let bgs_table = parser::BGS.get().unwrap();
+        let bgs_table = parser::BGS.read().unwrap();
         let mut bg_table =
             bgs_table.get(name.as_ref())?.clone().into_table().ok()?;
         remove_string_from_config("style", &mut bg_table).and_then(|style| {
@@ -164,6 +165,7 @@ impl Bg {
                 cr.rel_line_to(0.0, 2.0 * radius - max_height);
                 cr.arc(*radius, *radius, *radius, PI, -PI / 2.0);
 
+                let color = high_contrast::or(color, &high_contrast::BG);
                 cr.set_source_rgba(
                     color.r.into(),
                     color.g.into(),
@@ -193,6 +195,7 @@ impl Bg {
                 cr.rel_line_to(0.0, 2.0 * radius - max_height);
                 cr.arc(*radius, *radius, *radius, PI, -PI / 2.0);
 
+                let color = high_contrast::or(color, &high_contrast::BG);
                 cr.set_source_rgba(
                     color.r.into(),
                     color.g.into(),
@@ -228,6 +231,7 @@ impl Bg {
                 cr.rel_line_to(radius - total_width, 0.0);
                 cr.rel_line_to(0.0, max_height);
 
+                let color = high_contrast::or(color, &high_contrast::BG);
                 cr.set_source_rgba(
                     color.r.into(),
                     color.g.into(),
@@ -262,6 +266,7 @@ impl Bg {
                 cr.rel_line_to(0.0, 2.0 * radius - max_height);
                 cr.arc(*radius, *radius, *radius, PI, -PI / 2.0);
 
+                let color = high_contrast::or(color, &high_contrast::BG);
                 cr.set_source_rgba(
                     color.r.into(),
                     color.g.into(),
@@ -335,3 +340,167 @@ impl Bg {
         }
     }
 }
+
+/// The configuration options for the background of a bar
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum BarBg {
+    /// The whole bar is one color.
+    Solid(Color),
+    /// A linear gradient spans the whole bar.
+    Gradient {
+        /// The colors of the gradient, spaced evenly from one end to the
+        /// other
+        stops: Vec<Color>,
+        /// The direction of the gradient in degrees. 0 runs from left to
+        /// right, and 90 runs from top to bottom.
+        angle: f64,
+    },
+    /// The bar is split into thirds, each with its own color.
+    Sections {
+        /// The color behind the left panels
+        left: Color,
+        /// The color behind the center panels
+        center: Color,
+        /// The color behind the right panels
+        right: Color,
+    },
+}
+
+impl Default for BarBg {
+    fn default() -> Self {
+        Self::Solid(Color::default())
+    }
+}
+
+impl BarBg {
+    /// Removes `bg` and `bg_angle` from a bar's config table and returns an
+    /// attempt at parsing them into a [`BarBg`]. See
+    /// [`parser::parse`][crate::parser::parse] for the options.
+    pub fn parse(table: &mut HashMap<String, Value>) -> Self {
+        let angle =
+            remove_float_from_config("bg_angle", table).unwrap_or_default();
+        let bg = match table.get("bg").map(|bg| &bg.kind) {
+            Some(ValueKind::Array(_)) => table
+                .remove("bg")
+                .and_then(|bg| bg.into_array().ok())
+                .map(|stops| Self::Gradient {
+                    stops: stops.into_iter().filter_map(parse_color).collect(),
+                    angle,
+                }),
+            Some(ValueKind::Table(_)) => table
+                .remove("bg")
+                .and_then(|bg| bg.into_table().ok())
+                .map(|mut sections| {
+                    let mut section = |name| {
+                        remove_color_from_config(name, &mut sections)
+                            .unwrap_or_default()
+                    };
+                    Self::Sections {
+                        left: section("left"),
+                        center: section("center"),
+                        right: section("right"),
+                    }
+                }),
+            _ => remove_color_from_config("bg", table).map(Self::Solid),
+        };
+        match bg {
+            Some(Self::Gradient { stops, .. }) if stops.is_empty() => {
+                log::warn!("Ignoring a background gradient without colors");
+                Self::default()
+            }
+            bg => bg.unwrap_or_default(),
+        }
+    }
+
+    /// Returns a single color that stands in for the background, used for
+    /// the window itself and for popups.
+    #[must_use]
+    pub fn color(&self) -> Color {
+        match self {
+            Self::Solid(color) => color.clone(),
+            Self::Gradient { stops, angle: _ } => {
+                stops.first().cloned().unwrap_or_default()
+            }
+            Self::Sections {
+                left: _,
+                center,
+                right: _,
+            } => center.clone(),
+        }
+    }
+
+    /// Fills the current clip region of a bar with the background.
+    pub(crate) fn fill(
+        &self,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<()> {
+        match self {
+            Self::Solid(color) => {
+                set_source(cr, color);
+                cr.paint()?;
+            }
+            Self::Gradient { stops, angle } => {
+                let (dy, dx) = angle.to_radians().sin_cos();
+                let half = dx.abs().mul_add(width, dy.abs() * height) / 2.0;
+                let (x, y) = (width / 2.0, height / 2.0);
+                let gradient = cairo::LinearGradient::new(
+                    dx.mul_add(-half, x),
+                    dy.mul_add(-half, y),
+                    dx.mul_add(half, x),
+                    dy.mul_add(half, y),
+                );
+                let last = stops.len().saturating_sub(1).max(1) as f64;
+                for (idx, stop) in stops.iter().enumerate() {
+                    let color = high_contrast::or(stop, &high_contrast::BG);
+                    gradient.add_color_stop_rgba(
+                        idx as f64 / last,
+                        color.r.into(),
+                        color.g.into(),
+                        color.b.into(),
+                        color.a.into(),
+                    );
+                }
+                cr.set_source(&gradient)?;
+                cr.paint()?;
+            }
+            Self::Sections {
+                left,
+                center,
+                right,
+            } => {
+                let third = width / 3.0;
+                for (idx, color) in
+                    [left, center, right].into_iter().enumerate()
+                {
+                    set_source(cr, color);
+                    cr.rectangle(third * idx as f64, 0.0, third, height);
+                    cr.fill()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_color(value: Value) -> Option<Color> {
+    let color = value
+        .into_string()
+        .map_err(|e| log::warn!("Ignoring non-string color: {e}"))
+        .ok()?;
+    replace_consts(color.as_str(), &parser::CONSTS.read().unwrap())
+        .parse()
+        .map_err(|_| log::warn!("Invalid color {color}"))
+        .ok()
+}
+
+fn set_source(cr: &cairo::Context, color: &Color) {
+    let color = high_contrast::or(color, &high_contrast::BG);
+    cr.set_source_rgba(
+        color.r.into(),
+        color.g.into(),
+        color.b.into(),
+        color.a.into(),
+    );
+}
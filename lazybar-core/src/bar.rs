@@ -1,44 +1,105 @@
 use std::{
+    collections::{HashMap, HashSet},
+    f64::consts::PI,
     fmt::Display,
+    future::Future,
     ops::BitAnd,
     pin::Pin,
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
 };
 
 use anyhow::{anyhow, Result};
 use csscolorparser::Color;
 use derive_debug::Dbg;
 use lazy_static::lazy_static;
+use pangocairo::functions::{create_layout, show_layout};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tokio::{
     net::UnixStream,
-    sync::{mpsc::UnboundedSender, OnceCell},
-    task::JoinSet,
+    sync::mpsc::UnboundedSender,
+    task::{futures::TaskLocalFuture, JoinSet},
 };
 use tokio_stream::{Stream, StreamMap};
 use x11rb::{
     connection::Connection,
     protocol::{
         self,
-        xproto::{ConnectionExt, Visualtype, Window},
+        randr::{ConnectionExt as _, MonitorInfo, NotifyMask},
+        xproto::{
+            AtomEnum, ChangeWindowAttributesAux, ConfigureWindowAux,
+            ConnectionExt, CreateWindowAux, Cursor, EventMask, NotifyDetail,
+            Rectangle, Visualtype, Window, WindowClass,
+        },
     },
     xcb_ffi::XCBConnection,
+    COPY_DEPTH_FROM_PARENT, COPY_FROM_PARENT,
 };
 
 use crate::{
-    create_surface, create_window,
+    background::BarBg,
+    create_surface, create_window, diagnostics, effects, high_contrast,
     ipc::{self, ChannelEndpoint},
-    set_wm_properties, Alignment, IpcStream, Margins, PanelDrawFn, PanelHideFn,
-    PanelShowFn, PanelShutdownFn, PanelStream, Position,
+    menu::{self, Menu, MenuEntry},
+    popup::{PanelPopup, Popup},
+    set_wm_properties, style,
+    tooltip::Tooltip,
+    x::{
+        bar_geometry, beside_bar, find_monitor, hand_cursor, round_corners,
+        set_blur, set_layer, shape_input, subtract_input, with_shadow,
+        InternedAtoms,
+    },
+    Alignment, Attrs, BarConfig, IpcStream, Layer, Margins, PanelDrawFn,
+    PanelHideFn, PanelShowFn, PanelShutdownFn, PanelStream, Position,
 };
 
 lazy_static! {
     static ref REGEX: Regex =
-        Regex::new(r"(?<region>[lcr])(?<idx>\d+).(?<message>.+)").unwrap();
-    #[allow(missing_docs)]
-    pub static ref BAR_INFO: OnceCell<BarInfo> = OnceCell::new();
+        Regex::new(r"^(?<region>[lcr])(?<idx>\d+)$").unwrap();
+}
+
+/// The space in pixels on each side of the expander's arrow
+const EXPANDER_PADDING: f64 = 4.0;
+
+tokio::task_local! {
+    static BAR_INFO: BarInfo;
+}
+
+/// Returns information about the bar that the current task belongs to, or
+/// [`None`] outside of a bar's tasks. Every bar runs on the same thread, so
+/// this is set for each bar's panels and main loop, but not for tasks they
+/// spawn. Code that runs elsewhere, such as in
+/// [`spawn_blocking`][tokio::task::spawn_blocking], should get this first
+/// and take it along.
+#[must_use]
+pub fn bar_info() -> Option<BarInfo> {
+    BAR_INFO.try_with(Clone::clone).ok()
+}
+
+/// Like [`with_info`], but for code that runs outside of the bar's tasks,
+/// such as in [`spawn_blocking`][tokio::task::spawn_blocking].
+pub(crate) fn sync_with_info<R>(info: BarInfo, f: impl FnOnce() -> R) -> R {
+    BAR_INFO.sync_scope(info, f)
+}
+
+/// Calls `f` with the information about the bar that the current task belongs
+/// to, without cloning it. Returns [`None`] outside of a bar's tasks.
+pub(crate) fn with_current_info<R>(f: impl FnOnce(&BarInfo) -> R) -> Option<R> {
+    BAR_INFO.try_with(f).ok()
+}
+
+/// Runs a future as part of a bar, so that [`bar_info`] returns `info` while
+/// it's polled.
+pub(crate) fn with_info<F: Future>(
+    info: BarInfo,
+    future: F,
+) -> TaskLocalFuture<BarInfo, F> {
+    BAR_INFO.scope(info, future)
 }
 
 /// Information about the bar, usually for use in building panels.
@@ -50,12 +111,15 @@ pub struct BarInfo {
     pub visual: Visualtype,
     /// The width of the bar in pixels
     pub width: u16,
-    /// The height of the bar in pixels
+    /// The height of one row of the bar in pixels
     pub height: u16,
     /// Whether the bar supports transparency
     pub transparent: bool,
     /// The background color of the bar
     pub bg: Color,
+    /// Whether the bar is in high contrast mode. See
+    /// [`high_contrast`][crate::high_contrast].
+    pub(crate) high_contrast: Arc<AtomicBool>,
 }
 
 #[derive(PartialEq, Eq, Debug)]
@@ -66,6 +130,29 @@ enum CenterState {
     Unknown,
 }
 
+/// The part of the bar that was redrawn to make room for a resized panel
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Redrawn {
+    Left(usize),
+    CenterRight(usize),
+    Right(usize),
+    Bar,
+}
+
+impl Redrawn {
+    /// Whether the panels in `alignment` on `row` were all redrawn.
+    const fn covers(self, alignment: Alignment, row: usize) -> bool {
+        match self {
+            Self::Left(r) => matches!(alignment, Alignment::Left) && r == row,
+            Self::CenterRight(r) => {
+                !matches!(alignment, Alignment::Left) && r == row
+            }
+            Self::Right(r) => matches!(alignment, Alignment::Right) && r == row,
+            Self::Bar => true,
+        }
+    }
+}
+
 #[derive(Debug)]
 enum Region {
     Left,
@@ -87,6 +174,7 @@ struct Extents {
 /// If a panel is dependent on another panel with non-None dependence, it will
 /// not be shown.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
 pub enum Dependence {
     /// The panel will always be shown
     #[default]
@@ -101,6 +189,7 @@ pub enum Dependence {
 
 /// Information describing how to draw/redraw a [`Panel`].
 #[derive(Dbg)]
+#[non_exhaustive]
 pub struct PanelDrawInfo {
     /// The width in pixels of the panel.
     pub width: i32,
@@ -124,6 +213,62 @@ pub struct PanelDrawInfo {
     /// for all panels are held to a time limit.
     #[dbg(formatter = "fmt_option")]
     pub shutdown: Option<PanelShutdownFn>,
+    /// The plain text that the panel is showing, if any. This is exported
+    /// for screen readers when accessibility is enabled.
+    pub text: Option<String>,
+    /// Markup shown in a tooltip when the pointer rests on the panel. See
+    /// [`crate::tooltip`].
+    pub tooltip: Option<String>,
+    /// Markup shown instead of `tooltip` while the pointer rests on part of
+    /// the panel, like one icon of a tray.
+    pub tooltips: Vec<TooltipSpan>,
+    /// A popup window that the bar shows next to the panel while it's open.
+    pub popup: Option<PanelPopup>,
+    /// The entries of a menu that the bar opens when the panel is
+    /// right-clicked. Empty for panels without a menu.
+    pub menu: Vec<MenuEntry>,
+    /// Whether the bar should open `menu` as soon as it receives this draw
+    /// info. Panels that build a menu in response to a click set this, and
+    /// right clicks are sent to them instead of opening the menu.
+    pub show_menu: bool,
+    /// Whether the panel changes over time without updating, like scrolling
+    /// text. The bar redraws animated panels regularly on its own.
+    pub animated: bool,
+    /// Set by the bar while the pointer is on the panel, which is redrawn
+    /// whenever this changes. Only panels that look different when hovered
+    /// need to provide this.
+    pub hovered: Option<Arc<AtomicBool>>,
+    /// Whether clicking the panel does something. The bar shows a hand
+    /// cursor while the pointer is on interactive panels.
+    pub interactive: bool,
+    /// Named parts of the panel that can be clicked separately, such as the
+    /// previous, play, and next buttons of a media player. The name of the
+    /// span under the pointer is sent with each [`MouseEvent`].
+    pub spans: Vec<ClickSpan>,
+}
+
+/// A named range of x coordinates within a panel. See
+/// [`PanelDrawInfo::spans`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClickSpan {
+    /// The name sent with mouse events in this span
+    pub name: &'static str,
+    /// The left edge of the span, relative to the panel
+    pub start: i32,
+    /// The right edge of the span, relative to the panel
+    pub end: i32,
+}
+
+/// A range of x coordinates within a panel with its own tooltip. See
+/// [`PanelDrawInfo::tooltips`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct TooltipSpan {
+    /// The left edge of the span, relative to the panel
+    pub start: i32,
+    /// The right edge of the span, relative to the panel
+    pub end: i32,
+    /// The markup shown in the tooltip
+    pub markup: String,
 }
 
 fn fmt_option<T>(value: &Option<T>) -> &'static str {
@@ -152,8 +297,98 @@ impl PanelDrawInfo {
             show_fn,
             hide_fn,
             shutdown,
+            text: None,
+            tooltip: None,
+            tooltips: Vec::new(),
+            popup: None,
+            menu: Vec::new(),
+            show_menu: false,
+            animated: false,
+            hovered: None,
+            interactive: false,
+            spans: Vec::new(),
         }
     }
+
+    /// Sets the plain text that the panel is showing.
+    #[must_use]
+    pub fn with_text(mut self, text: impl Into<String>) -> Self {
+        self.text = Some(text.into());
+        self
+    }
+
+    /// Sets the markup shown in the panel's tooltip.
+    #[must_use]
+    pub fn with_tooltip(mut self, tooltip: impl Into<String>) -> Self {
+        self.tooltip = Some(tooltip.into());
+        self
+    }
+
+    /// Sets the markup shown in the tooltips of parts of the panel.
+    #[must_use]
+    pub fn with_tooltips(mut self, tooltips: Vec<TooltipSpan>) -> Self {
+        self.tooltips = tooltips;
+        self
+    }
+
+    /// Sets the popup that the panel can open.
+    #[must_use]
+    pub fn with_popup(mut self, popup: PanelPopup) -> Self {
+        self.popup = Some(popup);
+        self
+    }
+
+    /// Sets the entries of the panel's menu.
+    #[must_use]
+    pub fn with_menu(mut self, menu: Vec<MenuEntry>) -> Self {
+        self.menu = menu;
+        self
+    }
+
+    /// Sets the entries of the panel's menu and asks the bar to open it
+    /// right away. See [`PanelDrawInfo::show_menu`].
+    #[must_use]
+    pub fn with_shown_menu(mut self, menu: Vec<MenuEntry>) -> Self {
+        self.menu = menu;
+        self.show_menu = true;
+        self
+    }
+
+    /// Sets whether clicking the panel does something.
+    #[must_use]
+    pub fn with_interactive(mut self, interactive: bool) -> Self {
+        self.interactive = interactive;
+        self
+    }
+
+    /// Sets the parts of the panel that can be clicked separately.
+    #[must_use]
+    pub fn with_spans(mut self, spans: Vec<ClickSpan>) -> Self {
+        self.spans = spans;
+        self
+    }
+
+    /// Returns the name of the innermost span at `x`, relative to the panel.
+    #[must_use]
+    pub fn span_at(&self, x: i32) -> Option<&'static str> {
+        self.spans
+            .iter()
+            .filter(|span| span.start <= x && x < span.end)
+            .min_by_key(|span| span.end - span.start)
+            .map(|span| span.name)
+    }
+
+    /// Returns the tooltip at `x`, relative to the panel: the markup of the
+    /// innermost tooltip span there, or else the panel's tooltip.
+    #[must_use]
+    pub fn tooltip_at(&self, x: i32) -> Option<String> {
+        self.tooltips
+            .iter()
+            .filter(|span| span.start <= x && x < span.end)
+            .min_by_key(|span| span.end - span.start)
+            .map(|span| span.markup.clone())
+            .or_else(|| self.tooltip.clone())
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -177,7 +412,7 @@ impl BitAnd for PanelStatus {
 
 impl From<&Panel> for PanelStatus {
     fn from(value: &Panel) -> Self {
-        if value.visible {
+        if value.shown() && !value.overflowed {
             value.draw_info.as_ref().map_or(Self::ZeroWidth, |d| {
                 match (d.dependence, d.width) {
                     (Dependence::None, 0) => Self::ZeroWidth,
@@ -191,10 +426,162 @@ impl From<&Panel> for PanelStatus {
     }
 }
 
+/// How panels appear and disappear, i.e. when their width changes to or from
+/// zero.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum TransitionStyle {
+    /// Panels appear and disappear at once.
+    #[default]
+    None,
+    /// Panels grow from and shrink toward their left edge, with their
+    /// contents sliding in and out.
+    Slide,
+    /// Panels grow and shrink in place while fading in and out.
+    Fade,
+}
+
+impl TransitionStyle {
+    /// Parses an instance from its name (`none`, `slide`, or `fade`).
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "slide" => Some(Self::Slide),
+            "fade" => Some(Self::Fade),
+            _ => None,
+        }
+    }
+}
+
+/// A panel in the middle of appearing, disappearing, or changing width.
+#[derive(Debug)]
+struct Transition {
+    /// How the panel appears or disappears, or [`TransitionStyle::None`] if
+    /// it's only changing width
+    style: TransitionStyle,
+    start: Instant,
+    duration: Duration,
+    /// The width of the panel when the transition started
+    from: f64,
+    /// What the panel shows while it transitions
+    content: Rc<PanelDrawInfo>,
+    /// What the panel is left with once it has disappeared, or [`None`] if
+    /// it isn't disappearing
+    hidden: Option<PanelDrawInfo>,
+}
+
+impl Transition {
+    fn width(&self) -> f64 {
+        let progress = (self.start.elapsed().as_secs_f64()
+            / self.duration.as_secs_f64())
+        .min(1.0);
+        let to = if self.hidden.is_some() {
+            0.0
+        } else {
+            f64::from(self.content.width)
+        };
+        progress.mul_add(to - self.from, self.from)
+    }
+
+    fn done(&self) -> bool {
+        self.start.elapsed() >= self.duration
+    }
+
+    /// Returns how to draw the panel at this point in the transition.
+    fn frame(&self) -> PanelDrawInfo {
+        let content = self.content.clone();
+        let full_width = f64::from(content.width);
+        let width = self.width().round() as i32;
+        let alpha = if full_width > 0.0 {
+            (f64::from(width) / full_width).min(1.0)
+        } else {
+            1.0
+        };
+        let style = self.style;
+
+        let mut draw_info = PanelDrawInfo::new(
+            (width, content.height),
+            Dependence::None,
+            Box::new(move |cr, x| {
+                cr.save()?;
+                cr.rectangle(
+                    0.0,
+                    0.0,
+                    f64::from(width),
+                    f64::from(content.height),
+                );
+                cr.clip();
+                match style {
+                    TransitionStyle::None => (content.draw_fn)(cr, x)?,
+                    TransitionStyle::Slide => {
+                        cr.translate(
+                            (f64::from(width) - full_width).min(0.0),
+                            0.0,
+                        );
+                        (content.draw_fn)(cr, x)?;
+                    }
+                    TransitionStyle::Fade => {
+                        cr.push_group();
+                        (content.draw_fn)(cr, x)?;
+                        cr.pop_group_to_source()?;
+                        cr.paint_with_alpha(alpha)?;
+                    }
+                }
+                cr.restore()?;
+                Ok(())
+            }),
+            None,
+            None,
+            None,
+        );
+        draw_info.text.clone_from(&self.content.text);
+        draw_info
+    }
+}
+
+/// Adds a rectangle with rounded corners to the current path, inset from
+/// each edge of an area starting at (0, 0).
+fn rounded_rectangle(
+    cr: &cairo::Context,
+    inset: f64,
+    width: f64,
+    height: f64,
+    radius: f64,
+) {
+    let (width, height) = (width - 2.0 * inset, height - 2.0 * inset);
+    let radius = (radius - inset).max(0.0).min(width / 2.0).min(height / 2.0);
+    cr.new_sub_path();
+    cr.arc(
+        inset + width - radius,
+        inset + radius,
+        radius,
+        -PI / 2.0,
+        0.0,
+    );
+    cr.arc(
+        inset + width - radius,
+        inset + height - radius,
+        radius,
+        0.0,
+        PI / 2.0,
+    );
+    cr.arc(
+        inset + radius,
+        inset + height - radius,
+        radius,
+        PI / 2.0,
+        PI,
+    );
+    cr.arc(inset + radius, inset + radius, radius, PI, 1.5 * PI);
+    cr.close_path();
+}
+
 /// A button that can be linked to an action for a panel
 ///
 /// Note: scrolling direction may be incorrect depending on your configuration
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[non_exhaustive]
 pub enum MouseButton {
     /// The left mouse button
     #[default]
@@ -236,6 +623,7 @@ impl MouseButton {
 
 /// A mouse event that can be passed to a panel
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+#[non_exhaustive]
 pub struct MouseEvent {
     /// The button that was pressed (or scrolled)
     pub button: MouseButton,
@@ -243,15 +631,33 @@ pub struct MouseEvent {
     pub x: i16,
     /// The y coordinate of the press, relative to the bar
     pub y: i16,
+    /// The modifier keys and buttons held during the press, as a raw X11
+    /// `KeyButMask`
+    pub modifiers: u16,
+    /// How many clicks in a row this is, e.g. 2 for the second click of a
+    /// double-click. Always 1 for scrolling.
+    pub clicks: u8,
+    /// Whether the button was held down rather than clicked. Held buttons
+    /// are reported as soon as they have been held for long enough, and
+    /// their release isn't reported.
+    pub held: bool,
+    /// The name of the [`ClickSpan`] that was pressed, if the panel has one
+    /// there
+    pub span: Option<&'static str>,
 }
 
 /// An event that can be passed to a panel
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+#[non_exhaustive]
 pub enum Event {
     /// A mouse event
     Mouse(MouseEvent),
     /// A message (typically from another process)
     Action(String),
+    /// The pointer moved onto the panel
+    MouseEnter,
+    /// The pointer moved off of the panel
+    MouseLeave,
 }
 
 /// A response to an event
@@ -289,7 +695,27 @@ pub struct Panel {
     /// Whether the panel is visible. To set this value on startup, see
     /// [`PanelCommon`][crate::common::PanelCommon].
     pub visible: bool,
+    /// The row of the bar that the panel is drawn in, starting from zero at
+    /// the top
+    pub row: usize,
+    /// An identifier for the panel that doesn't change when other panels are
+    /// added, removed, or reordered. See [`Panel::default_id`].
+    pub id: String,
+    /// The group that the panel belongs to, if any. The panels in a group
+    /// are shown and hidden together.
+    pub group: Option<String>,
+    /// The group that clicking the panel shows or hides, if any.
+    pub toggles: Option<String>,
+    /// How important the panel is when its row runs out of room. Panels
+    /// with a priority are hidden behind an expander, lowest first, until
+    /// the rest fit. Panels without one are never hidden this way.
+    pub priority: Option<u64>,
+    /// Whether the panel is hidden behind the expander
+    overflowed: bool,
+    /// Whether the panel is hidden by zen mode, regardless of `visible`
+    zen_hidden: bool,
     endpoint: Option<Arc<Mutex<ChannelEndpoint<Event, EventResponse>>>>,
+    transition: Option<Transition>,
 }
 
 impl Panel {
@@ -306,8 +732,209 @@ impl Panel {
             x: 0.0,
             name,
             visible,
+            row: 0,
+            id: String::from(name),
+            group: None,
+            toggles: None,
+            priority: None,
+            overflowed: false,
+            zen_hidden: false,
             endpoint: endpoint.map(|e| Arc::new(Mutex::new(e))),
+            transition: None,
+        }
+    }
+
+    /// Whether the panel is drawn: it's visible and not hidden by zen mode.
+    const fn shown(&self) -> bool {
+        self.visible && !self.zen_hidden
+    }
+
+    /// Shows or hides the panel, running its show or hide function if that
+    /// changes whether it's drawn.
+    fn set_visible(&mut self, visible: bool) -> Result<()> {
+        let shown = self.shown();
+        self.visible = visible;
+        self.run_show_hide(shown)
+    }
+
+    /// Hides the panel for zen mode, or stops hiding it, without changing
+    /// whether it's visible otherwise.
+    fn set_zen_hidden(&mut self, hidden: bool) -> Result<()> {
+        let shown = self.shown();
+        self.zen_hidden = hidden;
+        self.run_show_hide(shown)
+    }
+
+    /// Runs the panel's show or hide function if whether it's drawn changed
+    /// from `shown`.
+    fn run_show_hide(&self, shown: bool) -> Result<()> {
+        if self.shown() == shown {
+            return Ok(());
+        }
+        if let Some(ref draw_info) = self.draw_info {
+            let f = if shown {
+                &draw_info.hide_fn
+            } else {
+                &draw_info.show_fn
+            };
+            if let Some(f) = f {
+                f()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether clicking the panel does something, either because it says so
+    /// or because it toggles a group.
+    fn interactive(&self) -> bool {
+        self.toggles.is_some()
+            || self.draw_info.as_ref().is_some_and(|d| d.interactive)
+    }
+
+    /// Whether a panel that doesn't depend on its neighbors has anything to
+    /// show, or [`None`] if it does depend on them.
+    fn shows(draw_info: Option<&PanelDrawInfo>) -> Option<bool> {
+        match draw_info {
+            None => Some(false),
+            Some(d) if d.dependence == Dependence::None => Some(d.width > 0),
+            Some(_) => None,
+        }
+    }
+
+    /// Replaces the panel's draw info, starting a transition if the panel
+    /// appears, disappears, or changes width. `transition` is the style and
+    /// duration of transitions for appearing and disappearing, and
+    /// `animation` is the duration of changes in width.
+    fn set_draw_info(
+        &mut self,
+        draw_info: PanelDrawInfo,
+        transition: (TransitionStyle, Duration),
+        animation: Duration,
+    ) {
+        let previous = self.transition.take();
+        let Some(shows) =
+            Self::shows(Some(&draw_info)).filter(|_| self.shown())
+        else {
+            self.draw_info = Some(draw_info);
+            return;
+        };
+
+        // keep disappearing, but end up with the latest draw info
+        if !shows && previous.as_ref().is_some_and(|p| p.hidden.is_some()) {
+            if let Some(mut previous) = previous {
+                previous.hidden = Some(draw_info);
+                self.draw_info = Some(previous.frame());
+                self.transition = Some(previous);
+            }
+            return;
+        }
+
+        let from = match &previous {
+            Some(previous) => previous.width(),
+            None => match Self::shows(self.draw_info.as_ref()) {
+                Some(true) => {
+                    f64::from(self.draw_info.as_ref().map_or(0, |d| d.width))
+                }
+                Some(false) => 0.0,
+                None => {
+                    self.draw_info = Some(draw_info);
+                    return;
+                }
+            },
+        };
+        let to = if shows {
+            f64::from(draw_info.width)
+        } else {
+            0.0
+        };
+
+        // appearing and disappearing are styled, changing width isn't
+        let styled = from <= 0.0
+            || !shows
+            || previous
+                .as_ref()
+                .is_some_and(|p| p.style != TransitionStyle::None);
+        let (style, duration) = if styled {
+            transition
+        } else {
+            (TransitionStyle::None, animation)
+        };
+        if duration.is_zero()
+            || (styled && style == TransitionStyle::None)
+            || (previous.is_none() && (from - to).abs() < 0.5)
+        {
+            self.draw_info = Some(draw_info);
+            return;
+        }
+
+        let (content, hidden) = if shows {
+            (Rc::new(draw_info), None)
+        } else if let Some(previous) = previous {
+            (previous.content, Some(draw_info))
+        } else if let Some(old) = self.draw_info.take() {
+            (Rc::new(old), Some(draw_info))
+        } else {
+            self.draw_info = Some(draw_info);
+            return;
+        };
+
+        let transition = Transition {
+            style,
+            start: Instant::now(),
+            duration,
+            from,
+            content,
+            hidden,
+        };
+        self.draw_info = Some(transition.frame());
+        self.transition = Some(transition);
+    }
+
+    /// Moves the panel's transition along, returning whether there was one.
+    fn step_transition(&mut self) -> bool {
+        let Some(transition) = self.transition.take() else {
+            return false;
+        };
+        if !transition.done() {
+            self.draw_info = Some(transition.frame());
+            self.transition = Some(transition);
+            return true;
         }
+
+        // the current frame holds onto the contents
+        drop(self.draw_info.take());
+        self.draw_info = if transition.hidden.is_some() {
+            transition.hidden
+        } else {
+            Rc::try_unwrap(transition.content)
+                .map_err(|_| {
+                    log::warn!("Panel {} is still being drawn", self.name);
+                })
+                .ok()
+        };
+        true
+    }
+
+    /// Returns the id of a panel that doesn't have one set explicitly.
+    ///
+    /// The id is made from the panel's name and a hash of its alignment, its
+    /// row, and the number of panels with the same name that come before it
+    /// in that row and alignment. As a result, it only changes if the panel
+    /// itself moves.
+    #[must_use]
+    pub fn default_id(
+        name: &str,
+        alignment: Alignment,
+        row: usize,
+        occurrence: usize,
+    ) -> String {
+        // FNV-1a, which unlike std's hashers is guaranteed to be stable
+        let hash = format!("{alignment}:{row}:{name}:{occurrence}")
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        format!("{name}-{:08x}", (hash >> 32) ^ (hash & 0xffff_ffff))
     }
 }
 
@@ -324,11 +951,13 @@ pub struct Bar {
     window: Window,
     surface: cairo::XCBSurface,
     pub(crate) cr: Rc<cairo::Context>,
+    info: BarInfo,
     width: i32,
     height: u16,
     bg: Color,
+    background: BarBg,
     margins: Margins,
-    extents: Extents,
+    extents: Vec<Extents>,
     reverse_scroll: bool,
     pub(crate) left_panels: Vec<Panel>,
     pub(crate) center_panels: Vec<Panel>,
@@ -337,38 +966,219 @@ pub struct Bar {
     pub(crate) streams: StreamMap<Alignment, StreamMap<usize, PanelStream>>,
     pub(crate) ipc: bool,
     mapped: bool,
-    center_state: CenterState,
+    center_state: Vec<CenterState>,
+    /// Whether each row has more panels than fit
+    overflowing: Vec<bool>,
+    /// Where the expander of each row starts, if it's drawn
+    expanders: Vec<Option<f64>>,
+    /// Whether the expander is open, showing the panels hidden behind it
+    overflow_expanded: bool,
+    mon: MonitorInfo,
+    monitor: Option<String>,
+    disconnected: bool,
+    rows: usize,
+    panel_height: u16,
+    y_offset: f64,
+    zen_panels: Vec<String>,
+    zen_height: Option<u16>,
+    zen: bool,
+    sensitive: HashSet<&'static str>,
+    privacy_classes: Vec<String>,
+    privacy_windows: HashMap<Window, bool>,
+    privacy_ipc: bool,
+    privacy_auto: bool,
+    privacy_screencast: bool,
+    privacy: bool,
+    focus_accent: Option<Color>,
+    focus_accent_height: u16,
+    focused: bool,
+    autohide: Option<Autohide>,
+    hide_on_fullscreen: bool,
+    fullscreen: bool,
+    /// The window whose `_NET_WM_STATE` is being watched for fullscreen
+    fullscreen_watch: Option<Window>,
+    attrs: Attrs,
+    tooltip_delay: Duration,
+    hover: Option<Hover>,
+    double_click_time: Duration,
+    hold_time: Duration,
+    transition: TransitionStyle,
+    transition_duration: Duration,
+    animation_duration: Duration,
+    transparent: bool,
+    corner_radius: f64,
+    border_width: f64,
+    border_color: Color,
+    shadow_radius: f64,
+    shadow_opacity: f64,
+    click_through: HashSet<&'static str>,
+    click_through_gaps: bool,
+    layer: Layer,
+    input_holes: Vec<Rectangle>,
+    press: Option<Press>,
+    last_click: Option<Press>,
+    /// The id of the panel under the pointer
+    hovered: Option<String>,
+    hand_cursor: Option<Cursor>,
+    /// Whether the hand cursor is showing
+    pointing: bool,
+    #[dbg(placeholder = "..")]
+    tooltip: Option<Tooltip>,
+    #[dbg(placeholder = "..")]
+    popup: Option<Popup>,
+    #[dbg(placeholder = "..")]
+    menu: Option<Menu>,
+    #[dbg(placeholder = "..")]
+    pending: Vec<(Alignment, usize, PanelDrawInfo)>,
+    /// The menu that a queued update asked to open, kept apart from the
+    /// update so that a newer update from the same panel doesn't drop it
+    pending_menu: Option<(Alignment, usize, Vec<MenuEntry>)>,
+    #[cfg(feature = "a11y")]
+    a11y: Option<crate::a11y::Exporter>,
 }
 
-impl Bar {
-    /// Create a new bar, typically from information held by a
-    /// [`BarConfig`][crate::BarConfig].
-    pub fn new(
-        name: &str,
+/// The state of a bar that hides itself while the pointer isn't on it.
+#[derive(Debug)]
+struct Autohide {
+    /// An input-only window along the edge of the screen that reveals the
+    /// bar when the pointer enters it
+    trigger: Window,
+    delay: Duration,
+    hide_at: Option<Instant>,
+    hidden: bool,
+}
+
+/// A mouse button pressed on a panel. The press is sent to the panel once
+/// the button is released or has been held down for long enough.
+#[derive(Debug)]
+struct Press {
+    id: String,
+    /// The X button number
+    detail: u8,
+    event: MouseEvent,
+    /// When the button was pressed, or released for the last click
+    at: Instant,
+}
+
+/// The panel under the pointer, for showing its tooltip.
+#[derive(Debug)]
+struct Hover {
+    id: String,
+    /// When the pointer last moved onto the panel
+    since: Instant,
+    /// The position of the pointer relative to the root window
+    pointer: (i16, i16),
+    /// The x coordinate of the pointer relative to the panel
+    x: i32,
+}
+
+impl Autohide {
+    fn new(
+        conn: &XCBConnection,
+        screen: usize,
         position: Position,
-        height: u16,
-        transparent: bool,
-        bg: Color,
-        margins: Margins,
-        reverse_scroll: bool,
-        ipc: bool,
-        monitor: Option<String>,
-    ) -> Result<(Self, IpcStream)> {
+        mon: &MonitorInfo,
+        delay: Duration,
+    ) -> Result<Self> {
+        let trigger = conn.generate_id()?;
+        let (x, y, width, height) = bar_geometry(position, 1, mon);
+        conn.create_window(
+            COPY_DEPTH_FROM_PARENT,
+            trigger,
+            conn.setup().roots[screen].root,
+            x,
+            y,
+            width,
+            height,
+            0,
+            WindowClass::INPUT_ONLY,
+            COPY_FROM_PARENT,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .event_mask(EventMask::ENTER_WINDOW),
+        )?;
+
+        Ok(Self {
+            trigger,
+            delay,
+            hide_at: Some(Instant::now() + delay),
+            hidden: false,
+        })
+    }
+}
+
+impl Bar {
+    /// Create a new bar from the options in a [`BarConfig`]. Its panels
+    /// aren't used; they're started by [`BarConfig::run`].
+    pub fn new(config: &BarConfig) -> Result<(Self, IpcStream)> {
+        let BarConfig {
+            position,
+            height,
+            rows,
+            transparent,
+            reverse_scroll,
+            ipc,
+            zen_height,
+            accessibility,
+            focus_accent_height,
+            autohide,
+            hide_on_fullscreen,
+            tooltip_delay,
+            double_click_time,
+            hold_time,
+            transition,
+            transition_duration,
+            animation_duration,
+            corner_radius,
+            border_width,
+            blur,
+            shadow_radius,
+            shadow_opacity,
+            click_through_gaps,
+            layer,
+            ..
+        } = *config;
+        let name = config.name.as_str();
+        let background = config.bg.clone();
+        let margins = config.margins.clone();
+        let monitor = config.monitor.clone();
+        let zen_panels = config.zen_panels.clone();
+        let sensitive = config.sensitive.clone();
+        let privacy_classes = config.privacy_classes.clone();
+        let focus_accent = config.focus_accent.clone();
+        let attrs = config.attrs.clone();
+        let border_color = config.border_color.clone();
+        let click_through = config.click_through.clone();
+
+        let panel_height = height;
+        let height = height * rows as u16;
+        let bg = background.color();
         let (conn, screen, window, width, visual, mon) =
-            create_window(position, height, transparent, &bg, monitor)?;
+            create_window(position, height, transparent, &bg, monitor.clone())?;
+        let hand_cursor = hand_cursor(&conn)
+            .map_err(|e| log::warn!("Failed to load the hand cursor: {e}"))
+            .ok();
 
-        BAR_INFO
-            .set(BarInfo {
-                window,
-                visual,
-                width,
-                height,
-                transparent,
-                bg: bg.clone(),
-            })
-            .unwrap();
+        let info = BarInfo {
+            window,
+            visual,
+            width,
+            height: panel_height,
+            transparent,
+            bg: bg.clone(),
+            high_contrast: Arc::new(AtomicBool::new(config.high_contrast)),
+        };
 
         let (result, name) = ipc::init(ipc, name);
+
+        #[cfg(feature = "a11y")]
+        let a11y = accessibility.then(|| crate::a11y::Exporter::start(&name));
+        #[cfg(not(feature = "a11y"))]
+        if accessibility {
+            log::warn!(
+                "Accessibility was requested, but the a11y feature is disabled"
+            );
+        }
         let ipc_stream: Pin<
             Box<
                 dyn Stream<
@@ -394,43 +1204,150 @@ impl Bar {
             height.into(),
             name.as_str(),
             &mon,
+            autohide.is_none(),
+            layer,
         );
+        if blur {
+            if transparent {
+                set_blur(&conn, window)?;
+            } else {
+                log::warn!("Background blur requires a transparent bar");
+            }
+        }
+        if shadow_radius > 0.0 && !transparent {
+            log::warn!("Drawing a shadow requires a transparent bar");
+        }
         conn.map_window(window)?;
-        let surface =
-            create_surface(window, visual, width.into(), height.into(), &conn)?;
+        let autohide = autohide
+            .map(|delay| Autohide::new(&conn, screen, position, &mon, delay))
+            .transpose()?;
+        // to be notified when monitors are connected, disconnected, or change
+        // resolution
+        conn.randr_select_input(
+            conn.setup().roots[screen].root,
+            NotifyMask::SCREEN_CHANGE
+                | NotifyMask::CRTC_CHANGE
+                | NotifyMask::OUTPUT_CHANGE,
+        )?;
+        // to be notified when _NET_ACTIVE_WINDOW changes, which also closes
+        // popups
+        conn.change_window_attributes(
+            conn.setup().roots[screen].root,
+            &ChangeWindowAttributesAux::new()
+                .event_mask(EventMask::PROPERTY_CHANGE),
+        )?;
+        let (_, _, window_width, window_height) =
+            bar_geometry(position, height, &mon);
+        let surface = create_surface(
+            window,
+            visual,
+            window_width.into(),
+            window_height.into(),
+            &conn,
+        )?;
         let cr = cairo::Context::new(&surface)?;
         surface.flush();
         conn.flush()?;
 
-        Ok((
-            Self {
-                name,
-                position,
-                conn: Arc::new(conn),
-                screen,
-                window,
-                surface,
-                cr: Rc::new(cr),
-                width: width.into(),
-                height,
-                bg,
-                margins,
-                extents: Extents {
+        let mut bar = Self {
+            name,
+            position,
+            conn,
+            screen,
+            info,
+            window,
+            surface,
+            cr: Rc::new(cr),
+            width: width.into(),
+            height,
+            bg,
+            background,
+            margins,
+            extents: (0..rows)
+                .map(|_| Extents {
                     left: 0.0,
                     center: ((width / 2).into(), (width / 2).into()),
                     right: width.into(),
-                },
-                reverse_scroll,
-                left_panels: Vec::new(),
-                center_panels: Vec::new(),
-                right_panels: Vec::new(),
-                streams: StreamMap::new(),
-                ipc,
-                mapped: true,
-                center_state: CenterState::Center,
-            },
-            ipc_stream,
-        ))
+                })
+                .collect(),
+            reverse_scroll,
+            left_panels: Vec::new(),
+            center_panels: Vec::new(),
+            right_panels: Vec::new(),
+            streams: StreamMap::new(),
+            ipc,
+            mapped: true,
+            center_state: (0..rows).map(|_| CenterState::Center).collect(),
+            overflowing: vec![false; rows],
+            expanders: vec![None; rows],
+            overflow_expanded: false,
+            mon,
+            monitor,
+            disconnected: false,
+            rows,
+            panel_height,
+            y_offset: 0.0,
+            zen_panels,
+            zen_height,
+            zen: false,
+            sensitive,
+            privacy_classes,
+            privacy_windows: HashMap::new(),
+            privacy_ipc: false,
+            privacy_auto: false,
+            privacy_screencast: false,
+            privacy: false,
+            focus_accent,
+            focus_accent_height,
+            focused: false,
+            autohide,
+            hide_on_fullscreen,
+            fullscreen: false,
+            fullscreen_watch: None,
+            attrs,
+            tooltip_delay,
+            hover: None,
+            double_click_time,
+            hold_time,
+            transition,
+            transition_duration,
+            animation_duration,
+            transparent,
+            corner_radius,
+            border_width,
+            border_color,
+            shadow_radius,
+            shadow_opacity,
+            click_through,
+            click_through_gaps,
+            layer,
+            input_holes: Vec::new(),
+            press: None,
+            last_click: None,
+            hovered: None,
+            hand_cursor,
+            pointing: false,
+            tooltip: None,
+            popup: None,
+            menu: None,
+            pending: Vec::new(),
+            pending_menu: None,
+            #[cfg(feature = "a11y")]
+            a11y,
+        };
+        if bar.shadow_extent() > 0 {
+            // grow the window to make room for the shadow
+            bar.configure()?;
+        }
+        bar.cr.set_matrix(bar.base_matrix());
+        bar.round_corners()?;
+
+        Ok((bar, ipc_stream))
+    }
+
+    /// Returns information about the bar for [`with_info`].
+    pub(crate) fn info(&self) -> BarInfo {
+        self.info.clone()
     }
 
     /// Calls each panel's shutdown function
@@ -444,23 +1361,28 @@ impl Bar {
             .for_each(|shutdown| shutdown());
     }
 
-    fn apply_dependence(panels: &[Panel]) -> Vec<PanelStatus> {
+    fn apply_dependence(panels: &[Panel], row: usize) -> Vec<PanelStatus> {
+        let panels = panels.iter().filter(|p| p.row == row).collect::<Vec<_>>();
         (0..panels.len())
-            .map(|idx| match PanelStatus::from(&panels[idx]) {
+            .map(|idx| match PanelStatus::from(panels[idx]) {
                 PanelStatus::Shown => PanelStatus::Shown,
                 PanelStatus::ZeroWidth => PanelStatus::ZeroWidth,
                 PanelStatus::Dependent(Dependence::Left) => panels
                     .get(idx - 1)
+                    .copied()
                     .map_or(PanelStatus::ZeroWidth, PanelStatus::from),
                 PanelStatus::Dependent(Dependence::Right) => panels
                     .get(idx + 1)
+                    .copied()
                     .map_or(PanelStatus::ZeroWidth, PanelStatus::from),
                 PanelStatus::Dependent(Dependence::Both) => {
                     panels
                         .get(idx - 1)
+                        .copied()
                         .map_or(PanelStatus::ZeroWidth, PanelStatus::from)
                         & panels
                             .get(idx + 1)
+                            .copied()
                             .map_or(PanelStatus::ZeroWidth, PanelStatus::from)
                 }
                 PanelStatus::Dependent(Dependence::None) => unreachable!(),
@@ -496,9 +1418,96 @@ impl Bar {
             });
     }
 
+    /// Returns whether an event is meant for this bar. Every bar shares one
+    /// X connection, so events on other bars' windows arrive here too. Events
+    /// that aren't tied to a window of a bar, such as property changes on the
+    /// root window, are meant for every bar.
+    fn owns_event(&self, event: &protocol::Event) -> bool {
+        let window = match event {
+            protocol::Event::Expose(event) => event.window,
+            protocol::Event::ButtonPress(event)
+            | protocol::Event::ButtonRelease(event) => event.event,
+            protocol::Event::EnterNotify(event)
+            | protocol::Event::LeaveNotify(event) => event.event,
+            protocol::Event::MotionNotify(event) => event.event,
+            _ => return true,
+        };
+        window == self.window
+            || self.tooltip.as_ref().is_some_and(|t| t.window == window)
+            || self.popup.as_ref().is_some_and(|p| p.window == window)
+            || self.menu.as_ref().is_some_and(|m| m.popup.window == window)
+            || self.autohide.as_ref().is_some_and(|a| a.trigger == window)
+    }
+
     /// Handle an event from the X server.
     pub fn process_event(&mut self, event: &protocol::Event) -> Result<()> {
+        if !self.owns_event(event) {
+            return Ok(());
+        }
         match event {
+            protocol::Event::Expose(event)
+                if self
+                    .tooltip
+                    .as_ref()
+                    .is_some_and(|tooltip| tooltip.window == event.window) =>
+            {
+                self.tooltip.as_ref().map_or(Ok(()), Tooltip::draw)
+            }
+            protocol::Event::Expose(event)
+                if self
+                    .popup
+                    .as_ref()
+                    .is_some_and(|popup| popup.window == event.window) =>
+            {
+                self.update_popup()
+            }
+            protocol::Event::Expose(event)
+                if self
+                    .menu
+                    .as_ref()
+                    .is_some_and(|menu| menu.popup.window == event.window) =>
+            {
+                self.menu
+                    .as_ref()
+                    .map_or(Ok(()), |menu| menu.popup.draw(&menu.content))
+            }
+            protocol::Event::ButtonPress(event)
+                if self
+                    .menu
+                    .as_ref()
+                    .is_some_and(|menu| menu.popup.window == event.event) =>
+            {
+                // any click closes the menu, but only clicks on an entry run
+                // its action
+                let Some(menu) = self.menu.take() else {
+                    return Ok(());
+                };
+                let Some(action) = menu.action_at(event.event_x, event.event_y)
+                else {
+                    return Ok(());
+                };
+                if let Some(e) = self
+                    .panel_by_id(menu.popup.owner.as_str())
+                    .and_then(|p| p.endpoint.as_ref())
+                {
+                    e.lock().unwrap().send.send(Event::Action(action))?;
+                }
+                Ok(())
+            }
+            protocol::Event::ButtonPress(event)
+                if self
+                    .popup
+                    .as_ref()
+                    .is_some_and(|popup| popup.window == event.event) =>
+            {
+                // clicks anywhere but the bar and the popup close it
+                if !self.popup.as_ref().is_some_and(|popup| {
+                    popup.contains(event.event_x, event.event_y)
+                }) {
+                    self.popup = None;
+                }
+                Ok(())
+            }
             protocol::Event::Expose(_) => {
                 log::info!(
                     "Received expose event from X server; redrawing entire bar"
@@ -513,113 +1522,1519 @@ impl Bar {
                         // TODO: make sure this works/is relevant
                         (event.root_x, event.root_y)
                     };
+                    let (x, y) = self.unrotate(x, y);
+                    // clicking dismisses the tooltip
+                    self.hover = None;
+                    self.tooltip = None;
+
+                    // the popup's own panel decides whether to close it
+                    let owner = self.popup.as_ref().is_some_and(|popup| {
+                        self.panel_at(x, y).is_some_and(|p| p.id == popup.owner)
+                    });
+                    if !owner {
+                        self.popup = None;
+                    }
 
-                    let panel = self
-                        .left_panels
-                        .iter()
-                        .chain(self.center_panels.iter())
-                        .chain(self.right_panels.iter())
-                        .filter(|p| p.draw_info.is_some())
-                        .find(|p| {
-                            p.x <= x as f64
-                                && p.x
-                                    + p.draw_info.as_ref().unwrap().width as f64
-                                    >= x as f64
-                        });
-                    if let Some(p) = panel {
-                        if let Some(e) = &p.endpoint {
-                            let e = e.lock().unwrap();
-                            e.send.send(Event::Mouse(MouseEvent {
-                                button: MouseButton::try_parse(
-                                    button,
-                                    self.reverse_scroll,
-                                )
-                                // this can never fail due to match arm
-                                .unwrap(),
-                                x: x - p.x as i16,
-                                y,
-                            }))?;
+                    // clicking the expander shows or hides the panels that
+                    // don't fit
+                    if button == 1 && self.expander_at(x, y) {
+                        self.overflow_expanded = !self.overflow_expanded;
+                        return self.redraw_bar();
+                    }
+
+                    // right-clicking a panel with a menu toggles the menu
+                    // instead of sending the click to the panel
+                    let menu_owner =
+                        self.menu.take().map(|menu| menu.popup.owner.clone());
+                    let menu = self
+                        .panel_at(x, y)
+                        .filter(|p| {
+                            p.draw_info.as_ref().is_some_and(|d| {
+                                !d.menu.is_empty() && !d.show_menu
+                            })
+                        })
+                        .map(|p| p.id.clone());
+                    if let (3, Some(id)) = (button, menu) {
+                        if menu_owner.as_ref() == Some(&id) {
+                            return Ok(());
                         }
+                        return self.open_menu(id.as_str());
+                    }
+
+                    // left-clicking a group's toggle shows or hides the group
+                    // instead of sending the click to the panel
+                    let group = self
+                        .panel_at(x, y)
+                        .and_then(|p| p.toggles.clone())
+                        .filter(|_| button == 1);
+                    if let Some(group) = group {
+                        return self
+                            .handle_group_event(group.as_str(), "toggle")
+                            .map(|_| ());
+                    }
+
+                    let Some(p) = self.panel_at(x, y) else {
+                        return Ok(());
+                    };
+                    let mut press = Press {
+                        id: p.id.clone(),
+                        detail: button,
+                        event: MouseEvent {
+                            button: MouseButton::try_parse(
+                                button,
+                                self.reverse_scroll,
+                            )
+                            // this can never fail due to match arm
+                            .unwrap(),
+                            x: x - p.x as i16,
+                            y: y - self.row_y(p.row) as i16,
+                            modifiers: u16::from(event.state),
+                            clicks: 1,
+                            held: false,
+                            span: p.draw_info.as_ref().and_then(|d| {
+                                d.span_at(i32::from(x - p.x as i16))
+                            }),
+                        },
+                        at: Instant::now(),
+                    };
+                    if button >= 4 {
+                        // scrolling can't be held or double-clicked
+                        return self.send_mouse(&press.id, press.event);
                     }
+
+                    press.event.clicks = match self.last_click {
+                        Some(ref last)
+                            if last.id == press.id
+                                && last.detail == button
+                                && last.at.elapsed()
+                                    <= self.double_click_time =>
+                        {
+                            last.event.clicks.saturating_add(1)
+                        }
+                        _ => 1,
+                    };
+                    // sent once the button is released or has been held
+                    self.press = Some(press);
                     Ok(())
                 }
                 _ => Ok(()),
             },
+            protocol::Event::ButtonRelease(event) => {
+                match self.press.take() {
+                    Some(mut press) if press.detail == event.detail => {
+                        self.send_mouse(&press.id, press.event)?;
+                        press.at = Instant::now();
+                        self.last_click = Some(press);
+                    }
+                    press => self.press = press,
+                }
+                Ok(())
+            }
+            protocol::Event::EnterNotify(event) => {
+                if let Some(ref mut autohide) = self.autohide {
+                    if event.event == autohide.trigger {
+                        return self.reveal();
+                    }
+                    // the pointer is on the bar, so keep it visible
+                    autohide.hide_at = None;
+                }
+                Ok(())
+            }
+            protocol::Event::MotionNotify(event) => {
+                let (x, y) = self.unrotate(event.event_x, event.event_y);
+                self.set_hovered(self.panel_at(x, y).map(|p| p.id.clone()))?;
+                self.set_pointing(
+                    self.panel_at(x, y).is_some_and(Panel::interactive)
+                        || self.expander_at(x, y),
+                )?;
+                let target = self
+                    .panel_at(x, y)
+                    .filter(|p| {
+                        p.draw_info.as_ref().is_some_and(|d| {
+                            d.tooltip.is_some() || !d.tooltips.is_empty()
+                        })
+                    })
+                    .map(|p| (p.id.clone(), i32::from(x) - p.x as i32));
+                let pointer = (event.root_x, event.root_y);
+                match (target, self.hover.as_mut()) {
+                    (Some((id, x)), Some(hover)) if hover.id == id => {
+                        // a tooltip that is already shown doesn't follow
+                        // the pointer unless its markup changes
+                        hover.pointer = pointer;
+                        hover.x = x;
+                    }
+                    (Some((id, x)), _) => {
+                        self.hover = Some(Hover {
+                            id,
+                            since: Instant::now(),
+                            pointer,
+                            x,
+                        });
+                        self.tooltip = None;
+                    }
+                    (None, _) => {
+                        self.hover = None;
+                        self.tooltip = None;
+                    }
+                }
+                Ok(())
+            }
+            protocol::Event::LeaveNotify(event)
+                if event.detail != NotifyDetail::INFERIOR =>
+            {
+                self.hover = None;
+                self.tooltip = None;
+                self.set_hovered(None)?;
+                if let Some(ref mut autohide) = self.autohide {
+                    if !autohide.hidden {
+                        autohide.hide_at =
+                            Some(Instant::now() + autohide.delay);
+                    }
+                }
+                Ok(())
+            }
+            protocol::Event::RandrScreenChangeNotify(_)
+            | protocol::Event::RandrNotify(_) => self.update_monitor(),
+            protocol::Event::PropertyNotify(event)
+                if event.atom
+                    == InternedAtoms::get(
+                        self.conn.as_ref(),
+                        "_NET_ACTIVE_WINDOW",
+                    )? =>
+            {
+                self.popup = None;
+                self.menu = None;
+                self.check_focus()?;
+                self.check_fullscreen()
+            }
+            protocol::Event::PropertyNotify(event)
+                if event.atom
+                    == InternedAtoms::get(
+                        self.conn.as_ref(),
+                        "_NET_CLIENT_LIST",
+                    )? =>
+            {
+                self.check_privacy()
+            }
+            protocol::Event::PropertyNotify(event)
+                if Some(event.window) == self.fullscreen_watch
+                    && event.atom
+                        == InternedAtoms::get(
+                            self.conn.as_ref(),
+                            "_NET_WM_STATE",
+                        )? =>
+            {
+                self.check_fullscreen()
+            }
             _ => Ok(()),
         }
     }
 
-    fn handle_ipc_event(&mut self, message: &str) -> Result<bool> {
-        match message {
-            "quit" => Ok(true),
-            "show" => {
-                self.mapped = true;
+    /// Moves and resizes the bar to fit its monitor after the monitor layout
+    /// changes. While the monitor is disconnected, the bar is unmapped and its
+    /// panels are hidden.
+    pub fn update_monitor(&mut self) -> Result<()> {
+        let root = self
+            .conn
+            .setup()
+            .roots
+            .get(self.screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?
+            .root;
+        let Some(mon) =
+            find_monitor(self.conn.as_ref(), root, self.monitor.as_deref())?
+        else {
+            if !self.disconnected {
+                log::info!("Monitor disconnected; hiding the bar");
+                self.disconnected = true;
+                self.conn.unmap_window(self.window)?;
+                self.popup = None;
+                self.menu = None;
+                self.hide_panels();
+                self.conn.flush()?;
+            }
+            return Ok(());
+        };
+
+        if !self.disconnected
+            && (mon.x, mon.y, mon.width, mon.height)
+                == (self.mon.x, self.mon.y, self.mon.width, self.mon.height)
+        {
+            return Ok(());
+        }
+        log::info!(
+            "Monitor changed to {}x{}+{}+{}; moving the bar",
+            mon.width,
+            mon.height,
+            mon.x,
+            mon.y
+        );
+
+        self.width = if self.position.is_vertical() {
+            mon.height.into()
+        } else {
+            mon.width.into()
+        };
+        self.mon = mon;
+        self.configure()?;
+
+        if self.disconnected {
+            log::info!("Monitor reconnected; showing the bar");
+            self.disconnected = false;
+            if self.shown() {
                 self.conn.map_window(self.window)?;
                 self.show_panels();
+            }
+        }
+        self.conn.flush()?;
+
+        self.redraw_bar()
+    }
+
+    /// Whether the bar hides itself while the pointer isn't on it.
+    #[must_use]
+    pub const fn autohides(&self) -> bool {
+        self.autohide.is_some()
+    }
+
+    /// Whether a mouse button is down on a panel.
+    #[must_use]
+    pub const fn pressing(&self) -> bool {
+        self.press.is_some()
+    }
+
+    /// Sends a mouse button that has been held down for long enough to its
+    /// panel as held.
+    pub fn check_hold(&mut self) -> Result<()> {
+        if !self
+            .press
+            .as_ref()
+            .is_some_and(|press| press.at.elapsed() >= self.hold_time)
+        {
+            return Ok(());
+        }
+        let Some(mut press) = self.press.take() else {
+            return Ok(());
+        };
+        press.event.held = true;
+        self.last_click = None;
+        self.send_mouse(&press.id, press.event)
+    }
+
+    /// Whether the bar is showing any animated panels, or any panels are
+    /// appearing, disappearing, or changing width.
+    #[must_use]
+    pub fn animating(&self) -> bool {
+        let mut panels = self
+            .left_panels
+            .iter()
+            .chain(self.center_panels.iter())
+            .chain(self.right_panels.iter());
+        if self.shown() {
+            panels.any(|p| p.transition.is_some() || Self::animated(p))
+        } else {
+            panels.any(|p| p.transition.is_some())
+        }
+    }
+
+    fn animated(panel: &Panel) -> bool {
+        panel.shown()
+            && panel
+                .draw_info
+                .as_ref()
+                .is_some_and(|draw_info| draw_info.animated)
+    }
+
+    /// Moves along any panels that are appearing, disappearing, or changing
+    /// width, and redraws each animated panel without waiting for it to
+    /// update.
+    pub fn animate(&mut self) -> Result<()> {
+        let mut transitioned = false;
+        for panel in self
+            .left_panels
+            .iter_mut()
+            .chain(self.center_panels.iter_mut())
+            .chain(self.right_panels.iter_mut())
+        {
+            transitioned |= panel.step_transition();
+        }
+        if !self.shown() {
+            return Ok(());
+        }
+        if transitioned {
+            return self.redraw_bar();
+        }
+
+        for (alignment, panels) in [
+            (Alignment::Left, &self.left_panels),
+            (Alignment::Center, &self.center_panels),
+            (Alignment::Right, &self.right_panels),
+        ] {
+            for (idx, _) in panels
+                .iter()
+                .enumerate()
+                .filter(|(_, panel)| Self::animated(panel))
+            {
+                self.redraw_one(alignment, idx)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the pointer is resting on a panel with a tooltip.
+    #[must_use]
+    pub const fn hovering(&self) -> bool {
+        self.hover.is_some()
+    }
+
+    /// Shows the tooltip of the panel under the pointer once the pointer has
+    /// rested on it for long enough, and keeps a shown tooltip up to date.
+    pub fn check_tooltip(&mut self) -> Result<()> {
+        let Some(ref hover) = self.hover else {
+            return Ok(());
+        };
+        if !self.shown() {
+            self.tooltip = None;
+            return Ok(());
+        }
+        if hover.since.elapsed() < self.tooltip_delay {
+            return Ok(());
+        }
+        let markup = self
+            .panel_by_id(hover.id.as_str())
+            .filter(|p| !(self.privacy && self.sensitive.contains(p.name)))
+            .and_then(|p| p.draw_info.as_ref())
+            .and_then(|d| d.tooltip_at(hover.x));
+        let Some(markup) = markup else {
+            self.tooltip = None;
+            return Ok(());
+        };
+        if self
+            .tooltip
+            .as_ref()
+            .is_some_and(|tooltip| tooltip.markup == markup)
+        {
+            return Ok(());
+        }
+
+        // drop the old tooltip first so that only one is ever mapped
+        self.tooltip = None;
+        self.tooltip = Some(Tooltip::show(
+            self.conn.clone(),
+            self.screen,
+            markup,
+            &self.attrs,
+            &self.bg,
+            self.position,
+            bar_geometry(self.position, self.height, &self.mon),
+            &self.mon,
+            hover.pointer,
+        )?);
+        Ok(())
+    }
+
+    /// Hides the bar if the pointer has been away from it for long enough.
+    pub fn check_autohide(&mut self) -> Result<()> {
+        match self.autohide {
+            Some(Autohide {
+                hide_at: Some(hide_at),
+                ..
+            }) if hide_at <= Instant::now() => self.conceal(),
+            _ => Ok(()),
+        }
+    }
+
+    /// Shows a bar that hides itself, and starts the countdown to hiding it
+    /// again. The countdown is stopped while the pointer is on the bar.
+    fn reveal(&mut self) -> Result<()> {
+        let Some(ref mut autohide) = self.autohide else {
+            return Ok(());
+        };
+        autohide.hide_at = Some(Instant::now() + autohide.delay);
+        if autohide.hidden {
+            log::debug!("Revealing the bar");
+            autohide.hidden = false;
+            self.conn.unmap_window(autohide.trigger)?;
+            if self.shown() {
+                self.conn.map_window(self.window)?;
+            }
+            self.conn.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Hides a bar that hides itself until the pointer touches the edge of
+    /// the screen. Panels keep running so that the bar is up to date when
+    /// it's revealed.
+    fn conceal(&mut self) -> Result<()> {
+        let Some(ref mut autohide) = self.autohide else {
+            return Ok(());
+        };
+        autohide.hide_at = None;
+        if !autohide.hidden {
+            log::debug!("Hiding the bar");
+            autohide.hidden = true;
+            self.conn.unmap_window(self.window)?;
+            self.popup = None;
+            self.menu = None;
+            // fullscreen windows shouldn't reveal the bar
+            if !self.fullscreen {
+                self.conn.map_window(autohide.trigger)?;
+            }
+            self.conn.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Checks whether the active window (`_NET_ACTIVE_WINDOW`) is on the
+    /// bar's monitor, and redraws the bar if that has changed. This does
+    /// nothing unless `focus_accent` is set or a panel is drawn differently
+    /// when the bar is inactive.
+    pub fn check_focus(&mut self) -> Result<()> {
+        if self.focus_accent.is_none() && !effects::tracks_focus() {
+            return Ok(());
+        }
+
+        // with nothing focused, keep showing the last monitor that had focus
+        let Some(active) = self.active_window()? else {
+            return Ok(());
+        };
+        let focused = self.on_monitor(active)?;
+
+        effects::set_active(focused);
+        if focused != self.focused {
+            log::debug!("bar focused: {focused}");
+            self.focused = focused;
+            self.redraw_bar()?;
+        }
+        Ok(())
+    }
+
+    /// Hides the bar while the active window is fullscreen on the bar's
+    /// monitor, and shows it again afterward. This does nothing unless
+    /// `hide_on_fullscreen` is set.
+    pub fn check_fullscreen(&mut self) -> Result<()> {
+        if !self.hide_on_fullscreen {
+            return Ok(());
+        }
+
+        let active = self.active_window()?;
+        if active != self.fullscreen_watch {
+            // to be notified when the active window enters or leaves
+            // fullscreen. the old window may have been destroyed already.
+            if let Some(window) = self.fullscreen_watch {
+                let _ = self.conn.change_window_attributes(
+                    window,
+                    &ChangeWindowAttributesAux::new()
+                        .event_mask(EventMask::NO_EVENT),
+                );
+            }
+            if let Some(window) = active {
+                self.conn.change_window_attributes(
+                    window,
+                    &ChangeWindowAttributesAux::new()
+                        .event_mask(EventMask::PROPERTY_CHANGE),
+                )?;
+            }
+            self.fullscreen_watch = active;
+        }
+
+        let fullscreen = match active {
+            Some(window) => {
+                self.is_fullscreen(window)? && self.on_monitor(window)?
+            }
+            None => false,
+        };
+        if fullscreen == self.fullscreen {
+            return Ok(());
+        }
+        log::debug!("fullscreen window focused: {fullscreen}");
+        self.fullscreen = fullscreen;
+
+        // remove or restore the struts
+        self.configure()?;
+        let concealed =
+            self.autohide.as_ref().filter(|autohide| autohide.hidden);
+        if fullscreen {
+            self.conn.unmap_window(self.window)?;
+            self.popup = None;
+            self.menu = None;
+            if let Some(autohide) = concealed {
+                self.conn.unmap_window(autohide.trigger)?;
+            }
+        } else if self.shown() {
+            self.conn.map_window(self.window)?;
+        } else if let Some(autohide) = concealed {
+            self.conn.map_window(autohide.trigger)?;
+        }
+        self.conn.flush()?;
+
+        Ok(())
+    }
+
+    /// Whether the bar window should be mapped, i.e. it hasn't been hidden
+    /// over IPC, by autohide, or by a fullscreen window, and its monitor is
+    /// connected.
+    fn shown(&self) -> bool {
+        self.mapped
+            && !self.disconnected
+            && !self.fullscreen
+            && !self
+                .autohide
+                .as_ref()
+                .is_some_and(|autohide| autohide.hidden)
+    }
+
+    /// Returns the active window (`_NET_ACTIVE_WINDOW`), if there is one.
+    fn active_window(&self) -> Result<Option<Window>> {
+        let root = self
+            .conn
+            .setup()
+            .roots
+            .get(self.screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?
+            .root;
+        let active_atom =
+            InternedAtoms::get(self.conn.as_ref(), "_NET_ACTIVE_WINDOW")?;
+        Ok(self
+            .conn
+            .get_property(false, root, active_atom, AtomEnum::WINDOW, 0, 1)?
+            .reply()?
+            .value32()
+            .and_then(|mut value| value.next())
+            .filter(|&window| window != x11rb::NONE))
+    }
+
+    /// Whether the center of a window is on the bar's monitor.
+    fn on_monitor(&self, window: Window) -> Result<bool> {
+        let root = self
+            .conn
+            .setup()
+            .roots
+            .get(self.screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?
+            .root;
+        let geometry = self.conn.get_geometry(window)?.reply()?;
+        let position = self
+            .conn
+            .translate_coordinates(window, root, 0, 0)?
+            .reply()?;
+        let x = i32::from(position.dst_x) + i32::from(geometry.width) / 2;
+        let y = i32::from(position.dst_y) + i32::from(geometry.height) / 2;
+        Ok(x >= i32::from(self.mon.x)
+            && x < i32::from(self.mon.x) + i32::from(self.mon.width)
+            && y >= i32::from(self.mon.y)
+            && y < i32::from(self.mon.y) + i32::from(self.mon.height))
+    }
+
+    /// Whether a window has `_NET_WM_STATE_FULLSCREEN` set.
+    fn is_fullscreen(&self, window: Window) -> Result<bool> {
+        let state_atom =
+            InternedAtoms::get(self.conn.as_ref(), "_NET_WM_STATE")?;
+        let fullscreen_atom =
+            InternedAtoms::get(self.conn.as_ref(), "_NET_WM_STATE_FULLSCREEN")?;
+        Ok(self
+            .conn
+            .get_property(false, window, state_atom, AtomEnum::ATOM, 0, 32)?
+            .reply()?
+            .value32()
+            .is_some_and(|mut atoms| atoms.any(|atom| atom == fullscreen_atom)))
+    }
+
+    /// Draws the focus accent along the bottom of the bar if the bar's
+    /// monitor has focus.
+    fn draw_accent(&self) -> Result<()> {
+        let Some(ref accent) = self.focus_accent else {
+            return Ok(());
+        };
+        if !self.focused {
+            return Ok(());
+        }
+
+        let accent = high_contrast::or(accent, &high_contrast::ACCENT);
+        let thickness = f64::from(self.focus_accent_height.min(self.height));
+        self.cr.save()?;
+        // panels may have left the context translated
+        self.cr.set_matrix(self.base_matrix());
+        self.cr.set_operator(cairo::Operator::Source);
+        self.cr.set_source_rgba(
+            accent.r.into(),
+            accent.g.into(),
+            accent.b.into(),
+            accent.a.into(),
+        );
+        self.cr.rectangle(
+            0.0,
+            f64::from(self.height) - thickness,
+            f64::from(self.width),
+            thickness,
+        );
+        self.cr.fill()?;
+        self.cr.restore()?;
+
+        Ok(())
+    }
+
+    fn handle_ipc_event(&mut self, message: &str) -> Result<bool> {
+        match message {
+            "quit" => Ok(true),
+            // bars that hide themselves are revealed until the pointer has
+            // been away for the usual delay, and hidden until it touches the
+            // edge of the screen
+            "show" if self.autohide.is_some() => {
+                self.reveal()?;
                 Ok(false)
             }
-            "hide" => {
+            "hide" if self.autohide.is_some() => {
+                self.conceal()?;
+                Ok(false)
+            }
+            "show" => {
                 self.mapped = true;
+                // shown again when the monitor is reconnected or the
+                // fullscreen window loses focus
+                if self.shown() {
+                    self.conn.map_window(self.window)?;
+                    self.show_panels();
+                }
+                Ok(false)
+            }
+            "hide" => {
+                self.mapped = false;
                 self.conn.unmap_window(self.window)?;
+                self.popup = None;
+                self.menu = None;
                 self.hide_panels();
                 Ok(false)
             }
             "toggle" => {
-                if self.mapped {
+                let shown = self
+                    .autohide
+                    .as_ref()
+                    .map_or(self.mapped, |autohide| !autohide.hidden);
+                if shown {
                     self.handle_ipc_event("hide")
                 } else {
                     self.handle_ipc_event("show")
                 }
             }
-            _ => Ok(false),
+            "zen" => {
+                self.toggle_zen()?;
+                Ok(false)
+            }
+            "privacy" => {
+                self.privacy_ipc = !self.privacy_ipc;
+                self.update_privacy()?;
+                Ok(false)
+            }
+            "high_contrast" => {
+                let enabled =
+                    !self.info.high_contrast.fetch_xor(true, Ordering::Relaxed);
+                log::info!(
+                    "{} high contrast mode",
+                    if enabled { "Entering" } else { "Leaving" }
+                );
+                self.redraw_bar()?;
+                Ok(false)
+            }
+            "restyle" => {
+                // every bar redraws itself once the styles are reloaded
+                style::reload()?;
+                Ok(false)
+            }
+            _ => {
+                if let Some(layer) =
+                    message.strip_prefix("layer:").and_then(Layer::parse)
+                {
+                    log::info!("Moving to the {layer:?} layer");
+                    self.layer = layer;
+                    set_layer(
+                        self.conn.as_ref(),
+                        self.conn.setup().roots[self.screen].root,
+                        self.window,
+                        layer,
+                    )?;
+                    // hidden bars ignore the request, but pick up the new
+                    // state from their properties when they're mapped
+                    if !self.shown() {
+                        self.configure()?;
+                    }
+                }
+                Ok(false)
+            }
         }
     }
 
-    fn handle_panel_event(&mut self, message: &str) -> Result<bool> {
-        if let Some(caps) = REGEX.captures_iter(message).next() {
-            let region = &caps["region"];
-            let idx = caps["idx"].parse::<usize>()?;
-
-            if let Some(target) = match region {
-                "l" => self.left_panels.get_mut(idx),
-                "c" => self.center_panels.get_mut(idx),
-                "r" => self.right_panels.get_mut(idx),
-                _ => unreachable!(),
-            } {
-                match &caps["message"] {
-                    "show" => {
-                        if let Some(ref draw_info) = target.draw_info {
-                            if let Some(ref f) = draw_info.show_fn {
-                                f()?;
-                            }
-                        }
-                        target.visible = true;
+    fn toggle_zen(&mut self) -> Result<()> {
+        self.zen = !self.zen;
+        log::info!(
+            "{} zen mode",
+            if self.zen { "Entering" } else { "Leaving" }
+        );
+        // zen mode hides panels on top of their own visibility, so panels
+        // shown or hidden in the meantime stay that way when it ends
+        for panel in self
+            .left_panels
+            .iter_mut()
+            .chain(self.center_panels.iter_mut())
+            .chain(self.right_panels.iter_mut())
+        {
+            let hidden = self.zen
+                && !self.zen_panels.iter().any(|name| name == panel.name);
+            panel.set_zen_hidden(hidden)?;
+        }
+        match self.zen_height {
+            Some(height) if self.zen => self.resize(height)?,
+            Some(_) => self.resize(self.panel_height * self.rows as u16)?,
+            None => {}
+        }
+
+        self.redraw_bar()
+    }
+
+    /// Checks whether any window with a class listed in `privacy_classes`
+    /// (e.g. a screen recorder) exists, and enables or disables privacy mode
+    /// accordingly. This runs whenever `_NET_CLIENT_LIST` changes, and only
+    /// the classes of new windows are fetched.
+    pub fn check_privacy(&mut self) -> Result<()> {
+        if self.privacy_classes.is_empty() || self.sensitive.is_empty() {
+            return Ok(());
+        }
+
+        let root = self
+            .conn
+            .setup()
+            .roots
+            .get(self.screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?
+            .root;
+        let client_atom =
+            InternedAtoms::get(self.conn.as_ref(), "_NET_CLIENT_LIST")?;
+        let clients: Vec<Window> = self
+            .conn
+            .get_property(
+                false,
+                root,
+                client_atom,
+                AtomEnum::WINDOW,
+                0,
+                u32::MAX,
+            )?
+            .reply()?
+            .value32()
+            .map(Iterator::collect)
+            .unwrap_or_default();
+
+        // send every request before waiting for any reply so that a burst of
+        // new windows costs one round trip
+        let cookies = clients
+            .iter()
+            .filter(|window| !self.privacy_windows.contains_key(window))
+            .map(|&window| {
+                let cookie = self.conn.get_property(
+                    false,
+                    window,
+                    AtomEnum::WM_CLASS,
+                    AtomEnum::STRING,
+                    0,
+                    64,
+                );
+                (window, cookie)
+            })
+            .collect::<Vec<_>>();
+        for (window, cookie) in cookies {
+            let matches = cookie
+                .ok()
+                .and_then(|cookie| cookie.reply().ok())
+                .is_some_and(|reply| {
+                    reply.value.split(|b| *b == 0).any(|class| {
+                        self.privacy_classes
+                            .iter()
+                            .any(|c| c.as_bytes().eq_ignore_ascii_case(class))
+                    })
+                });
+            self.privacy_windows.insert(window, matches);
+        }
+        self.privacy_windows
+            .retain(|window, _| clients.contains(window));
+        self.privacy_auto = self.privacy_windows.values().any(|&m| m);
+
+        self.update_privacy()
+    }
+
+    /// Enables or disables privacy mode when an xdg-desktop-portal screen cast
+    /// starts or stops.
+    pub fn set_screencast(&mut self, active: bool) -> Result<()> {
+        self.privacy_screencast = active;
+        self.update_privacy()
+    }
+
+    fn update_privacy(&mut self) -> Result<()> {
+        let privacy =
+            self.privacy_ipc || self.privacy_auto || self.privacy_screencast;
+        if privacy != self.privacy {
+            log::info!(
+                "{} privacy mode",
+                if privacy { "Entering" } else { "Leaving" }
+            );
+            self.privacy = privacy;
+            self.redraw_bar()?;
+        }
+        Ok(())
+    }
+
+    fn row_y(&self, row: usize) -> f64 {
+        (row as f64).mul_add(f64::from(self.panel_height), self.y_offset)
+    }
+
+    /// Changes the height of the bar window. Panels are still drawn at their
+    /// original height, centered vertically.
+    fn resize(&mut self, height: u16) -> Result<()> {
+        self.y_offset = (f64::from(height)
+            - f64::from(self.panel_height) * self.rows as f64)
+            / 2.0;
+        self.height = height;
+        self.configure()
+    }
+
+    /// Moves and resizes the bar window to fit its monitor and height.
+    fn configure(&mut self) -> Result<()> {
+        let geometry = bar_geometry(self.position, self.height, &self.mon);
+        let (x, y, width, height) =
+            with_shadow(self.position, geometry, self.shadow_extent());
+        self.conn.configure_window(
+            self.window,
+            &ConfigureWindowAux::new()
+                .x(i32::from(x))
+                .y(i32::from(y))
+                .width(u32::from(width))
+                .height(u32::from(height)),
+        )?;
+        set_wm_properties(
+            self.conn.as_ref(),
+            self.window,
+            self.position,
+            self.width as u32,
+            self.height.into(),
+            self.name.as_str(),
+            &self.mon,
+            self.autohide.is_none() && !self.fullscreen,
+            self.layer,
+        );
+        self.surface.set_size(width.into(), height.into())?;
+        // the input shape is reset below, or the window has just been resized
+        self.input_holes.clear();
+        if self.shadow_extent() > 0 {
+            // let clicks on the shadow through to the windows below
+            shape_input(
+                self.conn.as_ref(),
+                self.window,
+                (geometry.0 - x, geometry.1 - y, geometry.2, geometry.3),
+            )?;
+        }
+        if let Some(ref autohide) = self.autohide {
+            let (x, y, width, height) =
+                bar_geometry(self.position, 1, &self.mon);
+            self.conn.configure_window(
+                autohide.trigger,
+                &ConfigureWindowAux::new()
+                    .x(i32::from(x))
+                    .y(i32::from(y))
+                    .width(u32::from(width))
+                    .height(u32::from(height)),
+            )?;
+        }
+        // the rotation of vertical bars depends on their size
+        self.cr.set_matrix(self.base_matrix());
+        self.round_corners()?;
+        self.conn.flush()?;
+
+        Ok(())
+    }
+
+    /// Shapes the bar window to its rounded corners, unless it's transparent,
+    /// in which case the corners are cleared as it's drawn.
+    fn round_corners(&self) -> Result<()> {
+        if self.transparent || self.corner_radius <= 0.0 {
+            return Ok(());
+        }
+        let (_, _, width, height) =
+            bar_geometry(self.position, self.height, &self.mon);
+        round_corners(
+            self.conn.as_ref(),
+            self.window,
+            (width, height),
+            self.corner_radius,
+        )
+    }
+
+    /// Draws the bar's border, and clears the area outside of its rounded
+    /// corners if it's transparent.
+    fn draw_frame(&self) -> Result<()> {
+        if self.corner_radius <= 0.0 && self.border_width <= 0.0 {
+            return Ok(());
+        }
+        let (width, height) = (f64::from(self.width), f64::from(self.height));
+        self.cr.save()?;
+        // panels may have left the context translated
+        self.cr.set_matrix(self.base_matrix());
+
+        if self.transparent && self.corner_radius > 0.0 {
+            self.cr.set_operator(cairo::Operator::Clear);
+            self.cr.set_fill_rule(cairo::FillRule::EvenOdd);
+            self.cr.rectangle(0.0, 0.0, width, height);
+            rounded_rectangle(&self.cr, 0.0, width, height, self.corner_radius);
+            self.cr.fill()?;
+            self.cr.set_operator(cairo::Operator::Over);
+        }
+
+        if self.border_width > 0.0 {
+            let color =
+                high_contrast::or(&self.border_color, &high_contrast::FG);
+            self.cr.set_source_rgba(
+                color.r.into(),
+                color.g.into(),
+                color.b.into(),
+                color.a.into(),
+            );
+            self.cr.set_line_width(self.border_width);
+            rounded_rectangle(
+                &self.cr,
+                self.border_width / 2.0,
+                width,
+                height,
+                self.corner_radius,
+            );
+            self.cr.stroke()?;
+        }
+
+        self.cr.restore()?;
+        Ok(())
+    }
+
+    /// Hides the lowest-priority panels on a row behind the expander until
+    /// the rest fit, or shows them all if the expander is open. Returns
+    /// whether anything changed, in which case the whole bar needs to be
+    /// redrawn.
+    fn update_overflow(&mut self, row: usize) -> bool {
+        let before = self.overflow_state(row);
+        for panel in self
+            .left_panels
+            .iter_mut()
+            .chain(self.center_panels.iter_mut())
+            .chain(self.right_panels.iter_mut())
+            .filter(|p| p.row == row)
+        {
+            panel.overflowed = false;
+        }
+
+        let mut needed = 0.0;
+        let mut candidates = Vec::new();
+        for (alignment, panels) in [
+            (Alignment::Left, &self.left_panels),
+            (Alignment::Center, &self.center_panels),
+            (Alignment::Right, &self.right_panels),
+        ] {
+            let statuses = Self::apply_dependence(panels, row);
+            for ((idx, panel), status) in panels
+                .iter()
+                .enumerate()
+                .filter(|(_, p)| p.row == row)
+                .zip(statuses)
+            {
+                if status != PanelStatus::Shown {
+                    continue;
+                }
+                let width =
+                    f64::from(panel.draw_info.as_ref().map_or(0, |d| d.width));
+                needed += width;
+                if let Some(priority) = panel.priority {
+                    candidates.push((priority, alignment, idx, width));
+                }
+            }
+        }
+
+        let mut available = 2.0f64.mul_add(
+            -self.margins.internal,
+            f64::from(self.width) - self.margins.left - self.margins.right,
+        );
+        self.overflowing[row] = needed > available && !candidates.is_empty();
+        if self.overflowing[row] && !self.overflow_expanded {
+            available -= self.expander_width();
+            // the lowest priority first, and the rightmost of equals first
+            candidates.reverse();
+            candidates.sort_by_key(|(priority, ..)| *priority);
+            for (_, alignment, idx, width) in candidates {
+                if needed <= available {
+                    break;
+                }
+                let panels = match alignment {
+                    Alignment::Left => &mut self.left_panels,
+                    Alignment::Center => &mut self.center_panels,
+                    Alignment::Right => &mut self.right_panels,
+                };
+                panels[idx].overflowed = true;
+                needed -= width;
+            }
+        }
+
+        before != self.overflow_state(row)
+    }
+
+    /// Whether a row has too many panels, and which of its panels are
+    /// hidden behind the expander.
+    fn overflow_state(&self, row: usize) -> (bool, Vec<bool>) {
+        (
+            self.overflowing[row],
+            self.left_panels
+                .iter()
+                .chain(self.center_panels.iter())
+                .chain(self.right_panels.iter())
+                .filter(|p| p.row == row)
+                .map(|p| p.overflowed)
+                .collect(),
+        )
+    }
+
+    /// Lays out the arrow of the expander, which points toward the hidden
+    /// panels while they're hidden and back while they're shown.
+    fn expander_layout(&self) -> pango::Layout {
+        let layout = create_layout(&self.cr);
+        layout.set_text(if self.overflow_expanded { "«" } else { "»" });
+        self.attrs.apply_font(&layout);
+        layout
+    }
+
+    fn expander_width(&self) -> f64 {
+        2.0f64.mul_add(
+            EXPANDER_PADDING,
+            f64::from(self.expander_layout().pixel_size().0),
+        )
+    }
+
+    /// Draws the expander after the left panels of a row, making room for
+    /// it.
+    fn draw_expander(&mut self, row: usize) -> Result<()> {
+        let layout = self.expander_layout();
+        let x = self.extents[row].left;
+        self.cr.save()?;
+        self.cr.translate(
+            x + EXPANDER_PADDING,
+            self.row_y(row)
+                + f64::from(
+                    (i32::from(self.panel_height) - layout.pixel_size().1) / 2,
+                ),
+        );
+        self.attrs.apply_fg(&self.cr);
+        show_layout(&self.cr, &layout);
+        self.cr.restore()?;
+        self.expanders[row] = Some(x);
+        self.extents[row].left += self.expander_width();
+        Ok(())
+    }
+
+    /// Punches click-through panels (and the gaps between alignments, if
+    /// `click_through_gaps` is set) out of the part of the bar window that
+    /// receives pointer events, so that clicks on them reach the windows
+    /// below. Only talks to the X server when the holes have moved.
+    fn update_input_shape(&mut self) -> Result<()> {
+        if self.click_through.is_empty() && !self.click_through_gaps {
+            return Ok(());
+        }
+        let height = f64::from(self.panel_height);
+        let mut holes = Vec::new();
+        for row in 0..self.rows {
+            let y = self.row_y(row);
+            for panels in
+                [&self.left_panels, &self.center_panels, &self.right_panels]
+            {
+                let statuses = Self::apply_dependence(panels, row);
+                for (panel, status) in
+                    panels.iter().filter(|p| p.row == row).zip(statuses)
+                {
+                    if status != PanelStatus::Shown
+                        || !self.click_through.contains(panel.name)
+                    {
+                        continue;
                     }
-                    "hide" => {
-                        if let Some(ref draw_info) = target.draw_info {
-                            if let Some(ref f) = draw_info.hide_fn {
-                                f()?;
-                            }
-                        }
-                        target.visible = false;
+                    if let Some(draw_info) = &panel.draw_info {
+                        holes.push(self.window_rect(
+                            panel.x,
+                            y,
+                            f64::from(draw_info.width),
+                            height,
+                        ));
                     }
-                    "toggle" => target.visible = !target.visible,
-                    message => {
-                        return Err(anyhow!("Unknown message {message}"))
+                }
+            }
+            if self.click_through_gaps {
+                let extents = &self.extents[row];
+                for (start, end) in [
+                    (extents.left, extents.center.0),
+                    (extents.center.1, extents.right),
+                ] {
+                    if end > start {
+                        holes.push(self.window_rect(
+                            start,
+                            y,
+                            end - start,
+                            height,
+                        ));
                     }
                 }
+            }
+        }
+        holes.retain(|hole| hole.width > 0 && hole.height > 0);
+        if holes == self.input_holes {
+            return Ok(());
+        }
+
+        let bar = self.window_rect(
+            0.0,
+            0.0,
+            f64::from(self.width),
+            f64::from(self.height),
+        );
+        shape_input(
+            self.conn.as_ref(),
+            self.window,
+            (bar.x, bar.y, bar.width, bar.height),
+        )?;
+        if !holes.is_empty() {
+            subtract_input(self.conn.as_ref(), self.window, &holes)?;
+        }
+        self.input_holes = holes;
+        Ok(())
+    }
+
+    /// Converts a rectangle in the bar's own coordinates to the coordinates
+    /// of its window.
+    fn window_rect(
+        &self,
+        x: f64,
+        y: f64,
+        width: f64,
+        height: f64,
+    ) -> Rectangle {
+        let matrix = self.base_matrix();
+        let (x0, y0) = matrix.transform_point(x, y);
+        let (x1, y1) = matrix.transform_point(x + width, y + height);
+        Rectangle {
+            x: x0.min(x1).round() as i16,
+            y: y0.min(y1).round() as i16,
+            width: (x1 - x0).abs().round() as u16,
+            height: (y1 - y0).abs().round() as u16,
+        }
+    }
+
+    /// How many pixels the bar window extends past the bar itself to make
+    /// room for its shadow.
+    fn shadow_extent(&self) -> u16 {
+        if self.transparent {
+            self.shadow_radius.ceil() as u16
+        } else {
+            0
+        }
+    }
 
-                match region {
-                    "l" => self.redraw_left(),
-                    "c" => self.redraw_center_right(true),
-                    "r" => self.redraw_right(true, None),
-                    _ => unreachable!(),
-                }?;
+    /// Draws a soft shadow on the side of the bar facing the rest of the
+    /// monitor, in the space that [`Bar::shadow_extent`] adds to its window.
+    fn draw_shadow(&self) -> Result<()> {
+        let extent = f64::from(self.shadow_extent());
+        if extent <= 0.0 {
+            return Ok(());
+        }
+        let (start, end) = match self.position {
+            Position::Bottom => (0.0, -extent),
+            _ => (f64::from(self.height), f64::from(self.height) + extent),
+        };
+        self.cr.save()?;
+        self.cr.set_matrix(self.base_matrix());
+        self.cr.set_operator(cairo::Operator::Source);
+        let gradient = cairo::LinearGradient::new(0.0, start, 0.0, end);
+        // roughly the falloff of a gaussian blur
+        for (offset, alpha) in [
+            (0.0, 1.0),
+            (0.25, 0.55),
+            (0.5, 0.25),
+            (0.75, 0.07),
+            (1.0, 0.0),
+        ] {
+            gradient.add_color_stop_rgba(
+                offset,
+                0.0,
+                0.0,
+                0.0,
+                alpha * self.shadow_opacity,
+            );
+        }
+        self.cr.set_source(&gradient)?;
+        self.cr
+            .rectangle(0.0, start.min(end), f64::from(self.width), extent);
+        self.cr.fill()?;
+        self.cr.restore()?;
+        Ok(())
+    }
+
+    /// The transformation from the bar's own coordinates, in which it is
+    /// always horizontal, to the coordinates of its window.
+    fn base_matrix(&self) -> cairo::Matrix {
+        let extent = f64::from(self.shadow_extent());
+        match self.position {
+            Position::Top => cairo::Matrix::identity(),
+            Position::Bottom => {
+                cairo::Matrix::new(1.0, 0.0, 0.0, 1.0, 0.0, extent)
             }
+            Position::Left => cairo::Matrix::new(
+                0.0,
+                -1.0,
+                1.0,
+                0.0,
+                0.0,
+                f64::from(self.width),
+            ),
+            Position::Right => cairo::Matrix::new(
+                0.0,
+                1.0,
+                -1.0,
+                0.0,
+                f64::from(self.height) + extent,
+                0.0,
+            ),
+        }
+    }
+
+    /// Converts a point in the bar window to the bar's own coordinates,
+    /// undoing the rotation of vertical bars.
+    fn unrotate(&self, x: i16, y: i16) -> (i16, i16) {
+        let extent = self.shadow_extent() as i16;
+        match self.position {
+            Position::Top => (x, y),
+            Position::Bottom => (x, y - extent),
+            Position::Left => (self.width as i16 - y, x),
+            Position::Right => (y, self.height as i16 + extent - x),
+        }
+    }
+
+    /// Finds the panel drawn at a point, in unrotated bar coordinates.
+    /// Whether a point in the bar is on the expander of its row.
+    fn expander_at(&self, x: i16, y: i16) -> bool {
+        let row = ((f64::from(y) - self.y_offset)
+            / f64::from(self.panel_height))
+        .max(0.0) as usize;
+        let width = self.expander_width();
+        self.expanders
+            .get(row)
+            .copied()
+            .flatten()
+            .is_some_and(|start| {
+                start <= f64::from(x) && f64::from(x) <= start + width
+            })
+    }
+
+    fn panel_at(&self, x: i16, y: i16) -> Option<&Panel> {
+        let row = ((f64::from(y) - self.y_offset)
+            / f64::from(self.panel_height))
+        .max(0.0) as usize;
+
+        self.left_panels
+            .iter()
+            .chain(self.center_panels.iter())
+            .chain(self.right_panels.iter())
+            .filter(|p| p.draw_info.is_some() && p.row == row && !p.overflowed)
+            .find(|p| {
+                p.x <= x as f64
+                    && p.x + p.draw_info.as_ref().unwrap().width as f64
+                        >= x as f64
+            })
+    }
+
+    /// Sends a mouse event to the panel with a given id.
+    fn send_mouse(&self, id: &str, event: MouseEvent) -> Result<()> {
+        if let Some(e) = self.panel_by_id(id).and_then(|p| p.endpoint.as_ref())
+        {
+            e.lock().unwrap().send.send(Event::Mouse(event))?;
+        }
+        Ok(())
+    }
+
+    /// Finds a panel from its id.
+    fn panel_by_id(&self, id: &str) -> Option<&Panel> {
+        self.left_panels
+            .iter()
+            .chain(self.center_panels.iter())
+            .chain(self.right_panels.iter())
+            .find(|p| p.id == id)
+    }
+
+    /// Tells panels when the pointer moves onto or off of them.
+    /// Shows the hand cursor while the pointer is on an interactive panel,
+    /// and the default cursor otherwise.
+    fn set_pointing(&mut self, pointing: bool) -> Result<()> {
+        let Some(hand_cursor) = self.hand_cursor else {
+            return Ok(());
+        };
+        if pointing == self.pointing {
+            return Ok(());
+        }
+        self.pointing = pointing;
+        self.conn.change_window_attributes(
+            self.window,
+            &ChangeWindowAttributesAux::new().cursor(if pointing {
+                hand_cursor
+            } else {
+                x11rb::NONE
+            }),
+        )?;
+        self.conn.flush()?;
+        Ok(())
+    }
+
+    fn set_hovered(&mut self, id: Option<String>) -> Result<()> {
+        if id == self.hovered {
+            return Ok(());
+        }
+        let old = std::mem::replace(&mut self.hovered, id);
+        for (id, event) in [
+            (old.as_deref(), Event::MouseLeave),
+            (self.hovered.as_deref(), Event::MouseEnter),
+        ] {
+            if let Some(e) = id
+                .and_then(|id| self.panel_by_id(id))
+                .and_then(|p| p.endpoint.as_ref())
+            {
+                e.lock().unwrap().send.send(event)?;
+            }
+        }
+
+        for (id, hovered) in
+            [(old.as_deref(), false), (self.hovered.as_deref(), true)]
+        {
+            let Some((alignment, idx)) = id.and_then(|id| self.find_panel(id))
+            else {
+                continue;
+            };
+            let Some(panel) = match alignment {
+                Alignment::Left => &self.left_panels,
+                Alignment::Center => &self.center_panels,
+                Alignment::Right => &self.right_panels,
+            }
+            .get(idx) else {
+                continue;
+            };
+            let Some(flag) =
+                panel.draw_info.as_ref().and_then(|d| d.hovered.as_ref())
+            else {
+                continue;
+            };
+            flag.store(hovered, Ordering::Relaxed);
+            if self.shown() && panel.shown() {
+                self.redraw_one(alignment, idx)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Finds a panel from either its position (e.g. `l0` for the first left
+    /// panel) or its id.
+    fn find_panel(&self, target: &str) -> Option<(Alignment, usize)> {
+        if let Some(caps) = REGEX.captures(target) {
+            let idx = caps["idx"].parse::<usize>().ok()?;
+            let alignment = match &caps["region"] {
+                "l" => Alignment::Left,
+                "c" => Alignment::Center,
+                "r" => Alignment::Right,
+                _ => unreachable!(),
+            };
+            return Some((alignment, idx));
+        }
+
+        [
+            (Alignment::Left, &self.left_panels),
+            (Alignment::Center, &self.center_panels),
+            (Alignment::Right, &self.right_panels),
+        ]
+        .into_iter()
+        .find_map(|(alignment, panels)| {
+            panels
+                .iter()
+                .position(|p| p.id == target)
+                .map(|idx| (alignment, idx))
+        })
+    }
+
+    fn handle_panel_event(&mut self, message: &str) -> Result<bool> {
+        let Some((target, message)) = message.split_once('.') else {
+            return Err(anyhow!("Invalid message {message}"));
+        };
+        if let Some(group) = target.strip_prefix("group:") {
+            return self.handle_group_event(group, message);
+        }
+        let Some((alignment, idx)) = self.find_panel(target) else {
+            return Err(anyhow!("No panel {target} was found"));
+        };
+
+        if let Some(target) = match alignment {
+            Alignment::Left => self.left_panels.get_mut(idx),
+            Alignment::Center => self.center_panels.get_mut(idx),
+            Alignment::Right => self.right_panels.get_mut(idx),
+        } {
+            let row = target.row;
+            match message {
+                "show" => target.set_visible(true)?,
+                "hide" => target.set_visible(false)?,
+                "toggle" => target.set_visible(!target.visible)?,
+                message => return Err(anyhow!("Unknown message {message}")),
+            }
+
+            match alignment {
+                Alignment::Left => self.redraw_left(row),
+                Alignment::Center => self.redraw_center_right(row, true),
+                Alignment::Right => self.redraw_right(row, true, None),
+            }?;
         }
         Ok(false)
     }
 
+    /// Shows, hides, or toggles every panel in a group. Toggling a group
+    /// shows it if all of its panels are hidden, and hides it otherwise.
+    fn handle_group_event(
+        &mut self,
+        group: &str,
+        message: &str,
+    ) -> Result<bool> {
+        let in_group = |p: &&mut Panel| p.group.as_deref() == Some(group);
+        let visible = match message {
+            "show" => true,
+            "hide" => false,
+            "toggle" => !self
+                .left_panels
+                .iter()
+                .chain(self.center_panels.iter())
+                .chain(self.right_panels.iter())
+                .any(|p| p.group.as_deref() == Some(group) && p.visible),
+            message => return Err(anyhow!("Unknown message {message}")),
+        };
+
+        let mut found = false;
+        for panel in self
+            .left_panels
+            .iter_mut()
+            .chain(self.center_panels.iter_mut())
+            .chain(self.right_panels.iter_mut())
+            .filter(in_group)
+        {
+            found = true;
+            panel.set_visible(visible)?;
+        }
+        if !found {
+            return Err(anyhow!("No panels in group {group} were found"));
+        }
+
+        self.redraw_bar()?;
+        Ok(false)
+    }
+
     /// Sends a message to the appropriate panel.
     pub fn send_message(
         &mut self,
@@ -637,12 +3052,23 @@ impl Bar {
         };
 
         if let Some(panel) = dest {
-            let mut panels = self
+            // ids are unique, so try them before falling back to names
+            let by_id = self
                 .left_panels
                 .iter()
                 .chain(self.center_panels.iter())
                 .chain(self.right_panels.iter())
-                .filter(|p| p.name == panel);
+                .find(|p| p.id == panel);
+            let mut panels: Box<dyn Iterator<Item = &Panel>> = match by_id {
+                Some(p) => Box::new(std::iter::once(p)),
+                None => Box::new(
+                    self.left_panels
+                        .iter()
+                        .chain(self.center_panels.iter())
+                        .chain(self.right_panels.iter())
+                        .filter(|p| p.name == panel),
+                ),
+            };
 
             let target = panels.next();
             let (endpoint, message) = match if target.is_none() {
@@ -670,18 +3096,17 @@ impl Bar {
             };
 
             ipc_set.spawn_blocking(move || {
-                let send = endpoint.lock().unwrap().send.clone();
-                let response = if let Err(e) = send.send(Event::Action(message))
+                let mut endpoint = endpoint.lock().unwrap();
+                // discard responses to mouse events, which nothing waits for
+                while endpoint.recv.try_recv().is_ok() {}
+                let response = if let Err(e) =
+                    endpoint.send.send(Event::Action(message))
                 {
                     EventResponse::Err(e.to_string())
                 } else {
-                    endpoint
-                        .lock()
-                        .unwrap()
-                        .recv
-                        .blocking_recv()
-                        .unwrap_or(EventResponse::Ok)
+                    endpoint.recv.blocking_recv().unwrap_or(EventResponse::Ok)
                 };
+                drop(endpoint);
                 log::trace!("response received");
 
                 ipc_send.send(response)?;
@@ -698,35 +3123,31 @@ impl Bar {
         }
     }
 
-    fn redraw_background(&self, scope: &Region) -> Result<()> {
+    fn redraw_background(&self, scope: &Region, row: usize) -> Result<()> {
         self.cr.save()?;
         self.cr.set_operator(cairo::Operator::Source);
-        self.cr.set_source_rgba(
-            self.bg.r.into(),
-            self.bg.g.into(),
-            self.bg.b.into(),
-            self.bg.a.into(),
-        );
+        let y = self.row_y(row);
+        let height = f64::from(self.panel_height);
         match scope {
             Region::Left => self.cr.rectangle(
                 0.0,
-                0.0,
-                self.extents.left + self.margins.internal,
-                f64::from(self.height),
+                y,
+                self.extents[row].left + self.margins.internal,
+                height,
             ),
             Region::CenterRight => self.cr.rectangle(
-                self.extents.center.0 - self.margins.internal,
-                0.0,
+                self.extents[row].center.0 - self.margins.internal,
+                y,
                 f64::from(self.width)
-                    - (self.extents.center.0 - self.margins.internal),
-                f64::from(self.height),
+                    - (self.extents[row].center.0 - self.margins.internal),
+                height,
             ),
             Region::Right => self.cr.rectangle(
-                self.extents.right - self.margins.internal,
-                0.0,
+                self.extents[row].right - self.margins.internal,
+                y,
                 f64::from(self.width)
-                    - (self.extents.right - self.margins.internal),
-                f64::from(self.height),
+                    - (self.extents[row].right - self.margins.internal),
+                height,
             ),
             Region::All => {
                 self.cr.rectangle(
@@ -737,127 +3158,348 @@ impl Bar {
                 );
             }
             Region::Custom { start_x, end_x } => {
-                self.cr.rectangle(
-                    *start_x,
-                    0.0,
-                    end_x - start_x,
-                    f64::from(self.height),
-                );
+                self.cr.rectangle(*start_x, y, end_x - start_x, height);
             }
         }
-        self.cr.fill()?;
+        self.cr.clip();
+        self.background.fill(
+            &self.cr,
+            f64::from(self.width),
+            f64::from(self.height),
+        )?;
         self.cr.restore()?;
 
         Ok(())
     }
 
-    /// Handle a change in the content of a panel.
-    pub fn update_panel(
+    /// Sends the text of a panel to screen readers. Sensitive panels are
+    /// always exported as empty so that their contents can't leak.
+    #[cfg(feature = "a11y")]
+    fn export_text(
+        &self,
+        alignment: Alignment,
+        idx: usize,
+        draw_info: &PanelDrawInfo,
+    ) {
+        let Some(ref a11y) = self.a11y else {
+            return;
+        };
+        let panel = match alignment {
+            Alignment::Left => &self.left_panels,
+            Alignment::Center => &self.center_panels,
+            Alignment::Right => &self.right_panels,
+        }
+        .get(idx)
+        .expect("one or more panels have vanished");
+
+        let text = if self.sensitive.contains(panel.name) {
+            String::new()
+        } else {
+            draw_info.text.clone().unwrap_or_default()
+        };
+        a11y.update(crate::a11y::Update {
+            id: panel.id.clone(),
+            role: if panel.endpoint.is_some() {
+                "push button"
+            } else {
+                "label"
+            },
+            text,
+            position: (panel.row, alignment, idx),
+        });
+    }
+
+    /// Queues a change in the content of a panel, to be drawn by
+    /// [`Bar::flush_updates`]. If the panel already has an update waiting,
+    /// that update is dropped, since it would never be visible, but a menu
+    /// that it asked to open is still opened.
+    pub fn queue_update(
         &mut self,
         alignment: Alignment,
         idx: usize,
         draw_info: PanelDrawInfo,
-    ) -> Result<()> {
-        let new_width = f64::from(draw_info.width);
-        match alignment {
-            Alignment::Left => {
-                let cur_width = f64::from(
-                    self.left_panels
-                        .get(idx)
-                        .expect("one or more panels have vanished")
-                        .draw_info
-                        .as_ref()
-                        .map_or(0, |i| i.width),
+    ) {
+        if draw_info.show_menu && !draw_info.menu.is_empty() {
+            self.pending_menu = Some((alignment, idx, draw_info.menu.clone()));
+        }
+        if let Some(pending) = self
+            .pending
+            .iter_mut()
+            .find(|(a, i, _)| *a == alignment && *i == idx)
+        {
+            log::trace!(
+                "Dropping stale update from {alignment} panel at index {idx}"
+            );
+            pending.2 = draw_info;
+        } else {
+            self.pending.push((alignment, idx, draw_info));
+        }
+    }
+
+    /// Draws the updates queued by [`Bar::queue_update`].
+    ///
+    /// Panels whose width didn't change are redrawn on their own, unless
+    /// they're covered by a larger redraw. If exactly one panel changed width,
+    /// as little of the bar as possible is redrawn to make room for it, and if
+    /// more than one did, the entire bar is redrawn.
+    pub fn flush_updates(&mut self) -> Result<()> {
+        let mut unchanged = Vec::new();
+        let mut resized = Vec::new();
+        for (alignment, idx, draw_info) in std::mem::take(&mut self.pending) {
+            #[cfg(feature = "a11y")]
+            self.export_text(alignment, idx, &draw_info);
+
+            let panel = match alignment {
+                Alignment::Left => &mut self.left_panels,
+                Alignment::Center => &mut self.center_panels,
+                Alignment::Right => &mut self.right_panels,
+            }
+            .get_mut(idx)
+            .expect("one or more panels have vanished");
+            let cur_width = panel.draw_info.as_ref().map_or(0, |i| i.width);
+            if let Some(hovered) = &draw_info.hovered {
+                hovered.store(
+                    self.hovered.as_ref() == Some(&panel.id),
+                    Ordering::Relaxed,
                 );
+            }
+            panel.set_draw_info(
+                draw_info,
+                (self.transition, self.transition_duration),
+                self.animation_duration,
+            );
+            let new_width = panel.draw_info.as_ref().map_or(0, |i| i.width);
+
+            if new_width != cur_width {
+                resized.push((alignment, idx, f64::from(cur_width)));
+            } else if !panel.overflowed {
+                unchanged.push((alignment, idx, panel.row));
+            }
+        }
 
-                self.left_panels
-                    .get_mut(idx)
-                    .expect("one or more panels have vanished")
-                    .draw_info = Some(draw_info);
+        let redrawn = match resized.as_slice() {
+            [] => None,
+            &[(alignment, idx, cur_width)] => {
+                Some(self.redraw_resized(alignment, idx, cur_width)?)
+            }
+            _ => {
+                self.redraw_bar()?;
+                Some(Redrawn::Bar)
+            }
+        };
 
-                if (new_width - cur_width).abs() < f64::EPSILON {
-                    self.redraw_one(alignment, idx)?;
-                } else if new_width - cur_width
-                    + self.extents.left
-                    + self.margins.internal
-                    < self.extents.center.0
-                    && (self.center_state == CenterState::Center
-                        || self.center_state == CenterState::Left)
-                {
-                    self.redraw_left()?;
-                } else {
-                    self.redraw_bar()?;
-                }
+        for (alignment, idx, row) in unchanged {
+            if !redrawn.is_some_and(|r| r.covers(alignment, row)) {
+                self.redraw_one(alignment, idx)?;
+            }
+        }
 
+        self.update_popup()?;
+        match self.pending_menu.take() {
+            Some((alignment, idx, entries)) => {
+                let panels = match alignment {
+                    Alignment::Left => &self.left_panels,
+                    Alignment::Center => &self.center_panels,
+                    Alignment::Right => &self.right_panels,
+                };
+                let id = panels[idx].id.clone();
+                self.show_menu(id.as_str(), &entries)
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the geometry `(x, y, width, height)` of a window of a given
+    /// size placed next to a panel, starting at the panel's leading edge.
+    fn beside_panel(
+        &self,
+        panel: &Panel,
+        (width, height): (i32, i32),
+    ) -> (i32, i32, i32, i32) {
+        let bar = bar_geometry(self.position, self.height, &self.mon);
+        let start = match self.position {
+            Position::Top | Position::Bottom => {
+                i32::from(bar.0) + panel.x as i32
+            }
+            Position::Left => {
+                i32::from(bar.1) + self.width
+                    - panel.x as i32
+                    - panel.draw_info.as_ref().map_or(0, |d| d.width)
+            }
+            Position::Right => i32::from(bar.1) + panel.x as i32,
+        };
+        let (x, y) =
+            beside_bar(self.position, bar, &self.mon, start, (width, height));
+        (x, y, width, height)
+    }
+
+    /// Opens the menu of the panel with a given id, closing any open popup.
+    fn open_menu(&mut self, id: &str) -> Result<()> {
+        let entries = self
+            .panel_by_id(id)
+            .and_then(|p| p.draw_info.as_ref())
+            .map(|d| d.menu.clone())
+            .unwrap_or_default();
+        self.show_menu(id, &entries)
+    }
+
+    /// Opens a menu with the given entries next to the panel with a given
+    /// id, closing any open popup.
+    fn show_menu(&mut self, id: &str, entries: &[MenuEntry]) -> Result<()> {
+        self.popup = None;
+        self.menu = None;
+        if entries.is_empty() {
+            return Ok(());
+        }
+        let Some(panel) = self.panel_by_id(id) else {
+            return Ok(());
+        };
+        let (content, row_height) =
+            menu::content(&self.cr, entries, &self.attrs);
+        let geometry =
+            self.beside_panel(panel, (content.width, content.height));
+        let popup = Popup::show(
+            self.conn.clone(),
+            self.screen,
+            panel.id.clone(),
+            &content,
+            geometry,
+            &self.bg,
+        )?;
+        self.menu = Some(Menu {
+            popup,
+            content,
+            actions: entries.iter().map(|entry| entry.action.clone()).collect(),
+            row_height,
+        });
+        Ok(())
+    }
+
+    /// Opens, moves, redraws, or closes the popup to match the panels that
+    /// want one open. When more than one does, the newest request wins.
+    fn update_popup(&mut self) -> Result<()> {
+        let requests = self
+            .left_panels
+            .iter()
+            .chain(self.center_panels.iter())
+            .chain(self.right_panels.iter())
+            .filter_map(|p| {
+                let popup = p.draw_info.as_ref()?.popup.as_ref()?;
+                (popup.is_open() && p.shown()).then_some((p, popup))
+            })
+            .collect::<Vec<_>>();
+        let owner = self.popup.as_ref().map(|popup| popup.owner.as_str());
+        let request = requests
+            .iter()
+            .find(|(p, _)| Some(p.id.as_str()) != owner)
+            .or_else(|| requests.first())
+            .filter(|_| self.shown());
+        let Some(&(panel, content)) = request else {
+            self.popup = None;
+            return Ok(());
+        };
+
+        let geometry =
+            self.beside_panel(panel, (content.width, content.height));
+
+        match self.popup {
+            Some(ref mut popup) if popup.owner == panel.id => {
+                popup.update(content, geometry)
+            }
+            _ => {
+                // close the old popup first so that it releases the pointer
+                self.popup = None;
+                self.menu = None;
+                self.popup = Some(Popup::show(
+                    self.conn.clone(),
+                    self.screen,
+                    panel.id.clone(),
+                    content,
+                    geometry,
+                    &self.bg,
+                )?);
                 Ok(())
             }
-            Alignment::Center => {
-                let cur_width = f64::from(
-                    self.center_panels
-                        .get(idx)
-                        .expect("one or more panels have vanished")
-                        .draw_info
-                        .as_ref()
-                        .map_or(0, |i| i.width),
-                );
+        }
+    }
 
-                self.center_panels
-                    .get_mut(idx)
-                    .expect("one or more panels have vanished")
-                    .draw_info = Some(draw_info);
+    /// Redraws the part of the bar affected by a panel changing width from
+    /// `cur_width` to the width in its current [`PanelDrawInfo`].
+    fn redraw_resized(
+        &mut self,
+        alignment: Alignment,
+        idx: usize,
+        cur_width: f64,
+    ) -> Result<Redrawn> {
+        let panel = match alignment {
+            Alignment::Left => &self.left_panels,
+            Alignment::Center => &self.center_panels,
+            Alignment::Right => &self.right_panels,
+        }
+        .get(idx)
+        .expect("one or more panels have vanished");
+        let row = panel.row;
+        let new_width =
+            f64::from(panel.draw_info.as_ref().map_or(0, |i| i.width));
+
+        if self.update_overflow(row) {
+            self.redraw_bar()?;
+            return Ok(Redrawn::Bar);
+        }
 
-                if (new_width - cur_width).abs() < f64::EPSILON {
-                    self.redraw_one(alignment, idx)?;
+        let redrawn = match alignment {
+            Alignment::Left => {
+                if new_width - cur_width
+                    + self.extents[row].left
+                    + self.margins.internal
+                    < self.extents[row].center.0
+                    && (self.center_state[row] == CenterState::Center
+                        || self.center_state[row] == CenterState::Left)
+                {
+                    self.redraw_left(row)?;
+                    Redrawn::Left(row)
                 } else {
                     self.redraw_bar()?;
+                    Redrawn::Bar
                 }
-
-                Ok(())
+            }
+            Alignment::Center => {
+                self.redraw_bar()?;
+                Redrawn::Bar
             }
             Alignment::Right => {
-                let cur_width = f64::from(
-                    self.right_panels
-                        .get(idx)
-                        .expect("one or more panels have vanished")
-                        .draw_info
-                        .as_ref()
-                        .map_or(0, |i| i.width),
-                );
-
-                self.right_panels
-                    .get_mut(idx)
-                    .expect("one or more panels have vanished")
-                    .draw_info = Some(draw_info);
-
-                if (new_width - cur_width).abs() < f64::EPSILON {
-                    self.redraw_one(alignment, idx)?;
-                } else if self.extents.right
+                let redrawn = if self.extents[row].right
                     - new_width
                     - cur_width
                     - self.margins.internal
-                    > self.extents.center.1
+                    > self.extents[row].center.1
                 {
-                    self.redraw_right(true, None)?;
-                } else if (self.extents.right
-                    - self.extents.center.1
+                    self.redraw_right(row, true, None)?;
+                    Redrawn::Right(row)
+                } else if (self.extents[row].right
+                    - self.extents[row].center.1
                     - self.margins.internal)
-                    + (self.extents.center.0
-                        - self.extents.left
+                    + (self.extents[row].center.0
+                        - self.extents[row].left
                         - self.margins.internal)
                     > new_width - cur_width
                 {
-                    self.extents.right += new_width - cur_width;
-                    self.redraw_center_right(true)?;
+                    self.extents[row].right += new_width - cur_width;
+                    self.redraw_center_right(row, true)?;
+                    Redrawn::CenterRight(row)
                 } else {
                     self.redraw_bar()?;
-                }
+                    Redrawn::Bar
+                };
 
                 self.surface.flush();
                 self.conn.flush()?;
 
-                Ok(())
+                redrawn
             }
-        }
+        };
+
+        Ok(redrawn)
     }
 
     fn redraw_one(&self, alignment: Alignment, idx: usize) -> Result<()> {
@@ -870,14 +3512,22 @@ impl Bar {
                     .get(idx)
                     .expect("one or more panels have vanished");
                 if let Some(draw_info) = &panel.draw_info {
-                    self.redraw_background(&Region::Custom {
-                        start_x: panel.x,
-                        end_x: panel.x + f64::from(draw_info.width),
-                    })?;
-                    self.cr.translate(panel.x, 0.0);
-                    (draw_info.draw_fn)(&self.cr, panel.x)?;
+                    self.redraw_background(
+                        &Region::Custom {
+                            start_x: panel.x,
+                            end_x: panel.x + f64::from(draw_info.width),
+                        },
+                        panel.row,
+                    )?;
+                    self.cr.translate(panel.x, self.row_y(panel.row));
+                    if !(self.privacy && self.sensitive.contains(panel.name)) {
+                        diagnostics::redraw();
+                        (draw_info.draw_fn)(&self.cr, panel.x)?;
+                    }
                 }
 
+                self.draw_accent()?;
+                self.draw_frame()?;
                 self.surface.flush();
                 self.conn.flush()?;
                 self.cr.restore()?;
@@ -897,14 +3547,22 @@ impl Bar {
                     .expect("one or more panels have vanished")
                     .draw_info
                 {
-                    self.redraw_background(&Region::Custom {
-                        start_x: panel.x,
-                        end_x: panel.x + f64::from(draw_info.width),
-                    })?;
-                    self.cr.translate(panel.x, 0.0);
-                    (draw_info.draw_fn)(&self.cr, panel.x)?;
+                    self.redraw_background(
+                        &Region::Custom {
+                            start_x: panel.x,
+                            end_x: panel.x + f64::from(draw_info.width),
+                        },
+                        panel.row,
+                    )?;
+                    self.cr.translate(panel.x, self.row_y(panel.row));
+                    if !(self.privacy && self.sensitive.contains(panel.name)) {
+                        diagnostics::redraw();
+                        (draw_info.draw_fn)(&self.cr, panel.x)?;
+                    }
                 }
 
+                self.draw_accent()?;
+                self.draw_frame()?;
                 self.surface.flush();
                 self.conn.flush()?;
                 self.cr.restore()?;
@@ -924,14 +3582,22 @@ impl Bar {
                     .expect("one or more panels have vanished")
                     .draw_info
                 {
-                    self.redraw_background(&Region::Custom {
-                        start_x: panel.x,
-                        end_x: panel.x + f64::from(draw_info.width),
-                    })?;
-                    self.cr.translate(panel.x, 0.0);
-                    (draw_info.draw_fn)(&self.cr, panel.x)?;
+                    self.redraw_background(
+                        &Region::Custom {
+                            start_x: panel.x,
+                            end_x: panel.x + f64::from(draw_info.width),
+                        },
+                        panel.row,
+                    )?;
+                    self.cr.translate(panel.x, self.row_y(panel.row));
+                    if !(self.privacy && self.sensitive.contains(panel.name)) {
+                        diagnostics::redraw();
+                        (draw_info.draw_fn)(&self.cr, panel.x)?;
+                    }
                 }
 
+                self.draw_accent()?;
+                self.draw_frame()?;
                 self.surface.flush();
                 self.conn.flush()?;
                 self.cr.restore()?;
@@ -947,30 +3613,38 @@ impl Bar {
     /// Note: this function is not called for every panel update. If the width
     /// doesn't change, only one panel is redrawn, and there are a number of
     /// other cases in which we can redraw only the left or right side. See
-    /// [`Bar::update_panel`] for specifics.
+    /// [`Bar::flush_updates`] for specifics.
     pub fn redraw_bar(&mut self) -> Result<()> {
         log::info!("Redrawing entire bar");
 
-        self.redraw_background(&Region::All)?;
+        for row in 0..self.rows {
+            self.update_overflow(row);
+        }
+        self.redraw_background(&Region::All, 0)?;
+        self.draw_shadow()?;
 
-        self.redraw_left()?;
-        self.redraw_center_right(false)?;
+        for row in 0..self.rows {
+            self.redraw_left(row)?;
+            self.redraw_center_right(row, false)?;
+        }
 
         Ok(())
     }
 
-    fn redraw_left(&mut self) -> Result<()> {
+    fn redraw_left(&mut self, row: usize) -> Result<()> {
         log::info!("Redrawing left");
 
-        self.redraw_background(&Region::Left)?;
+        self.redraw_background(&Region::Left, row)?;
 
-        self.extents.left = self.margins.left;
+        self.extents[row].left = self.margins.left;
 
-        let statuses = Self::apply_dependence(self.left_panels.as_slice());
+        let statuses = Self::apply_dependence(self.left_panels.as_slice(), row);
+        let y = self.row_y(row);
 
         for panel in self
             .left_panels
             .iter_mut()
+            .filter(|p| p.row == row)
             .enumerate()
             .filter(|(idx, _)| {
                 statuses.get(*idx).unwrap() == &PanelStatus::Shown
@@ -979,33 +3653,50 @@ impl Bar {
         {
             if let Some(draw_info) = &panel.draw_info {
                 self.cr.save()?;
-                let x = self.extents.left;
+                let x = self.extents[row].left;
                 panel.x = x;
-                self.cr.translate(x, 0.0);
-                (draw_info.draw_fn)(&self.cr, x)?;
-                self.extents.left += f64::from(draw_info.width);
+                self.cr.translate(x, y);
+                if !(self.privacy && self.sensitive.contains(panel.name)) {
+                    diagnostics::redraw();
+                    (draw_info.draw_fn)(&self.cr, x)?;
+                }
+                self.extents[row].left += f64::from(draw_info.width);
                 self.cr.restore()?;
             }
         }
 
+        self.expanders[row] = None;
+        if self.overflowing[row] {
+            self.draw_expander(row)?;
+        }
+
+        self.draw_accent()?;
+        self.draw_frame()?;
+        self.update_input_shape()?;
         self.surface.flush();
         self.conn.flush()?;
 
         Ok(())
     }
 
-    fn redraw_center_right(&mut self, standalone: bool) -> Result<()> {
+    fn redraw_center_right(
+        &mut self,
+        row: usize,
+        standalone: bool,
+    ) -> Result<()> {
         log::info!("Redrawing center panels");
+        let y = self.row_y(row);
         if standalone {
-            self.redraw_background(&Region::CenterRight)?;
+            self.redraw_background(&Region::CenterRight, row)?;
         }
 
         let center_statuses =
-            Self::apply_dependence(self.center_panels.as_slice());
+            Self::apply_dependence(self.center_panels.as_slice(), row);
 
         let center_panels = self
             .center_panels
             .iter_mut()
+            .filter(|p| p.row == row)
             .enumerate()
             .filter(|(idx, _)| {
                 center_statuses.get(*idx).unwrap() == &PanelStatus::Shown
@@ -1014,11 +3705,12 @@ impl Bar {
             .collect::<Vec<_>>();
 
         let right_statuses =
-            Self::apply_dependence(self.right_panels.as_slice());
+            Self::apply_dependence(self.right_panels.as_slice(), row);
 
         let right_panels = self
             .right_panels
             .iter()
+            .filter(|p| p.row == row)
             .enumerate()
             .filter(|(idx, _)| {
                 right_statuses.get(*idx).unwrap() == &PanelStatus::Shown
@@ -1032,7 +3724,7 @@ impl Bar {
                 .sum::<i32>(),
         );
 
-        self.extents.right = f64::from(
+        self.extents[row].right = f64::from(
             self.width
                 - right_panels
                     .filter_map(|p| p.draw_info.as_ref().map(|i| i.width))
@@ -1042,52 +3734,62 @@ impl Bar {
         if center_width
             > 2.0f64.mul_add(
                 -self.margins.internal,
-                self.extents.right - self.extents.left,
+                self.extents[row].right - self.extents[row].left,
             )
         {
-            self.extents.center.0 = self.margins.internal + self.extents.left;
-            self.extents.center.1 = self.margins.internal + self.extents.left;
-            self.center_state = CenterState::Unknown;
+            self.extents[row].center.0 =
+                self.margins.internal + self.extents[row].left;
+            self.extents[row].center.1 =
+                self.margins.internal + self.extents[row].left;
+            self.center_state[row] = CenterState::Unknown;
         } else if center_width / 2.0
-            > self.extents.right
+            > self.extents[row].right
                 - f64::from(self.width / 2)
                 - self.margins.internal
         {
-            self.extents.center.0 =
-                self.extents.right - center_width - self.margins.internal;
-            self.extents.center.1 =
-                self.extents.right - center_width - self.margins.internal;
-            self.center_state = CenterState::Left;
+            self.extents[row].center.0 =
+                self.extents[row].right - center_width - self.margins.internal;
+            self.extents[row].center.1 =
+                self.extents[row].right - center_width - self.margins.internal;
+            self.center_state[row] = CenterState::Left;
         } else if center_width / 2.0
             > f64::from(self.width / 2)
-                - self.extents.left
+                - self.extents[row].left
                 - self.margins.internal
         {
-            self.extents.center.0 = self.extents.left + self.margins.internal;
-            self.extents.center.1 = self.extents.left + self.margins.internal;
-            self.center_state = CenterState::Right;
+            self.extents[row].center.0 =
+                self.extents[row].left + self.margins.internal;
+            self.extents[row].center.1 =
+                self.extents[row].left + self.margins.internal;
+            self.center_state[row] = CenterState::Right;
         } else {
-            self.extents.center.0 =
+            self.extents[row].center.0 =
                 f64::from(self.width / 2) - center_width / 2.0;
-            self.extents.center.1 =
+            self.extents[row].center.1 =
                 f64::from(self.width / 2) - center_width / 2.0;
-            self.center_state = CenterState::Center;
+            self.center_state[row] = CenterState::Center;
         }
 
         for panel in center_panels {
             if let Some(draw_info) = &panel.draw_info {
                 self.cr.save()?;
-                let x = self.extents.center.1;
+                let x = self.extents[row].center.1;
                 panel.x = x;
-                self.cr.translate(x, 0.0);
-                (draw_info.draw_fn)(&self.cr, x)?;
-                self.extents.center.1 += f64::from(draw_info.width);
+                self.cr.translate(x, y);
+                if !(self.privacy && self.sensitive.contains(panel.name)) {
+                    diagnostics::redraw();
+                    (draw_info.draw_fn)(&self.cr, x)?;
+                }
+                self.extents[row].center.1 += f64::from(draw_info.width);
                 self.cr.restore()?;
             }
         }
 
-        self.redraw_right(standalone, Some(right_statuses))?;
+        self.redraw_right(row, standalone, Some(right_statuses))?;
 
+        self.draw_accent()?;
+        self.draw_frame()?;
+        self.update_input_shape()?;
         self.surface.flush();
         self.conn.flush()?;
 
@@ -1096,22 +3798,24 @@ impl Bar {
 
     fn redraw_right(
         &mut self,
+        row: usize,
         standalone: bool,
         statuses: Option<Vec<PanelStatus>>,
     ) -> Result<()> {
         log::info!("Redrawing right panels");
 
         if standalone {
-            self.redraw_background(&Region::Right)?;
+            self.redraw_background(&Region::Right, row)?;
         }
 
         let statuses = statuses.unwrap_or_else(|| {
-            Self::apply_dependence(self.right_panels.as_slice())
+            Self::apply_dependence(self.right_panels.as_slice(), row)
         });
 
         let total_width = f64::from(
             self.right_panels
                 .iter()
+                .filter(|p| p.row == row)
                 .enumerate()
                 .filter(|(idx, _)| {
                     statuses.get(*idx).unwrap() == &PanelStatus::Shown
@@ -1121,17 +3825,20 @@ impl Bar {
                 .sum::<i32>(),
         ) + self.margins.right;
 
-        if total_width > f64::from(self.width) - self.extents.center.1 {
-            self.extents.right = self.extents.center.1 + self.margins.internal;
+        if total_width > f64::from(self.width) - self.extents[row].center.1 {
+            self.extents[row].right =
+                self.extents[row].center.1 + self.margins.internal;
         } else {
-            self.extents.right = f64::from(self.width) - total_width;
+            self.extents[row].right = f64::from(self.width) - total_width;
         }
 
-        let mut temp = self.extents.right;
+        let mut temp = self.extents[row].right;
+        let y = self.row_y(row);
 
         for panel in self
             .right_panels
             .iter_mut()
+            .filter(|p| p.row == row)
             .enumerate()
             .filter(|(idx, _)| {
                 statuses.get(*idx).unwrap() == &PanelStatus::Shown
@@ -1142,13 +3849,19 @@ impl Bar {
                 self.cr.save()?;
                 let x = temp;
                 panel.x = x;
-                self.cr.translate(x, 0.0);
-                (draw_info.draw_fn)(&self.cr, x)?;
+                self.cr.translate(x, y);
+                if !(self.privacy && self.sensitive.contains(panel.name)) {
+                    diagnostics::redraw();
+                    (draw_info.draw_fn)(&self.cr, x)?;
+                }
                 temp += f64::from(draw_info.width);
                 self.cr.restore()?;
             }
         }
 
+        self.draw_accent()?;
+        self.draw_frame()?;
+        self.update_input_shape()?;
         self.surface.flush();
         self.conn.flush()?;
 
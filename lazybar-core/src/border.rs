@@ -0,0 +1,109 @@
+use std::{collections::HashMap, f64::consts::PI};
+
+use anyhow::Result;
+use config::Value;
+use csscolorparser::Color;
+
+use crate::{
+    high_contrast, remove_color_from_config, remove_float_from_config,
+};
+
+/// A border drawn around a panel's background, inside of its edges.
+#[derive(Debug, Clone, Default, PartialEq, PartialOrd)]
+pub struct Border {
+    /// The width in pixels of the top side
+    pub top: f64,
+    /// The width in pixels of the right side
+    pub right: f64,
+    /// The width in pixels of the bottom side
+    pub bottom: f64,
+    /// The width in pixels of the left side
+    pub left: f64,
+    /// How rounded the corners are. Only used when all of the sides are
+    /// equally wide.
+    pub radius: f64,
+    /// The color of the border
+    pub color: Color,
+}
+
+impl Border {
+    /// Removes the border options from an attrs table and returns an attempt
+    /// at parsing them into a [`Border`]. See
+    /// [`Attrs::parse`][crate::Attrs::parse] for the options.
+    pub fn parse(table: &mut HashMap<String, Value>) -> Option<Self> {
+        let width = remove_float_from_config("border_width", table);
+        let mut side = |name| {
+            remove_float_from_config(format!("border_{name}").as_str(), table)
+                .or(width)
+        };
+        let (top, right, bottom, left) =
+            (side("top"), side("right"), side("bottom"), side("left"));
+        let radius = remove_float_from_config("border_radius", table);
+        let color = remove_color_from_config("border_color", table);
+
+        if [top, right, bottom, left].iter().all(Option::is_none) {
+            if radius.is_some() || color.is_some() {
+                log::warn!("Ignoring a border without a width");
+            }
+            return None;
+        }
+
+        Some(Self {
+            top: top.unwrap_or_default().max(0.0),
+            right: right.unwrap_or_default().max(0.0),
+            bottom: bottom.unwrap_or_default().max(0.0),
+            left: left.unwrap_or_default().max(0.0),
+            radius: radius.unwrap_or_default().max(0.0),
+            color: color.unwrap_or_default(),
+        })
+    }
+
+    /// Draws the border around a rectangle starting at (0, 0).
+    pub(crate) fn draw(
+        &self,
+        cr: &cairo::Context,
+        width: f64,
+        height: f64,
+    ) -> Result<()> {
+        if width <= 0.0 || height <= 0.0 {
+            return Ok(());
+        }
+        cr.save()?;
+        let color = high_contrast::or(&self.color, &high_contrast::FG);
+        cr.set_source_rgba(
+            color.r.into(),
+            color.g.into(),
+            color.b.into(),
+            color.a.into(),
+        );
+
+        if self.top == self.right
+            && self.top == self.bottom
+            && self.top == self.left
+        {
+            // stroke along the middle of the border so that it stays inside
+            let inset = self.top / 2.0;
+            let (width, height) = (width - self.top, height - self.top);
+            let radius = self.radius.min(width / 2.0).min(height / 2.0);
+
+            cr.translate(inset, inset);
+            cr.new_sub_path();
+            cr.arc(width - radius, radius, radius, -PI / 2.0, 0.0);
+            cr.arc(width - radius, height - radius, radius, 0.0, PI / 2.0);
+            cr.arc(radius, height - radius, radius, PI / 2.0, PI);
+            cr.arc(radius, radius, radius, PI, -PI / 2.0);
+            cr.close_path();
+            cr.set_line_width(self.top);
+            cr.stroke()?;
+        } else {
+            cr.rectangle(0.0, 0.0, width, self.top);
+            cr.rectangle(width - self.right, 0.0, self.right, height);
+            cr.rectangle(0.0, height - self.bottom, width, self.bottom);
+            cr.rectangle(0.0, 0.0, self.left, height);
+            cr.fill()?;
+        }
+
+        cr.restore()?;
+        Ok(())
+    }
+}
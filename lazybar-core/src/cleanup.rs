@@ -1,17 +1,290 @@
 use std::{
-    fs::{read_dir, remove_file},
-    os::unix::fs::FileTypeExt,
-    sync::OnceLock,
+    cell::Cell,
+    collections::BTreeMap,
+    fs::{self, read_dir, remove_file},
+    os::unix::{fs::FileTypeExt, process::CommandExt},
+    pin::Pin,
+    process::{Child, Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    task::{Context, Poll},
     time::Duration,
 };
 
 use anyhow::{anyhow, Result};
-use tokio::{io::AsyncWriteExt, net::UnixStream, time};
+use nix::{
+    errno::Errno,
+    sys::signal::{killpg, Signal},
+    unistd::Pid,
+};
+use tokio::{
+    io::AsyncWriteExt,
+    net::UnixStream,
+    task::{self, JoinHandle},
+    time,
+};
+use tokio_stream::Stream;
+
+use crate::{
+    ipc::{self, ChannelEndpoint},
+    PanelDrawInfo, PanelStream,
+};
+
+/// One endpoint for each running bar, used to shut them down.
+pub(crate) static ENDPOINTS: Mutex<Vec<ChannelEndpoint<(), ()>>> =
+    Mutex::new(Vec::new());
+
+/// The process groups that may still have processes in them, by process
+/// group id.
+static GROUPS: Mutex<BTreeMap<i32, Group>> = Mutex::new(BTreeMap::new());
+/// Children started with [`spawn_command`] that haven't been reaped yet.
+static DETACHED: Mutex<Vec<Child>> = Mutex::new(Vec::new());
+/// The id of the next panel stream passed to [`owned`].
+static NEXT_OWNER: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+    /// The panel whose stream is being polled on this thread, which owns any
+    /// children started in the meantime.
+    static OWNER: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// A process group started by lazybar
+struct Group {
+    /// The panel that started the group, if any
+    owner: Option<u64>,
+    /// The start time of the group's leader, used to tell the group apart
+    /// from a later one that reuses its id
+    started: Option<u64>,
+}
+
+/// Starts tracking a process group, owned by the panel whose stream is being
+/// polled, if any. The group's leader must not have been reaped yet.
+fn track(pgid: i32) {
+    let group = Group {
+        owner: OWNER.get(),
+        started: start_time(pgid),
+    };
+    GROUPS.lock().unwrap().insert(pgid, group);
+}
+
+/// Returns the start time of a process in clock ticks since boot, or `None`
+/// if there is no such process.
+fn start_time(pid: i32) -> Option<u64> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // the command name can contain spaces and parentheses, so the fields are
+    // counted from the last parenthesis. the start time is field 22.
+    stat.rsplit_once(')')?
+        .1
+        .split_whitespace()
+        .nth(19)?
+        .parse()
+        .ok()
+}
+
+/// Returns whether any process is left in a process group. A group outlives
+/// its leader as long as anything the leader started is still running.
+fn group_alive(pgid: i32) -> bool {
+    killpg(Pid::from_raw(pgid), None::<Signal>) != Err(Errno::ESRCH)
+}
+
+impl Group {
+    /// Returns whether the group's id now belongs to a different group.
+    ///
+    /// An id can only be reused once every process in the group has exited,
+    /// and a new process with that id becomes the leader of the new group.
+    /// So if a process with the id exists but started at a different time
+    /// than the original leader, the group is gone. If there is no such
+    /// process, the original leader has exited, and whatever is left of the
+    /// group is still ours.
+    fn reused(&self, pgid: i32) -> bool {
+        start_time(pgid).is_some_and(|t| Some(t) != self.started)
+    }
+}
+
+/// Sends a signal to a tracked process group, unless its id has been reused.
+fn signal_group(pgid: i32, group: &Group, signal: Signal) {
+    if !group.reused(pgid) {
+        let _ = killpg(Pid::from_raw(pgid), signal);
+    }
+}
+
+/// Removes a process group from [`GROUPS`] when dropped, killing it first if
+/// the child wasn't waited on successfully.
+struct GroupGuard {
+    pgid: i32,
+    done: bool,
+}
+
+impl GroupGuard {
+    fn new(child: &Child) -> Self {
+        let pgid = child.id() as i32;
+        track(pgid);
+        Self { pgid, done: false }
+    }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = killpg(Pid::from_raw(self.pgid), Signal::SIGKILL);
+        }
+        // anything the child left running keeps the group tracked
+        if !group_alive(self.pgid) {
+            GROUPS.lock().unwrap().remove(&self.pgid);
+        }
+    }
+}
+
+/// Runs a command in a new process group and waits for its output.
+///
+/// The process group is tracked until nothing is left in it, so that
+/// [`kill_children`] can terminate it (along with anything it spawned), as
+/// can dropping the stream of the panel that ran it (see [`owned`]). If
+/// waiting fails, the process group is killed immediately.
+pub fn command_output(command: &mut Command) -> Result<Output> {
+    let child = command
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut guard = GroupGuard::new(&child);
+    let output = child.wait_with_output()?;
+    guard.done = true;
+    Ok(output)
+}
+
+/// Like [`command_output`], but waits on a blocking thread so that a slow
+/// command doesn't hold up the bar. The command still belongs to the panel
+/// that started it.
+pub async fn command_output_blocking(mut command: Command) -> Result<Output> {
+    spawn_blocking(move || command_output(&mut command)).await?
+}
+
+/// Like [`task::spawn_blocking`], but any children that `f` starts with
+/// [`command_output`] or [`spawn_command`] belong to the panel that called
+/// this, just as if the panel had started them itself. Panels should use
+/// this instead of [`task::spawn_blocking`] for anything that runs commands.
+pub fn spawn_blocking<F, R>(f: F) -> JoinHandle<R>
+where
+    F: FnOnce() -> R + Send + 'static,
+    R: Send + 'static,
+{
+    let owner = owner();
+    task::spawn_blocking(move || with_owner(owner, f))
+}
+
+/// Returns an id for the panel whose stream is being polled on this thread,
+/// if any. Pass this to [`with_owner`] to start children for the panel from
+/// another thread.
+#[must_use]
+pub fn owner() -> Option<u64> {
+    OWNER.get()
+}
+
+/// Runs `f` as if the stream of `owner` were being polled, so that any
+/// children it starts belong to that panel.
+pub fn with_owner<R>(owner: Option<u64>, f: impl FnOnce() -> R) -> R {
+    /// Restores the previous owner, even if `f` panics.
+    struct Restore(Option<u64>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            OWNER.set(self.0);
+        }
+    }
 
-use crate::ipc::{self, ChannelEndpoint};
+    let _restore = Restore(OWNER.replace(owner));
+    f()
+}
 
-pub(crate) static mut ENDPOINT: OnceLock<ChannelEndpoint<(), ()>> =
-    OnceLock::new();
+/// Starts a command in a new process group without waiting for it.
+///
+/// The child is reaped by [`reap_children`] once it exits. Its process group
+/// is terminated when the stream of the panel that started it is dropped
+/// (see [`owned`]), or by [`kill_children`] if it's still running when the
+/// bar shuts down.
+pub fn spawn_command(command: &mut Command) -> Result<()> {
+    let child = command
+        .process_group(0)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    track(child.id() as i32);
+    DETACHED.lock().unwrap().push(child);
+    Ok(())
+}
+
+/// Reaps any children started with [`spawn_command`] that have exited, and
+/// stops tracking process groups that nothing is left in.
+pub fn reap_children() {
+    DETACHED
+        .lock()
+        .unwrap()
+        .retain_mut(|child| matches!(child.try_wait(), Ok(None)));
+    GROUPS
+        .lock()
+        .unwrap()
+        .retain(|&pgid, group| group_alive(pgid) && !group.reused(pgid));
+}
+
+/// Sends SIGTERM to the process group of every child that may still be
+/// running, then reaps any that have already exited.
+pub fn kill_children() {
+    for (&pgid, group) in GROUPS.lock().unwrap().iter() {
+        signal_group(pgid, group, Signal::SIGTERM);
+    }
+    reap_children();
+}
+
+/// Sends SIGTERM to the process groups started by one panel.
+fn kill_owned(owner: u64) {
+    let groups = GROUPS.lock().unwrap();
+    let owned = groups
+        .iter()
+        .filter(|(_, group)| group.owner == Some(owner));
+    for (&pgid, group) in owned {
+        signal_group(pgid, group, Signal::SIGTERM);
+    }
+}
+
+/// Wraps a panel's stream so that the children the panel starts while it's
+/// polled belong to it. When the stream is dropped, e.g. because the panel
+/// stopped or its bar shut down, those children are terminated.
+pub(crate) fn owned(stream: PanelStream) -> PanelStream {
+    Box::pin(Owned {
+        id: NEXT_OWNER.fetch_add(1, Ordering::Relaxed),
+        stream,
+    })
+}
+
+struct Owned {
+    id: u64,
+    stream: PanelStream,
+}
+
+impl Stream for Owned {
+    type Item = Result<PanelDrawInfo>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let previous = OWNER.replace(Some(self.id));
+        let item = self.stream.as_mut().poll_next(cx);
+        OWNER.set(previous);
+        item
+    }
+}
+
+impl Drop for Owned {
+    fn drop(&mut self) {
+        kill_owned(self.id);
+    }
+}
 
 /// Removes any sockets in `/tmp/lazybar-ipc/` that can't be connected to.
 pub async fn cleanup() -> Result<()> {
@@ -54,24 +327,36 @@ pub async fn cleanup() -> Result<()> {
     Ok(())
 }
 
+/// Removes the IPC socket of the bar named `bar`.
+pub(crate) fn remove_socket(bar: &str) {
+    let _ = remove_file(format!("/tmp/lazybar-ipc/{bar}"));
+}
+
 /// Shutdown the bar as cleanly as possible. Short of SIGKILL, lazybar should
 /// never exit without calling this function.
+///
+/// `bar` is the name of the bar that is exiting, if any, and whether it uses
+/// IPC. Other bars remove their own sockets as they shut down.
 pub async fn exit(
-    bar: Option<(&str, bool)>,
+    bar: Option<(String, bool)>,
     in_runtime: bool,
     exit_code: i32,
 ) -> ! {
     if let Some((bar, true)) = bar {
-        let _ = remove_file(format!("/tmp/lazybar-ipc/{bar}"));
+        remove_socket(&bar);
     }
     if in_runtime {
-        if let Some(mut endpoint) = unsafe { ENDPOINT.take() } {
-            if endpoint.send.send(()).is_ok() {
-                let _ =
-                    time::timeout(Duration::from_secs(2), endpoint.recv.recv())
-                        .await;
-            }
-        }
+        let mut endpoints = std::mem::take(&mut *ENDPOINTS.lock().unwrap());
+        // ask every bar to shut down before waiting on any of them
+        endpoints.retain(|endpoint| endpoint.send.send(()).is_ok());
+        let _ = time::timeout(
+            Duration::from_secs(2),
+            futures::future::join_all(
+                endpoints.iter_mut().map(|endpoint| endpoint.recv.recv()),
+            ),
+        )
+        .await;
     }
+    kill_children();
     std::process::exit(exit_code);
 }
@@ -1,8 +1,14 @@
 use std::{
-    collections::HashMap,
+    cell::Cell,
+    collections::{HashMap, HashSet},
+    f64::consts::FRAC_PI_2,
     hash::BuildHasher,
     rc::Rc,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use anyhow::Result;
@@ -10,20 +16,32 @@ use config::Value;
 use derive_builder::Builder;
 use derive_debug::Dbg;
 use futures::task::AtomicWaker;
+use lazy_static::lazy_static;
 use pangocairo::functions::show_layout;
 
 use crate::{
     actions::Actions,
     attrs::Attrs,
-    bar::{Dependence, PanelDrawInfo},
-    image::Image,
+    bar::{ClickSpan, Dependence, PanelDrawInfo},
+    effects::Effects,
+    format::{self, Block, BlockKind},
+    gauge::Gauge,
+    graph::Graph,
+    high_contrast, highlight,
+    image::{self, Image},
+    menu::MenuEntry,
+    progress::ProgressBar,
     remove_array_from_config, remove_bool_from_config,
-    remove_string_from_config, Highlight, PanelHideFn, PanelShowFn, Ramp,
+    remove_float_from_config, remove_string_from_config,
+    remove_uint_from_config, style,
+    threshold::Thresholds,
+    Highlight, HighlightScope, PanelHideFn, PanelShowFn, Ramp,
 };
 
 /// A [`PanelShowFn`] and a [`PanelHideFn`] bundled together. Only for use with
-/// [`draw_common`].
+/// [`draw_panel`].
 #[derive(Dbg)]
+#[non_exhaustive]
 pub enum ShowHide {
     /// This is designed for use with a [`ManagedIntervalStream`], but other
     /// streams can also work.
@@ -38,6 +56,171 @@ pub enum ShowHide {
     None,
 }
 
+impl ShowHide {
+    fn into_fns(self) -> (Option<PanelShowFn>, Option<PanelHideFn>) {
+        match self {
+            Self::Default(paused, waker) => {
+                let paused_ = paused.clone();
+                (
+                    Some(Box::new(move || {
+                        *paused.lock().unwrap() = false;
+                        waker.wake();
+                        Ok(())
+                    })),
+                    Some(Box::new(move || {
+                        *paused_.lock().unwrap() = true;
+                        Ok(())
+                    })),
+                )
+            }
+            Self::Custom(show, hide) => (show, hide),
+            Self::None => (None, None),
+        }
+    }
+}
+
+/// The space in pixels between the end of looping text and its next
+/// repetition
+const MARQUEE_GAP: f64 = 32.0;
+
+lazy_static! {
+    static ref SPAN_NAMES: Mutex<HashSet<&'static str>> =
+        Mutex::new(HashSet::new());
+}
+
+/// Returns a copy of a span name that lives for the life of the bar, so that
+/// it can be sent in a [`MouseEvent`][crate::bar::MouseEvent]. Each name is
+/// only allocated once, however many times the panel is drawn.
+fn intern_span_name(name: &str) -> &'static str {
+    let mut names = SPAN_NAMES.lock().unwrap();
+    if let Some(&name) = names.get(name) {
+        return name;
+    }
+    let name: &'static str = name.to_string().leak();
+    names.insert(name);
+    name
+}
+
+/// Finds where each `%{span <name>}` block is in `layout`. `left` is the x
+/// coordinate in the panel of the start of the layout, which is `width`
+/// pixels wide.
+fn click_spans(
+    layout: &pango::Layout,
+    blocks: &[Block],
+    left: f64,
+    width: i32,
+) -> Vec<ClickSpan> {
+    let x = |idx: i32| {
+        let (_, x) = layout.index_to_line_x(idx, false);
+        (f64::from(x) / f64::from(pango::SCALE)).clamp(0.0, f64::from(width))
+            + left
+    };
+    blocks
+        .iter()
+        .filter(|block| block.kind == BlockKind::Span)
+        .map(|block| {
+            let (start, end) = (x(block.range.0), x(block.range.1));
+            ClickSpan {
+                name: intern_span_name(block.name.as_str()),
+                start: start.min(end).round() as i32,
+                end: start.max(end).round() as i32,
+            }
+        })
+        .collect()
+}
+
+/// How text that is wider than a panel's `max_width` scrolls.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Marquee {
+    /// The text scrolls to the left forever, with each repetition following
+    /// the last.
+    Loop,
+    /// The text scrolls to its end, then back to its start.
+    PingPong,
+}
+
+impl Marquee {
+    /// Parses an instance from its name (`loop` or `pingpong`).
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "loop" => Some(Self::Loop),
+            "pingpong" | "ping-pong" => Some(Self::PingPong),
+            _ => None,
+        }
+    }
+
+    /// Returns how far in pixels the text is scrolled at the moment. The
+    /// position depends only on the time, so it carries on smoothly when the
+    /// panel updates.
+    fn offset(self, speed: f64, text_width: f64, visible_width: f64) -> f64 {
+        let distance = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs_f64()
+            * speed;
+        match self {
+            Self::Loop => distance.rem_euclid(text_width + MARQUEE_GAP),
+            Self::PingPong => {
+                let range = text_width - visible_width;
+                let position = distance.rem_euclid(2.0 * range);
+                if position < range {
+                    position
+                } else {
+                    2.0 * range - position
+                }
+            }
+        }
+    }
+}
+
+/// Which way a panel's text is turned. On bars on the left or right of the
+/// screen, text runs along the bar, so turning it a quarter turn the other
+/// way makes it upright.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Rotation {
+    /// The text runs along the bar.
+    #[default]
+    None,
+    /// The text is turned a quarter turn clockwise, which makes it upright on
+    /// a bar on the left.
+    Clockwise,
+    /// The text is turned a quarter turn counterclockwise, which makes it
+    /// upright on a bar on the right.
+    CounterClockwise,
+}
+
+impl Rotation {
+    /// Parses an instance from an angle in degrees clockwise (0, 90, or 270).
+    #[must_use]
+    pub const fn parse(degrees: u64) -> Option<Self> {
+        match degrees {
+            0 => Some(Self::None),
+            90 => Some(Self::Clockwise),
+            270 => Some(Self::CounterClockwise),
+            _ => None,
+        }
+    }
+
+    /// Turns `cr` so that text drawn at (0, 0) fills a box of `dims` (after
+    /// turning) whose top left corner is at the current origin.
+    fn apply(self, cr: &cairo::Context, dims: (i32, i32)) {
+        match self {
+            Self::None => {}
+            Self::Clockwise => {
+                cr.translate(f64::from(dims.0), 0.0);
+                cr.rotate(FRAC_PI_2);
+            }
+            Self::CounterClockwise => {
+                cr.translate(0.0, f64::from(dims.1));
+                cr.rotate(-FRAC_PI_2);
+            }
+        }
+    }
+}
+
 /// The end of a typical draw function.
 ///
 /// Takes a cairo context, a string to
@@ -46,7 +229,206 @@ pub enum ShowHide {
 ///
 /// The text will be interpreted as markup. If this is not your intended
 /// behavior, use [`markup_escape_text`][crate::markup_escape_text] to display
-/// what you want or implement this functionality manually.
+/// what you want or implement this functionality manually. Expressions are
+/// evaluated first; see [`format::process`].
+///
+/// An `%image:<path>%` token draws a PNG or SVG inline with the text. The
+/// path may be followed by `;size=<pixels>` to set the image's height (at
+/// most the height of the bar), `;padding=<pixels>` to add space on each
+/// side, and `;recolor` to draw a monochrome icon in the panel's foreground
+/// color, e.g. `%image:/usr/share/icons/vol.svg;size=16;recolor%`. SVGs are
+/// rendered with librsvg's `rsvg-convert`. `%icon:<name>%` takes the same
+/// modifiers and draws an icon from the icon theme, e.g.
+/// `%icon:audio-volume-high;recolor%`. See [`crate::icon::lookup`].
+///
+/// Text between `%{highlight <name>}` and `%{endhighlight}` gets its own
+/// over- and underlines from the highlight with that name (see
+/// [`Highlight::parse`]), drawn across only that part of the text, e.g.
+/// `CPU %{highlight hot}%percentage%%{endhighlight}`. Panels can emit these
+/// markers to pick out part of their text, such as the active item in a
+/// list. Segments aren't drawn on turned text.
+///
+/// Similarly, text between `%{span <name>}` and `%{endspan}` can be clicked
+/// on its own: mouse events on it carry the span's name (see
+/// [`MouseEvent::span`][crate::bar::MouseEvent::span]), which actions can
+/// use as `%span%`. For example, with `click_left = "%span%"`,
+/// `%{span prev}⏮%{endspan} %{span next}⏭%{endspan}` sends `prev` or `next`
+/// depending on which icon is clicked. Spans aren't found on turned or
+/// scrolling text.
+///
+/// The padding and margin from `common` are added around the text, unless the
+/// text is empty, in which case the panel has no width. Whether the background
+/// and highlight cover the padding depends on
+/// [`highlight_scope`][PanelCommon::highlight_scope].
+///
+/// Text wider than [`max_width`][PanelCommon::max_width] is cut off,
+/// ellipsized if [`ellipsize`][PanelCommon::ellipsize] is set, or scrolls if
+/// [`marquee`][PanelCommon::marquee] is set, in which case the bar redraws the
+/// panel regularly on its own.
+///
+/// If [`rotation`][PanelCommon::rotation] is set, the text is turned a
+/// quarter turn, and the panel's width and height are swapped to fit it.
+/// Turned text that is longer than the bar is tall is ellipsized instead,
+/// and `max_width` and `marquee` don't apply.
+pub fn draw_panel(
+    cr: &Rc<cairo::Context>,
+    text: &str,
+    attrs: &Attrs,
+    common: &PanelCommon,
+    highlight: Option<Highlight>,
+    height: i32,
+    show_hide: ShowHide,
+) -> Result<PanelDrawInfo> {
+    // the draw function applies the font again if the styles are reloaded
+    // before the panel next updates
+    let styled_at = Cell::new(style::generation());
+
+    let layout = pangocairo::functions::create_layout(cr);
+    let (markup, mut inline_images) =
+        image::extract_inline(format::process(text).as_ref(), height);
+    let (markup, blocks) = format::extract_blocks(markup.as_str());
+    layout.set_markup(markup.as_str());
+    attrs.restyled().apply_font(&layout);
+    image::reserve_inline(&layout, &mut inline_images);
+    let (mut text_width, mut text_height) = layout.pixel_size();
+    let rotation = common.rotation;
+    let rotated = rotation != Rotation::None;
+    if rotated {
+        // turned text runs across the bar, so it can't be longer than the bar
+        // is tall
+        if text_width > height {
+            layout.set_width(height * pango::SCALE);
+            layout.set_ellipsize(
+                common.ellipsize.unwrap_or(pango::EllipsizeMode::End),
+            );
+            (text_width, text_height) = layout.pixel_size();
+        }
+    } else if let (Some(max_width), Some(ellipsize), None) =
+        (common.max_width, common.ellipsize, common.marquee)
+    {
+        if text_width > max_width {
+            layout.set_width(max_width * pango::SCALE);
+            layout.set_ellipsize(ellipsize);
+            (text_width, text_height) = layout.pixel_size();
+        }
+    }
+    let text_dims = if rotated {
+        (text_height, text_width)
+    } else {
+        (
+            common
+                .max_width
+                .map_or(text_width, |max_width| text_width.min(max_width)),
+            text_height,
+        )
+    };
+    // segments are drawn across the bar, which doesn't work for turned text
+    let segments = if rotated {
+        Vec::new()
+    } else {
+        highlight::segments(&blocks)
+    };
+    let clipped = !rotated && text_dims.0 < text_width;
+    let marquee = common.marquee.filter(|_| clipped);
+    let marquee_speed = common.marquee_speed;
+    let plain = layout.text().to_string();
+    let markup_attrs = layout.attributes();
+    let plain_attrs = markup_attrs.as_ref().map(high_contrast::strip_colors);
+
+    let frame = Frame::new(attrs, common, highlight, text_dims, height);
+
+    // spans would have to move with turned or scrolling text
+    let spans = if rotated || marquee.is_some() {
+        Vec::new()
+    } else {
+        click_spans(&layout, &blocks, frame.content_x, text_dims.0)
+    };
+
+    let (show, hide) = show_hide.into_fns();
+    let hovered = frame.hovered.clone();
+
+    let mut draw_info = PanelDrawInfo::new(
+        frame.dims,
+        common.dependence,
+        Box::new(move |cr, _| {
+            frame.draw(cr, |cr, attrs| {
+                let generation = style::generation();
+                if styled_at.replace(generation) != generation {
+                    attrs.apply_font(&layout);
+                }
+
+                // the top of the bar, which segments are drawn from
+                let top = -f64::from(height - text_dims.1) / 2.0;
+                if clipped {
+                    cr.rectangle(
+                        0.0,
+                        top,
+                        text_dims.0 as f64,
+                        f64::from(height),
+                    );
+                    cr.clip();
+                }
+
+                if high_contrast::enabled() {
+                    layout.set_attributes(plain_attrs.as_ref());
+                } else {
+                    layout.set_attributes(markup_attrs.as_ref());
+                }
+                let draw_segments = |cr: &cairo::Context| {
+                    highlight::draw_segments(
+                        cr,
+                        &layout,
+                        &segments,
+                        top,
+                        f64::from(height),
+                    )
+                };
+                attrs.apply_fg(cr);
+                rotation.apply(cr, text_dims);
+                if let Some(marquee) = marquee {
+                    let scrolled = marquee.offset(
+                        marquee_speed,
+                        text_width as f64,
+                        text_dims.0 as f64,
+                    );
+                    cr.translate(-scrolled, 0.0);
+                    draw_segments(cr)?;
+                    show_layout(cr, &layout);
+                    image::draw_inline(cr, &layout, &inline_images, attrs)?;
+                    if marquee == Marquee::Loop {
+                        cr.translate(text_width as f64 + MARQUEE_GAP, 0.0);
+                        draw_segments(cr)?;
+                        show_layout(cr, &layout);
+                        image::draw_inline(cr, &layout, &inline_images, attrs)?;
+                    }
+                } else {
+                    draw_segments(cr)?;
+                    show_layout(cr, &layout);
+                    image::draw_inline(cr, &layout, &inline_images, attrs)?;
+                }
+                Ok(())
+            })
+        }),
+        show,
+        hide,
+        None,
+    )
+    .with_text(plain);
+    draw_info.tooltip.clone_from(&common.tooltip);
+    draw_info.menu.clone_from(&common.menu);
+    draw_info.animated = marquee.is_some();
+    draw_info.hovered = hovered;
+    draw_info.interactive =
+        !common.actions.is_empty() || !common.menu.is_empty();
+    draw_info.spans = spans;
+    Ok(draw_info)
+}
+
+/// The end of a typical draw function for panels without a [`PanelCommon`].
+///
+/// Draws `text` like [`draw_panel`] with a [`PanelCommon`] that only sets
+/// `dependence` and `images`, so there is no padding, margin, or any of the
+/// other options.
 pub fn draw_common(
     cr: &Rc<cairo::Context>,
     text: &str,
@@ -57,63 +439,343 @@ pub fn draw_common(
     height: i32,
     show_hide: ShowHide,
 ) -> Result<PanelDrawInfo> {
-    let layout = pangocairo::functions::create_layout(cr);
-    layout.set_markup(text);
-    attrs.apply_font(&layout);
-    let dims = layout.pixel_size();
+    let common = PanelCommonBuilder::default()
+        .dependence(dependence)
+        .actions(Actions::default())
+        .images(images)
+        .visible(true)
+        .build()?;
+    draw_panel(cr, text, attrs, &common, highlight, height, show_hide)
+}
 
-    let attrs = attrs.clone();
-    let bg = attrs.bg.clone().unwrap_or_default();
+/// What [`draw_panel`], [`draw_graph`], and [`draw_gauge`] draw around a
+/// panel's contents: its margin, padding, background, images, highlight,
+/// accent, border, and effects.
+struct Frame {
+    /// The attrs as the panel was configured, restyled on each draw
+    attrs: Attrs,
+    highlight: Option<Highlight>,
+    accent: Option<Highlight>,
+    /// Set while the pointer is on the panel, if the accent depends on it
+    hovered: Option<Arc<AtomicBool>>,
+    images: Vec<Image>,
+    effects: Effects,
+    margin: i32,
+    padding_left: i32,
+    /// The left edge of the background and highlight, past the margin
+    bg_x: i32,
+    bg_width: i32,
+    /// The width of the border, which goes around the background
+    frame_width: i32,
+    content_height: i32,
+    height: i32,
+    /// The size of the whole panel
+    dims: (i32, i32),
+    /// The distance from the left edge of the panel to its contents
+    content_x: f64,
+}
 
-    let (show, hide): (Option<PanelShowFn>, Option<PanelHideFn>) =
-        match show_hide {
-            ShowHide::Default(paused, waker) => {
-                let paused_ = paused.clone();
-                (
-                    Some(Box::new(move || {
-                        *paused.lock().unwrap() = false;
-                        waker.wake();
-                        Ok(())
-                    })),
-                    Some(Box::new(move || {
-                        *paused_.lock().unwrap() = true;
-                        Ok(())
-                    })),
-                )
-            }
-            ShowHide::Custom(show, hide) => (show, hide),
-            ShowHide::None => (None, None),
+impl Frame {
+    /// Lays out a frame around contents of size `content_dims`. Contents
+    /// without any width get no padding or margin, so that the panel has no
+    /// width either.
+    fn new(
+        attrs: &Attrs,
+        common: &PanelCommon,
+        highlight: Option<Highlight>,
+        content_dims: (i32, i32),
+        height: i32,
+    ) -> Self {
+        let (padding_left, padding_right, margin) = if content_dims.0 == 0 {
+            (0, 0, 0)
+        } else {
+            (common.padding_left, common.padding_right, common.margin)
+        };
+        let dims = (
+            content_dims.0 + padding_left + padding_right,
+            content_dims.1,
+        );
+        let (bg_x, bg_width) = match common.highlight_scope {
+            HighlightScope::Text => (padding_left, content_dims.0),
+            HighlightScope::Panel => (0, dims.0),
         };
+        let bg = attrs.restyled().bg.clone().unwrap_or_default();
+        let (width, bg_height) = bg.adjust_dims(dims, height);
 
-    Ok(PanelDrawInfo::new(
-        bg.adjust_dims(dims, height),
-        dependence,
-        Box::new(move |cr, _| {
+        Self {
+            attrs: attrs.clone(),
+            highlight,
+            accent: common.accent.clone(),
+            hovered: common
+                .accent_active_only
+                .then(|| Arc::new(AtomicBool::new(false))),
+            images: common.images.clone(),
+            effects: common.effects,
+            margin,
+            padding_left,
+            bg_x,
+            bg_width,
+            frame_width: bg.adjust_dims((bg_width, dims.1), height).0,
+            content_height: content_dims.1,
+            height,
+            dims: (width + 2 * margin, bg_height),
+            content_x: f64::from(margin + padding_left)
+                + bg.get_offset(f64::from(dims.1), f64::from(height)),
+        }
+    }
+
+    /// Draws the frame, then calls `content` to draw the panel's contents
+    /// with the origin at their top left corner. `content` is given the
+    /// panel's attrs as of the current styles.
+    fn draw(
+        &self,
+        cr: &cairo::Context,
+        content: impl FnOnce(&cairo::Context, &Attrs) -> Result<()>,
+    ) -> Result<()> {
+        let attrs = self.attrs.restyled();
+        let bg = attrs.bg.clone().unwrap_or_default();
+        let height = f64::from(self.height);
+        let bg_x = f64::from(self.bg_x);
+        let bg_width = f64::from(self.bg_width);
+
+        self.effects.apply(cr, height, |cr| {
+            cr.save()?;
+            cr.translate(f64::from(self.margin), 0.0);
+
+            cr.save()?;
+            cr.translate(bg_x, 0.0);
             let offset =
-                bg.draw(cr, dims.0 as f64, dims.1 as f64, height as f64)?;
+                bg.draw(cr, bg_width, f64::from(self.content_height), height)?;
+            cr.restore()?;
 
-            for image in &images {
+            // recolored images are drawn in the foreground color
+            attrs.apply_fg(cr);
+            for image in &self.images {
                 image.draw(cr)?;
             }
 
-            cr.save()?;
+            if let Some(ref highlight) = self.highlight {
+                cr.save()?;
+                cr.translate(bg_x + offset, 0.0);
+                highlight.restyled().draw(cr, height, bg_width)?;
+                cr.restore()?;
+            }
 
-            cr.translate(offset, 0.0);
-            if let Some(ref highlight) = highlight {
-                highlight.draw(cr, height as f64, dims.0 as f64)?;
+            let inactive = self
+                .hovered
+                .as_ref()
+                .is_some_and(|hovered| !hovered.load(Ordering::Relaxed));
+            if let Some(accent) = self.accent.as_ref().filter(|_| !inactive) {
+                cr.save()?;
+                cr.translate(bg_x + offset, 0.0);
+                accent.restyled().draw(cr, height, bg_width)?;
+                cr.restore()?;
             }
 
-            cr.translate(0.0, (height - dims.1) as f64 / 2.0);
+            if let Some(ref border) = attrs.border {
+                cr.save()?;
+                cr.translate(bg_x, 0.0);
+                border.draw(cr, f64::from(self.frame_width), height)?;
+                cr.restore()?;
+            }
 
-            attrs.apply_fg(cr);
-            show_layout(cr, &layout);
+            cr.translate(
+                offset + f64::from(self.padding_left),
+                f64::from(self.height - self.content_height) / 2.0,
+            );
+            content(cr, &attrs)?;
             cr.restore()?;
             Ok(())
+        })
+    }
+}
+
+/// How a panel shows its value. Panels document which of these they
+/// support, and are shown as text otherwise.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub enum Display {
+    /// The panel's format string
+    #[default]
+    Text,
+    /// A sparkline of the panel's recent values
+    Graph(Graph),
+    /// A ring filled by the panel's value, around its text
+    Gauge(Gauge),
+}
+
+impl Display {
+    /// Parses the `display` option, which is `text` (the default), `graph`,
+    /// or `gauge`, along with the options of the chosen display. See
+    /// [`Graph::parse`] and [`Gauge::parse`].
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let display = remove_string_from_config("display", table);
+        let display = match display.as_deref() {
+            None | Some("text") => Self::Text,
+            Some("graph") => Self::Graph(Graph::parse(table)),
+            Some("gauge") => Self::Gauge(Gauge::parse(table)),
+            Some(other) => {
+                log::warn!("Unknown display {other}, using text");
+                Self::Text
+            }
+        };
+        log::debug!("got display: {display:?}");
+        display
+    }
+
+    /// Returns the graph for panels that can only be shown as text or as a
+    /// graph, warning if a gauge was asked for.
+    #[must_use]
+    pub fn into_graph(self) -> Option<Graph> {
+        match self {
+            Self::Text => None,
+            Self::Graph(graph) => Some(graph),
+            Self::Gauge(_) => {
+                log::warn!("This panel can't be shown as a gauge");
+                None
+            }
+        }
+    }
+
+    /// Returns the gauge for panels that can only be shown as text or as a
+    /// gauge, warning if a graph was asked for.
+    #[must_use]
+    pub fn into_gauge(self) -> Option<Gauge> {
+        match self {
+            Self::Text => None,
+            Self::Graph(_) => {
+                log::warn!("This panel can't be shown as a graph");
+                None
+            }
+            Self::Gauge(gauge) => Some(gauge),
+        }
+    }
+}
+
+/// The end of a draw function for a panel shown as a [`Graph`].
+///
+/// Draws the graph in place of the panel's text, with the panel's padding,
+/// margin, background, highlight, accent, border, and effects around it like
+/// [`draw_panel`]. `text` isn't drawn, but is exported for screen readers.
+pub fn draw_graph(
+    cr: &Rc<cairo::Context>,
+    graph: &Graph,
+    text: &str,
+    attrs: &Attrs,
+    common: &PanelCommon,
+    highlight: Option<Highlight>,
+    height: i32,
+    show_hide: ShowHide,
+) -> Result<PanelDrawInfo> {
+    let graph = graph.clone();
+    let frame =
+        Frame::new(attrs, common, highlight, (graph.width, height), height);
+
+    // like markup, but without any of its formatting
+    let layout = pangocairo::functions::create_layout(cr);
+    layout.set_markup(format::process(text).as_ref());
+    let plain = layout.text().to_string();
+
+    let (show, hide) = show_hide.into_fns();
+    let hovered = frame.hovered.clone();
+
+    let mut draw_info = PanelDrawInfo::new(
+        frame.dims,
+        common.dependence,
+        Box::new(move |cr, _| {
+            frame.draw(cr, |cr, attrs| graph.draw(cr, attrs, height))
         }),
         show,
         hide,
         None,
-    ))
+    )
+    .with_text(plain);
+    draw_info.tooltip.clone_from(&common.tooltip);
+    draw_info.menu.clone_from(&common.menu);
+    draw_info.hovered = hovered;
+    draw_info.interactive =
+        !common.actions.is_empty() || !common.menu.is_empty();
+    Ok(draw_info)
+}
+
+/// The end of a draw function for a panel shown as a [`Gauge`].
+///
+/// Draws the gauge filled to `level` (from 0 to 1) with `text` centered in
+/// it, with the panel's padding, margin, background, highlight, accent,
+/// border, and effects around it like [`draw_panel`]. The panel is as wide
+/// as the gauge or the text, whichever is wider.
+pub fn draw_gauge(
+    cr: &Rc<cairo::Context>,
+    gauge: &Gauge,
+    level: f64,
+    text: &str,
+    attrs: &Attrs,
+    common: &PanelCommon,
+    highlight: Option<Highlight>,
+    height: i32,
+    show_hide: ShowHide,
+) -> Result<PanelDrawInfo> {
+    let gauge = gauge.clone();
+    // the draw function applies the font again if the styles are reloaded
+    // before the panel next updates
+    let styled_at = Cell::new(style::generation());
+
+    let layout = pangocairo::functions::create_layout(cr);
+    layout.set_markup(format::process(text).as_ref());
+    attrs.restyled().apply_font(&layout);
+    let (text_width, text_height) = layout.pixel_size();
+    let plain = layout.text().to_string();
+    let markup_attrs = layout.attributes();
+    let plain_attrs = markup_attrs.as_ref().map(high_contrast::strip_colors);
+
+    let inner_width = text_width.max(gauge.diameter(height));
+    let frame =
+        Frame::new(attrs, common, highlight, (inner_width, height), height);
+
+    let (show, hide) = show_hide.into_fns();
+    let hovered = frame.hovered.clone();
+
+    let mut draw_info = PanelDrawInfo::new(
+        frame.dims,
+        common.dependence,
+        Box::new(move |cr, _| {
+            frame.draw(cr, |cr, attrs| {
+                let generation = style::generation();
+                if styled_at.replace(generation) != generation {
+                    attrs.apply_font(&layout);
+                }
+
+                let center =
+                    (f64::from(inner_width) / 2.0, f64::from(height) / 2.0);
+                gauge.draw(cr, attrs, center, height, level)?;
+
+                if high_contrast::enabled() {
+                    layout.set_attributes(plain_attrs.as_ref());
+                } else {
+                    layout.set_attributes(markup_attrs.as_ref());
+                }
+                attrs.apply_fg(cr);
+                cr.move_to(
+                    f64::from(inner_width - text_width) / 2.0,
+                    f64::from(height - text_height) / 2.0,
+                );
+                show_layout(cr, &layout);
+                Ok(())
+            })
+        }),
+        show,
+        hide,
+        None,
+    )
+    .with_text(plain);
+    draw_info.tooltip.clone_from(&common.tooltip);
+    draw_info.menu.clone_from(&common.menu);
+    draw_info.hovered = hovered;
+    draw_info.interactive =
+        !common.actions.is_empty() || !common.menu.is_empty();
+    Ok(draw_info)
 }
 
 /// The common part of most [`PanelConfigs`][crate::PanelConfig]. Stores format
@@ -121,6 +783,7 @@ pub fn draw_common(
 #[derive(Debug, Clone, Builder)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
+#[non_exhaustive]
 pub struct PanelCommon {
     /// Whether the panel depends on its neighbors
     pub dependence: Dependence,
@@ -130,10 +793,66 @@ pub struct PanelCommon {
     pub images: Vec<Image>,
     /// Whether the panel should be visible on startup
     pub visible: bool,
+    /// The space in pixels between the left edge of the panel's background
+    /// and its text
+    #[builder(default)]
+    pub padding_left: i32,
+    /// The space in pixels between the panel's text and the right edge of its
+    /// background
+    #[builder(default)]
+    pub padding_right: i32,
+    /// The space in pixels on each side of the panel, outside of its
+    /// background and highlight
+    #[builder(default)]
+    pub margin: i32,
+    /// Whether the panel's background and highlight cover its padding
+    #[builder(default)]
+    pub highlight_scope: HighlightScope,
+    /// Over- and underlines drawn on top of the panel's highlight
+    #[builder(default)]
+    pub accent: Option<Highlight>,
+    /// Whether the accent is only drawn while the pointer is on the panel
+    #[builder(default)]
+    pub accent_active_only: bool,
+    /// The effects applied to everything the panel draws
+    #[builder(default)]
+    pub effects: Effects,
+    /// The markup shown in a tooltip when the pointer rests on the panel
+    #[builder(default)]
+    pub tooltip: Option<String>,
+    /// The entries of the menu opened by right-clicking the panel
+    #[builder(default)]
+    pub menu: Vec<MenuEntry>,
+    /// The widest in pixels that the panel's text can be
+    #[builder(default)]
+    pub max_width: Option<i32>,
+    /// Where text wider than `max_width` is ellipsized, if it is
+    #[builder(default)]
+    pub ellipsize: Option<pango::EllipsizeMode>,
+    /// How text wider than `max_width` scrolls, if it does
+    #[builder(default)]
+    pub marquee: Option<Marquee>,
+    /// How fast text scrolls, in pixels per second
+    #[builder(default = "30.0")]
+    pub marquee_speed: f64,
+    /// How `%bar%` tokens are drawn in panels that support them
+    #[builder(default)]
+    pub progress: ProgressBar,
+    /// Which way the panel's text is turned
+    #[builder(default)]
+    pub rotation: Rotation,
+    /// The values at which the panel switches attrs, for panels that show a
+    /// number
+    #[builder(default)]
+    pub thresholds: Thresholds,
 }
 
 impl PanelCommon {
     /// Parses a single format from a subset of the global config.
+    ///
+    /// Formats can contain conditional blocks such as
+    /// `%{if %artist%}%artist% - %{endif}`, which are processed along with
+    /// expressions when the panel is drawn. See [`format::process`].
     pub fn parse_format<S: BuildHasher>(
         table: &mut HashMap<String, Value, S>,
         suffix: &'static str,
@@ -309,7 +1028,51 @@ impl PanelCommon {
     /// Dependence should be specified as `dependence = "value"`, where value is
     /// a valid variant of [`Dependence`].
     ///
-    /// See [`Actions::parse`] and [`Image::parse`] for more parsing details.
+    /// `padding_left` and `padding_right` add space in pixels between the text
+    /// and the edges of the panel's background and highlight, and `margin`
+    /// adds space in pixels on both sides of the panel, outside of the
+    /// background. Both count toward the panel's width, so clicks on them
+    /// go to the panel. `highlight_scope` is either `panel` (the default),
+    /// to draw the background and highlight across the padding, or `text`,
+    /// to draw them only behind the text.
+    ///
+    /// `accent` is the name of a [`Highlight`] whose over- and underlines are
+    /// drawn on any panel, on top of the panel's own highlight. If
+    /// `accent_active_only` is true, the accent is only drawn while the panel
+    /// is active, i.e. while the pointer is on it.
+    ///
+    /// `tooltip` is markup shown next to the bar when the pointer rests on
+    /// the panel. See [`crate::tooltip`].
+    ///
+    /// `max_width` is the widest in pixels that the panel's text can be.
+    /// Wider text is cut off, unless `ellipsize` is set to `start`, `middle`,
+    /// or `end` to replace the overflowing part with an ellipsis there, or
+    /// `marquee` is set to `loop` to scroll it continuously or `pingpong` to
+    /// scroll it back and forth. `marquee` takes precedence over `ellipsize`.
+    /// `marquee_speed` is how fast it scrolls in pixels per second, 30 by
+    /// default.
+    ///
+    /// `menu` is an array of entries for a menu that opens when the panel is
+    /// right-clicked, in place of `click_right`. See [`MenuEntry::parse`].
+    ///
+    /// `rotate` turns the panel's text by 90 or 270 degrees clockwise, so that
+    /// it can be read on a bar on the left or right of the screen. See
+    /// [`Rotation`].
+    ///
+    /// Panels that show a number, such as a CPU's usage or a battery's
+    /// charge, switch to other attrs when it passes `warn_above`,
+    /// `crit_above`, `warn_below`, or `crit_below`. See
+    /// [`Thresholds::parse`].
+    ///
+    /// Panels that show a level, such as a volume or a battery's charge,
+    /// replace `%bar%` and `%bar:<width>%` in their formats with a bar of
+    /// characters. See [`ProgressBar::parse`] for its options.
+    ///
+    /// Any panel's text can include images with `%image:<path>%`. See
+    /// [`draw_panel`].
+    ///
+    /// See [`Actions::parse`], [`Image::parse`], and [`Effects::parse`] for
+    /// more parsing details.
     pub fn parse_common<S: BuildHasher>(
         table: &mut HashMap<String, Value, S>,
     ) -> Result<Self> {
@@ -355,6 +1118,107 @@ impl PanelCommon {
         builder
             .visible(remove_bool_from_config("visible", table).unwrap_or(true));
 
+        if let Some(padding) = remove_uint_from_config("padding_left", table) {
+            builder.padding_left(padding as i32);
+        }
+        if let Some(padding) = remove_uint_from_config("padding_right", table) {
+            builder.padding_right(padding as i32);
+        }
+        if let Some(margin) = remove_uint_from_config("margin", table) {
+            builder.margin(margin as i32);
+        }
+        log::debug!(
+            "got padding and margin: {:?} {:?} {:?}",
+            builder.padding_left,
+            builder.padding_right,
+            builder.margin
+        );
+
+        if let Some(scope) = remove_string_from_config("highlight_scope", table)
+        {
+            builder.highlight_scope(
+                HighlightScope::parse(scope.to_lowercase().as_str())
+                    .unwrap_or_else(|| {
+                        log::warn!("Invalid highlight_scope {scope}");
+                        HighlightScope::default()
+                    }),
+            );
+        }
+        log::debug!("got highlight scope: {:?}", builder.highlight_scope);
+
+        if let Some(accent) = remove_string_from_config("accent", table) {
+            builder.accent(Highlight::parse(accent.as_str()));
+            if builder.accent == Some(None) {
+                log::warn!("Couldn't find highlight {accent} for accent");
+            }
+        }
+        if let Some(active_only) =
+            remove_bool_from_config("accent_active_only", table)
+        {
+            builder.accent_active_only(active_only);
+        }
+        log::debug!(
+            "got accent: {:?} {:?}",
+            builder.accent,
+            builder.accent_active_only
+        );
+
+        builder.effects(Effects::parse(table));
+        log::debug!("got effects: {:?}", builder.effects);
+
+        builder.tooltip(remove_string_from_config("tooltip", table));
+        log::debug!("got tooltip: {:?}", builder.tooltip);
+
+        builder.menu(MenuEntry::parse(table));
+        log::debug!("got menu: {:?}", builder.menu);
+
+        if let Some(max_width) = remove_uint_from_config("max_width", table) {
+            builder.max_width(Some(max_width as i32));
+        }
+        if let Some(ellipsize) = remove_string_from_config("ellipsize", table) {
+            builder.ellipsize(match ellipsize.to_lowercase().as_str() {
+                "start" => Some(pango::EllipsizeMode::Start),
+                "middle" => Some(pango::EllipsizeMode::Middle),
+                "end" => Some(pango::EllipsizeMode::End),
+                "none" => None,
+                _ => {
+                    log::warn!("Invalid ellipsize mode {ellipsize}");
+                    None
+                }
+            });
+        }
+        if let Some(marquee) = remove_string_from_config("marquee", table) {
+            builder.marquee(Marquee::parse(marquee.to_lowercase().as_str()));
+            if builder.marquee == Some(None) {
+                log::warn!("Invalid marquee {marquee}");
+            }
+        }
+        if let Some(speed) = remove_float_from_config("marquee_speed", table) {
+            builder.marquee_speed(speed.max(1.0));
+        }
+        log::debug!(
+            "got max width, ellipsize, and marquee: {:?} {:?} {:?} {:?}",
+            builder.max_width,
+            builder.ellipsize,
+            builder.marquee,
+            builder.marquee_speed
+        );
+
+        builder.progress(ProgressBar::parse(table));
+        log::debug!("got progress bar: {:?}", builder.progress);
+
+        if let Some(degrees) = remove_uint_from_config("rotate", table) {
+            builder.rotation(Rotation::parse(degrees).unwrap_or_else(|| {
+                log::warn!(
+                    "Invalid rotation {degrees}, expected 0, 90, or 270"
+                );
+                Rotation::None
+            }));
+        }
+
+        builder.thresholds(Thresholds::parse(table));
+        log::debug!("got thresholds: {:?}", builder.thresholds);
+
         Ok(builder.build()?)
     }
 }
@@ -0,0 +1,80 @@
+use std::{
+    collections::HashSet,
+    net::{IpAddr, Ipv4Addr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static START: OnceLock<Instant> = OnceLock::new();
+
+/// How often simulated data changes. In demo mode, panels showing simulated
+/// data redraw at least this often.
+pub const TICK: Duration = Duration::from_secs(2);
+
+/// The order in which the simulated workspaces are visited, one per
+/// [`TICK`].
+const WORKSPACE_SCRIPT: [u32; 8] = [0, 1, 0, 2, 3, 2, 1, 4];
+const WORKSPACE_COUNT: u32 = 6;
+
+/// Switches panels that support it over to simulated data. This should be
+/// called before the config is parsed.
+pub fn enable() {
+    START.get_or_init(Instant::now);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Returns whether the bar is running in demo mode.
+#[must_use]
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+fn ticks() -> u64 {
+    (START.get_or_init(Instant::now).elapsed().as_millis() / TICK.as_millis())
+        as u64
+}
+
+/// Returns the capacity and status of a battery that drains from full to 5%
+/// at 1% per tick, then charges back up at 5% per tick.
+pub(crate) fn battery() -> (u8, &'static str) {
+    const DRAIN: u64 = 95;
+    const CHARGE: u64 = 19;
+
+    let t = ticks() % (DRAIN + CHARGE + 1);
+    if t < DRAIN {
+        ((100 - t) as u8, "Discharging")
+    } else if t < DRAIN + CHARGE {
+        ((5 + (t - DRAIN) * 5) as u8, "Charging")
+    } else {
+        (100, "Full")
+    }
+}
+
+/// Returns the ESSID and address of a connection that drops for 10 seconds
+/// every minute.
+pub(crate) fn network() -> Option<(&'static str, IpAddr)> {
+    let period = 60 / TICK.as_secs();
+    (ticks() % period < period - 10 / TICK.as_secs())
+        .then_some(("lazybar-demo", IpAddr::V4(Ipv4Addr::new(192, 168, 1, 23))))
+}
+
+/// Returns the workspace names, the current workspace, the nonempty
+/// workspaces, and the urgent workspaces.
+pub(crate) fn workspaces() -> (Vec<String>, u32, HashSet<u32>, HashSet<u32>) {
+    let step = ticks() as usize % WORKSPACE_SCRIPT.len();
+    let current = WORKSPACE_SCRIPT[step];
+    let names = (1..=WORKSPACE_COUNT).map(|n| n.to_string()).collect();
+    let nonempty = WORKSPACE_SCRIPT[..=step].iter().copied().collect();
+    // something on the last workspace wants attention until it's visited
+    let urgent = if step + 1 < WORKSPACE_SCRIPT.len() && step > 2 {
+        HashSet::from([WORKSPACE_SCRIPT[WORKSPACE_SCRIPT.len() - 1]])
+    } else {
+        HashSet::new()
+    };
+
+    (names, current, nonempty, urgent)
+}
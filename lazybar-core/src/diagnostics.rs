@@ -0,0 +1,25 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static WAKEUPS: AtomicU64 = AtomicU64::new(0);
+static REDRAWS: AtomicU64 = AtomicU64::new(0);
+
+/// Returns the number of times the bar's event loop has woken up, whether
+/// because of a panel, an X event, an IPC message, or an internal timer.
+#[must_use]
+pub fn wakeups() -> u64 {
+    WAKEUPS.load(Ordering::Relaxed)
+}
+
+/// Returns the number of times a panel has been redrawn.
+#[must_use]
+pub fn redraws() -> u64 {
+    REDRAWS.load(Ordering::Relaxed)
+}
+
+pub(crate) fn wakeup() {
+    WAKEUPS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub(crate) fn redraw() {
+    REDRAWS.fetch_add(1, Ordering::Relaxed);
+}
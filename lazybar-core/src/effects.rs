@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    hash::BuildHasher,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::Result;
+use config::Value;
+
+use crate::{remove_bool_from_config, remove_float_from_config};
+
+static ACTIVE: AtomicBool = AtomicBool::new(true);
+static TRACK_FOCUS: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether the bar is active, meaning that its monitor has focus.
+#[must_use]
+pub fn active() -> bool {
+    ACTIVE.load(Ordering::Relaxed)
+}
+
+/// Marks the bar as active or inactive. The bar needs to be redrawn for this
+/// to take effect.
+pub fn set_active(active: bool) {
+    ACTIVE.store(active, Ordering::Relaxed);
+}
+
+/// Returns whether any panel changes its appearance when the bar is
+/// inactive, in which case the bar needs to watch the focused window.
+pub(crate) fn tracks_focus() -> bool {
+    TRACK_FOCUS.load(Ordering::Relaxed)
+}
+
+/// Visual effects applied to everything a panel draws, including its
+/// background and highlight.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Effects {
+    /// Multiplies the opacity of the panel, from 0.0 to 1.0
+    pub opacity: f64,
+    /// Moves the panel down by this many pixels, or up if negative
+    pub offset_y: f64,
+    /// Shears the panel horizontally around its vertical center, like italic
+    /// text. This is the horizontal shift per pixel of height.
+    pub skew: f64,
+    /// Whether to draw the panel in grayscale when the bar is inactive
+    pub grayscale_inactive: bool,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            opacity: 1.0,
+            offset_y: 0.0,
+            skew: 0.0,
+            grayscale_inactive: false,
+        }
+    }
+}
+
+impl Effects {
+    /// Parses effects from a subset of the global config.
+    ///
+    /// - `opacity`: a multiplier for the opacity of the panel
+    ///   - type: f64
+    ///   - default: 1.0
+    /// - `offset_y`: how many pixels to move the panel down, or up if
+    ///   negative
+    ///   - type: f64
+    ///   - default: 0.0
+    /// - `skew`: how far to shear the panel horizontally, like italic text.
+    ///   0.2 is a slight slant.
+    ///   - type: f64
+    ///   - default: 0.0
+    /// - `grayscale_inactive`: whether to draw the panel in grayscale when
+    ///   the bar's monitor doesn't have focus
+    ///   - type: bool
+    ///   - default: false
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let mut effects = Self::default();
+        if let Some(opacity) = remove_float_from_config("opacity", table) {
+            effects.opacity = opacity.clamp(0.0, 1.0);
+        }
+        if let Some(offset_y) = remove_float_from_config("offset_y", table) {
+            effects.offset_y = offset_y;
+        }
+        if let Some(skew) = remove_float_from_config("skew", table) {
+            effects.skew = skew;
+        }
+        if let Some(grayscale) =
+            remove_bool_from_config("grayscale_inactive", table)
+        {
+            effects.grayscale_inactive = grayscale;
+            if grayscale {
+                TRACK_FOCUS.store(true, Ordering::Relaxed);
+            }
+        }
+        effects
+    }
+
+    /// Returns the effects `t` of the way from `self` to `other`, where `t`
+    /// is between 0.0 and 1.0. Grayscale switches halfway through.
+    #[must_use]
+    pub fn interpolate(&self, other: &Self, t: f64) -> Self {
+        let t = t.clamp(0.0, 1.0);
+        Self {
+            opacity: (other.opacity - self.opacity).mul_add(t, self.opacity),
+            offset_y: (other.offset_y - self.offset_y)
+                .mul_add(t, self.offset_y),
+            skew: (other.skew - self.skew).mul_add(t, self.skew),
+            grayscale_inactive: if t < 0.5 {
+                self.grayscale_inactive
+            } else {
+                other.grayscale_inactive
+            },
+        }
+    }
+
+    /// Calls `draw` with the effects applied to `cr`. `height` is the height
+    /// of the panel, which the skew is centered on.
+    pub fn apply(
+        &self,
+        cr: &cairo::Context,
+        height: f64,
+        draw: impl FnOnce(&cairo::Context) -> Result<()>,
+    ) -> Result<()> {
+        let grayscale = self.grayscale_inactive && !active();
+        let composite = self.opacity < 1.0 || grayscale;
+        if !composite && self.offset_y == 0.0 && self.skew == 0.0 {
+            return draw(cr);
+        }
+
+        cr.save()?;
+        cr.translate(0.0, self.offset_y);
+        if self.skew != 0.0 {
+            cr.translate(0.0, height / 2.0);
+            cr.transform(cairo::Matrix::new(
+                1.0, 0.0, -self.skew, 1.0, 0.0, 0.0,
+            ));
+            cr.translate(0.0, -height / 2.0);
+        }
+
+        if composite {
+            cr.push_group();
+            draw(cr)?;
+            let mut group = cr.pop_group()?;
+
+            if grayscale {
+                // take the saturation of a gray source everywhere the panel
+                // drew something
+                cr.push_group();
+                cr.set_source(&group)?;
+                cr.paint()?;
+                cr.set_operator(cairo::Operator::HslSaturation);
+                cr.set_source_rgb(0.5, 0.5, 0.5);
+                cr.mask(&group)?;
+                cr.set_operator(cairo::Operator::Over);
+                group = cr.pop_group()?;
+            }
+
+            cr.set_source(&group)?;
+            cr.paint_with_alpha(self.opacity)?;
+        } else {
+            draw(cr)?;
+        }
+
+        cr.restore()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,568 @@
+use std::{thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use derive_builder::Builder;
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        xkb::{self, ConnectionExt as _},
+        xproto::{
+            ConnectionExt, CreateWindowAux, EventMask, GrabMode, GrabStatus,
+            KeyButMask, Keycode, Keysym, Visualtype, Window, WindowClass,
+        },
+        Event,
+    },
+    xcb_ffi::XCBConnection,
+    CURRENT_TIME,
+};
+
+use crate::{
+    bar::{self, BarInfo},
+    high_contrast,
+    x::{create_surface, find_visual},
+    Attrs,
+};
+
+const BACKSPACE: Keysym = 0xff08;
+const TAB: Keysym = 0xff09;
+const RETURN: Keysym = 0xff0d;
+const ESCAPE: Keysym = 0xff1b;
+const HOME: Keysym = 0xff50;
+const LEFT: Keysym = 0xff51;
+const UP: Keysym = 0xff52;
+const RIGHT: Keysym = 0xff53;
+const DOWN: Keysym = 0xff54;
+const END: Keysym = 0xff57;
+const KP_ENTER: Keysym = 0xff8d;
+const DELETE: Keysym = 0xffff;
+
+/// A popup window that lets the user type a line of text, for panels that
+/// need input without shelling out to an external prompt like `dmenu`.
+///
+/// The popup appears directly below the bar (or above it, if the bar is at
+/// the bottom of the screen) and grabs the keyboard until the user presses
+/// Enter or Escape.
+///
+/// Supported keys:
+/// - Left/Right/Home/End (or Ctrl+A/Ctrl+E) to move the cursor
+/// - Backspace/Delete to delete a character
+/// - Ctrl+U to clear the line, Ctrl+W to delete the previous word
+/// - Up/Down to cycle through `history`
+/// - Tab to cycle through the entries of `completions` that start with the
+///   current word
+///
+/// Keys are translated using the XKB keyboard mapping, so the active layout
+/// and shift level are followed, falling back to the core mapping if the X
+/// server doesn't support XKB. Input methods (XIM) and dead keys are not
+/// supported, so text that needs them, e.g. Chinese or Japanese, can't be
+/// entered.
+#[derive(Builder, Debug, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Entry {
+    /// The text shown before the input
+    #[builder(default, setter(into))]
+    pub prompt: String,
+    /// The text that the input starts with
+    #[builder(default, setter(into))]
+    pub initial: String,
+    /// The x coordinate of the left edge of the popup, relative to the bar
+    #[builder(default)]
+    pub x: i16,
+    /// The width of the popup in pixels
+    #[builder(default = "300")]
+    pub width: u16,
+    /// The attributes used to draw the text
+    #[builder(default)]
+    pub attrs: Attrs,
+    /// Previous inputs, oldest first
+    #[builder(default)]
+    pub history: Vec<String>,
+    /// Candidates for completing the current word
+    #[builder(default)]
+    pub completions: Vec<String>,
+    /// The bar that the popup belongs to. Defaults to the bar running the
+    /// task that builds the entry. [`bar::bar_info`] returns [`None`] in
+    /// [`spawn_blocking`][tokio::task::spawn_blocking] and other threads, so
+    /// this needs to be set when building the entry from a blocking task.
+    #[builder(default = "bar::bar_info()")]
+    pub bar: Option<BarInfo>,
+}
+
+impl Entry {
+    /// Creates a new [`EntryBuilder`].
+    #[must_use]
+    pub fn builder() -> EntryBuilder {
+        EntryBuilder::default()
+    }
+
+    /// Shows the popup and blocks until the user finishes typing. Returns
+    /// [`None`] if the input was cancelled with Escape.
+    ///
+    /// This should be called from a blocking context, e.g. with
+    /// [`tokio::task::spawn_blocking`].
+    ///
+    /// # Errors
+    ///
+    /// If the popup couldn't be created, or the keyboard couldn't be grabbed.
+    pub fn run(self) -> Result<Option<String>> {
+        let bar_info = self
+            .bar
+            .as_ref()
+            .ok_or_else(|| anyhow!("The bar hasn't been created yet"))?;
+        let (conn, screen) = XCBConnection::connect(None)?;
+        let screen = conn
+            .setup()
+            .roots
+            .get(screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?;
+        let root = screen.root;
+        let visual = *find_visual(screen, 24)
+            .ok_or_else(|| anyhow!("Failed to find visual"))?;
+
+        let bar = conn
+            .translate_coordinates(bar_info.window, root, 0, 0)?
+            .reply()?;
+        let bar_height = conn.get_geometry(bar_info.window)?.reply()?.height;
+        let height = bar_info.height;
+        let y = if bar.dst_y as u16 + bar_height / 2
+            < screen.height_in_pixels / 2
+        {
+            bar.dst_y + bar_height as i16
+        } else {
+            bar.dst_y - height as i16
+        };
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            24,
+            window,
+            root,
+            bar.dst_x + self.x,
+            y,
+            self.width,
+            height,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            visual.visual_id,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .event_mask(EventMask::EXPOSURE | EventMask::KEY_PRESS),
+        )?;
+        conn.map_window(window)?;
+        conn.flush()?;
+
+        let result = self.input(&conn, window, visual, height);
+
+        conn.ungrab_keyboard(CURRENT_TIME)?;
+        conn.destroy_window(window)?;
+        conn.flush()?;
+
+        result
+    }
+
+    fn input(
+        &self,
+        conn: &XCBConnection,
+        window: Window,
+        visual: Visualtype,
+        height: u16,
+    ) -> Result<Option<String>> {
+        // the window manager may still be holding the keyboard, so retry for
+        // a short while
+        let mut grabbed = false;
+        for _ in 0..100 {
+            if conn
+                .grab_keyboard(
+                    true,
+                    window,
+                    CURRENT_TIME,
+                    GrabMode::ASYNC,
+                    GrabMode::ASYNC,
+                )?
+                .reply()?
+                .status
+                == GrabStatus::SUCCESS
+            {
+                grabbed = true;
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        if !grabbed {
+            return Err(anyhow!("Failed to grab the keyboard"));
+        }
+
+        let keymap = Keymap::new(conn)?;
+
+        let surface = create_surface(
+            window,
+            visual,
+            i32::from(self.width),
+            i32::from(height),
+            conn,
+        )?;
+        let cr = cairo::Context::new(&surface)?;
+
+        let mut state = State::new(self.initial.as_str(), self.history.len());
+
+        loop {
+            match conn.wait_for_event()? {
+                Event::Expose(_) => {}
+                Event::KeyPress(event) => {
+                    let keysym = keymap.keysym(event.detail, event.state);
+                    let control = event.state.contains(KeyButMask::CONTROL);
+                    match (control, keysym) {
+                        (_, RETURN | KP_ENTER) => {
+                            return Ok(Some(state.text.iter().collect()));
+                        }
+                        (_, ESCAPE) => return Ok(None),
+                        (_, BACKSPACE) => state.backspace(),
+                        (_, DELETE) => state.delete(),
+                        (false, LEFT) => {
+                            state.cursor = state.cursor.saturating_sub(1);
+                        }
+                        (false, RIGHT) => {
+                            state.cursor =
+                                (state.cursor + 1).min(state.text.len());
+                        }
+                        (false, HOME) | (true, 0x61) => state.cursor = 0,
+                        (false, END) | (true, 0x65) => {
+                            state.cursor = state.text.len();
+                        }
+                        (false, UP) => state.history(&self.history, -1),
+                        (false, DOWN) => state.history(&self.history, 1),
+                        (false, TAB) => state.complete(&self.completions),
+                        (true, 0x75) => {
+                            state.text.clear();
+                            state.cursor = 0;
+                        }
+                        (true, 0x77) => state.delete_word(),
+                        (false, keysym) => {
+                            if let Some(c) = to_char(keysym) {
+                                state.insert(c);
+                            }
+                        }
+                        _ => {}
+                    }
+                    if keysym != TAB {
+                        state.completion = None;
+                    }
+                }
+                _ => continue,
+            }
+
+            self.draw(&cr, &state, height)?;
+            surface.flush();
+            conn.flush()?;
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &cairo::Context,
+        state: &State,
+        height: u16,
+    ) -> Result<()> {
+        // this runs outside of the bar's tasks, so the bar is brought along
+        // to draw in its high contrast mode
+        match self.bar.clone() {
+            Some(info) => bar::sync_with_info(info, || {
+                self.draw_contents(cr, state, height)
+            }),
+            None => self.draw_contents(cr, state, height),
+        }
+    }
+
+    fn draw_contents(
+        &self,
+        cr: &cairo::Context,
+        state: &State,
+        height: u16,
+    ) -> Result<()> {
+        let bg = self
+            .bar
+            .as_ref()
+            .map(|info| info.bg.clone())
+            .unwrap_or_default();
+        let bg = high_contrast::or(&bg, &high_contrast::BG);
+        cr.set_source_rgba(bg.r.into(), bg.g.into(), bg.b.into(), 1.0);
+        cr.paint()?;
+
+        let text: String = state.text.iter().collect();
+        let before: String = state.text[..state.cursor].iter().collect();
+
+        let layout = pangocairo::functions::create_layout(cr);
+        self.attrs.apply_font(&layout);
+
+        layout.set_text(format!("{}{before}", self.prompt).as_str());
+        let cursor_x = f64::from(layout.pixel_size().0);
+
+        layout.set_text(format!("{}{text}", self.prompt).as_str());
+        let text_height = f64::from(layout.pixel_size().1);
+        let y = (f64::from(height) - text_height) / 2.0;
+
+        self.attrs.apply_fg(cr);
+        cr.move_to(0.0, y);
+        pangocairo::functions::show_layout(cr, &layout);
+
+        cr.rectangle(cursor_x, y, 1.0, text_height);
+        cr.fill()?;
+
+        Ok(())
+    }
+}
+
+struct State {
+    text: Vec<char>,
+    cursor: usize,
+    history_idx: usize,
+    completion: Option<(String, usize)>,
+}
+
+impl State {
+    fn new(initial: &str, history_len: usize) -> Self {
+        let text: Vec<char> = initial.chars().collect();
+        Self {
+            cursor: text.len(),
+            text,
+            history_idx: history_len,
+            completion: None,
+        }
+    }
+
+    fn insert(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += 1;
+    }
+
+    fn backspace(&mut self) {
+        if self.cursor > 0 {
+            self.cursor -= 1;
+            self.text.remove(self.cursor);
+        }
+    }
+
+    fn delete(&mut self) {
+        if self.cursor < self.text.len() {
+            self.text.remove(self.cursor);
+        }
+    }
+
+    fn delete_word(&mut self) {
+        let end = self.cursor;
+        while self.cursor > 0 && self.text[self.cursor - 1].is_whitespace() {
+            self.cursor -= 1;
+        }
+        while self.cursor > 0 && !self.text[self.cursor - 1].is_whitespace() {
+            self.cursor -= 1;
+        }
+        self.text.drain(self.cursor..end);
+    }
+
+    fn history(&mut self, history: &[String], direction: isize) {
+        let Some(idx) = self.history_idx.checked_add_signed(direction) else {
+            return;
+        };
+        if idx > history.len() {
+            return;
+        }
+        self.history_idx = idx;
+        self.text = history
+            .get(idx)
+            .map(|entry| entry.chars().collect())
+            .unwrap_or_default();
+        self.cursor = self.text.len();
+    }
+
+    fn complete(&mut self, completions: &[String]) {
+        let start = self.text[..self.cursor]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |idx| idx + 1);
+        let (prefix, idx) = self.completion.take().unwrap_or_else(|| {
+            (self.text[start..self.cursor].iter().collect(), 0)
+        });
+
+        let candidates = completions
+            .iter()
+            .filter(|c| c.starts_with(prefix.as_str()))
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            return;
+        }
+        let candidate = candidates[idx % candidates.len()];
+
+        self.text.splice(start..self.cursor, candidate.chars());
+        self.cursor = start + candidate.chars().count();
+        self.completion = Some((prefix, idx + 1));
+    }
+}
+
+/// The keyboard mapping used to translate key presses into keysyms
+enum Keymap {
+    /// The XKB mapping, which follows the active group and shift level
+    Xkb {
+        min_keycode: Keycode,
+        types: Vec<xkb::KeyType>,
+        syms: Vec<xkb::KeySymMap>,
+    },
+    /// The core mapping, for X servers without XKB. Only Shift and Lock are
+    /// taken into account.
+    Core {
+        min_keycode: Keycode,
+        keysyms: Vec<Keysym>,
+        per_keycode: u8,
+    },
+}
+
+impl Keymap {
+    fn new(conn: &XCBConnection) -> Result<Self> {
+        let setup = conn.setup();
+        let min_keycode = setup.min_keycode;
+        let count = setup.max_keycode - min_keycode + 1;
+
+        // once XKB is in use, key events carry the active group in their
+        // state
+        let xkb = conn
+            .xkb_use_extension(1, 0)
+            .ok()
+            .and_then(|cookie| cookie.reply().ok())
+            .is_some_and(|reply| reply.supported);
+        if xkb {
+            let map = conn
+                .xkb_get_map(
+                    xkb::ID::USE_CORE_KBD.into(),
+                    xkb::MapPart::KEY_TYPES | xkb::MapPart::KEY_SYMS,
+                    xkb::MapPart::from(0u16),
+                    0,
+                    0,
+                    min_keycode,
+                    count,
+                    0,
+                    0,
+                    0,
+                    0,
+                    xkb::VMod::from(0u16),
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                    0,
+                )?
+                .reply()?;
+            if let (Some(types), Some(syms)) =
+                (map.map.types_rtrn, map.map.syms_rtrn)
+            {
+                return Ok(Self::Xkb {
+                    min_keycode: map.first_key_sym,
+                    types,
+                    syms,
+                });
+            }
+            log::warn!("Failed to get the XKB keymap; using the core mapping");
+        }
+
+        let mapping = conn.get_keyboard_mapping(min_keycode, count)?.reply()?;
+        Ok(Self::Core {
+            min_keycode,
+            keysyms: mapping.keysyms,
+            per_keycode: mapping.keysyms_per_keycode,
+        })
+    }
+
+    fn keysym(&self, keycode: Keycode, state: KeyButMask) -> Keysym {
+        match self {
+            Self::Xkb {
+                min_keycode,
+                types,
+                syms,
+            } => {
+                let Some(map) = keycode
+                    .checked_sub(*min_keycode)
+                    .and_then(|idx| syms.get(usize::from(idx)))
+                else {
+                    return 0;
+                };
+                let groups = (map.group_info & 0x0f).min(4);
+                if groups == 0 {
+                    return 0;
+                }
+                // groups past the key's last one are wrapped, clamped, or
+                // redirected, as set by the key's group info
+                let group = ((u16::from(state) >> 13) & 0x03) as u8;
+                let group = if group < groups {
+                    group
+                } else {
+                    match map.group_info & 0xc0 {
+                        0x40 => groups - 1,
+                        0x80 => {
+                            let redirect = (map.group_info >> 4) & 0x03;
+                            if redirect < groups {
+                                redirect
+                            } else {
+                                0
+                            }
+                        }
+                        _ => group % groups,
+                    }
+                };
+                let mods = u16::from(state) & 0xff;
+                let level = types
+                    .get(usize::from(map.kt_index[usize::from(group)]))
+                    .and_then(|kind| {
+                        let mods = mods & u16::from(kind.mods_mask);
+                        kind.map
+                            .iter()
+                            .find(|entry| {
+                                entry.active
+                                    && u16::from(entry.mods_mask) == mods
+                            })
+                            .map(|entry| entry.level)
+                    })
+                    .unwrap_or(0);
+                map.syms
+                    .get(
+                        usize::from(group) * usize::from(map.width)
+                            + usize::from(level),
+                    )
+                    .copied()
+                    .unwrap_or(0)
+            }
+            Self::Core {
+                min_keycode,
+                keysyms,
+                per_keycode,
+            } => {
+                let Some(keycode) = keycode.checked_sub(*min_keycode) else {
+                    return 0;
+                };
+                let base = usize::from(keycode) * usize::from(*per_keycode);
+                let lower = keysyms.get(base).copied().unwrap_or_default();
+                let upper = keysyms
+                    .get(base + 1)
+                    .copied()
+                    .filter(|k| *k != 0)
+                    .unwrap_or(lower);
+
+                let shift = state.contains(KeyButMask::SHIFT);
+                let lock = state.contains(KeyButMask::LOCK)
+                    && to_char(lower).is_some_and(char::is_alphabetic);
+                if shift ^ lock {
+                    upper
+                } else {
+                    lower
+                }
+            }
+        }
+    }
+}
+
+fn to_char(keysym: Keysym) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        0x0100_0000..=0x0110_ffff => char::from_u32(keysym - 0x0100_0000),
+        _ => None,
+    }
+}
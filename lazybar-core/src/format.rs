@@ -0,0 +1,543 @@
+use std::{borrow::Cow, iter::Peekable, str::Chars};
+
+use anyhow::{anyhow, Result};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+lazy_static! {
+    // block markers come first so that they're matched and kept as they are
+    // instead of being read as an expression along with a `%` after them
+    static ref EXPR: Regex = Regex::new(concat!(
+        r"%\{(?:(?:highlight|span)\s[^}]*|end(?:highlight|span))\}",
+        r"|%\{(?<expr>[^}]*)\}%"
+    ))
+    .unwrap();
+    static ref MODIFIED: Regex =
+        Regex::new(r"%(?<name>\w+):(?<mods>[^%\s]*)%").unwrap();
+    static ref CONDITIONAL: Regex =
+        Regex::new(r"%\{(?:if\s+(?<cond>[^}]*)|(?<else>else)|endif)\}")
+            .unwrap();
+    static ref BLOCK: Regex = Regex::new(concat!(
+        r"%\{(?:(?<kind>highlight|span)\s+(?<name>[^}\s]+)\s*",
+        r"|end(?<end>highlight|span))\}"
+    ))
+    .unwrap();
+}
+
+/// The units for `unit=iec`, in powers of 1024
+const IEC_UNITS: &[&str] = &["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+/// The units for `unit=si`, in powers of 1000
+const SI_UNITS: &[&str] = &["B", "kB", "MB", "GB", "TB", "PB"];
+
+/// Processes the parts of a format string that are shared by all panels. This
+/// runs after a panel has replaced its own tokens.
+///
+/// First, conditional blocks of the form `%{if <cond>}...%{endif}` are kept
+/// or removed, with an optional `%{else}` in between. Blocks can be nested.
+/// A condition is true if it isn't empty, `0`, or `false`, so that
+/// `%{if %artist%}%artist% - %{endif}` leaves out the separator when there is
+/// no artist. Conditions can also compare two values with `==`, `!=`, `<`,
+/// `>`, `<=`, or `>=`, and be negated with a leading `!`, e.g.
+/// `%{if %percentage% < 20}low%{else}ok%{endif}`. Values that are both
+/// numbers are compared as numbers, and any others as text (only with `==`
+/// and `!=`). A format string with unbalanced blocks is left as-is.
+///
+/// Then, this evaluates inline expressions of the form `%{ <expr> }%`.
+/// An expression supports numbers, parentheses, `+`, `-`, `*`, `/`, `%`, and
+/// `^`, along with the functions `round`, `floor`, `ceil`, `abs`, `min`,
+/// `max`, and `clamp`. Functions can be called directly (`min(1, 2)`) or at
+/// the end of an expression with a pipe, in which case the expression is
+/// passed as the first argument (`%rx_rate% / 1024 | round(1)`). `round`
+/// takes an optional number of decimal places, which also determines how the
+/// result is printed.
+///
+/// Expressions that fail to evaluate (e.g. because a token was replaced with
+/// something that isn't a number) are left as-is.
+#[must_use]
+pub fn process(text: &str) -> Cow<'_, str> {
+    match conditionals(text) {
+        Cow::Borrowed(text) => expressions(text),
+        Cow::Owned(text) => Cow::Owned(expressions(text.as_str()).into_owned()),
+    }
+}
+
+fn conditionals(text: &str) -> Cow<'_, str> {
+    if !text.contains("%{if") {
+        return Cow::Borrowed(text);
+    }
+
+    // for each open block: whether its parent is shown, and whether the
+    // block is currently in a shown branch
+    let mut stack: Vec<(bool, bool)> = Vec::new();
+    let mut output = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in CONDITIONAL.captures_iter(text) {
+        let token = caps.get(0).unwrap();
+        let shown = stack.last().map_or(true, |(_, shown)| *shown);
+        if shown {
+            output.push_str(&text[last..token.start()]);
+        }
+        last = token.end();
+
+        if let Some(cond) = caps.name("cond") {
+            stack.push((shown, shown && condition(cond.as_str())));
+        } else if caps.name("else").is_some() {
+            let Some((parent, branch)) = stack.last_mut() else {
+                log::warn!("Unmatched %{{else}} in {text}");
+                return Cow::Borrowed(text);
+            };
+            *branch = *parent && !*branch;
+        } else if stack.pop().is_none() {
+            log::warn!("Unmatched %{{endif}} in {text}");
+            return Cow::Borrowed(text);
+        }
+    }
+    if !stack.is_empty() {
+        log::warn!("Unclosed %{{if}} in {text}");
+        return Cow::Borrowed(text);
+    }
+    output.push_str(&text[last..]);
+    Cow::Owned(output)
+}
+
+fn condition(cond: &str) -> bool {
+    let cond = cond.trim();
+    if let Some(cond) = cond.strip_prefix('!') {
+        return !condition(cond);
+    }
+
+    for op in ["==", "!=", "<=", ">=", "<", ">"] {
+        let Some((lhs, rhs)) = cond.split_once(op) else {
+            continue;
+        };
+        let (lhs, rhs) = (lhs.trim(), rhs.trim());
+        return match (lhs.parse::<f64>(), rhs.parse::<f64>()) {
+            (Ok(lhs), Ok(rhs)) => match op {
+                "==" => (lhs - rhs).abs() < f64::EPSILON,
+                "!=" => (lhs - rhs).abs() >= f64::EPSILON,
+                "<=" => lhs <= rhs,
+                ">=" => lhs >= rhs,
+                "<" => lhs < rhs,
+                _ => lhs > rhs,
+            },
+            _ => match op {
+                "==" => lhs == rhs,
+                "!=" => lhs != rhs,
+                _ => false,
+            },
+        };
+    }
+
+    !(cond.is_empty() || cond == "0" || cond.eq_ignore_ascii_case("false"))
+}
+
+/// Replaces a numeric token in a format string. `%<name>%` is replaced with
+/// `default`, which is how the panel shows the value on its own, and
+/// `%<name>:<modifiers>%` is replaced with `value` formatted according to
+/// the modifiers, which are separated by semicolons:
+/// - `.<n>`: show `n` decimal places, e.g. `%percentage:.1%`.
+/// - `unit=iec` or `unit=si`: treat the value as a number of bytes and show
+///   it with a unit suffix, in powers of 1024 (`KiB`, `MiB`, ...) or 1000
+///   (`kB`, `MB`, ...). Defaults to one decimal place.
+/// - `pad=<n>`: pad the result with spaces on the left to at least `n`
+///   characters, so that the panel doesn't change width with the value.
+///   `pad=0<n>` pads with zeros instead.
+///
+/// For example, `%rx:.1;unit=iec%` or `%percentage:pad=3%`.
+#[must_use]
+pub fn replace_number(
+    text: &str,
+    name: &str,
+    value: f64,
+    default: &str,
+) -> String {
+    let text = text.replace(format!("%{name}%").as_str(), default);
+    if !text.contains(format!("%{name}:").as_str()) {
+        return text;
+    }
+    MODIFIED
+        .replace_all(text.as_str(), |caps: &Captures| {
+            if &caps["name"] == name {
+                modify(value, &caps["mods"])
+            } else {
+                caps[0].to_string()
+            }
+        })
+        .into_owned()
+}
+
+fn modify(value: f64, modifiers: &str) -> String {
+    let mut precision = None;
+    let mut unit = None;
+    let mut pad = None;
+    for modifier in modifiers.split(';').filter(|m| !m.is_empty()) {
+        if let Some(digits) = modifier.strip_prefix('.') {
+            precision = digits.parse::<usize>().ok();
+            continue;
+        }
+        match modifier.split_once('=') {
+            Some(("unit", "iec")) => unit = Some((1024.0, IEC_UNITS)),
+            Some(("unit", "si")) => unit = Some((1000.0, SI_UNITS)),
+            Some(("pad", width)) => pad = Some(width),
+            _ => log::warn!("Unknown format modifier {modifier}"),
+        }
+    }
+
+    let mut text = match unit {
+        Some((base, units)) => {
+            let mut value = value;
+            let mut idx = 0;
+            while value.abs() >= base && idx < units.len() - 1 {
+                value /= base;
+                idx += 1;
+            }
+            let precision = precision.unwrap_or(1);
+            format!("{value:.precision$}{}", units[idx])
+        }
+        None => match precision {
+            Some(precision) => format!("{value:.precision$}"),
+            None => value.to_string(),
+        },
+    };
+
+    if let Some(pad) = pad {
+        let width = pad.parse::<usize>().unwrap_or_default();
+        let len = text.chars().count();
+        if len < width {
+            let fill = if pad.starts_with('0') { '0' } else { ' ' };
+            // zeros go after the sign, like `{:0w}`
+            let at = usize::from(fill == '0' && text.starts_with('-'));
+            text.insert_str(at, fill.to_string().repeat(width - len).as_str());
+        }
+    }
+    text
+}
+
+/// What a `%{<kind> <name>}...%{end<kind>}` block in a panel's text marks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum BlockKind {
+    /// Part of the text drawn with its own [`Highlight`][crate::Highlight]
+    Highlight,
+    /// Part of the text that can be clicked on its own
+    Span,
+}
+
+impl BlockKind {
+    fn parse(name: &str) -> Self {
+        if name == "span" {
+            Self::Span
+        } else {
+            Self::Highlight
+        }
+    }
+
+    /// The characters that stand in for the start and end of a block while
+    /// the markup is parsed
+    const fn markers(self) -> (char, char) {
+        match self {
+            Self::Highlight => ('\u{e000}', '\u{e001}'),
+            Self::Span => ('\u{e002}', '\u{e003}'),
+        }
+    }
+}
+
+/// A block of a panel's text, found by [`extract_blocks`].
+#[derive(Debug, Clone)]
+pub(crate) struct Block {
+    pub kind: BlockKind,
+    pub name: String,
+    /// The byte indices of the start and end of the block
+    pub range: (i32, i32),
+}
+
+/// Removes each `%{highlight <name>}`, `%{span <name>}`, `%{endhighlight}`,
+/// and `%{endspan}` marker from some markup, returning the markup and the
+/// blocks of its text that the markers enclosed. Blocks can be nested, and
+/// are returned with outer blocks before the ones inside them. Unbalanced
+/// markers are logged and left out.
+///
+/// The ranges are byte indices into the text of a [`pango::Layout`] whose
+/// markup is set to the returned markup.
+pub(crate) fn extract_blocks(markup: &str) -> (String, Vec<Block>) {
+    if !markup.contains("%{") {
+        return (markup.to_string(), Vec::new());
+    }
+
+    let mut names = Vec::new();
+    let marked = BLOCK.replace_all(markup, |caps: &Captures| {
+        if let Some(kind) = caps.name("kind") {
+            names.push(caps["name"].to_string());
+            BlockKind::parse(kind.as_str()).markers().0.to_string()
+        } else {
+            BlockKind::parse(&caps["end"]).markers().1.to_string()
+        }
+    });
+    let markers = [BlockKind::Highlight, BlockKind::Span]
+        .into_iter()
+        .flat_map(|kind| {
+            let (start, end) = kind.markers();
+            [(start, kind, true), (end, kind, false)]
+        })
+        .collect::<Vec<_>>();
+    let cleaned =
+        marked.replace(|c: char| markers.iter().any(|(m, _, _)| *m == c), "");
+    if cleaned.len() == marked.len() {
+        return (cleaned, Vec::new());
+    }
+    let text = match pango::parse_markup(marked.as_ref(), '\0') {
+        Ok((_, text, _)) => text,
+        Err(e) => {
+            log::warn!("Failed to parse markup {markup}: {e}");
+            return (cleaned, Vec::new());
+        }
+    };
+
+    let mut names = names.into_iter();
+    let mut open: Vec<(BlockKind, String, i32)> = Vec::new();
+    let mut blocks = Vec::new();
+    // the markers are removed from the final text, so each index has to
+    // skip the ones before it
+    let mut removed = 0;
+    for (idx, c) in text.char_indices() {
+        let Some(&(_, kind, is_start)) =
+            markers.iter().find(|(marker, _, _)| *marker == c)
+        else {
+            continue;
+        };
+        let idx = (idx - removed) as i32;
+        removed += c.len_utf8();
+        if is_start {
+            open.push((kind, names.next().unwrap_or_default(), idx));
+            continue;
+        }
+        match open.iter().rposition(|(open, _, _)| *open == kind) {
+            Some(pos) => {
+                let (kind, name, start) = open.remove(pos);
+                blocks.push(Block {
+                    kind,
+                    name,
+                    range: (start, idx),
+                });
+            }
+            None => log::warn!("Unbalanced end of block in {markup}"),
+        }
+    }
+    if !open.is_empty() {
+        log::warn!("Unbalanced start of block in {markup}");
+    }
+    blocks.sort_by_key(|block| (block.range.0, -block.range.1));
+    (cleaned, blocks)
+}
+
+fn expressions(text: &str) -> Cow<'_, str> {
+    EXPR.replace_all(text, |caps: &Captures| {
+        let Some(expr) = caps.name("expr").map(|expr| expr.as_str()) else {
+            return caps[0].to_string();
+        };
+        evaluate(expr).unwrap_or_else(|e| {
+            log::warn!("Failed to evaluate expression {expr}: {e}");
+            caps[0].to_string()
+        })
+    })
+}
+
+fn evaluate(expr: &str) -> Result<String> {
+    let mut parser = Parser {
+        chars: expr.chars().peekable(),
+    };
+    let mut value = parser.expr()?;
+    let mut precision = None;
+
+    while parser.eat('|') {
+        let name = parser.ident()?;
+        let mut args = vec![value];
+        if parser.eat('(') && !parser.eat(')') {
+            args.extend(parser.args()?);
+        }
+        if let Some(p) = output_precision(name.as_str(), args.as_slice()) {
+            precision = Some(p);
+        }
+        value = call(name.as_str(), args.as_slice())?;
+    }
+
+    parser.skip_whitespace();
+    if let Some(c) = parser.chars.next() {
+        return Err(anyhow!("Unexpected character {c}"));
+    }
+
+    Ok(match precision {
+        Some(precision) => format!("{value:.precision$}"),
+        None if value.fract() == 0.0 => format!("{value:.0}"),
+        None => value.to_string(),
+    })
+}
+
+fn output_precision(name: &str, args: &[f64]) -> Option<usize> {
+    match name {
+        "round" => Some(args.get(1).copied().unwrap_or_default() as usize),
+        "floor" | "ceil" => Some(0),
+        _ => None,
+    }
+}
+
+fn call(name: &str, args: &[f64]) -> Result<f64> {
+    let arg = |idx: usize| {
+        args.get(idx)
+            .copied()
+            .ok_or_else(|| anyhow!("Missing argument {idx} to {name}"))
+    };
+
+    Ok(match name {
+        "round" => {
+            let factor =
+                10f64.powi(args.get(1).copied().unwrap_or_default() as i32);
+            (arg(0)? * factor).round() / factor
+        }
+        "floor" => arg(0)?.floor(),
+        "ceil" => arg(0)?.ceil(),
+        "abs" => arg(0)?.abs(),
+        "min" => arg(0)?.min(arg(1)?),
+        "max" => arg(0)?.max(arg(1)?),
+        "clamp" => arg(0)?.clamp(arg(1)?, arg(2)?),
+        _ => return Err(anyhow!("Unknown function {name}")),
+    })
+}
+
+struct Parser<'a> {
+    chars: Peekable<Chars<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&c).is_some()
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        self.skip_whitespace();
+        let mut ident = String::new();
+        while let Some(c) = self.chars.next_if(char::is_ascii_alphabetic) {
+            ident.push(c);
+        }
+        if ident.is_empty() {
+            Err(anyhow!("Expected a function name"))
+        } else {
+            Ok(ident)
+        }
+    }
+
+    fn args(&mut self) -> Result<Vec<f64>> {
+        let mut args = vec![self.expr()?];
+        while self.eat(',') {
+            args.push(self.expr()?);
+        }
+        if self.eat(')') {
+            Ok(args)
+        } else {
+            Err(anyhow!("Expected )"))
+        }
+    }
+
+    fn expr(&mut self) -> Result<f64> {
+        let mut value = self.term()?;
+        loop {
+            if self.eat('+') {
+                value += self.term()?;
+            } else if self.eat('-') {
+                value -= self.term()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn term(&mut self) -> Result<f64> {
+        let mut value = self.factor()?;
+        loop {
+            if self.eat('*') {
+                value *= self.factor()?;
+            } else if self.eat('/') {
+                value /= self.factor()?;
+            } else if self.eat('%') {
+                value %= self.factor()?;
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn factor(&mut self) -> Result<f64> {
+        if self.eat('-') {
+            return Ok(-self.factor()?);
+        }
+        let value = self.primary()?;
+        if self.eat('^') {
+            Ok(value.powf(self.factor()?))
+        } else {
+            Ok(value)
+        }
+    }
+
+    fn primary(&mut self) -> Result<f64> {
+        if self.eat('(') {
+            let value = self.expr()?;
+            return if self.eat(')') {
+                Ok(value)
+            } else {
+                Err(anyhow!("Expected )"))
+            };
+        }
+
+        self.skip_whitespace();
+        match self.chars.peek() {
+            Some(c) if c.is_ascii_alphabetic() => {
+                let name = self.ident()?;
+                if self.eat('(') {
+                    let args = self.args()?;
+                    call(name.as_str(), args.as_slice())
+                } else {
+                    Err(anyhow!("Expected ( after {name}"))
+                }
+            }
+            Some(c) if c.is_ascii_digit() || *c == '.' => {
+                let mut number = String::new();
+                while let Some(c) =
+                    self.chars.next_if(|c| c.is_ascii_digit() || *c == '.')
+                {
+                    number.push(c);
+                }
+                Ok(number.parse()?)
+            }
+            Some(c) => Err(anyhow!("Unexpected character {c}")),
+            None => Err(anyhow!("Unexpected end of expression")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::process;
+
+    #[test]
+    fn evaluates_expressions() {
+        assert_eq!(process("%{1 + 2}%"), "3");
+        assert_eq!(process("a %{10 / 4 | round(1)}% b"), "a 2.5 b");
+    }
+
+    #[test]
+    fn leaves_block_markers_alone() {
+        assert_eq!(
+            process("%{highlight hot}50%{endhighlight}%"),
+            "%{highlight hot}50%{endhighlight}%"
+        );
+        assert_eq!(
+            process("%{span up}%{1 + 1}%%{endspan}"),
+            "%{span up}2%{endspan}"
+        );
+        assert_eq!(process("%{endspan}%{2 * 3}%"), "%{endspan}6");
+    }
+}
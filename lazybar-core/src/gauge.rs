@@ -0,0 +1,135 @@
+use std::{collections::HashMap, f64::consts::PI, hash::BuildHasher};
+
+use anyhow::Result;
+use config::Value;
+use csscolorparser::Color;
+
+use crate::{
+    high_contrast, remove_color_from_config, remove_float_from_config,
+    remove_uint_from_config, Attrs,
+};
+
+/// A ring that fills up with a panel's value, with the panel's text in the
+/// middle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gauge {
+    /// The diameter in pixels of the ring, or the height of the bar if unset
+    pub size: Option<i32>,
+    /// The width in pixels of the ring
+    pub thickness: f64,
+    /// Where the ring starts, in degrees clockwise from the top
+    pub start_angle: f64,
+    /// How far around the ring goes when it's full, in degrees
+    pub sweep: f64,
+    /// The color of the filled part of the ring, or the panel's foreground if
+    /// unset
+    pub color: Option<Color>,
+    /// The color of the empty part of the ring, if it's drawn
+    pub bg: Option<Color>,
+}
+
+impl Gauge {
+    /// Parses a gauge from a panel's table.
+    ///
+    /// Configuration options:
+    /// - `gauge_size`: the diameter in pixels of the gauge. Defaults to the
+    ///   height of the bar.
+    /// - `gauge_thickness`: the width in pixels of the ring. Defaults to 3.
+    /// - `gauge_start_angle`: where the ring starts, in degrees clockwise
+    ///   from the top. Defaults to 0.
+    /// - `gauge_sweep`: how far around the ring goes when the value is at
+    ///   its maximum, in degrees. Defaults to 360. A sweep of 270 with a
+    ///   start angle of 225 makes a speedometer with a gap at the bottom.
+    /// - `gauge_color`: the color of the filled part. Defaults to the
+    ///   foreground color of the panel's attrs. See [`csscolorparser::parse`].
+    /// - `gauge_bg`: the color of the empty part. Defaults to none.
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        Self {
+            size: remove_uint_from_config("gauge_size", table)
+                .map(|size| size as i32),
+            thickness: remove_float_from_config("gauge_thickness", table)
+                .unwrap_or(3.0)
+                .max(0.0),
+            start_angle: remove_float_from_config("gauge_start_angle", table)
+                .unwrap_or_default(),
+            sweep: remove_float_from_config("gauge_sweep", table)
+                .unwrap_or(360.0)
+                .clamp(0.0, 360.0),
+            color: remove_color_from_config("gauge_color", table),
+            bg: remove_color_from_config("gauge_bg", table),
+        }
+    }
+
+    /// Returns the diameter of the gauge on a bar of the given height.
+    #[must_use]
+    pub fn diameter(&self, height: i32) -> i32 {
+        self.size.map_or(height, |size| size.min(height))
+    }
+
+    /// Draws the ring filled to `level` (from 0 to 1), centered on `center`.
+    pub(crate) fn draw(
+        &self,
+        cr: &cairo::Context,
+        attrs: &Attrs,
+        center: (f64, f64),
+        height: i32,
+        level: f64,
+    ) -> Result<()> {
+        let radius = (f64::from(self.diameter(height)) - self.thickness) / 2.0;
+        if radius <= 0.0 {
+            return Ok(());
+        }
+        // cairo measures angles clockwise from the right
+        let start = (self.start_angle - 90.0).to_radians();
+        let sweep = self.sweep.to_radians();
+        let level = if level.is_finite() {
+            level.clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        let filled = sweep * level;
+
+        cr.save()?;
+        cr.set_line_width(self.thickness);
+
+        if let Some(ref bg) = self.bg {
+            let bg = high_contrast::or(bg, &high_contrast::BG);
+            cr.set_source_rgba(
+                bg.r.into(),
+                bg.g.into(),
+                bg.b.into(),
+                bg.a.into(),
+            );
+            cr.new_sub_path();
+            cr.arc(center.0, center.1, radius, start + filled, start + sweep);
+            cr.stroke()?;
+        }
+
+        if filled > 0.0 {
+            match self.color {
+                Some(ref color) => {
+                    let color = high_contrast::or(color, &high_contrast::FG);
+                    cr.set_source_rgba(
+                        color.r.into(),
+                        color.g.into(),
+                        color.b.into(),
+                        color.a.into(),
+                    );
+                }
+                None => attrs.apply_fg(cr),
+            }
+            cr.new_sub_path();
+            if filled >= 2.0 * PI {
+                cr.arc(center.0, center.1, radius, 0.0, 2.0 * PI);
+            } else {
+                cr.arc(center.0, center.1, radius, start, start + filled);
+            }
+            cr.stroke()?;
+        }
+
+        cr.restore()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::BuildHasher,
+};
+
+use anyhow::Result;
+use config::Value;
+use csscolorparser::Color;
+
+use crate::{
+    high_contrast, remove_color_from_config, remove_float_from_config,
+    remove_uint_from_config, Attrs,
+};
+
+/// A sparkline of a panel's recent values, drawn in place of its text. Each
+/// value is a column whose height is relative to the graph's scale, with
+/// the newest on the right.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    /// The width in pixels of the graph
+    pub width: i32,
+    /// The height in pixels of the graph, or the height of the bar if unset
+    pub height: Option<i32>,
+    /// The color of the columns, or the panel's foreground if unset
+    pub color: Option<Color>,
+    /// The color drawn behind the columns, if any
+    pub bg: Option<Color>,
+    /// The value drawn as an empty column
+    pub min: f64,
+    /// The value drawn as a full column, or the largest value in the graph
+    /// if unset
+    pub max: Option<f64>,
+    /// How many values are kept
+    pub samples: usize,
+    history: VecDeque<Option<f64>>,
+}
+
+impl Graph {
+    /// Parses a graph from a panel's table.
+    ///
+    /// Configuration options:
+    /// - `graph_width`: the width in pixels of the graph. Defaults to 40.
+    /// - `graph_height`: the height in pixels of the graph, which is centered
+    ///   vertically. Defaults to the height of the bar.
+    /// - `graph_color`: the color of the graph. Defaults to the foreground
+    ///   color of the panel's attrs. See [`csscolorparser::parse`].
+    /// - `graph_bg`: the color behind the graph. Defaults to none.
+    /// - `graph_min`: the value at the bottom of the graph. Defaults to 0.
+    /// - `graph_max`: the value at the top of the graph. Defaults to the
+    ///   largest value in the graph, so that the graph shows relative
+    ///   changes.
+    /// - `graph_samples`: how many of the most recent values are drawn. The
+    ///   columns are spread evenly across the width of the graph. Defaults
+    ///   to 20.
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let width = remove_uint_from_config("graph_width", table);
+        let height = remove_uint_from_config("graph_height", table);
+        let color = remove_color_from_config("graph_color", table);
+        let bg = remove_color_from_config("graph_bg", table);
+        let min = remove_float_from_config("graph_min", table);
+        let max = remove_float_from_config("graph_max", table);
+        let samples = remove_uint_from_config("graph_samples", table);
+
+        let samples = samples.unwrap_or(20).max(1) as usize;
+        Self {
+            width: width.unwrap_or(40) as i32,
+            height: height.map(|h| h as i32),
+            color,
+            bg,
+            min: min.unwrap_or_default(),
+            max,
+            samples,
+            history: VecDeque::with_capacity(samples),
+        }
+    }
+
+    /// Adds a value to the graph, dropping the oldest one if the graph is
+    /// full. [`None`] leaves a gap, e.g. for a lost ping.
+    pub fn push(&mut self, value: Option<f64>) {
+        if self.history.len() == self.samples {
+            self.history.pop_front();
+        }
+        self.history.push_back(value);
+    }
+
+    /// Draws the graph with its top left corner at (0, 0) of a panel with the
+    /// given height.
+    pub(crate) fn draw(
+        &self,
+        cr: &cairo::Context,
+        attrs: &Attrs,
+        height: i32,
+    ) -> Result<()> {
+        let graph_height = self.height.map_or(height, |h| h.min(height));
+        let (width, graph_height) =
+            (f64::from(self.width), f64::from(graph_height));
+
+        cr.save()?;
+        cr.translate(0.0, (f64::from(height) - graph_height) / 2.0);
+
+        if let Some(ref bg) = self.bg {
+            let bg = high_contrast::or(bg, &high_contrast::BG);
+            cr.set_source_rgba(
+                bg.r.into(),
+                bg.g.into(),
+                bg.b.into(),
+                bg.a.into(),
+            );
+            cr.rectangle(0.0, 0.0, width, graph_height);
+            cr.fill()?;
+        }
+
+        let max = self.max.unwrap_or_else(|| {
+            self.history
+                .iter()
+                .flatten()
+                .copied()
+                .fold(self.min, f64::max)
+        });
+        let range = max - self.min;
+        if range > 0.0 {
+            match self.color {
+                Some(ref color) => {
+                    let color = high_contrast::or(color, &high_contrast::FG);
+                    cr.set_source_rgba(
+                        color.r.into(),
+                        color.g.into(),
+                        color.b.into(),
+                        color.a.into(),
+                    );
+                }
+                None => attrs.apply_fg(cr),
+            }
+
+            let step = width / self.samples as f64;
+            // right-align the values so that a new graph fills in from the
+            // right like a full one scrolls
+            let start = self.samples - self.history.len();
+            for (idx, value) in self.history.iter().enumerate() {
+                let Some(value) = value else {
+                    continue;
+                };
+                let level = ((value - self.min) / range).clamp(0.0, 1.0);
+                cr.rectangle(
+                    (start + idx) as f64 * step,
+                    graph_height * (1.0 - level),
+                    step,
+                    graph_height * level,
+                );
+            }
+            cr.fill()?;
+        }
+
+        cr.restore()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::atomic::Ordering;
+
+use csscolorparser::Color;
+
+use crate::bar;
+
+/// The color of text in high contrast mode.
+pub const FG: Color = Color::new(1.0, 1.0, 1.0, 1.0);
+/// The color of the bar and panel backgrounds in high contrast mode.
+pub const BG: Color = Color::new(0.0, 0.0, 0.0, 1.0);
+/// The color of highlights in high contrast mode.
+pub const ACCENT: Color = Color::new(1.0, 1.0, 0.0, 1.0);
+
+/// Returns whether high contrast mode is enabled for the bar that the current
+/// task belongs to (see [`bar::bar_info`]). Always `false` outside of a bar's
+/// tasks.
+#[must_use]
+pub fn enabled() -> bool {
+    bar::with_current_info(|info| info.high_contrast.load(Ordering::Relaxed))
+        .unwrap_or(false)
+}
+
+/// Enables or disables high contrast mode for the bar that the current task
+/// belongs to. The bar needs to be redrawn for this to take effect.
+pub fn set(enabled: bool) {
+    bar::with_current_info(|info| {
+        info.high_contrast.store(enabled, Ordering::Relaxed);
+    });
+}
+
+/// Returns `replacement` in high contrast mode, otherwise `color`.
+#[must_use]
+pub fn or<'a>(color: &'a Color, replacement: &'a Color) -> &'a Color {
+    if enabled() {
+        replacement
+    } else {
+        color
+    }
+}
+
+/// Returns a thicker line height in high contrast mode. Lines that aren't
+/// drawn at all stay that way.
+#[must_use]
+pub fn thicken(height: f64) -> f64 {
+    if enabled() && height > 0.0 {
+        (height * 2.0).max(3.0)
+    } else {
+        height
+    }
+}
+
+/// Returns a copy of `attrs` without any of the colors set by markup.
+pub(crate) fn strip_colors(attrs: &pango::AttrList) -> pango::AttrList {
+    let attrs = attrs.copy().unwrap_or_else(pango::AttrList::new);
+    attrs.filter(|attr| {
+        matches!(
+            attr.type_(),
+            pango::AttrType::Foreground
+                | pango::AttrType::Background
+                | pango::AttrType::ForegroundAlpha
+                | pango::AttrType::BackgroundAlpha
+                | pango::AttrType::UnderlineColor
+                | pango::AttrType::OverlineColor
+                | pango::AttrType::StrikethroughColor
+        )
+    });
+    attrs
+}
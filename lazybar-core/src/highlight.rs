@@ -1,7 +1,36 @@
+use std::borrow::Cow;
+
 use anyhow::Result;
 use csscolorparser::Color;
 
-use crate::{parser, remove_color_from_config, remove_float_from_config};
+use crate::{
+    format::{Block, BlockKind},
+    high_contrast, parser, remove_color_from_config, remove_float_from_config,
+    style,
+};
+
+/// What a panel's background and [`Highlight`] cover.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum HighlightScope {
+    /// Only the text, leaving the panel's padding uncovered.
+    Text,
+    /// The full width of the panel, including its padding.
+    #[default]
+    Panel,
+}
+
+impl HighlightScope {
+    /// Parses an instance from its name (`text` or `panel`).
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Self::Text),
+            "panel" => Some(Self::Panel),
+            _ => None,
+        }
+    }
+}
 
 /// Describes a bar to be drawn below a workspace name
 #[derive(Clone, Debug, Default, PartialEq, PartialOrd)]
@@ -14,6 +43,9 @@ pub struct Highlight {
     pub underline_height: f64,
     /// the color of the bottom highlight
     pub underline_color: Color,
+    /// The name this instance was parsed from, used to look it up again when
+    /// the styles are reloaded.
+    name: Option<String>,
 }
 
 impl Highlight {
@@ -28,6 +60,7 @@ impl Highlight {
             overline_color: Color::new(0.0, 0.0, 0.0, 1.0),
             underline_height: 0.0,
             underline_color: Color::new(0.0, 0.0, 0.0, 1.0),
+            name: None,
         }
     }
 
@@ -44,9 +77,21 @@ impl Highlight {
             overline_color,
             underline_height,
             underline_color,
+            name: None,
         }
     }
 
+    /// Returns this instance as it would be parsed from the current styles if
+    /// they have been reloaded (see [`style::reload`]).
+    #[must_use]
+    pub fn restyled(&self) -> Cow<'_, Self> {
+        self.name
+            .as_ref()
+            .filter(|_| style::reloaded())
+            .and_then(|name| style::cached(name, Self::parse))
+            .map_or(Cow::Borrowed(self), Cow::Owned)
+    }
+
     /// Draws the {over,under}lines associated with this highlight.
     ///
     /// The current point of `cr` should have the same x coordinate as the left
@@ -59,29 +104,36 @@ impl Highlight {
     ) -> Result<()> {
         cr.save()?;
 
-        if self.overline_height > 0.0 {
-            cr.rectangle(0.0, 0.0, width, self.overline_height);
+        let overline_height = high_contrast::thicken(self.overline_height);
+        let underline_height = high_contrast::thicken(self.underline_height);
+        let overline_color =
+            high_contrast::or(&self.overline_color, &high_contrast::ACCENT);
+        let underline_color =
+            high_contrast::or(&self.underline_color, &high_contrast::ACCENT);
+
+        if overline_height > 0.0 {
+            cr.rectangle(0.0, 0.0, width, overline_height);
             cr.set_source_rgba(
-                self.overline_color.r.into(),
-                self.overline_color.g.into(),
-                self.overline_color.b.into(),
-                self.overline_color.a.into(),
+                overline_color.r.into(),
+                overline_color.g.into(),
+                overline_color.b.into(),
+                overline_color.a.into(),
             );
             cr.fill()?;
         }
 
-        if self.underline_height > 0.0 {
+        if underline_height > 0.0 {
             cr.rectangle(
                 0.0,
-                bar_height - self.underline_height,
+                bar_height - underline_height,
                 width,
-                self.underline_height,
+                underline_height,
             );
             cr.set_source_rgba(
-                self.underline_color.r.into(),
-                self.underline_color.g.into(),
-                self.underline_color.b.into(),
-                self.underline_color.a.into(),
+                underline_color.r.into(),
+                underline_color.g.into(),
+                underline_color.b.into(),
+                underline_color.a.into(),
             );
             cr.fill()?;
         }
@@ -91,6 +143,31 @@ impl Highlight {
         Ok(())
     }
 
+    /// Draws the {over,under}lines under the part of `layout` between two
+    /// byte indices of its text. `cr` should be positioned where the layout
+    /// is shown, and `top` is the y coordinate of the top of the bar.
+    fn draw_range(
+        &self,
+        cr: &cairo::Context,
+        layout: &pango::Layout,
+        range: (i32, i32),
+        top: f64,
+        bar_height: f64,
+    ) -> Result<()> {
+        let (_, start) = layout.index_to_line_x(range.0, false);
+        let (_, end) = layout.index_to_line_x(range.1, false);
+        let (start, end) = (start.min(end), start.max(end));
+        cr.save()?;
+        cr.translate(f64::from(start) / f64::from(pango::SCALE), top);
+        self.draw(
+            cr,
+            bar_height,
+            f64::from(end - start) / f64::from(pango::SCALE),
+        )?;
+        cr.restore()?;
+        Ok(())
+    }
+
     /// Parses a new instance from a subset of the global
     /// [`Config`][config::Config]
     ///
@@ -104,7 +181,7 @@ impl Highlight {
     ///   - type: String
     ///   - default: none
     pub fn parse(name: impl AsRef<str>) -> Option<Self> {
-        let highlights_table = parser::HIGHLIGHTS.get().unwrap();
+        let highlights_table = parser::HIGHLIGHTS.read().unwrap();
         let mut highlight_table = highlights_table
             .get(name.as_ref())?
             .clone()
@@ -142,6 +219,59 @@ impl Highlight {
             overline_color,
             underline_height,
             underline_color,
+            name: Some(name.as_ref().to_owned()),
         })
     }
 }
+
+/// A [`Highlight`] drawn under part of a panel's text, from a
+/// `%{highlight <name>}...%{endhighlight}` block in its format string.
+#[derive(Debug, Clone)]
+pub(crate) struct Segment {
+    highlight: Highlight,
+    start: i32,
+    end: i32,
+}
+
+/// Looks up the highlight for each `%{highlight <name>}` block (see
+/// [`format::extract_blocks`][crate::format::extract_blocks]). Unknown
+/// highlights are logged and left out.
+pub(crate) fn segments(blocks: &[Block]) -> Vec<Segment> {
+    blocks
+        .iter()
+        .filter(|block| block.kind == BlockKind::Highlight)
+        .filter_map(|block| {
+            let Some(highlight) = Highlight::parse(block.name.as_str()) else {
+                log::warn!("Couldn't find highlight {}", block.name);
+                return None;
+            };
+            Some(Segment {
+                highlight,
+                start: block.range.0,
+                end: block.range.1,
+            })
+        })
+        .collect()
+}
+
+/// Draws each segment's highlight under its part of `layout`. `cr` should be
+/// positioned where the layout is shown, and `top` is the y coordinate of
+/// the top of the bar.
+pub(crate) fn draw_segments(
+    cr: &cairo::Context,
+    layout: &pango::Layout,
+    segments: &[Segment],
+    top: f64,
+    bar_height: f64,
+) -> Result<()> {
+    for segment in segments {
+        segment.highlight.draw_range(
+            cr,
+            layout,
+            (segment.start, segment.end),
+            top,
+            bar_height,
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,336 @@
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use cairo::ImageSurface;
+use lazy_static::lazy_static;
+
+use crate::{image, parser};
+
+lazy_static! {
+    static ref THEMES: Mutex<HashMap<String, Option<Arc<Theme>>>> =
+        Mutex::new(HashMap::new());
+    static ref LOOKUPS: Mutex<HashMap<(String, i32), Option<PathBuf>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// The extensions that are searched for, in order of preference. XPM icons
+/// are skipped, since they can't be loaded.
+const EXTENSIONS: [&str; 2] = ["png", "svg"];
+
+/// The theme that every other theme falls back to
+const FALLBACK_THEME: &str = "hicolor";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Fixed,
+    Scalable,
+    Threshold,
+}
+
+#[derive(Debug)]
+struct Directory {
+    path: String,
+    size: i32,
+    scale: i32,
+    kind: Kind,
+    min_size: i32,
+    max_size: i32,
+    threshold: i32,
+}
+
+impl Directory {
+    fn matches(&self, size: i32) -> bool {
+        match self.kind {
+            Kind::Fixed => self.size == size,
+            Kind::Scalable => (self.min_size..=self.max_size).contains(&size),
+            Kind::Threshold => (self.size - self.threshold
+                ..=self.size + self.threshold)
+                .contains(&size),
+        }
+    }
+
+    fn distance(&self, size: i32) -> i32 {
+        let (min, max) = match self.kind {
+            Kind::Fixed => (self.size, self.size),
+            Kind::Scalable => (self.min_size, self.max_size),
+            Kind::Threshold => {
+                (self.size - self.threshold, self.size + self.threshold)
+            }
+        };
+        let (min, max) = (min * self.scale, max * self.scale);
+        if size < min {
+            min - size
+        } else if size > max {
+            size - max
+        } else {
+            0
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Theme {
+    /// Every folder with this theme's name in the base directories, since a
+    /// theme can be spread across several of them
+    roots: Vec<PathBuf>,
+    directories: Vec<Directory>,
+    inherits: Vec<String>,
+}
+
+impl Theme {
+    fn load(name: &str) -> Option<Self> {
+        let roots = base_dirs()
+            .into_iter()
+            .map(|base| base.join(name))
+            .filter(|root| root.is_dir())
+            .collect::<Vec<_>>();
+        let index = roots.iter().find_map(|root| {
+            std::fs::read_to_string(root.join("index.theme")).ok()
+        })?;
+        let sections = parse_index(index.as_str());
+
+        let theme = sections.get("Icon Theme")?;
+        let list = |key: &str| {
+            theme
+                .get(key)
+                .map(|value| {
+                    value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+
+        let directories = list("Directories")
+            .into_iter()
+            .chain(list("ScaledDirectories"))
+            .filter_map(|path| {
+                let section = sections.get(path.as_str())?;
+                let int = |key: &str| {
+                    section.get(key).and_then(|value| value.parse().ok())
+                };
+                let size = int("Size")?;
+                Some(Directory {
+                    size,
+                    scale: int("Scale").unwrap_or(1).max(1),
+                    kind: match section.get("Type").map(String::as_str) {
+                        Some("Fixed") => Kind::Fixed,
+                        Some("Scalable") => Kind::Scalable,
+                        _ => Kind::Threshold,
+                    },
+                    min_size: int("MinSize").unwrap_or(size),
+                    max_size: int("MaxSize").unwrap_or(size),
+                    threshold: int("Threshold").unwrap_or(2),
+                    path,
+                })
+            })
+            .collect();
+
+        Some(Self {
+            roots,
+            directories,
+            inherits: list("Inherits"),
+        })
+    }
+
+    fn find(&self, dir: &Directory, name: &str) -> Option<PathBuf> {
+        self.roots.iter().find_map(|root| {
+            EXTENSIONS
+                .iter()
+                .map(|ext| {
+                    root.join(dir.path.as_str()).join(format!("{name}.{ext}"))
+                })
+                .find(|path| path.is_file())
+        })
+    }
+
+    fn lookup(&self, name: &str, size: i32) -> Option<PathBuf> {
+        let mut closest: Option<(i32, PathBuf)> = None;
+        for dir in &self.directories {
+            let exact = dir.scale == 1 && dir.matches(size);
+            let distance = dir.distance(size);
+            if !exact && closest.as_ref().is_some_and(|(d, _)| *d <= distance) {
+                continue;
+            }
+            if let Some(path) = self.find(dir, name) {
+                if exact {
+                    return Some(path);
+                }
+                closest = Some((distance, path));
+            }
+        }
+        closest.map(|(_, path)| path)
+    }
+}
+
+/// Parses the sections of an `index.theme` file, which is in the desktop
+/// entry format. GTK's `settings.ini` is close enough to be read the same
+/// way.
+fn parse_index(index: &str) -> HashMap<&str, HashMap<&str, String>> {
+    let mut sections = HashMap::new();
+    let mut current = None;
+    for line in index.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.strip_suffix(']'))
+        {
+            current = Some(name);
+            sections.entry(name).or_insert_with(HashMap::new);
+        } else if let (Some(section), Some((key, value))) =
+            (current, line.split_once('='))
+        {
+            sections
+                .entry(section)
+                .or_insert_with(HashMap::new)
+                .insert(key.trim(), value.trim().to_string());
+        }
+    }
+    sections
+}
+
+/// The directories that icon themes are searched for in, in order.
+fn base_dirs() -> Vec<PathBuf> {
+    let home = env::var("HOME").ok().filter(|home| !home.is_empty());
+    let data_home = env::var("XDG_DATA_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .or_else(|| home.as_ref().map(|home| format!("{home}/.local/share")));
+    let data_dirs = env::var("XDG_DATA_DIRS")
+        .ok()
+        .filter(|dirs| !dirs.is_empty())
+        .unwrap_or_else(|| String::from("/usr/local/share:/usr/share"));
+
+    home.map(|home| PathBuf::from(format!("{home}/.icons")))
+        .into_iter()
+        .chain(data_home.map(|dir| PathBuf::from(format!("{dir}/icons"))))
+        .chain(
+            data_dirs
+                .split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(|dir| PathBuf::from(format!("{dir}/icons"))),
+        )
+        .collect()
+}
+
+fn load_theme(name: &str) -> Option<Arc<Theme>> {
+    THEMES
+        .lock()
+        .unwrap()
+        .entry(name.to_string())
+        .or_insert_with(|| Theme::load(name).map(Arc::new))
+        .clone()
+}
+
+fn lookup_in(
+    theme_name: &str,
+    name: &str,
+    size: i32,
+    visited: &mut HashSet<String>,
+) -> Option<PathBuf> {
+    if !visited.insert(theme_name.to_string()) {
+        return None;
+    }
+    let theme = load_theme(theme_name)?;
+    theme.lookup(name, size).or_else(|| {
+        theme
+            .inherits
+            .iter()
+            .find_map(|parent| lookup_in(parent, name, size, visited))
+    })
+}
+
+/// Returns the icon theme that GTK is set to use, or `hicolor` if there
+/// isn't one.
+pub(crate) fn default_theme_name() -> String {
+    let config_home = env::var("XDG_CONFIG_HOME")
+        .ok()
+        .filter(|dir| !dir.is_empty())
+        .or_else(|| {
+            env::var("HOME").ok().map(|home| format!("{home}/.config"))
+        });
+    config_home
+        .and_then(|dir| {
+            std::fs::read_to_string(format!("{dir}/gtk-3.0/settings.ini")).ok()
+        })
+        .and_then(|settings| {
+            parse_index(settings.as_str())
+                .get("Settings")?
+                .get("gtk-icon-theme-name")
+                .map(|name| name.trim_matches('"').to_string())
+        })
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| String::from(FALLBACK_THEME))
+}
+
+/// Returns the name of the icon theme chosen with the top-level
+/// `icon_theme` key. If it's unset, this is the theme that GTK is set to
+/// use, or `hicolor`.
+#[must_use]
+pub fn theme_name() -> &'static str {
+    parser::ICON_THEME
+        .get()
+        .map_or(FALLBACK_THEME, String::as_str)
+}
+
+/// Finds the file for an icon, following the freedesktop icon theme
+/// specification. The configured theme (see [`theme_name`]) is searched
+/// first, then the themes it inherits from, then `hicolor`, and finally
+/// `/usr/share/pixmaps`. Within a theme, an icon made for `size` is
+/// preferred, then the one closest to it.
+///
+/// `name` may also be an absolute path, which is returned as-is. Results
+/// are cached for the life of the bar.
+#[must_use]
+pub fn lookup(name: &str, size: i32) -> Option<PathBuf> {
+    if name.is_empty() {
+        return None;
+    }
+    if name.starts_with('/') {
+        return Some(PathBuf::from(name));
+    }
+
+    let key = (name.to_string(), size);
+    if let Some(path) = LOOKUPS.lock().unwrap().get(&key) {
+        return path.clone();
+    }
+
+    let mut visited = HashSet::new();
+    let path = lookup_in(theme_name(), name, size, &mut visited)
+        .or_else(|| lookup_in(FALLBACK_THEME, name, size, &mut visited))
+        .or_else(|| {
+            base_dirs()
+                .into_iter()
+                .chain(std::iter::once(PathBuf::from("/usr/share/pixmaps")))
+                .flat_map(|dir| {
+                    EXTENSIONS
+                        .iter()
+                        .map(move |ext| dir.join(format!("{name}.{ext}")))
+                })
+                .find(|path| path.is_file())
+        });
+    if path.is_none() {
+        log::debug!("No icon named {name} in theme {}", theme_name());
+    }
+    LOOKUPS.lock().unwrap().insert(key, path.clone());
+    path
+}
+
+/// Finds an icon with [`lookup`] and loads it, `size` pixels tall if it's
+/// an SVG.
+#[must_use]
+pub fn load(name: &str, size: i32) -> Option<ImageSurface> {
+    let path = lookup(name, size)?;
+    image::load_surface(path.as_path(), Some(size))
+        .map_err(|e| log::warn!("Failed to load icon {}: {e}", path.display()))
+        .ok()
+}
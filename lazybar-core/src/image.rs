@@ -1,14 +1,42 @@
-use std::{fs::File, path::PathBuf};
+use std::{
+    cell::RefCell,
+    collections::HashMap,
+    fs::File,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
 
-use anyhow::{Context, Result};
+use anyhow::{anyhow, Context, Result};
 use cairo::ImageSurface;
 use derive_builder::Builder;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
 
 use crate::{
-    get_table_from_config, parser, remove_float_from_config,
-    remove_string_from_config,
+    cleanup, get_table_from_config, icon, parser, remove_bool_from_config,
+    remove_float_from_config, remove_string_from_config, Attrs,
 };
 
+lazy_static! {
+    static ref INLINE: Regex = Regex::new(
+        r"%(?<kind>image|icon):(?<path>[^;%]+)(?<mods>(?:;[^;%]*)*)%"
+    )
+    .unwrap();
+}
+
+/// How many decoded images are kept before the cache is cleared
+const CACHE_SIZE: usize = 64;
+
+/// The character that holds an inline image's place in a layout
+const PLACEHOLDER: char = '\u{fffc}';
+
+thread_local! {
+    static CACHE: RefCell<
+        HashMap<(PathBuf, Option<SystemTime>, Option<i32>), ImageSurface>,
+    > = RefCell::new(HashMap::new());
+}
+
 /// An image to be rendered on the bar
 #[derive(Debug, Builder, Clone)]
 #[builder_struct_attr(allow(missing_docs))]
@@ -19,25 +47,90 @@ pub struct Image {
     x: f64,
     #[builder(default)]
     y: f64,
+    #[builder(default)]
+    recolor: bool,
+}
+
+/// Loads a PNG or SVG image. SVGs are rendered by librsvg's `rsvg-convert`,
+/// `height` pixels tall if set, so that they stay sharp at any size.
+///
+/// Images are cached until their file changes, so panels can load them
+/// every time they're drawn.
+pub(crate) fn load_surface(
+    path: &Path,
+    height: Option<i32>,
+) -> Result<ImageSurface> {
+    let svg = path.extension().is_some_and(|ext| {
+        ext.eq_ignore_ascii_case("svg") || ext.eq_ignore_ascii_case("svgz")
+    });
+    // PNGs are the same at any height
+    let height = height.filter(|_| svg);
+    let mtime = std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok();
+    let key = (path.to_path_buf(), mtime, height);
+    if let Some(surface) = CACHE.with(|cache| cache.borrow().get(&key).cloned())
+    {
+        return Ok(surface);
+    }
+
+    let surface = if svg {
+        render_svg(path, height)?
+    } else {
+        ImageSurface::create_from_png(&mut File::open(path)?)?
+    };
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+        // the same path with a new mtime is a new entry, so a frequently
+        // changing image (e.g. album art) would otherwise grow this forever
+        if cache.len() >= CACHE_SIZE {
+            cache.clear();
+        }
+        cache.insert(key, surface.clone());
+    });
+    Ok(surface)
+}
+
+fn render_svg(path: &Path, height: Option<i32>) -> Result<ImageSurface> {
+    let mut command = Command::new("rsvg-convert");
+    command.args(["--format", "png", "--keep-aspect-ratio"]);
+    if let Some(height) = height {
+        command.arg("--height").arg(height.to_string());
+    }
+    let output = cleanup::command_output(command.arg(path))
+        .context("Failed to run rsvg-convert, which renders SVG images")?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "rsvg-convert failed: {}",
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+    Ok(ImageSurface::create_from_png(
+        &mut output.stdout.as_slice(),
+    )?)
 }
 
 impl Image {
     /// Creates a new instance
     pub fn new(path: PathBuf, x: f64, y: f64) -> Result<Self> {
-        let mut file = File::open(path)?;
         Ok(Self {
-            surface: ImageSurface::create_from_png(&mut file)?,
+            surface: load_surface(path.as_path(), None)?,
             x,
             y,
+            recolor: false,
         })
     }
 
     /// Attempts to parse a new instance from the global config
     ///
     /// Configuration options:
-    /// - `path`: the file path of the image (PNG only)
+    /// - `path`: the file path of the image, a PNG or an SVG. SVGs are
+    ///   rendered with `rsvg-convert`, which comes with librsvg.
     /// - `x`: the x coordinate of the image, relative to the panel
     /// - `y`: the y coordinate of the image, relative to the panel
+    /// - `recolor`: whether to draw the image in the panel's foreground color,
+    ///   keeping only its shape. This lets a monochrome icon follow the
+    ///   theme. Defaults to false.
     pub fn parse(name: &str) -> Result<Self> {
         let images_table = parser::IMAGES.get().unwrap();
 
@@ -48,9 +141,7 @@ impl Image {
 
         let path = remove_string_from_config("path", &mut table)
             .context("No path specified")?;
-        let mut file = File::open(path)?;
-
-        builder.surface(ImageSurface::create_from_png(&mut file)?);
+        builder.surface(load_surface(Path::new(path.as_str()), None)?);
 
         if let Some(x) = remove_float_from_config("x", &mut table) {
             builder.x(x);
@@ -60,15 +151,19 @@ impl Image {
             builder.y(y);
         }
 
+        if let Some(recolor) = remove_bool_from_config("recolor", &mut table) {
+            builder.recolor(recolor);
+        }
+
         Ok(builder.build()?)
     }
 
     /// Draws the image on the bar. `cr`'s (0, 0) should be at the top left
-    /// corner of the panel.
+    /// corner of the panel. A recolored image is drawn in `cr`'s current
+    /// source.
     pub fn draw(&self, cr: &cairo::Context) -> Result<()> {
         cr.save()?;
 
-        cr.set_source_surface(self.surface.as_ref(), 0.0, 0.0)?;
         cr.rectangle(
             self.x,
             self.y,
@@ -76,9 +171,179 @@ impl Image {
             self.surface.height() as f64,
         );
 
-        cr.fill()?;
+        if self.recolor {
+            cr.clip();
+            cr.mask_surface(self.surface.as_ref(), 0.0, 0.0)?;
+        } else {
+            cr.set_source_surface(self.surface.as_ref(), 0.0, 0.0)?;
+            cr.fill()?;
+        }
         cr.restore()?;
 
         Ok(())
     }
 }
+
+/// An image from an `%image:...%` token in a format string, drawn in the
+/// space that its placeholder character takes up in the layout.
+#[derive(Debug, Clone)]
+pub(crate) struct InlineImage {
+    surface: ImageSurface,
+    scale: f64,
+    padding: f64,
+    recolor: bool,
+    index: i32,
+}
+
+impl InlineImage {
+    fn width(&self) -> f64 {
+        f64::from(self.surface.width()).mul_add(self.scale, 2.0 * self.padding)
+    }
+
+    fn height(&self) -> f64 {
+        f64::from(self.surface.height()) * self.scale
+    }
+}
+
+/// Replaces each `%image:<path>%` token in a format string with a
+/// placeholder character and loads the image it names. `%icon:<name>%`
+/// does the same for an icon from the icon theme (see [`icon::lookup`]).
+/// Images that can't be loaded are logged and left out.
+///
+/// Modifiers follow the path or name, separated by semicolons:
+/// - `size=<pixels>`: the height of the image. Defaults to the image's own
+///   height, or the height of the bar for an SVG.
+/// - `padding=<pixels>`: the space on each side of the image. Defaults to 0.
+/// - `recolor`: draws the image in the panel's foreground color, keeping
+///   only its shape, so that monochrome icons follow the theme.
+///
+/// Images are never taller than `height`.
+pub(crate) fn extract_inline(
+    text: &str,
+    height: i32,
+) -> (String, Vec<InlineImage>) {
+    let mut images = Vec::new();
+    let text = INLINE.replace_all(text, |caps: &Captures| {
+        let name = caps["path"].trim();
+        let mut size: Option<f64> = None;
+        let mut padding: f64 = 0.0;
+        let mut recolor = false;
+        for modifier in caps["mods"].split(';').filter(|m| !m.is_empty()) {
+            match modifier.split_once('=') {
+                Some(("size", value)) => size = value.trim().parse().ok(),
+                Some(("padding", value)) => {
+                    padding = value.trim().parse().unwrap_or_default();
+                }
+                None if modifier.trim() == "recolor" => recolor = true,
+                _ => log::warn!("Unknown image modifier {modifier}"),
+            }
+        }
+
+        // SVGs are rendered at the size they're drawn at
+        let render_height = size
+            .map_or(height, |size| size.round() as i32)
+            .clamp(1, height.max(1));
+        let path = if &caps["kind"] == "icon" {
+            match icon::lookup(name, render_height) {
+                Some(path) => path.to_string_lossy().into_owned(),
+                None => {
+                    log::warn!("No icon named {name} found");
+                    return String::new();
+                }
+            }
+        } else {
+            name.to_string()
+        };
+        match load_surface(Path::new(path.as_str()), Some(render_height)) {
+            Ok(surface) if surface.height() > 0 => {
+                let natural = f64::from(surface.height());
+                let scale =
+                    size.unwrap_or(natural).min(f64::from(height)).max(0.0)
+                        / natural;
+                images.push(InlineImage {
+                    surface,
+                    scale,
+                    padding: padding.max(0.0),
+                    recolor,
+                    index: 0,
+                });
+                PLACEHOLDER.to_string()
+            }
+            Ok(_) => String::new(),
+            Err(e) => {
+                log::warn!("Failed to load inline image {path}: {e}");
+                String::new()
+            }
+        }
+    });
+    (text.into_owned(), images)
+}
+
+/// Sets aside space in `layout` for each image, in the order they appeared
+/// in the format string. This should be called after the layout's text is
+/// set.
+pub(crate) fn reserve_inline(
+    layout: &pango::Layout,
+    images: &mut [InlineImage],
+) {
+    if images.is_empty() {
+        return;
+    }
+
+    let text = layout.text();
+    let attrs = layout
+        .attributes()
+        .and_then(|attrs| attrs.copy())
+        .unwrap_or_else(pango::AttrList::new);
+    let placeholders = text
+        .char_indices()
+        .filter(|(_, c)| *c == PLACEHOLDER)
+        .map(|(idx, _)| idx);
+    for (image, idx) in images.iter_mut().zip(placeholders) {
+        image.index = idx as i32;
+        let rect = pango::Rectangle::new(
+            0,
+            -(image.height() * f64::from(pango::SCALE)) as i32,
+            (image.width() * f64::from(pango::SCALE)) as i32,
+            (image.height() * f64::from(pango::SCALE)) as i32,
+        );
+        let mut shape = pango::AttrShape::new(&rect, &rect);
+        shape.set_start_index(idx as u32);
+        shape.set_end_index((idx + PLACEHOLDER.len_utf8()) as u32);
+        attrs.insert(shape);
+    }
+    layout.set_attributes(Some(&attrs));
+}
+
+/// Draws each image in the space that [`reserve_inline`] set aside. `cr`
+/// should be positioned where the layout was shown. Recolored images are
+/// drawn in the foreground color of `attrs`.
+pub(crate) fn draw_inline(
+    cr: &cairo::Context,
+    layout: &pango::Layout,
+    images: &[InlineImage],
+    attrs: &Attrs,
+) -> Result<()> {
+    for image in images {
+        let pos = layout.index_to_pos(image.index);
+        let x = f64::from(pos.x()) / f64::from(pango::SCALE) + image.padding;
+        // the image's space rises from the baseline of its line
+        let mut iter = layout.iter();
+        while iter.index() < image.index && iter.next_char() {}
+        let y = f64::from(iter.baseline()) / f64::from(pango::SCALE)
+            - image.height();
+
+        cr.save()?;
+        cr.translate(x, y);
+        cr.scale(image.scale, image.scale);
+        if image.recolor {
+            attrs.apply_fg(cr);
+            cr.mask_surface(&image.surface, 0.0, 0.0)?;
+        } else {
+            cr.set_source_surface(&image.surface, 0.0, 0.0)?;
+            cr.paint()?;
+        }
+        cr.restore()?;
+    }
+    Ok(())
+}
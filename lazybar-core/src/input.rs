@@ -0,0 +1,153 @@
+use std::{collections::HashMap, path::PathBuf, pin::Pin};
+
+use anyhow::{anyhow, Result};
+use config::Value;
+use tokio_stream::Stream;
+
+use crate::{remove_bool_from_config, remove_string_from_config};
+
+/// An input device whose events are sent to the bar as messages.
+#[derive(Debug, Clone)]
+pub struct InputDevice {
+    /// The path to the device, e.g. `/dev/input/by-id/...-event-kbd`.
+    pub path: PathBuf,
+    /// Whether to grab the device so that its events only reach the bar.
+    pub grab: bool,
+    /// The message to send for each event, keyed by event name in uppercase.
+    pub bindings: HashMap<String, String>,
+}
+
+impl InputDevice {
+    /// Parses a device from a table with the following keys:
+    ///
+    /// - `device`: the path to the device. Paths in `/dev/input/by-id` and
+    ///   `/dev/input/by-path` are stable across reboots, unlike
+    ///   `/dev/input/eventN`.
+    ///   - type: String
+    ///   - default: none (required)
+    /// - `grab`: whether to take exclusive access to the device, so that e.g.
+    ///   a presenter remote doesn't also type into the focused window
+    ///   - type: bool
+    ///   - default: false
+    /// - `bindings`: a table mapping event names to messages. Keys are named
+    ///   as in `linux/input-event-codes.h`, e.g. `KEY_VOLUMEUP` or `BTN_LEFT`,
+    ///   and fire when pressed. Relative axes are named with the direction
+    ///   appended, e.g. `"REL_DIAL+"` and `"REL_DIAL-"` for a volume knob.
+    ///   Messages are the same as those sent with `lazybar-msg`, e.g.
+    ///   `volume.up`, `#clock.toggle`, or `zen`.
+    ///   - type: Table of Strings
+    ///   - default: none (required)
+    pub fn parse(mut table: HashMap<String, Value>) -> Result<Self> {
+        let path = remove_string_from_config("device", &mut table)
+            .ok_or_else(|| anyhow!("An input device has no `device`"))?;
+        let grab = remove_bool_from_config("grab", &mut table).unwrap_or(false);
+        let bindings = table
+            .remove("bindings")
+            .ok_or_else(|| anyhow!("Input device {path} has no `bindings`"))?
+            .into_table()?
+            .into_iter()
+            .filter_map(|(event, message)| {
+                message
+                    .into_string()
+                    .map_err(|e| {
+                        log::warn!(
+                            "Invalid binding for {event} on {path}: {e}"
+                        );
+                    })
+                    .ok()
+                    .map(|message| (event.to_uppercase(), message))
+            })
+            .collect();
+
+        Ok(Self {
+            path: PathBuf::from(path),
+            grab,
+            bindings,
+        })
+    }
+}
+
+/// Returns a stream of the messages bound to events from `devices`. Devices
+/// that can't be opened are skipped with a warning.
+#[cfg(feature = "input")]
+pub(crate) fn listen(
+    devices: Vec<InputDevice>,
+) -> Pin<Box<dyn Stream<Item = String>>> {
+    use evdev::{Device, InputEventKind};
+    use tokio::sync::mpsc::unbounded_channel;
+    use tokio_stream::wrappers::UnboundedReceiverStream;
+
+    let (send, recv) = unbounded_channel();
+    for device in devices {
+        let mut events = match Device::open(&device.path).and_then(|mut dev| {
+            if device.grab {
+                dev.grab()?;
+            }
+            dev.into_event_stream()
+        }) {
+            Ok(events) => events,
+            Err(e) => {
+                log::warn!(
+                    "Failed to open input device {}: {e}",
+                    device.path.display()
+                );
+                continue;
+            }
+        };
+        log::debug!("Listening for input on {}", device.path.display());
+
+        let send = send.clone();
+        tokio::spawn(async move {
+            loop {
+                let event = match events.next_event().await {
+                    Ok(event) => event,
+                    Err(e) => {
+                        log::warn!(
+                            "Stopped reading input device {}: {e}",
+                            device.path.display()
+                        );
+                        break;
+                    }
+                };
+                let name = match event.kind() {
+                    // ignore releases and autorepeat
+                    InputEventKind::Key(key) if event.value() == 1 => {
+                        format!("{key:?}")
+                    }
+                    InputEventKind::RelAxis(axis) if event.value() > 0 => {
+                        format!("{axis:?}+")
+                    }
+                    InputEventKind::RelAxis(axis) if event.value() < 0 => {
+                        format!("{axis:?}-")
+                    }
+                    _ => continue,
+                };
+                if let Some(message) = device.bindings.get(&name) {
+                    log::debug!(
+                        "{name} on {}: {message}",
+                        device.path.display()
+                    );
+                    if send.send(message.clone()).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    Box::pin(UnboundedReceiverStream::new(recv))
+}
+
+/// Returns a stream of the messages bound to events from `devices`. Devices
+/// that can't be opened are skipped with a warning.
+#[cfg(not(feature = "input"))]
+pub(crate) fn listen(
+    devices: Vec<InputDevice>,
+) -> Pin<Box<dyn Stream<Item = String>>> {
+    if !devices.is_empty() {
+        log::warn!(
+            "Input devices were configured, but the input feature is disabled"
+        );
+    }
+    Box::pin(tokio_stream::pending())
+}
@@ -34,6 +34,10 @@
 //! - `consts`: each value is a string that can be substituted into any other
 //!   string by using `%{key}`
 //!
+//! Most format strings can also contain inline expressions of the form
+//! `%{ <expr> }%`, which are evaluated after a panel fills in its own tokens.
+//! See [`format::process`] for details.
+//!
 //! Other than `images` and `consts`, none of these tables need to be declared
 //! explicitly, as they hold no values of their own. `[bars.example]` is
 //! sufficient to define a bar named `example`. Any values in these top level
@@ -44,6 +48,22 @@
 //! figure out what you mean, but if you have issues, make sure that your types
 //! are correct.
 //!
+//! # Writing panels
+//!
+//! Panels that live outside of this crate should import what they need from
+//! [`prelude`], implement [`PanelConfig`], and usually finish drawing with
+//! [`draw_panel`][common::draw_panel]. The panels in [`panels`] are written
+//! the same way and make good examples. `derive_builder` isn't re-exported,
+//! because its derive macro expects to be a direct dependency.
+//!
+//! The items in [`prelude`] follow semantic versioning: while this crate is
+//! below 1.0, they only change in breaking ways in releases that bump the
+//! minor version. Enums and structs in the prelude that are likely to grow,
+//! like [`Event`][bar::Event] and [`PanelCommon`][common::PanelCommon], are
+//! marked `#[non_exhaustive]`, so matches on them need a wildcard arm and
+//! structs need to be built with their constructors or builders. Everything
+//! outside of the prelude may change in any release.
+//!
 //! # Example Config
 //! ```toml
 #![doc = include_str!("../examples/config.toml")]
@@ -63,6 +83,9 @@
 #![allow(clippy::too_many_lines)]
 #![allow(clippy::too_many_arguments)]
 
+/// Exporting the contents of the bar over D-Bus for screen readers.
+#[cfg(feature = "a11y")]
+pub mod a11y;
 /// Configuration options for click/scroll events on panels.
 pub mod actions;
 /// Configuration options for colors and fonts.
@@ -71,24 +94,72 @@ pub mod attrs;
 pub mod background;
 /// The bar itself and bar-related utility structs and functions.
 pub mod bar;
+/// Borders drawn around panels.
+pub mod border;
 /// Functions to ease a clean shutdown.
 pub mod cleanup;
 /// Common configuration for panels.
 pub mod common;
+/// Simulated data sources for designing themes and layouts without the
+/// real hardware or services.
+pub mod demo;
+/// Counters describing the bar's own overhead.
+pub mod diagnostics;
+/// Visual effects that can be applied to any panel.
+pub mod effects;
+/// A text entry popup for panels that need input from the user.
+pub mod entry;
+/// Processing for format strings that is shared by all panels.
+pub mod format;
+/// Rings that panels can draw around their text to show a level.
+pub mod gauge;
+/// Sparklines that panels can draw in place of their text.
+pub mod graph;
+/// A palette that overrides all configured colors for users with low vision.
+pub mod high_contrast;
 mod highlight;
+/// Finding icons by name in the user's icon theme.
+pub mod icon;
 /// Support for embedding images onto the bar
 pub mod image;
+/// Sending messages to the bar from input devices like remotes and knobs.
+pub mod input;
 /// Support for inter-process communication, like that provided by the
 /// `lazybar-msg` crate.
 pub mod ipc;
 /// Macros used internally which may be of use to other developers.
 pub mod macros;
+/// Dropdown menus that open when a panel is right-clicked.
+pub mod menu;
 /// Panels that can be added to the bar. A new panel must implement
 /// [`PanelConfig`].
 pub mod panels;
 /// The parser for the `config.toml` file.
 pub mod parser;
+/// Popup windows that panels can open next to themselves.
+pub mod popup;
+/// Everything needed to write a panel outside of this crate. See
+/// [Writing panels](crate#writing-panels).
+pub mod prelude;
+/// Bars of characters that show a level or a breakdown in a format string.
+pub mod progress;
 mod ramp;
+/// Restrictions for commands spawned by panels.
+pub mod sandbox;
+/// Detection of xdg-desktop-portal screen casts for privacy mode.
+#[cfg(feature = "screencast")]
+pub mod screencast;
+/// A store of values published by panels for use by other panels.
+pub mod state;
+/// Reloading of styles while the bar is running.
+pub mod style;
+/// Switching a panel's attrs when its value passes a threshold.
+pub mod threshold;
+/// Tooltips shown when the pointer rests on a panel. A panel provides one
+/// by setting [`PanelDrawInfo::tooltip`][bar::PanelDrawInfo::tooltip], or
+/// with the `tooltip` option (see
+/// [`PanelCommon::parse_common`][common::PanelCommon::parse_common]).
+pub mod tooltip;
 mod utils;
 mod x;
 
@@ -108,9 +179,9 @@ pub use builders::BarConfig;
 use config::{Config, Value};
 pub use csscolorparser::Color;
 pub use glib::markup_escape_text;
-pub use highlight::Highlight;
+pub use highlight::{Highlight, HighlightScope};
 use ipc::ChannelEndpoint;
-pub use ramp::Ramp;
+pub use ramp::{ramp_index, ColorRamp, OutOfRange, Ramp, RampPalette};
 use tokio_stream::Stream;
 pub use utils::*;
 use x::{create_surface, create_window, set_wm_properties};
@@ -190,6 +261,45 @@ pub enum Position {
     Top,
     /// The bottom of the screen
     Bottom,
+    /// The left side of the screen. The bar is rotated so that its text
+    /// reads from bottom to top.
+    Left,
+    /// The right side of the screen. The bar is rotated so that its text
+    /// reads from top to bottom.
+    Right,
+}
+
+impl Position {
+    /// Whether the bar runs along the side of the screen.
+    #[must_use]
+    pub const fn is_vertical(self) -> bool {
+        matches!(self, Self::Left | Self::Right)
+    }
+}
+
+/// Describes how the bar is stacked relative to other windows.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Default)]
+pub enum Layer {
+    /// Above normal windows
+    Above,
+    /// Wherever the window manager places docks
+    #[default]
+    Normal,
+    /// Below normal windows
+    Below,
+}
+
+impl Layer {
+    /// Parses a layer from `above`, `normal`, or `below`.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "above" => Some(Self::Above),
+            "normal" => Some(Self::Normal),
+            "below" => Some(Self::Below),
+            _ => None,
+        }
+    }
 }
 
 /// Describes where on the bar a panel should appear.
@@ -257,16 +367,21 @@ impl Margins {
 /// Builder structs for non-panel items, courtesy of [`derive_builder`]. See
 /// [`panels::builders`] for panel builders.
 pub mod builders {
-    use std::thread;
+    use std::{
+        collections::{HashMap, HashSet},
+        thread,
+        time::Duration,
+    };
 
     use anyhow::Result;
     use derive_builder::Builder;
-    use futures::executor;
+    use futures::{executor, stream::FuturesUnordered, FutureExt};
     use signal_hook::{consts::TERM_SIGNALS, iterator::Signals};
     use tokio::{
         runtime::Runtime,
         sync::mpsc::unbounded_channel,
         task::{self, JoinSet},
+        time::{self, MissedTickBehavior},
     };
     use tokio_stream::{StreamExt, StreamMap};
     use x11rb::errors::{
@@ -274,10 +389,19 @@ pub mod builders {
     };
 
     use crate::{
-        cleanup, ipc::ChannelEndpoint, x::XStream, Alignment, Attrs, Bar,
-        Color, Margins, Panel, PanelConfig, Position, UnixStreamWrapper,
+        background::BarBg,
+        bar::{self, TransitionStyle},
+        cleanup, diagnostics,
+        input::{self, InputDevice},
+        ipc::ChannelEndpoint,
+        style, x, Alignment, Attrs, Bar, Color, Margins, Panel, PanelConfig,
+        Position, UnixStreamWrapper,
     };
 
+    // the most waiting panel updates that are drawn at once, so that panels
+    // that are always ready can't keep X events and IPC waiting
+    const MAX_BATCH: usize = 64;
+
     /// A set of options for a bar.
     ///
     /// See [`parser::parse`][crate::parser::parse] for configuration details.
@@ -288,20 +412,22 @@ pub mod builders {
     pub struct BarConfig {
         /// The bar name to look for in the config file
         pub name: String,
-        left: Vec<Box<dyn PanelConfig>>,
-        center: Vec<Box<dyn PanelConfig>>,
-        right: Vec<Box<dyn PanelConfig>>,
-        /// Whether the bar should be rendered at the top or bottom of the
-        /// screen
+        left: Vec<(Box<dyn PanelConfig>, usize)>,
+        center: Vec<(Box<dyn PanelConfig>, usize)>,
+        right: Vec<(Box<dyn PanelConfig>, usize)>,
+        /// Which edge of the screen the bar should be rendered along
         pub position: Position,
-        /// In pixels
+        /// The height of each row in pixels
         pub height: u16,
+        /// The number of rows of panels in the bar
+        #[builder(default = "1")]
+        pub rows: usize,
         /// Whether the bar can be transparent. The background color still
         /// applies!
         pub transparent: bool,
-        /// The background color. Supports transparency if `transparent` is
-        /// true.
-        pub bg: Color,
+        /// The background of the bar. Supports transparency if
+        /// `transparent` is true.
+        pub bg: BarBg,
         /// The minimum gaps between the edges of the screen and panel
         /// sections. See [`Margins`] for details.
         pub margins: Margins,
@@ -316,6 +442,155 @@ pub mod builders {
         /// Which monitor to display the bar on. Defaults to the primary
         /// monitor.
         pub monitor: Option<String>,
+        /// The names of the panels that stay visible in zen mode.
+        #[builder(default)]
+        pub zen_panels: Vec<String>,
+        /// The height of the bar in zen mode, if it should change.
+        #[builder(default)]
+        pub zen_height: Option<u16>,
+        /// The names of the panels that are blanked in privacy mode.
+        #[builder(default)]
+        pub sensitive: HashSet<&'static str>,
+        /// The window classes that enable privacy mode while any window with
+        /// one of them exists. Privacy mode is also enabled during screen
+        /// casts if the `screencast` feature is enabled.
+        #[builder(default)]
+        pub privacy_classes: Vec<String>,
+        /// Explicit ids for panels, keyed by panel name. Panels without one
+        /// get an id from [`Panel::default_id`].
+        #[builder(default)]
+        pub ids: HashMap<&'static str, String>,
+        /// The group that each panel belongs to and the group that it
+        /// toggles, keyed by panel name.
+        #[builder(default)]
+        pub groups: HashMap<&'static str, (Option<String>, Option<String>)>,
+        /// Whether to export the text of each panel over D-Bus for screen
+        /// readers. Requires the `a11y` feature.
+        #[builder(default)]
+        pub accessibility: bool,
+        /// Whether to start in high contrast mode. See
+        /// [`high_contrast`][crate::high_contrast] for details.
+        #[builder(default)]
+        pub high_contrast: bool,
+        /// The color of the line drawn along the bottom of the bar while the
+        /// active window is on its monitor, if any.
+        #[builder(default)]
+        pub focus_accent: Option<Color>,
+        /// The thickness in pixels of the focus accent.
+        #[builder(default = "2")]
+        pub focus_accent_height: u16,
+        /// The input devices whose events are sent to the bar as messages.
+        /// Requires the `input` feature.
+        #[builder(default)]
+        pub input: Vec<InputDevice>,
+        /// If set, the bar hides itself once the pointer has been away from
+        /// it for this long, and reappears when the pointer touches the edge
+        /// of the screen.
+        #[builder(default)]
+        pub autohide: Option<Duration>,
+        /// Whether to hide the bar while the active window is fullscreen on
+        /// its monitor.
+        #[builder(default)]
+        pub hide_on_fullscreen: bool,
+        /// How long the pointer must rest on a panel before its tooltip is
+        /// shown.
+        #[builder(default = "Duration::from_millis(500)")]
+        pub tooltip_delay: Duration,
+        /// How soon after a click another click on the same panel counts as
+        /// a double-click.
+        #[builder(default = "Duration::from_millis(400)")]
+        pub double_click_time: Duration,
+        /// How long a mouse button has to be held down on a panel to count
+        /// as held rather than clicked.
+        #[builder(default = "Duration::from_millis(500)")]
+        pub hold_time: Duration,
+        /// How panels appear and disappear.
+        #[builder(default)]
+        pub transition: TransitionStyle,
+        /// How long panels take to appear and disappear.
+        #[builder(default = "Duration::from_millis(200)")]
+        pub transition_duration: Duration,
+        /// How long panels take to change width. Zero disables the
+        /// animation.
+        #[builder(default)]
+        pub animation_duration: Duration,
+        /// The radius in pixels of the bar's corners.
+        #[builder(default)]
+        pub corner_radius: f64,
+        /// The width in pixels of the border around the bar.
+        #[builder(default)]
+        pub border_width: f64,
+        /// The color of the border around the bar.
+        #[builder(default)]
+        pub border_color: Color,
+        /// Whether to ask the compositor to blur what's behind the bar.
+        #[builder(default)]
+        pub blur: bool,
+        /// How far in pixels the bar's shadow extends.
+        #[builder(default)]
+        pub shadow_radius: f64,
+        /// The opacity of the bar's shadow, from 0 to 1.
+        #[builder(default = "0.5")]
+        pub shadow_opacity: f64,
+        /// The names of panels that clicks pass through to the windows below.
+        #[builder(default)]
+        pub click_through: HashSet<&'static str>,
+        /// Whether clicks on the empty space between alignments pass through
+        /// to the windows below.
+        #[builder(default)]
+        pub click_through_gaps: bool,
+        /// The priorities of panels that can be collapsed when their row
+        /// overflows, keyed by name.
+        #[builder(default)]
+        pub priorities: HashMap<&'static str, u64>,
+        /// How the bar is stacked relative to other windows.
+        #[builder(default)]
+        pub layer: Layer,
+    }
+
+    /// Assigns an id to each panel in one alignment, warning about any
+    /// duplicates.
+    fn panel_ids(
+        panels: &[(Box<dyn PanelConfig>, usize)],
+        alignment: Alignment,
+        explicit: &HashMap<&'static str, String>,
+        seen: &mut HashSet<String>,
+    ) -> Vec<String> {
+        let mut occurrences = HashMap::new();
+        panels
+            .iter()
+            .map(|(panel, row)| {
+                let name = panel.props().0;
+                let occurrence = occurrences.entry((name, *row)).or_insert(0);
+                let mut id = explicit.get(name).cloned().unwrap_or_else(|| {
+                    Panel::default_id(name, alignment, *row, *occurrence)
+                });
+                *occurrence += 1;
+                if !seen.insert(id.clone()) {
+                    log::warn!(
+                        "Panel id {id} is used more than once; only the first \
+                         panel with it can be addressed by id"
+                    );
+                    id = Panel::default_id(name, alignment, *row, *occurrence);
+                }
+                log::debug!("{alignment} panel {name} has id {id}");
+                id
+            })
+            .collect()
+    }
+
+    /// Looks up the group that each panel in one alignment belongs to and
+    /// the group that it toggles.
+    fn panel_groups(
+        panels: &[(Box<dyn PanelConfig>, usize)],
+        groups: &HashMap<&'static str, (Option<String>, Option<String>)>,
+    ) -> Vec<(Option<String>, Option<String>)> {
+        panels
+            .iter()
+            .map(|(panel, _)| {
+                groups.get(panel.props().0).cloned().unwrap_or_default()
+            })
+            .collect()
     }
 
     impl BarConfig {
@@ -325,11 +600,22 @@ pub mod builders {
             &mut self,
             panel: Box<dyn PanelConfig>,
             alignment: Alignment,
+        ) {
+            self.add_panel_to_row(panel, alignment, 0);
+        }
+
+        /// Add a panel to a given row of the bar with a given [`Alignment`].
+        /// Rows are numbered from zero, starting at the top.
+        pub fn add_panel_to_row(
+            &mut self,
+            panel: Box<dyn PanelConfig>,
+            alignment: Alignment,
+            row: usize,
         ) {
             match alignment {
-                Alignment::Left => self.left.push(panel),
-                Alignment::Center => self.center.push(panel),
-                Alignment::Right => self.right.push(panel),
+                Alignment::Left => self.left.push((panel, row)),
+                Alignment::Center => self.center.push((panel, row)),
+                Alignment::Right => self.right.push((panel, row)),
             };
         }
 
@@ -340,37 +626,79 @@ pub mod builders {
         ///
         /// In the case of unrecoverable runtime errors.
         pub fn run(self) -> Result<()> {
-            log::info!("Starting bar {}", self.name);
+            Self::run_all(vec![self])
+        }
+
+        /// Like [`run`][Self::run], but for several bars at once. The bars
+        /// share one tokio runtime and one X connection, and each one runs
+        /// as a task on the current thread.
+        ///
+        /// # Errors
+        ///
+        /// In the case of unrecoverable runtime errors. The first error is
+        /// returned once every bar has stopped.
+        pub fn run_all(bars: Vec<Self>) -> Result<()> {
             let rt = Runtime::new()?;
+            handle_signals()?;
             let local = task::LocalSet::new();
-            local.block_on(&rt, self.run_inner())?;
-            Ok(())
+            let mut handles = bars
+                .into_iter()
+                .map(|bar| local.spawn_local(bar.run_inner()))
+                .collect::<FuturesUnordered<_>>();
+            local.block_on(&rt, async {
+                let mut result = Ok(());
+                // in the order they stop, so that errors are logged right away
+                while let Some(bar_result) = handles.next().await {
+                    let bar_result = bar_result.map_err(anyhow::Error::from);
+                    if let Err(e) = bar_result.and_then(|r| r) {
+                        log::error!("Bar stopped with an error: {e}");
+                        if result.is_ok() {
+                            result = Err(e);
+                        }
+                    }
+                }
+                result
+            })
         }
 
         #[allow(clippy::future_not_send)]
         async fn run_inner(self) -> Result<()> {
-            let (mut bar, mut ipc_stream) = Bar::new(
-                self.name.as_str(),
-                self.position,
-                self.height,
-                self.transparent,
-                self.bg,
-                self.margins,
-                self.reverse_scroll,
-                self.ipc,
-                self.monitor,
-            )?;
+            log::info!("Starting bar {}", self.name);
+            // before the bar's windows exist, so that none of their events
+            // are missed
+            let mut x_stream = x::events()?;
+            let (mut bar, mut ipc_stream) = Bar::new(&self)?;
             log::debug!("bar created");
+            if let Err(e) = bar.check_focus() {
+                log::warn!("Error checking for focus: {e}");
+            }
+            if let Err(e) = bar.check_fullscreen() {
+                log::warn!("Error checking for fullscreen windows: {e}");
+            }
+            if let Err(e) = bar.check_privacy() {
+                log::warn!("Error checking for privacy mode: {e}");
+            }
 
             let mut joinset = JoinSet::new();
 
             let mut left_stream = StreamMap::with_capacity(self.left.len());
             let mut left_panels = Vec::new();
-            for (idx, panel) in self.left.into_iter().enumerate() {
+            let left_rows =
+                self.left.iter().map(|(_, row)| *row).collect::<Vec<_>>();
+            let left_groups = panel_groups(&self.left, &self.groups);
+            let mut seen_ids = HashSet::new();
+            let left_ids = panel_ids(
+                &self.left,
+                Alignment::Left,
+                &self.ids,
+                &mut seen_ids,
+            );
+            for (idx, (panel, _)) in self.left.into_iter().enumerate() {
                 left_panels.push(None);
                 let cr = bar.cr.clone();
                 let attrs = self.attrs.clone();
-                joinset.spawn_local(async move {
+                // panels look up the bar they belong to while starting
+                joinset.spawn_local(bar::with_info(bar.info(), async move {
                     (
                         Alignment::Left,
                         idx,
@@ -383,16 +711,25 @@ pub mod builders {
                             )
                             .await,
                     )
-                });
+                }));
             }
 
             let mut center_stream = StreamMap::with_capacity(self.center.len());
             let mut center_panels = Vec::new();
-            for (idx, panel) in self.center.into_iter().enumerate() {
+            let center_rows =
+                self.center.iter().map(|(_, row)| *row).collect::<Vec<_>>();
+            let center_groups = panel_groups(&self.center, &self.groups);
+            let center_ids = panel_ids(
+                &self.center,
+                Alignment::Center,
+                &self.ids,
+                &mut seen_ids,
+            );
+            for (idx, (panel, _)) in self.center.into_iter().enumerate() {
                 center_panels.push(None);
                 let cr = bar.cr.clone();
                 let attrs = self.attrs.clone();
-                joinset.spawn_local(async move {
+                joinset.spawn_local(bar::with_info(bar.info(), async move {
                     (
                         Alignment::Center,
                         idx,
@@ -405,16 +742,25 @@ pub mod builders {
                             )
                             .await,
                     )
-                });
+                }));
             }
 
             let mut right_stream = StreamMap::with_capacity(self.right.len());
             let mut right_panels = Vec::new();
-            for (idx, panel) in self.right.into_iter().enumerate() {
+            let right_rows =
+                self.right.iter().map(|(_, row)| *row).collect::<Vec<_>>();
+            let right_groups = panel_groups(&self.right, &self.groups);
+            let right_ids = panel_ids(
+                &self.right,
+                Alignment::Right,
+                &self.ids,
+                &mut seen_ids,
+            );
+            for (idx, (panel, _)) in self.right.into_iter().enumerate() {
                 right_panels.push(None);
                 let cr = bar.cr.clone();
                 let attrs = self.attrs.clone();
-                joinset.spawn_local(async move {
+                joinset.spawn_local(bar::with_info(bar.info(), async move {
                     (
                         Alignment::Right,
                         idx,
@@ -427,7 +773,7 @@ pub mod builders {
                             )
                             .await,
                     )
-                });
+                }));
             }
 
             while !joinset.is_empty() {
@@ -439,19 +785,37 @@ pub mod builders {
                         Ok((stream, sender)),
                     ))) => match alignment {
                         Alignment::Left => {
-                            left_panels[idx] =
-                                Some(Panel::new(None, name, sender, visible));
-                            left_stream.insert(idx, stream);
+                            let mut panel =
+                                Panel::new(None, name, sender, visible);
+                            panel.row = left_rows[idx];
+                            panel.id.clone_from(&left_ids[idx]);
+                            panel.priority = self.priorities.get(name).copied();
+                            (panel.group, panel.toggles) =
+                                left_groups[idx].clone();
+                            left_panels[idx] = Some(panel);
+                            left_stream.insert(idx, cleanup::owned(stream));
                         }
                         Alignment::Center => {
-                            center_panels[idx] =
-                                Some(Panel::new(None, name, sender, visible));
-                            center_stream.insert(idx, stream);
+                            let mut panel =
+                                Panel::new(None, name, sender, visible);
+                            panel.row = center_rows[idx];
+                            panel.id.clone_from(&center_ids[idx]);
+                            panel.priority = self.priorities.get(name).copied();
+                            (panel.group, panel.toggles) =
+                                center_groups[idx].clone();
+                            center_panels[idx] = Some(panel);
+                            center_stream.insert(idx, cleanup::owned(stream));
                         }
                         Alignment::Right => {
-                            right_panels[idx] =
-                                Some(Panel::new(None, name, sender, visible));
-                            right_stream.insert(idx, stream);
+                            let mut panel =
+                                Panel::new(None, name, sender, visible);
+                            panel.row = right_rows[idx];
+                            panel.id.clone_from(&right_ids[idx]);
+                            panel.priority = self.priorities.get(name).copied();
+                            (panel.group, panel.toggles) =
+                                right_groups[idx].clone();
+                            right_panels[idx] = Some(panel);
+                            right_stream.insert(idx, cleanup::owned(stream));
                         }
                     },
                     Some(Ok((alignment, idx, (name, _), Err(e)))) => {
@@ -482,54 +846,68 @@ pub mod builders {
             bar.streams.insert(Alignment::Right, right_stream);
             log::debug!("right panels running");
 
-            let mut x_stream = XStream::new(bar.conn.clone());
-
-            let mut signals = Signals::new(TERM_SIGNALS)?;
-            let name = bar.name.clone();
+            let mut input_stream = input::listen(self.input);
 
+            // signals and exiting bars ask each bar to shut down through
+            // this
             let (send1, recv2) = unbounded_channel();
             let (send2, recv1) = unbounded_channel();
             let mut endpoint1 = ChannelEndpoint::new(send1, recv1);
             let endpoint2 = ChannelEndpoint::new(send2, recv2);
-            unsafe { cleanup::ENDPOINT.set(endpoint2).unwrap() };
-            thread::spawn(move || loop {
-                if let Some(signal) = signals.wait().next() {
-                    log::info!("Received signal {signal} - shutting down");
-                    if let Ok(rt) = Runtime::new() {
-                        rt.block_on(async {
-                            cleanup::exit(
-                                Some((name.as_str(), self.ipc)),
-                                true,
-                                0,
-                            )
-                            .await;
-                        });
-                    } else {
-                        executor::block_on(cleanup::exit(
-                            Some((name.as_str(), self.ipc)),
-                            false,
-                            0,
-                        ));
-                    }
-                }
-            });
-            log::debug!("Set up signal listener");
+            cleanup::ENDPOINTS.lock().unwrap().push(endpoint2);
+            let mut restyled = style::subscribe();
 
             let mut ipc_set = JoinSet::<Result<()>>::new();
 
             let mut cleanup = task::spawn_local(cleanup::cleanup());
             let mut cleanup_done = false;
 
-            task::spawn_local(async move { loop {
+            // privacy mode is also entered while a screen is being cast.
+            // without the feature, the sender is dropped and nothing is
+            // received.
+            #[cfg(feature = "screencast")]
+            let mut screencast = if self.sensitive.is_empty() {
+                unbounded_channel().1
+            } else {
+                crate::screencast::watch()
+            };
+            #[cfg(not(feature = "screencast"))]
+            let mut screencast = unbounded_channel::<bool>().1;
+
+            let mut reap_interval = time::interval(Duration::from_secs(5));
+            let mut autohide_interval =
+                time::interval(Duration::from_millis(100));
+            let mut tooltip_interval =
+                time::interval(Duration::from_millis(100));
+            let mut hold_interval = time::interval(Duration::from_millis(50));
+            let mut animation_interval =
+                time::interval(Duration::from_millis(33));
+            // ticks missed while the bar was busy are stale, so they're
+            // dropped rather than run late in a burst
+            for interval in [
+                &mut reap_interval,
+                &mut autohide_interval,
+                &mut tooltip_interval,
+                &mut hold_interval,
+                &mut animation_interval,
+            ] {
+                interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            }
+
+            task::spawn_local(bar::with_info(bar.info(), async move { loop {
                 tokio::select! {
-                    Some(Ok(event)) = x_stream.next() => {
+                    event = x_stream.next() => {
+                        // the connection was lost
+                        let Some(event) = event else {
+                            cleanup::exit(Some((bar.name.clone(), self.ipc)), true, 0).await;
+                        };
                         log::trace!("X event: {event:?}");
                         if let Err(e) = bar.process_event(&event) {
                             if let Some(e) = e.downcast_ref::<ConnectionError>() {
                                 log::warn!("X connection error (this probably points to an issue external to lazybar): {e}");
                                 // close when X server does
                                 // this could cause problems, maybe only exit under certain circumstances?
-                                cleanup::exit(Some((bar.name.as_str(), self.ipc)), true, 0).await;
+                                cleanup::exit(Some((bar.name.clone(), self.ipc)), true, 0).await;
                             } else if let Some(e) = e.downcast_ref::<ParseError>() {
                                 log::warn!("Error parsing data from X server: {e}");
                             } else if let Some(e) = e.downcast_ref::<ReplyError>() {
@@ -541,14 +919,27 @@ pub mod builders {
                             }
                         }
                     },
-                    Some((alignment, result)) = bar.streams.next() => {
-                        log::debug!("Received event from {alignment} panel at index {}", result.0);
-                        match result {
-                            (idx, Ok(draw_info)) => if let Err(e) = bar.update_panel(alignment, idx, draw_info) {
-                                log::warn!("Error updating {alignment} panel at index {idx}: {e}");
+                    Some(first) = bar.streams.next() => {
+                        // collect every update that's already waiting so that bursts are
+                        // drawn once
+                        let mut next = Some(first);
+                        let mut drained = 0;
+                        while let Some((alignment, result)) = next {
+                            log::debug!("Received event from {alignment} panel at index {}", result.0);
+                            match result {
+                                (idx, Ok(draw_info)) => bar.queue_update(alignment, idx, draw_info),
+                                (idx, Err(e)) =>
+                                    log::warn!("Error produced by {alignment} panel at index {idx:?}: {e}"),
                             }
-                            (idx, Err(e)) =>
-                                log::warn!("Error produced by {alignment} panel at index {idx:?}: {e}"),
+                            drained += 1;
+                            next = if drained < MAX_BATCH {
+                                bar.streams.next().now_or_never().flatten()
+                            } else {
+                                None
+                            };
+                        }
+                        if let Err(e) = bar.flush_updates() {
+                            log::warn!("Error updating panels: {e}");
                         }
                     },
                     Some(Ok(stream)) = ipc_stream.next(), if bar.ipc => {
@@ -568,18 +959,34 @@ pub mod builders {
                         if let Some(message) = message {
                             match bar.send_message(message.as_str(), &mut ipc_set, ipc_send) {
                                 Ok(true) => {
-                                    task::spawn_local(cleanup::exit(Some((bar.name.clone().leak(), self.ipc)), true, 0));
+                                    task::spawn_local(cleanup::exit(Some((bar.name.clone(), self.ipc)), true, 0));
                                 }
                                 Err(e) => log::warn!("Sending message {message} generated an error: {e}"),
                                 _ => {}
                             }
                         }
                     }
+                    Some(message) = input_stream.next() => {
+                        log::debug!("Received message {message} from an input device");
+
+                        // nobody is waiting for the response
+                        let (response_send, _) = unbounded_channel();
+                        match bar.send_message(message.as_str(), &mut ipc_set, response_send) {
+                            Ok(true) => {
+                                task::spawn_local(cleanup::exit(Some((bar.name.clone(), self.ipc)), true, 0));
+                            }
+                            Err(e) => log::warn!("Sending message {message} generated an error: {e}"),
+                            _ => {}
+                        }
+                    }
                     // maybe not strictly necessary, but ensures that the ipc futures get polled
                     Some(_) = ipc_set.join_next() => {
                         log::debug!("ipc future completed");
                     }
                     Some(()) = endpoint1.recv.recv() => {
+                        if bar.ipc {
+                            cleanup::remove_socket(&bar.name);
+                        }
                         bar.shutdown();
                         let _ = endpoint1.send.send(());
                         // this message will never arrive, but it avoids a race condition with the
@@ -589,6 +996,39 @@ pub mod builders {
                         // never run.
                         break;
                     }
+                    Ok(()) = restyled.changed() => {
+                        if let Err(e) = bar.redraw_bar() {
+                            log::warn!("Error redrawing the bar with the new styles: {e}");
+                        }
+                    }
+                    _ = reap_interval.tick() => {
+                        cleanup::reap_children();
+                    }
+                    Some(active) = screencast.recv() => {
+                        if let Err(e) = bar.set_screencast(active) {
+                            log::warn!("Error checking for privacy mode: {e}");
+                        }
+                    }
+                    _ = autohide_interval.tick(), if bar.autohides() => {
+                        if let Err(e) = bar.check_autohide() {
+                            log::warn!("Error hiding the bar: {e}");
+                        }
+                    }
+                    _ = tooltip_interval.tick(), if bar.hovering() => {
+                        if let Err(e) = bar.check_tooltip() {
+                            log::warn!("Error showing a tooltip: {e}");
+                        }
+                    }
+                    _ = hold_interval.tick(), if bar.pressing() => {
+                        if let Err(e) = bar.check_hold() {
+                            log::warn!("Error sending a held button: {e}");
+                        }
+                    }
+                    _ = animation_interval.tick(), if bar.animating() => {
+                        if let Err(e) = bar.animate() {
+                            log::warn!("Error animating panels: {e}");
+                        }
+                    }
                     res = &mut cleanup, if !cleanup_done => {
                         match res {
                             Ok(Ok(())) => {
@@ -604,9 +1044,27 @@ pub mod builders {
                         cleanup_done = true;
                     }
                 }
-            } }).await?;
+                diagnostics::wakeup();
+            } })).await?;
 
             Ok(())
         }
     }
+
+    /// Shuts down every bar when lazybar receives a termination signal.
+    fn handle_signals() -> Result<()> {
+        let mut signals = Signals::new(TERM_SIGNALS)?;
+        thread::spawn(move || loop {
+            if let Some(signal) = signals.wait().next() {
+                log::info!("Received signal {signal} - shutting down");
+                if let Ok(rt) = Runtime::new() {
+                    rt.block_on(cleanup::exit(None, true, 0));
+                } else {
+                    executor::block_on(cleanup::exit(None, false, 0));
+                }
+            }
+        });
+        log::debug!("Set up signal listener");
+        Ok(())
+    }
 }
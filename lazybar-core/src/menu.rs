@@ -0,0 +1,135 @@
+use std::{
+    collections::HashMap,
+    hash::BuildHasher,
+    sync::{atomic::AtomicBool, Arc},
+};
+
+use config::Value;
+use pangocairo::functions::{create_layout, show_layout, update_layout};
+
+use crate::{
+    popup::{PanelPopup, Popup},
+    remove_array_from_config, remove_string_from_config, Attrs,
+};
+
+/// The space in pixels around the label of each menu entry
+const PADDING: i32 = 6;
+
+/// An entry in a panel's menu. Choosing it sends its action to the panel as
+/// an [`Event::Action`][crate::bar::Event::Action], just like a click bound
+/// to that action would.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MenuEntry {
+    /// The text shown for the entry, as markup
+    pub label: String,
+    /// The action sent to the panel when the entry is chosen
+    pub action: String,
+}
+
+impl MenuEntry {
+    /// Creates a new [`MenuEntry`].
+    #[must_use]
+    pub fn new(label: impl Into<String>, action: impl Into<String>) -> Self {
+        Self {
+            label: label.into(),
+            action: action.into(),
+        }
+    }
+
+    /// Parses the entries of a menu from a subset of the global config.
+    ///
+    /// `menu` is an array of tables, each with the keys
+    /// - `label`: the text shown for the entry, as markup
+    /// - `action`: the event sent to the panel when the entry is chosen.
+    ///   The events that each panel supports are listed in its
+    ///   documentation.
+    ///
+    /// Entries missing either key are skipped.
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Vec<Self> {
+        remove_array_from_config("menu", table)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|entry| {
+                let mut entry = entry
+                    .into_table()
+                    .map_err(|e| log::warn!("Invalid menu entry: {e}"))
+                    .ok()?;
+                let label = remove_string_from_config("label", &mut entry);
+                let action = remove_string_from_config("action", &mut entry);
+                match (label, action) {
+                    (Some(label), Some(action)) => Some(Self { label, action }),
+                    _ => {
+                        log::warn!("Menu entries need a label and an action");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+}
+
+/// A menu opened by the bar. Closing the menu is the same as dropping it.
+pub(crate) struct Menu {
+    pub(crate) popup: Popup,
+    pub(crate) content: PanelPopup,
+    pub(crate) actions: Vec<String>,
+    pub(crate) row_height: i32,
+}
+
+impl Menu {
+    /// Returns the action of the entry at a point relative to the menu.
+    pub(crate) fn action_at(&self, x: i16, y: i16) -> Option<String> {
+        if !self.popup.contains(x, y) {
+            return None;
+        }
+        self.actions
+            .get((i32::from(y) / self.row_height.max(1)) as usize)
+            .cloned()
+    }
+}
+
+/// Lays out the entries of a menu, one per row. Returns the contents of the
+/// menu window and the height of each row.
+pub(crate) fn content(
+    cr: &cairo::Context,
+    entries: &[MenuEntry],
+    attrs: &Attrs,
+) -> (PanelPopup, i32) {
+    let attrs = attrs.restyled().into_owned();
+    let layouts = entries
+        .iter()
+        .map(|entry| {
+            let layout = create_layout(cr);
+            layout.set_markup(entry.label.as_str());
+            attrs.apply_font(&layout);
+            layout
+        })
+        .collect::<Vec<_>>();
+    let (width, height) = layouts
+        .iter()
+        .map(pango::Layout::pixel_size)
+        .fold((0, 0), |(w, h), (width, height)| {
+            (w.max(width), h.max(height))
+        });
+    let row_height = height + 2 * PADDING;
+
+    let popup = PanelPopup::new(
+        (width + 2 * PADDING, row_height * layouts.len() as i32),
+        Box::new(move |cr, _| {
+            attrs.apply_fg(cr);
+            for (idx, layout) in layouts.iter().enumerate() {
+                update_layout(cr, layout);
+                cr.move_to(
+                    f64::from(PADDING),
+                    f64::from(row_height * idx as i32 + PADDING),
+                );
+                show_layout(cr, layout);
+            }
+            Ok(())
+        }),
+        Arc::new(AtomicBool::new(true)),
+    );
+    (popup, row_height)
+}
@@ -19,7 +19,10 @@ use tokio_stream::{Stream, StreamExt, StreamMap};
 use crate::{
     array_to_struct,
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{
+        draw_gauge, draw_graph, draw_panel, Display, PanelCommon, ShowHide,
+    },
+    demo, format,
     ipc::ChannelEndpoint,
     remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
     ManagedIntervalStream, PanelConfig, PanelStream, Ramp,
@@ -47,75 +50,114 @@ pub struct Battery {
     #[builder(default, setter(strip_option))]
     highlight: Option<Highlight>,
     ramp: Ramp,
+    #[builder(default)]
+    display: Display,
     common: PanelCommon,
 }
 
 impl Battery {
     fn draw(
-        &self,
+        &mut self,
         cr: &Rc<cairo::Context>,
         height: i32,
         paused: Arc<Mutex<bool>>,
     ) -> Result<PanelDrawInfo> {
-        let mut capacity_f = File::open(format!(
-            "/sys/class/power_supply/{}/capacity",
-            self.battery
-        ))?;
-        let mut capacity = String::new();
-        capacity_f.read_to_string(&mut capacity)?;
+        let demo = demo::enabled().then(demo::battery);
+        let capacity = if let Some((capacity, _)) = demo {
+            capacity.to_string()
+        } else {
+            let mut capacity_f = File::open(format!(
+                "/sys/class/power_supply/{}/capacity",
+                self.battery
+            ))?;
+            let mut capacity = String::new();
+            capacity_f.read_to_string(&mut capacity)?;
+            capacity
+        };
         let capacity_val = capacity.trim().parse::<u8>()?;
-        let text =
+        let format =
             if self.full_at.is_some_and(|full_at| capacity_val > full_at) {
-                self.formats.full.replace("%percentage%", capacity.trim())
+                self.formats.full.as_str()
             } else {
-                let mut status_f = File::open(format!(
-                    "/sys/class/power_supply/{}/status",
-                    self.battery
-                ))?;
-                let mut status = String::new();
-                status_f.read_to_string(&mut status)?;
+                let status = if let Some((_, status)) = demo {
+                    String::from(status)
+                } else {
+                    let mut status_f = File::open(format!(
+                        "/sys/class/power_supply/{}/status",
+                        self.battery
+                    ))?;
+                    let mut status = String::new();
+                    status_f.read_to_string(&mut status)?;
+                    status
+                };
 
                 match status.trim() {
-                    "Charging" => self
-                        .formats
-                        .charging
-                        .replace("%percentage%", capacity.trim()),
-                    "Discharging" => self
-                        .formats
-                        .discharging
-                        .replace("%percentage%", capacity.trim()),
-                    "Not charging" => self
-                        .formats
-                        .not_charging
-                        .replace("%percentage%", capacity.trim()),
-                    "Full" => self
-                        .formats
-                        .full
-                        .replace("%percentage%", capacity.trim()),
-                    "Unknown" => self
-                        .formats
-                        .unknown
-                        .replace("%percentage%", capacity.trim()),
-                    _ => String::from("Unknown battery state"),
+                    "Charging" => self.formats.charging.as_str(),
+                    "Discharging" => self.formats.discharging.as_str(),
+                    "Not charging" => self.formats.not_charging.as_str(),
+                    "Full" => self.formats.full.as_str(),
+                    "Unknown" => self.formats.unknown.as_str(),
+                    _ => "Unknown battery state",
                 }
-            }
-            .replace(
-                "%ramp%",
-                self.ramp
-                    .choose(capacity.trim().parse::<u32>()?, 0, 100)
-                    .as_str(),
-            );
-
-        draw_common(
-            cr,
-            text.as_str(),
-            &self.attrs,
-            self.common.dependence,
-            self.highlight.clone(),
-            self.common.images.clone(),
-            height,
-            ShowHide::Default(paused, self.waker.clone()),
+            };
+        let text = format::replace_number(
+            format,
+            "percentage",
+            f64::from(capacity_val),
+            capacity.trim(),
         )
+        .replace(
+            "%ramp%",
+            self.ramp
+                .choose(capacity.trim().parse::<u32>()?, 0, 100)
+                .as_str(),
+        );
+        let text = self.common.progress.replace(
+            text.as_str(),
+            f64::from(capacity_val),
+            0.0,
+            100.0,
+        );
+
+        let charge = f64::from(capacity_val);
+        let attrs = self.common.thresholds.attrs(&self.attrs, charge);
+        let attrs = attrs.ramped(charge, 0.0, 100.0);
+        let show_hide = ShowHide::Default(paused, self.waker.clone());
+        match self.display {
+            Display::Text => draw_panel(
+                cr,
+                text.as_str(),
+                &attrs,
+                &self.common,
+                self.highlight.clone(),
+                height,
+                show_hide,
+            ),
+            Display::Graph(ref mut graph) => {
+                graph.push(Some(f64::from(capacity_val)));
+                draw_graph(
+                    cr,
+                    graph,
+                    text.as_str(),
+                    &attrs,
+                    &self.common,
+                    self.highlight.clone(),
+                    height,
+                    show_hide,
+                )
+            }
+            Display::Gauge(ref gauge) => draw_gauge(
+                cr,
+                gauge,
+                f64::from(capacity_val) / 100.0,
+                text.as_str(),
+                &attrs,
+                &self.common,
+                self.highlight.clone(),
+                height,
+                show_hide,
+            ),
+        }
     }
 }
 
@@ -135,33 +177,40 @@ impl PanelConfig for Battery {
     ///   the provided value.
     /// - `format_charging`: format string when the battery is charging
     ///   - type: String
-    ///   - formatting options: `%percentage%`
+    ///   - formatting options: `%percentage%`, `%ramp%`, `%bar%`
     ///   - default: "CHG: %percentage%%"
     /// - `format_discharging`: format string when the battery is discharging
     ///   - type: String
-    ///   - formatting options: `%percentage%`
+    ///   - formatting options: `%percentage%`, `%ramp%`, `%bar%`
     ///   - default: "DSCHG: %percentage%%"
     /// - `format_not_charging`: format string when the battery is not charging
     ///   - type: String
-    ///   - formatting options: `%percentage%`
+    ///   - formatting options: `%percentage%`, `%ramp%`, `%bar%`
     ///   - default: "NCHG: %percentage%%"
     /// - `format_full`: format string when the battery is full
     ///   - type: String
-    ///   - formatting options: `%percentage%`
+    ///   - formatting options: `%percentage%`, `%ramp%`, `%bar%`
     ///   - default: "FULL: %percentage%%"
     /// - `format_unknown`: format string when the battery is unknown
     ///   - type: String
-    ///   - formatting options: `%percentage%`
+    ///   - formatting options: `%percentage%`, `%ramp%`, `%bar%`
     ///   - default: "%percentage%%"
+    /// - In every format, `%percentage%` accepts modifiers like
+    ///   `%percentage:pad=3%`. See [`format::replace_number`].
     /// - `interval`: how often (in seconds) to poll for new values
     ///   - type: u64
     ///   - default: 10
     /// - `attrs`: A string specifying the attrs for the panel. See
-    ///   [`Attrs::parse`] for details.
+    ///   [`Attrs::parse`] for details. A `fg_ramp` uses the charge from 0 to
+    ///   100, as do `warn_below` and `crit_below` (see
+    ///   [`Thresholds::parse`][crate::threshold::Thresholds::parse]).
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
     /// - `ramp`: A string specifying the ramp to show battery level. See
     ///   [`Ramp::parse`] for details.
+    /// - `display`: `graph` draws a sparkline of the battery level instead of
+    ///   the format string, and `gauge` draws a ring filled by the battery
+    ///   level around it. See [`Display::parse`] for the other options.
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
@@ -206,6 +255,11 @@ impl PanelConfig for Battery {
         builder.attrs(PanelCommon::parse_attr(table, ""));
         builder.highlight(PanelCommon::parse_highlight(table, ""));
         builder.ramp(PanelCommon::parse_ramp(table, ""));
+        let mut display = Display::parse(table);
+        if let Display::Graph(ref mut graph) = display {
+            graph.max.get_or_insert(100.0);
+        }
+        builder.display(display);
 
         builder.common(common);
 
@@ -228,7 +282,12 @@ impl PanelConfig for Battery {
         let mut map =
             StreamMap::<_, Pin<Box<dyn Stream<Item = ()>>>>::with_capacity(2);
 
-        let interval = Arc::new(Mutex::new(interval(self.duration)));
+        let duration = if demo::enabled() {
+            self.duration.min(demo::TICK)
+        } else {
+            self.duration
+        };
+        let interval = Arc::new(Mutex::new(interval(duration)));
         let paused = Arc::new(Mutex::new(false));
         let waker = Arc::new(AtomicWaker::new());
 
@@ -0,0 +1,427 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use zbus::{
+    fdo::ObjectManagerProxy,
+    names::OwnedInterfaceName,
+    proxy,
+    zvariant::{OwnedObjectPath, OwnedValue},
+    Connection,
+};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream, Ramp,
+};
+
+const BLUEZ: &str = "org.bluez";
+
+type Properties = HashMap<String, OwnedValue>;
+
+#[proxy(interface = "org.bluez.Adapter1", default_service = "org.bluez")]
+trait Adapter1 {
+    #[zbus(property)]
+    fn powered(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_powered(&self, value: bool) -> zbus::Result<()>;
+}
+
+array_to_struct!(BluetoothFormats, off, on, connected);
+
+#[derive(Debug, Clone, Default)]
+struct Device {
+    name: String,
+    battery: Option<u8>,
+}
+
+/// The adapter and the devices connected through it
+#[derive(Debug, Clone, Default)]
+struct State {
+    adapter: Option<OwnedObjectPath>,
+    powered: bool,
+    devices: Vec<Device>,
+}
+
+#[derive(Debug)]
+enum Update {
+    State(Result<State>),
+    Event(Event),
+}
+
+/// Shows whether a Bluetooth adapter is powered, along with the name and
+/// battery level of a connected device, using `BlueZ` over D-Bus.
+///
+/// When several devices are connected, scrolling cycles through them. Left
+/// clicking toggles the adapter's power.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Bluetooth {
+    name: &'static str,
+    #[builder(default, setter(strip_option))]
+    adapter: Option<String>,
+    #[builder(default = "Duration::from_secs(5)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    state: State,
+    /// The index of the connected device being shown
+    #[builder(default, setter(skip))]
+    index: usize,
+    formats: BluetoothFormats<String>,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    ramp: Ramp,
+    common: PanelCommon,
+}
+
+impl Bluetooth {
+    fn update(
+        &mut self,
+        update: Update,
+        conn: &Connection,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::State(Ok(state)) => {
+                self.state = state;
+                None
+            }
+            Update::State(Err(e)) => {
+                log::warn!("{}: {e}", self.name);
+                self.state = State::default();
+                None
+            }
+            Update::Event(event) => {
+                Some(self.process_event(event, conn, refresh_send))
+            }
+        }
+    }
+
+    fn process_event(
+        &mut self,
+        event: Event,
+        conn: &Connection,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        let count = self.state.devices.len().max(1);
+        match event {
+            Event::Mouse(event) => match event.button {
+                MouseButton::Left => self.toggle_power(conn, refresh_send),
+                MouseButton::ScrollUp => {
+                    self.index = (self.index + count - 1) % count;
+                    Ok(())
+                }
+                MouseButton::ScrollDown => {
+                    self.index = (self.index + 1) % count;
+                    Ok(())
+                }
+                _ => Ok(()),
+            },
+            Event::Action(action) => match action.as_str() {
+                "power" => self.toggle_power(conn, refresh_send),
+                "prev" => {
+                    self.index = (self.index + count - 1) % count;
+                    Ok(())
+                }
+                "next" => {
+                    self.index = (self.index + 1) % count;
+                    Ok(())
+                }
+                _ => Err(anyhow!("Unknown event {action}")),
+            },
+            Event::MouseEnter | Event::MouseLeave => Ok(()),
+        }
+    }
+
+    fn toggle_power(
+        &self,
+        conn: &Connection,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        let path = self
+            .state
+            .adapter
+            .clone()
+            .ok_or_else(|| anyhow!("No Bluetooth adapter found"))?;
+        let powered = self.state.powered;
+        let conn = conn.clone();
+        let refresh_send = refresh_send.clone();
+        let name = self.name;
+        tokio::spawn(async move {
+            let result = async {
+                Adapter1Proxy::builder(&conn)
+                    .path(path)?
+                    .build()
+                    .await?
+                    .set_powered(!powered)
+                    .await
+            }
+            .await;
+            if let Err(e) = result {
+                log::warn!("{name}: failed to set adapter power: {e}");
+            }
+            let _ = refresh_send.send(());
+        });
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let devices = &self.state.devices;
+        let text = if !self.state.powered {
+            self.formats.off.clone()
+        } else if devices.is_empty() {
+            self.formats.on.clone()
+        } else {
+            let device = &devices[self.index.min(devices.len() - 1)];
+            self.formats
+                .connected
+                .replace(
+                    "%device%",
+                    glib::markup_escape_text(device.name.as_str()).as_str(),
+                )
+                .replace(
+                    "%battery%",
+                    device
+                        .battery
+                        .map_or_else(|| String::from("?"), |b| b.to_string())
+                        .as_str(),
+                )
+                .replace(
+                    "%ramp%",
+                    device
+                        .battery
+                        .map_or_else(String::new, |b| {
+                            self.ramp.choose(b, 0, 100)
+                        })
+                        .as_str(),
+                )
+                .replace("%count%", devices.len().to_string().as_str())
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Bluetooth {
+    /// Configuration options:
+    ///
+    /// - `adapter`: the name of the adapter to show, e.g. `hci0`
+    ///   - type: String
+    ///   - default: the first adapter found
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 5
+    /// - `format_off`: the format string when the adapter is powered off or
+    ///   missing
+    ///   - type: String
+    ///   - default: `BT off`
+    /// - `format_on`: the format string when the adapter is powered on and no
+    ///   devices are connected
+    ///   - type: String
+    ///   - default: `BT on`
+    /// - `format_connected`: the format string when a device is connected
+    ///   - type: String
+    ///   - default: `BT %device%`
+    ///   - formatting options: `%device%` (the device's alias), `%battery%`
+    ///     (its battery percentage, or `?` if it doesn't report one),
+    ///     `%ramp%`, `%count%` (the number of connected devices)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `ramp`: A string specifying the ramp to show device battery levels.
+    ///   See [`Ramp::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel toggles
+    ///   the adapter's power, and scrolling cycles through connected devices.
+    ///   The supported events are `power`, `next`, and `prev`.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = BluetoothBuilder::default();
+
+        builder.name(name);
+        if let Some(adapter) = remove_string_from_config("adapter", table) {
+            builder.adapter(adapter);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_off", "_on", "_connected"],
+            &["BT off", "BT on", "BT %device%"],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let ramp = PanelCommon::parse_ramp(table, "");
+
+        builder.common(common);
+        builder.formats(BluetoothFormats::new(formats));
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+        builder.ramp(ramp);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let conn = Connection::system().await?;
+
+        // the state is fetched again right after the power is toggled
+        let (refresh_send, refresh_recv) = unbounded_channel();
+        let fetch_conn = conn.clone();
+        let adapter = self.adapter.clone();
+        let states =
+            IntervalStream::new(interval(self.interval))
+                .map(|_| ())
+                .merge(UnboundedReceiverStream::new(refresh_recv))
+                .then(move |()| {
+                    let conn = fetch_conn.clone();
+                    let adapter = adapter.clone();
+                    async move {
+                        Update::State(fetch(&conn, adapter.as_deref()).await)
+                    }
+                });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = states.merge(events).map(move |update| {
+            if let Some(result) = self.update(update, &conn, &refresh_send) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+async fn fetch(conn: &Connection, adapter: Option<&str>) -> Result<State> {
+    let objects = ObjectManagerProxy::builder(conn)
+        .destination(BLUEZ)?
+        .path("/")?
+        .build()
+        .await?
+        .get_managed_objects()
+        .await?;
+
+    let mut adapters = objects
+        .iter()
+        .filter_map(|(path, interfaces)| {
+            Some((path, interface(interfaces, "org.bluez.Adapter1")?))
+        })
+        .filter(|(path, _)| {
+            adapter.map_or(true, |adapter| {
+                path.as_str().rsplit('/').next() == Some(adapter)
+            })
+        })
+        .collect::<Vec<_>>();
+    adapters.sort_by(|a, b| a.0.as_str().cmp(b.0.as_str()));
+    let Some((path, properties)) = adapters.first() else {
+        return Ok(State::default());
+    };
+
+    let prefix = format!("{}/", path.as_str());
+    let mut devices = objects
+        .iter()
+        .filter(|(device, _)| device.as_str().starts_with(prefix.as_str()))
+        .filter_map(|(device, interfaces)| {
+            let properties = interface(interfaces, "org.bluez.Device1")?;
+            if !get::<bool>(properties, "Connected").unwrap_or(false) {
+                return None;
+            }
+            Some((
+                device.as_str(),
+                Device {
+                    name: get::<&str>(properties, "Alias")
+                        .unwrap_or_default()
+                        .to_owned(),
+                    battery: interface(interfaces, "org.bluez.Battery1")
+                        .and_then(|battery| get(battery, "Percentage")),
+                },
+            ))
+        })
+        .collect::<Vec<_>>();
+    // keep the order stable so that scrolling is predictable
+    devices.sort_by(|a, b| a.0.cmp(b.0));
+
+    Ok(State {
+        adapter: Some((*path).clone()),
+        powered: get(properties, "Powered").unwrap_or(false),
+        devices: devices.into_iter().map(|(_, device)| device).collect(),
+    })
+}
+
+fn interface<'a>(
+    interfaces: &'a HashMap<OwnedInterfaceName, Properties>,
+    name: &str,
+) -> Option<&'a Properties> {
+    interfaces
+        .iter()
+        .find(|(interface, _)| interface.as_str() == name)
+        .map(|(_, properties)| properties)
+}
+
+fn get<'a, T>(properties: &'a Properties, name: &str) -> Option<T>
+where
+    T: TryFrom<&'a OwnedValue>,
+{
+    properties
+        .get(name)
+        .and_then(|value| T::try_from(value).ok())
+}
@@ -0,0 +1,499 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_array_from_config, remove_bool_from_config,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+array_to_struct!(CalendarFormats, event, none);
+
+#[derive(Debug, Clone)]
+struct Appointment {
+    start: DateTime<Local>,
+    all_day: bool,
+    title: String,
+}
+
+/// Where events are read from
+#[derive(Debug, Clone)]
+enum Source {
+    /// ICS files, or directories containing them
+    Ics(Vec<PathBuf>),
+    /// The output of `khal list`, with the formats used to parse start times
+    Khal {
+        datetime_format: String,
+        date_format: String,
+    },
+}
+
+impl Source {
+    fn load(&self, days: u64) -> Result<Vec<Appointment>> {
+        match self {
+            Self::Ics(paths) => {
+                let mut appointments = Vec::new();
+                for path in paths {
+                    read_ics(path, &mut appointments)?;
+                }
+                Ok(appointments)
+            }
+            Self::Khal {
+                datetime_format,
+                date_format,
+            } => {
+                let output = cleanup::command_output(
+                    Command::new("khal")
+                        .args(["list", "--day-format", ""])
+                        .args(["--format", "{start-long}\t{title}"])
+                        .args(["today", format!("{days}d").as_str()]),
+                )?;
+                if !output.status.success() {
+                    return Err(anyhow!(
+                        "khal list failed: {}",
+                        String::from_utf8_lossy(output.stderr.as_slice())
+                            .trim()
+                    ));
+                }
+                Ok(String::from_utf8_lossy(output.stdout.as_slice())
+                    .lines()
+                    .filter_map(|line| {
+                        let (start, title) = line.split_once('\t')?;
+                        let (start, all_day) = NaiveDateTime::parse_from_str(
+                            start.trim(),
+                            datetime_format,
+                        )
+                        .map(|start| (start, false))
+                        .or_else(|_| {
+                            NaiveDate::parse_from_str(start.trim(), date_format)
+                                .map(|date| {
+                                    (date.and_time(NaiveTime::MIN), true)
+                                })
+                        })
+                        .map_err(|e| {
+                            log::debug!(
+                                "Failed to parse khal time {start}: {e}"
+                            );
+                        })
+                        .ok()?;
+                        Some(Appointment {
+                            start: start
+                                .and_local_timezone(Local)
+                                .earliest()?,
+                            all_day,
+                            title: title.to_string(),
+                        })
+                    })
+                    .collect())
+            }
+        }
+    }
+}
+
+/// Reads the events from an ICS file, or from every ICS file in a directory
+/// and its subdirectories.
+fn read_ics(path: &Path, appointments: &mut Vec<Appointment>) -> Result<()> {
+    if path.is_dir() {
+        for entry in read_dir(path)? {
+            let path = entry?.path();
+            if path.is_dir() || path.extension().is_some_and(|ext| ext == "ics")
+            {
+                read_ics(path.as_path(), appointments)?;
+            }
+        }
+        return Ok(());
+    }
+
+    // long lines are folded by starting the continuation with whitespace
+    let text = read_to_string(path)?
+        .replace("\r\n", "\n")
+        .replace("\n ", "")
+        .replace("\n\t", "");
+
+    let mut start = None;
+    let mut title = String::new();
+    let mut cancelled = false;
+    let mut in_event = false;
+    for line in text.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // parameters like TZID follow the name, separated by semicolons
+        let (name, params) = name.split_once(';').unwrap_or((name, ""));
+        match (name, value) {
+            ("BEGIN", "VEVENT") => {
+                in_event = true;
+                start = None;
+                title.clear();
+                cancelled = false;
+            }
+            ("END", "VEVENT") => {
+                in_event = false;
+                if let (Some((start, all_day)), false) = (start, cancelled) {
+                    appointments.push(Appointment {
+                        start,
+                        all_day,
+                        title: title.clone(),
+                    });
+                }
+            }
+            ("DTSTART", _) if in_event => {
+                start = parse_ics_time(value, params);
+            }
+            ("SUMMARY", _) if in_event => {
+                title = value
+                    .replace("\\n", " ")
+                    .replace("\\N", " ")
+                    .replace("\\,", ",")
+                    .replace("\\;", ";")
+                    .replace("\\\\", "\\");
+            }
+            ("STATUS", "CANCELLED") if in_event => cancelled = true,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Parses the value of a `DTSTART` property. Times in UTC are converted to
+/// local time, and all other times are assumed to be local already.
+fn parse_ics_time(
+    value: &str,
+    params: &str,
+) -> Option<(DateTime<Local>, bool)> {
+    if (params.contains("VALUE=DATE") && !params.contains("VALUE=DATE-TIME"))
+        || value.len() == 8
+    {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+        let start = date.and_time(NaiveTime::MIN);
+        return Some((start.and_local_timezone(Local).earliest()?, true));
+    }
+    if let Some(value) = value.strip_suffix('Z') {
+        let start = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .ok()?
+            .and_utc();
+        return Some((start.with_timezone(&Local), false));
+    }
+    let start = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+    Some((start.and_local_timezone(Local).earliest()?, false))
+}
+
+/// Formats the time until an event, e.g. `2d 3h`, `1h 5m`, or `12m`.
+fn until(delta: TimeDelta) -> String {
+    let minutes = delta.num_minutes().max(0);
+    let (days, hours, minutes) =
+        (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Appointments(Result<Vec<Appointment>>),
+    Tick,
+}
+
+/// Displays the next upcoming event from a calendar, along with the time
+/// until it starts.
+///
+/// Events are read from local ICS files (such as those synced by
+/// `vdirsyncer`), or from `khal list`. Recurring events in ICS files only
+/// count their first occurrence, and times with a `TZID` are treated as local
+/// time, so use khal for calendars that rely on either of these.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Calendar {
+    name: &'static str,
+    source: Source,
+    #[builder(default = "Duration::from_secs(300)")]
+    interval: Duration,
+    #[builder(default = "7")]
+    days: u64,
+    #[builder(default)]
+    all_day: bool,
+    #[builder(default = "TimeDelta::minutes(10)")]
+    urgent: TimeDelta,
+    #[builder(default = r#"String::from("%H:%M")"#)]
+    time_format: String,
+    #[builder(default, setter(skip))]
+    appointments: Vec<Appointment>,
+    formats: CalendarFormats<String>,
+    attrs: Attrs,
+    attrs_urgent: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    #[builder(default, setter(strip_option))]
+    highlight_urgent: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Calendar {
+    fn update(&mut self, update: Update) {
+        match update {
+            Update::Appointments(Ok(mut appointments)) => {
+                appointments.sort_by_key(|appointment| appointment.start);
+                self.appointments = appointments;
+            }
+            // keep showing the old events if they can't be read
+            Update::Appointments(Err(e)) => {
+                log::warn!("{}: failed to read events: {e}", self.name);
+            }
+            Update::Tick => {}
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let now = Local::now();
+        let horizon = now + TimeDelta::days(self.days as i64);
+        let next = self.appointments.iter().find(|appointment| {
+            appointment.start >= now
+                && appointment.start < horizon
+                && (self.all_day || !appointment.all_day)
+        });
+
+        let (text, attrs, highlight) = match next {
+            None => (self.formats.none.clone(), &self.attrs, &self.highlight),
+            Some(appointment) => {
+                let delta = appointment.start - now;
+                let (attrs, highlight) = if delta <= self.urgent {
+                    (&self.attrs_urgent, &self.highlight_urgent)
+                } else {
+                    (&self.attrs, &self.highlight)
+                };
+                (
+                    self.formats
+                        .event
+                        .replace(
+                            "%title%",
+                            glib::markup_escape_text(
+                                appointment.title.as_str(),
+                            )
+                            .as_str(),
+                        )
+                        .replace("%until%", until(delta).as_str())
+                        .replace(
+                            "%time%",
+                            appointment
+                                .start
+                                .format(self.time_format.as_str())
+                                .to_string()
+                                .as_str(),
+                        ),
+                    attrs,
+                    highlight,
+                )
+            }
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            attrs,
+            &self.common,
+            highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Calendar {
+    /// Configuration options:
+    ///
+    /// - `source`: `ics` to read ICS files from `paths`, or `khal` to run
+    ///   `khal list`
+    ///   - type: String
+    ///   - default: `ics`
+    /// - `paths`: the ICS files to read. Directories are searched for files
+    ///   ending in `.ics`, including their subdirectories. Required for the
+    ///   `ics` source.
+    ///   - type: Array of Strings
+    ///   - default: none
+    /// - `khal_datetime_format`: the format of timed events in khal's output,
+    ///   which should match `longdatetimeformat` in khal's config
+    ///   - type: String
+    ///   - default: `%d.%m.%Y %H:%M`
+    /// - `khal_date_format`: the format of all-day events in khal's output,
+    ///   which should match `longdateformat` in khal's config
+    ///   - type: String
+    ///   - default: `%d.%m.%Y`
+    /// - `interval`: how often to read events, in seconds. The time until the
+    ///   next event is updated every 30 seconds regardless.
+    ///   - type: u64
+    ///   - default: 300
+    /// - `days`: how many days ahead to look for events
+    ///   - type: u64
+    ///   - default: 7
+    /// - `all_day`: whether to show all-day events
+    ///   - type: bool
+    ///   - default: false
+    /// - `urgent`: how many minutes before an event starts to switch to
+    ///   `attrs_urgent` and `highlight_urgent`
+    ///   - type: u64
+    ///   - default: 10
+    /// - `time_format`: the format of `%time%`. See
+    ///   [`chrono::format::strftime`] for details.
+    ///   - type: String
+    ///   - default: `%H:%M`
+    /// - `format_event`: the format string when there's an upcoming event
+    ///   - type: String
+    ///   - default: `%title% in %until%`
+    ///   - formatting options: `%title%`, `%until%` (e.g. `1h 5m`), `%time%`
+    ///     (the start time)
+    /// - `format_none`: the format string when there are no upcoming events
+    ///   - type: String
+    ///   - default: empty
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `attrs_urgent`: A string specifying the attrs for the panel shortly
+    ///   before an event. Anything not set is taken from `attrs`. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `highlight_urgent`: A string specifying the highlight for the panel
+    ///   shortly before an event. See [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CalendarBuilder::default();
+
+        builder.name(name);
+        let paths = remove_array_from_config("paths", table)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|path| path.into_string().ok().map(PathBuf::from))
+            .collect::<Vec<_>>();
+        let khal_datetime_format =
+            remove_string_from_config("khal_datetime_format", table)
+                .unwrap_or_else(|| String::from("%d.%m.%Y %H:%M"));
+        let khal_date_format =
+            remove_string_from_config("khal_date_format", table)
+                .unwrap_or_else(|| String::from("%d.%m.%Y"));
+        builder.source(
+            match remove_string_from_config("source", table).as_deref() {
+                None | Some("ics") => {
+                    if paths.is_empty() {
+                        return Err(anyhow!("{name}: no paths are set"));
+                    }
+                    Source::Ics(paths)
+                }
+                Some("khal") => Source::Khal {
+                    datetime_format: khal_datetime_format,
+                    date_format: khal_date_format,
+                },
+                Some(source) => {
+                    return Err(anyhow!("{name}: unknown source {source}"))
+                }
+            },
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(days) = remove_uint_from_config("days", table) {
+            builder.days(days);
+        }
+        if let Some(all_day) = remove_bool_from_config("all_day", table) {
+            builder.all_day(all_day);
+        }
+        if let Some(urgent) = remove_uint_from_config("urgent", table) {
+            builder.urgent(TimeDelta::minutes(urgent as i64));
+        }
+        if let Some(format) = remove_string_from_config("time_format", table) {
+            builder.time_format(format);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_event", "_none"],
+            &["%title% in %until%", ""],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let attrs_urgent = PanelCommon::parse_attr(table, "_urgent");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let highlight_urgent = PanelCommon::parse_highlight(table, "_urgent");
+
+        builder.common(common);
+        builder.formats(CalendarFormats::new(formats));
+        builder.attrs(attrs);
+        builder.attrs_urgent(attrs_urgent);
+        builder.highlight(highlight);
+        builder.highlight_urgent(highlight_urgent);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+        self.attrs_urgent.apply_to(&self.attrs);
+
+        let source = self.source.clone();
+        let days = self.days;
+        let appointments =
+            IntervalStream::new(interval(self.interval)).then(move |_| {
+                let source = source.clone();
+                async move {
+                    Update::Appointments(
+                        cleanup::spawn_blocking(move || source.load(days))
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|appointments| appointments),
+                    )
+                }
+            });
+        let mut tick = interval(Duration::from_secs(30));
+        // the first tick completes immediately
+        tick.reset();
+        let ticks = IntervalStream::new(tick).map(|_| Update::Tick);
+
+        let stream = appointments.merge(ticks).map(move |update| {
+            self.update(update);
+            self.draw(&cr, height)
+        });
+
+        Ok((Box::pin(stream), None))
+    }
+}
@@ -0,0 +1,273 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::task::AtomicWaker;
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    human_readable,
+    ipc::ChannelEndpoint,
+    remove_array_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, ManagedIntervalStream,
+    PanelConfig, PanelStream,
+};
+
+/// How deep to search below the root for a unit given by name.
+const MAX_DEPTH: usize = 4;
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    usage_usec: u64,
+    time: Instant,
+}
+
+/// Displays the CPU and memory usage of one or more cgroups (such as systemd
+/// slices and services) using the cgroup v2 interface files.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Cgroup {
+    name: &'static str,
+    units: Vec<String>,
+    #[builder(default = r#"PathBuf::from("/sys/fs/cgroup")"#)]
+    root: PathBuf,
+    #[builder(default = "Duration::from_secs(5)")]
+    interval: Duration,
+    #[builder(default)]
+    waker: Arc<AtomicWaker>,
+    /// Where each unit was last found.
+    #[builder(default, setter(skip))]
+    paths: HashMap<String, PathBuf>,
+    #[builder(default, setter(skip))]
+    samples: HashMap<String, Sample>,
+    format: &'static str,
+    separator: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Cgroup {
+    /// Returns the directory of a unit, searching for it if it hasn't been
+    /// found yet or has moved.
+    fn path(&mut self, unit: &str) -> Option<PathBuf> {
+        if let Some(path) = self.paths.get(unit) {
+            if path.is_dir() {
+                return Some(path.clone());
+            }
+        }
+        let path = if unit.contains('/') {
+            Some(self.root.join(unit.trim_start_matches('/')))
+                .filter(|path| path.is_dir())
+        } else {
+            find(&self.root, unit, MAX_DEPTH)
+        }?;
+        self.paths.insert(unit.to_owned(), path.clone());
+        Some(path)
+    }
+
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        paused: Arc<Mutex<bool>>,
+    ) -> Result<PanelDrawInfo> {
+        let mut parts = Vec::new();
+        for unit in self.units.clone() {
+            // units that aren't running have no cgroup
+            let Some(path) = self.path(unit.as_str()) else {
+                self.samples.remove(&unit);
+                continue;
+            };
+            let usage_usec = read_usage(&path)?;
+            let memory = read_to_string(path.join("memory.current"))?
+                .trim()
+                .parse::<u64>()?;
+
+            let now = Instant::now();
+            let cpu = self.samples.get(&unit).map_or(0.0, |last| {
+                let elapsed = now.duration_since(last.time).as_micros();
+                if elapsed == 0 {
+                    0.0
+                } else {
+                    usage_usec.saturating_sub(last.usage_usec) as f64
+                        / elapsed as f64
+                        * 100.0
+                }
+            });
+            self.samples.insert(
+                unit.clone(),
+                Sample {
+                    usage_usec,
+                    time: now,
+                },
+            );
+
+            let label = unit.rsplit('/').next().unwrap_or(unit.as_str());
+            parts.push(
+                self.format
+                    .replace("%unit%", label)
+                    .replace("%cpu%", format!("{cpu:.0}").as_str())
+                    .replace("%memory%", human_readable(memory).as_str()),
+            );
+        }
+
+        draw_panel(
+            cr,
+            parts.join(self.separator).as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::Default(paused, self.waker.clone()),
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Cgroup {
+    /// Configuration options:
+    ///
+    /// - `units`: the cgroups to show. A name without a `/`, like
+    ///   `user.slice` or `docker.service`, is searched for below `root`.
+    ///   Anything else is a path relative to `root`, like
+    ///   `system.slice/docker.service`. Units that aren't running are skipped.
+    ///   - type: Array of Strings
+    ///   - default: none (required)
+    /// - `root`: where the cgroup v2 hierarchy is mounted
+    ///   - type: String
+    ///   - default: `/sys/fs/cgroup`
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 5
+    /// - `format`: the format string for each unit
+    ///   - type: String
+    ///   - default: `%unit%: %cpu%% %memory%`
+    ///   - formatting options: `%unit%` (the last part of the unit's path),
+    ///     `%cpu%` (the CPU usage since the last check, where 100 is one full
+    ///     core), `%memory%` (the current memory usage, e.g. `1.5G`)
+    /// - `separator`: the string to put between units
+    ///   - type: String
+    ///   - default: ` ` (one space)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CgroupBuilder::default();
+
+        builder.name(name);
+        builder.units(
+            remove_array_from_config("units", table)
+                .map(|units| {
+                    units
+                        .into_iter()
+                        .filter_map(|v| v.into_string().ok())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|units| !units.is_empty())
+                .ok_or_else(|| anyhow!("{name}: `units` is required"))?,
+        );
+        if let Some(root) = remove_string_from_config("root", table) {
+            builder.root(PathBuf::from(root));
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        builder.separator(
+            remove_string_from_config("separator", table)
+                .unwrap_or_else(|| String::from(" "))
+                .leak(),
+        );
+
+        let common = PanelCommon::parse_common(table)?;
+        let format =
+            PanelCommon::parse_format(table, "", "%unit%: %cpu%% %memory%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let paused = Arc::new(Mutex::new(false));
+
+        let stream = ManagedIntervalStream::builder()
+            .duration(self.interval)
+            .paused(paused.clone())
+            .waker(self.waker.clone())
+            .build()?
+            .map(move |_| self.draw(&cr, height, paused.clone()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+/// Searches breadth-first for a directory named `unit` below `root`.
+fn find(root: &Path, unit: &str, max_depth: usize) -> Option<PathBuf> {
+    let mut dirs = vec![root.to_path_buf()];
+    for _ in 0..max_depth {
+        let mut children = Vec::new();
+        for dir in dirs {
+            let Ok(entries) = read_dir(dir) else {
+                continue;
+            };
+            for entry in entries.flatten() {
+                if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    continue;
+                }
+                if entry.file_name() == unit {
+                    return Some(entry.path());
+                }
+                children.push(entry.path());
+            }
+        }
+        dirs = children;
+    }
+    None
+}
+
+fn read_usage(path: &Path) -> Result<u64> {
+    read_to_string(path.join("cpu.stat"))?
+        .lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .ok_or_else(|| anyhow!("Failed to read cpu.stat in {path:?}"))?
+        .trim()
+        .parse::<u64>()
+        .map_err(anyhow::Error::from)
+}
@@ -3,17 +3,23 @@ use std::{
     pin::Pin,
     rc::Rc,
     str::FromStr,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
     task::{Context, Poll},
     time::Duration,
 };
 
 use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use chrono::{Local, Timelike};
+use chrono::{Datelike, Local, Months, NaiveDate, Timelike};
+#[cfg(feature = "timezones")]
+use chrono_tz::Tz;
 use config::{Config, Value};
 use derive_builder::Builder;
 use futures::task::AtomicWaker;
+use pangocairo::functions::{create_layout, show_layout, update_layout};
 use tokio::{
     sync::mpsc::{unbounded_channel, UnboundedSender},
     time::{interval, Instant, Interval},
@@ -24,9 +30,10 @@ use tokio_stream::{
 
 use crate::{
     actions::Actions,
-    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
+    popup::PanelPopup,
     remove_array_from_config, remove_string_from_config,
     remove_uint_from_config, Attrs, PanelConfig, PanelStream,
 };
@@ -79,12 +86,103 @@ impl FromStr for Precision {
     }
 }
 
+/// Without the `timezones` feature, only the local time zone can be shown.
+#[cfg(not(feature = "timezones"))]
+#[derive(Debug, Clone, Copy)]
+enum Tz {}
+
+/// A time zone shown by `%zones%`.
+#[derive(Debug, Clone)]
+struct Zone {
+    name: String,
+    /// `None` for the local time zone
+    tz: Option<Tz>,
+    format: String,
+}
+
+impl Zone {
+    fn parse(value: Value, default_format: &str) -> Result<Self> {
+        let mut table = value.into_table()?;
+        let tz = remove_string_from_config("tz", &mut table)
+            .ok_or_else(|| anyhow!("zone has no tz"))?;
+        let name = remove_string_from_config("name", &mut table)
+            .unwrap_or_else(|| {
+                tz.rsplit('/').next().unwrap_or_default().replace('_', " ")
+            });
+        let tz = match tz.as_str() {
+            "local" => None,
+            #[cfg(feature = "timezones")]
+            tz => Some(tz.parse::<Tz>().map_err(|e| anyhow!("{e}"))?),
+            #[cfg(not(feature = "timezones"))]
+            tz => {
+                return Err(anyhow!(
+                    "Time zone {tz} requires the timezones feature"
+                ))
+            }
+        };
+        let format = remove_string_from_config("format", &mut table)
+            .unwrap_or_else(|| default_format.to_string());
+        Ok(Self { name, tz, format })
+    }
+
+    fn format(&self, now: chrono::DateTime<Local>) -> String {
+        // the name is inserted before formatting, so escape any % signs
+        let format = self
+            .format
+            .replace("%name%", self.name.replace('%', "%%").as_str());
+        match self.tz {
+            None => now.format(format.as_str()).to_string(),
+            #[cfg(feature = "timezones")]
+            Some(tz) => {
+                now.with_timezone(&tz).format(format.as_str()).to_string()
+            }
+            #[cfg(not(feature = "timezones"))]
+            Some(tz) => match tz {},
+        }
+    }
+}
+
+/// The space in pixels between the calendar and the edges of its popup
+const CALENDAR_PADDING: i32 = 8;
+
+/// Lays out the month containing `today` like `cal`, with weeks starting on
+/// Monday and today in bold.
+fn calendar(today: NaiveDate) -> String {
+    let first = today.with_day(1).unwrap();
+    let days = (first + Months::new(1) - first).num_days() as u32;
+    let offset = first.weekday().num_days_from_monday();
+
+    let mut lines = vec![
+        format!("{:^20}", first.format("%B %Y").to_string()),
+        String::from("Mo Tu We Th Fr Sa Su"),
+    ];
+    let mut line = "   ".repeat(offset as usize);
+    for day in 1..=days {
+        if day == today.day() {
+            line.push_str(format!("<b>{day:>2}</b>").as_str());
+        } else {
+            line.push_str(format!("{day:>2}").as_str());
+        }
+        if (offset + day) % 7 == 0 {
+            lines.push(std::mem::take(&mut line));
+        } else {
+            line.push(' ');
+        }
+    }
+    if !line.trim().is_empty() {
+        lines.push(line.trim_end().to_string());
+    }
+    format!("<tt>{}</tt>", lines.join("\n"))
+}
+
 /// Displays the current time, updating at a given precision.
 ///
 /// Uses an [`Interval`] to update as close to the unit boundaries as possible.
 ///
 /// Available actions: `cycle` and `cycle_back` to change the format that is
-/// used
+/// used, and `cycle_zone` and `cycle_zone_back` to change the time zone that
+/// is shown when `zone_mode` is `cycle`, and `calendar` to open or close a
+/// popup with a calendar of the current month
 #[derive(Builder, Debug, Clone)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
@@ -98,8 +196,17 @@ pub struct Clock {
     formats: Vec<String>,
     precisions: Vec<Precision>,
     attrs: Vec<Attrs>,
+    #[builder(default)]
+    zones: Vec<Zone>,
+    #[builder(default = r#"String::from(" | ")"#)]
+    zone_separator: String,
+    #[builder(default)]
+    cycle_zones: bool,
+    zone_idx: Arc<Mutex<(usize, usize)>>,
     #[builder(default = "Duration::from_millis(1)")]
     offset: Duration,
+    #[builder(default)]
+    calendar_open: Arc<AtomicBool>,
     common: PanelCommon,
 }
 
@@ -113,32 +220,109 @@ impl Clock {
     ) -> Result<PanelDrawInfo> {
         data?;
         let now = chrono::Local::now();
-        let text = now
-            .format(&self.formats[self.idx.lock().unwrap().0])
-            .to_string();
+        let format = &self.formats[self.idx.lock().unwrap().0];
+        let text = if format.contains("%zones%") {
+            let zones = if self.cycle_zones {
+                self.zones
+                    .get(self.zone_idx.lock().unwrap().0)
+                    .map(|zone| zone.format(now))
+                    .unwrap_or_default()
+            } else {
+                self.zones
+                    .iter()
+                    .map(|zone| zone.format(now))
+                    .collect::<Vec<_>>()
+                    .join(self.zone_separator.as_str())
+            };
+            // the zones are already formatted, so escape any % signs
+            now.format(
+                format
+                    .replace("%zones%", zones.replace('%', "%%").as_str())
+                    .as_str(),
+            )
+            .to_string()
+        } else {
+            now.format(format).to_string()
+        };
 
-        draw_common(
+        let attrs = &self.attrs[self.idx.lock().unwrap().0];
+        let draw_info = draw_panel(
             cr,
             text.as_str(),
-            &self.attrs[self.idx.lock().unwrap().0],
-            self.common.dependence,
+            attrs,
+            &self.common,
             None,
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
+        )?;
+
+        Ok(if self.calendar_open.load(Ordering::Relaxed) {
+            draw_info.with_popup(self.calendar(cr, attrs, now.date_naive()))
+        } else {
+            draw_info
+        })
+    }
+
+    fn calendar(
+        &self,
+        cr: &Rc<cairo::Context>,
+        attrs: &Attrs,
+        today: NaiveDate,
+    ) -> PanelPopup {
+        let attrs = attrs.restyled().into_owned();
+        let layout = create_layout(cr);
+        layout.set_markup(calendar(today).as_str());
+        attrs.apply_font(&layout);
+        let (width, height) = layout.pixel_size();
+
+        PanelPopup::new(
+            (width + 2 * CALENDAR_PADDING, height + 2 * CALENDAR_PADDING),
+            Box::new(move |cr, _| {
+                update_layout(cr, &layout);
+                attrs.apply_fg(cr);
+                cr.move_to(
+                    f64::from(CALENDAR_PADDING),
+                    f64::from(CALENDAR_PADDING),
+                );
+                show_layout(cr, &layout);
+                Ok(())
+            }),
+            self.calendar_open.clone(),
         )
     }
 
     fn process_event(
         event: Event,
         idx: Arc<Mutex<(usize, usize)>>,
+        zone_idx: Arc<Mutex<(usize, usize)>>,
         actions: Actions,
         precision: Arc<Mutex<Precision>>,
         precisions: &[Precision],
+        calendar_open: &AtomicBool,
         send: UnboundedSender<EventResponse>,
         waker: &Arc<AtomicWaker>,
     ) -> Result<()> {
         match event {
+            Event::Action(value) if value == "calendar" => {
+                // the event itself triggers a redraw
+                calendar_open.fetch_xor(true, Ordering::Relaxed);
+                send.send(EventResponse::Ok)?;
+            }
+            Event::Action(value)
+                if value == "cycle_zone" || value == "cycle_zone_back" =>
+            {
+                let mut zone_idx = zone_idx.lock().unwrap();
+                let (current, len) = *zone_idx;
+                if len > 0 {
+                    zone_idx.0 = if value == "cycle_zone" {
+                        (current + 1) % len
+                    } else {
+                        (current + len - 1) % len
+                    };
+                }
+                drop(zone_idx);
+                send.send(EventResponse::Ok)?;
+            }
             Event::Action(value) => {
                 let mut idx = idx.lock().unwrap();
                 let new_idx = match value.as_str() {
@@ -163,20 +347,17 @@ impl Clock {
                 drop(idx);
                 send.send(EventResponse::Ok)?;
             }
+            Event::MouseEnter | Event::MouseLeave => {}
             Event::Mouse(event) => {
-                let action = match event.button {
-                    MouseButton::Left => actions.left.clone(),
-                    MouseButton::Right => actions.right.clone(),
-                    MouseButton::Middle => actions.middle.clone(),
-                    MouseButton::ScrollUp => actions.up.clone(),
-                    MouseButton::ScrollDown => actions.down.clone(),
-                };
+                let action = actions.get(&event).to_string();
                 Self::process_event(
                     Event::Action(action),
                     idx,
+                    zone_idx,
                     actions,
                     precision,
                     precisions,
+                    calendar_open,
                     send,
                     waker,
                 )?;
@@ -207,8 +388,26 @@ impl PanelConfig for Clock {
     ///   `attrs` is unset.
     /// - `offset`: This panel will anticipate a delay of this many milliseconds
     ///   and trigger early. The default value is 1.
-    /// - See [`PanelCommon::parse_common`]. The supported events are `cycle`
-    ///   and `cycle_back`.
+    /// - `zones`: An array of tables, each describing a time zone to show in
+    ///   place of `%zones%` in a format string. Each table has the keys
+    ///   - `tz`: the IANA name of the time zone, like `America/New_York`, or
+    ///     `local`. Zones other than `local` require the `timezones`
+    ///     feature.
+    ///   - `name`: the name to show for `%name%`. The default is the last
+    ///     part of `tz`, like `New York`.
+    ///   - `format`: the format string for this zone. The default is
+    ///     `zone_format`.
+    /// - `zone_format`: The default format string for each zone. `%name%` is
+    ///   replaced by the zone's name. The default value is `%name% %H:%M`.
+    /// - `zone_mode`: `all` to show every zone at once, or `cycle` to show one
+    ///   zone at a time, changing with the `cycle_zone` and `cycle_zone_back`
+    ///   events (e.g. `click_left = "cycle_zone"`). The default value is
+    ///   `all`.
+    /// - `zone_separator`: The text between zones when `zone_mode` is `all`.
+    ///   The default value is ` | `.
+    /// - See [`PanelCommon::parse_common`]. The supported events are `cycle`,
+    ///   `cycle_back`, `cycle_zone`, `cycle_zone_back`, and `calendar` (e.g.
+    ///   `click_left = "calendar"`).
     fn parse(
         name: &'static str,
         table: &mut HashMap<String, Value>,
@@ -272,6 +471,34 @@ impl PanelConfig for Clock {
             builder.offset(Duration::from_millis(offset));
         }
 
+        let zone_format = remove_string_from_config("zone_format", table)
+            .unwrap_or_else(|| String::from("%name% %H:%M"));
+        let zones = remove_array_from_config("zones", table)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|zone| {
+                Zone::parse(zone, zone_format.as_str())
+                    .map_err(|e| log::warn!("{name}: ignoring zone: {e}"))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        builder.zone_idx(Arc::new(Mutex::new((0, zones.len()))));
+        builder.zones(zones);
+        match remove_string_from_config("zone_mode", table).as_deref() {
+            None | Some("all") => {}
+            Some("cycle") => {
+                builder.cycle_zones(true);
+            }
+            Some(mode) => {
+                return Err(anyhow!("{name}: unknown zone_mode {mode}"))
+            }
+        }
+        if let Some(separator) =
+            remove_string_from_config("zone_separator", table)
+        {
+            builder.zone_separator(separator);
+        }
+
         Ok(builder.build()?)
     }
 
@@ -291,10 +518,12 @@ impl PanelConfig for Clock {
         }
 
         let idx = self.idx.clone();
+        let zone_idx = self.zone_idx.clone();
         let actions = self.common.actions.clone();
         let precision = self.precision.clone();
         let precisions = self.precisions.clone();
         let waker = self.waker.clone();
+        let calendar_open = self.calendar_open.clone();
         let paused = Arc::new(Mutex::new(false));
         let (event_send, event_recv) = unbounded_channel();
         let (response_send, response_recv) = unbounded_channel();
@@ -305,14 +534,17 @@ impl PanelConfig for Clock {
             0,
             Box::pin(UnboundedReceiverStream::new(event_recv).map(move |s| {
                 let idx = idx.clone();
+                let zone_idx = zone_idx.clone();
                 let actions = actions.clone();
                 let send = response_send.clone();
                 Self::process_event(
                     s,
                     idx,
+                    zone_idx,
                     actions,
                     precision.clone(),
                     &precisions,
+                    &calendar_open,
                     send,
                     &waker,
                 )
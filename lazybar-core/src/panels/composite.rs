@@ -0,0 +1,140 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    state, Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+lazy_static! {
+    static ref REGEX: Regex = Regex::new(r"%(?<key>[\w-]+\.\w+)%").unwrap();
+}
+
+/// Combines values published by other panels into one format string,
+/// updating whenever any of them change.
+///
+/// Values are referenced as `%<panel>.<token>%`, where `<panel>` is the name
+/// of the panel's table in the config file. The following values are
+/// currently published:
+/// - cpu: `percentage`
+/// - memory: `percentage_used`, `percentage_swap_used`
+/// - temp: `temp`, `temp<n>`
+#[derive(Builder, Debug, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Composite {
+    name: &'static str,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Composite {
+    fn text(&self) -> String {
+        REGEX
+            .replace_all(self.format, |caps: &Captures| {
+                state::get(&caps["key"]).unwrap_or_default()
+            })
+            .to_string()
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        text: &str,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        draw_panel(
+            cr,
+            text,
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Composite {
+    /// Configuration options:
+    ///
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: none
+    ///   - formatting options: `%<panel>.<token>%` for any published value
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CompositeBuilder::default();
+
+        builder.name(name);
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let mut last = None;
+        let stream = tokio_stream::once(())
+            .chain(futures::stream::unfold(
+                state::subscribe(),
+                |mut recv| async move {
+                    recv.changed().await.ok()?;
+                    Some(((), recv))
+                },
+            ))
+            .filter_map(move |()| {
+                let text = self.text();
+                if last.as_ref() == Some(&text) {
+                    None
+                } else {
+                    let draw_info = self.draw(&cr, text.as_str(), height);
+                    last = Some(text);
+                    Some(draw_info)
+                }
+            });
+
+        Ok((Box::pin(stream), None))
+    }
+}
@@ -17,10 +17,13 @@ use tokio_stream::StreamExt;
 
 use crate::{
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{
+        draw_gauge, draw_graph, draw_panel, Display, PanelCommon, ShowHide,
+    },
+    format,
     ipc::ChannelEndpoint,
-    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
-    ManagedIntervalStream, PanelConfig, PanelStream, Ramp,
+    remove_string_from_config, remove_uint_from_config, state, Attrs,
+    Highlight, ManagedIntervalStream, PanelConfig, PanelStream, Ramp,
 };
 
 lazy_static! {
@@ -46,6 +49,8 @@ pub struct Cpu {
     #[builder(default, setter(strip_option))]
     highlight: Option<Highlight>,
     ramp: Ramp,
+    #[builder(default)]
+    display: Display,
     common: PanelCommon,
 }
 
@@ -64,26 +69,60 @@ impl Cpu {
             / diff as f64
             * 100.0;
 
-        let text = self
-            .format
-            .replace("%percentage%", format!("{percentage:.0}").as_str())
-            .replace(
-                "%ramp%",
-                self.ramp.choose(percentage, 0.0, 100.0).as_str(),
-            );
+        state::publish(self.name, "percentage", format!("{percentage:.0}"));
+
+        let text = format::replace_number(
+            self.format,
+            "percentage",
+            percentage,
+            format!("{percentage:.0}").as_str(),
+        )
+        .replace("%ramp%", self.ramp.choose(percentage, 0.0, 100.0).as_str());
+        let text =
+            self.common
+                .progress
+                .replace(text.as_str(), percentage, 0.0, 100.0);
 
         self.last_load = load;
 
-        draw_common(
-            cr,
-            text.as_str(),
-            &self.attrs,
-            self.common.dependence,
-            self.highlight.clone(),
-            self.common.images.clone(),
-            height,
-            ShowHide::Default(paused, self.waker.clone()),
-        )
+        let attrs = self.common.thresholds.attrs(&self.attrs, percentage);
+        let attrs = attrs.ramped(percentage, 0.0, 100.0);
+        let show_hide = ShowHide::Default(paused, self.waker.clone());
+        match self.display {
+            Display::Text => draw_panel(
+                cr,
+                text.as_str(),
+                &attrs,
+                &self.common,
+                self.highlight.clone(),
+                height,
+                show_hide,
+            ),
+            Display::Graph(ref mut graph) => {
+                graph.push(Some(percentage).filter(|p| p.is_finite()));
+                draw_graph(
+                    cr,
+                    graph,
+                    text.as_str(),
+                    &attrs,
+                    &self.common,
+                    self.highlight.clone(),
+                    height,
+                    show_hide,
+                )
+            }
+            Display::Gauge(ref gauge) => draw_gauge(
+                cr,
+                gauge,
+                percentage / 100.0,
+                text.as_str(),
+                &attrs,
+                &self.common,
+                self.highlight.clone(),
+                height,
+                show_hide,
+            ),
+        }
     }
 }
 
@@ -102,13 +141,21 @@ impl PanelConfig for Cpu {
     /// - `format`: the format string
     ///   - type: String
     ///   - default: `CPU: %percentage%%`
-    ///   - formatting options: `%percentage%`
+    ///   - formatting options: `%percentage%`, `%ramp%`, `%bar%`. Modifiers
+    ///     like `%percentage:.1%` are supported; see
+    ///     [`format::replace_number`].
     /// - `attrs`: A string specifying the attrs for the panel. See
-    ///   [`Attrs::parse`] for details.
+    ///   [`Attrs::parse`] for details. A `fg_ramp` uses the usage from 0 to
+    ///   100, as do `warn_above` and `crit_above` (see
+    ///   [`Thresholds::parse`][crate::threshold::Thresholds::parse]).
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
     /// - `ramp`: A string specifying the ramp to show CPU usage. See
     ///   [`Ramp::parse`] for details.
+    /// - `display`: `graph` draws a sparkline of CPU usage instead of the
+    ///   format string, scaled from 0 to 100 unless `graph_max` is set.
+    ///   `gauge` draws a ring filled by CPU usage around the format string.
+    ///   See [`Display::parse`] for the other options.
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
@@ -136,6 +183,11 @@ impl PanelConfig for Cpu {
         builder.attrs(attr);
         builder.highlight(PanelCommon::parse_highlight(table, ""));
         builder.ramp(ramp);
+        let mut display = Display::parse(table);
+        if let Display::Graph(ref mut graph) = display {
+            graph.max.get_or_insert(100.0);
+        }
+        builder.display(display);
 
         Ok(builder.build()?)
     }
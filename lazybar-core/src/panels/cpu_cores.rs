@@ -0,0 +1,335 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::task::AtomicWaker;
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::PanelCommon,
+    ipc::ChannelEndpoint,
+    remove_color_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Color, ManagedIntervalStream, PanelConfig,
+    PanelStream,
+};
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Load {
+    idle: u64,
+    total: u64,
+}
+
+/// Reads the load of each logical CPU from the `cpuN` lines of `/proc/stat`,
+/// skipping the aggregate `cpu` line.
+fn read_core_loads(path: &str) -> Result<Vec<Load>> {
+    read_to_string(path)?
+        .lines()
+        .filter(|line| {
+            line.strip_prefix("cpu").is_some_and(|rest| {
+                rest.starts_with(|c: char| c.is_ascii_digit())
+            })
+        })
+        .map(|line| {
+            let fields = line
+                .split_whitespace()
+                .skip(1)
+                .map(str::parse::<u64>)
+                .collect::<Result<Vec<_>, _>>()?;
+            // user nice system idle iowait irq softirq steal
+            if fields.len() < 8 {
+                return Err(anyhow!("Malformed line in {path:?}: {line}"));
+            }
+            Ok(Load {
+                idle: fields[3],
+                total: fields[0]
+                    + fields[1]
+                    + fields[2]
+                    + fields[3]
+                    + fields[7],
+            })
+        })
+        .collect()
+}
+
+/// Returns the color `t` of the way from `from` to `to`.
+fn mix(from: &Color, to: &Color, t: f64) -> Color {
+    let t = t.clamp(0.0, 1.0) as f32;
+    Color::new(
+        (to.r - from.r).mul_add(t, from.r),
+        (to.g - from.g).mul_add(t, from.g),
+        (to.b - from.b).mul_add(t, from.b),
+        (to.a - from.a).mul_add(t, from.a),
+    )
+}
+
+/// Displays the usage of each logical CPU as a thin vertical bar, based on
+/// `/proc/stat`. Each bar grows with its usage, and its color moves from
+/// `color_idle` to `color_busy`.
+///
+/// This is meant to sit alongside [`Cpu`][crate::panels::Cpu] on machines
+/// with too many cores to show each one as a number.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct CpuCores {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default)]
+    waker: Arc<AtomicWaker>,
+    #[builder(default = r#"String::from("/proc/stat")"#)]
+    path: String,
+    last_loads: Vec<Load>,
+    #[builder(default = "3")]
+    bar_width: i32,
+    #[builder(default = "1")]
+    gap: i32,
+    #[builder(default = "2")]
+    padding_y: i32,
+    #[builder(default = r#""#4caf50".parse().unwrap()"#)]
+    color_idle: Color,
+    #[builder(default = r#""#f44336".parse().unwrap()"#)]
+    color_busy: Color,
+    #[builder(default, setter(strip_option))]
+    color_track: Option<Color>,
+    attrs: Attrs,
+    common: PanelCommon,
+}
+
+impl CpuCores {
+    fn draw(
+        &mut self,
+        height: i32,
+        paused: Arc<Mutex<bool>>,
+    ) -> Result<PanelDrawInfo> {
+        let loads = read_core_loads(self.path.as_str())?;
+        let usages = loads
+            .iter()
+            .zip(self.last_loads.iter())
+            .map(|(load, last)| {
+                let diff = load.total.saturating_sub(last.total);
+                if diff == 0 {
+                    0.0
+                } else {
+                    diff.saturating_sub(load.idle.saturating_sub(last.idle))
+                        as f64
+                        / diff as f64
+                }
+            })
+            .collect::<Vec<_>>();
+        self.last_loads = loads;
+
+        let cores = usages.len() as i32;
+        let bars_width = if cores == 0 {
+            0
+        } else {
+            cores * self.bar_width + (cores - 1) * self.gap
+        };
+        let (padding_left, padding_right, margin) = if cores == 0 {
+            (0, 0, 0)
+        } else {
+            (
+                self.common.padding_left,
+                self.common.padding_right,
+                self.common.margin,
+            )
+        };
+        let dims = (bars_width + padding_left + padding_right, height);
+
+        let bg = self.attrs.restyled().bg.clone().unwrap_or_default();
+        let (width, bg_height) = bg.adjust_dims(dims, height);
+        let effects = self.common.effects;
+        let bar_width = f64::from(self.bar_width);
+        let step = f64::from(self.bar_width + self.gap);
+        let max_height = f64::from((height - 2 * self.padding_y).max(0));
+        let top = f64::from(self.padding_y);
+        let colors = usages
+            .iter()
+            .map(|usage| mix(&self.color_idle, &self.color_busy, *usage))
+            .collect::<Vec<_>>();
+        let track = self.color_track.clone();
+        let text = usages
+            .iter()
+            .map(|usage| format!("{:.0}%", usage * 100.0))
+            .collect::<Vec<_>>()
+            .join(" ");
+
+        let paused_ = paused.clone();
+        let waker = self.waker.clone();
+
+        Ok(PanelDrawInfo::new(
+            (width + 2 * margin, bg_height),
+            self.common.dependence,
+            Box::new(move |cr, _| {
+                effects.apply(cr, f64::from(height), |cr| {
+                    cr.save()?;
+                    cr.translate(f64::from(margin), 0.0);
+                    let offset = bg.draw(
+                        cr,
+                        f64::from(dims.0),
+                        f64::from(dims.1),
+                        f64::from(height),
+                    )?;
+                    cr.translate(offset + f64::from(padding_left), top);
+
+                    for (idx, (usage, color)) in
+                        usages.iter().zip(colors.iter()).enumerate()
+                    {
+                        let x = idx as f64 * step;
+                        if let Some(ref track) = track {
+                            cr.set_source_rgba(
+                                track.r.into(),
+                                track.g.into(),
+                                track.b.into(),
+                                track.a.into(),
+                            );
+                            cr.rectangle(x, 0.0, bar_width, max_height);
+                            cr.fill()?;
+                        }
+                        let bar_height = (usage * max_height).round();
+                        cr.set_source_rgba(
+                            color.r.into(),
+                            color.g.into(),
+                            color.b.into(),
+                            color.a.into(),
+                        );
+                        cr.rectangle(
+                            x,
+                            max_height - bar_height,
+                            bar_width,
+                            bar_height,
+                        );
+                        cr.fill()?;
+                    }
+
+                    cr.restore()?;
+                    Ok(())
+                })
+            }),
+            Some(Box::new(move || {
+                *paused.lock().unwrap() = false;
+                waker.wake();
+                Ok(())
+            })),
+            Some(Box::new(move || {
+                *paused_.lock().unwrap() = true;
+                Ok(())
+            })),
+            None,
+        )
+        .with_text(text))
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for CpuCores {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 2
+    /// - `path`: the file path to check
+    ///   - type: String
+    ///   - default: `/proc/stat`
+    /// - `bar_width`: the width in pixels of each bar
+    ///   - type: u64
+    ///   - default: 3
+    /// - `gap`: the space in pixels between bars
+    ///   - type: u64
+    ///   - default: 1
+    /// - `padding_y`: the space in pixels above and below the bars
+    ///   - type: u64
+    ///   - default: 2
+    /// - `color_idle`: the color of a bar for an idle core
+    ///   - type: String
+    ///   - default: `#4caf50`
+    /// - `color_busy`: the color of a bar for a saturated core. Cores in
+    ///   between get a mix of the two colors.
+    ///   - type: String
+    ///   - default: `#f44336`
+    /// - `color_track`: the color of the full height of each bar, drawn
+    ///   behind it
+    ///   - type: String
+    ///   - default: none
+    /// - `attrs`: A string specifying the attrs for the panel. Only the
+    ///   background is used. See [`Attrs::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = CpuCoresBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(path) = remove_string_from_config("path", table) {
+            builder.last_loads(read_core_loads(path.as_str())?);
+            builder.path(path);
+        } else {
+            builder.last_loads(read_core_loads("/proc/stat")?);
+        }
+        if let Some(bar_width) = remove_uint_from_config("bar_width", table) {
+            builder.bar_width(bar_width.max(1) as i32);
+        }
+        if let Some(gap) = remove_uint_from_config("gap", table) {
+            builder.gap(gap as i32);
+        }
+        if let Some(padding_y) = remove_uint_from_config("padding_y", table) {
+            builder.padding_y(padding_y as i32);
+        }
+        if let Some(color) = remove_color_from_config("color_idle", table) {
+            builder.color_idle(color);
+        }
+        if let Some(color) = remove_color_from_config("color_busy", table) {
+            builder.color_busy(color);
+        }
+        if let Some(color) = remove_color_from_config("color_track", table) {
+            builder.color_track(color);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let attrs = PanelCommon::parse_attr(table, "");
+
+        builder.common(common);
+        builder.attrs(attrs);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        _cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let paused = Arc::new(Mutex::new(false));
+
+        let stream = ManagedIntervalStream::builder()
+            .duration(self.interval)
+            .paused(paused.clone())
+            .waker(self.waker.clone())
+            .build()?
+            .map(move |_| self.draw(height, paused.clone()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
@@ -12,15 +12,23 @@ use anyhow::Result;
 use async_trait::async_trait;
 use derive_builder::Builder;
 use futures::task::AtomicWaker;
-use tokio::time::{interval, Interval};
-use tokio_stream::{Stream, StreamExt};
+use tokio::{
+    sync::mpsc::unbounded_channel,
+    time::{interval, Interval},
+};
+use tokio_stream::{
+    wrappers::UnboundedReceiverStream, Stream, StreamExt, StreamMap,
+};
+use x11rb::protocol::xproto::KeyButMask;
 
 use crate::{
-    bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    bar::{Event, EventResponse, MouseButton, MouseEvent, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
-    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
-    PanelConfig, PanelStream,
+    remove_string_from_config, remove_uint_from_config,
+    sandbox::Sandbox,
+    Attrs, Highlight, PanelConfig, PanelStream,
 };
 
 /// Runs a custom command with `sh -c <command>`, either once or on a given
@@ -33,6 +41,8 @@ pub struct Custom {
     name: &'static str,
     #[builder(default = r#"Command::new("echo")"#)]
     command: Command,
+    #[builder(default, setter(strip_option))]
+    click_command: Option<Command>,
     #[builder(setter(strip_option))]
     interval: Option<Duration>,
     #[builder(default)]
@@ -49,9 +59,29 @@ impl Custom {
         &mut self,
         cr: &Rc<cairo::Context>,
         height: i32,
+        click: Option<MouseEvent>,
         paused: Arc<Mutex<bool>>,
     ) -> Result<PanelDrawInfo> {
-        let output = self.command.output()?;
+        let vars = click.map(click_vars).unwrap_or_default();
+
+        if click.is_some() {
+            if let Some(ref mut click_command) = self.click_command {
+                cleanup::spawn_command(
+                    click_command.envs(vars.iter().cloned()),
+                )?;
+                for (key, _) in &vars {
+                    click_command.env_remove(key);
+                }
+            }
+        }
+
+        let output =
+            cleanup::command_output(self.command.envs(vars.iter().cloned()));
+        for (key, _) in &vars {
+            self.command.env_remove(key);
+        }
+        let output = output?;
+
         let text = self
             .format
             .replace(
@@ -63,13 +93,12 @@ impl Custom {
                 String::from_utf8_lossy(output.stderr.as_slice()).as_ref(),
             );
 
-        draw_common(
+        draw_panel(
             cr,
             text.trim(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
@@ -87,42 +116,61 @@ impl PanelConfig for Custom {
     ///   - type: u64
     ///   - default: none
     ///   - if not present, the command will run exactly once.
+    /// - `click_command`: the command to run when the panel is clicked or
+    ///   scrolled
+    ///   - type: String
+    ///   - default: none
+    ///   - `click_command` runs in the background, and `command` is always
+    ///     rerun after a click. When run due to a click, both commands receive
+    ///     `LAZYBAR_BUTTON` (`left`, `middle`, `right`, `scroll_up`, or
+    ///     `scroll_down`), `LAZYBAR_X` (relative to the panel), `LAZYBAR_Y`,
+    ///     `LAZYBAR_MODIFIERS` (a comma-separated list of `shift`, `lock`,
+    ///     `control`, and `mod1` through `mod5`), `LAZYBAR_CLICKS` (2 for a
+    ///     double-click), `LAZYBAR_HELD` (`true` if the button was held
+    ///     down), and `LAZYBAR_SPAN` (the `%{span <name>}` that was clicked,
+    ///     if any) as environment variables. Without `click_command`, the
+    ///     panel doesn't respond to clicks.
     /// - `format`: the format string
     ///   - type: String
     ///   - default: `%stdout%`
     ///   - formatting options: `%stdout%`, `%stderr%`
+    ///   - the command can print `%image:<path>%` to show a PNG inline with
+    ///     its text. See [`draw_panel`].
     /// - `attrs`: A string specifying the attrs for the panel. See
     ///   [`Attrs::parse`] for details.
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
+    /// - See [`Sandbox::parse`] for options that restrict both commands.
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
         table: &mut HashMap<String, config::Value>,
         _global: &config::Config,
     ) -> Result<Self> {
+        let sandbox = Sandbox::parse(table)?;
+
         let builder = match (
             remove_string_from_config("command", table),
             remove_uint_from_config("interval", table),
         ) {
-            (Some(command), Some(interval)) => {
-                let mut cmd = Command::new("sh");
-                cmd.arg("-c").arg(command.as_str());
-                CustomBuilder::default()
-                    .command(cmd)
-                    .interval(Duration::from_secs(interval))
-            }
-            (Some(command), None) => {
-                let mut cmd = Command::new("sh");
-                cmd.arg("-c").arg(command.as_str());
-                CustomBuilder::default().command(cmd)
-            }
+            (Some(command), Some(interval)) => CustomBuilder::default()
+                .command(sandbox.command(command.as_str()))
+                .interval(Duration::from_secs(interval)),
+            (Some(command), None) => CustomBuilder::default()
+                .command(sandbox.command(command.as_str())),
             (None, Some(interval)) => {
                 CustomBuilder::default().interval(Duration::from_secs(interval))
             }
             (None, None) => CustomBuilder::default(),
         };
 
+        let builder = match remove_string_from_config("click_command", table) {
+            Some(click_command) => {
+                builder.click_command(sandbox.command(click_command.as_str()))
+            }
+            None => builder,
+        };
+
         let common = PanelCommon::parse_common(table)?;
         let format = PanelCommon::parse_format(table, "", "%stdout%");
         let attrs = PanelCommon::parse_attr(table, "");
@@ -152,20 +200,92 @@ impl PanelConfig for Custom {
 
         let paused = Arc::new(Mutex::new(false));
 
-        Ok((
+        let mut map = StreamMap::<
+            usize,
+            Pin<Box<dyn Stream<Item = Option<MouseEvent>>>>,
+        >::new();
+
+        map.insert(
+            0,
             Box::pin(
                 CustomStream::new(
                     self.interval.map(|d| interval(d)),
                     paused.clone(),
                     self.waker.clone(),
                 )
-                .map(move |()| self.draw(&cr, height, paused.clone())),
+                .map(|()| None),
             ),
-            None,
+        );
+
+        // without a click_command, a click would only rerun `command`
+        let endpoint = if self.click_command.is_some() {
+            let (event_send, event_recv) = unbounded_channel();
+            let (response_send, response_recv) = unbounded_channel();
+            map.insert(
+                1,
+                Box::pin(UnboundedReceiverStream::new(event_recv).filter_map(
+                    move |event| match event {
+                        Event::Mouse(event) => Some(Some(event)),
+                        Event::MouseEnter | Event::MouseLeave => None,
+                        Event::Action(event) => {
+                            let _ = response_send.send(EventResponse::Err(
+                                format!("Unknown event {event}"),
+                            ));
+                            None
+                        }
+                    },
+                )),
+            );
+            Some(ChannelEndpoint::new(event_send, response_recv))
+        } else {
+            None
+        };
+
+        Ok((
+            Box::pin(map.map(move |(_, click)| {
+                self.draw(&cr, height, click, paused.clone())
+            })),
+            endpoint,
         ))
     }
 }
 
+fn click_vars(event: MouseEvent) -> Vec<(&'static str, String)> {
+    let button = match event.button {
+        MouseButton::Left => "left",
+        MouseButton::Middle => "middle",
+        MouseButton::Right => "right",
+        MouseButton::ScrollUp => "scroll_up",
+        MouseButton::ScrollDown => "scroll_down",
+    };
+
+    let modifiers = [
+        (KeyButMask::SHIFT, "shift"),
+        (KeyButMask::LOCK, "lock"),
+        (KeyButMask::CONTROL, "control"),
+        (KeyButMask::MOD1, "mod1"),
+        (KeyButMask::MOD2, "mod2"),
+        (KeyButMask::MOD3, "mod3"),
+        (KeyButMask::MOD4, "mod4"),
+        (KeyButMask::MOD5, "mod5"),
+    ]
+    .into_iter()
+    .filter(|(mask, _)| event.modifiers & u16::from(*mask) != 0)
+    .map(|(_, name)| name)
+    .collect::<Vec<_>>()
+    .join(",");
+
+    vec![
+        ("LAZYBAR_BUTTON", button.to_string()),
+        ("LAZYBAR_X", event.x.to_string()),
+        ("LAZYBAR_Y", event.y.to_string()),
+        ("LAZYBAR_MODIFIERS", modifiers),
+        ("LAZYBAR_CLICKS", event.clicks.to_string()),
+        ("LAZYBAR_HELD", event.held.to_string()),
+        ("LAZYBAR_SPAN", event.span.unwrap_or_default().to_string()),
+    ]
+}
+
 struct CustomStream {
     interval: Option<Interval>,
     paused: Arc<Mutex<bool>>,
@@ -0,0 +1,344 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use zbus::{proxy, proxy::CacheProperties, Connection};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_uint_from_config, Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+#[proxy(
+    interface = "org.dunstproject.cmd0",
+    default_service = "org.freedesktop.Notifications",
+    default_path = "/org/freedesktop/Notifications"
+)]
+trait Dunst {
+    /// Shows the most recent notification from the history
+    fn notification_show(&self) -> zbus::Result<()>;
+
+    fn notification_close_last(&self) -> zbus::Result<()>;
+
+    fn notification_close_all(&self) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn paused(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_paused(&self, value: bool) -> zbus::Result<()>;
+
+    #[zbus(property, name = "displayedLength")]
+    fn displayed_length(&self) -> zbus::Result<u32>;
+
+    #[zbus(property, name = "historyLength")]
+    fn history_length(&self) -> zbus::Result<u32>;
+
+    #[zbus(property, name = "waitingLength")]
+    fn waiting_length(&self) -> zbus::Result<u32>;
+}
+
+array_to_struct!(DunstFormats, active, paused);
+
+#[derive(Debug, Clone, Copy, Default)]
+struct State {
+    paused: bool,
+    displayed: u32,
+    history: u32,
+    waiting: u32,
+}
+
+impl State {
+    async fn fetch(proxy: &DunstProxy<'_>) -> Result<Self> {
+        Ok(Self {
+            paused: proxy.paused().await?,
+            displayed: proxy.displayed_length().await?,
+            history: proxy.history_length().await?,
+            waiting: proxy.waiting_length().await?,
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    State(Result<State>),
+    Event(Event),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    Toggle,
+    Pop,
+    Close,
+    CloseAll,
+}
+
+/// Shows the number of notifications waiting while dunst is paused (do not
+/// disturb), along with the size of its history. Other daemons that
+/// implement dunst's `org.dunstproject.cmd0` interface work too.
+///
+/// Left clicking toggles do not disturb, scrolling up shows the most recent
+/// notification from the history, and scrolling down closes the most recent
+/// notification.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Dunst {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    state: Option<State>,
+    formats: DunstFormats<String>,
+    attrs: Attrs,
+    attrs_paused: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    #[builder(default, setter(strip_option))]
+    highlight_paused: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Dunst {
+    fn update(
+        &mut self,
+        update: Update,
+        proxy: &DunstProxy<'static>,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::State(Ok(state)) => {
+                self.state = Some(state);
+                None
+            }
+            Update::State(Err(e)) => {
+                log::warn!("{}: {e}", self.name);
+                self.state = None;
+                None
+            }
+            Update::Event(event) => {
+                Some(self.process_event(event, proxy, refresh_send))
+            }
+        }
+    }
+
+    fn process_event(
+        &self,
+        event: Event,
+        proxy: &DunstProxy<'static>,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        let command = match event {
+            Event::Mouse(event) => match event.button {
+                MouseButton::Left => Command::Toggle,
+                MouseButton::ScrollUp => Command::Pop,
+                MouseButton::ScrollDown => Command::Close,
+                _ => return Ok(()),
+            },
+            Event::MouseEnter | Event::MouseLeave => return Ok(()),
+            Event::Action(action) => match action.as_str() {
+                "toggle" => Command::Toggle,
+                "pop" => Command::Pop,
+                "close" => Command::Close,
+                "close_all" => Command::CloseAll,
+                _ => return Err(anyhow!("Unknown event {action}")),
+            },
+        };
+        let paused = self.state.is_some_and(|state| state.paused);
+
+        let proxy = proxy.clone();
+        let refresh_send = refresh_send.clone();
+        let name = self.name;
+        tokio::spawn(async move {
+            let result = match command {
+                Command::Toggle => proxy.set_paused(!paused).await,
+                Command::Pop => proxy.notification_show().await,
+                Command::Close => proxy.notification_close_last().await,
+                Command::CloseAll => proxy.notification_close_all().await,
+            };
+            if let Err(e) = result {
+                log::warn!("{name}: {command:?} failed: {e}");
+            }
+            let _ = refresh_send.send(());
+        });
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        // hidden if dunst isn't running
+        let (text, attrs, highlight) = match self.state {
+            None => (String::new(), &self.attrs, &self.highlight),
+            Some(state) => {
+                let (format, attrs, highlight) = if state.paused {
+                    (
+                        &self.formats.paused,
+                        &self.attrs_paused,
+                        &self.highlight_paused,
+                    )
+                } else {
+                    (&self.formats.active, &self.attrs, &self.highlight)
+                };
+                (
+                    format
+                        .replace(
+                            "%waiting%",
+                            state.waiting.to_string().as_str(),
+                        )
+                        .replace(
+                            "%displayed%",
+                            state.displayed.to_string().as_str(),
+                        )
+                        .replace(
+                            "%history%",
+                            state.history.to_string().as_str(),
+                        ),
+                    attrs,
+                    highlight,
+                )
+            }
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            attrs,
+            &self.common,
+            highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Dunst {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 2
+    /// - `format_active`: the format string when notifications are shown
+    ///   - type: String
+    ///   - default: `N %history%`
+    ///   - formatting options: `%waiting%` (notifications held back while
+    ///     paused), `%displayed%` (notifications on screen), `%history%`
+    ///     (notifications in the history)
+    /// - `format_paused`: the format string when do not disturb is on
+    ///   - type: String
+    ///   - default: `DND %waiting%`
+    ///   - formatting options: the same as `format_active`
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `attrs_paused`: A string specifying the attrs for the panel while do
+    ///   not disturb is on. Anything not set is taken from `attrs`. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `highlight_paused`: A string specifying the highlight for the panel
+    ///   while do not disturb is on. See [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. The supported events are
+    ///   `toggle`, `pop` (show the most recent notification from the
+    ///   history), `close`, and `close_all`.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = DunstBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_active", "_paused"],
+            &["N %history%", "DND %waiting%"],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let attrs_paused = PanelCommon::parse_attr(table, "_paused");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let highlight_paused = PanelCommon::parse_highlight(table, "_paused");
+
+        builder.common(common);
+        builder.formats(DunstFormats::new(formats));
+        builder.attrs(attrs);
+        builder.attrs_paused(attrs_paused);
+        builder.highlight(highlight);
+        builder.highlight_paused(highlight_paused);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+        self.attrs_paused.apply_to(&self.attrs);
+
+        let conn = Connection::session().await?;
+        // dunst doesn't signal changes to the lengths
+        let proxy = DunstProxy::builder(&conn)
+            .cache_properties(CacheProperties::No)
+            .build()
+            .await?;
+
+        // the state is fetched again right after each command
+        let (refresh_send, refresh_recv) = unbounded_channel();
+        let fetch_proxy = proxy.clone();
+        let states = IntervalStream::new(interval(self.interval))
+            .map(|_| ())
+            .merge(UnboundedReceiverStream::new(refresh_recv))
+            .then(move |()| {
+                let proxy = fetch_proxy.clone();
+                async move { Update::State(State::fetch(&proxy).await) }
+            });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = states.merge(events).map(move |update| {
+            if let Some(result) = self.update(update, &proxy, &refresh_send) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
@@ -21,7 +21,7 @@ use tokio_stream::{Stream, StreamExt};
 
 use crate::{
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
     remove_string_from_config, Attrs, PanelConfig, PanelStream,
 };
@@ -55,13 +55,12 @@ impl Fanotify {
             .format
             .replace("%file%", buf.lines().next().unwrap_or(""));
 
-        draw_common(
+        draw_panel(
             cr,
             text.as_str(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             None,
-            self.common.images.clone(),
             height,
             ShowHide::None,
         )
@@ -0,0 +1,351 @@
+use std::{
+    collections::{HashMap, HashSet},
+    process::Command,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::future::join_all;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Client,
+};
+use tokio::{sync::mpsc::unbounded_channel, time::interval};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    markup_escape_text, remove_array_from_config, remove_uint_from_config,
+    Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+#[derive(Debug, Clone)]
+struct Headline {
+    id: String,
+    title: String,
+    link: Option<String>,
+    feed: String,
+    published: Option<i64>,
+}
+
+#[derive(Debug)]
+enum Update {
+    Entries(Vec<Result<Vec<Headline>>>),
+    Rotate,
+    Event(Event),
+}
+
+/// Displays headlines from one or more RSS or Atom feeds, cycling through the
+/// newest entries, along with the number of unread entries.
+///
+/// Entries are marked as read when the panel is clicked. Entries that are
+/// already in the feeds when the bar starts are considered read.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Feed {
+    name: &'static str,
+    urls: Vec<String>,
+    #[builder(default = "Duration::from_secs(600)")]
+    interval: Duration,
+    #[builder(default = "Duration::from_secs(10)")]
+    rotate: Duration,
+    #[builder(default = "5")]
+    headlines: usize,
+    #[builder(default)]
+    feeds: Vec<Vec<Headline>>,
+    #[builder(default)]
+    read: HashSet<String>,
+    #[builder(default)]
+    started: bool,
+    #[builder(default)]
+    idx: usize,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Feed {
+    /// Returns the newest entries across all feeds, newest first.
+    fn newest(&self) -> Vec<&Headline> {
+        let mut headlines = self.feeds.iter().flatten().collect::<Vec<_>>();
+        headlines.sort_by(|a, b| b.published.cmp(&a.published));
+        headlines.truncate(self.headlines);
+        headlines
+    }
+
+    fn update(&mut self, update: Update) -> Option<Result<()>> {
+        match update {
+            Update::Entries(results) => {
+                self.feeds.resize_with(results.len(), Vec::new);
+                // keep showing the old entries if a request fails
+                for ((feed, result), url) in
+                    self.feeds.iter_mut().zip(results).zip(&self.urls)
+                {
+                    match result {
+                        Ok(headlines) => *feed = headlines,
+                        Err(e) => {
+                            log::warn!("Failed to fetch feed {url}: {e}");
+                        }
+                    }
+                }
+                if !self.started {
+                    self.started = true;
+                    self.mark_read();
+                }
+                if self.idx >= self.newest().len() {
+                    self.idx = 0;
+                }
+                None
+            }
+            Update::Rotate => {
+                self.idx = (self.idx + 1) % self.newest().len().max(1);
+                None
+            }
+            Update::Event(event) => Some(self.process_event(event)),
+        }
+    }
+
+    fn mark_read(&mut self) {
+        self.read = self
+            .feeds
+            .iter()
+            .flatten()
+            .map(|headline| headline.id.clone())
+            .collect();
+    }
+
+    fn process_event(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                self.open()
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "open" => self.open(),
+            Event::Action(action) if action == "read" => {
+                self.mark_read();
+                Ok(())
+            }
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    /// Opens the link of the headline that is currently shown and marks all
+    /// entries as read.
+    fn open(&mut self) -> Result<()> {
+        let link = self
+            .newest()
+            .get(self.idx)
+            .and_then(|headline| headline.link.clone());
+        self.mark_read();
+
+        let link = link.ok_or_else(|| anyhow!("No link to open"))?;
+        cleanup::spawn_command(Command::new("xdg-open").arg(link))
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let unread = self
+            .feeds
+            .iter()
+            .flatten()
+            .filter(|headline| !self.read.contains(&headline.id))
+            .count();
+
+        let text = match self.newest().get(self.idx) {
+            Some(headline) => self
+                .format
+                .replace("%count%", unread.to_string().as_str())
+                .replace(
+                    "%title%",
+                    markup_escape_text(headline.title.as_str()).as_str(),
+                )
+                .replace(
+                    "%feed%",
+                    markup_escape_text(headline.feed.as_str()).as_str(),
+                ),
+            None => String::new(),
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Feed {
+    /// Configuration options:
+    ///
+    /// - `urls`: the URLs of the RSS or Atom feeds to fetch
+    ///   - type: Array of Strings
+    ///   - default: none (required)
+    /// - `interval`: how often to fetch the feeds, in seconds
+    ///   - type: u64
+    ///   - default: 600
+    /// - `rotate`: how often to switch to the next headline, in seconds
+    ///   - type: u64
+    ///   - default: 10
+    /// - `headlines`: the number of the newest entries to cycle through. Set
+    ///   to 1 to always show the newest entry.
+    ///   - type: u64
+    ///   - default: 5
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%count% %title%`
+    ///   - formatting options: `%count%` (the number of unread entries),
+    ///     `%title%`, `%feed%` (the title of the feed)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel opens the
+    ///   link of the headline that is shown with `xdg-open` and marks all
+    ///   entries as read. The supported events are `open`, which does the
+    ///   same, and `read`, which only marks all entries as read.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = FeedBuilder::default();
+
+        builder.name(name);
+        builder.urls(
+            remove_array_from_config("urls", table)
+                .map(|urls| {
+                    urls.into_iter()
+                        .filter_map(|v| v.into_string().ok())
+                        .collect::<Vec<_>>()
+                })
+                .filter(|urls| !urls.is_empty())
+                .ok_or_else(|| anyhow!("{name}: `urls` is required"))?,
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(rotate) = remove_uint_from_config("rotate", table) {
+            builder.rotate(Duration::from_secs(rotate.max(1)));
+        }
+        if let Some(headlines) = remove_uint_from_config("headlines", table) {
+            builder.headlines(headlines.max(1) as usize);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%count% %title%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("lazybar"));
+        let client = Client::builder().default_headers(headers).build()?;
+        let urls = self.urls.clone();
+
+        let entries =
+            IntervalStream::new(interval(self.interval)).then(move |_| {
+                let client = client.clone();
+                let urls = urls.clone();
+                async move {
+                    Update::Entries(
+                        join_all(urls.iter().map(|url| fetch(&client, url)))
+                            .await,
+                    )
+                }
+            });
+        let mut rotate = interval(self.rotate);
+        // the first tick completes immediately
+        rotate.reset();
+        let rotate = IntervalStream::new(rotate).map(|_| Update::Rotate);
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = entries.merge(rotate).merge(events).map(move |update| {
+            if let Some(result) = self.update(update) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+async fn fetch(client: &Client, url: &str) -> Result<Vec<Headline>> {
+    let body = client
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    let feed = feed_rs::parser::parse(body.as_ref())?;
+    let title = feed
+        .title
+        .map_or_else(|| String::from(url), |title| title.content);
+
+    Ok(feed
+        .entries
+        .into_iter()
+        .map(|entry| Headline {
+            id: entry.id,
+            title: entry.title.map(|title| title.content).unwrap_or_default(),
+            link: entry.links.into_iter().next().map(|link| link.href),
+            feed: title.clone(),
+            published: entry
+                .published
+                .or(entry.updated)
+                .map(|date| date.timestamp()),
+        })
+        .collect())
+}
@@ -1,14 +1,16 @@
 use std::{
+    collections::HashMap,
     fs::File,
     io::Read,
     pin::Pin,
+    process::Command,
     rc::Rc,
     sync::{Arc, Mutex},
     task::Poll,
     time::Duration,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use derive_builder::Builder;
 use futures::{task::AtomicWaker, FutureExt, StreamExt};
@@ -20,15 +22,18 @@ use reqwest::{
 };
 use serde::Deserialize;
 use tokio::{
+    sync::mpsc::unbounded_channel,
     task::{self, JoinHandle},
     time::{interval, Interval},
 };
-use tokio_stream::Stream;
+use tokio_stream::{wrappers::UnboundedReceiverStream, Stream};
 
 use crate::{
     attrs::Attrs,
-    bar::PanelDrawInfo,
-    common::{draw_common, PanelCommon, ShowHide},
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
     remove_array_from_config, remove_bool_from_config,
     remove_string_from_config, remove_uint_from_config, Highlight, PanelConfig,
 };
@@ -38,6 +43,32 @@ lazy_static! {
         Regex::new(r#"<(?<url>\S*)>; rel="next""#).unwrap();
 }
 
+/// The notification reasons that GitHub currently documents. Each one is
+/// available as a formatting option.
+const REASONS: &[&str] = &[
+    "approval_requested",
+    "assign",
+    "author",
+    "ci_activity",
+    "comment",
+    "invitation",
+    "manual",
+    "member_feature_requested",
+    "mention",
+    "review_requested",
+    "security_advisory_credit",
+    "security_alert",
+    "state_change",
+    "subscribed",
+    "team_mention",
+];
+
+#[derive(Debug, Default)]
+struct Counts {
+    total: usize,
+    reasons: HashMap<String, usize>,
+}
+
 /// Displays the number of github notifications you have.
 #[derive(Debug, Clone, Builder)]
 #[builder_struct_attr(allow(missing_docs))]
@@ -53,6 +84,10 @@ pub struct Github {
     filter: Vec<String>,
     #[builder(default)]
     include: bool,
+    #[builder(default)]
+    repos: Vec<String>,
+    #[builder(default = r#"String::from("https://github.com/notifications")"#)]
+    url: String,
     #[builder(default = "true")]
     show_zero: bool,
     format: &'static str,
@@ -67,30 +102,59 @@ impl Github {
         &self,
         cr: &Rc<cairo::Context>,
         height: i32,
-        count: usize,
+        counts: &Counts,
         paused: Arc<Mutex<bool>>,
     ) -> Result<PanelDrawInfo> {
-        let mut text = if !self.show_zero && count == 0 {
+        let mut text = if !self.show_zero && counts.total == 0 {
             String::new()
         } else {
-            self.format.replace("%count%", count.to_string().as_str())
+            REASONS.iter().fold(
+                self.format
+                    .replace("%count%", counts.total.to_string().as_str()),
+                |text, reason| {
+                    text.replace(
+                        format!("%{reason}%").as_str(),
+                        counts
+                            .reasons
+                            .get(*reason)
+                            .copied()
+                            .unwrap_or_default()
+                            .to_string()
+                            .as_str(),
+                    )
+                },
+            )
         };
 
-        if count == 50 {
+        if counts.total == 50 {
             text.push('+');
         }
 
-        draw_common(
+        draw_panel(
             cr,
             text.as_str(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
     }
+
+    fn process_event(url: &str, event: Event) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                open(url)
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "open" => open(url),
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+}
+
+fn open(url: &str) -> Result<()> {
+    cleanup::spawn_command(Command::new("xdg-open").arg(url))
 }
 
 #[async_trait(?Send)]
@@ -111,15 +175,23 @@ impl PanelConfig for Github {
     ///   `include` is true, only notifications with one of the reasons in
     ///   `filter` will be counted. Otherwise, only notifications with reasons
     ///   not in `filter` will be counted.
+    /// - `repos`: An array of repositories in the form `owner/name`. If set,
+    ///   only notifications from these repositories are counted, including in
+    ///   the per-reason counts.
+    /// - `url`: The page that is opened with `xdg-open` when the panel is
+    ///   clicked. Defaults to <https://github.com/notifications>.
     /// - `show_zero`: Whether or not the panel is shown when you have zero
     ///   notifications.
-    /// - `format`: The formatting option. The only formatting option is
-    ///   `%count%`.
+    /// - `format`: The formatting option. `%count%` is the number of
+    ///   notifications that pass `filter`. The number of notifications with
+    ///   each reason (regardless of `filter`) is available as `%<reason>%`,
+    ///   e.g. `%mention%` or `%review_requested%`.
     /// - `attrs`: A string specifying the attrs for the panel. See
     ///   [`Attrs::parse`] for details.
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
-    /// - See [`PanelCommon::parse_common`].
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel opens
+    ///   `url`, and the supported event is `open`, which does the same.
     fn parse(
         name: &'static str,
         table: &mut std::collections::HashMap<String, config::Value>,
@@ -153,6 +225,19 @@ impl PanelConfig for Github {
             builder.include(include);
         }
 
+        if let Some(repos) = remove_array_from_config("repos", table) {
+            builder.repos(
+                repos
+                    .into_iter()
+                    .filter_map(|v| v.into_string().ok())
+                    .collect(),
+            );
+        }
+
+        if let Some(url) = remove_string_from_config("url", table) {
+            builder.url(url);
+        }
+
         if let Some(show_zero) = remove_bool_from_config("show_zero", table) {
             builder.show_zero(show_zero);
         }
@@ -192,26 +277,47 @@ impl PanelConfig for Github {
 
         let paused = Arc::new(Mutex::new(false));
 
-        let stream = GithubStream::new(
-            self.token.as_str(),
-            self.interval,
-            paused.clone(),
-            self.filter.clone(),
-            self.include,
-        )?
-        .map(move |r| self.draw(&cr, height, r?, paused.clone()));
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let url = self.url.clone();
+        let events =
+            UnboundedReceiverStream::new(event_recv).filter_map(move |event| {
+                let response = match Self::process_event(url.as_str(), event) {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                };
+                let _ = response_send.send(response);
+                futures::future::ready(None)
+            });
+
+        let stream = futures::stream::select(
+            GithubStream::new(
+                self.token.as_str(),
+                self.interval,
+                paused.clone(),
+                self.filter.clone(),
+                self.include,
+                self.repos.clone(),
+            )?,
+            events,
+        )
+        .map(move |r| self.draw(&cr, height, &r?, paused.clone()));
 
-        Ok((Box::pin(stream), None))
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
     }
 }
 
 struct GithubStream {
-    handle: Option<JoinHandle<Result<usize>>>,
+    handle: Option<JoinHandle<Result<Counts>>>,
     interval: Arc<futures::lock::Mutex<Interval>>,
     waker: Arc<AtomicWaker>,
     paused: Arc<Mutex<bool>>,
     filter: Vec<String>,
     include: bool,
+    repos: Vec<String>,
     client: Client,
 }
 
@@ -222,6 +328,7 @@ impl GithubStream {
         paused: Arc<Mutex<bool>>,
         filter: Vec<String>,
         include: bool,
+        repos: Vec<String>,
     ) -> Result<Self> {
         let mut headers = HeaderMap::new();
         headers.insert(
@@ -247,13 +354,14 @@ impl GithubStream {
             paused,
             filter,
             include,
+            repos,
             client,
         })
     }
 }
 
 impl Stream for GithubStream {
-    type Item = Result<usize>;
+    type Item = Result<Counts>;
 
     fn poll_next(
         mut self: Pin<&mut Self>,
@@ -274,9 +382,10 @@ impl Stream for GithubStream {
             let interval = self.interval.clone();
             let filter = self.filter.clone();
             let include = self.include;
+            let repos = self.repos.clone();
             let client = self.client.clone();
             self.handle = Some(task::spawn(get_notifications(
-                interval, filter, include, client,
+                interval, filter, include, repos, client,
             )));
 
             Poll::Pending
@@ -288,8 +397,9 @@ async fn get_notifications(
     interval: Arc<futures::lock::Mutex<Interval>>,
     filter: Vec<String>,
     include: bool,
+    repos: Vec<String>,
     client: Client,
-) -> Result<usize> {
+) -> Result<Counts> {
     interval.lock().await.tick().await;
 
     let request = client.get("https://api.github.com/notifications").build()?;
@@ -307,16 +417,29 @@ async fn get_notifications(
 
     let body = response.json::<Vec<Thread>>().await?;
 
-    let count = body
+    let mut counts = Counts::default();
+    for thread in body
         .into_iter()
-        .filter(|t| !(include ^ filter.contains(&t.reason)))
-        .count();
+        .filter(|t| repos.is_empty() || repos.contains(&t.repository.full_name))
+    {
+        if !(include ^ filter.contains(&thread.reason)) {
+            counts.total += 1;
+        }
+        *counts.reasons.entry(thread.reason).or_default() += 1;
+    }
 
-    Ok(count)
+    Ok(counts)
 }
 
 #[derive(Deserialize, Debug)]
 #[non_exhaustive]
 struct Thread {
     reason: String,
+    repository: Repository,
+}
+
+#[derive(Deserialize, Debug)]
+#[non_exhaustive]
+struct Repository {
+    full_name: String,
 }
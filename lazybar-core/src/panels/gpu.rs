@@ -0,0 +1,351 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::task::AtomicWaker;
+use nvml_wrapper::{enum_wrappers::device::TemperatureSensor, Nvml};
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    human_readable,
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, state, Attrs,
+    Highlight, ManagedIntervalStream, PanelConfig, PanelStream, Ramp,
+};
+
+const AMD_VENDOR: &str = "0x1002";
+
+/// Which driver interface to read the GPU stats from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Vendor {
+    /// NVIDIA if NVML can be loaded and has the device, otherwise AMD
+    #[default]
+    Auto,
+    /// NVML
+    Nvidia,
+    /// sysfs
+    Amd,
+}
+
+enum Backend {
+    Nvidia(Nvml),
+    /// The `device` directory of an AMD card in `/sys/class/drm`
+    Amd(PathBuf),
+}
+
+/// A single reading. Anything the driver doesn't report is [`None`].
+#[derive(Debug, Default)]
+struct Stats {
+    /// Utilization in percent
+    util: Option<u32>,
+    /// VRAM used and total, in bytes
+    vram: Option<(u64, u64)>,
+    /// Temperature in degrees Celsius
+    temp: Option<u32>,
+    /// Power draw in watts
+    power: Option<f64>,
+}
+
+/// Displays the utilization, VRAM usage, temperature, and power draw of a
+/// GPU.
+///
+/// NVIDIA GPUs are read through NVML, which is loaded at runtime from the
+/// proprietary driver, and AMD GPUs are read from `/sys/class/drm` and their
+/// hwmon sensors.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Gpu {
+    name: &'static str,
+    #[builder(default)]
+    vendor: Vendor,
+    #[builder(default)]
+    device: u32,
+    #[builder(default = "Duration::from_secs(5)")]
+    interval: Duration,
+    #[builder(default)]
+    waker: Arc<AtomicWaker>,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    ramp: Ramp,
+    common: PanelCommon,
+}
+
+impl Gpu {
+    fn backend(&self) -> Result<Backend> {
+        let nvidia = || -> Result<Backend> {
+            let nvml = Nvml::init()?;
+            if self.device >= nvml.device_count()? {
+                return Err(anyhow!("NVIDIA GPU {} not found", self.device));
+            }
+            Ok(Backend::Nvidia(nvml))
+        };
+        let amd = || -> Result<Backend> {
+            find_amd(self.device as usize)
+                .map(Backend::Amd)
+                .ok_or_else(|| anyhow!("AMD GPU {} not found", self.device))
+        };
+
+        match self.vendor {
+            Vendor::Nvidia => nvidia(),
+            Vendor::Amd => amd(),
+            Vendor::Auto => nvidia().or_else(|e| {
+                log::debug!("{}: not using NVML: {e}", self.name);
+                amd()
+            }),
+        }
+    }
+
+    fn read(&self, backend: &Backend) -> Result<Stats> {
+        match backend {
+            Backend::Nvidia(nvml) => {
+                let device = nvml.device_by_index(self.device)?;
+                Ok(Stats {
+                    util: device.utilization_rates().ok().map(|u| u.gpu),
+                    vram: device
+                        .memory_info()
+                        .ok()
+                        .map(|mem| (mem.used, mem.total)),
+                    temp: device.temperature(TemperatureSensor::Gpu).ok(),
+                    power: device
+                        .power_usage()
+                        .ok()
+                        .map(|mw| f64::from(mw) / 1000.0),
+                })
+            }
+            Backend::Amd(path) => {
+                let hwmon = read_dir(path.join("hwmon"))
+                    .ok()
+                    .and_then(|mut dir| dir.next()?.ok())
+                    .map(|entry| entry.path());
+                Ok(Stats {
+                    util: read_value(&path.join("gpu_busy_percent")),
+                    vram: read_value(&path.join("mem_info_vram_used"))
+                        .zip(read_value(&path.join("mem_info_vram_total"))),
+                    temp: hwmon.as_ref().and_then(|hwmon| {
+                        read_value::<u32>(&hwmon.join("temp1_input"))
+                            .map(|t| t / 1000)
+                    }),
+                    power: hwmon.as_ref().and_then(|hwmon| {
+                        read_value::<u64>(&hwmon.join("power1_average"))
+                            .or_else(|| read_value(&hwmon.join("power1_input")))
+                            .map(|uw| uw as f64 / 1_000_000.0)
+                    }),
+                })
+            }
+        }
+    }
+
+    fn draw(
+        &self,
+        backend: &Backend,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        paused: Arc<Mutex<bool>>,
+    ) -> Result<PanelDrawInfo> {
+        let stats = self.read(backend)?;
+
+        if let Some(util) = stats.util {
+            state::publish(self.name, "util", util);
+        }
+
+        let unknown = || String::from("?");
+        let text = self
+            .format
+            .replace(
+                "%ramp%",
+                stats
+                    .util
+                    .map_or_else(String::new, |util| {
+                        self.ramp.choose(util, 0, 100)
+                    })
+                    .as_str(),
+            )
+            .replace(
+                "%util%",
+                stats.util.map_or_else(unknown, |u| u.to_string()).as_str(),
+            )
+            .replace(
+                "%vram_used%",
+                stats
+                    .vram
+                    .map_or_else(unknown, |(used, _)| human_readable(used))
+                    .as_str(),
+            )
+            .replace(
+                "%vram_total%",
+                stats
+                    .vram
+                    .map_or_else(unknown, |(_, total)| human_readable(total))
+                    .as_str(),
+            )
+            .replace(
+                "%vram_percent%",
+                stats
+                    .vram
+                    .filter(|(_, total)| *total > 0)
+                    .map_or_else(unknown, |(used, total)| {
+                        (used * 100 / total).to_string()
+                    })
+                    .as_str(),
+            )
+            .replace(
+                "%temp%",
+                stats.temp.map_or_else(unknown, |t| t.to_string()).as_str(),
+            )
+            .replace(
+                "%power%",
+                stats
+                    .power
+                    .map_or_else(unknown, |p| format!("{p:.1}"))
+                    .as_str(),
+            );
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::Default(paused, self.waker.clone()),
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Gpu {
+    /// Configuration options:
+    ///
+    /// - `vendor`: `nvidia`, `amd`, or `auto`. `auto` uses NVML if it can be
+    ///   loaded and has the device, and falls back to AMD otherwise.
+    ///   - type: String
+    ///   - default: `auto`
+    /// - `device`: the index of the GPU among those of the same vendor
+    ///   - type: u64
+    ///   - default: 0
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 5
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `GPU: %util%%`
+    ///   - formatting options: `%util%`, `%ramp%`, `%vram_used%`,
+    ///     `%vram_total%`, `%vram_percent%`, `%temp%` (in degrees Celsius),
+    ///     `%power%` (in watts). Anything the driver doesn't report is shown
+    ///     as `?`.
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `ramp`: A string specifying the ramp to show GPU utilization. See
+    ///   [`Ramp::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = GpuBuilder::default();
+
+        builder.name(name);
+        if let Some(vendor) = remove_string_from_config("vendor", table) {
+            builder.vendor(match vendor.to_lowercase().as_str() {
+                "nvidia" => Vendor::Nvidia,
+                "amd" => Vendor::Amd,
+                "auto" => Vendor::Auto,
+                _ => {
+                    log::warn!("{name}: unknown vendor {vendor}");
+                    Vendor::Auto
+                }
+            });
+        }
+        if let Some(device) = remove_uint_from_config("device", table) {
+            builder.device(device as u32);
+        }
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "GPU: %util%%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let ramp = PanelCommon::parse_ramp(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+        builder.ramp(ramp);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let backend = self.backend()?;
+        let paused = Arc::new(Mutex::new(false));
+
+        let stream = ManagedIntervalStream::builder()
+            .duration(self.interval)
+            .paused(paused.clone())
+            .waker(self.waker.clone())
+            .build()?
+            .map(move |_| self.draw(&backend, &cr, height, paused.clone()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+/// Returns the `device` directory of the `idx`th AMD card.
+fn find_amd(idx: usize) -> Option<PathBuf> {
+    let mut cards = read_dir("/sys/class/drm")
+        .ok()?
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        // skip connectors like card0-DP-1
+        .filter(|name| {
+            name.strip_prefix("card")
+                .is_some_and(|n| n.chars().all(|c| c.is_ascii_digit()))
+        })
+        .collect::<Vec<_>>();
+    cards.sort_by_key(|name| name[4..].parse::<u32>().unwrap_or(u32::MAX));
+
+    cards
+        .into_iter()
+        .map(|name| PathBuf::from("/sys/class/drm").join(name).join("device"))
+        .filter(|device| {
+            read_to_string(device.join("vendor"))
+                .is_ok_and(|vendor| vendor.trim() == AMD_VENDOR)
+        })
+        .nth(idx)
+}
+
+fn read_value<T: std::str::FromStr>(path: &Path) -> Option<T> {
+    read_to_string(path).ok()?.trim().parse().ok()
+}
@@ -0,0 +1,272 @@
+use std::{
+    collections::HashMap, process::Command, rc::Rc, sync::Arc, time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{sync::mpsc::unbounded_channel, time::interval};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        screensaver::ConnectionExt as _,
+        xproto::{ConnectionExt as _, ScreenSaver, Window},
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::{
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Event(Event),
+}
+
+/// Displays the time remaining until the screen locks, based on how long the
+/// user has been idle according to the X screen saver extension.
+///
+/// By default, the timeout is the X server's screen saver timeout (see `xset
+/// q`), which is what `xss-lock` responds to. Lockers with their own timer,
+/// like `xautolock`, need `timeout` to be set to match. The countdown starts
+/// over whenever there's keyboard or mouse activity.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct IdleCountdown {
+    name: &'static str,
+    conn: Arc<RustConnection>,
+    screen: usize,
+    /// The timeout in seconds, or [`None`] to ask the X server
+    #[builder(default, setter(strip_option))]
+    timeout: Option<u64>,
+    #[builder(default = "30")]
+    urgent: u64,
+    #[builder(default, setter(strip_option))]
+    postpone_command: Option<String>,
+    format: &'static str,
+    attrs: Attrs,
+    attrs_urgent: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    #[builder(default, setter(strip_option))]
+    highlight_urgent: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl IdleCountdown {
+    fn process_event(&self, event: Event) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                self.postpone()
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "postpone" => self.postpone(),
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    /// Resets the idle timer by running `postpone_command`, or by resetting
+    /// the X screen saver if it isn't set.
+    fn postpone(&self) -> Result<()> {
+        if let Some(ref command) = self.postpone_command {
+            cleanup::spawn_command(Command::new("sh").arg("-c").arg(command))
+        } else {
+            self.conn.force_screen_saver(ScreenSaver::RESET)?;
+            self.conn.flush()?;
+            Ok(())
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        root: Window,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let timeout = match self.timeout {
+            Some(timeout) => timeout,
+            None => u64::from(self.conn.get_screen_saver()?.reply()?.timeout),
+        };
+        let idle = u64::from(
+            self.conn
+                .screensaver_query_info(root)?
+                .reply()?
+                .ms_since_user_input,
+        ) / 1000;
+
+        // a timeout of zero means that the screen never locks
+        let remaining = timeout.saturating_sub(idle);
+        let text = if timeout == 0 {
+            String::new()
+        } else {
+            self.format
+                .replace(
+                    "%remaining%",
+                    format!("{}:{:02}", remaining / 60, remaining % 60)
+                        .as_str(),
+                )
+                .replace("%seconds%", remaining.to_string().as_str())
+                .replace("%idle%", idle.to_string().as_str())
+        };
+
+        let (attrs, highlight) = if timeout > 0 && remaining <= self.urgent {
+            (&self.attrs_urgent, &self.highlight_urgent)
+        } else {
+            (&self.attrs, &self.highlight)
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            attrs,
+            &self.common,
+            highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for IdleCountdown {
+    /// Configuration options:
+    ///
+    /// - `screen`: the name of the X screen to monitor
+    ///   - type: String
+    ///   - default: None (This will tell X to choose the default screen, which
+    ///     is probably what you want.)
+    /// - `timeout`: how long in seconds the user can be idle before the
+    ///   screen locks. If this is 0, the panel is hidden.
+    ///   - type: u64
+    ///   - default: the X screen saver timeout
+    /// - `urgent`: how many seconds before the screen locks to switch to
+    ///   `attrs_urgent` and `highlight_urgent`
+    ///   - type: u64
+    ///   - default: 30
+    /// - `postpone_command`: the command to run with `sh -c` when the panel
+    ///   is clicked, e.g. `xautolock -restart`. If unset, the X screen saver
+    ///   is reset, which postpones `xss-lock`.
+    ///   - type: String
+    ///   - default: none
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `lock in %remaining%`
+    ///   - formatting options: `%remaining%` (e.g. `4:05`), `%seconds%` (the
+    ///     remaining time in seconds), `%idle%` (how long the user has been
+    ///     idle in seconds)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `attrs_urgent`: A string specifying the attrs for the panel in the
+    ///   final seconds. Anything not set is taken from `attrs`. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `highlight_urgent`: A string specifying the highlight for the panel
+    ///   in the final seconds. See [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel postpones
+    ///   the lock, as does the `postpone` event.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = IdleCountdownBuilder::default();
+
+        builder.name(name);
+        let screen = remove_string_from_config("screen", table);
+        if let Ok((conn, screen)) = RustConnection::connect(screen.as_deref()) {
+            builder.conn(Arc::new(conn)).screen(screen);
+        } else {
+            log::error!("Failed to connect to X server");
+        }
+        if let Some(timeout) = remove_uint_from_config("timeout", table) {
+            builder.timeout(timeout);
+        }
+        if let Some(urgent) = remove_uint_from_config("urgent", table) {
+            builder.urgent(urgent);
+        }
+        if let Some(command) =
+            remove_string_from_config("postpone_command", table)
+        {
+            builder.postpone_command(command);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format =
+            PanelCommon::parse_format(table, "", "lock in %remaining%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let attrs_urgent = PanelCommon::parse_attr(table, "_urgent");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let highlight_urgent = PanelCommon::parse_highlight(table, "_urgent");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.attrs_urgent(attrs_urgent);
+        builder.highlight(highlight);
+        builder.highlight_urgent(highlight_urgent);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+        self.attrs_urgent.apply_to(&self.attrs);
+
+        let root = self
+            .conn
+            .setup()
+            .roots
+            .get(self.screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?
+            .root;
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        // the countdown is in seconds, so it's redrawn every second
+        let stream = IntervalStream::new(interval(Duration::from_secs(1)))
+            .map(|_| Update::Tick)
+            .merge(events)
+            .map(move |update| {
+                if let Update::Event(event) = update {
+                    let _ =
+                        response_send.send(match self.process_event(event) {
+                            Ok(()) => EventResponse::Ok,
+                            Err(e) => EventResponse::Err(e.to_string()),
+                        });
+                }
+                self.draw(&cr, root, height)
+            });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
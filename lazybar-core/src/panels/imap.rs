@@ -0,0 +1,800 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, BufReader, ErrorKind, Read, Write},
+    net::TcpStream,
+    process::Command,
+    rc::Rc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use native_tls::TlsConnector;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_array_from_config, remove_bool_from_config,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+/// How long a read waits before checking whether the panel still exists
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+/// How long to wait for the server to answer a command
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(60);
+/// Servers may drop idle connections after 30 minutes, so IDLE is restarted
+/// before then
+const KEEPALIVE: Duration = Duration::from_secs(29 * 60);
+
+/// How the connection to the server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Security {
+    /// TLS from the start
+    Tls,
+    /// A plain connection that is upgraded with STARTTLS before logging in
+    StartTls,
+    /// No encryption at all
+    None,
+}
+
+/// Displays the number of unread messages in one or more IMAP mailboxes.
+///
+/// Each mailbox gets its own connection, which uses IDLE to be notified of
+/// new mail as soon as the server sees it. The password is read from the
+/// output of a command each time a connection is made, so it never needs to
+/// be stored in the config file.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Imap {
+    name: &'static str,
+    host: String,
+    port: u16,
+    #[builder(default = "Security::Tls")]
+    security: Security,
+    user: String,
+    password_command: String,
+    mailboxes: Vec<String>,
+    labels: Vec<String>,
+    #[builder(default = "Duration::from_secs(60)")]
+    retry: Duration,
+    #[builder(default = "true")]
+    show_zero: bool,
+    format: &'static str,
+    format_mailbox: &'static str,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Imap {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        counts: &[Option<usize>],
+    ) -> Result<PanelDrawInfo> {
+        let total = counts.iter().flatten().sum::<usize>();
+
+        let text = if !self.show_zero && total == 0 {
+            String::new()
+        } else {
+            let mailboxes = self
+                .labels
+                .iter()
+                .zip(counts)
+                .filter_map(|(label, count)| {
+                    count.filter(|c| *c > 0).map(|count| {
+                        self.format_mailbox
+                            .replace("%label%", label.as_str())
+                            .replace("%count%", count.to_string().as_str())
+                    })
+                })
+                .collect::<Vec<_>>()
+                .join(self.separator.as_str());
+
+            self.labels.iter().zip(counts).fold(
+                self.format
+                    .replace("%total%", total.to_string().as_str())
+                    .replace("%mailboxes%", mailboxes.as_str()),
+                |text, (label, count)| {
+                    text.replace(
+                        format!("%{label}%").as_str(),
+                        count
+                            .map_or_else(
+                                || String::from("?"),
+                                |c| c.to_string(),
+                            )
+                            .as_str(),
+                    )
+                },
+            )
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+
+    /// Watches one mailbox until the panel is dropped, reconnecting after
+    /// errors.
+    fn watch(
+        &self,
+        idx: usize,
+        send: &UnboundedSender<(usize, Option<usize>)>,
+    ) {
+        let mailbox = self.mailboxes[idx].as_str();
+        while !send.is_closed() {
+            let result = self
+                .connect()
+                .and_then(|session| self.idle(session, mailbox, idx, send));
+            if let Err(e) = result {
+                log::warn!("{}: error watching {mailbox}: {e}", self.name);
+                let _ = send.send((idx, None));
+            }
+
+            let retry_at = Instant::now() + self.retry;
+            while !send.is_closed() {
+                let left = retry_at.saturating_duration_since(Instant::now());
+                if left.is_zero() {
+                    break;
+                }
+                thread::sleep(left.min(CHECK_INTERVAL));
+            }
+        }
+        log::debug!("{}: stopped watching {mailbox}", self.name);
+    }
+
+    /// Connects to the server and reads its greeting, upgrading the
+    /// connection with STARTTLS if configured to.
+    fn connect(&self) -> Result<Session<Box<dyn Stream>>> {
+        let host = self.host.as_str();
+        let tcp = TcpStream::connect((host, self.port))?;
+        tcp.set_read_timeout(Some(RESPONSE_TIMEOUT))?;
+        tcp.set_write_timeout(Some(RESPONSE_TIMEOUT))?;
+        // the timeout belongs to the socket, so it can be shortened through
+        // this handle once the connection is wrapped
+        let socket = tcp.try_clone()?;
+
+        let mut session = match self.security {
+            Security::Tls => {
+                let tls = TlsConnector::new()?.connect(host, tcp)?;
+                Session::new(Box::new(tls) as Box<dyn Stream>)
+            }
+            Security::StartTls => {
+                let mut plain = Session::new(tcp);
+                plain.greeting()?;
+                plain.command("STARTTLS")?;
+                let tcp = plain.into_inner()?;
+                let tls = TlsConnector::new()?.connect(host, tcp)?;
+                // the greeting was read before the upgrade
+                socket.set_read_timeout(Some(CHECK_INTERVAL))?;
+                return Ok(Session::new(Box::new(tls) as Box<dyn Stream>));
+            }
+            Security::None => Session::new(Box::new(tcp) as Box<dyn Stream>),
+        };
+        socket.set_read_timeout(Some(CHECK_INTERVAL))?;
+        session.greeting()?;
+        Ok(session)
+    }
+
+    /// Logs in, then sends the unread count every time the mailbox changes.
+    /// Returns on error, or once the panel has been dropped.
+    fn idle<S: Read + Write>(
+        &self,
+        mut session: Session<S>,
+        mailbox: &str,
+        idx: usize,
+        send: &UnboundedSender<(usize, Option<usize>)>,
+    ) -> Result<()> {
+        let password = password(self.password_command.as_str())?;
+        session.login(self.user.as_str(), password.as_str())?;
+        session.examine(mailbox)?;
+        log::debug!("{}: watching {mailbox}", self.name);
+
+        loop {
+            let unseen = session.unseen()?;
+            if send.send((idx, Some(unseen))).is_err() {
+                break;
+            }
+            if !session.idle(KEEPALIVE, || send.is_closed())? {
+                break;
+            }
+        }
+        session.logout();
+        Ok(())
+    }
+}
+
+/// A connection to an IMAP server, secured or not
+trait Stream: Read + Write {}
+
+impl<T: Read + Write> Stream for T {}
+
+/// The few IMAP commands that the panel needs, over a blocking stream.
+///
+/// Reads are expected to time out every [`CHECK_INTERVAL`] or so, which is
+/// when [`Session::idle`] checks whether to stop. Responses that contain
+/// literals aren't parsed, since none of the responses used here have them.
+struct Session<S> {
+    stream: BufReader<S>,
+    /// The number of commands sent so far, used to tag the next one
+    tag: u32,
+    /// The part of the current line that has been read so far
+    partial: Vec<u8>,
+}
+
+impl<S: Read + Write> Session<S> {
+    fn new(stream: S) -> Self {
+        Self {
+            stream: BufReader::new(stream),
+            tag: 0,
+            partial: Vec::new(),
+        }
+    }
+
+    /// Returns the stream. Fails if the server has sent anything that
+    /// hasn't been read yet, which would otherwise be trusted as if it
+    /// came after a STARTTLS upgrade.
+    fn into_inner(self) -> Result<S> {
+        if !self.stream.buffer().is_empty() || !self.partial.is_empty() {
+            return Err(anyhow!("server sent data before the TLS handshake"));
+        }
+        Ok(self.stream.into_inner())
+    }
+
+    /// Reads the next line without its line ending, or returns `None` if the
+    /// read times out first. A line that is cut off by a timeout is finished
+    /// by the next call.
+    fn read_line(&mut self) -> Result<Option<String>> {
+        match self.stream.read_until(b'\n', &mut self.partial) {
+            Ok(_) if self.partial.ends_with(b"\n") => {
+                let line = String::from_utf8_lossy(&self.partial)
+                    .trim_end()
+                    .to_string();
+                self.partial.clear();
+                Ok(Some(line))
+            }
+            Ok(_) => Err(anyhow!("server closed the connection")),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    ErrorKind::WouldBlock | ErrorKind::TimedOut
+                ) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Reads a line, waiting up to [`RESPONSE_TIMEOUT`] for it.
+    fn expect_line(&mut self) -> Result<String> {
+        let deadline = Instant::now() + RESPONSE_TIMEOUT;
+        loop {
+            if let Some(line) = self.read_line()? {
+                return Ok(line);
+            }
+            if Instant::now() >= deadline {
+                return Err(anyhow!("timed out waiting for the server"));
+            }
+        }
+    }
+
+    fn send(&mut self, text: &str) -> Result<()> {
+        let stream = self.stream.get_mut();
+        stream.write_all(text.as_bytes())?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    /// Sends a command with the next tag and returns the tag.
+    fn send_command(&mut self, command: &str) -> Result<String> {
+        self.tag += 1;
+        let tag = format!("a{}", self.tag);
+        self.send(format!("{tag} {command}\r\n").as_str())?;
+        Ok(tag)
+    }
+
+    /// Reads responses until the one tagged with `tag`, and returns the
+    /// untagged responses before it. Fails unless the command succeeded.
+    fn finish(&mut self, tag: &str) -> Result<Vec<String>> {
+        let mut untagged = Vec::new();
+        loop {
+            let line = self.expect_line()?;
+            let Some(status) = line
+                .strip_prefix(tag)
+                .and_then(|rest| rest.strip_prefix(' '))
+            else {
+                untagged.push(line);
+                continue;
+            };
+            return if starts_with_word(status, "OK") {
+                Ok(untagged)
+            } else {
+                Err(anyhow!("server replied {status}"))
+            };
+        }
+    }
+
+    /// Sends a command and returns the untagged responses to it.
+    fn command(&mut self, command: &str) -> Result<Vec<String>> {
+        let tag = self.send_command(command)?;
+        self.finish(tag.as_str())
+    }
+
+    /// Reads the server's greeting.
+    fn greeting(&mut self) -> Result<()> {
+        let line = self.expect_line()?;
+        match line.strip_prefix("* ") {
+            Some(status)
+                if starts_with_word(status, "OK")
+                    || starts_with_word(status, "PREAUTH") =>
+            {
+                Ok(())
+            }
+            _ => Err(anyhow!("unexpected greeting {line}")),
+        }
+    }
+
+    fn login(&mut self, user: &str, password: &str) -> Result<()> {
+        let command = format!("LOGIN {} {}", quote(user)?, quote(password)?);
+        self.command(command.as_str())?;
+        Ok(())
+    }
+
+    /// Opens a mailbox read-only, so that nothing is marked as read.
+    fn examine(&mut self, mailbox: &str) -> Result<()> {
+        self.command(format!("EXAMINE {}", quote(mailbox)?).as_str())?;
+        Ok(())
+    }
+
+    /// Returns the number of unread messages in the open mailbox.
+    fn unseen(&mut self) -> Result<usize> {
+        Ok(self
+            .command("SEARCH UNSEEN")?
+            .iter()
+            .filter_map(|line| line.strip_prefix("* "))
+            .filter(|line| starts_with_word(line, "SEARCH"))
+            .map(|line| line.split_whitespace().count() - 1)
+            .sum())
+    }
+
+    /// Waits with IDLE until the open mailbox changes or `keepalive` passes,
+    /// and returns `true`. Returns `false` as soon as `stop` does, which is
+    /// checked between reads.
+    fn idle(
+        &mut self,
+        keepalive: Duration,
+        stop: impl Fn() -> bool,
+    ) -> Result<bool> {
+        let tag = self.send_command("IDLE")?;
+        loop {
+            let line = self.expect_line()?;
+            if line.starts_with('+') {
+                break;
+            }
+            if line
+                .strip_prefix(tag.as_str())
+                .is_some_and(|rest| rest.starts_with(' '))
+            {
+                return Err(anyhow!("server refused IDLE: {line}"));
+            }
+        }
+
+        let end = Instant::now() + keepalive;
+        let running = loop {
+            if stop() {
+                break false;
+            }
+            if Instant::now() >= end {
+                break true;
+            }
+            if self.read_line()?.is_some_and(|line| changed(&line)) {
+                break true;
+            }
+        };
+        self.send("DONE\r\n")?;
+        self.finish(tag.as_str())?;
+        Ok(running)
+    }
+
+    /// Logs out, ignoring errors since the connection is closed either way.
+    fn logout(&mut self) {
+        let _ = self.command("LOGOUT");
+    }
+}
+
+/// Returns whether `text` starts with `word`, ignoring case, followed by a
+/// space or nothing.
+fn starts_with_word(text: &str, word: &str) -> bool {
+    text.get(..word.len())
+        .is_some_and(|start| start.eq_ignore_ascii_case(word))
+        && matches!(text.as_bytes().get(word.len()), None | Some(b' '))
+}
+
+/// Returns whether a response during IDLE means the mailbox changed, i.e.
+/// whether it's a message count like `* 3 EXISTS` or `* 2 EXPUNGE`, or a
+/// flag change like `* 4 FETCH (FLAGS (\Seen))`.
+fn changed(line: &str) -> bool {
+    line.strip_prefix("* ")
+        .and_then(|rest| rest.split_whitespace().next())
+        .is_some_and(|number| number.parse::<u32>().is_ok())
+}
+
+/// Quotes a string for use as an argument to a command.
+fn quote(text: &str) -> Result<String> {
+    if text.contains(['\r', '\n', '\0']) {
+        return Err(anyhow!("line breaks can't be sent to the server"));
+    }
+    Ok(format!(
+        "\"{}\"",
+        text.replace('\\', "\\\\").replace('"', "\\\"")
+    ))
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Imap {
+    /// Configuration options:
+    ///
+    /// - `host`: the hostname of the IMAP server
+    ///   - type: String
+    ///   - default: none (required)
+    /// - `port`: the port of the IMAP server
+    ///   - type: u64
+    ///   - default: 993 if `security` is `tls`, 143 otherwise
+    /// - `security`: how to secure the connection: `tls` to use TLS from the
+    ///   start, `starttls` to upgrade a plain connection before logging in,
+    ///   or `none` for no encryption at all, which should only be used for
+    ///   servers on localhost (e.g. a bridge)
+    ///   - type: String
+    ///   - default: `tls`
+    /// - `user`: the username to log in with
+    ///   - type: String
+    ///   - default: none (required)
+    /// - `password_command`: a command that prints the password (or app
+    ///   password) to stdout, e.g. `pass show mail/imap`. It is run with `sh
+    ///   -c` each time a connection is made, and only the first line of its
+    ///   output is used.
+    ///   - type: String
+    ///   - default: none (required)
+    /// - `mailboxes`: the mailboxes to watch
+    ///   - type: Array of Strings
+    ///   - default: `["INBOX"]`
+    /// - `labels`: the names to show for each mailbox. This must be the same
+    ///   length as `mailboxes` if it is set.
+    ///   - type: Array of Strings
+    ///   - default: the values of `mailboxes`
+    /// - `retry`: how long to wait before reconnecting after an error, in
+    ///   seconds
+    ///   - type: u64
+    ///   - default: 60
+    /// - `show_zero`: whether to show the panel when there are no unread
+    ///   messages
+    ///   - type: bool
+    ///   - default: true
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%total%`
+    ///   - formatting options: `%total%`, `%mailboxes%`, and `%<label>%` for
+    ///     each label, which is `?` until the mailbox has been checked
+    /// - `format_mailbox`: the format string for each mailbox with unread
+    ///   messages in `%mailboxes%`
+    ///   - type: String
+    ///   - default: `%label%: %count%`
+    ///   - formatting options: `%label%`, `%count%`
+    /// - `separator`: the text between mailboxes in `%mailboxes%`
+    ///   - type: String
+    ///   - default: ` `
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = ImapBuilder::default();
+
+        builder.name(name);
+        builder.host(
+            remove_string_from_config("host", table)
+                .ok_or_else(|| anyhow!("{name}: `host` is required"))?,
+        );
+        let security =
+            match remove_string_from_config("security", table).as_deref() {
+                None | Some("tls") => Security::Tls,
+                Some("starttls") => Security::StartTls,
+                Some("none") => Security::None,
+                Some(s) => return Err(anyhow!("{name}: unknown security {s}")),
+            };
+        builder.security(security);
+        builder.port(
+            remove_uint_from_config("port", table).map_or(
+                if security == Security::Tls { 993 } else { 143 },
+                |p| p as u16,
+            ),
+        );
+        builder.user(
+            remove_string_from_config("user", table)
+                .ok_or_else(|| anyhow!("{name}: `user` is required"))?,
+        );
+        builder.password_command(
+            remove_string_from_config("password_command", table).ok_or_else(
+                || anyhow!("{name}: `password_command` is required"),
+            )?,
+        );
+
+        let mailboxes = remove_array_from_config("mailboxes", table)
+            .map(|mailboxes| {
+                mailboxes
+                    .into_iter()
+                    .filter_map(|v| v.into_string().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|| vec![String::from("INBOX")]);
+        let labels = remove_array_from_config("labels", table)
+            .map(|labels| {
+                labels
+                    .into_iter()
+                    .filter_map(|v| v.into_string().ok())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|labels| {
+                let valid = labels.len() == mailboxes.len();
+                if !valid {
+                    log::warn!("labels and mailboxes have different lengths");
+                }
+                valid
+            })
+            .unwrap_or_else(|| mailboxes.clone());
+        builder.mailboxes(mailboxes);
+        builder.labels(labels);
+
+        if let Some(retry) = remove_uint_from_config("retry", table) {
+            builder.retry(Duration::from_secs(retry.max(1)));
+        }
+        if let Some(show_zero) = remove_bool_from_config("show_zero", table) {
+            builder.show_zero(show_zero);
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%total%");
+        let format_mailbox =
+            PanelCommon::parse_format(table, "_mailbox", "%label%: %count%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.format_mailbox(format_mailbox.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        // the watchers are started when the stream is first polled, so
+        // that the password commands they run belong to this panel
+        let imap = (*self).clone();
+        let watchers = futures::stream::once(async move {
+            let (send, recv) = unbounded_channel();
+            let owner = cleanup::owner();
+            for idx in 0..imap.mailboxes.len() {
+                let imap = imap.clone();
+                let send = send.clone();
+                // each watcher blocks, and stops soon after the panel is
+                // dropped
+                thread::spawn(move || {
+                    cleanup::with_owner(owner, || imap.watch(idx, &send));
+                });
+            }
+            UnboundedReceiverStream::new(recv)
+        });
+
+        let mut counts = vec![None; self.mailboxes.len()];
+        let updates =
+            futures::StreamExt::flatten(watchers).map(move |(idx, count)| {
+                counts[idx] = count;
+                counts.clone()
+            });
+
+        let stream = tokio_stream::once(vec![None; self.mailboxes.len()])
+            .chain(updates)
+            .map(move |counts| self.draw(&cr, height, counts.as_slice()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+/// Runs `command` and returns the first line of its output.
+fn password(command: &str) -> Result<String> {
+    let output =
+        cleanup::command_output(Command::new("sh").arg("-c").arg(command))?;
+    if !output.status.success() {
+        return Err(anyhow!("password command exited with {}", output.status));
+    }
+    String::from_utf8(output.stdout)?
+        .lines()
+        .next()
+        .map(String::from)
+        .ok_or_else(|| anyhow!("password command printed nothing"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::VecDeque,
+        io::{self, ErrorKind, Read, Write},
+        time::Duration,
+    };
+
+    use super::{changed, quote, starts_with_word, Session};
+
+    /// A server that replies with a script, where `None` is a read that
+    /// times out
+    struct Script {
+        reads: VecDeque<Option<&'static str>>,
+        written: Vec<u8>,
+    }
+
+    impl Script {
+        fn session(reads: &[Option<&'static str>]) -> Session<Self> {
+            Session::new(Self {
+                reads: reads.iter().copied().collect(),
+                written: Vec::new(),
+            })
+        }
+    }
+
+    impl Read for Script {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            match self.reads.pop_front() {
+                Some(Some(text)) => {
+                    buf[..text.len()].copy_from_slice(text.as_bytes());
+                    Ok(text.len())
+                }
+                Some(None) => Err(ErrorKind::WouldBlock.into()),
+                None => Ok(0),
+            }
+        }
+    }
+
+    impl Write for Script {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn written(session: Session<Script>) -> String {
+        String::from_utf8(session.stream.into_inner().written).unwrap()
+    }
+
+    #[test]
+    fn counts_unseen() {
+        let mut session = Script::session(&[
+            Some("* OK IMAP4rev1 ready\r\n"),
+            Some("a1 OK LOGIN completed\r\n"),
+            Some("* 4 EXISTS\r\n* OK [UIDVALIDITY 1]\r\na2 OK\r\n"),
+            Some("* SEARCH 1 3 4\r\n"),
+            Some("a3 OK SEARCH completed\r\n"),
+        ]);
+        session.greeting().unwrap();
+        session.login("me", r#"p"a\ss"#).unwrap();
+        session.examine("INBOX").unwrap();
+        assert_eq!(session.unseen().unwrap(), 3);
+        assert_eq!(
+            written(session),
+            "a1 LOGIN \"me\" \"p\\\"a\\\\ss\"\r\n\
+             a2 EXAMINE \"INBOX\"\r\n\
+             a3 SEARCH UNSEEN\r\n"
+        );
+    }
+
+    #[test]
+    fn finishes_lines_cut_off_by_timeouts() {
+        let mut session = Script::session(&[
+            Some("* SEA"),
+            None,
+            Some("RCH\r\na1 O"),
+            None,
+            Some("K\r\n"),
+        ]);
+        assert_eq!(session.unseen().unwrap(), 0);
+    }
+
+    #[test]
+    fn fails_on_no() {
+        let mut session =
+            Script::session(&[Some("a1 NO [AUTHENTICATIONFAILED] nope\r\n")]);
+        assert!(session.login("me", "wrong").is_err());
+    }
+
+    #[test]
+    fn fails_on_closed_connection() {
+        let mut session = Script::session(&[Some("* OK rea")]);
+        assert!(session.greeting().is_err());
+    }
+
+    #[test]
+    fn idles_until_the_mailbox_changes() {
+        let mut session = Script::session(&[
+            Some("+ idling\r\n"),
+            None,
+            Some("* OK still here\r\n"),
+            Some("* 5 EXISTS\r\n"),
+            Some("a1 OK IDLE terminated\r\n"),
+        ]);
+        assert!(session.idle(Duration::from_secs(60), || false).unwrap());
+        assert_eq!(written(session), "a1 IDLE\r\nDONE\r\n");
+    }
+
+    #[test]
+    fn stops_idling() {
+        let mut session = Script::session(&[
+            Some("+ idling\r\n"),
+            Some("a1 OK IDLE terminated\r\n"),
+        ]);
+        assert!(!session.idle(Duration::from_secs(60), || true).unwrap());
+        assert_eq!(written(session), "a1 IDLE\r\nDONE\r\n");
+    }
+
+    #[test]
+    fn refuses_data_before_starttls() {
+        let mut session =
+            Script::session(&[Some("a1 OK Begin TLS\r\n* OK injected\r\n")]);
+        session.command("STARTTLS").unwrap();
+        assert!(session.into_inner().is_err());
+    }
+
+    #[test]
+    fn parses_responses() {
+        assert!(changed("* 3 EXISTS"));
+        assert!(changed("* 2 FETCH (FLAGS (\\Seen))"));
+        assert!(!changed("* OK still here"));
+        assert!(starts_with_word("ok done", "OK"));
+        assert!(starts_with_word("OK", "OK"));
+        assert!(!starts_with_word("OKAY", "OK"));
+        assert!(quote("a\r\nb").is_err());
+    }
+}
@@ -19,7 +19,7 @@ use tokio_stream::{Stream, StreamExt};
 
 use crate::{
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
     remove_string_from_config, Attrs, Highlight, PanelConfig, PanelStream,
 };
@@ -56,13 +56,12 @@ impl Inotify {
             .format
             .replace("%file%", buf.lines().next().unwrap_or(""));
 
-        draw_common(
+        draw_panel(
             cr,
             text.as_str(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
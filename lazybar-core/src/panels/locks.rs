@@ -0,0 +1,242 @@
+use std::{collections::HashMap, pin::Pin, rc::Rc, sync::Arc, task::Poll};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::task::{self, JoinHandle};
+use tokio_stream::{Stream, StreamExt};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        self,
+        xkb::{self, ConnectionExt as _},
+        xproto::Atom,
+    },
+    rust_connection::RustConnection,
+};
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config,
+    x::intern_named_atom,
+    Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+/// Displays whether Caps Lock and Num Lock are active, using the XKB
+/// extension
+///
+/// When neither is active, the panel has zero width, so it works well with
+/// [`Dependence`][crate::bar::Dependence].
+#[derive(Debug, Builder, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Locks {
+    name: &'static str,
+    conn: Arc<RustConnection>,
+    format: &'static str,
+    caps: &'static str,
+    num: &'static str,
+    separator: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Locks {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        caps_atom: Atom,
+        num_atom: Atom,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let caps = self.indicator(caps_atom)?;
+        let num = self.indicator(num_atom)?;
+
+        let text = if caps || num {
+            let active = [(caps, self.caps), (num, self.num)]
+                .into_iter()
+                .filter_map(|(on, s)| on.then_some(s))
+                .collect::<Vec<_>>()
+                .join(self.separator);
+            self.format
+                .replace("%locks%", active.as_str())
+                .replace("%caps%", if caps { self.caps } else { "" })
+                .replace("%num%", if num { self.num } else { "" })
+        } else {
+            String::new()
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+
+    fn indicator(&self, atom: Atom) -> Result<bool> {
+        Ok(self
+            .conn
+            .xkb_get_named_indicator(
+                xkb::ID::USE_CORE_KBD.into(),
+                xkb::LedClass::DFLT_XI_CLASS,
+                xkb::ID::DFLT_XI_ID.into(),
+                atom,
+            )?
+            .reply()?
+            .on)
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Locks {
+    /// Configuration options:
+    ///
+    /// - `screen`: the name of the X screen to monitor
+    ///   - type: String
+    ///   - default: None (This will tell X to choose the default screen, which
+    ///     is probably what you want.)
+    /// - `format`: the format string. If neither lock is active, the panel is
+    ///   empty regardless of this value.
+    ///   - type: String
+    ///   - default: `%locks%`
+    ///   - formatting options: `%locks%` (the active locks, joined by
+    ///     `separator`), `%caps%`, `%num%`
+    /// - `caps`: the string to show when Caps Lock is active
+    ///   - type: String
+    ///   - default: `CAPS`
+    /// - `num`: the string to show when Num Lock is active
+    ///   - type: String
+    ///   - default: `NUM`
+    /// - `separator`: the string to put between active locks in `%locks%`
+    ///   - type: String
+    ///   - default: ` ` (one space)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = LocksBuilder::default();
+
+        builder.name(name);
+        let screen = remove_string_from_config("screen", table);
+        if let Ok((conn, _)) = RustConnection::connect(screen.as_deref()) {
+            builder.conn(Arc::new(conn));
+        } else {
+            log::error!("Failed to connect to X server");
+        }
+
+        builder.caps(
+            remove_string_from_config("caps", table)
+                .unwrap_or_else(|| String::from("CAPS"))
+                .leak(),
+        );
+        builder.num(
+            remove_string_from_config("num", table)
+                .unwrap_or_else(|| String::from("NUM"))
+                .leak(),
+        );
+        builder.separator(
+            remove_string_from_config("separator", table)
+                .unwrap_or_else(|| String::from(" "))
+                .leak(),
+        );
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%locks%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.conn.xkb_use_extension(1, 0)?.reply()?;
+        self.conn.xkb_select_events(
+            xkb::ID::USE_CORE_KBD.into(),
+            xkb::EventType::from(0u16),
+            xkb::EventType::INDICATOR_STATE_NOTIFY,
+            xkb::MapPart::from(0u16),
+            xkb::MapPart::from(0u16),
+            &xkb::SelectEventsAux::new(),
+        )?;
+        let caps_atom = intern_named_atom(self.conn.as_ref(), b"Caps Lock")?;
+        let num_atom = intern_named_atom(self.conn.as_ref(), b"Num Lock")?;
+
+        self.attrs.apply_to(&global_attrs);
+
+        let stream = tokio_stream::once(())
+            .chain(XkbStream::new(self.conn.clone()))
+            .map(move |()| self.draw(&cr, caps_atom, num_atom, height));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+struct XkbStream {
+    conn: Arc<RustConnection>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl XkbStream {
+    const fn new(conn: Arc<RustConnection>) -> Self {
+        Self { conn, handle: None }
+    }
+}
+
+impl Stream for XkbStream {
+    type Item = ();
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        if let Some(handle) = &self.handle {
+            if handle.is_finished() {
+                self.handle = None;
+                Poll::Ready(Some(()))
+            } else {
+                Poll::Pending
+            }
+        } else {
+            let conn = self.conn.clone();
+            let waker = cx.waker().clone();
+            self.handle = Some(task::spawn_blocking(move || loop {
+                let event = conn.wait_for_event();
+                if let Ok(protocol::Event::XkbIndicatorStateNotify(_)) = event {
+                    waker.wake();
+                    break;
+                }
+            }));
+            Poll::Pending
+        }
+    }
+}
@@ -0,0 +1,348 @@
+use std::{
+    collections::HashMap,
+    env,
+    fs::read_dir,
+    path::{Path, PathBuf},
+    process::Command,
+    rc::Rc,
+    thread,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    Stream, StreamExt,
+};
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+/// Where unread counts come from. Each source is a list of names, sorted
+/// alphabetically, and what to count for each.
+#[derive(Debug, Clone)]
+enum Source {
+    /// Maildir folders, watched with inotify
+    Maildir(Vec<(String, PathBuf)>),
+    /// notmuch queries, run every `interval`
+    Notmuch(Vec<(String, String)>),
+}
+
+impl Source {
+    fn count(&self) -> Result<Vec<(String, u64)>> {
+        match self {
+            Self::Maildir(dirs) => dirs
+                .iter()
+                .map(|(name, dir)| Ok((name.clone(), count_maildir(dir)?)))
+                .collect(),
+            Self::Notmuch(queries) => queries
+                .iter()
+                .map(|(name, query)| Ok((name.clone(), count_notmuch(query)?)))
+                .collect(),
+        }
+    }
+}
+
+/// Counts the messages in a maildir that haven't been seen. Everything in
+/// `new` is unread, and messages in `cur` are unread unless they're flagged
+/// as seen (`S`) or trashed (`T`).
+fn count_maildir(dir: &Path) -> Result<u64> {
+    let new = read_dir(dir.join("new"))?.count() as u64;
+    let cur = read_dir(dir.join("cur"))?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            let flags = name.rsplit_once(":2,").map_or("", |(_, flags)| flags);
+            !flags.contains(['S', 'T'])
+        })
+        .count() as u64;
+    Ok(new + cur)
+}
+
+fn count_notmuch(query: &str) -> Result<u64> {
+    let output = cleanup::command_output(
+        Command::new("notmuch").arg("count").arg(query),
+    )?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "notmuch count failed: {}",
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(output.stdout.as_slice())
+        .trim()
+        .parse()?)
+}
+
+/// Watches the `new` and `cur` folders of each maildir, sending on `send`
+/// whenever messages are added, removed, or renamed (which is how flags
+/// change). The inotify instance is read on its own thread, which exits at the
+/// first change after `send` is closed.
+fn watch_maildirs(
+    dirs: &[(String, PathBuf)],
+    send: UnboundedSender<()>,
+) -> Result<()> {
+    let inotify = Inotify::init(InitFlags::IN_CLOEXEC)?;
+    let flags = AddWatchFlags::IN_CREATE
+        | AddWatchFlags::IN_DELETE
+        | AddWatchFlags::IN_MOVED_FROM
+        | AddWatchFlags::IN_MOVED_TO;
+    for (_, dir) in dirs {
+        inotify.add_watch(dir.join("new").as_path(), flags)?;
+        inotify.add_watch(dir.join("cur").as_path(), flags)?;
+    }
+
+    thread::spawn(move || loop {
+        // each read returns every event that's waiting, so a burst of new
+        // mail only causes one recount
+        match inotify.read_events() {
+            Ok(_) => {
+                if send.send(()).is_err() {
+                    break;
+                }
+            }
+            Err(e) => {
+                log::warn!("Stopped watching maildirs: {e}");
+                break;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Expands a leading `~` to the home directory.
+fn expand_home(path: String) -> PathBuf {
+    match (path.strip_prefix("~/"), env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Parses a table of names to strings, sorted by name.
+fn parse_names(value: Value) -> Result<Vec<(String, String)>> {
+    let mut names = value
+        .into_table()?
+        .into_iter()
+        .map(|(name, value)| Ok((name, value.into_string()?)))
+        .collect::<Result<Vec<_>>>()?;
+    names.sort();
+    Ok(names)
+}
+
+/// Displays the number of unread messages in maildir folders or notmuch
+/// queries, e.g. `inbox 3 lists 12`. The panel is hidden when everything is
+/// read.
+///
+/// Maildirs are watched with inotify, so counts update as soon as mail
+/// arrives or is read. notmuch queries are counted every `interval`.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Mail {
+    name: &'static str,
+    source: Source,
+    #[builder(default = "Duration::from_secs(30)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    counts: Vec<(String, u64)>,
+    format: &'static str,
+    format_count: &'static str,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Mail {
+    fn update(&mut self, counts: Result<Vec<(String, u64)>>) {
+        match counts {
+            Ok(counts) => self.counts = counts,
+            // keep showing the last counts
+            Err(e) => log::warn!("{}: failed to count mail: {e}", self.name),
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let total = self.counts.iter().map(|(_, count)| count).sum::<u64>();
+        let text = if total == 0 {
+            String::new()
+        } else {
+            let counts = self
+                .counts
+                .iter()
+                .filter(|(_, count)| *count > 0)
+                .map(|(name, count)| {
+                    self.format_count
+                        .replace("%name%", name.as_str())
+                        .replace("%count%", count.to_string().as_str())
+                })
+                .collect::<Vec<_>>()
+                .join(self.separator.as_str());
+            self.format
+                .replace("%counts%", counts.as_str())
+                .replace("%total%", total.to_string().as_str())
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Mail {
+    /// Configuration options:
+    ///
+    /// - `maildirs`: a table mapping names to maildir folders (the folders
+    ///   containing `new` and `cur`), e.g. `{ inbox = "~/Mail/INBOX" }`
+    ///   - type: Table of Strings
+    ///   - default: none
+    /// - `queries`: a table mapping names to notmuch queries, e.g. `{ inbox =
+    ///   "tag:inbox and tag:unread" }`. Exactly one of `maildirs` and
+    ///   `queries` must be set.
+    ///   - type: Table of Strings
+    ///   - default: none
+    /// - `interval`: how long to wait in seconds between each notmuch count.
+    ///   Ignored for maildirs.
+    ///   - type: u64
+    ///   - default: 30
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%counts%`
+    ///   - formatting options: `%counts%` (each nonzero count, formatted with
+    ///     `format_count` and joined with `separator`), `%total%`
+    /// - `format_count`: the format string for each name
+    ///   - type: String
+    ///   - default: `%name% %count%`
+    ///   - formatting options: `%name%`, `%count%`
+    /// - `separator`: the text between each count
+    ///   - type: String
+    ///   - default: a space
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = MailBuilder::default();
+
+        builder.name(name);
+        builder.source(
+            match (table.remove("maildirs"), table.remove("queries")) {
+                (Some(maildirs), None) => Source::Maildir(
+                    parse_names(maildirs)?
+                        .into_iter()
+                        .map(|(name, path)| (name, expand_home(path)))
+                        .collect(),
+                ),
+                (None, Some(queries)) => Source::Notmuch(parse_names(queries)?),
+                _ => {
+                    return Err(anyhow!(
+                        "{name}: exactly one of maildirs and queries must be \
+                         set"
+                    ))
+                }
+            },
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%counts%");
+        let format_count =
+            PanelCommon::parse_format(table, "_count", "%name% %count%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.format_count(format_count.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let triggers: Box<dyn Stream<Item = ()> + Unpin> = match self.source {
+            Source::Maildir(ref dirs) => {
+                let (send, recv) = unbounded_channel();
+                watch_maildirs(dirs, send)?;
+                // count once at startup, then after each change
+                Box::new(
+                    tokio_stream::once(())
+                        .chain(UnboundedReceiverStream::new(recv)),
+                )
+            }
+            Source::Notmuch(_) => Box::new(
+                IntervalStream::new(interval(self.interval)).map(|_| ()),
+            ),
+        };
+
+        let source = self.source.clone();
+        let stream = triggers
+            .then(move |()| {
+                let source = source.clone();
+                async move {
+                    cleanup::spawn_blocking(move || source.count())
+                        .await
+                        .map_err(anyhow::Error::from)
+                        .and_then(|counts| counts)
+                }
+            })
+            .map(move |counts| {
+                self.update(counts);
+                self.draw(&cr, height)
+            });
+
+        Ok((Box::pin(stream), None))
+    }
+}
@@ -19,10 +19,11 @@ use tokio_stream::StreamExt;
 
 use crate::{
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
-    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
-    ManagedIntervalStream, PanelConfig, PanelStream,
+    progress::StackedBar,
+    remove_string_from_config, remove_uint_from_config, state, Attrs,
+    Highlight, ManagedIntervalStream, PanelConfig, PanelStream,
 };
 
 lazy_static! {
@@ -48,6 +49,8 @@ pub struct Memory {
     attrs: Attrs,
     #[builder(default, setter(strip_option))]
     highlight: Option<Highlight>,
+    #[builder(default)]
+    stack: StackedBar,
     common: PanelCommon,
 }
 
@@ -87,6 +90,14 @@ impl Memory {
                 anyhow!("couldn't find or approximate MemAvailable")
             })?;
         let mem_used = mem_total - mem_free;
+        // reclaimable memory that the kernel is using as a cache, which is
+        // counted as free above
+        let mem_cache = ["Buffers", "Cached", "SReclaimable"]
+            .iter()
+            .filter_map(|key| map.get(*key))
+            .sum::<u64>()
+            .saturating_sub(map.get("Shmem").copied().unwrap_or_default())
+            .min(mem_free);
 
         let percentage_used =
             (mem_used as f64 / mem_total as f64 * 100.0) as u64;
@@ -102,6 +113,9 @@ impl Memory {
         let percentage_swap_used =
             (swap_used as f64 / swap_total as f64 * 100.0) as u64;
 
+        state::publish(self.name, "percentage_used", percentage_used);
+        state::publish(self.name, "percentage_swap_used", percentage_swap_used);
+
         let mut text = String::new();
         self.formatter.replace_all_with(
             self.format,
@@ -221,14 +235,23 @@ impl Memory {
                 }
             },
         );
+        let text = self.stack.replace(
+            text.as_str(),
+            &[
+                mem_used as f64,
+                mem_cache as f64,
+                (mem_free - mem_cache) as f64,
+            ],
+        );
 
-        draw_common(
+        let percentage_used = percentage_used as f64;
+        let attrs = self.common.thresholds.attrs(&self.attrs, percentage_used);
+        draw_panel(
             cr,
             text.as_str(),
-            &self.attrs,
-            self.common.dependence,
+            &attrs.ramped(percentage_used, 0.0, 100.0),
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
@@ -251,9 +274,12 @@ impl PanelConfig for Memory {
     ///   - type: String
     ///   - default: `RAM: %percentage_used%`
     ///   - formatting options: `%{gb,mb}_[swap_]{total,used,free}%,
-    ///     %percentage_[swap_]{used,free}%`
+    ///     %percentage_[swap_]{used,free}%`, `%stack%` (a bar split into
+    ///     used, cached, and free memory; see [`StackedBar::parse`])
     /// - `attrs`: A string specifying the attrs for the panel. See
-    ///   [`Attrs::parse`] for details.
+    ///   [`Attrs::parse`] for details. A `fg_ramp` uses the percentage of
+    ///   memory used, as do `warn_above` and `crit_above` (see
+    ///   [`Thresholds::parse`][crate::threshold::Thresholds::parse]).
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
     /// - See [`PanelCommon::parse_common`].
@@ -282,6 +308,7 @@ impl PanelConfig for Memory {
         builder.format(format.leak());
         builder.attrs(attrs);
         builder.highlight(highlight);
+        builder.stack(StackedBar::parse(table));
 
         builder.formatter(AhoCorasick::new([
             "%gb_total%",
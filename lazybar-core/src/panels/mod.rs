@@ -1,33 +1,89 @@
 #[cfg(feature = "battery")]
 mod battery;
+#[cfg(feature = "bluetooth")]
+mod bluetooth;
+#[cfg(feature = "calendar")]
+mod calendar;
+#[cfg(feature = "cgroup")]
+mod cgroup;
 #[cfg(feature = "clock")]
 mod clock;
+#[cfg(feature = "composite")]
+mod composite;
 #[cfg(feature = "cpu")]
 mod cpu;
+#[cfg(feature = "cpu_cores")]
+mod cpu_cores;
 #[cfg(feature = "custom")]
 mod custom;
+#[cfg(feature = "dunst")]
+mod dunst;
 #[cfg(feature = "fanotify")]
 mod fanotify;
+#[cfg(feature = "feed")]
+mod feed;
 #[cfg(feature = "github")]
 mod github;
+#[cfg(feature = "gpu")]
+mod gpu;
+#[cfg(feature = "idle")]
+mod idle;
+#[cfg(feature = "imap")]
+mod imap;
 #[cfg(feature = "inotify")]
 mod inotify;
+#[cfg(feature = "locks")]
+mod locks;
+#[cfg(feature = "mail")]
+mod mail;
 #[cfg(feature = "memory")]
 mod memory;
 #[cfg(feature = "mpd")]
 mod mpd;
 #[cfg(feature = "network")]
 mod network;
+#[cfg(feature = "nightlight")]
+mod nightlight;
 #[cfg(feature = "ping")]
 mod ping;
+#[cfg(feature = "pomodoro")]
+mod pomodoro;
+#[cfg(feature = "powerprofile")]
+mod powerprofile;
+#[cfg(feature = "privacy")]
+mod privacy;
+#[cfg(feature = "publicip")]
+mod publicip;
 #[cfg(feature = "pulseaudio")]
 mod pulseaudio;
+#[cfg(feature = "runner")]
+mod runner;
+#[cfg(feature = "selfstats")]
+mod selfstats;
 #[cfg(feature = "separator")]
 mod separator;
+#[cfg(feature = "sni")]
+mod sni;
+#[cfg(feature = "spacer")]
+mod spacer;
+#[cfg(feature = "storage")]
+mod storage;
+#[cfg(feature = "sun")]
+mod sun;
 #[cfg(feature = "systray")]
 mod systray;
+#[cfg(feature = "taskwarrior")]
+mod taskwarrior;
 #[cfg(feature = "temp")]
 mod temp;
+#[cfg(feature = "ticker")]
+mod ticker;
+#[cfg(feature = "timer")]
+mod timer;
+#[cfg(feature = "updates")]
+mod updates;
+#[cfg(feature = "uptime")]
+mod uptime;
 #[cfg(feature = "xwindow")]
 mod xwindow;
 #[cfg(feature = "xworkspaces")]
@@ -35,34 +91,90 @@ mod xworkspaces;
 
 #[cfg(feature = "battery")]
 pub use battery::Battery;
+#[cfg(feature = "bluetooth")]
+pub use bluetooth::Bluetooth;
+#[cfg(feature = "calendar")]
+pub use calendar::Calendar;
+#[cfg(feature = "cgroup")]
+pub use cgroup::Cgroup;
 #[cfg(feature = "clock")]
 pub use clock::Clock;
+#[cfg(feature = "composite")]
+pub use composite::Composite;
 #[cfg(feature = "cpu")]
 pub use cpu::Cpu;
+#[cfg(feature = "cpu_cores")]
+pub use cpu_cores::CpuCores;
 #[cfg(feature = "custom")]
 pub use custom::Custom;
+#[cfg(feature = "dunst")]
+pub use dunst::Dunst;
 #[cfg(feature = "fanotify")]
 pub use fanotify::Fanotify;
+#[cfg(feature = "feed")]
+pub use feed::Feed;
 #[cfg(feature = "github")]
 pub use github::Github;
+#[cfg(feature = "gpu")]
+pub use gpu::Gpu;
+#[cfg(feature = "idle")]
+pub use idle::IdleCountdown;
+#[cfg(feature = "imap")]
+pub use imap::Imap;
 #[cfg(feature = "inotify")]
 pub use inotify::Inotify;
+#[cfg(feature = "locks")]
+pub use locks::Locks;
+#[cfg(feature = "mail")]
+pub use mail::Mail;
 #[cfg(feature = "memory")]
 pub use memory::Memory;
 #[cfg(feature = "mpd")]
 pub use mpd::Mpd;
 #[cfg(feature = "network")]
 pub use network::Network;
+#[cfg(feature = "nightlight")]
+pub use nightlight::NightLight;
 #[cfg(feature = "ping")]
 pub use ping::Ping;
+#[cfg(feature = "pomodoro")]
+pub use pomodoro::Pomodoro;
+#[cfg(feature = "powerprofile")]
+pub use powerprofile::PowerProfile;
+#[cfg(feature = "privacy")]
+pub use privacy::Privacy;
+#[cfg(feature = "publicip")]
+pub use publicip::PublicIp;
 #[cfg(feature = "pulseaudio")]
 pub use pulseaudio::Pulseaudio;
+#[cfg(feature = "runner")]
+pub use runner::Runner;
+#[cfg(feature = "selfstats")]
+pub use selfstats::SelfStats;
 #[cfg(feature = "separator")]
 pub use separator::Separator;
+#[cfg(feature = "sni")]
+pub use sni::Sni;
+#[cfg(feature = "spacer")]
+pub use spacer::Spacer;
+#[cfg(feature = "storage")]
+pub use storage::Storage;
+#[cfg(feature = "sun")]
+pub use sun::Sun;
 #[cfg(feature = "systray")]
 pub use systray::Systray;
+#[cfg(feature = "taskwarrior")]
+pub use taskwarrior::Taskwarrior;
 #[cfg(feature = "temp")]
 pub use temp::Temp;
+#[cfg(feature = "ticker")]
+pub use ticker::Ticker;
+#[cfg(feature = "timer")]
+pub use timer::Timer;
+#[cfg(feature = "updates")]
+pub use updates::Updates;
+#[cfg(feature = "uptime")]
+pub use uptime::Uptime;
 #[cfg(feature = "xwindow")]
 pub use xwindow::XWindow;
 #[cfg(feature = "xworkspaces")]
@@ -72,34 +184,92 @@ pub use xworkspaces::XWorkspaces;
 pub mod builders {
     #[cfg(feature = "battery")]
     pub use super::battery::{BatteryBuilder, BatteryBuilderError};
+    #[cfg(feature = "bluetooth")]
+    pub use super::bluetooth::{BluetoothBuilder, BluetoothBuilderError};
+    #[cfg(feature = "calendar")]
+    pub use super::calendar::{CalendarBuilder, CalendarBuilderError};
+    #[cfg(feature = "cgroup")]
+    pub use super::cgroup::{CgroupBuilder, CgroupBuilderError};
     #[cfg(feature = "clock")]
     pub use super::clock::{ClockBuilder, ClockBuilderError};
+    #[cfg(feature = "composite")]
+    pub use super::composite::{CompositeBuilder, CompositeBuilderError};
     #[cfg(feature = "cpu")]
     pub use super::cpu::{CpuBuilder, CpuBuilderError};
+    #[cfg(feature = "cpu_cores")]
+    pub use super::cpu_cores::{CpuCoresBuilder, CpuCoresBuilderError};
     #[cfg(feature = "custom")]
     pub use super::custom::{CustomBuilder, CustomBuilderError};
+    #[cfg(feature = "dunst")]
+    pub use super::dunst::{DunstBuilder, DunstBuilderError};
     #[cfg(feature = "fanotify")]
     pub use super::fanotify::{FanotifyBuilder, FanotifyBuilderError};
+    #[cfg(feature = "feed")]
+    pub use super::feed::{FeedBuilder, FeedBuilderError};
     #[cfg(feature = "github")]
     pub use super::github::{GithubBuilder, GithubBuilderError};
+    #[cfg(feature = "gpu")]
+    pub use super::gpu::{GpuBuilder, GpuBuilderError};
+    #[cfg(feature = "idle")]
+    pub use super::idle::{IdleCountdownBuilder, IdleCountdownBuilderError};
+    #[cfg(feature = "imap")]
+    pub use super::imap::{ImapBuilder, ImapBuilderError};
     #[cfg(feature = "inotify")]
     pub use super::inotify::{InotifyBuilder, InotifyBuilderError};
+    #[cfg(feature = "locks")]
+    pub use super::locks::{LocksBuilder, LocksBuilderError};
+    #[cfg(feature = "mail")]
+    pub use super::mail::{MailBuilder, MailBuilderError};
     #[cfg(feature = "memory")]
     pub use super::memory::{MemoryBuilder, MemoryBuilderError};
     #[cfg(feature = "mpd")]
     pub use super::mpd::{MpdBuilder, MpdBuilderError};
     #[cfg(feature = "network")]
     pub use super::network::{NetworkBuilder, NetworkBuilderError};
+    #[cfg(feature = "nightlight")]
+    pub use super::nightlight::{NightLightBuilder, NightLightBuilderError};
     #[cfg(feature = "ping")]
     pub use super::ping::{PingBuilder, PingBuilderError};
+    #[cfg(feature = "pomodoro")]
+    pub use super::pomodoro::{PomodoroBuilder, PomodoroBuilderError};
+    #[cfg(feature = "powerprofile")]
+    pub use super::powerprofile::{
+        PowerProfileBuilder, PowerProfileBuilderError,
+    };
+    #[cfg(feature = "privacy")]
+    pub use super::privacy::{PrivacyBuilder, PrivacyBuilderError};
+    #[cfg(feature = "publicip")]
+    pub use super::publicip::{PublicIpBuilder, PublicIpBuilderError};
     #[cfg(feature = "pulseaudio")]
     pub use super::pulseaudio::{PulseaudioBuilder, PulseaudioBuilderError};
+    #[cfg(feature = "runner")]
+    pub use super::runner::{RunnerBuilder, RunnerBuilderError};
+    #[cfg(feature = "selfstats")]
+    pub use super::selfstats::{SelfStatsBuilder, SelfStatsBuilderError};
     #[cfg(feature = "separator")]
     pub use super::separator::{SeparatorBuilder, SeparatorBuilderError};
+    #[cfg(feature = "sni")]
+    pub use super::sni::{SniBuilder, SniBuilderError};
+    #[cfg(feature = "spacer")]
+    pub use super::spacer::{SpacerBuilder, SpacerBuilderError};
+    #[cfg(feature = "storage")]
+    pub use super::storage::{StorageBuilder, StorageBuilderError};
+    #[cfg(feature = "sun")]
+    pub use super::sun::{SunBuilder, SunBuilderError};
     #[cfg(feature = "systray")]
     pub use super::systray::{SystrayBuilder, SystrayBuilderError};
+    #[cfg(feature = "taskwarrior")]
+    pub use super::taskwarrior::{TaskwarriorBuilder, TaskwarriorBuilderError};
     #[cfg(feature = "temp")]
     pub use super::temp::{TempBuilder, TempBuilderError};
+    #[cfg(feature = "ticker")]
+    pub use super::ticker::{TickerBuilder, TickerBuilderError};
+    #[cfg(feature = "timer")]
+    pub use super::timer::{TimerBuilder, TimerBuilderError};
+    #[cfg(feature = "updates")]
+    pub use super::updates::{UpdatesBuilder, UpdatesBuilderError};
+    #[cfg(feature = "uptime")]
+    pub use super::uptime::{UptimeBuilder, UptimeBuilderError};
     #[cfg(feature = "xwindow")]
     pub use super::xwindow::{XWindowBuilder, XWindowBuilderError};
     #[cfg(feature = "xworkspaces")]
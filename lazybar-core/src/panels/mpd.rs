@@ -521,6 +521,7 @@ impl Mpd {
                 }
                 Ok(())
             }
+            Event::MouseEnter | Event::MouseLeave => Ok(()),
         }
         .map_or_else(
             |e| {
@@ -622,11 +623,12 @@ impl PanelConfig for Mpd {
     ///   - default: `false`
     /// - `progress_bg`: the background color of the progress bar (ignored if
     ///   `!progress_bar`)
-    /// - `max_width`: the maximum width in characters of the panel (0 means no
-    ///   maximum)
+    /// - `max_length`: the maximum length in characters of the panel (0 means
+    ///   no maximum). To limit the width in pixels instead, use `max_width`
+    ///   from [`PanelCommon::parse_common`].
     ///   - type: u64
     ///   - default: 0
-    /// - `strategy`: how to handle overflow of `max_width`
+    /// - `strategy`: how to handle overflow of `max_length`
     ///   - type: String - `scroll` or `truncate`
     ///   - default: truncate
     /// - `scroll_interval`: how often in milliseconds to scroll the text
@@ -692,8 +694,8 @@ impl PanelConfig for Mpd {
         {
             builder.progress_bg(progress_bg);
         }
-        if let Some(max_width) = remove_uint_from_config("max_width", table) {
-            builder.max_width(max_width as usize);
+        if let Some(max_length) = remove_uint_from_config("max_length", table) {
+            builder.max_width(max_length as usize);
         }
         builder.last_layout(Rc::new(Mutex::new(None)));
         builder.index_cache(Arc::new(Mutex::new(None)));
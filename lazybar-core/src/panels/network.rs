@@ -5,7 +5,7 @@ use std::{
     os::fd::AsRawFd,
     rc::Rc,
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
@@ -22,7 +22,10 @@ use tokio_stream::StreamExt;
 use crate::{
     array_to_struct,
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_graph, draw_panel, Display, PanelCommon, ShowHide},
+    demo, format,
+    graph::Graph,
+    human_readable,
     ipc::ChannelEndpoint,
     remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
     ManagedIntervalStream, PanelConfig, PanelStream,
@@ -47,22 +50,47 @@ pub struct Network {
     attrs: Attrs,
     #[builder(default, setter(strip_option))]
     highlight: Option<Highlight>,
+    #[builder(default)]
+    graph: Option<Graph>,
+    /// The bytes received and sent on the interface as of the last poll
+    #[builder(default, setter(skip))]
+    last_bytes: Option<((u64, u64), Instant)>,
     common: PanelCommon,
 }
 
 impl Network {
     fn draw(
-        &self,
+        &mut self,
         cr: &Rc<cairo::Context>,
         height: i32,
         paused: Arc<Mutex<bool>>,
     ) -> Result<PanelDrawInfo> {
-        let essid = glib::markup_escape_text(
-            query_essid(self.if_name.as_str())
-                .unwrap_or_default()
-                .as_str(),
+        let (essid, ip) = if demo::enabled() {
+            demo::network().map_or((String::new(), None), |(essid, ip)| {
+                (String::from(essid), Some(ip))
+            })
+        } else {
+            (
+                query_essid(self.if_name.as_str()).unwrap_or_default(),
+                query_ip(self.if_name.as_str()),
+            )
+        };
+        let essid = glib::markup_escape_text(essid.as_str());
+
+        let bytes = query_bytes(self.if_name.as_str());
+        let now = Instant::now();
+        // bytes per second received and sent since the last poll
+        let rates = bytes.zip(self.last_bytes).map(
+            |((rx, tx), ((last_rx, last_tx), then))| {
+                let secs =
+                    now.duration_since(then).as_secs_f64().max(f64::EPSILON);
+                (
+                    rx.saturating_sub(last_rx) as f64 / secs,
+                    tx.saturating_sub(last_tx) as f64 / secs,
+                )
+            },
         );
-        let ip = query_ip(self.if_name.as_str());
+        self.last_bytes = bytes.map(|bytes| (bytes, now));
 
         let text = ip.map_or_else(
             || {
@@ -79,14 +107,40 @@ impl Network {
                     .replace("%local_ip%", ip.to_string().as_str())
             },
         );
+        let (rx, tx) = rates.unwrap_or_default();
+        let text = format::replace_number(
+            text.as_str(),
+            "rx",
+            rx,
+            human_readable(rx as u64).as_str(),
+        );
+        let text = format::replace_number(
+            text.as_str(),
+            "tx",
+            tx,
+            human_readable(tx as u64).as_str(),
+        );
+
+        if let Some(ref mut graph) = self.graph {
+            graph.push(rates.map(|(rx, tx)| rx + tx));
+            return draw_graph(
+                cr,
+                graph,
+                text.as_str(),
+                &self.attrs,
+                &self.common,
+                self.highlight.clone(),
+                height,
+                ShowHide::Default(paused, self.waker.clone()),
+            );
+        }
 
-        draw_common(
+        draw_panel(
             cr,
             text.as_str(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
@@ -108,6 +162,10 @@ impl PanelConfig for Network {
     ///   present on the interface
     ///   - type: String
     ///   - default: "%ifname% %essid% %local_ip%"
+    ///   - formatting options: `%ifname%`, `%essid%`, `%local_ip%`, `%rx%`
+    ///     and `%tx%` (the bytes per second received and sent since the last
+    ///     poll, e.g. `1.5K`; see [`format::replace_number`] for modifiers
+    ///     like `%rx:unit=iec%`)
     /// - `format_disconnected`: the format string when there is no connection
     ///   present on the interface
     ///   - type: String
@@ -116,6 +174,9 @@ impl PanelConfig for Network {
     ///   [`Attrs::parse`] for details.
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
+    /// - `display`: `graph` draws a sparkline of the bytes per second sent
+    ///   and received on the interface instead of the format string. See
+    ///   [`Graph::parse`] for the other options.
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
@@ -145,6 +206,7 @@ impl PanelConfig for Network {
         builder.formats(NetworkFormats::new(formats));
         builder.attrs(attrs);
         builder.highlight(highlight);
+        builder.graph(Display::parse(table).into_graph());
 
         Ok(builder.build()?)
     }
@@ -164,7 +226,11 @@ impl PanelConfig for Network {
 
         let paused = Arc::new(Mutex::new(false));
         let stream = ManagedIntervalStream::builder()
-            .duration(self.duration)
+            .duration(if demo::enabled() {
+                self.duration.min(demo::TICK)
+            } else {
+                self.duration
+            })
             .paused(paused.clone())
             .waker(self.waker.clone())
             .build()?
@@ -261,3 +327,17 @@ fn query_ipv6(if_name: &str) -> Option<IpAddr> {
 fn query_ip(if_name: &str) -> Option<IpAddr> {
     query_ipv4(if_name).or_else(|| query_ipv6(if_name))
 }
+
+/// Returns the total number of bytes sent and received on an interface.
+fn query_bytes(if_name: &str) -> Option<(u64, u64)> {
+    let query = |stat: &str| {
+        std::fs::read_to_string(format!(
+            "/sys/class/net/{if_name}/statistics/{stat}"
+        ))
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+    };
+    Some((query("rx_bytes")?, query("tx_bytes")?))
+}
@@ -0,0 +1,460 @@
+use std::{
+    collections::HashMap,
+    fs::{read_dir, read_to_string},
+    process::Command,
+    rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use nix::{
+    sys::signal::{kill, Signal},
+    unistd::Pid,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+use zbus::{proxy, proxy::CacheProperties, Connection};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+/// The daemons that toggle on SIGUSR1
+const PROGRAMS: [&str; 2] = ["gammastep", "redshift"];
+
+#[proxy(
+    interface = "dk.jonls.redshift.Redshift",
+    default_service = "dk.jonls.redshift.Redshift",
+    default_path = "/dk/jonls/redshift/Redshift"
+)]
+trait Redshift {
+    #[zbus(property)]
+    fn inhibited(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn set_inhibited(&self, value: bool) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn temperature(&self) -> zbus::Result<u32>;
+}
+
+/// How to find the daemon
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum Backend {
+    /// Use D-Bus when the daemon is on the bus, otherwise look for its process
+    #[default]
+    Auto,
+    /// Only use D-Bus
+    DBus,
+    /// Only look for the process
+    Process,
+}
+
+array_to_struct!(NightLightFormats, on, off);
+
+#[derive(Debug, Clone, Copy)]
+struct State {
+    /// The daemon's process, if it was found that way rather than over D-Bus
+    pid: Option<Pid>,
+    inhibited: bool,
+    temp: Option<u32>,
+}
+
+impl State {
+    async fn fetch(
+        proxy: Option<RedshiftProxy<'static>>,
+        backend: Backend,
+    ) -> Result<Option<Self>> {
+        if let Some(proxy) = proxy {
+            match Self::from_dbus(&proxy).await {
+                Ok(state) => return Ok(Some(state)),
+                Err(e) if backend == Backend::DBus => return Err(e),
+                Err(_) => {}
+            }
+        }
+        if backend == Backend::DBus {
+            Ok(None)
+        } else {
+            cleanup::spawn_blocking(Self::from_process).await?
+        }
+    }
+
+    async fn from_dbus(proxy: &RedshiftProxy<'_>) -> Result<Self> {
+        Ok(Self {
+            pid: None,
+            inhibited: proxy.inhibited().await?,
+            temp: Some(proxy.temperature().await?),
+        })
+    }
+
+    /// Finds a running daemon in `/proc` and asks a copy of it, started with
+    /// the same arguments, for the current color temperature.
+    fn from_process() -> Result<Option<Self>> {
+        for entry in read_dir("/proc")? {
+            let entry = entry?;
+            let Ok(pid) = entry.file_name().to_string_lossy().parse::<i32>()
+            else {
+                continue;
+            };
+            let Ok(comm) = read_to_string(entry.path().join("comm")) else {
+                continue;
+            };
+            if !PROGRAMS.contains(&comm.trim()) {
+                continue;
+            }
+
+            let temp = read_to_string(entry.path().join("cmdline"))
+                .map_err(anyhow::Error::from)
+                .and_then(|cmdline| print_temp(cmdline.as_str()))
+                .map_err(|e| log::debug!("Failed to get temperature: {e}"))
+                .ok();
+            return Ok(Some(Self {
+                pid: Some(Pid::from_raw(pid)),
+                inhibited: false,
+                temp,
+            }));
+        }
+        Ok(None)
+    }
+}
+
+/// Runs the command line in `cmdline` (NUL separated, as in
+/// `/proc/<pid>/cmdline`) with `-p`, which prints the current settings and
+/// exits, and returns the color temperature.
+fn print_temp(cmdline: &str) -> Result<u32> {
+    let mut args = cmdline.split('\0').filter(|arg| !arg.is_empty());
+    let program = args.next().ok_or_else(|| anyhow!("Empty command line"))?;
+    let output =
+        cleanup::command_output(Command::new(program).args(args).arg("-p"))?;
+    String::from_utf8_lossy(output.stdout.as_slice())
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("Color temperature:"))
+        .ok_or_else(|| anyhow!("No color temperature in output"))?
+        .trim()
+        .trim_end_matches('K')
+        .parse()
+        .map_err(Into::into)
+}
+
+#[derive(Debug)]
+enum Update {
+    State(Result<Option<State>>),
+    Event(Event),
+}
+
+/// Shows whether a screen color temperature daemon (gammastep or redshift) is
+/// active, along with the current color temperature.
+///
+/// The daemon is found over D-Bus with the `dk.jonls.redshift.Redshift`
+/// interface if it's available, and otherwise by looking for a running
+/// `gammastep` or `redshift` process. Processes don't report whether they're
+/// toggled off, so the panel assumes that a newly found process is on and
+/// keeps track of the toggles it sends.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct NightLight {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    #[builder(default)]
+    backend: Backend,
+    #[builder(default, setter(skip))]
+    state: Option<State>,
+    formats: NightLightFormats<String>,
+    attrs: Attrs,
+    attrs_off: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    #[builder(default, setter(strip_option))]
+    highlight_off: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl NightLight {
+    fn update(
+        &mut self,
+        update: Update,
+        proxy: Option<&RedshiftProxy<'static>>,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::State(Ok(state)) => {
+                self.state = state.map(|mut state| {
+                    // keep the toggles sent to the same process
+                    if let Some(old) = self.state {
+                        if state.pid.is_some() && state.pid == old.pid {
+                            state.inhibited = old.inhibited;
+                        }
+                    }
+                    state
+                });
+                None
+            }
+            Update::State(Err(e)) => {
+                log::debug!("{}: {e}", self.name);
+                self.state = None;
+                None
+            }
+            Update::Event(event) => {
+                Some(self.process_event(event, proxy, refresh_send))
+            }
+        }
+    }
+
+    fn process_event(
+        &mut self,
+        event: Event,
+        proxy: Option<&RedshiftProxy<'static>>,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                self.toggle(proxy, refresh_send)
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "toggle" => {
+                self.toggle(proxy, refresh_send)
+            }
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    fn toggle(
+        &mut self,
+        proxy: Option<&RedshiftProxy<'static>>,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        let state = self
+            .state
+            .as_mut()
+            .ok_or_else(|| anyhow!("No night light daemon is running"))?;
+
+        if let Some(pid) = state.pid {
+            kill(pid, Signal::SIGUSR1)?;
+            state.inhibited = !state.inhibited;
+            return Ok(());
+        }
+
+        let proxy = proxy
+            .ok_or_else(|| anyhow!("Not connected to the session bus"))?
+            .clone();
+        let inhibited = state.inhibited;
+        let refresh_send = refresh_send.clone();
+        let name = self.name;
+        tokio::spawn(async move {
+            if let Err(e) = proxy.set_inhibited(!inhibited).await {
+                log::warn!("{name}: failed to toggle: {e}");
+            }
+            let _ = refresh_send.send(());
+        });
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        // hidden if no daemon is running
+        let (text, attrs, highlight) = match self.state {
+            None => (String::new(), &self.attrs, &self.highlight),
+            Some(state) => {
+                let (format, attrs, highlight) = if state.inhibited {
+                    (&self.formats.off, &self.attrs_off, &self.highlight_off)
+                } else {
+                    (&self.formats.on, &self.attrs, &self.highlight)
+                };
+                (
+                    format.replace(
+                        "%temp%",
+                        state
+                            .temp
+                            .map_or_else(
+                                || String::from("?"),
+                                |t| t.to_string(),
+                            )
+                            .as_str(),
+                    ),
+                    attrs,
+                    highlight,
+                )
+            }
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            attrs,
+            &self.common,
+            highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for NightLight {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 10
+    /// - `backend`: how to find the daemon. `dbus` only uses the
+    ///   `dk.jonls.redshift.Redshift` D-Bus interface, `process` only looks
+    ///   for a running `gammastep` or `redshift` process, and `auto` tries
+    ///   D-Bus first.
+    ///   - type: String
+    ///   - default: `auto`
+    /// - `format_on`: the format string when the daemon is active
+    ///   - type: String
+    ///   - default: `%temp%K`
+    ///   - formatting options: `%temp%` (the current color temperature in
+    ///     Kelvin, or `?` if it's unknown)
+    /// - `format_off`: the format string when the daemon is toggled off
+    ///   - type: String
+    ///   - default: `off`
+    ///   - formatting options: the same as `format_on`
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `attrs_off`: A string specifying the attrs for the panel while the
+    ///   daemon is toggled off. Anything not set is taken from `attrs`. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `highlight_off`: A string specifying the highlight for the panel
+    ///   while the daemon is toggled off. See [`Highlight::parse`] for
+    ///   details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel toggles
+    ///   the daemon, as does the `toggle` event.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = NightLightBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        match remove_string_from_config("backend", table).as_deref() {
+            None | Some("auto") => {}
+            Some("dbus") => {
+                builder.backend(Backend::DBus);
+            }
+            Some("process") => {
+                builder.backend(Backend::Process);
+            }
+            Some(backend) => {
+                return Err(anyhow!("{name}: unknown backend {backend}"))
+            }
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_on", "_off"],
+            &["%temp%K", "off"],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let attrs_off = PanelCommon::parse_attr(table, "_off");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let highlight_off = PanelCommon::parse_highlight(table, "_off");
+
+        builder.common(common);
+        builder.formats(NightLightFormats::new(formats));
+        builder.attrs(attrs);
+        builder.attrs_off(attrs_off);
+        builder.highlight(highlight);
+        builder.highlight_off(highlight_off);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+        self.attrs_off.apply_to(&self.attrs);
+
+        let proxy = if self.backend == Backend::Process {
+            None
+        } else {
+            let proxy = async {
+                let conn = Connection::session().await?;
+                RedshiftProxy::builder(&conn)
+                    .cache_properties(CacheProperties::No)
+                    .build()
+                    .await
+            }
+            .await;
+            match proxy {
+                Ok(proxy) => Some(proxy),
+                Err(e) if self.backend == Backend::DBus => return Err(e.into()),
+                Err(e) => {
+                    log::info!("{}: not using D-Bus: {e}", self.name);
+                    None
+                }
+            }
+        };
+
+        // the state is fetched again right after each D-Bus toggle
+        let (refresh_send, refresh_recv) = unbounded_channel();
+        let fetch_proxy = proxy.clone();
+        let backend = self.backend;
+        let states = IntervalStream::new(interval(self.interval))
+            .map(|_| ())
+            .merge(UnboundedReceiverStream::new(refresh_recv))
+            .then(move |()| {
+                let proxy = fetch_proxy.clone();
+                async move { Update::State(State::fetch(proxy, backend).await) }
+            });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = states.merge(events).map(move |update| {
+            if let Some(result) =
+                self.update(update, proxy.as_ref(), &refresh_send)
+            {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     pin::Pin,
     rc::Rc,
     sync::{mpsc::Receiver, Arc, Mutex},
@@ -22,7 +22,8 @@ use tokio_stream::{Stream, StreamExt};
 use crate::{
     array_to_struct,
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_graph, draw_panel, Display, PanelCommon, ShowHide},
+    graph::Graph,
     ipc::ChannelEndpoint,
     remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
     PanelConfig, PanelStream, Ramp,
@@ -30,6 +31,75 @@ use crate::{
 
 array_to_struct!(PingFormats, connected, disconnected);
 
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// The round trip time of each ping in a run, or [`None`] for each ping that
+/// was lost
+type Run = Vec<Option<Duration>>;
+
+/// Statistics over the rolling window of recent pings
+#[derive(Debug, Clone, Copy)]
+struct Stats {
+    loss: f64,
+    jitter: f64,
+    min: u128,
+    max: u128,
+}
+
+impl Stats {
+    fn new(samples: &VecDeque<Option<Duration>>) -> Self {
+        let received = samples.iter().flatten().collect::<Vec<_>>();
+        let loss = if samples.is_empty() {
+            0.0
+        } else {
+            (samples.len() - received.len()) as f64 / samples.len() as f64
+                * 100.0
+        };
+        // the mean difference between consecutive round trip times
+        let jitter = if received.len() < 2 {
+            0.0
+        } else {
+            received
+                .windows(2)
+                .map(|w| {
+                    (w[0].as_secs_f64() - w[1].as_secs_f64()).abs() * 1000.0
+                })
+                .sum::<f64>()
+                / (received.len() - 1) as f64
+        };
+        Self {
+            loss,
+            jitter,
+            min: received.iter().min().map_or(0, |d| d.as_millis()),
+            max: received.iter().max().map_or(0, |d| d.as_millis()),
+        }
+    }
+}
+
+/// Draws each sample as a block whose height is relative to the fastest and
+/// slowest pings in the window. Lost pings are drawn as spaces.
+fn sparkline(samples: &VecDeque<Option<Duration>>) -> String {
+    let received = samples.iter().flatten();
+    let (Some(min), Some(max)) = (received.clone().min(), received.max())
+    else {
+        return " ".repeat(samples.len());
+    };
+    let range = (*max - *min).as_secs_f64();
+    samples
+        .iter()
+        .map(|sample| {
+            sample.map_or(' ', |rtt| {
+                if range == 0.0 {
+                    SPARKS[0]
+                } else {
+                    let level = (rtt - *min).as_secs_f64() / range;
+                    SPARKS[(level * (SPARKS.len() - 1) as f64).round() as usize]
+                }
+            })
+        })
+        .collect()
+}
+
 /// Displays the ping to a given address
 ///
 /// Requires the `cap_net_raw` capability. See
@@ -47,6 +117,10 @@ pub struct Ping {
     waker: Arc<AtomicWaker>,
     #[builder(default = "5")]
     pings: usize,
+    #[builder(default = "20")]
+    history: usize,
+    #[builder(default, setter(skip))]
+    samples: VecDeque<Option<Duration>>,
     #[builder(default, setter(strip_option))]
     max_ping: Option<u32>,
     formats: PingFormats<String>,
@@ -54,43 +128,94 @@ pub struct Ping {
     #[builder(default, setter(strip_option))]
     highlight: Option<Highlight>,
     ramp: Ramp,
+    #[builder(default)]
+    graph: Option<Graph>,
     common: PanelCommon,
 }
 
 impl Ping {
     fn draw(
-        &self,
+        &mut self,
         cr: &Rc<cairo::Context>,
-        ping: Result<u128>,
+        run: Result<Run>,
         height: i32,
         paused: Arc<Mutex<bool>>,
     ) -> Result<PanelDrawInfo> {
-        let text = ping.map_or_else(
-            |_| self.formats.disconnected.to_string(),
-            |ping| {
-                self.formats
-                    .connected
-                    .replace("%ping%", ping.to_string().as_str())
-                    .replace(
-                        "%ramp%",
-                        self.ramp
-                            .choose::<u32>(
-                                ping as u32,
-                                0,
-                                self.max_ping.unwrap_or(2000).clamp(0, 2000),
-                            )
-                            .as_str(),
+        let ping = match run {
+            Ok(run) => {
+                self.samples.extend(run.iter().copied());
+                let excess = self.samples.len().saturating_sub(self.history);
+                self.samples.drain(..excess);
+                if let Some(ref mut graph) = self.graph {
+                    for rtt in &run {
+                        graph.push(rtt.map(|rtt| rtt.as_secs_f64() * 1000.0));
+                    }
+                }
+
+                let received = run.iter().flatten().collect::<Vec<_>>();
+                if received.is_empty() {
+                    None
+                } else {
+                    Some(
+                        (received.iter().copied().sum::<Duration>()
+                            / received.len() as u32)
+                            .as_millis(),
                     )
-            },
-        );
+                }
+            }
+            Err(e) => {
+                log::warn!("{}: {e}", self.name);
+                None
+            }
+        };
+
+        let stats = Stats::new(&self.samples);
+        let text = ping
+            .map_or_else(
+                || self.formats.disconnected.clone(),
+                |ping| {
+                    self.formats
+                        .connected
+                        .replace("%ping%", ping.to_string().as_str())
+                        .replace(
+                            "%ramp%",
+                            self.ramp
+                                .choose::<u32>(
+                                    ping as u32,
+                                    0,
+                                    self.max_ping
+                                        .unwrap_or(2000)
+                                        .clamp(0, 2000),
+                                )
+                                .as_str(),
+                        )
+                },
+            )
+            .replace("%sparkline%", sparkline(&self.samples).as_str())
+            .replace("%loss%", format!("{:.0}", stats.loss).as_str())
+            .replace("%jitter%", format!("{:.0}", stats.jitter).as_str())
+            .replace("%min%", stats.min.to_string().as_str())
+            .replace("%max%", stats.max.to_string().as_str());
+
+        if let Some(ref graph) = self.graph {
+            return draw_graph(
+                cr,
+                graph,
+                text.as_str(),
+                &self.attrs,
+                &self.common,
+                self.highlight.clone(),
+                height,
+                ShowHide::Default(paused, self.waker.clone()),
+            );
+        }
 
-        draw_common(
+        draw_panel(
             cr,
             text.as_str(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
@@ -110,12 +235,22 @@ impl PanelConfig for Ping {
     /// - `pings`: how many times to ping per run (the results will be averaged)
     ///   - type: u64
     ///   - default 5
+    /// - `history`: how many of the most recent pings to keep for `%loss%`,
+    ///   `%jitter%`, `%min%`, `%max%`, and `%sparkline%`
+    ///   - type: u64
+    ///   - default: 20
     /// - `format_connected`: the format string
     ///   - type: String
-    ///   - formatting options: `%ping%`, `%ramp%`
+    ///   - formatting options: `%ping%` (the average of the latest run),
+    ///     `%ramp%`, `%loss%` (the percentage of pings lost), `%jitter%` (the
+    ///     mean difference between consecutive pings), `%min%`, `%max%`,
+    ///     `%sparkline%` (a graph of recent pings, e.g. `▂▁▃█▂`)
     ///   - default: `%ping%ms`
-    /// - `format_disconnected`: the format string when all pings fail
+    /// - `format_disconnected`: the format string when all pings in a run
+    ///   fail
     ///   - type: String
+    ///   - formatting options: the same as `format_connected`, except for
+    ///     `%ping%` and `%ramp%`
     ///   - default: `disconnected`
     /// - `ramp`: the ramp to display based on the ping time. See
     ///   [`Ramp::parse`][crate::ramp::Ramp::parse] for parsing details.
@@ -129,6 +264,9 @@ impl PanelConfig for Ping {
     ///   [`Highlight::parse`] for details.
     /// - `ramp`: A string specifying the ramp to show ping. See [`Ramp::parse`]
     ///   for details.
+    /// - `display`: `graph` draws a sparkline of the round trip time of each
+    ///   ping in milliseconds instead of the format string, with gaps for
+    ///   lost pings. See [`Graph::parse`] for the other options.
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
@@ -152,6 +290,9 @@ impl PanelConfig for Ping {
         if let Some(pings) = remove_uint_from_config("pings", table) {
             builder.pings(pings as usize);
         }
+        if let Some(history) = remove_uint_from_config("history", table) {
+            builder.history((history as usize).max(1));
+        }
         if let Some(max_ping) = remove_uint_from_config("max_ping", table) {
             builder.max_ping(max_ping as u32);
         }
@@ -171,6 +312,7 @@ impl PanelConfig for Ping {
         builder.attrs(attrs);
         builder.highlight(highlight);
         builder.ramp(ramp);
+        builder.graph(Display::parse(table).into_graph());
 
         Ok(builder.build()?)
     }
@@ -217,14 +359,14 @@ struct PingStream {
     interval: Option<Interval>,
     paused: Arc<Mutex<bool>>,
     waker: Arc<AtomicWaker>,
-    handle: Option<JoinHandle<Result<u128>>>,
+    handle: Option<JoinHandle<Result<Run>>>,
 }
 
 fn ping(
     pings: usize,
     pinger: &Arc<Mutex<Pinger>>,
     recv: &Arc<Mutex<Receiver<PingResult>>>,
-) -> Result<u128> {
+) -> Result<Run> {
     // hold both ends for the duration of the test to avoid weird behavior
     // around short intervals
     let pinger = pinger.lock().unwrap();
@@ -233,9 +375,9 @@ fn ping(
     pinger.run_pinger();
     for _ in 0..pings {
         match recv.recv() {
-            Ok(PingResult::Idle { addr: _ }) => {}
+            Ok(PingResult::Idle { addr: _ }) => results.push(None),
             Ok(PingResult::Receive { addr: _, rtt }) => {
-                results.push(rtt);
+                results.push(Some(rtt));
             }
             Err(e) => {
                 pinger.stop_pinger();
@@ -246,16 +388,11 @@ fn ping(
     pinger.stop_pinger();
     drop(pinger);
 
-    if results.is_empty() {
-        Err(anyhow!("No connection"))
-    } else {
-        Ok((results.iter().sum::<Duration>() / results.len() as u32)
-            .as_millis())
-    }
+    Ok(results)
 }
 
 impl Stream for PingStream {
-    type Item = Result<u128>;
+    type Item = Result<Run>;
 
     fn poll_next(
         mut self: Pin<&mut Self>,
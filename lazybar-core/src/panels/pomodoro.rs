@@ -0,0 +1,376 @@
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{sync::mpsc::unbounded_channel, time::interval};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_bool_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream, Ramp,
+};
+
+array_to_struct!(PomodoroFormats, running, paused);
+array_to_struct!(PomodoroRamps, work, rest);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Work,
+    ShortBreak,
+    LongBreak,
+}
+
+impl Phase {
+    const fn label(self) -> &'static str {
+        match self {
+            Self::Work => "work",
+            Self::ShortBreak => "break",
+            Self::LongBreak => "long break",
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Event(Event),
+}
+
+/// A pomodoro timer that alternates between work and breaks.
+///
+/// The timer starts paused. Every `long_break_after` work periods, the break
+/// is a long break instead of a short one.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Pomodoro {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(25 * 60)")]
+    work: Duration,
+    #[builder(default = "Duration::from_secs(5 * 60)")]
+    short_break: Duration,
+    #[builder(default = "Duration::from_secs(15 * 60)")]
+    long_break: Duration,
+    #[builder(default = "4")]
+    long_break_after: usize,
+    #[builder(default = "true")]
+    auto_start: bool,
+    #[builder(default = "Phase::Work", setter(skip))]
+    phase: Phase,
+    #[builder(default, setter(skip))]
+    completed: usize,
+    /// The time left in the current phase when the timer was last paused.
+    #[builder(default, setter(skip))]
+    remaining: Option<Duration>,
+    /// When the current phase ends, if the timer is running.
+    #[builder(default, setter(skip))]
+    ends_at: Option<Instant>,
+    formats: PomodoroFormats<String>,
+    ramps: PomodoroRamps<Ramp>,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Pomodoro {
+    const fn length(&self, phase: Phase) -> Duration {
+        match phase {
+            Phase::Work => self.work,
+            Phase::ShortBreak => self.short_break,
+            Phase::LongBreak => self.long_break,
+        }
+    }
+
+    fn remaining(&self) -> Duration {
+        self.ends_at.map_or_else(
+            || self.remaining.unwrap_or_else(|| self.length(self.phase)),
+            |end| end.saturating_duration_since(Instant::now()),
+        )
+    }
+
+    fn start(&mut self) {
+        if self.ends_at.is_none() {
+            self.ends_at = Some(Instant::now() + self.remaining());
+            self.remaining = None;
+        }
+    }
+
+    fn pause(&mut self) {
+        if self.ends_at.is_some() {
+            self.remaining = Some(self.remaining());
+            self.ends_at = None;
+        }
+    }
+
+    /// Moves on to the next phase, starting it if the timer was running and
+    /// `auto_start` is set.
+    fn skip(&mut self) {
+        let running = self.ends_at.is_some();
+        self.phase = match self.phase {
+            Phase::Work => {
+                self.completed += 1;
+                if self.long_break_after > 0
+                    && self.completed % self.long_break_after == 0
+                {
+                    Phase::LongBreak
+                } else {
+                    Phase::ShortBreak
+                }
+            }
+            Phase::ShortBreak | Phase::LongBreak => Phase::Work,
+        };
+        log::info!("{}: starting {}", self.name, self.phase.label());
+        self.remaining = None;
+        self.ends_at = None;
+        if running && self.auto_start {
+            self.start();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.phase = Phase::Work;
+        self.completed = 0;
+        self.remaining = None;
+        self.ends_at = None;
+    }
+
+    fn update(&mut self, update: Update) -> Option<Result<()>> {
+        match update {
+            Update::Tick => {
+                if self.ends_at.is_some_and(|end| end <= Instant::now()) {
+                    self.skip();
+                }
+                None
+            }
+            Update::Event(event) => Some(self.process_event(event)),
+        }
+    }
+
+    fn process_event(&mut self, event: Event) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                self.toggle();
+                Ok(())
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) => match action.as_str() {
+                "start" => {
+                    self.start();
+                    Ok(())
+                }
+                "pause" => {
+                    self.pause();
+                    Ok(())
+                }
+                "toggle" => {
+                    self.toggle();
+                    Ok(())
+                }
+                "skip" => {
+                    self.skip();
+                    Ok(())
+                }
+                "reset" => {
+                    self.reset();
+                    Ok(())
+                }
+                _ => Err(anyhow!("Unknown event {action}")),
+            },
+        }
+    }
+
+    fn toggle(&mut self) {
+        if self.ends_at.is_some() {
+            self.pause();
+        } else {
+            self.start();
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let remaining = self.remaining();
+        let length = self.length(self.phase);
+        let ramp = match self.phase {
+            Phase::Work => &self.ramps.work,
+            Phase::ShortBreak | Phase::LongBreak => &self.ramps.rest,
+        };
+        let format = if self.ends_at.is_some() {
+            &self.formats.running
+        } else {
+            &self.formats.paused
+        };
+
+        let secs = remaining.as_secs();
+        let text = format
+            .replace(
+                "%ramp%",
+                ramp.choose(
+                    (length - remaining).as_secs_f64(),
+                    0.0,
+                    length.as_secs_f64().max(1.0),
+                )
+                .as_str(),
+            )
+            .replace("%phase%", self.phase.label())
+            .replace(
+                "%time%",
+                format!("{:02}:{:02}", secs / 60, secs % 60).as_str(),
+            )
+            .replace("%count%", self.completed.to_string().as_str());
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Pomodoro {
+    /// Configuration options:
+    ///
+    /// - `work`: the length of a work period, in minutes
+    ///   - type: u64
+    ///   - default: 25
+    /// - `short_break`: the length of a short break, in minutes
+    ///   - type: u64
+    ///   - default: 5
+    /// - `long_break`: the length of a long break, in minutes
+    ///   - type: u64
+    ///   - default: 15
+    /// - `long_break_after`: the number of work periods before each long
+    ///   break. Set to 0 to disable long breaks.
+    ///   - type: u64
+    ///   - default: 4
+    /// - `auto_start`: whether the next phase starts automatically when one
+    ///   ends. If false, the timer pauses at the start of each phase.
+    ///   - type: bool
+    ///   - default: true
+    /// - `format_running`: the format string while the timer is running
+    ///   - type: String
+    ///   - default: `%ramp% %time%`
+    ///   - formatting options: `%ramp%`, `%phase%` (`work`, `break`, or `long
+    ///     break`), `%time%` (the time left in the phase as `mm:ss`),
+    ///     `%count%` (the number of completed work periods)
+    /// - `format_paused`: the format string while the timer is paused
+    ///   - type: String
+    ///   - default: `%ramp% %time% (paused)`
+    ///   - formatting options: same as `format_running`
+    /// - `ramp_work`: A string specifying the ramp to show during work
+    ///   periods, chosen by how much of the period has elapsed. See
+    ///   [`Ramp::parse`] for details.
+    /// - `ramp_rest`: A string specifying the ramp to show during breaks. See
+    ///   [`Ramp::parse`] for details.
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel starts or
+    ///   pauses the timer. The supported events are `start`, `pause`,
+    ///   `toggle`, `skip` (end the current phase early), and `reset` (go back
+    ///   to the first work period and pause).
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PomodoroBuilder::default();
+
+        builder.name(name);
+        if let Some(work) = remove_uint_from_config("work", table) {
+            builder.work(Duration::from_secs(work.max(1) * 60));
+        }
+        if let Some(short_break) = remove_uint_from_config("short_break", table)
+        {
+            builder.short_break(Duration::from_secs(short_break.max(1) * 60));
+        }
+        if let Some(long_break) = remove_uint_from_config("long_break", table) {
+            builder.long_break(Duration::from_secs(long_break.max(1) * 60));
+        }
+        if let Some(long_break_after) =
+            remove_uint_from_config("long_break_after", table)
+        {
+            builder.long_break_after(long_break_after as usize);
+        }
+        if let Some(auto_start) = remove_bool_from_config("auto_start", table) {
+            builder.auto_start(auto_start);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_running", "_paused"],
+            &["%ramp% %time%", "%ramp% %time% (paused)"],
+        );
+        let ramps = PanelCommon::parse_ramps(table, &["_work", "_rest"]);
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.formats(PomodoroFormats::new(formats));
+        builder.ramps(PomodoroRamps::new(ramps));
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+        let ticks = IntervalStream::new(interval(Duration::from_secs(1)))
+            .map(|_| Update::Tick);
+
+        let stream = ticks.merge(events).map(move |update| {
+            if let Some(result) = self.update(update) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
@@ -0,0 +1,308 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::sync::mpsc::unbounded_channel;
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use zbus::{proxy, zvariant::OwnedValue, Connection};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+/// The order that profiles are cycled through
+const ORDER: [&str; 3] = ["performance", "balanced", "power-saver"];
+
+#[proxy(
+    interface = "net.hadess.PowerProfiles",
+    default_service = "net.hadess.PowerProfiles",
+    default_path = "/net/hadess/PowerProfiles"
+)]
+trait PowerProfiles {
+    #[zbus(property)]
+    fn active_profile(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn set_active_profile(&self, value: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn profiles(&self) -> zbus::Result<Vec<HashMap<String, OwnedValue>>>;
+}
+
+array_to_struct!(PowerProfileFormats, performance, balanced, power_saver);
+
+#[derive(Debug)]
+enum Update {
+    Profile(Result<String>),
+    Event(Event),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Direction {
+    Next,
+    Prev,
+}
+
+/// Displays the active profile from
+/// [power-profiles-daemon](https://gitlab.freedesktop.org/upower/power-profiles-daemon).
+///
+/// Left clicking cycles through performance, balanced, and power-saver,
+/// skipping any that aren't available, and right clicking cycles the other
+/// way. Changes made elsewhere are shown as soon as the daemon announces them.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct PowerProfile {
+    name: &'static str,
+    #[builder(default, setter(skip))]
+    profile: Option<String>,
+    formats: PowerProfileFormats<String>,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl PowerProfile {
+    fn update(
+        &mut self,
+        update: Update,
+        proxy: &PowerProfilesProxy<'static>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::Profile(Ok(profile)) => {
+                self.profile = Some(profile);
+                None
+            }
+            Update::Profile(Err(e)) => {
+                log::warn!("{}: {e}", self.name);
+                self.profile = None;
+                None
+            }
+            Update::Event(event) => Some(self.process_event(event, proxy)),
+        }
+    }
+
+    fn process_event(
+        &self,
+        event: Event,
+        proxy: &PowerProfilesProxy<'static>,
+    ) -> Result<()> {
+        let direction = match event {
+            Event::Mouse(event) => match event.button {
+                MouseButton::Left => Direction::Next,
+                MouseButton::Right => Direction::Prev,
+                _ => return Ok(()),
+            },
+            Event::MouseEnter | Event::MouseLeave => return Ok(()),
+            Event::Action(action) => match action.as_str() {
+                "next" => Direction::Next,
+                "prev" => Direction::Prev,
+                profile if ORDER.contains(&profile) => {
+                    self.set(proxy, profile.to_string());
+                    return Ok(());
+                }
+                _ => return Err(anyhow!("Unknown event {action}")),
+            },
+        };
+        let current = self
+            .profile
+            .clone()
+            .ok_or_else(|| anyhow!("power-profiles-daemon isn't running"))?;
+
+        let proxy = proxy.clone();
+        let name = self.name;
+        tokio::spawn(async move {
+            let result = async {
+                let available = proxy
+                    .profiles()
+                    .await?
+                    .iter()
+                    .filter_map(|profile| {
+                        profile
+                            .get("Profile")?
+                            .downcast_ref::<&str>()
+                            .ok()
+                            .map(String::from)
+                    })
+                    .collect::<Vec<_>>();
+                let profile = cycle(&available, current.as_str(), direction)
+                    .ok_or_else(|| anyhow!("No profiles available"))?;
+                proxy.set_active_profile(profile.as_str()).await?;
+                Ok::<_, anyhow::Error>(())
+            };
+            if let Err(e) = result.await {
+                log::warn!("{name}: failed to change profile: {e}");
+            }
+        });
+        Ok(())
+    }
+
+    fn set(&self, proxy: &PowerProfilesProxy<'static>, profile: String) {
+        let proxy = proxy.clone();
+        let name = self.name;
+        tokio::spawn(async move {
+            if let Err(e) = proxy.set_active_profile(profile.as_str()).await {
+                log::warn!("{name}: failed to set profile to {profile}: {e}");
+            }
+        });
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        // hidden if power-profiles-daemon isn't running
+        let text = self.profile.as_ref().map_or_else(String::new, |profile| {
+            let format = match profile.as_str() {
+                "performance" => &self.formats.performance,
+                "balanced" => &self.formats.balanced,
+                "power-saver" => &self.formats.power_saver,
+                _ => return profile.clone(),
+            };
+            format.replace("%profile%", profile.as_str())
+        });
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+/// Returns the profile after (or before) `current` in [`ORDER`], skipping
+/// any that aren't in `available`.
+fn cycle(
+    available: &[String],
+    current: &str,
+    direction: Direction,
+) -> Option<String> {
+    let profiles = ORDER
+        .iter()
+        .filter(|profile| available.iter().any(|p| p == *profile))
+        .collect::<Vec<_>>();
+    if profiles.is_empty() {
+        return None;
+    }
+    let len = profiles.len();
+    let idx = profiles
+        .iter()
+        .position(|profile| **profile == current)
+        .map_or(0, |idx| match direction {
+            Direction::Next => (idx + 1) % len,
+            Direction::Prev => (idx + len - 1) % len,
+        });
+    Some(profiles[idx].to_string())
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for PowerProfile {
+    /// Configuration options:
+    ///
+    /// - `format_performance`: the format string for the performance profile
+    ///   - type: String
+    ///   - default: `performance`
+    ///   - formatting options: `%profile%`
+    /// - `format_balanced`: the format string for the balanced profile
+    ///   - type: String
+    ///   - default: `balanced`
+    ///   - formatting options: `%profile%`
+    /// - `format_power_saver`: the format string for the power-saver profile
+    ///   - type: String
+    ///   - default: `power-saver`
+    ///   - formatting options: `%profile%`
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. The supported events are `next`,
+    ///   `prev`, and the name of a profile (`performance`, `balanced`, or
+    ///   `power-saver`) to switch to it.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PowerProfileBuilder::default();
+
+        builder.name(name);
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_performance", "_balanced", "_power_saver"],
+            &["performance", "balanced", "power-saver"],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.formats(PowerProfileFormats::new(formats));
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let conn = Connection::system().await?;
+        let proxy = PowerProfilesProxy::new(&conn).await?;
+
+        // change notifications need the property cache, which is on by default
+        let profiles = proxy.receive_active_profile_changed().await.then(
+            |change| async move {
+                Update::Profile(change.get().await.map_err(anyhow::Error::from))
+            },
+        );
+        let initial = Update::Profile(
+            proxy.active_profile().await.map_err(anyhow::Error::from),
+        );
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = tokio_stream::once(initial)
+            .chain(profiles)
+            .merge(events)
+            .map(move |update| {
+                if let Some(result) = self.update(update, &proxy) {
+                    let _ = response_send.send(match result {
+                        Ok(()) => EventResponse::Ok,
+                        Err(e) => EventResponse::Err(e.to_string()),
+                    });
+                }
+                self.draw(&cr, height)
+            });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
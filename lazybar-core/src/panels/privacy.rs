@@ -0,0 +1,313 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{read_dir, read_link, read_to_string},
+    process::Command,
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+/// What is capturing the camera, microphone, and screen.
+#[derive(Debug, Clone, Default)]
+struct Usage {
+    camera: bool,
+    mic: bool,
+    screen: bool,
+    /// The names of the applications doing the capturing
+    apps: BTreeSet<String>,
+}
+
+impl Usage {
+    /// Checks everything that might be capturing. If PipeWire can't be
+    /// checked, `pipewire` is cleared so that it isn't tried again.
+    fn detect(name: &str, pipewire: &AtomicBool) -> Self {
+        let mut usage = Self::default();
+        usage.scan_video_devices();
+        if pipewire.load(Ordering::Relaxed) {
+            if let Err(e) = usage.scan_pipewire() {
+                log::warn!("{name}: {e}; disabling PipeWire checks");
+                pipewire.store(false, Ordering::Relaxed);
+            }
+        }
+        usage
+    }
+
+    /// Looks for processes with a `/dev/video*` device open. Only processes
+    /// that this user can inspect are found.
+    fn scan_video_devices(&mut self) {
+        let Ok(procs) = read_dir("/proc") else {
+            return;
+        };
+        for proc in procs.filter_map(Result::ok) {
+            let Ok(fds) = read_dir(proc.path().join("fd")) else {
+                continue;
+            };
+            let open = fds.filter_map(Result::ok).any(|fd| {
+                read_link(fd.path()).is_ok_and(|target| {
+                    target.to_string_lossy().starts_with("/dev/video")
+                })
+            });
+            if open {
+                self.camera = true;
+                if let Ok(comm) = read_to_string(proc.path().join("comm")) {
+                    self.apps.insert(comm.trim().to_string());
+                }
+            }
+        }
+    }
+
+    /// Looks for running capture streams in the PipeWire graph. Audio input
+    /// streams are microphones, and running video sources are cameras if
+    /// they belong to a device or screen casts otherwise.
+    fn scan_pipewire(&mut self) -> Result<()> {
+        let output = cleanup::command_output(&mut Command::new("pw-dump"))?;
+        if !output.status.success() {
+            return Err(anyhow!(
+                "pw-dump failed: {}",
+                String::from_utf8_lossy(output.stderr.as_slice()).trim()
+            ));
+        }
+        let objects: Vec<serde_json::Value> =
+            serde_json::from_slice(output.stdout.as_slice())?;
+
+        for object in objects {
+            if object["type"] != "PipeWire:Interface:Node"
+                || object["info"]["state"] != "running"
+            {
+                continue;
+            }
+            let props = &object["info"]["props"];
+            let found = match props["media.class"].as_str() {
+                Some("Stream/Input/Audio") => &mut self.mic,
+                Some("Video/Source") if props["device.api"].is_string() => {
+                    &mut self.camera
+                }
+                Some("Video/Source" | "Stream/Output/Video") => {
+                    &mut self.screen
+                }
+                _ => continue,
+            };
+            *found = true;
+            if let Some(app) = props["application.name"]
+                .as_str()
+                .or_else(|| props["node.name"].as_str())
+            {
+                self.apps.insert(app.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shows which of the camera, microphone, and screen are being captured,
+/// and hides entirely when none are.
+///
+/// The camera counts as in use when any process has a `/dev/video*` device
+/// open. Microphones, PipeWire cameras, and screen casts are found in the
+/// output of `pw-dump`.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Privacy {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default = "Arc::new(AtomicBool::new(true))")]
+    pipewire: Arc<AtomicBool>,
+    #[builder(default, setter(skip))]
+    usage: Usage,
+    format: &'static str,
+    icon_camera: String,
+    icon_mic: String,
+    icon_screen: String,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Privacy {
+    fn update(&mut self, usage: Result<Usage>) {
+        match usage {
+            Ok(usage) => self.usage = usage,
+            // keep showing the last usage
+            Err(e) => log::warn!("{}: {e}", self.name),
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let icons = [
+            (self.usage.camera, &self.icon_camera),
+            (self.usage.mic, &self.icon_mic),
+            (self.usage.screen, &self.icon_screen),
+        ]
+        .into_iter()
+        .filter(|(active, _)| *active)
+        .map(|(_, icon)| icon.as_str())
+        .collect::<Vec<_>>();
+
+        let text = if icons.is_empty() {
+            String::new()
+        } else {
+            let apps = self
+                .usage
+                .apps
+                .iter()
+                .map(|app| glib::markup_escape_text(app.as_str()).to_string())
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.format
+                .replace(
+                    "%icons%",
+                    icons.join(self.separator.as_str()).as_str(),
+                )
+                .replace("%apps%", apps.as_str())
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Privacy {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 2
+    /// - `pipewire`: whether to check PipeWire for microphones and screen
+    ///   casts. Without it, only cameras opened directly are found.
+    ///   - type: bool
+    ///   - default: true
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%icons%`
+    ///   - formatting options: `%icons%` (the icon for each device in use,
+    ///     joined with `separator`), `%apps%` (the applications capturing)
+    /// - `icon_camera`: the icon shown while the camera is in use
+    ///   - type: String
+    ///   - default: `CAM`
+    /// - `icon_mic`: the icon shown while a microphone is in use
+    ///   - type: String
+    ///   - default: `MIC`
+    /// - `icon_screen`: the icon shown while the screen is being shared
+    ///   - type: String
+    ///   - default: `SCR`
+    /// - `separator`: the text between each icon
+    ///   - type: String
+    ///   - default: a space
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PrivacyBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(pipewire) = remove_bool_from_config("pipewire", table) {
+            builder.pipewire(Arc::new(AtomicBool::new(pipewire)));
+        }
+        builder.icon_camera(
+            remove_string_from_config("icon_camera", table)
+                .unwrap_or_else(|| String::from("CAM")),
+        );
+        builder.icon_mic(
+            remove_string_from_config("icon_mic", table)
+                .unwrap_or_else(|| String::from("MIC")),
+        );
+        builder.icon_screen(
+            remove_string_from_config("icon_screen", table)
+                .unwrap_or_else(|| String::from("SCR")),
+        );
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%icons%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let name = self.name;
+        let pipewire = self.pipewire.clone();
+        let stream = IntervalStream::new(interval(self.interval))
+            .then(move |_| {
+                let pipewire = pipewire.clone();
+                async move {
+                    cleanup::spawn_blocking(move || {
+                        Usage::detect(name, &pipewire)
+                    })
+                    .await
+                    .map_err(anyhow::Error::from)
+                }
+            })
+            .map(move |usage| {
+                self.update(usage);
+                self.draw(&cr, height)
+            });
+
+        Ok((Box::pin(stream), None))
+    }
+}
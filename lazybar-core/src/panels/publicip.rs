@@ -0,0 +1,375 @@
+use std::{
+    collections::HashMap, os::fd::AsRawFd, rc::Rc, thread, time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use nix::sys::socket::{
+    bind, recv, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag,
+    SockProtocol, SockType,
+};
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Client,
+};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+const RTM_NEWROUTE: u16 = 24;
+const RTM_DELROUTE: u16 = 25;
+
+#[derive(Debug, Clone)]
+struct Address {
+    ip: String,
+    country: Option<String>,
+}
+
+#[derive(Debug)]
+enum Update {
+    Address(Result<Address>),
+    Event(Event),
+}
+
+/// Displays the public IP address of this machine, and optionally its
+/// country, as reported by a web service.
+///
+/// The address is fetched again whenever the default route changes (e.g. when
+/// connecting to a VPN or switching networks), in addition to every
+/// `interval`. If a request fails, the last address is kept.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct PublicIp {
+    name: &'static str,
+    url: String,
+    ip_path: String,
+    country_path: String,
+    #[builder(default = "Duration::from_secs(600)")]
+    interval: Duration,
+    #[builder(default, setter(skip))]
+    address: Option<Address>,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl PublicIp {
+    fn update(
+        &mut self,
+        update: Update,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::Address(Ok(address)) => {
+                self.address = Some(address);
+                None
+            }
+            // keep showing the old address if a request fails
+            Update::Address(Err(e)) => {
+                log::warn!("{}: failed to fetch address: {e}", self.name);
+                None
+            }
+            Update::Event(event) => {
+                Some(Self::process_event(event, refresh_send))
+            }
+        }
+    }
+
+    fn process_event(
+        event: Event,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                Ok(refresh_send.send(())?)
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "refresh" => {
+                Ok(refresh_send.send(())?)
+            }
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let text = self.address.as_ref().map_or_else(String::new, |address| {
+            self.format.replace("%ip%", address.ip.as_str()).replace(
+                "%country%",
+                address.country.as_deref().unwrap_or_default(),
+            )
+        });
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for PublicIp {
+    /// Configuration options:
+    ///
+    /// - `url`: the URL to fetch the address from. The response can either be
+    ///   the address in plain text, or a JSON object (see `ip_path` and
+    ///   `country_path`). Use a JSON endpoint like `https://ipinfo.io/json` to
+    ///   get the country.
+    ///   - type: String
+    ///   - default: `https://ifconfig.me/ip`
+    /// - `ip_path`: the dot-separated path to the address in a JSON response
+    ///   - type: String
+    ///   - default: `ip`
+    /// - `country_path`: the dot-separated path to the country in a JSON
+    ///   response
+    ///   - type: String
+    ///   - default: `country`
+    /// - `interval`: how often to fetch the address, in seconds
+    ///   - type: u64
+    ///   - default: 600
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%ip%`
+    ///   - formatting options: `%ip%`, `%country%` (empty if the response
+    ///     doesn't include it)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel fetches the
+    ///   address again, as does the `refresh` event.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = PublicIpBuilder::default();
+
+        builder.name(name);
+        builder.url(
+            remove_string_from_config("url", table)
+                .unwrap_or_else(|| String::from("https://ifconfig.me/ip")),
+        );
+        builder.ip_path(
+            remove_string_from_config("ip_path", table)
+                .unwrap_or_else(|| String::from("ip")),
+        );
+        builder.country_path(
+            remove_string_from_config("country_path", table)
+                .unwrap_or_else(|| String::from("country")),
+        );
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%ip%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("lazybar"));
+        let client = Client::builder()
+            .default_headers(headers)
+            .timeout(Duration::from_secs(30))
+            .build()?;
+
+        let fetcher = Fetcher {
+            client,
+            url: self.url.clone(),
+            ip_path: self.ip_path.clone(),
+            country_path: self.country_path.clone(),
+        };
+
+        // route changes and clicks both trigger a refresh
+        let (refresh_send, refresh_recv) = unbounded_channel();
+        if let Err(e) = watch_routes(refresh_send.clone()) {
+            log::warn!("{}: failed to watch for route changes: {e}", self.name);
+        }
+
+        let addresses = IntervalStream::new(interval(self.interval))
+            .map(|_| ())
+            .merge(UnboundedReceiverStream::new(refresh_recv))
+            .then(move |()| {
+                let fetcher = fetcher.clone();
+                async move { Update::Address(fetcher.fetch().await) }
+            });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = addresses.merge(events).map(move |update| {
+            if let Some(result) = self.update(update, &refresh_send) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Fetcher {
+    client: Client,
+    url: String,
+    ip_path: String,
+    country_path: String,
+}
+
+impl Fetcher {
+    async fn fetch(self) -> Result<Address> {
+        let body = self
+            .client
+            .get(self.url.as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+
+        match serde_json::from_str::<serde_json::Value>(body.as_str()) {
+            Ok(json) if json.is_object() => Ok(Address {
+                ip: lookup(&json, self.ip_path.as_str()).ok_or_else(|| {
+                    anyhow!("No address found in response from {}", self.url)
+                })?,
+                country: lookup(&json, self.country_path.as_str()),
+            }),
+            _ => {
+                let ip = body.trim();
+                if ip.is_empty() {
+                    Err(anyhow!("Empty response from {}", self.url))
+                } else {
+                    Ok(Address {
+                        ip: ip.to_string(),
+                        country: None,
+                    })
+                }
+            }
+        }
+    }
+}
+
+/// Follows a dot-separated path of object keys through a JSON value,
+/// returning the string at the end.
+fn lookup(value: &serde_json::Value, path: &str) -> Option<String> {
+    path.split('.')
+        .try_fold(value, |value, key| value.get(key))
+        .and_then(|value| value.as_str())
+        .map(String::from)
+}
+
+/// Listens for changes to the default route on a netlink socket, sending on
+/// `send` each time. The socket is read on its own thread, which exits at the
+/// first change after `send` is closed.
+fn watch_routes(send: UnboundedSender<()>) -> Result<()> {
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkRoute,
+    )?;
+    bind(
+        fd.as_raw_fd(),
+        &NetlinkAddr::new(0, RTMGRP_IPV4_ROUTE | RTMGRP_IPV6_ROUTE),
+    )?;
+
+    thread::spawn(move || {
+        let mut buf = [0; 8192];
+        loop {
+            let len = match recv(fd.as_raw_fd(), &mut buf, MsgFlags::empty()) {
+                Ok(len) => len,
+                Err(e) => {
+                    log::warn!("Stopped watching for route changes: {e}");
+                    break;
+                }
+            };
+            if changes_default_route(&buf[..len]) {
+                log::debug!("default route changed");
+                if send.send(()).is_err() {
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Returns whether any of the netlink messages in `buf` adds or removes a
+/// default route, i.e. one with a destination prefix length of zero.
+fn changes_default_route(mut buf: &[u8]) -> bool {
+    // struct nlmsghdr is 16 bytes, followed by struct rtmsg, whose second
+    // byte is rtm_dst_len
+    while buf.len() >= 18 {
+        let len = u32::from_ne_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        let kind = u16::from_ne_bytes([buf[4], buf[5]]);
+        if (kind == RTM_NEWROUTE || kind == RTM_DELROUTE) && buf[17] == 0 {
+            return true;
+        }
+        // messages are aligned to 4 bytes
+        let len = (len + 3) & !3;
+        if len == 0 || len > buf.len() {
+            break;
+        }
+        buf = &buf[len..];
+    }
+    false
+}
@@ -36,9 +36,9 @@ use tokio_stream::{
 use crate::{
     actions::Actions,
     array_to_struct,
-    bar::{Dependence, Event, EventResponse, MouseButton, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
-    image::Image,
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_gauge, draw_panel, Display, PanelCommon, ShowHide},
+    gauge::Gauge,
     ipc::ChannelEndpoint,
     remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
     PanelConfig, PanelStream, Ramp,
@@ -72,6 +72,8 @@ pub struct Pulseaudio {
     #[builder(default, setter(strip_option))]
     highlight: Option<Highlight>,
     ramps: PulseaudioRamps<Ramp>,
+    #[builder(default)]
+    gauge: Option<Gauge>,
     common: PanelCommon,
 }
 
@@ -119,10 +121,10 @@ impl Pulseaudio {
         format_muted: &str,
         ramp: &Ramp,
         ramp_muted: &Ramp,
+        gauge: Option<&Gauge>,
         attrs: &Attrs,
-        dependence: Dependence,
+        common: &PanelCommon,
         highlight: Option<Highlight>,
-        images: Vec<Image>,
         height: i32,
         paused: Arc<Mutex<bool>>,
         waker: Arc<AtomicWaker>,
@@ -143,14 +145,33 @@ impl Pulseaudio {
         let text = format
             .replace("%ramp%", ramp_text.as_str())
             .replace("%volume%", volume.to_string().as_str());
+        let text = common.progress.replace(
+            text.as_str(),
+            f64::from(volume.0),
+            f64::from(Volume::MUTED.0),
+            f64::from(Volume::NORMAL.0),
+        );
 
-        draw_common(
+        if let Some(gauge) = gauge {
+            return draw_gauge(
+                cr,
+                gauge,
+                f64::from(volume.0) / f64::from(Volume::NORMAL.0),
+                text.as_str(),
+                attrs,
+                common,
+                highlight,
+                height,
+                ShowHide::Default(paused, waker),
+            );
+        }
+
+        draw_panel(
             cr,
             text.as_str(),
             attrs,
-            dependence,
+            common,
             highlight,
-            images,
             height,
             ShowHide::Default(paused, waker),
         )
@@ -273,13 +294,7 @@ impl Pulseaudio {
                 )))?)
             }
             Event::Mouse(event) => {
-                let action = match event.button {
-                    MouseButton::Left => actions.left.clone(),
-                    MouseButton::Right => actions.right.clone(),
-                    MouseButton::Middle => actions.middle.clone(),
-                    MouseButton::ScrollUp => actions.up.clone(),
-                    MouseButton::ScrollDown => actions.down.clone(),
-                };
+                let action = actions.get(&event).to_string();
                 Ok(Self::process_event(
                     &Event::Action(action),
                     actions,
@@ -290,6 +305,7 @@ impl Pulseaudio {
                     response_send,
                 )?)
             }
+            Event::MouseEnter | Event::MouseLeave => Ok(()),
         }
     }
 }
@@ -309,11 +325,11 @@ impl PanelConfig for Pulseaudio {
     /// - `format_unmuted`: the format string when the default sink is unmuted
     ///   - type: String
     ///   - default: `%ramp%%volume%%`
-    ///   - formatting options: `%volume%`, `%ramp%`
+    ///   - formatting options: `%volume%`, `%ramp%`, `%bar%`
     /// - `format_muted`: the format string when the default sink is muted
     ///   - type: String
     ///   - default: `%ramp%%volume%%`
-    ///   - formatting options: `%volume%`, `%ramp%`
+    ///   - formatting options: `%volume%`, `%ramp%`, `%bar%`
     /// - `attrs`: A string specifying the attrs for the panel. See
     ///   [`Attrs::parse`] for details.
     /// - `highlight`: A string specifying the highlight for the panel. See
@@ -324,6 +340,8 @@ impl PanelConfig for Pulseaudio {
     /// - `ramp_muted`: Shows an icon based on the volume level. See
     ///   [`Ramp::parse`] for parsing details. This ramp is used when the sink
     ///   is muted.
+    /// - `display`: `gauge` draws a ring filled by the volume around the
+    ///   format string. See [`Display::parse`] for the other options.
     /// - See [`PanelCommon::parse_common`]. The supported events are
     ///   `increment`, `decrement`, and `toggle`.
     fn parse(
@@ -363,6 +381,7 @@ impl PanelConfig for Pulseaudio {
         builder.attrs(attrs);
         builder.highlight(highlight);
         builder.ramps(PulseaudioRamps::new(ramps));
+        builder.gauge(Display::parse(table).into_gauge());
 
         Ok(builder.build()?)
     }
@@ -488,10 +507,10 @@ impl PanelConfig for Pulseaudio {
         let ramp_muted = self.ramps.muted.clone();
         let format_unmuted = self.formats.unmuted.clone();
         let format_muted = self.formats.muted.clone();
+        let gauge = self.gauge.clone();
         let attrs = self.attrs.clone();
-        let dependence = self.common.dependence;
+        let common = self.common.clone();
         let highlight = self.highlight.clone();
-        let images = self.common.images.clone();
         let paused = self.paused.clone();
         let waker = self.waker.clone();
 
@@ -543,10 +562,10 @@ impl PanelConfig for Pulseaudio {
                     format_muted.as_str(),
                     &ramp,
                     &ramp_muted,
+                    gauge.as_ref(),
                     &attrs,
-                    dependence,
+                    &common,
                     highlight.clone(),
-                    images.clone(),
                     height,
                     paused.clone(),
                     waker.clone(),
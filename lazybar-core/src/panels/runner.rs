@@ -0,0 +1,314 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs::{self, OpenOptions},
+    io::Write,
+    os::unix::fs::PermissionsExt,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{sync::mpsc::unbounded_channel, task};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use x11rb::{protocol::xproto::ConnectionExt, rust_connection::RustConnection};
+
+use crate::{
+    bar::{self, BarInfo, Event, EventResponse, MouseButton, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    entry::Entry,
+    ipc::ChannelEndpoint,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config,
+    sandbox::Sandbox,
+    Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+/// A tiny built-in launcher. Clicking the panel opens a text
+/// [`Entry`] below the bar, and the command that is typed into it is run with
+/// `sh -c`.
+///
+/// Commands are started in their own process group, which is terminated when
+/// the bar exits, like any other child of the bar (see
+/// [`cleanup::spawn_command`]). To keep a program running after the bar
+/// exits, start it with `setsid -f`.
+#[derive(Debug, Builder, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Runner {
+    name: &'static str,
+    format: &'static str,
+    prompt: String,
+    #[builder(default = "300")]
+    width: u16,
+    #[builder(default)]
+    history: Arc<Mutex<Vec<String>>>,
+    #[builder(default = "100")]
+    history_size: usize,
+    #[builder(default, setter(strip_option))]
+    history_file: Option<String>,
+    #[builder(default = "true")]
+    complete: bool,
+    sandbox: Sandbox,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Runner {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        draw_panel(
+            cr,
+            self.format,
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+        // clicking opens the entry
+        .map(|draw_info| draw_info.with_interactive(true))
+    }
+
+    fn process_event(&self, event: Event) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                self.prompt(Some(event.x));
+                Ok(())
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "run" => {
+                self.prompt(None);
+                Ok(())
+            }
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    /// Opens the entry and runs the resulting command in the background.
+    /// `click_x` is the x coordinate of the click relative to the panel, and
+    /// is used to line the entry up with the left edge of the panel.
+    fn prompt(&self, click_x: Option<i16>) {
+        let runner = self.clone();
+        // the blocking task runs on another thread, away from the bar
+        let bar_info = bar::bar_info();
+        task::spawn_blocking(move || {
+            if let Err(e) = runner.prompt_blocking(click_x, bar_info) {
+                log::warn!("Failed to run command: {e}");
+            }
+        });
+    }
+
+    fn prompt_blocking(
+        &self,
+        click_x: Option<i16>,
+        bar_info: Option<BarInfo>,
+    ) -> Result<()> {
+        let bar_info = bar_info
+            .ok_or_else(|| anyhow!("The bar hasn't been created yet"))?;
+        let x = match click_x {
+            Some(click_x) => pointer_x(&bar_info)? - click_x,
+            None => 0,
+        };
+        let history = self.history.lock().unwrap().clone();
+        let completions = if self.complete {
+            executables()
+        } else {
+            Vec::new()
+        };
+
+        let Some(command) = Entry::builder()
+            .prompt(self.prompt.as_str())
+            .x(x)
+            .width(self.width)
+            .attrs(self.attrs.clone())
+            .history(history)
+            .completions(completions)
+            .bar(Some(bar_info))
+            .build()?
+            .run()?
+        else {
+            return Ok(());
+        };
+        let command = command.trim();
+        if command.is_empty() {
+            return Ok(());
+        }
+
+        self.remember(command);
+
+        cleanup::spawn_command(&mut self.sandbox.command(command))
+    }
+
+    fn remember(&self, command: &str) {
+        if self.history_size == 0 {
+            return;
+        }
+
+        let mut history = self.history.lock().unwrap();
+        history.retain(|c| c != command);
+        history.push(command.to_string());
+        let excess = history.len().saturating_sub(self.history_size);
+        history.drain(..excess);
+        drop(history);
+
+        if let Some(ref path) = self.history_file {
+            if let Err(e) = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .and_then(|mut file| writeln!(file, "{command}"))
+            {
+                log::warn!("Failed to write to history file {path}: {e}");
+            }
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Runner {
+    /// Configuration options:
+    ///
+    /// - `format`: the text shown on the bar
+    ///   - type: String
+    ///   - default: `run`
+    /// - `prompt`: the text shown before the input
+    ///   - type: String
+    ///   - default: `run: `
+    /// - `width`: the width of the entry in pixels
+    ///   - type: u64
+    ///   - default: 300
+    /// - `history_size`: the number of previous commands to remember. Set to
+    ///   0 to disable history.
+    ///   - type: u64
+    ///   - default: 100
+    /// - `history_file`: a file that previous commands are loaded from and
+    ///   appended to, one per line
+    ///   - type: String
+    ///   - default: none
+    /// - `complete`: whether Tab should complete the names of executables in
+    ///   `$PATH`
+    ///   - type: bool
+    ///   - default: true
+    /// - `attrs`: A string specifying the attrs for the panel and the entry.
+    ///   See [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`Sandbox::parse`] for options that restrict commands.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel opens the
+    ///   entry, and the supported event is `run`, which opens the entry at the
+    ///   left edge of the bar.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = RunnerBuilder::default();
+
+        builder.name(name);
+        builder.prompt(
+            remove_string_from_config("prompt", table)
+                .unwrap_or_else(|| String::from("run: ")),
+        );
+        if let Some(width) = remove_uint_from_config("width", table) {
+            builder.width(width as u16);
+        }
+        let history_size = remove_uint_from_config("history_size", table)
+            .map_or(100, |s| s as usize);
+        builder.history_size(history_size);
+        if let Some(path) = remove_string_from_config("history_file", table) {
+            let mut history = fs::read_to_string(path.as_str())
+                .map(|s| s.lines().map(String::from).collect::<Vec<_>>())
+                .unwrap_or_default();
+            let excess = history.len().saturating_sub(history_size);
+            history.drain(..excess);
+            builder.history(Arc::new(Mutex::new(history)));
+            builder.history_file(path);
+        }
+        if let Some(complete) = remove_bool_from_config("complete", table) {
+            builder.complete(complete);
+        }
+        builder.sandbox(Sandbox::parse(table)?);
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "run");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let runner = self.clone();
+        let events =
+            UnboundedReceiverStream::new(event_recv).filter_map(move |event| {
+                let response = match runner.process_event(event) {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                };
+                let _ = response_send.send(response);
+                None
+            });
+
+        let stream = tokio_stream::once(())
+            .merge(events)
+            .map(move |()| self.draw(&cr, height));
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+/// Returns the x coordinate of the pointer relative to the bar.
+fn pointer_x(bar_info: &BarInfo) -> Result<i16> {
+    let (conn, _) = RustConnection::connect(None)?;
+    Ok(conn.query_pointer(bar_info.window)?.reply()?.win_x)
+}
+
+/// Returns the names of all executable files in `$PATH`, sorted and
+/// deduplicated.
+fn executables() -> Vec<String> {
+    let path = std::env::var("PATH").unwrap_or_default();
+    path.split(':')
+        .filter_map(|dir| fs::read_dir(dir).ok())
+        .flatten()
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            fs::metadata(entry.path()).is_ok_and(|meta| {
+                meta.is_file() && meta.permissions().mode() & 0o111 != 0
+            })
+        })
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
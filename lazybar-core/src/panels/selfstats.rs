@@ -0,0 +1,199 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::task::AtomicWaker;
+use nix::sys::{
+    resource::{getrusage, UsageWho},
+    time::TimeValLike,
+};
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    diagnostics, human_readable,
+    ipc::ChannelEndpoint,
+    remove_uint_from_config, Attrs, Highlight, ManagedIntervalStream,
+    PanelConfig, PanelStream,
+};
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    cpu: Duration,
+    wakeups: u64,
+    redraws: u64,
+    time: Instant,
+}
+
+impl Sample {
+    fn now() -> Result<Self> {
+        let usage = getrusage(UsageWho::RUSAGE_SELF)?;
+        let cpu = usage.user_time() + usage.system_time();
+        Ok(Self {
+            cpu: Duration::from_micros(cpu.num_microseconds() as u64),
+            wakeups: diagnostics::wakeups(),
+            redraws: diagnostics::redraws(),
+            time: Instant::now(),
+        })
+    }
+}
+
+/// Displays lazybar's own CPU usage, memory usage, and how often it wakes up
+/// and redraws panels, to help with tuning intervals and animations.
+///
+/// The rates are averaged over the time since the last check. Redraws include
+/// those of this panel.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct SelfStats {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(2)")]
+    interval: Duration,
+    #[builder(default)]
+    waker: Arc<AtomicWaker>,
+    #[builder(default, setter(skip))]
+    last: Option<Sample>,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl SelfStats {
+    fn draw(
+        &mut self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        paused: Arc<Mutex<bool>>,
+    ) -> Result<PanelDrawInfo> {
+        let sample = Sample::now()?;
+        let (cpu, wakeups, redraws) =
+            self.last.map_or((0.0, 0.0, 0.0), |last| {
+                let elapsed =
+                    sample.time.duration_since(last.time).as_secs_f64();
+                if elapsed == 0.0 {
+                    (0.0, 0.0, 0.0)
+                } else {
+                    (
+                        sample.cpu.saturating_sub(last.cpu).as_secs_f64()
+                            / elapsed
+                            * 100.0,
+                        (sample.wakeups - last.wakeups) as f64 / elapsed,
+                        (sample.redraws - last.redraws) as f64 / elapsed,
+                    )
+                }
+            });
+        self.last = Some(sample);
+
+        let text = self
+            .format
+            .replace("%cpu%", format!("{cpu:.1}").as_str())
+            .replace("%rss%", human_readable(read_rss()?).as_str())
+            .replace("%wakeups%", format!("{wakeups:.1}").as_str())
+            .replace("%redraws%", format!("{redraws:.1}").as_str());
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::Default(paused, self.waker.clone()),
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for SelfStats {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 2
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%cpu%% %rss% %wakeups%/s`
+    ///   - formatting options: `%cpu%` (CPU usage, where 100 is one full
+    ///     core), `%rss%` (resident memory, e.g. `24.1M`), `%wakeups%` (event
+    ///     loop wakeups per second), `%redraws%` (panel redraws per second)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = SelfStatsBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format =
+            PanelCommon::parse_format(table, "", "%cpu%% %rss% %wakeups%/s");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let paused = Arc::new(Mutex::new(false));
+
+        let stream = ManagedIntervalStream::builder()
+            .duration(self.interval)
+            .paused(paused.clone())
+            .waker(self.waker.clone())
+            .build()?
+            .map(move |_| self.draw(&cr, height, paused.clone()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+/// Returns the resident set size of this process in bytes.
+fn read_rss() -> Result<u64> {
+    let kb = read_to_string("/proc/self/status")?
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))
+        .ok_or_else(|| anyhow!("Failed to read /proc/self/status"))?
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse::<u64>()?;
+    Ok(kb * 1024)
+}
@@ -7,7 +7,7 @@ use derive_builder::Builder;
 
 use crate::{
     bar::{Event, EventResponse},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
     Attrs, PanelConfig, PanelStream,
 };
@@ -71,13 +71,12 @@ impl PanelConfig for Separator {
         self.attrs.apply_to(&global_attrs);
 
         Ok((
-            Box::pin(tokio_stream::once(draw_common(
+            Box::pin(tokio_stream::once(draw_panel(
                 &cr,
                 self.format,
                 &self.attrs,
-                self.common.dependence,
+                &self.common,
                 None,
-                self.common.images.clone(),
                 height,
                 ShowHide::None,
             ))),
@@ -0,0 +1,969 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    rc::Rc,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use cairo::{Format, ImageSurface};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    task::JoinHandle,
+};
+use tokio_stream::{wrappers::UnboundedReceiverStream, StreamExt};
+use x11rb::{
+    connection::Connection as _, protocol::xproto::ConnectionExt,
+    rust_connection::RustConnection,
+};
+use zbus::{
+    fdo, interface,
+    message::Header,
+    proxy,
+    proxy::CacheProperties,
+    zvariant::{OwnedObjectPath, OwnedValue, Value},
+    Connection, SignalContext,
+};
+
+use crate::{
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo, TooltipSpan},
+    common::PanelCommon,
+    icon, image,
+    ipc::ChannelEndpoint,
+    menu::MenuEntry,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, PanelConfig, PanelStream,
+};
+
+const WATCHER: &str = "org.kde.StatusNotifierWatcher";
+const WATCHER_PATH: &str = "/StatusNotifierWatcher";
+const ITEM_PATH: &str = "/StatusNotifierItem";
+
+type Pixmap = (i32, i32, Vec<u8>);
+/// The icon name, icon pixmaps, title, and description of a tooltip
+type ToolTip = (String, Vec<Pixmap>, String, String);
+/// The id, properties, and children of an entry in a dbusmenu
+type Layout = (i32, HashMap<String, OwnedValue>, Vec<OwnedValue>);
+
+#[proxy(
+    interface = "org.kde.StatusNotifierWatcher",
+    default_service = "org.kde.StatusNotifierWatcher",
+    default_path = "/StatusNotifierWatcher"
+)]
+trait StatusNotifierWatcher {
+    fn register_status_notifier_host(&self, service: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> zbus::Result<Vec<String>>;
+
+    #[zbus(signal)]
+    fn status_notifier_item_registered(
+        &self,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    fn status_notifier_item_unregistered(
+        &self,
+        service: &str,
+    ) -> zbus::Result<()>;
+}
+
+#[proxy(interface = "org.kde.StatusNotifierItem", assume_defaults = false)]
+trait StatusNotifierItem {
+    fn activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    fn secondary_activate(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    fn context_menu(&self, x: i32, y: i32) -> zbus::Result<()>;
+
+    fn scroll(&self, delta: i32, orientation: &str) -> zbus::Result<()>;
+
+    #[zbus(property)]
+    fn title(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn status(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn icon_name(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn icon_pixmap(&self) -> zbus::Result<Vec<Pixmap>>;
+
+    #[zbus(property)]
+    fn icon_theme_path(&self) -> zbus::Result<String>;
+
+    #[zbus(property)]
+    fn item_is_menu(&self) -> zbus::Result<bool>;
+
+    #[zbus(property)]
+    fn menu(&self) -> zbus::Result<OwnedObjectPath>;
+
+    #[zbus(property)]
+    fn tool_tip(&self) -> zbus::Result<ToolTip>;
+}
+
+#[proxy(interface = "com.canonical.dbusmenu", assume_defaults = false)]
+trait DBusMenu {
+    fn get_layout(
+        &self,
+        parent_id: i32,
+        recursion_depth: i32,
+        property_names: &[&str],
+    ) -> zbus::Result<(u32, Layout)>;
+
+    fn event(
+        &self,
+        id: i32,
+        event_id: &str,
+        data: &Value<'_>,
+        timestamp: u32,
+    ) -> zbus::Result<()>;
+
+    fn about_to_show(&self, id: i32) -> zbus::Result<bool>;
+}
+
+/// Displays icons from applications using the `StatusNotifierItem` D-Bus
+/// protocol (also known as `AppIndicator`). See
+/// <https://www.freedesktop.org/wiki/Specifications/StatusNotifierItem/> for
+/// details.
+///
+/// If no other program owns `org.kde.StatusNotifierWatcher`, this panel will
+/// act as the watcher. Left clicking an icon activates it, middle clicking
+/// calls `SecondaryActivate`, right clicking opens its context menu, and
+/// scrolling is forwarded to the application. Menus exported over
+/// `com.canonical.dbusmenu` are shown by the bar, with submenus indented
+/// under their parent entry; otherwise, the application is asked to show its
+/// own menu. Hovering over an icon shows the item's `ToolTip`.
+///
+/// Icons are taken from `IconPixmap` if the application provides it.
+/// Otherwise, `IconName` is looked up in the application's `IconThemePath`,
+/// then in the icon theme (see [`icon::lookup`]). If no icon can be found,
+/// the first character of the item's title is shown instead.
+#[derive(Debug, Builder, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Sni {
+    name: &'static str,
+    conn: Arc<RustConnection>,
+    screen: usize,
+    #[builder(default)]
+    items: Arc<Mutex<Vec<Item>>>,
+    #[builder(default, setter(strip_option))]
+    size: Option<i32>,
+    #[builder(default = "4")]
+    spacing: i32,
+    #[builder(default)]
+    show_passive: bool,
+    attrs: Attrs,
+    common: PanelCommon,
+}
+
+impl Sni {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        menu: Option<Vec<MenuEntry>>,
+    ) -> Result<PanelDrawInfo> {
+        let size = self.size.unwrap_or(height).min(height);
+        let spacing = self.spacing;
+        let items = visible(&self.items, self.show_passive);
+        let icons = items
+            .iter()
+            .map(|item| Icon::new(item, cr, &self.attrs, size))
+            .collect::<Vec<_>>();
+        let tooltips = items
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, item)| {
+                let start = idx as i32 * (size + spacing);
+                Some(TooltipSpan {
+                    start,
+                    end: start + size,
+                    markup: item.tooltip.clone()?,
+                })
+            })
+            .collect();
+        let count = icons.len() as i32;
+        let width = (count * (size + spacing) - spacing).max(0);
+        let attrs = self.attrs.clone();
+
+        let draw_info = PanelDrawInfo::new(
+            (width, height),
+            self.common.dependence,
+            Box::new(move |cr, _| {
+                for (idx, icon) in icons.iter().enumerate() {
+                    cr.save()?;
+                    cr.translate(
+                        f64::from(idx as i32 * (size + spacing)),
+                        f64::from(height - size) / 2.0,
+                    );
+                    icon.draw(cr, &attrs, size)?;
+                    cr.restore()?;
+                }
+                Ok(())
+            }),
+            None,
+            None,
+            None,
+        )
+        .with_tooltips(tooltips);
+
+        Ok(match menu {
+            Some(menu) => draw_info.with_shown_menu(menu),
+            None => draw_info,
+        })
+    }
+
+    fn click(
+        &self,
+        button: MouseButton,
+        x: i16,
+        height: i32,
+        send: &UnboundedSender<Request>,
+    ) -> Result<()> {
+        let size = self.size.unwrap_or(height).min(height);
+        let idx = i32::from(x) / (size + self.spacing);
+        let Some(item) = visible(&self.items, self.show_passive)
+            .into_iter()
+            .nth(idx as usize)
+        else {
+            return Ok(());
+        };
+
+        let root = self.conn.setup().roots[self.screen].root;
+        let pointer = self.conn.query_pointer(root)?.reply()?;
+        send.send(Request::Click(Click {
+            id: item.id,
+            button,
+            menu: item.menu,
+            menu_path: item.menu_path,
+            x: i32::from(pointer.root_x),
+            y: i32::from(pointer.root_y),
+        }))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Sni {
+    /// Configuration options:
+    ///
+    /// - `screen`: the name of the X screen to use for finding the pointer
+    ///   position when an icon is clicked
+    ///   - type: String
+    ///   - default: None (This will tell X to choose the default screen, which
+    ///     is probably what you want.)
+    /// - `size`: the size of each icon in pixels
+    ///   - type: u64
+    ///   - default: the height of the bar
+    /// - `spacing`: the space between icons in pixels
+    ///   - type: u64
+    ///   - default: 4
+    /// - `show_passive`: whether to show items whose status is `Passive`
+    ///   - type: bool
+    ///   - default: false
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details. These are only used for items without
+    ///   an icon.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = SniBuilder::default();
+
+        builder.name(name);
+        let screen = remove_string_from_config("screen", table);
+        if let Ok((conn, screen)) = RustConnection::connect(screen.as_deref()) {
+            builder.conn(Arc::new(conn));
+            builder.screen(screen);
+        } else {
+            log::error!("Failed to connect to X server");
+        }
+
+        if let Some(size) = remove_uint_from_config("size", table) {
+            builder.size(size as i32);
+        }
+        if let Some(spacing) = remove_uint_from_config("spacing", table) {
+            builder.spacing(spacing as i32);
+        }
+        if let Some(show_passive) =
+            remove_bool_from_config("show_passive", table)
+        {
+            builder.show_passive(show_passive);
+        }
+
+        builder.common(PanelCommon::parse_common(table)?);
+        builder.attrs(PanelCommon::parse_attr(table, ""));
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let (changed_send, changed_recv) = unbounded_channel();
+        let (request_send, request_recv) = unbounded_channel();
+        let (menu_send, menu_recv) = unbounded_channel();
+        let items = self.items.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                host(items, changed_send, request_recv, menu_send).await
+            {
+                log::error!("StatusNotifier host failed: {e}");
+            }
+        });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let panel = self.clone();
+        let events =
+            UnboundedReceiverStream::new(event_recv).filter_map(move |event| {
+                match event {
+                    Event::Mouse(event) => {
+                        if let Err(e) = panel.click(
+                            event.button,
+                            event.x,
+                            height,
+                            &request_send,
+                        ) {
+                            log::warn!("Failed to handle click: {e}");
+                        }
+                    }
+                    Event::MouseEnter | Event::MouseLeave => {}
+                    Event::Action(event) => {
+                        // menu entries send `menu <entry id> <item id>`
+                        let mut parts = event.splitn(3, ' ');
+                        let request = match (
+                            parts.next(),
+                            parts.next().and_then(|entry| entry.parse().ok()),
+                            parts.next(),
+                        ) {
+                            (Some("menu"), Some(entry), Some(id)) => {
+                                Some(Request::MenuEvent {
+                                    id: id.to_string(),
+                                    entry,
+                                })
+                            }
+                            _ => None,
+                        };
+                        let response = match request {
+                            Some(request) => {
+                                request_send.send(request).map_or_else(
+                                    |e| EventResponse::Err(e.to_string()),
+                                    |()| EventResponse::Ok,
+                                )
+                            }
+                            None => EventResponse::Err(format!(
+                                "Unknown event {event}"
+                            )),
+                        };
+                        let _ = response_send.send(response);
+                    }
+                }
+                None
+            });
+
+        let stream = tokio_stream::once(None)
+            .chain(UnboundedReceiverStream::new(changed_recv).map(|()| None))
+            .merge(UnboundedReceiverStream::new(menu_recv).map(Some))
+            .merge(events)
+            .map(move |menu| self.draw(&cr, height, menu));
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct Item {
+    id: String,
+    title: String,
+    status: String,
+    icon_name: String,
+    icon_pixmap: Vec<Pixmap>,
+    icon_theme_path: String,
+    menu: bool,
+    /// The object path of the item's dbusmenu, or empty if it has none
+    menu_path: String,
+    /// The markup of the item's tooltip
+    tooltip: Option<String>,
+}
+
+impl Item {
+    async fn fetch(id: String, proxy: &StatusNotifierItemProxy<'_>) -> Self {
+        let title = proxy.title().await.unwrap_or_default();
+        let (_, _, tooltip_title, description) =
+            proxy.tool_tip().await.unwrap_or_default();
+        let tooltip_title = if tooltip_title.is_empty() {
+            title.as_str()
+        } else {
+            tooltip_title.as_str()
+        };
+        // descriptions may use HTML that pango doesn't understand, so they're
+        // shown as plain text
+        let tooltip = match (tooltip_title, description.as_str()) {
+            ("", "") => None,
+            (title, "") => Some(glib::markup_escape_text(title).to_string()),
+            (title, description) => Some(format!(
+                "<b>{}</b>\n{}",
+                glib::markup_escape_text(title),
+                glib::markup_escape_text(description)
+            )),
+        };
+        Self {
+            id,
+            status: proxy.status().await.unwrap_or_default(),
+            icon_name: proxy.icon_name().await.unwrap_or_default(),
+            icon_pixmap: proxy.icon_pixmap().await.unwrap_or_default(),
+            icon_theme_path: proxy.icon_theme_path().await.unwrap_or_default(),
+            menu: proxy.item_is_menu().await.unwrap_or_default(),
+            menu_path: proxy
+                .menu()
+                .await
+                .map(|path| path.to_string())
+                .unwrap_or_default(),
+            tooltip,
+            title,
+        }
+    }
+}
+
+fn visible(items: &Mutex<Vec<Item>>, show_passive: bool) -> Vec<Item> {
+    items
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|item| show_passive || item.status != "Passive")
+        .cloned()
+        .collect()
+}
+
+enum Icon {
+    Image(ImageSurface),
+    Text(pango::Layout),
+}
+
+impl Icon {
+    fn new(item: &Item, cr: &cairo::Context, attrs: &Attrs, size: i32) -> Self {
+        pixmap_surface(item.icon_pixmap.as_slice(), size)
+            .or_else(|| {
+                named_surface(
+                    item.icon_name.as_str(),
+                    item.icon_theme_path.as_str(),
+                    size,
+                )
+            })
+            .map_or_else(
+                || {
+                    let layout = pangocairo::functions::create_layout(cr);
+                    attrs.apply_font(&layout);
+                    layout.set_text(
+                        item.title
+                            .chars()
+                            .next()
+                            .map(String::from)
+                            .unwrap_or_default()
+                            .as_str(),
+                    );
+                    Self::Text(layout)
+                },
+                Self::Image,
+            )
+    }
+
+    fn draw(
+        &self,
+        cr: &cairo::Context,
+        attrs: &Attrs,
+        size: i32,
+    ) -> Result<()> {
+        match self {
+            Self::Image(surface) => {
+                let scale = f64::from(size)
+                    / f64::from(surface.width().max(surface.height()));
+                cr.scale(scale, scale);
+                cr.set_source_surface(surface, 0.0, 0.0)?;
+                cr.paint()?;
+            }
+            Self::Text(layout) => {
+                let (width, height) = layout.pixel_size();
+                cr.translate(
+                    f64::from(size - width) / 2.0,
+                    f64::from(size - height) / 2.0,
+                );
+                attrs.apply_fg(cr);
+                pangocairo::functions::show_layout(cr, layout);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Converts the smallest pixmap that is at least `size` pixels wide (or the
+/// largest one, if they are all smaller) from the non-premultiplied, network
+/// byte order ARGB that the protocol uses to a cairo surface.
+fn pixmap_surface(pixmaps: &[Pixmap], size: i32) -> Option<ImageSurface> {
+    let (width, height, data) = pixmaps
+        .iter()
+        .filter(|(w, h, data)| {
+            *w > 0 && *h > 0 && data.len() == (w * h * 4) as usize
+        })
+        .min_by_key(|(w, _, _)| if *w >= size { (0, *w) } else { (1, -*w) })?;
+
+    let mut buf = Vec::with_capacity(data.len());
+    for pixel in data.chunks_exact(4) {
+        let alpha = u32::from(pixel[0]);
+        let channel = |c: u8| u32::from(c) * alpha / 255;
+        let argb = alpha << 24
+            | channel(pixel[1]) << 16
+            | channel(pixel[2]) << 8
+            | channel(pixel[3]);
+        buf.extend_from_slice(&argb.to_ne_bytes());
+    }
+
+    ImageSurface::create_for_data(
+        buf,
+        Format::ARgb32,
+        *width,
+        *height,
+        width * 4,
+    )
+    .ok()
+}
+
+fn named_surface(
+    name: &str,
+    theme_path: &str,
+    size: i32,
+) -> Option<ImageSurface> {
+    if name.is_empty() {
+        return None;
+    }
+
+    if !name.starts_with('/') && !theme_path.is_empty() {
+        let surface = ["png", "svg"].into_iter().find_map(|ext| {
+            let path = PathBuf::from(format!("{theme_path}/{name}.{ext}"));
+            if !path.is_file() {
+                return None;
+            }
+            image::load_surface(path.as_path(), Some(size)).ok()
+        });
+        if surface.is_some() {
+            return surface;
+        }
+    }
+
+    icon::load(name, size)
+}
+
+/// Something for the host to send to an item
+#[derive(Debug)]
+enum Request {
+    /// A click on the item's icon
+    Click(Click),
+    /// An entry of the item's dbusmenu was chosen
+    MenuEvent { id: String, entry: i32 },
+}
+
+impl Request {
+    async fn send(
+        self,
+        conn: Connection,
+        menus: UnboundedSender<Vec<MenuEntry>>,
+    ) -> Result<()> {
+        match self {
+            Self::Click(click) => click.send(conn, menus).await,
+            Self::MenuEvent { id, entry } => {
+                let menu_path = item_proxy(&conn, id.as_str())
+                    .await?
+                    .menu()
+                    .await?
+                    .to_string();
+                menu_proxy(&conn, id.as_str(), menu_path.as_str())
+                    .await?
+                    .event(entry, "clicked", &Value::from(0), 0)
+                    .await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Click {
+    id: String,
+    button: MouseButton,
+    menu: bool,
+    menu_path: String,
+    x: i32,
+    y: i32,
+}
+
+impl Click {
+    async fn send(
+        self,
+        conn: Connection,
+        menus: UnboundedSender<Vec<MenuEntry>>,
+    ) -> Result<()> {
+        let opens_menu = match self.button {
+            MouseButton::Left => self.menu,
+            MouseButton::Right => true,
+            _ => false,
+        };
+        if opens_menu && !self.menu_path.is_empty() {
+            let proxy =
+                menu_proxy(&conn, self.id.as_str(), self.menu_path.as_str())
+                    .await?;
+            // lets applications fill in their menus before they're read
+            let _ = proxy.about_to_show(0).await;
+            let (_, layout) = proxy.get_layout(0, -1, &[]).await?;
+            let mut entries = Vec::new();
+            menu_entries(layout, self.id.as_str(), 0, &mut entries);
+            menus.send(entries)?;
+            return Ok(());
+        }
+
+        let proxy = item_proxy(&conn, self.id.as_str()).await?;
+        match self.button {
+            MouseButton::Left if self.menu => {
+                proxy.context_menu(self.x, self.y).await?;
+            }
+            MouseButton::Left => proxy.activate(self.x, self.y).await?,
+            MouseButton::Middle => {
+                proxy.secondary_activate(self.x, self.y).await?;
+            }
+            MouseButton::Right => proxy.context_menu(self.x, self.y).await?,
+            MouseButton::ScrollUp => proxy.scroll(-1, "vertical").await?,
+            MouseButton::ScrollDown => proxy.scroll(1, "vertical").await?,
+        }
+        Ok(())
+    }
+}
+
+/// Adds the children of a dbusmenu entry to `entries`, flattening submenus
+/// by indenting their entries under the submenu's label. Separators and
+/// hidden entries are skipped, and entries that can't be chosen have no
+/// action.
+fn menu_entries(
+    layout: Layout,
+    id: &str,
+    depth: usize,
+    entries: &mut Vec<MenuEntry>,
+) {
+    for child in layout.2 {
+        let Ok(child) = Layout::try_from(child) else {
+            continue;
+        };
+        let (entry, properties, _) = &child;
+        let string = |name: &str| {
+            properties
+                .get(name)
+                .and_then(|value| value.downcast_ref::<&str>().ok())
+                .unwrap_or_default()
+        };
+        let flag = |name: &str| {
+            properties
+                .get(name)
+                .and_then(|value| value.downcast_ref::<bool>().ok())
+                .unwrap_or(true)
+        };
+        if string("type") == "separator" || !flag("visible") {
+            continue;
+        }
+
+        // underscores mark mnemonics, and doubled ones are literal
+        let label = string("label").replace("__", "\0").replace('_', "");
+        let label = label.replace('\0', "_");
+        let toggled = properties
+            .get("toggle-state")
+            .and_then(|value| value.downcast_ref::<i32>().ok())
+            == Some(1);
+        let label = format!(
+            "{}{}{}",
+            "    ".repeat(depth),
+            if toggled { "✓ " } else { "" },
+            glib::markup_escape_text(label.as_str())
+        );
+        let submenu = string("children-display") == "submenu";
+        if flag("enabled") && !submenu {
+            entries.push(MenuEntry::new(label, format!("menu {entry} {id}")));
+        } else {
+            entries.push(MenuEntry::new(
+                format!("<span alpha=\"50%\">{label}</span>"),
+                "",
+            ));
+        }
+        if submenu {
+            menu_entries(child, id, depth + 1, entries);
+        }
+    }
+}
+
+/// Splits an item id of the form `<bus name><object path>` into its parts.
+fn split_id(id: &str) -> (&str, &str) {
+    id.find('/')
+        .map_or((id, ITEM_PATH), |idx| (&id[..idx], &id[idx..]))
+}
+
+async fn item_proxy(
+    conn: &Connection,
+    id: &str,
+) -> Result<StatusNotifierItemProxy<'static>> {
+    let (service, path) = split_id(id);
+    Ok(StatusNotifierItemProxy::builder(conn)
+        .destination(service.to_string())?
+        .path(path.to_string())?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+async fn menu_proxy(
+    conn: &Connection,
+    id: &str,
+    menu_path: &str,
+) -> Result<DBusMenuProxy<'static>> {
+    let (service, _) = split_id(id);
+    Ok(DBusMenuProxy::builder(conn)
+        .destination(service.to_string())?
+        .path(menu_path.to_string())?
+        .cache_properties(CacheProperties::No)
+        .build()
+        .await?)
+}
+
+async fn host(
+    items: Arc<Mutex<Vec<Item>>>,
+    changed: UnboundedSender<()>,
+    mut requests: UnboundedReceiver<Request>,
+    menus: UnboundedSender<Vec<MenuEntry>>,
+) -> Result<()> {
+    let conn = Connection::session().await?;
+
+    if conn.request_name(WATCHER).await.is_ok() {
+        conn.object_server()
+            .at(WATCHER_PATH, Watcher::default())
+            .await?;
+        let conn = conn.clone();
+        tokio::spawn(async move {
+            if let Err(e) = watch_names(conn).await {
+                log::error!("StatusNotifier watcher failed: {e}");
+            }
+        });
+    }
+
+    let host = format!("org.kde.StatusNotifierHost-{}", std::process::id());
+    conn.request_name(host.as_str()).await?;
+
+    let watcher = StatusNotifierWatcherProxy::new(&conn).await?;
+    let mut registered =
+        watcher.receive_status_notifier_item_registered().await?;
+    let mut unregistered =
+        watcher.receive_status_notifier_item_unregistered().await?;
+    watcher.register_status_notifier_host(host.as_str()).await?;
+
+    let mut tasks = HashMap::new();
+    for id in watcher.registered_status_notifier_items().await? {
+        let task = watch_item(&conn, id.clone(), &items, &changed);
+        tasks.insert(id, task);
+    }
+
+    loop {
+        tokio::select! {
+            Some(signal) = registered.next() => {
+                let id = signal.args()?.service().to_string();
+                if !tasks.contains_key(&id) {
+                    let task = watch_item(&conn, id.clone(), &items, &changed);
+                    tasks.insert(id, task);
+                }
+            }
+            Some(signal) = unregistered.next() => {
+                let id = signal.args()?.service().to_string();
+                if let Some(task) = tasks.remove(&id) {
+                    task.abort();
+                }
+                items.lock().unwrap().retain(|item| item.id != id);
+                changed.send(())?;
+            }
+            Some(request) = requests.recv() => {
+                let conn = conn.clone();
+                let menus = menus.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = request.send(conn, menus).await {
+                        log::warn!("Failed to send click to item: {e}");
+                    }
+                });
+            }
+            else => break,
+        }
+    }
+
+    Ok(())
+}
+
+fn watch_item(
+    conn: &Connection,
+    id: String,
+    items: &Arc<Mutex<Vec<Item>>>,
+    changed: &UnboundedSender<()>,
+) -> JoinHandle<()> {
+    let conn = conn.clone();
+    let items = items.clone();
+    let changed = changed.clone();
+    tokio::spawn(async move {
+        let result: Result<()> = async {
+            let proxy = item_proxy(&conn, id.as_str()).await?;
+            // every signal on this interface (NewIcon, NewTitle, NewStatus,
+            // etc.) means that some property has changed
+            let mut signals = proxy.inner().receive_all_signals().await?;
+            loop {
+                let item = Item::fetch(id.clone(), &proxy).await;
+                {
+                    let mut items = items.lock().unwrap();
+                    if let Some(old) = items.iter_mut().find(|i| i.id == id) {
+                        *old = item;
+                    } else {
+                        items.push(item);
+                    }
+                }
+                changed.send(())?;
+                if signals.next().await.is_none() {
+                    return Ok(());
+                }
+            }
+        }
+        .await;
+        if let Err(e) = result {
+            log::warn!("Failed to watch item {id}: {e}");
+        }
+    })
+}
+
+async fn watch_names(conn: Connection) -> Result<()> {
+    let dbus = fdo::DBusProxy::new(&conn).await?;
+    let mut changes = dbus.receive_name_owner_changed().await?;
+    let iface = conn
+        .object_server()
+        .interface::<_, Watcher>(WATCHER_PATH)
+        .await?;
+
+    while let Some(change) = changes.next().await {
+        let args = change.args()?;
+        if args.new_owner().is_some() {
+            continue;
+        }
+        let name = args.name().as_str();
+
+        let mut watcher = iface.get_mut().await;
+        let (gone, kept) = watcher
+            .items
+            .drain(..)
+            .partition::<Vec<_>, _>(|id| split_id(id).0 == name);
+        watcher.items = kept;
+        drop(watcher);
+
+        for id in gone {
+            Watcher::status_notifier_item_unregistered(
+                iface.signal_context(),
+                id.as_str(),
+            )
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+struct Watcher {
+    items: Vec<String>,
+}
+
+#[interface(name = "org.kde.StatusNotifierWatcher")]
+impl Watcher {
+    async fn register_status_notifier_item(
+        &mut self,
+        service: &str,
+        #[zbus(header)] header: Header<'_>,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
+        // items may register with either a bus name or an object path on
+        // their own connection
+        let id = if service.starts_with('/') {
+            let sender = header
+                .sender()
+                .ok_or_else(|| fdo::Error::Failed("Unknown sender".into()))?;
+            format!("{sender}{service}")
+        } else {
+            format!("{service}{ITEM_PATH}")
+        };
+
+        if !self.items.contains(&id) {
+            self.items.push(id.clone());
+            Self::status_notifier_item_registered(&ctxt, id.as_str()).await?;
+        }
+        Ok(())
+    }
+
+    async fn register_status_notifier_host(
+        &self,
+        _service: &str,
+        #[zbus(signal_context)] ctxt: SignalContext<'_>,
+    ) -> fdo::Result<()> {
+        Self::status_notifier_host_registered(&ctxt).await?;
+        Ok(())
+    }
+
+    #[zbus(property)]
+    fn registered_status_notifier_items(&self) -> Vec<String> {
+        self.items.clone()
+    }
+
+    #[zbus(property)]
+    fn is_status_notifier_host_registered(&self) -> bool {
+        true
+    }
+
+    #[zbus(property)]
+    fn protocol_version(&self) -> i32 {
+        0
+    }
+
+    #[zbus(signal)]
+    async fn status_notifier_item_registered(
+        ctxt: &SignalContext<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_item_unregistered(
+        ctxt: &SignalContext<'_>,
+        service: &str,
+    ) -> zbus::Result<()>;
+
+    #[zbus(signal)]
+    async fn status_notifier_host_registered(
+        ctxt: &SignalContext<'_>,
+    ) -> zbus::Result<()>;
+}
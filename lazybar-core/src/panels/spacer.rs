@@ -0,0 +1,93 @@
+use std::{collections::HashMap, rc::Rc};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::PanelCommon,
+    ipc::ChannelEndpoint,
+    remove_uint_from_config, Attrs, PanelConfig, PanelStream,
+};
+
+/// Displays nothing, taking up a fixed amount of space.
+#[derive(Builder, Debug, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Spacer {
+    name: &'static str,
+    width: i32,
+    attrs: Attrs,
+    common: PanelCommon,
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Spacer {
+    /// Configuration options:
+    ///
+    /// - `width`: the width in pixels of the space
+    ///   - type: u64
+    ///   - default: 10
+    /// - `attrs`: A string specifying the attrs for the panel. Only the
+    ///   background is used. See [`Attrs::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = SpacerBuilder::default();
+
+        builder.name(name);
+        builder.width(
+            remove_uint_from_config("width", table).unwrap_or(10) as i32,
+        );
+
+        let common = PanelCommon::parse_common(table)?;
+        let attrs = PanelCommon::parse_attr(table, "");
+
+        builder.common(common);
+        builder.attrs(attrs);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        _cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let width = self.width;
+        let bg = self.attrs.bg.clone().unwrap_or_default();
+
+        Ok((
+            Box::pin(tokio_stream::once(Ok(PanelDrawInfo::new(
+                (width, height),
+                self.common.dependence,
+                Box::new(move |cr, _| {
+                    bg.draw(
+                        cr,
+                        f64::from(width),
+                        f64::from(height),
+                        f64::from(height),
+                    )?;
+                    Ok(())
+                }),
+                None,
+                None,
+                None,
+            )))),
+            None,
+        ))
+    }
+}
@@ -0,0 +1,294 @@
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::task::AtomicWaker;
+use nix::sys::statvfs::statvfs;
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    format, human_readable,
+    ipc::ChannelEndpoint,
+    progress::StackedBar,
+    remove_array_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, ManagedIntervalStream,
+    PanelConfig, PanelStream,
+};
+
+#[derive(Debug, Clone)]
+struct Mount {
+    path: String,
+    format: &'static str,
+    format_critical: &'static str,
+}
+
+/// Displays disk and inode usage for one or more mountpoints, using
+/// `statvfs(3)`
+#[derive(Debug, Builder, Clone)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Storage {
+    name: &'static str,
+    mounts: Vec<Mount>,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    #[builder(default)]
+    waker: Arc<AtomicWaker>,
+    #[builder(default = "90")]
+    threshold: u64,
+    separator: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    #[builder(default)]
+    stack: StackedBar,
+    common: PanelCommon,
+}
+
+impl Storage {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        paused: Arc<Mutex<bool>>,
+    ) -> Result<PanelDrawInfo> {
+        let text = self
+            .mounts
+            .iter()
+            .map(|mount| {
+                let stat = statvfs(mount.path.as_str())?;
+                let fragment = stat.fragment_size() as u64;
+                let total = stat.blocks() as u64 * fragment;
+                let avail = stat.blocks_available() as u64 * fragment;
+                let used = total - stat.blocks_free() as u64 * fragment;
+                // free space that only root can use
+                let reserved = total - used - avail;
+                let inodes_total = stat.files() as u64;
+                let inodes_avail = stat.files_available() as u64;
+                let inodes_used = inodes_total - stat.files_free() as u64;
+
+                let percent = percentage(used, used + avail);
+                let inodes_percent =
+                    percentage(inodes_used, inodes_used + inodes_avail);
+
+                let format = if percent >= self.threshold {
+                    mount.format_critical
+                } else {
+                    mount.format
+                };
+
+                let mut text = self.stack.replace(
+                    format,
+                    &[used as f64, reserved as f64, avail as f64],
+                );
+                for (name, bytes) in
+                    [("used", used), ("avail", avail), ("total", total)]
+                {
+                    text = format::replace_number(
+                        text.as_str(),
+                        name,
+                        bytes as f64,
+                        human_readable(bytes).as_str(),
+                    );
+                }
+                let text = format::replace_number(
+                    text.as_str(),
+                    "percent",
+                    percent as f64,
+                    percent.to_string().as_str(),
+                );
+                Ok(text
+                    .replace("%mount%", mount.path.as_str())
+                    .replace("%inodes_used%", inodes_used.to_string().as_str())
+                    .replace(
+                        "%inodes_avail%",
+                        inodes_avail.to_string().as_str(),
+                    )
+                    .replace(
+                        "%inodes_total%",
+                        inodes_total.to_string().as_str(),
+                    )
+                    .replace(
+                        "%inodes_percent%",
+                        inodes_percent.to_string().as_str(),
+                    ))
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join(self.separator);
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::Default(paused, self.waker.clone()),
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Storage {
+    /// Configuration options:
+    ///
+    /// - `mountpoints`: the mountpoints to check. Each entry is either a path
+    ///   or a table with a `path` and optionally its own `format` and
+    ///   `format_critical`, which override the panel-wide formats for that
+    ///   mountpoint.
+    ///   - type: Array of Strings or Tables
+    ///   - default: `["/"]`
+    /// - `format`: the format string for each mountpoint
+    ///   - type: String
+    ///   - default: `%mount%: %percent%%`
+    ///   - formatting options: `%mount%`, `%used%`, `%avail%`, `%total%`,
+    ///     `%percent%`, `%inodes_used%`, `%inodes_avail%`, `%inodes_total%`,
+    ///     `%inodes_percent%`, `%stack%` (a bar split into used, reserved, and
+    ///     available space; see [`StackedBar::parse`]). `%used%`, `%avail%`,
+    ///     `%total%`, and `%percent%` accept modifiers like
+    ///     `%used:.2;unit=si%`; see [`format::replace_number`].
+    /// - `format_critical`: the format string for each mountpoint whose usage
+    ///   is at or above `threshold`
+    ///   - type: String
+    ///   - default: the value of `format`
+    ///   - formatting options: same as `format`
+    /// - `threshold`: the percentage at which `format_critical` is used
+    ///   - type: u64
+    ///   - default: 90
+    /// - `separator`: the string to put between mountpoints
+    ///   - type: String
+    ///   - default: ` ` (one space)
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 10
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = StorageBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        if let Some(threshold) = remove_uint_from_config("threshold", table) {
+            builder.threshold(threshold);
+        }
+        builder.separator(
+            remove_string_from_config("separator", table)
+                .unwrap_or_else(|| String::from(" "))
+                .leak(),
+        );
+
+        let common = PanelCommon::parse_common(table)?;
+        let format: &'static str =
+            PanelCommon::parse_format(table, "", "%mount%: %percent%%").leak();
+        let format_critical: &'static str =
+            remove_string_from_config("format_critical", table)
+                .map_or(format, |f| f.leak());
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        let mounts = remove_array_from_config("mountpoints", table)
+            .unwrap_or_else(|| vec![Value::from("/")])
+            .into_iter()
+            .filter_map(|mount| {
+                mount.clone().into_string().map_or_else(
+                    |_| {
+                        mount.into_table().map_or_else(
+                            |e| {
+                                log::warn!("Ignoring invalid mountpoint: {e}");
+                                None
+                            },
+                            |mut table| {
+                                let Some(path) = remove_string_from_config(
+                                    "path", &mut table,
+                                ) else {
+                                    log::warn!(
+                                        "Ignoring mountpoint without a path"
+                                    );
+                                    return None;
+                                };
+                                Some(Mount {
+                                    path,
+                                    format: remove_string_from_config(
+                                        "format", &mut table,
+                                    )
+                                    .map_or(format, |f| f.leak()),
+                                    format_critical: remove_string_from_config(
+                                        "format_critical",
+                                        &mut table,
+                                    )
+                                    .map_or(format_critical, |f| f.leak()),
+                                })
+                            },
+                        )
+                    },
+                    |path| {
+                        Some(Mount {
+                            path,
+                            format,
+                            format_critical,
+                        })
+                    },
+                )
+            })
+            .collect();
+
+        builder.mounts(mounts);
+        builder.common(common);
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+        builder.stack(StackedBar::parse(table));
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let paused = Arc::new(Mutex::new(false));
+
+        let stream = ManagedIntervalStream::builder()
+            .duration(self.interval)
+            .paused(paused.clone())
+            .waker(self.waker.clone())
+            .build()?
+            .map(move |_| self.draw(&cr, height, paused.clone()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+fn percentage(part: u64, total: u64) -> u64 {
+    if total == 0 {
+        0
+    } else {
+        (part * 100).div_ceil(total)
+    }
+}
@@ -0,0 +1,334 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_float_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, PanelConfig, PanelStream, Ramp,
+};
+
+/// The Julian date of 2000-01-01 12:00 UTC
+const J2000: f64 = 2_451_545.0;
+/// The Julian date of the Unix epoch
+const UNIX_EPOCH: f64 = 2_440_587.5;
+
+array_to_struct!(SunFormats, day, night);
+array_to_struct!(SunRamps, day, night);
+
+/// A sunrise or sunset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Rise,
+    Set,
+}
+
+/// What the sun does on one day at some location.
+#[derive(Debug, Clone, Copy)]
+enum Day {
+    Normal {
+        rise: DateTime<Utc>,
+        set: DateTime<Utc>,
+    },
+    /// The sun never sets
+    PolarDay,
+    /// The sun never rises
+    PolarNight,
+}
+
+impl Day {
+    /// Calculates sunrise and sunset with the sunrise equation, which is
+    /// accurate to within a minute or two away from the poles.
+    fn new(date: NaiveDate, latitude: f64, longitude: f64) -> Self {
+        let n = (date - NaiveDate::from_ymd_opt(2000, 1, 1).unwrap()).num_days()
+            as f64;
+        // mean solar noon
+        let j = n - longitude / 360.0;
+        // solar mean anomaly
+        let m = 0.985_600_28f64.mul_add(j, 357.5291).rem_euclid(360.0);
+        let m_rad = m.to_radians();
+        // equation of the center
+        let c = 0.0003f64.mul_add(
+            (3.0 * m_rad).sin(),
+            1.9148f64.mul_add(m_rad.sin(), 0.02 * (2.0 * m_rad).sin()),
+        );
+        // ecliptic longitude
+        let lambda = (m + c + 180.0 + 102.9372).rem_euclid(360.0).to_radians();
+        let transit = 0.0053f64
+            .mul_add(m_rad.sin(), J2000 + j - 0.0069 * (2.0 * lambda).sin());
+        let declination = (lambda.sin() * 23.4397f64.to_radians().sin()).asin();
+        let latitude = latitude.to_radians();
+        // the sun's center is 0.833 degrees below the horizon at sunrise,
+        // accounting for refraction and the size of its disk
+        let cos_hour_angle = ((-0.833f64).to_radians().sin()
+            - latitude.sin() * declination.sin())
+            / (latitude.cos() * declination.cos());
+
+        if cos_hour_angle > 1.0 {
+            Self::PolarNight
+        } else if cos_hour_angle < -1.0 {
+            Self::PolarDay
+        } else {
+            let offset = cos_hour_angle.acos().to_degrees() / 360.0;
+            Self::Normal {
+                rise: from_julian(transit - offset),
+                set: from_julian(transit + offset),
+            }
+        }
+    }
+}
+
+fn from_julian(date: f64) -> DateTime<Utc> {
+    let secs = (date - UNIX_EPOCH) * 86400.0;
+    DateTime::from_timestamp(secs as i64, 0).unwrap_or_default()
+}
+
+/// Where `now` falls between sunrises and sunsets.
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    /// Whether the sun is up
+    day: bool,
+    /// How far through the current day or night `now` is, from 0.0 to 1.0
+    progress: f64,
+    /// The next transition, if there is one in the next few days
+    next: Option<DateTime<Utc>>,
+    next_rise: Option<DateTime<Utc>>,
+    next_set: Option<DateTime<Utc>>,
+}
+
+impl Position {
+    fn new(now: DateTime<Utc>, latitude: f64, longitude: f64) -> Self {
+        let today = now.date_naive();
+        let mut events = Vec::new();
+        let mut polar_day = false;
+        for offset in -2..=2 {
+            match Day::new(today + TimeDelta::days(offset), latitude, longitude)
+            {
+                Day::Normal { rise, set } => {
+                    events.push((rise, Kind::Rise));
+                    events.push((set, Kind::Set));
+                }
+                Day::PolarDay if offset == 0 => polar_day = true,
+                Day::PolarDay | Day::PolarNight => {}
+            }
+        }
+        events.sort_by_key(|(time, _)| *time);
+
+        let last = events.iter().rev().find(|(time, _)| *time <= now);
+        let next = events.iter().find(|(time, _)| *time > now);
+        let day = last.map_or_else(
+            || next.map_or(polar_day, |(_, kind)| *kind == Kind::Set),
+            |(_, kind)| *kind == Kind::Rise,
+        );
+        let progress = match (last, next) {
+            (Some((last, _)), Some((next, _))) => {
+                (now - *last).num_seconds() as f64
+                    / (*next - *last).num_seconds().max(1) as f64
+            }
+            _ => 0.5,
+        };
+        let find = |kind| {
+            events
+                .iter()
+                .find(|(time, k)| *time > now && *k == kind)
+                .map(|(time, _)| *time)
+        };
+
+        Self {
+            day,
+            progress,
+            next: next.map(|(time, _)| *time),
+            next_rise: find(Kind::Rise),
+            next_set: find(Kind::Set),
+        }
+    }
+}
+
+/// Formats the time until a transition, e.g. `3h 12m` or `5m`.
+fn until(delta: TimeDelta) -> String {
+    let minutes = delta.num_minutes().max(0);
+    let (hours, minutes) = (minutes / 60, minutes % 60);
+    if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// Displays the time until the next sunrise or sunset at a given location,
+/// calculated locally.
+///
+/// `%ramp%` shows how far through the day or night it is, using `ramp_day`
+/// from sunrise to sunset and `ramp_night` from sunset to sunrise. Near the
+/// poles, where the sun may not rise or set for days, the times are empty.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Sun {
+    name: &'static str,
+    latitude: f64,
+    longitude: f64,
+    #[builder(default = "Duration::from_secs(30)")]
+    interval: Duration,
+    #[builder(default = r#"String::from("%H:%M")"#)]
+    time_format: String,
+    formats: SunFormats<String>,
+    ramps: SunRamps<Ramp>,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Sun {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let now = Utc::now();
+        let position = Position::new(now, self.latitude, self.longitude);
+        let (format, ramp) = if position.day {
+            (&self.formats.day, &self.ramps.day)
+        } else {
+            (&self.formats.night, &self.ramps.night)
+        };
+        let time = |time: Option<DateTime<Utc>>| {
+            time.map_or_else(String::new, |time| {
+                time.with_timezone(&Local)
+                    .format(self.time_format.as_str())
+                    .to_string()
+            })
+        };
+
+        let text = format
+            .replace(
+                "%until%",
+                position
+                    .next
+                    .map_or_else(String::new, |next| until(next - now))
+                    .as_str(),
+            )
+            .replace("%time%", time(position.next).as_str())
+            .replace("%sunrise%", time(position.next_rise).as_str())
+            .replace("%sunset%", time(position.next_set).as_str())
+            .replace(
+                "%ramp%",
+                ramp.choose(position.progress, 0.0, 1.0).as_str(),
+            );
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Sun {
+    /// Configuration options:
+    ///
+    /// - `latitude`: the latitude of the location, north positive
+    ///   - type: f64
+    ///   - default: none
+    /// - `longitude`: the longitude of the location, east positive
+    ///   - type: f64
+    ///   - default: none
+    /// - `interval`: how often to update, in seconds
+    ///   - type: u64
+    ///   - default: 30
+    /// - `time_format`: the format of `%time%`, `%sunrise%`, and `%sunset%`.
+    ///   See [`chrono::format::strftime`] for details.
+    ///   - type: String
+    ///   - default: `%H:%M`
+    /// - `format_day`: the format string while the sun is up
+    ///   - type: String
+    ///   - default: `sunset in %until%`
+    ///   - formatting options: `%until%` (e.g. `3h 12m`), `%time%` (the time
+    ///     of the next sunrise or sunset), `%sunrise%`, `%sunset%`, `%ramp%`
+    /// - `format_night`: the format string while the sun is down
+    ///   - type: String
+    ///   - default: `sunrise in %until%`
+    ///   - formatting options: the same as `format_day`
+    /// - `ramp_day`: A string specifying the ramp to show how far it is from
+    ///   sunrise to sunset. See [`Ramp::parse`] for details.
+    /// - `ramp_night`: A string specifying the ramp to show how far it is
+    ///   from sunset to sunrise. See [`Ramp::parse`] for details.
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = SunBuilder::default();
+
+        builder.name(name);
+        let latitude = remove_float_from_config("latitude", table)
+            .ok_or_else(|| anyhow!("{name}: latitude is not set"))?;
+        let longitude = remove_float_from_config("longitude", table)
+            .ok_or_else(|| anyhow!("{name}: longitude is not set"))?;
+        builder.latitude(latitude.clamp(-90.0, 90.0));
+        builder.longitude(longitude);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(format) = remove_string_from_config("time_format", table) {
+            builder.time_format(format);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_day", "_night"],
+            &["sunset in %until%", "sunrise in %until%"],
+        );
+        let ramps = PanelCommon::parse_ramps(table, &["_day", "_night"]);
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.formats(SunFormats::new(formats));
+        builder.ramps(SunRamps::new(ramps));
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let stream = IntervalStream::new(interval(self.interval))
+            .map(move |_| self.draw(&cr, height));
+
+        Ok((Box::pin(stream), None))
+    }
+}
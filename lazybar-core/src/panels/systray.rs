@@ -56,8 +56,11 @@ impl SortMethod {
     }
 }
 
-/// Display icons from some applications. See
+/// Display icons from some applications using the XEmbed protocol. See
 /// <https://specifications.freedesktop.org/systemtray-spec/> for details.
+///
+/// This panel can be configured with either `type = "systray"` or
+/// `type = "tray"`.
 #[derive(Debug, Builder, Clone)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
@@ -122,7 +125,11 @@ impl Systray {
         PanelDrawInfo::new(
             (self.width as i32, self.height as i32),
             self.common.dependence,
-            Box::new(move |_, x| {
+            Box::new(move |cr, x| {
+                // the bar translates the context to the panel's position,
+                // which may not be in the first row
+                let (_, y) = cr.user_to_device(0.0, 0.0);
+
                 if let Some((window, mapped)) = pending {
                     config_conn.reparent_window(
                         window,
@@ -140,7 +147,7 @@ impl Systray {
 
                 config_conn.configure_window(
                     tray,
-                    &ConfigureWindowAux::new().x(x as i32).y(0),
+                    &ConfigureWindowAux::new().x(x as i32).y(y as i32),
                 )?;
 
                 Ok(())
@@ -323,7 +330,7 @@ impl PanelConfig for Systray {
             InternedAtoms::get(&self.conn, "_NET_SYSTEM_TRAY_VISUAL")?;
         let xembed_atom = InternedAtoms::get(&self.conn, "_XEMBED")?;
 
-        let bar_info = bar::BAR_INFO.get().unwrap();
+        let bar_info = bar::bar_info().unwrap();
         let bar_visual = bar_info.visual;
 
         let screen = self
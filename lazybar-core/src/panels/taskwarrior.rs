@@ -0,0 +1,359 @@
+use std::{collections::HashMap, process::Command, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{TimeDelta, Utc};
+use config::{Config, Value};
+use derive_builder::Builder;
+use serde::Deserialize;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_string_from_config, remove_uint_from_config, Attrs, Highlight,
+    PanelConfig, PanelStream,
+};
+
+/// The format of dates in `task export`, which sorts chronologically
+const DATE_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+array_to_struct!(TaskwarriorFormats, tasks, empty);
+
+#[derive(Debug, Clone, Deserialize)]
+struct Task {
+    uuid: String,
+    description: String,
+    due: Option<String>,
+    #[serde(default)]
+    urgency: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Summary {
+    pending: usize,
+    due: usize,
+    overdue: usize,
+    top: Option<Task>,
+}
+
+impl Summary {
+    fn new(tasks: Vec<Task>, due_within: TimeDelta) -> Self {
+        let now = Utc::now();
+        let soon = (now + due_within).format(DATE_FORMAT).to_string();
+        let now = now.format(DATE_FORMAT).to_string();
+
+        let mut summary = Self {
+            pending: tasks.len(),
+            ..Self::default()
+        };
+        for task in tasks {
+            match task.due.as_deref() {
+                Some(due) if due < now.as_str() => summary.overdue += 1,
+                Some(due) if due < soon.as_str() => summary.due += 1,
+                _ => {}
+            }
+            if summary
+                .top
+                .as_ref()
+                .map_or(true, |top| task.urgency > top.urgency)
+            {
+                summary.top = Some(task);
+            }
+        }
+        summary
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Summary(Result<Summary>),
+    Event(Event),
+}
+
+/// Displays the number of due and overdue tasks in
+/// [Taskwarrior](https://taskwarrior.org), along with the most urgent task.
+///
+/// Tasks are read with `task export`. The most urgent task can be marked as
+/// done with the `done` event, e.g. by setting `click_middle = "done"`.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Taskwarrior {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(60)")]
+    interval: Duration,
+    #[builder(default)]
+    filter: Vec<String>,
+    #[builder(default = "TimeDelta::days(7)")]
+    due_within: TimeDelta,
+    #[builder(default, setter(skip))]
+    summary: Option<Summary>,
+    formats: TaskwarriorFormats<String>,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Taskwarrior {
+    fn update(
+        &mut self,
+        update: Update,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::Summary(Ok(summary)) => {
+                self.summary = Some(summary);
+                None
+            }
+            Update::Summary(Err(e)) => {
+                // keep showing the last summary
+                log::warn!("{}: {e}", self.name);
+                None
+            }
+            Update::Event(event) => {
+                Some(self.process_event(event, refresh_send))
+            }
+        }
+    }
+
+    fn process_event(
+        &self,
+        event: Event,
+        refresh_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        match event {
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "done" => {
+                self.complete_top(refresh_send)
+            }
+            Event::Action(action) if action == "refresh" => {
+                Ok(refresh_send.send(())?)
+            }
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    /// Marks the task shown in `%top%` as done. The task is identified by its
+    /// UUID, so a task that became more urgent since the last check isn't
+    /// completed by mistake.
+    fn complete_top(&self, refresh_send: &UnboundedSender<()>) -> Result<()> {
+        let top = self
+            .summary
+            .as_ref()
+            .and_then(|summary| summary.top.as_ref())
+            .ok_or_else(|| anyhow!("No task to mark as done"))?;
+        log::info!("{}: marking {:?} as done", self.name, top.description);
+
+        let uuid = top.uuid.clone();
+        let refresh_send = refresh_send.clone();
+        let name = self.name;
+        cleanup::spawn_blocking(move || {
+            match cleanup::command_output(
+                Command::new("task")
+                    .arg("rc.confirmation=off")
+                    .arg(uuid)
+                    .arg("done"),
+            ) {
+                Ok(output) if !output.status.success() => log::warn!(
+                    "{name}: task done failed: {}",
+                    String::from_utf8_lossy(output.stderr.as_slice()).trim()
+                ),
+                Ok(_) => {}
+                Err(e) => log::warn!("{name}: failed to run task: {e}"),
+            }
+            let _ = refresh_send.send(());
+        });
+        Ok(())
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let text = match self.summary {
+            None => String::new(),
+            Some(ref summary) => {
+                let format = if summary.pending == 0 {
+                    &self.formats.empty
+                } else {
+                    &self.formats.tasks
+                };
+                let (top, urgency) =
+                    summary.top.as_ref().map_or_else(Default::default, |top| {
+                        (
+                            glib::markup_escape_text(top.description.as_str())
+                                .to_string(),
+                            format!("{:.1}", top.urgency),
+                        )
+                    });
+                format
+                    .replace("%pending%", summary.pending.to_string().as_str())
+                    .replace("%due%", summary.due.to_string().as_str())
+                    .replace("%overdue%", summary.overdue.to_string().as_str())
+                    .replace("%urgency%", urgency.as_str())
+                    .replace("%top%", top.as_str())
+            }
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+/// Reads the pending tasks that match `filter`.
+fn export(filter: &[String]) -> Result<Vec<Task>> {
+    let output = cleanup::command_output(
+        Command::new("task")
+            .args(filter)
+            .arg("status:pending")
+            .arg("export"),
+    )?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "task export failed: {}",
+            String::from_utf8_lossy(output.stderr.as_slice()).trim()
+        ));
+    }
+    Ok(serde_json::from_slice(output.stdout.as_slice())?)
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Taskwarrior {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 60
+    /// - `filter`: a Taskwarrior filter to narrow down the tasks, e.g.
+    ///   `project:work +next`. Only pending tasks are ever counted.
+    ///   - type: String
+    ///   - default: none
+    /// - `due_days`: how many days ahead a task has to be due to count toward
+    ///   `%due%`
+    ///   - type: u64
+    ///   - default: 7
+    /// - `format_tasks`: the format string when there are pending tasks
+    ///   - type: String
+    ///   - default: `%overdue%/%due% %top%`
+    ///   - formatting options: `%pending%`, `%due%` (tasks due within
+    ///     `due_days` that aren't overdue), `%overdue%`, `%top%` (the
+    ///     description of the most urgent task), `%urgency%` (the urgency of
+    ///     the most urgent task)
+    /// - `format_empty`: the format string when there are no pending tasks
+    ///   - type: String
+    ///   - default: empty
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. The supported events are `done`
+    ///   (mark the most urgent task as done) and `refresh`.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = TaskwarriorBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(filter) = remove_string_from_config("filter", table) {
+            builder
+                .filter(filter.split_whitespace().map(String::from).collect());
+        }
+        if let Some(days) = remove_uint_from_config("due_days", table) {
+            builder.due_within(TimeDelta::days(days as i64));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_tasks", "_empty"],
+            &["%overdue%/%due% %top%", ""],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.formats(TaskwarriorFormats::new(formats));
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let (refresh_send, refresh_recv) = unbounded_channel();
+        let filter = self.filter.clone();
+        let due_within = self.due_within;
+        let summaries = IntervalStream::new(interval(self.interval))
+            .map(|_| ())
+            .merge(UnboundedReceiverStream::new(refresh_recv))
+            .then(move |()| {
+                let filter = filter.clone();
+                async move {
+                    Update::Summary(
+                        cleanup::spawn_blocking(move || export(&filter))
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|tasks| tasks)
+                            .map(|tasks| Summary::new(tasks, due_within)),
+                    )
+                }
+            });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = summaries.merge(events).map(move |update| {
+            if let Some(result) = self.update(update, &refresh_send) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
@@ -1,6 +1,8 @@
 use std::{
+    collections::HashSet,
     fs::File,
     io::Read,
+    path::{Path, PathBuf},
     rc::Rc,
     sync::{Arc, Mutex},
     time::Duration,
@@ -14,23 +16,44 @@ use tokio_stream::StreamExt;
 
 use crate::{
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_panel, PanelCommon, ShowHide},
     ipc::ChannelEndpoint,
-    remove_uint_from_config, Attrs, Highlight, ManagedIntervalStream,
+    remove_array_from_config, remove_string_from_config,
+    remove_uint_from_config, state, Attrs, Highlight, ManagedIntervalStream,
     PanelConfig, PanelStream, Ramp,
 };
 
-/// Displays the temperature of a provided thermal zone.
+/// Shown in place of a reading from a source that can't be read
+const UNAVAILABLE: &str = "?";
+
+/// How to combine the readings from multiple sources into `%temp%`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum Aggregate {
+    /// The highest reading
+    #[default]
+    Max,
+    /// The mean of all readings, rounded down
+    Avg,
+    /// Every reading, separated by the panel's separator
+    List,
+}
+
+/// Displays the temperature of one or more thermal zones or hwmon sensors.
 ///
 /// The thermal zone meanings are listed in
-/// `/sys/class/thermal/thermal_zone*/type`.
+/// `/sys/class/thermal/thermal_zone*/type`, and hwmon sensors are found at
+/// `/sys/class/hwmon/hwmon*/temp*_input`.
 #[derive(Debug, Builder, Clone)]
 #[builder_struct_attr(allow(missing_docs))]
 #[builder_impl_attr(allow(missing_docs))]
 pub struct Temp {
     name: &'static str,
-    #[builder(default = "0")]
-    zone: usize,
+    #[builder(default = r#"vec![zone_path(0)]"#)]
+    sources: Vec<PathBuf>,
+    #[builder(default)]
+    aggregate: Aggregate,
+    #[builder(default = r#"String::from(" ")"#)]
+    separator: String,
     #[builder(default = "Duration::from_secs(10)")]
     interval: Duration,
     #[builder(default)]
@@ -41,36 +64,81 @@ pub struct Temp {
     highlight: Option<Highlight>,
     ramp: Ramp,
     common: PanelCommon,
+    /// The indices of the sources that couldn't be read last time, so that
+    /// each failure is only logged once
+    #[builder(default)]
+    failing: HashSet<usize>,
 }
 
 impl Temp {
     fn draw(
-        &self,
+        &mut self,
         cr: &Rc<cairo::Context>,
         height: i32,
         paused: Arc<Mutex<bool>>,
     ) -> Result<PanelDrawInfo> {
-        let mut temp = String::new();
-        File::open(format!(
-            "/sys/class/thermal/thermal_zone{}/temp",
-            self.zone
-        ))?
-        .read_to_string(&mut temp)?;
+        let temps = self
+            .sources
+            .iter()
+            .enumerate()
+            .map(|(i, path)| match read_temp(path) {
+                Ok(temp) => {
+                    self.failing.remove(&i);
+                    Some(temp)
+                }
+                Err(e) => {
+                    if self.failing.insert(i) {
+                        log::warn!(
+                            "Failed to read a temperature from {}: {e}",
+                            path.display()
+                        );
+                    }
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        let readings = temps.iter().flatten().copied().collect::<Vec<_>>();
+        let texts = temps
+            .iter()
+            .map(|temp| {
+                temp.map_or_else(
+                    || String::from(UNAVAILABLE),
+                    |t| t.to_string(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let max = readings.iter().copied().max().unwrap_or_default();
+        let temp = match self.aggregate {
+            Aggregate::List => texts.join(self.separator.as_str()),
+            _ if readings.is_empty() => String::from(UNAVAILABLE),
+            Aggregate::Max => max.to_string(),
+            Aggregate::Avg => (readings.iter().sum::<u32>()
+                / readings.len() as u32)
+                .to_string(),
+        };
 
-        let temp = temp.trim().parse::<u32>()? / 1000;
+        state::publish(self.name, "temp", temp.as_str());
+        for (i, temp) in texts.iter().enumerate() {
+            state::publish(self.name, format!("temp{i}").as_str(), temp);
+        }
 
-        let text = self
+        let mut text = self
             .format
-            .replace("%temp%", temp.to_string().as_str())
-            .replace("%ramp%", self.ramp.choose(temp, 0, 200).as_str());
+            .replace("%temp%", temp.as_str())
+            .replace("%ramp%", self.ramp.choose(max, 0, 200).as_str());
+        for (i, temp) in texts.iter().enumerate() {
+            text = text.replace(format!("%temp{i}%").as_str(), temp);
+        }
 
-        draw_common(
+        let max = f64::from(max);
+        let attrs = self.common.thresholds.attrs(&self.attrs, max);
+        draw_panel(
             cr,
             text.as_str(),
-            &self.attrs,
-            self.common.dependence,
+            &attrs.ramped(max, 0.0, 200.0),
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Default(paused, self.waker.clone()),
         )
@@ -84,19 +152,37 @@ impl PanelConfig for Temp {
     /// - `format`: the format string
     ///   - type: String
     ///   - default: `TEMP: %temp%`
-    ///   - formatting options: `%temp%`
+    ///   - formatting options: `%temp%`, `%ramp%`, and `%temp<n>%` for the
+    ///     reading of the nth source (starting at 0). Sources that can't be
+    ///     read are shown as `?` and left out of `%temp%`.
     /// - `interval`: how long to wait in seconds between each check
     ///   - type: u64
     ///   - default: 10
-    /// - `zone`: the thermal zone to check
+    /// - `zone`: the thermal zone to check (ignored if `sources` is present)
     ///   - type: u64
     ///   - default: 0
+    /// - `sources`: the thermal zones and hwmon sensors to check. Each entry
+    ///   is either a thermal zone number or a path to a file containing a
+    ///   temperature in millidegrees Celsius, such as
+    ///   `/sys/class/hwmon/hwmon2/temp1_input`.
+    ///   - type: Array of Strings or u64s
+    ///   - default: none
+    /// - `aggregate`: how to combine the sources into `%temp%`
+    ///   - type: String - `max`, `avg`, or `list`
+    ///   - default: `max`
+    /// - `separator`: the string to put between readings when `aggregate` is
+    ///   `list`
+    ///   - type: String
+    ///   - default: ` ` (one space)
     /// - `attrs`: A string specifying the attrs for the panel. See
-    ///   [`Attrs::parse`] for details.
+    ///   [`Attrs::parse`] for details. A `fg_ramp` uses the highest
+    ///   temperature from 0 to 200, like `ramp`. `warn_above` and
+    ///   `crit_above` are compared with the highest temperature too (see
+    ///   [`Thresholds::parse`][crate::threshold::Thresholds::parse]).
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
     /// - `ramp`: A string specifying the ramp to show internal temperature. See
-    ///   [`Ramp::parse`] for details.
+    ///   [`Ramp::parse`] for details. The highest reading is used.
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
@@ -109,8 +195,45 @@ impl PanelConfig for Temp {
         if let Some(interval) = remove_uint_from_config("interval", table) {
             builder.interval(Duration::from_secs(interval));
         }
-        if let Some(zone) = remove_uint_from_config("zone", table) {
-            builder.zone(zone as usize);
+        if let Some(sources) = remove_array_from_config("sources", table) {
+            builder.sources(
+                sources
+                    .into_iter()
+                    .filter_map(|source| {
+                        source.clone().into_uint().map_or_else(
+                            |_| {
+                                source.into_string().map_or_else(
+                                    |e| {
+                                        log::warn!(
+                                            "Ignoring invalid source: {e}"
+                                        );
+                                        None
+                                    },
+                                    |path| Some(PathBuf::from(path)),
+                                )
+                            },
+                            |zone| Some(zone_path(zone)),
+                        )
+                    })
+                    .collect(),
+            );
+            table.remove("zone");
+        } else if let Some(zone) = remove_uint_from_config("zone", table) {
+            builder.sources(vec![zone_path(zone)]);
+        }
+        if let Some(aggregate) = remove_string_from_config("aggregate", table) {
+            builder.aggregate(match aggregate.as_str() {
+                "avg" => Aggregate::Avg,
+                "list" => Aggregate::List,
+                "max" => Aggregate::Max,
+                _ => {
+                    log::warn!("Unknown aggregate {aggregate}, using max");
+                    Aggregate::Max
+                }
+            });
+        }
+        if let Some(separator) = remove_string_from_config("separator", table) {
+            builder.separator(separator);
         }
 
         let common = PanelCommon::parse_common(table)?;
@@ -153,3 +276,14 @@ impl PanelConfig for Temp {
         Ok((Box::pin(stream), None))
     }
 }
+
+/// Reads a temperature in millidegrees Celsius and returns it in degrees.
+fn read_temp(path: &Path) -> Result<u32> {
+    let mut temp = String::new();
+    File::open(path)?.read_to_string(&mut temp)?;
+    Ok(temp.trim().parse::<u32>()? / 1000)
+}
+
+fn zone_path(zone: u64) -> PathBuf {
+    PathBuf::from(format!("/sys/class/thermal/thermal_zone{zone}/temp"))
+}
@@ -0,0 +1,368 @@
+use std::{collections::HashMap, rc::Rc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use reqwest::{
+    header::{HeaderMap, HeaderValue, USER_AGENT},
+    Client,
+};
+use tokio::time::interval;
+use tokio_stream::{wrappers::IntervalStream, StreamExt};
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_array_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+const COINGECKO: &str = "https://api.coingecko.com/api/v3/simple/price\
+                         ?ids=%symbols%&vs_currencies=%currency%\
+                         &include_24hr_change=true";
+
+#[derive(Debug, Clone)]
+struct Quote {
+    price: f64,
+    change: Option<f64>,
+}
+
+#[derive(Debug)]
+enum Update {
+    Quotes(Result<Vec<Option<Quote>>>),
+    Rotate,
+}
+
+/// Displays prices fetched from an HTTP API, cycling through multiple symbols.
+///
+/// By default, prices of cryptocurrencies are fetched from
+/// [CoinGecko](https://www.coingecko.com/en/api), but any API that returns
+/// JSON can be used by setting `url`, `price_path`, and `change_path`.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Ticker {
+    name: &'static str,
+    symbols: Vec<String>,
+    labels: Vec<String>,
+    currency: String,
+    url: String,
+    price_path: String,
+    change_path: String,
+    #[builder(default = "Duration::from_secs(300)")]
+    interval: Duration,
+    #[builder(default = "Duration::from_secs(10)")]
+    rotate: Duration,
+    #[builder(default = "2")]
+    precision: usize,
+    #[builder(default)]
+    quotes: Vec<Option<Quote>>,
+    #[builder(default)]
+    idx: usize,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Ticker {
+    fn update(&mut self, update: Update) {
+        match update {
+            // keep showing the old prices if a request fails
+            Update::Quotes(Ok(quotes)) => self.quotes = quotes,
+            Update::Quotes(Err(e)) => {
+                log::warn!("Failed to fetch prices: {e}");
+            }
+            Update::Rotate => {
+                self.idx = (self.idx + 1) % self.symbols.len().max(1);
+            }
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let quote = self.quotes.get(self.idx).cloned().flatten();
+        let attrs = match quote.as_ref().and_then(|quote| quote.change) {
+            Some(change) => self.common.thresholds.attrs(&self.attrs, change),
+            None => self.attrs.restyled(),
+        };
+        let text = match quote {
+            Some(quote) => {
+                let precision = self.precision;
+                let change = quote
+                    .change
+                    .map_or_else(String::new, |change| format!("{change:+.2}"));
+                self.format
+                    .replace("%symbol%", self.labels[self.idx].as_str())
+                    .replace("%currency%", self.currency.as_str())
+                    .replace(
+                        "%price%",
+                        format!("{:.precision$}", quote.price).as_str(),
+                    )
+                    .replace("%change%", change.as_str())
+            }
+            None => String::new(),
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Ticker {
+    /// Configuration options:
+    ///
+    /// - `symbols`: the symbols to fetch prices for. With the default `url`,
+    ///   these are CoinGecko ids like `bitcoin` or `ethereum`.
+    ///   - type: Array of Strings
+    ///   - default: `["bitcoin"]`
+    /// - `labels`: the names to show for each symbol in `%symbol%`. This must
+    ///   be the same length as `symbols` if it is set.
+    ///   - type: Array of Strings
+    ///   - default: the values of `symbols`
+    /// - `currency`: the currency that prices are shown in
+    ///   - type: String
+    ///   - default: `usd`
+    /// - `url`: the URL to fetch prices from. `%symbols%` is replaced with the
+    ///   comma-separated list of symbols, and `%currency%` is replaced with
+    ///   `currency`.
+    ///   - type: String
+    ///   - default: the CoinGecko simple price endpoint
+    /// - `price_path`: the dot-separated path to each symbol's price in the
+    ///   response. `%symbol%` and `%currency%` are replaced as above.
+    ///   - type: String
+    ///   - default: `%symbol%.%currency%`
+    /// - `change_path`: the dot-separated path to each symbol's change (in
+    ///   percent) in the response. If the path doesn't exist, `%change%` is
+    ///   empty.
+    ///   - type: String
+    ///   - default: `%symbol%.%currency%_24h_change`
+    /// - `interval`: how often to fetch prices, in seconds
+    ///   - type: u64
+    ///   - default: 300
+    /// - `rotate`: how often to switch to the next symbol, in seconds
+    ///   - type: u64
+    ///   - default: 10
+    /// - `precision`: the number of decimal places to show in `%price%`
+    ///   - type: u64
+    ///   - default: 2
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%symbol% %price% (%change%%)`
+    ///   - formatting options: `%symbol%`, `%currency%`, `%price%`, `%change%`
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details. `warn_above`, `crit_above`,
+    ///   `warn_below`, and `crit_below` use the change in percent, so e.g.
+    ///   `crit_below = -5` switches to the critical attrs when the price has
+    ///   fallen by more than 5% (see
+    ///   [`Thresholds::parse`][crate::threshold::Thresholds::parse]).
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = TickerBuilder::default();
+
+        builder.name(name);
+
+        let symbols = remove_array_from_config("symbols", table)
+            .map(|symbols| {
+                symbols
+                    .into_iter()
+                    .filter_map(|v| v.into_string().ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_else(|| vec![String::from("bitcoin")]);
+        let labels = remove_array_from_config("labels", table)
+            .map(|labels| {
+                labels
+                    .into_iter()
+                    .filter_map(|v| v.into_string().ok())
+                    .collect::<Vec<_>>()
+            })
+            .filter(|labels| {
+                let valid = labels.len() == symbols.len();
+                if !valid {
+                    log::warn!("labels and symbols have different lengths");
+                }
+                valid
+            })
+            .unwrap_or_else(|| symbols.clone());
+        builder.symbols(symbols);
+        builder.labels(labels);
+
+        builder.currency(
+            remove_string_from_config("currency", table)
+                .unwrap_or_else(|| String::from("usd")),
+        );
+        builder.url(
+            remove_string_from_config("url", table)
+                .unwrap_or_else(|| String::from(COINGECKO)),
+        );
+        builder.price_path(
+            remove_string_from_config("price_path", table)
+                .unwrap_or_else(|| String::from("%symbol%.%currency%")),
+        );
+        builder.change_path(
+            remove_string_from_config("change_path", table).unwrap_or_else(
+                || String::from("%symbol%.%currency%_24h_change"),
+            ),
+        );
+
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(rotate) = remove_uint_from_config("rotate", table) {
+            builder.rotate(Duration::from_secs(rotate.max(1)));
+        }
+        if let Some(precision) = remove_uint_from_config("precision", table) {
+            builder.precision(precision as usize);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(
+            table,
+            "",
+            "%symbol% %price% (%change%%)",
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(USER_AGENT, HeaderValue::from_static("lazybar"));
+        let client = Client::builder().default_headers(headers).build()?;
+
+        let fetcher = Fetcher {
+            client,
+            url: self
+                .url
+                .replace("%symbols%", self.symbols.join(",").as_str())
+                .replace("%currency%", self.currency.as_str()),
+            price_paths: self.paths(self.price_path.as_str()),
+            change_paths: self.paths(self.change_path.as_str()),
+        };
+
+        let quotes =
+            IntervalStream::new(interval(self.interval)).then(move |_| {
+                let fetcher = fetcher.clone();
+                async move { Update::Quotes(fetcher.fetch().await) }
+            });
+        let mut rotate = interval(self.rotate);
+        // the first tick completes immediately
+        rotate.reset();
+        let rotate = IntervalStream::new(rotate).map(|_| Update::Rotate);
+
+        let stream = quotes.merge(rotate).map(move |update| {
+            self.update(update);
+            self.draw(&cr, height)
+        });
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+impl Ticker {
+    fn paths(&self, template: &str) -> Vec<String> {
+        self.symbols
+            .iter()
+            .map(|symbol| {
+                template
+                    .replace("%symbol%", symbol.as_str())
+                    .replace("%currency%", self.currency.as_str())
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Fetcher {
+    client: Client,
+    url: String,
+    price_paths: Vec<String>,
+    change_paths: Vec<String>,
+}
+
+impl Fetcher {
+    async fn fetch(self) -> Result<Vec<Option<Quote>>> {
+        let body = self
+            .client
+            .get(self.url.as_str())
+            .send()
+            .await?
+            .error_for_status()?
+            .json::<serde_json::Value>()
+            .await?;
+
+        let quotes = self
+            .price_paths
+            .iter()
+            .zip(&self.change_paths)
+            .map(|(price, change)| {
+                let price = lookup(&body, price.as_str())?;
+                Some(Quote {
+                    price,
+                    change: lookup(&body, change.as_str()),
+                })
+            })
+            .collect::<Vec<_>>();
+
+        if quotes.iter().all(Option::is_none) {
+            Err(anyhow!("No prices found in response from {}", self.url))
+        } else {
+            Ok(quotes)
+        }
+    }
+}
+
+/// Follows a dot-separated path of object keys and array indices through a
+/// JSON value, returning the number at the end.
+fn lookup(value: &serde_json::Value, path: &str) -> Option<f64> {
+    path.split('.')
+        .try_fold(value, |value, key| match key.parse::<usize>() {
+            Ok(idx) if value.is_array() => value.get(idx),
+            _ => value.get(key),
+        })
+        .and_then(|value| {
+            value
+                .as_f64()
+                .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+        })
+}
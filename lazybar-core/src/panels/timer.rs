@@ -0,0 +1,349 @@
+use std::{
+    collections::HashMap,
+    rc::Rc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime};
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{sync::mpsc::unbounded_channel, time::interval};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    array_to_struct,
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+array_to_struct!(TimerFormats, running, stopped, done);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    /// Counts down to a point in time. Always running.
+    Target(DateTime<Local>),
+    /// Counts down from a duration.
+    Countdown(Duration),
+    /// Counts up from zero.
+    Stopwatch,
+}
+
+#[derive(Debug)]
+enum Update {
+    Tick,
+    Event(Event),
+}
+
+/// Counts down to a point in time, counts down from a fixed duration (e.g. a
+/// tea timer), or counts up like a stopwatch.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Timer {
+    name: &'static str,
+    mode: Mode,
+    #[builder(default)]
+    precision: usize,
+    /// The time counted before the timer was last stopped.
+    #[builder(default, setter(skip))]
+    elapsed: Duration,
+    /// When the timer was last started, if it is running.
+    #[builder(default, setter(skip))]
+    started: Option<Instant>,
+    formats: TimerFormats<String>,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Timer {
+    fn elapsed(&self) -> Duration {
+        self.elapsed + self.started.map_or(Duration::ZERO, |s| s.elapsed())
+    }
+
+    /// Returns the time to show, or [`None`] if a countdown has finished.
+    fn time(&self) -> Option<Duration> {
+        match self.mode {
+            Mode::Target(target) => (target - Local::now()).to_std().ok(),
+            Mode::Countdown(length) => length.checked_sub(self.elapsed()),
+            Mode::Stopwatch => Some(self.elapsed()),
+        }
+    }
+
+    const fn running(&self) -> bool {
+        matches!(self.mode, Mode::Target(_)) || self.started.is_some()
+    }
+
+    fn start(&mut self) {
+        if self.started.is_none() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    fn stop(&mut self) {
+        if let Some(started) = self.started.take() {
+            self.elapsed += started.elapsed();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.elapsed = Duration::ZERO;
+        if self.started.is_some() {
+            self.started = Some(Instant::now());
+        }
+    }
+
+    fn update(&mut self, update: Update) -> Option<Result<()>> {
+        match update {
+            Update::Tick => {
+                // stop counting once a countdown is done so that it can be
+                // reset to a stopped state
+                if matches!(self.mode, Mode::Countdown(_))
+                    && self.started.is_some()
+                    && self.time().is_none()
+                {
+                    self.stop();
+                }
+                None
+            }
+            Update::Event(event) => Some(self.process_event(event)),
+        }
+    }
+
+    fn process_event(&mut self, event: Event) -> Result<()> {
+        if matches!(self.mode, Mode::Target(_)) {
+            return match event {
+                Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => {
+                    Ok(())
+                }
+                Event::Action(_) => {
+                    Err(anyhow!("Timers with a target can't be controlled"))
+                }
+            };
+        }
+
+        match event {
+            Event::Mouse(event) => match event.button {
+                MouseButton::Left => self.toggle(),
+                MouseButton::Right => self.reset(),
+                _ => {}
+            },
+            Event::MouseEnter | Event::MouseLeave => {}
+            Event::Action(action) => match action.as_str() {
+                "start" => self.start(),
+                "stop" => self.stop(),
+                "toggle" => self.toggle(),
+                "reset" => self.reset(),
+                _ => return Err(anyhow!("Unknown event {action}")),
+            },
+        }
+        Ok(())
+    }
+
+    fn toggle(&mut self) {
+        if self.started.is_some() {
+            self.stop();
+        } else {
+            self.start();
+        }
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let text = match self.time() {
+            Some(time) => {
+                let format = if self.running() {
+                    &self.formats.running
+                } else {
+                    &self.formats.stopped
+                };
+                format.replace(
+                    "%time%",
+                    format_duration(time, self.precision).as_str(),
+                )
+            }
+            None => self.formats.done.clone(),
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Timer {
+    /// Configuration options:
+    ///
+    /// - `target`: the time to count down to, either in RFC 3339 format or as
+    ///   `YYYY-MM-DD HH:MM:SS` in local time. The timer is always running and
+    ///   can't be controlled.
+    ///   - type: String
+    ///   - default: none
+    /// - `duration`: the number of seconds to count down from. Ignored if
+    ///   `target` is set.
+    ///   - type: u64
+    ///   - default: none
+    /// - If neither `target` nor `duration` is set, the timer counts up from
+    ///   zero like a stopwatch.
+    /// - `autostart`: whether the timer starts running immediately
+    ///   - type: bool
+    ///   - default: false
+    /// - `precision`: the number of decimal places of seconds to show, from 0
+    ///   to 3. While the timer is running, the panel is redrawn this often (up
+    ///   to 100 times per second), so higher values use more CPU.
+    ///   - type: u64
+    ///   - default: 0
+    /// - `format_running`: the format string while the timer is running
+    ///   - type: String
+    ///   - default: `%time%`
+    ///   - formatting options: `%time%`, shown as `mm:ss`, or `h:mm:ss` if it
+    ///     is at least an hour
+    /// - `format_stopped`: the format string while the timer is stopped
+    ///   - type: String
+    ///   - default: `%time%`
+    ///   - formatting options: `%time%`
+    /// - `format_done`: the text shown when a countdown has finished
+    ///   - type: String
+    ///   - default: `done`
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel starts or
+    ///   stops the timer, and right clicking resets it. The supported events
+    ///   are `start`, `stop`, `toggle`, and `reset`.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = TimerBuilder::default();
+
+        builder.name(name);
+        let mode =
+            if let Some(target) = remove_string_from_config("target", table) {
+                Mode::Target(parse_target(target.as_str()).ok_or_else(
+                    || anyhow!("{name}: invalid target {target}"),
+                )?)
+            } else if let Some(duration) =
+                remove_uint_from_config("duration", table)
+            {
+                Mode::Countdown(Duration::from_secs(duration))
+            } else {
+                Mode::Stopwatch
+            };
+        builder.mode(mode);
+        if let Some(precision) = remove_uint_from_config("precision", table) {
+            builder.precision((precision as usize).min(3));
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let formats = PanelCommon::parse_formats(
+            table,
+            &["_running", "_stopped", "_done"],
+            &["%time%", "%time%", "done"],
+        );
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.formats(TimerFormats::new(formats));
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        let mut timer = builder.build()?;
+        if remove_bool_from_config("autostart", table).unwrap_or_default() {
+            timer.start();
+        }
+        Ok(timer)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+        // don't redraw more than 100 times per second
+        let period = Duration::from_millis(
+            (1000 / 10_u64.pow(self.precision as u32)).max(10),
+        );
+        let ticks = IntervalStream::new(interval(period)).map(|_| Update::Tick);
+
+        let mut first = true;
+        let stream = ticks.merge(events).filter_map(move |update| {
+            let redraw =
+                first || self.running() || matches!(update, Update::Event(_));
+            first = false;
+            if let Some(result) = self.update(update) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            redraw.then(|| self.draw(&cr, height))
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+fn parse_target(target: &str) -> Option<DateTime<Local>> {
+    DateTime::parse_from_rfc3339(target)
+        .map(|time| time.with_timezone(&Local))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(target, "%Y-%m-%d %H:%M:%S")
+                .ok()?
+                .and_local_timezone(Local)
+                .earliest()
+        })
+}
+
+fn format_duration(time: Duration, precision: usize) -> String {
+    let secs = time.as_secs();
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let mut text = if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes:02}:{seconds:02}")
+    };
+    if precision > 0 {
+        let fraction = time.subsec_millis() / 10_u32.pow(3 - precision as u32);
+        text.push_str(format!(".{fraction:0precision$}").as_str());
+    }
+    text
+}
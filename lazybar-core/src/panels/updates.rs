@@ -0,0 +1,356 @@
+use std::{
+    collections::HashMap, env, path::Path, process::Command, rc::Rc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedSender},
+    time::interval,
+};
+use tokio_stream::{
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    StreamExt,
+};
+
+use crate::{
+    bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
+    cleanup,
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config, Attrs, Highlight, PanelConfig, PanelStream,
+};
+
+/// The program used to check for updates
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Backend {
+    /// `checkupdates` from pacman-contrib
+    Pacman,
+    /// `apt list --upgradable`
+    Apt,
+    /// `dnf check-update`
+    Dnf,
+    /// A command that prints one line per update
+    Custom(String),
+}
+
+impl Backend {
+    /// Returns the first backend whose program is installed.
+    fn detect() -> Option<Self> {
+        [
+            ("checkupdates", Self::Pacman),
+            ("apt", Self::Apt),
+            ("dnf", Self::Dnf),
+        ]
+        .into_iter()
+        .find(|(program, _)| in_path(program))
+        .map(|(_, backend)| backend)
+    }
+
+    /// Runs the checker and returns the number of pending updates.
+    fn check(&self) -> Result<usize> {
+        let mut command = match self {
+            Self::Pacman => Command::new("checkupdates"),
+            Self::Apt => {
+                let mut command = Command::new("apt");
+                command.args(["list", "--upgradable"]);
+                command
+            }
+            Self::Dnf => {
+                let mut command = Command::new("dnf");
+                command.args(["check-update", "-q"]);
+                command
+            }
+            Self::Custom(cmd) => {
+                let mut command = Command::new("sh");
+                command.arg("-c").arg(cmd);
+                command
+            }
+        };
+        let output = cleanup::command_output(&mut command)?;
+        let stdout = String::from_utf8_lossy(output.stdout.as_slice());
+
+        match (self, output.status.code()) {
+            // checkupdates exits with 2 when there are no updates, and dnf
+            // exits with 100 when there are some
+            (Self::Pacman, Some(0 | 2)) | (Self::Dnf, Some(0 | 100)) => {}
+            (Self::Apt | Self::Custom(_), _) if output.status.success() => {}
+            _ => {
+                return Err(anyhow!(
+                    "Update check failed: {}",
+                    String::from_utf8_lossy(output.stderr.as_slice()).trim()
+                ))
+            }
+        }
+
+        Ok(match self {
+            Self::Apt => stdout
+                .lines()
+                .filter(|line| line.contains("[upgradable from"))
+                .count(),
+            // dnf lists obsoleted packages after a blank line
+            Self::Dnf => stdout
+                .lines()
+                .skip_while(|line| line.trim().is_empty())
+                .take_while(|line| !line.trim().is_empty())
+                .count(),
+            Self::Pacman | Self::Custom(_) => stdout
+                .lines()
+                .filter(|line| !line.trim().is_empty())
+                .count(),
+        })
+    }
+}
+
+#[derive(Debug)]
+enum Update {
+    Count(Result<usize>),
+    Event(Event),
+}
+
+/// Displays the number of pending package updates, checked periodically with
+/// `checkupdates` (pacman), `apt`, `dnf`, or a custom command.
+///
+/// The panel is hidden while there are no updates unless `show_zero` is set.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Updates {
+    name: &'static str,
+    backend: Backend,
+    #[builder(default = "Duration::from_secs(3600)")]
+    interval: Duration,
+    #[builder(default, setter(strip_option))]
+    update_command: Option<String>,
+    #[builder(default)]
+    show_zero: bool,
+    #[builder(default, setter(skip))]
+    count: Option<usize>,
+    format: &'static str,
+    attrs: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Updates {
+    fn update(
+        &mut self,
+        update: Update,
+        check_send: &UnboundedSender<()>,
+    ) -> Option<Result<()>> {
+        match update {
+            Update::Count(Ok(count)) => {
+                self.count = Some(count);
+                None
+            }
+            Update::Count(Err(e)) => {
+                // keep showing the last count
+                log::warn!("{}: {e}", self.name);
+                None
+            }
+            Update::Event(event) => Some(self.process_event(event, check_send)),
+        }
+    }
+
+    fn process_event(
+        &self,
+        event: Event,
+        check_send: &UnboundedSender<()>,
+    ) -> Result<()> {
+        match event {
+            Event::Mouse(event) if event.button == MouseButton::Left => {
+                self.run_update()
+            }
+            Event::Mouse(_) | Event::MouseEnter | Event::MouseLeave => Ok(()),
+            Event::Action(action) if action == "update" => self.run_update(),
+            Event::Action(action) if action == "check" => {
+                Ok(check_send.send(())?)
+            }
+            Event::Action(action) => Err(anyhow!("Unknown event {action}")),
+        }
+    }
+
+    fn run_update(&self) -> Result<()> {
+        let command = self
+            .update_command
+            .as_ref()
+            .ok_or_else(|| anyhow!("No update_command is set"))?;
+        cleanup::spawn_command(Command::new("sh").arg("-c").arg(command))
+    }
+
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+    ) -> Result<PanelDrawInfo> {
+        let text = match self.count {
+            Some(count) if count > 0 || self.show_zero => {
+                self.format.replace("%count%", count.to_string().as_str())
+            }
+            _ => String::new(),
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            &self.attrs,
+            &self.common,
+            self.highlight.clone(),
+            height,
+            ShowHide::None,
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Updates {
+    /// Configuration options:
+    ///
+    /// - `backend`: `pacman` (which requires `checkupdates` from
+    ///   pacman-contrib), `apt`, `dnf`, or `auto` to use whichever of those
+    ///   is installed
+    ///   - type: String
+    ///   - default: `auto`
+    /// - `command`: a command to run with `sh -c` instead of a backend. It
+    ///   should print one line per pending update.
+    ///   - type: String
+    ///   - default: none
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 3600
+    /// - `update_command`: the command to run with `sh -c` when the panel is
+    ///   clicked, e.g. `alacritty -e sudo pacman -Syu`
+    ///   - type: String
+    ///   - default: none
+    /// - `show_zero`: whether to show the panel when there are no updates
+    ///   - type: bool
+    ///   - default: false
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `%count% updates`
+    ///   - formatting options: `%count%`
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - See [`PanelCommon::parse_common`]. Left clicking the panel runs
+    ///   `update_command`. The supported events are `update`, which does the
+    ///   same, and `check`, which checks for updates immediately.
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = UpdatesBuilder::default();
+
+        builder.name(name);
+        let backend = if let Some(command) =
+            remove_string_from_config("command", table)
+        {
+            Backend::Custom(command)
+        } else {
+            match remove_string_from_config("backend", table)
+                .map(|b| b.to_lowercase())
+                .as_deref()
+            {
+                Some("pacman") => Backend::Pacman,
+                Some("apt") => Backend::Apt,
+                Some("dnf") => Backend::Dnf,
+                None | Some("auto") => Backend::detect().ok_or_else(|| {
+                    anyhow!("{name}: no supported package manager found")
+                })?,
+                Some(backend) => {
+                    return Err(anyhow!("{name}: unknown backend {backend}"))
+                }
+            }
+        };
+        builder.backend(backend);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval.max(1)));
+        }
+        if let Some(command) =
+            remove_string_from_config("update_command", table)
+        {
+            builder.update_command(command);
+        }
+        if let Some(show_zero) = remove_bool_from_config("show_zero", table) {
+            builder.show_zero(show_zero);
+        }
+
+        let common = PanelCommon::parse_common(table)?;
+        let format = PanelCommon::parse_format(table, "", "%count% updates");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let highlight = PanelCommon::parse_highlight(table, "");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.highlight(highlight);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+
+        // checks can also be requested over IPC
+        let (check_send, check_recv) = unbounded_channel();
+        let backend = self.backend.clone();
+        let counts = IntervalStream::new(interval(self.interval))
+            .map(|_| ())
+            .merge(UnboundedReceiverStream::new(check_recv))
+            .then(move |()| {
+                let backend = backend.clone();
+                async move {
+                    Update::Count(
+                        cleanup::spawn_blocking(move || backend.check())
+                            .await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|count| count),
+                    )
+                }
+            });
+
+        let (event_send, event_recv) = unbounded_channel();
+        let (response_send, response_recv) = unbounded_channel();
+        let events =
+            UnboundedReceiverStream::new(event_recv).map(Update::Event);
+
+        let stream = counts.merge(events).map(move |update| {
+            if let Some(result) = self.update(update, &check_send) {
+                let _ = response_send.send(match result {
+                    Ok(()) => EventResponse::Ok,
+                    Err(e) => EventResponse::Err(e.to_string()),
+                });
+            }
+            self.draw(&cr, height)
+        });
+
+        Ok((
+            Box::pin(stream),
+            Some(ChannelEndpoint::new(event_send, response_recv)),
+        ))
+    }
+}
+
+fn in_path(program: &str) -> bool {
+    env::var_os("PATH").is_some_and(|path| {
+        env::split_paths(&path)
+            .any(|dir| Path::new(&dir).join(program).is_file())
+    })
+}
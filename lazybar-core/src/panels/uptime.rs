@@ -0,0 +1,215 @@
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    rc::Rc,
+    sync::{Arc, Mutex},
+    thread::available_parallelism,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use config::{Config, Value};
+use derive_builder::Builder;
+use futures::task::AtomicWaker;
+use tokio_stream::StreamExt;
+
+use crate::{
+    bar::{Event, EventResponse, PanelDrawInfo},
+    common::{draw_panel, PanelCommon, ShowHide},
+    ipc::ChannelEndpoint,
+    remove_float_from_config, remove_uint_from_config, state, Attrs, Highlight,
+    ManagedIntervalStream, PanelConfig, PanelStream,
+};
+
+/// Displays the system uptime and load averages based on `/proc/uptime` and
+/// `/proc/loadavg`.
+///
+/// When the 1-minute load average exceeds the threshold (by default, the
+/// number of CPU cores), the panel is drawn with `attrs_high` and
+/// `highlight_high`.
+#[derive(Debug, Clone, Builder)]
+#[builder_struct_attr(allow(missing_docs))]
+#[builder_impl_attr(allow(missing_docs))]
+pub struct Uptime {
+    name: &'static str,
+    #[builder(default = "Duration::from_secs(10)")]
+    interval: Duration,
+    #[builder(default)]
+    waker: Arc<AtomicWaker>,
+    threshold: f64,
+    format: &'static str,
+    attrs: Attrs,
+    attrs_high: Attrs,
+    #[builder(default, setter(strip_option))]
+    highlight: Option<Highlight>,
+    #[builder(default, setter(strip_option))]
+    highlight_high: Option<Highlight>,
+    common: PanelCommon,
+}
+
+impl Uptime {
+    fn draw(
+        &self,
+        cr: &Rc<cairo::Context>,
+        height: i32,
+        paused: Arc<Mutex<bool>>,
+    ) -> Result<PanelDrawInfo> {
+        let uptime = read_uptime()?;
+        let [load1, load5, load15] = read_loadavg()?;
+
+        state::publish(self.name, "load1", format!("{load1:.2}"));
+
+        let secs = uptime.as_secs();
+        let (days, hours, minutes) =
+            (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+        let text = self
+            .format
+            .replace("%uptime%", format_uptime(days, hours, minutes).as_str())
+            .replace("%days%", days.to_string().as_str())
+            .replace("%hours%", hours.to_string().as_str())
+            .replace("%minutes%", minutes.to_string().as_str())
+            .replace("%load1%", format!("{load1:.2}").as_str())
+            .replace("%load5%", format!("{load5:.2}").as_str())
+            .replace("%load15%", format!("{load15:.2}").as_str());
+
+        let (attrs, highlight) = if load1 > self.threshold {
+            (&self.attrs_high, &self.highlight_high)
+        } else {
+            (&self.attrs, &self.highlight)
+        };
+
+        draw_panel(
+            cr,
+            text.as_str(),
+            attrs,
+            &self.common,
+            highlight.clone(),
+            height,
+            ShowHide::Default(paused, self.waker.clone()),
+        )
+    }
+}
+
+#[async_trait(?Send)]
+impl PanelConfig for Uptime {
+    /// Configuration options:
+    ///
+    /// - `interval`: how long to wait in seconds between each check
+    ///   - type: u64
+    ///   - default: 10
+    /// - `threshold`: the 1-minute load average above which the panel is
+    ///   drawn with `attrs_high` and `highlight_high`
+    ///   - type: f64
+    ///   - default: the number of CPU cores
+    /// - `format`: the format string
+    ///   - type: String
+    ///   - default: `up %uptime% load %load1%`
+    ///   - formatting options: `%uptime%` (e.g. `2d 3h 15m`), `%days%`,
+    ///     `%hours%`, `%minutes%` (the parts of the uptime, where hours and
+    ///     minutes don't include the larger units), `%load1%`, `%load5%`,
+    ///     `%load15%` (the 1, 5, and 15-minute load averages)
+    /// - `attrs`: A string specifying the attrs for the panel. See
+    ///   [`Attrs::parse`] for details.
+    /// - `attrs_high`: A string specifying the attrs for the panel while the
+    ///   load is above the threshold. Anything not set is taken from `attrs`.
+    ///   See [`Attrs::parse`] for details.
+    /// - `highlight`: A string specifying the highlight for the panel. See
+    ///   [`Highlight::parse`] for details.
+    /// - `highlight_high`: A string specifying the highlight for the panel
+    ///   while the load is above the threshold. See [`Highlight::parse`] for
+    ///   details.
+    /// - See [`PanelCommon::parse_common`].
+    fn parse(
+        name: &'static str,
+        table: &mut HashMap<String, Value>,
+        _global: &Config,
+    ) -> Result<Self> {
+        let mut builder = UptimeBuilder::default();
+
+        builder.name(name);
+        if let Some(interval) = remove_uint_from_config("interval", table) {
+            builder.interval(Duration::from_secs(interval));
+        }
+        builder.threshold(
+            remove_float_from_config("threshold", table).unwrap_or_else(|| {
+                available_parallelism().map_or(1.0, |cores| cores.get() as f64)
+            }),
+        );
+
+        let common = PanelCommon::parse_common(table)?;
+        let format =
+            PanelCommon::parse_format(table, "", "up %uptime% load %load1%");
+        let attrs = PanelCommon::parse_attr(table, "");
+        let attrs_high = PanelCommon::parse_attr(table, "_high");
+        let highlight = PanelCommon::parse_highlight(table, "");
+        let highlight_high = PanelCommon::parse_highlight(table, "_high");
+
+        builder.common(common);
+        builder.format(format.leak());
+        builder.attrs(attrs);
+        builder.attrs_high(attrs_high);
+        builder.highlight(highlight);
+        builder.highlight_high(highlight_high);
+
+        Ok(builder.build()?)
+    }
+
+    fn props(&self) -> (&'static str, bool) {
+        (self.name, self.common.visible)
+    }
+
+    async fn run(
+        mut self: Box<Self>,
+        cr: Rc<cairo::Context>,
+        global_attrs: Attrs,
+        height: i32,
+    ) -> Result<(PanelStream, Option<ChannelEndpoint<Event, EventResponse>>)>
+    {
+        self.attrs.apply_to(&global_attrs);
+        self.attrs_high.apply_to(&self.attrs);
+
+        let paused = Arc::new(Mutex::new(false));
+
+        let stream = ManagedIntervalStream::builder()
+            .duration(self.interval)
+            .paused(paused.clone())
+            .waker(self.waker.clone())
+            .build()?
+            .map(move |_| self.draw(&cr, height, paused.clone()));
+
+        Ok((Box::pin(stream), None))
+    }
+}
+
+fn format_uptime(days: u64, hours: u64, minutes: u64) -> String {
+    if days > 0 {
+        format!("{days}d {hours}h {minutes}m")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+fn read_uptime() -> Result<Duration> {
+    let uptime = read_to_string("/proc/uptime")?;
+    let secs = uptime
+        .split_whitespace()
+        .next()
+        .ok_or_else(|| anyhow!("Failed to read /proc/uptime"))?
+        .parse::<f64>()?;
+    Ok(Duration::from_secs_f64(secs))
+}
+
+fn read_loadavg() -> Result<[f64; 3]> {
+    let loadavg = read_to_string("/proc/loadavg")?;
+    let mut fields = loadavg.split_whitespace().map(str::parse::<f64>);
+    let mut next = || {
+        fields
+            .next()
+            .ok_or_else(|| anyhow!("Failed to read /proc/loadavg"))?
+            .map_err(anyhow::Error::from)
+    };
+    Ok([next()?, next()?, next()?])
+}
@@ -8,6 +8,7 @@ use std::{
 
 use anyhow::{anyhow, Context, Result};
 use async_trait::async_trait;
+use cairo::ImageSurface;
 use config::{Config, Value};
 use derive_builder::Builder;
 use tokio::task::{self, JoinHandle};
@@ -26,9 +27,11 @@ use x11rb::{
 
 use crate::{
     bar::{Event, EventResponse, PanelDrawInfo},
-    common::{draw_common, PanelCommon, ShowHide},
+    common::{draw_panel, PanelCommon, ShowHide},
+    icon, image,
     ipc::ChannelEndpoint,
-    remove_string_from_config, remove_uint_from_config,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config,
     x::InternedAtoms,
     Attrs, Highlight, PanelConfig, PanelStream,
 };
@@ -46,6 +49,15 @@ pub struct XWindow {
     windows: HashSet<Window>,
     #[builder(setter(strip_option), default = "None")]
     max_width: Option<u32>,
+    #[builder(default)]
+    show_icon: bool,
+    #[builder(default, setter(strip_option))]
+    icon_size: Option<i32>,
+    #[builder(default)]
+    icon_padding: i32,
+    /// The last window whose `_NET_WM_ICON` was read, and the icon
+    #[builder(default)]
+    icon: Option<(Window, Option<ImageSurface>)>,
     format: &'static str,
     attrs: Attrs,
     #[builder(default, setter(strip_option))]
@@ -54,11 +66,186 @@ pub struct XWindow {
 }
 
 impl XWindow {
+    /// Returns the class (the second string of `WM_CLASS`) of a window.
+    fn class(&self, window: Window) -> Result<String> {
+        let value = self
+            .conn
+            .get_property(
+                false,
+                window,
+                AtomEnum::WM_CLASS,
+                AtomEnum::STRING,
+                0,
+                64,
+            )?
+            .reply()?
+            .value;
+        Ok(value
+            .split(|&byte| byte == 0)
+            .nth(1)
+            .map(String::from_utf8_lossy)
+            .unwrap_or_default()
+            .into_owned())
+    }
+
+    /// Returns the icon (`_NET_WM_ICON`) of a window that is closest to
+    /// `size` pixels tall, if the window has one.
+    fn window_icon(
+        &self,
+        window: Window,
+        icon_atom: Atom,
+        size: i32,
+    ) -> Result<Option<ImageSurface>> {
+        let reply = self
+            .conn
+            .get_property(
+                false,
+                window,
+                icon_atom,
+                AtomEnum::CARDINAL,
+                0,
+                u32::MAX,
+            )?
+            .reply()?;
+        let Some(values) = reply.value32() else {
+            return Ok(None);
+        };
+        let values = values.collect::<Vec<_>>();
+
+        // the property holds any number of icons, each a width and height
+        // followed by that many ARGB pixels
+        let mut icons = Vec::new();
+        let mut rest = values.as_slice();
+        while let [width, height, pixels @ ..] = rest {
+            let len = (*width as usize).saturating_mul(*height as usize);
+            if len == 0 || pixels.len() < len {
+                break;
+            }
+            icons.push((*width as i32, *height as i32, &pixels[..len]));
+            rest = &pixels[len..];
+        }
+        let Some(&(width, height, pixels)) = icons
+            .iter()
+            .filter(|(_, height, _)| *height >= size)
+            .min_by_key(|(_, height, _)| *height)
+            .or_else(|| icons.iter().max_by_key(|(_, height, _)| *height))
+        else {
+            return Ok(None);
+        };
+
+        let mut surface =
+            ImageSurface::create(cairo::Format::ARgb32, width, height)?;
+        let stride = surface.stride() as usize;
+        {
+            let mut data = surface.data()?;
+            for (row, line) in pixels.chunks(width as usize).enumerate() {
+                for (col, &pixel) in line.iter().enumerate() {
+                    // cairo expects the colors to be premultiplied by alpha
+                    let alpha = pixel >> 24;
+                    let channel =
+                        |shift: u32| ((pixel >> shift) & 0xff) * alpha / 255;
+                    let value = (alpha << 24)
+                        | (channel(16) << 16)
+                        | (channel(8) << 8)
+                        | channel(0);
+                    let offset = row * stride + col * 4;
+                    data[offset..offset + 4]
+                        .copy_from_slice(&value.to_ne_bytes());
+                }
+            }
+        }
+        surface.mark_dirty();
+        Ok(Some(surface))
+    }
+
+    /// Returns the icon named after the class of a window in the icon theme.
+    fn theme_icon(
+        &self,
+        window: Window,
+        size: i32,
+    ) -> Result<Option<ImageSurface>> {
+        // icon themes name application icons after the lowercase class
+        let class = self.class(window)?.to_lowercase();
+        Ok(icon::lookup(class.as_str(), size).and_then(|path| {
+            image::load_surface(path.as_path(), Some(size))
+                .map_err(|e| log::warn!("Failed to load icon {class}: {e}"))
+                .ok()
+        }))
+    }
+
+    /// Returns the icon of the active window, reading it again only when
+    /// another window becomes active. Windows without their own icon get
+    /// the one from the icon theme instead.
+    fn icon(
+        &mut self,
+        window: Window,
+        icon_atom: Atom,
+        size: i32,
+    ) -> Result<Option<ImageSurface>> {
+        if !self.icon.as_ref().is_some_and(|(w, _)| *w == window) {
+            let icon = match self.window_icon(window, icon_atom, size)? {
+                Some(icon) => Some(icon),
+                None => self.theme_icon(window, size)?,
+            };
+            self.icon = Some((window, icon));
+        }
+        Ok(self.icon.as_ref().and_then(|(_, icon)| icon.clone()))
+    }
+
+    /// Draws `icon` to the left of the rest of the panel, scaled to `size`
+    /// pixels tall and centered vertically, with `padding` on each side.
+    fn with_icon(
+        mut draw_info: PanelDrawInfo,
+        icon: ImageSurface,
+        size: i32,
+        padding: i32,
+        height: i32,
+    ) -> PanelDrawInfo {
+        let size = size.clamp(1, height.max(1));
+        let scale = f64::from(size) / f64::from(icon.height().max(1));
+        let icon_width = (f64::from(icon.width()) * scale).round() as i32;
+        let offset = icon_width + 2 * padding;
+        if draw_info.width > 0 {
+            draw_info.width += offset;
+        } else {
+            draw_info.width = offset;
+        }
+        draw_info.height = draw_info.height.max(size);
+        for span in &mut draw_info.spans {
+            span.start += offset;
+            span.end += offset;
+        }
+
+        let draw_fn = draw_info.draw_fn;
+        draw_info.draw_fn = Box::new(move |cr, x| {
+            cr.save()?;
+            cr.translate(f64::from(padding), f64::from(height - size) / 2.0);
+            cr.scale(scale, scale);
+            cr.set_source_surface(&icon, 0.0, 0.0)?;
+            cr.rectangle(
+                0.0,
+                0.0,
+                f64::from(icon.width()),
+                f64::from(icon.height()),
+            );
+            cr.fill()?;
+            cr.restore()?;
+
+            cr.save()?;
+            cr.translate(f64::from(offset), 0.0);
+            let result = draw_fn(cr, x + f64::from(offset));
+            cr.restore()?;
+            result
+        });
+        draw_info
+    }
+
     fn draw(
         &mut self,
         cr: &Rc<cairo::Context>,
         name_atom: Atom,
         window_atom: Atom,
+        icon_atom: Atom,
         root: Window,
         utf8_atom: Atom,
         height: i32,
@@ -121,21 +308,36 @@ impl XWindow {
             }
         };
 
-        let text = self.format.replace(
-            "%name%",
-            glib::markup_escape_text(name.as_str()).as_str(),
-        );
+        let class = if active != 0 && self.format.contains("%class%") {
+            self.class(active)?
+        } else {
+            String::new()
+        };
+
+        let text = self
+            .format
+            .replace("%name%", glib::markup_escape_text(name.as_str()).as_str())
+            .replace(
+                "%class%",
+                glib::markup_escape_text(class.as_str()).as_str(),
+            );
+
+        let size = self.icon_size.unwrap_or(height);
+        let icon = if active != 0 && self.show_icon {
+            self.icon(active, icon_atom, size)?
+        } else {
+            None
+        };
 
         let conn = self.conn.clone();
         let conn_ = self.conn.clone();
 
-        draw_common(
+        let draw_info = draw_panel(
             cr,
             text.as_str(),
             &self.attrs,
-            self.common.dependence,
+            &self.common,
             self.highlight.clone(),
-            self.common.images.clone(),
             height,
             ShowHide::Custom(
                 Some(Box::new(move || {
@@ -155,7 +357,18 @@ impl XWindow {
                     Ok(())
                 })),
             ),
-        )
+        )?;
+
+        Ok(match icon {
+            Some(icon) => Self::with_icon(
+                draw_info,
+                icon,
+                size,
+                self.icon_padding,
+                height,
+            ),
+            None => draw_info,
+        })
     }
 }
 
@@ -170,11 +383,28 @@ impl PanelConfig for XWindow {
     /// - `format`: the format string
     ///   - type: String
     ///   - default: `%name%`
-    ///   - formatting options: `%name%`
+    ///   - formatting options: `%name%`, `%class%` (the window's class from
+    ///     `WM_CLASS`)
+    /// - `show_icon`: whether to draw the window's icon (`_NET_WM_ICON`) to
+    ///   the left of the panel. Windows without one get the icon named after
+    ///   their class in the icon theme (see [`crate::icon::lookup`]).
+    ///   - type: bool
+    ///   - default: false
+    /// - `icon_size`: the height of the icon in pixels
+    ///   - type: u64
+    ///   - default: the height of the bar
+    /// - `icon_padding`: the space in pixels on each side of the icon
+    ///   - type: u64
+    ///   - default: 0
     /// - `attrs`: A string specifying the attrs for the panel. See
     ///   [`Attrs::parse`] for details.
     /// - `highlight`: A string specifying the highlight for the panel. See
     ///   [`Highlight::parse`] for details.
+    /// - `max_length`: the maximum length in characters of the window title.
+    ///   To limit the width in pixels instead, use `max_width` from
+    ///   [`PanelCommon::parse_common`].
+    ///   - type: u64
+    ///   - default: none
     /// - See [`PanelCommon::parse_common`].
     fn parse(
         name: &'static str,
@@ -192,8 +422,17 @@ impl PanelConfig for XWindow {
         }
 
         builder.windows(HashSet::new());
-        if let Some(max_width) = remove_uint_from_config("max_width", table) {
-            builder.max_width(max_width as u32);
+        if let Some(max_length) = remove_uint_from_config("max_length", table) {
+            builder.max_width(max_length as u32);
+        }
+        if let Some(show_icon) = remove_bool_from_config("show_icon", table) {
+            builder.show_icon(show_icon);
+        }
+        if let Some(icon_size) = remove_uint_from_config("icon_size", table) {
+            builder.icon_size(icon_size as i32);
+        }
+        if let Some(padding) = remove_uint_from_config("icon_padding", table) {
+            builder.icon_padding(padding as i32);
         }
 
         let common = PanelCommon::parse_common(table)?;
@@ -223,6 +462,7 @@ impl PanelConfig for XWindow {
         let name_atom = InternedAtoms::get(&self.conn, "_NET_WM_NAME")?;
         let window_atom = InternedAtoms::get(&self.conn, "_NET_ACTIVE_WINDOW")?;
         let utf8_atom = InternedAtoms::get(&self.conn, "UTF8_STRING")?;
+        let icon_atom = InternedAtoms::get(&self.conn, "_NET_WM_ICON")?;
         let root = self
             .conn
             .setup()
@@ -241,7 +481,15 @@ impl PanelConfig for XWindow {
         let stream = tokio_stream::once(())
             .chain(XStream::new(self.conn.clone(), name_atom, window_atom))
             .map(move |()| {
-                self.draw(&cr, name_atom, window_atom, root, utf8_atom, height)
+                self.draw(
+                    &cr,
+                    name_atom,
+                    window_atom,
+                    icon_atom,
+                    root,
+                    utf8_atom,
+                    height,
+                )
             });
         Ok((Box::pin(stream), None))
     }
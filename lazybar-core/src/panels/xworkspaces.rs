@@ -14,9 +14,11 @@ use pangocairo::functions::{create_layout, show_layout};
 use tokio::{
     sync::mpsc::{unbounded_channel, UnboundedSender},
     task::{self, JoinHandle},
+    time::interval,
 };
 use tokio_stream::{
-    wrappers::UnboundedReceiverStream, Stream, StreamExt, StreamMap,
+    wrappers::{IntervalStream, UnboundedReceiverStream},
+    Stream, StreamExt, StreamMap,
 };
 use x11rb::{
     connection::Connection,
@@ -36,21 +38,33 @@ use crate::{
     background::Bg,
     bar::{Event, EventResponse, MouseButton, PanelDrawInfo},
     common::PanelCommon,
+    demo,
     ipc::ChannelEndpoint,
-    remove_string_from_config,
+    remove_bool_from_config, remove_string_from_config,
+    remove_uint_from_config,
     x::InternedAtoms,
-    Attrs, Highlight, PanelConfig, PanelStream,
+    Attrs, Highlight, HighlightScope, PanelConfig, PanelStream,
 };
 
 #[derive(PartialEq, Eq, Debug)]
 enum WorkspaceState {
     Active,
+    Urgent,
     Nonempty,
     Inactive,
 }
 
 /// Display information about workspaces
 ///
+/// Clicking a workspace switches to it, and scrolling cycles through
+/// workspaces. Workspaces containing a window that demands attention
+/// (`_NET_WM_STATE_DEMANDS_ATTENTION`) are drawn with the urgent attrs and
+/// highlight.
+///
+/// The windows on each workspace are counted from `_NET_CLIENT_LIST` and
+/// `_NET_WM_DESKTOP`, so the format can show how full each workspace is, and
+/// empty workspaces can be hidden.
+///
 /// Requires an EWMH-compliant window manager
 #[derive(Clone, Debug, Builder)]
 #[builder_struct_attr(allow(missing_docs))]
@@ -59,6 +73,16 @@ pub struct XWorkspaces {
     name: &'static str,
     conn: Arc<RustConnection>,
     screen: usize,
+    #[builder(default)]
+    watched: Arc<Mutex<HashSet<Window>>>,
+    #[builder(default = r#"String::from("%name%")"#)]
+    format: String,
+    #[builder(default = r#"String::from("•")"#)]
+    occupancy_icon: String,
+    #[builder(default = "5")]
+    occupancy_max: usize,
+    #[builder(default)]
+    hide_empty: bool,
     attrs: XWorkspacesConfig<Attrs>,
     highlights: XWorkspacesConfig<Highlight>,
     common: PanelCommon,
@@ -79,25 +103,45 @@ impl XWorkspaces {
         type_atom: Atom,
         normal_atom: Atom,
         desktop_atom: Atom,
+        state_atom: Atom,
+        attention_atom: Atom,
     ) -> Result<PanelDrawInfo> {
-        let workspaces = get_workspaces(
-            &self.conn,
-            root,
-            number_atom,
-            names_atom,
-            utf8_atom,
-        )?;
-        let current = get_current(&self.conn, root, current_atom)?;
-        let nonempty_set = get_nonempty(
-            &self.conn,
-            root,
-            client_atom,
-            type_atom,
-            normal_atom,
-            desktop_atom,
-        )?;
+        let (workspaces, current, counts, urgent_set) = if demo::enabled() {
+            let (workspaces, current, nonempty, urgent) = demo::workspaces();
+            let counts = nonempty.into_iter().map(|w| (w, 1)).collect();
+            (workspaces, current, counts, urgent)
+        } else {
+            (
+                get_workspaces(
+                    &self.conn,
+                    root,
+                    number_atom,
+                    names_atom,
+                    utf8_atom,
+                )?,
+                get_current(&self.conn, root, current_atom)?,
+                get_counts(
+                    &self.conn,
+                    root,
+                    client_atom,
+                    type_atom,
+                    normal_atom,
+                    desktop_atom,
+                )?,
+                get_urgent(
+                    &self.conn,
+                    root,
+                    client_atom,
+                    desktop_atom,
+                    state_atom,
+                    attention_atom,
+                    &self.watched,
+                )?,
+            )
+        };
 
         let active = self.attrs.active.clone();
+        let urgent = self.attrs.urgent.clone();
         let nonempty = self.attrs.nonempty.clone();
         let inactive = self.attrs.inactive.clone();
         let layouts: Vec<_> = workspaces
@@ -105,12 +149,27 @@ impl XWorkspaces {
             .enumerate()
             .map(move |(i, w)| {
                 let i = i as u32;
+                let count = counts.get(&i).copied().unwrap_or_default();
                 let layout = create_layout(cr);
-                layout.set_text(w.as_str());
+                layout.set_text(
+                    self.format
+                        .replace("%name%", w.as_str())
+                        .replace("%count%", count.to_string().as_str())
+                        .replace(
+                            "%occupancy%",
+                            self.occupancy_icon
+                                .repeat(count.min(self.occupancy_max))
+                                .as_str(),
+                        )
+                        .as_str(),
+                );
                 if i == current {
                     active.apply_font(&layout);
                     (WorkspaceState::Active, layout)
-                } else if nonempty_set.contains(&i) {
+                } else if urgent_set.contains(&i) {
+                    urgent.apply_font(&layout);
+                    (WorkspaceState::Urgent, layout)
+                } else if count > 0 {
                     nonempty.apply_font(&layout);
                     (WorkspaceState::Nonempty, layout)
                 } else {
@@ -120,13 +179,21 @@ impl XWorkspaces {
             })
             .collect();
 
+        let hide_empty = self.hide_empty;
         let mut width_cache = width_cache.lock().unwrap();
         width_cache.clear();
         for l in &layouts {
+            // hidden workspaces keep a zero-width entry so that clicks still
+            // line up with workspace indices
+            if hide_empty && l.0 == WorkspaceState::Inactive {
+                width_cache.push(0);
+                continue;
+            }
             let size = l.1.pixel_size();
             width_cache.push(
                 match l.0 {
                     WorkspaceState::Active => &self.attrs.active,
+                    WorkspaceState::Urgent => &self.attrs.urgent,
                     WorkspaceState::Nonempty => &self.attrs.nonempty,
                     WorkspaceState::Inactive => &self.attrs.inactive,
                 }
@@ -138,14 +205,21 @@ impl XWorkspaces {
         }
         let width = width_cache.iter().sum::<i32>();
         drop(width_cache);
+        let layouts = layouts
+            .into_iter()
+            .filter(|l| !hide_empty || l.0 != WorkspaceState::Inactive)
+            .collect::<Vec<_>>();
 
         let active = self.attrs.active.clone();
+        let urgent = self.attrs.urgent.clone();
         let nonempty = self.attrs.nonempty.clone();
         let inactive = self.attrs.inactive.clone();
         let active_highlight = self.highlights.active.clone();
+        let urgent_highlight = self.highlights.urgent.clone();
         let nonempty_highlight = self.highlights.nonempty.clone();
         let inactive_highlight = self.highlights.inactive.clone();
         let images = self.common.images.clone();
+        let highlight_scope = self.common.highlight_scope;
         let conn = self.conn.clone();
         let conn_ = self.conn.clone();
 
@@ -170,6 +244,15 @@ impl XWorkspaces {
                             )?,
                             active_highlight.clone(),
                         ),
+                        WorkspaceState::Urgent => (
+                            urgent.bg.as_ref().unwrap_or(&Bg::None).draw(
+                                cr,
+                                size.0 as f64,
+                                size.1 as f64,
+                                height as f64,
+                            )?,
+                            urgent_highlight.clone(),
+                        ),
                         WorkspaceState::Nonempty => (
                             nonempty.bg.as_ref().unwrap_or(&Bg::None).draw(
                                 cr,
@@ -191,16 +274,29 @@ impl XWorkspaces {
                     };
 
                     cr.save()?;
-                    highlight.draw(
-                        cr,
-                        height as f64,
-                        2.0f64.mul_add(offset, f64::from(size.0)),
-                    )?;
+                    match highlight_scope {
+                        HighlightScope::Text => {
+                            cr.save()?;
+                            cr.translate(offset, 0.0);
+                            highlight.draw(
+                                cr,
+                                height as f64,
+                                f64::from(size.0),
+                            )?;
+                            cr.restore()?;
+                        }
+                        HighlightScope::Panel => highlight.draw(
+                            cr,
+                            height as f64,
+                            2.0f64.mul_add(offset, f64::from(size.0)),
+                        )?,
+                    }
 
                     cr.translate(offset, f64::from(height - size.1) / 2.0);
 
                     match i {
                         WorkspaceState::Active => active.apply_fg(cr),
+                        WorkspaceState::Urgent => urgent.apply_fg(cr),
                         WorkspaceState::Nonempty => nonempty.apply_fg(cr),
                         WorkspaceState::Inactive => inactive.apply_fg(cr),
                     }
@@ -233,7 +329,9 @@ impl XWorkspaces {
                 Ok(())
             })),
             None,
-        ))
+        )
+        // clicking a workspace switches to it
+        .with_interactive(true))
     }
 
     fn process_event(
@@ -270,19 +368,22 @@ impl XWorkspaces {
 
             Event::Mouse(event) => {
                 let len = names.len();
+                if len == 0 {
+                    return Ok(());
+                }
                 let idx = match event.button {
                     MouseButton::Left
                     | MouseButton::Right
                     | MouseButton::Middle => {
-                        let mut idx = 0;
                         let cache = width_cache.lock().unwrap();
-                        if cache.is_empty() {
-                            return Ok(());
-                        }
-                        let mut x = cache[0];
-                        while x < event.x as i32 && idx < len {
+                        let mut idx = 0;
+                        let mut x = 0;
+                        for width in cache.iter() {
+                            x += width;
+                            if *width > 0 && x >= event.x as i32 {
+                                break;
+                            }
                             idx += 1;
-                            x += cache[idx];
                         }
                         drop(cache);
 
@@ -290,11 +391,10 @@ impl XWorkspaces {
                     }
                     MouseButton::ScrollUp => {
                         let current = get_current(&conn, root, current_atom)?;
-                        (current + 1) as usize % names.len()
+                        (current as usize + 1) % len
                     }
                     MouseButton::ScrollDown => {
                         let current = get_current(&conn, root, current_atom)?;
-                        let len = names.len();
                         (current as usize + len - 1) % len
                     }
                 };
@@ -311,6 +411,7 @@ impl XWorkspaces {
                     )?;
                 }
             }
+            Event::MouseEnter | Event::MouseLeave => {}
         };
 
         Ok(())
@@ -331,20 +432,46 @@ impl PanelConfig for XWorkspaces {
     ///   the nonempty workspaces. See [`Highlight::parse`] for parsing options.
     /// - `inactive_highlight`: The name of the highlight that will appear on
     ///   the inactive workspaces. See [`Highlight::parse`] for parsing options.
+    /// - `urgent_highlight`: The name of the highlight that will appear on
+    ///   workspaces with a window that demands attention. See
+    ///   [`Highlight::parse`] for parsing options.
     /// - `attrs_active`: A string specifying the attrs for the active
     ///   workspace. See [`Attrs::parse`] for details.
     /// - `attrs_nonempty`: A string specifying the attrs for the nonempty
     ///   workspaces. See [`Attrs::parse`] for details.
     /// - `attrs_inactive`: A string specifying the attrs for the inactive
     ///   workspaces. See [`Attrs::parse`] for details.
+    /// - `attrs_urgent`: A string specifying the attrs for workspaces with a
+    ///   window that demands attention. See [`Attrs::parse`] for details.
     /// - `highlight_active`: The highlight to be used for the active workspace.
     ///   See [`Highlight::parse`] for more details.
     /// - `highlight_nonempty`: The highlight to be used for the nonempty
     ///   workspaces. See [`Highlight::parse`] for more details.
     /// - `highlight_inactive`: The highlight to be used for the inactive
     ///   workspaces. See [`Highlight::parse`] for more details.
-    /// - See [`PanelCommon::parse_common`]. The supported events are each the
-    ///   name of a current workspace.
+    /// - `highlight_urgent`: The highlight to be used for workspaces with a
+    ///   window that demands attention. See [`Highlight::parse`] for more
+    ///   details.
+    /// - `format`: the format string for each workspace
+    ///   - type: String
+    ///   - default: `%name%`
+    ///   - formatting options: `%name%`, `%count%` (the number of windows on
+    ///     the workspace), `%occupancy%` (`occupancy_icon` repeated once per
+    ///     window)
+    /// - `occupancy_icon`: the icon repeated in `%occupancy%`
+    ///   - type: String
+    ///   - default: `•`
+    /// - `occupancy_max`: the most times `occupancy_icon` is repeated
+    ///   - type: u64
+    ///   - default: 5
+    /// - `hide_empty`: whether to hide workspaces without any windows, other
+    ///   than the active one
+    ///   - type: bool
+    ///   - default: false
+    /// - See [`PanelCommon::parse_common`]. With `highlight_scope = "text"`,
+    ///   highlights only cover the workspace names instead of their whole
+    ///   backgrounds. The supported events are each the name of a current
+    ///   workspace.
     fn parse(
         name: &'static str,
         table: &mut HashMap<String, Value>,
@@ -359,15 +486,27 @@ impl PanelConfig for XWorkspaces {
         } else {
             log::error!("Failed to connect to X server");
         }
+        if let Some(format) = remove_string_from_config("format", table) {
+            builder.format(format);
+        }
+        if let Some(icon) = remove_string_from_config("occupancy_icon", table) {
+            builder.occupancy_icon(icon);
+        }
+        if let Some(max) = remove_uint_from_config("occupancy_max", table) {
+            builder.occupancy_max(max as usize);
+        }
+        if let Some(hide_empty) = remove_bool_from_config("hide_empty", table) {
+            builder.hide_empty(hide_empty);
+        }
 
         let common = PanelCommon::parse_common(table)?;
         let attrs = PanelCommon::parse_attrs(
             table,
-            &["_active", "_nonempty", "_inactive"],
+            &["_active", "_urgent", "_nonempty", "_inactive"],
         );
         let highlights = PanelCommon::parse_highlights(
             table,
-            &["_active", "_nonempty", "_inactive"],
+            &["_active", "_urgent", "_nonempty", "_inactive"],
         );
 
         builder.common(common);
@@ -399,6 +538,9 @@ impl PanelConfig for XWorkspaces {
         let normal_atom =
             InternedAtoms::get(&self.conn, "_NET_WM_WINDOW_TYPE_NORMAL")?;
         let desktop_atom = InternedAtoms::get(&self.conn, "_NET_WM_DESKTOP")?;
+        let state_atom = InternedAtoms::get(&self.conn, "_NET_WM_STATE")?;
+        let attention_atom =
+            InternedAtoms::get(&self.conn, "_NET_WM_STATE_DEMANDS_ATTENTION")?;
 
         let root = self
             .conn
@@ -415,6 +557,7 @@ impl PanelConfig for XWorkspaces {
 
         // TODO: clean up
         self.attrs.active.apply_to(&global_attrs);
+        self.attrs.urgent.apply_to(&global_attrs);
         self.attrs.nonempty.apply_to(&global_attrs);
         self.attrs.inactive.apply_to(&global_attrs);
 
@@ -430,11 +573,24 @@ impl PanelConfig for XWorkspaces {
                         number_atom,
                         current_atom,
                         names_atom,
+                        client_atom,
+                        state_atom,
+                        desktop_atom,
                     ))
                     .map(|()| Ok(())),
             ),
         );
 
+        // the simulated workspaces change on their own
+        if demo::enabled() {
+            map.insert(
+                2,
+                Box::pin(
+                    IntervalStream::new(interval(demo::TICK)).map(|_| Ok(())),
+                ),
+            );
+        }
+
         let (event_send, event_recv) = unbounded_channel();
         let (response_send, response_recv) = unbounded_channel();
         let conn = self.conn.clone();
@@ -478,6 +634,8 @@ impl PanelConfig for XWorkspaces {
                     type_atom,
                     normal_atom,
                     desktop_atom,
+                    state_atom,
+                    attention_atom,
                 )
             })),
             Some(ChannelEndpoint::new(event_send, response_recv)),
@@ -531,15 +689,17 @@ fn get_current(
         .context("Empty reply from X server")
 }
 
-fn get_nonempty(
+/// Counts the normal windows on each workspace.
+fn get_counts(
     conn: &RustConnection,
     root: Window,
     client_atom: Atom,
     type_atom: Atom,
     normal_atom: Atom,
     desktop_atom: Atom,
-) -> Result<HashSet<u32>> {
-    Ok(get_clients(conn, root, client_atom)?
+) -> Result<HashMap<u32, usize>> {
+    let mut counts = HashMap::new();
+    for desktop in get_clients(conn, root, client_atom)?
         .iter()
         .filter(|&&w| {
             conn.get_property(false, w, type_atom, AtomEnum::ATOM, 0, 1)
@@ -557,6 +717,54 @@ fn get_nonempty(
                 .and_then(|c| c.reply().ok())
         })
         .filter_map(|r| r.value32().and_then(|mut val| val.next()))
+    {
+        *counts.entry(desktop).or_default() += 1;
+    }
+    Ok(counts)
+}
+
+fn get_urgent(
+    conn: &RustConnection,
+    root: Window,
+    client_atom: Atom,
+    desktop_atom: Atom,
+    state_atom: Atom,
+    attention_atom: Atom,
+    watched: &Mutex<HashSet<Window>>,
+) -> Result<HashSet<u32>> {
+    let clients = get_clients(conn, root, client_atom)?;
+
+    let mut watched = watched.lock().unwrap();
+    watched.retain(|w| clients.contains(w));
+    for &window in &clients {
+        if watched.insert(window) {
+            conn.change_window_attributes(
+                window,
+                &ChangeWindowAttributesAux::new()
+                    .event_mask(EventMask::PROPERTY_CHANGE),
+            )?;
+        }
+    }
+    drop(watched);
+
+    Ok(clients
+        .iter()
+        .filter(|&&w| {
+            conn.get_property(false, w, state_atom, AtomEnum::ATOM, 0, 32)
+                .ok()
+                .and_then(|c| c.reply().ok())
+                .is_some_and(|r| {
+                    r.value32().is_some_and(|mut iter| {
+                        iter.any(|a| a == attention_atom)
+                    })
+                })
+        })
+        .filter_map(|&w| {
+            conn.get_property(false, w, desktop_atom, AtomEnum::CARDINAL, 0, 1)
+                .ok()
+                .and_then(|c| c.reply().ok())
+        })
+        .filter_map(|r| r.value32().and_then(|mut val| val.next()))
         .collect())
 }
 
@@ -595,6 +803,9 @@ struct XStream {
     number_atom: Atom,
     current_atom: Atom,
     names_atom: Atom,
+    client_atom: Atom,
+    state_atom: Atom,
+    desktop_atom: Atom,
     handle: Option<JoinHandle<()>>,
 }
 
@@ -604,12 +815,18 @@ impl XStream {
         number_atom: Atom,
         current_atom: Atom,
         names_atom: Atom,
+        client_atom: Atom,
+        state_atom: Atom,
+        desktop_atom: Atom,
     ) -> Self {
         Self {
             conn,
             number_atom,
             current_atom,
             names_atom,
+            client_atom,
+            state_atom,
+            desktop_atom,
             handle: None,
         }
     }
@@ -635,12 +852,18 @@ impl Stream for XStream {
             let number_atom = self.number_atom;
             let current_atom = self.current_atom;
             let names_atom = self.names_atom;
+            let client_atom = self.client_atom;
+            let state_atom = self.state_atom;
+            let desktop_atom = self.desktop_atom;
             self.handle = Some(task::spawn_blocking(move || loop {
                 let event = conn.wait_for_event();
                 if let Ok(protocol::Event::PropertyNotify(event)) = event {
                     if event.atom == number_atom
                         || event.atom == current_atom
                         || event.atom == names_atom
+                        || event.atom == client_atom
+                        || event.atom == state_atom
+                        || event.atom == desktop_atom
                     {
                         waker.wake();
                         break;
@@ -652,4 +875,4 @@ impl Stream for XStream {
     }
 }
 
-array_to_struct!(XWorkspacesConfig, active, nonempty, inactive);
+array_to_struct!(XWorkspacesConfig, active, urgent, nonempty, inactive);
@@ -1,4 +1,9 @@
-use std::{collections::HashMap, path::Path};
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    sync::RwLock,
+    time::Duration,
+};
 
 use anyhow::{anyhow, Context, Result};
 use config::{Config, File, FileFormat, Value};
@@ -8,69 +13,132 @@ use tokio::sync::OnceCell;
 
 #[cfg(feature = "battery")]
 use crate::panels::Battery;
+#[cfg(feature = "bluetooth")]
+use crate::panels::Bluetooth;
+#[cfg(feature = "calendar")]
+use crate::panels::Calendar;
+#[cfg(feature = "cgroup")]
+use crate::panels::Cgroup;
 #[cfg(feature = "clock")]
 use crate::panels::Clock;
+#[cfg(feature = "composite")]
+use crate::panels::Composite;
 #[cfg(feature = "cpu")]
 use crate::panels::Cpu;
+#[cfg(feature = "cpu_cores")]
+use crate::panels::CpuCores;
 #[cfg(feature = "custom")]
 use crate::panels::Custom;
+#[cfg(feature = "dunst")]
+use crate::panels::Dunst;
 #[cfg(feature = "fanotify")]
 use crate::panels::Fanotify;
+#[cfg(feature = "feed")]
+use crate::panels::Feed;
 #[cfg(feature = "github")]
 use crate::panels::Github;
+#[cfg(feature = "gpu")]
+use crate::panels::Gpu;
+#[cfg(feature = "idle")]
+use crate::panels::IdleCountdown;
+#[cfg(feature = "imap")]
+use crate::panels::Imap;
 #[cfg(feature = "inotify")]
 use crate::panels::Inotify;
+#[cfg(feature = "locks")]
+use crate::panels::Locks;
+#[cfg(feature = "mail")]
+use crate::panels::Mail;
 #[cfg(feature = "memory")]
 use crate::panels::Memory;
 #[cfg(feature = "mpd")]
 use crate::panels::Mpd;
 #[cfg(feature = "network")]
 use crate::panels::Network;
+#[cfg(feature = "nightlight")]
+use crate::panels::NightLight;
 #[cfg(feature = "ping")]
 use crate::panels::Ping;
+#[cfg(feature = "pomodoro")]
+use crate::panels::Pomodoro;
+#[cfg(feature = "powerprofile")]
+use crate::panels::PowerProfile;
+#[cfg(feature = "privacy")]
+use crate::panels::Privacy;
+#[cfg(feature = "publicip")]
+use crate::panels::PublicIp;
 #[cfg(feature = "pulseaudio")]
 use crate::panels::Pulseaudio;
+#[cfg(feature = "runner")]
+use crate::panels::Runner;
+#[cfg(feature = "selfstats")]
+use crate::panels::SelfStats;
 #[cfg(feature = "separator")]
 use crate::panels::Separator;
+#[cfg(feature = "sni")]
+use crate::panels::Sni;
+#[cfg(feature = "spacer")]
+use crate::panels::Spacer;
+#[cfg(feature = "storage")]
+use crate::panels::Storage;
+#[cfg(feature = "sun")]
+use crate::panels::Sun;
 #[cfg(feature = "systray")]
 use crate::panels::Systray;
+#[cfg(feature = "taskwarrior")]
+use crate::panels::Taskwarrior;
 #[cfg(feature = "temp")]
 use crate::panels::Temp;
+#[cfg(feature = "ticker")]
+use crate::panels::Ticker;
+#[cfg(feature = "timer")]
+use crate::panels::Timer;
+#[cfg(feature = "updates")]
+use crate::panels::Updates;
+#[cfg(feature = "uptime")]
+use crate::panels::Uptime;
 #[cfg(feature = "xwindow")]
 use crate::panels::XWindow;
 #[cfg(feature = "xworkspaces")]
 use crate::panels::XWorkspaces;
 use crate::{
-    builders::BarConfigBuilder, cleanup, get_table_from_config,
-    remove_string_from_config, Alignment, Attrs, BarConfig, Margins,
-    PanelConfig, Position,
+    background::BarBg, bar::TransitionStyle, builders::BarConfigBuilder,
+    cleanup, get_table_from_config, icon, input::InputDevice,
+    remove_array_from_config, remove_bool_from_config,
+    remove_color_from_config, remove_float_from_config,
+    remove_string_from_config, remove_uint_from_config, style, x, Alignment,
+    Attrs, BarConfig, Layer, Margins, PanelConfig, Position, RampPalette,
 };
 
 lazy_static! {
     /// The `attrs` table from the global [`Config`].
     ///
-    /// This cell is guaranteed to be initialized during the execution of all
-    /// [`PanelConfig::parse`] functions.
-    pub static ref ATTRS: OnceCell<HashMap<String, Value>> =
-        OnceCell::new();
+    /// This table is filled in before any [`PanelConfig::parse`] function
+    /// runs, and is replaced when the styles are reloaded. See
+    /// [`style::reload`].
+    pub static ref ATTRS: RwLock<HashMap<String, Value>> =
+        RwLock::new(HashMap::new());
     /// The `ramps` table from the global [`Config`].
     ///
-    /// This cell is guaranteed to be initialized during the execution of all
-    /// [`PanelConfig::parse`] functions.
-    pub static ref RAMPS: OnceCell<HashMap<String, Value>> =
-        OnceCell::new();
+    /// This table is filled in before any [`PanelConfig::parse`] function
+    /// runs, and is replaced when the styles are reloaded. See
+    /// [`style::reload`].
+    pub static ref RAMPS: RwLock<HashMap<String, Value>> =
+        RwLock::new(HashMap::new());
     /// The `bgs` table from the global [`Config`].
     ///
-    /// This cell is guaranteed to be initialized during the execution of all
-    /// [`PanelConfig::parse`] functions.
-    pub static ref BGS: OnceCell<HashMap<String, Value>> =
-        OnceCell::new();
+    /// This table is filled in before any [`PanelConfig::parse`] function
+    /// runs, and is replaced when the styles are reloaded. See
+    /// [`style::reload`].
+    pub static ref BGS: RwLock<HashMap<String, Value>> =
+        RwLock::new(HashMap::new());
     /// The `consts` table from the global [`Config`].
     ///
-    /// This cell is guaranteed to be initialized during the execution of all
-    /// [`PanelConfig::parse`] functions.
-    pub static ref CONSTS: OnceCell<HashMap<String, Value>> =
-        OnceCell::new();
+    /// This table is filled in before any [`PanelConfig::parse`] function
+    /// runs, and is replaced when the styles are reloaded. See
+    /// [`style::reload`].
+    pub static ref CONSTS: RwLock<HashMap<String, Value>> =
+        RwLock::new(HashMap::new());
     /// The `images` table from the global [`Config`].
     ///
     /// This cell is guaranteed to be initialized during the execution of all
@@ -79,20 +147,47 @@ lazy_static! {
         OnceCell::new();
     /// The `highlights` table from the global [`Config`].
     ///
+    /// This table is filled in before any [`PanelConfig::parse`] function
+    /// runs, and is replaced when the styles are reloaded. See
+    /// [`style::reload`].
+    pub static ref HIGHLIGHTS: RwLock<HashMap<String, Value>> =
+        RwLock::new(HashMap::new());
+    /// The palette chosen with the top-level `ramp_palette` key.
+    ///
     /// This cell is guaranteed to be initialized during the execution of all
     /// [`PanelConfig::parse`] functions.
-    pub static ref HIGHLIGHTS: OnceCell<HashMap<String, Value>> =
-        OnceCell::new();
+    pub static ref RAMP_PALETTE: OnceCell<RampPalette> = OnceCell::new();
+    /// The icon theme chosen with the top-level `icon_theme` key. See
+    /// [`icon::lookup`].
+    ///
+    /// This cell is guaranteed to be initialized during the execution of all
+    /// [`PanelConfig::parse`] functions.
+    pub static ref ICON_THEME: OnceCell<String> = OnceCell::new();
 }
 
 /// Parses a bar with a given name from the global [`Config`]
 ///
 /// Configuration options:
-/// - `position`: `top` or `bottom`
-/// - `height`: the height in pixels of the bar
+/// - `position`: `top`, `bottom`, `left`, or `right`. Bars on the left or
+///   right are drawn as if they were horizontal and then rotated, so panels
+///   are laid out from bottom to top on the left and from top to bottom on
+///   the right. The systray panel can't be rotated, so it only works on top
+///   and bottom bars.
+/// - `height`: the height in pixels of the bar. If there are multiple rows,
+///   this is the height of each row. For bars on the left or right, this is
+///   their width.
+/// - `rows`: the number of rows of panels. The panels in the first row are
+///   listed in `panels_left`, `panels_center`, and `panels_right`. The panels
+///   in the nth row (counting from one) are listed in `panels_left_<n>`,
+///   `panels_center_<n>`, and `panels_right_<n>`. Defaults to 1.
 /// - `transparent`: `true` or `false`. If `bg` isn't transparent, the bar won't
 ///   be either.
-/// - `bg`: the background color. See [`csscolorparser::parse`].
+/// - `bg`: the background color. See [`csscolorparser::parse`]. It can
+///   also be an array of colors, which are drawn as a linear gradient
+///   across the bar, or a table with the keys `left`, `center`, and `right`,
+///   which splits the bar into thirds with one color each. See [`BarBg`].
+/// - `bg_angle`: The direction of a gradient background in degrees. 0 runs
+///   from left to right, and 90 runs from top to bottom. Defaults to 0.
 /// - `margins`: See [`Margins`]. Keys are `margin_left`, `margin_right`, and
 ///   `margin_internal`.
 /// - `reverse_scroll`: `true` or `false`. Whether to reverse scrolling.
@@ -102,48 +197,186 @@ lazy_static! {
 /// - `monitor`: The name of the monitor on which the bar should display. You
 ///   can use `xrandr --query` to find monitor names in most cases. However,
 ///   discovering all monitors is a complicated problem and beyond the scope of
-///   this documentation.
-pub fn parse(bar_name: &str, config: &Path) -> Result<BarConfig> {
-    let config = Config::builder()
-        .add_source(
-            File::new(
-                config.to_str().unwrap_or_else(|| {
-                    log::error!("Invalid config path");
-                    executor::block_on(cleanup::exit(None, false, 101))
-                }),
-                FileFormat::Toml,
-            )
-            .required(true),
-        )
-        .build()
-        .unwrap_or_else(|e| {
-            log::error!("Error parsing config file: {e}");
-            executor::block_on(cleanup::exit(None, false, 101))
-        });
+///   this documentation. This can also be `"all"` or an array of monitor
+///   names, in which case an identical bar is created on each of them (see
+///   [`monitors`]). Each of those bars runs its own copy of every panel, so
+///   panels that poll, like `cpu` or `ping`, poll once per monitor, and
+///   panels with state, like `timer`, keep it separately on each monitor.
+///   Bars follow their monitor when its resolution or position changes, and
+///   are hidden while it is disconnected, but monitors connected after
+///   lazybar starts don't get a bar of their own.
+/// - `zen_panels`: The names of the panels that remain visible in zen mode,
+///   which is toggled by sending `zen` to the bar over IPC. All other panels
+///   are hidden and paused.
+/// - `zen_height`: The height in pixels of the bar in zen mode. Defaults to
+///   `height`.
+/// - `privacy_classes`: Window classes (see `xprop WM_CLASS`) that enable
+///   privacy mode while any window with one of them exists. Privacy mode can
+///   also be toggled by sending `privacy` to the bar over IPC, and is entered
+///   automatically while an xdg-desktop-portal screen cast is running if the
+///   `screencast` feature is enabled. While it is active, panels with
+///   `sensitive = true` in their table are left blank. Defaults to
+///   `["obs"]`.
+/// - `accessibility`: `true` or `false`. Whether to export the text of each
+///   panel over D-Bus so that screen readers can announce it. The service is
+///   named `org.lazybar.Bar.b<name>`, where `<name>` is the bar name with
+///   non-alphanumeric characters replaced with underscores. Sensitive panels
+///   are always exported as empty. Requires the `a11y` feature. Defaults to
+///   `false`.
+/// - `high_contrast`: `true` or `false`. Whether to start in high contrast
+///   mode, which replaces every configured color (including colors set with
+///   markup) with white text on a black background and yellow highlights,
+///   and makes highlights thicker. It can also be toggled by sending
+///   `high_contrast` to the bar over IPC. Each bar has its own mode. Defaults
+///   to `false`.
+/// - `focus_accent`: A color. If set, a line of this color is drawn along
+///   the bottom of the bar while the active window (`_NET_ACTIVE_WINDOW`) is
+///   on the bar's monitor, which shows at a glance which monitor has focus
+///   when a bar runs on each one. See [`csscolorparser::parse`].
+/// - `focus_accent_height`: The thickness in pixels of the focus accent.
+///   Defaults to 2.
+/// - `autohide`: `true` or `false`. Whether the bar hides itself once the
+///   pointer has left it, reappearing when the pointer touches the edge of
+///   the screen. The bar doesn't reserve space while this is enabled, so
+///   windows may be placed under it. Sending `show` to the bar over IPC
+///   reveals it as if the pointer had touched the edge, and sending `hide`
+///   hides it right away. Defaults to `false`.
+/// - `autohide_delay`: How long in milliseconds the pointer has to be away
+///   from the bar before it hides. Defaults to 1000.
+/// - `hide_on_fullscreen`: `true` or `false`. Whether to hide the bar, and
+///   stop reserving space for it, while the active window is fullscreen on
+///   the bar's monitor. Defaults to `false`.
+/// - `tooltip_delay`: How long in milliseconds the pointer has to rest on a
+///   panel before its tooltip is shown. Defaults to 500.
+/// - `double_click_time`: How soon in milliseconds after a click another
+///   click on the same panel counts as a double-click. Defaults to 400.
+/// - `hold_time`: How long in milliseconds a mouse button has to be held
+///   down on a panel to count as held. Defaults to 500. See
+///   [`Actions::parse`][crate::actions::Actions::parse].
+/// - `transition`: How panels appear and disappear. One of `none`, `slide`,
+///   or `fade`. Defaults to `none`. See [`TransitionStyle`].
+/// - `transition_duration`: How long in milliseconds panels take to appear
+///   and disappear. Defaults to 200.
+/// - `animation_duration`: How long in milliseconds panels take to change
+///   width, moving their neighbors along smoothly instead of all at once.
+///   Defaults to 0, which disables the animation.
+/// - `separator`: The name of a panel to insert between each pair of
+///   neighboring panels in every alignment and row, usually a `separator` or
+///   `spacer` panel. Giving it `dependence = "both"` keeps it from showing
+///   next to panels that are hidden. It shouldn't set an `id`.
+/// - `corner_radius`: The radius in pixels of the bar's corners. Defaults to
+///   0. The corners are smooth if `transparent` is `true`, and jagged
+///   otherwise. Combine this with `margins` to make the bar float away from
+///   the edges of the screen.
+/// - `border_width`: The width in pixels of a border drawn around the bar,
+///   following its corners. Defaults to 0.
+/// - `border_color`: The color of the border. Defaults to black. See
+///   [`csscolorparser::parse`].
+/// - `blur`: `true` or `false`. Whether to ask the compositor to blur what's
+///   behind the bar, which only shows through if `transparent` is `true` and
+///   `bg` is translucent. This sets `_KDE_NET_WM_BLUR_BEHIND_REGION`, which
+///   KWin reads directly. Other compositors, such as picom, can match the
+///   property in their blur rules. Defaults to `false`.
+/// - `shadow_radius`: How far in pixels a soft shadow extends from the side
+///   of the bar facing the rest of the monitor. The bar window grows to make
+///   room for it, but clicks on the shadow pass through to the windows below.
+///   This helps a bar with a translucent `bg` or rounded corners stand out
+///   from the wallpaper. Requires `transparent` to be `true`. Defaults to 0,
+///   which disables the shadow.
+/// - `shadow_opacity`: The opacity of the shadow next to the bar, from 0 to
+///   1. Defaults to 0.5.
+/// - `click_through_gaps`: `true` or `false`. Whether clicks on the empty
+///   space between the left, center, and right panels pass through to the
+///   windows below, which suits a bar whose center is transparent. Defaults
+///   to `false`.
+/// - `layer`: `above`, `normal`, or `below`. How the bar is stacked relative
+///   to other windows, set through `_NET_WM_STATE_ABOVE` and
+///   `_NET_WM_STATE_BELOW`. The layer can be changed while the bar is running
+///   by sending `layer:above`, `layer:normal`, or `layer:below` to the bar
+///   over IPC, e.g. to drop it below a fullscreen preview. Defaults to
+///   `normal`.
+/// - `input`: An array of tables describing input devices, such as a
+///   presenter remote or a volume knob, whose events are sent to the bar as
+///   messages, e.g. `[[bars.main.input]]`. See [`InputDevice::parse`] for
+///   the keys. Requires the `input` feature, and read access to the devices
+///   (usually by being in the `input` group).
+/// - `monitor_overrides`: A table of per-monitor changes to the bar, keyed by
+///   monitor name, e.g. `[bars.main.monitor_overrides."HDMI-1"]`. This is
+///   useful when the same bar is run on several monitors (see the `monitor`
+///   argument). The entry for the bar's monitor (or the primary monitor, if
+///   `monitor` is unset) is applied as follows:
+///   - Any of the options above (e.g. `height` or `panels_right`) replace the
+///     bar's value.
+///   - `remove_panels`: An array of panel names to remove from the bar.
+///   - `add_panels_left`, `add_panels_center`, `add_panels_right`, and their
+///     `_<n>` variants for other rows: Arrays of panel names to append to
+///     the corresponding list.
+///
+///   Moving a panel to another alignment can be done by removing it and
+///   adding it again.
+///
+/// Every panel table can also set `id`, a string that identifies the panel
+/// over IPC. Panels without one are given an id based on their name and
+/// position (see [`Panel::default_id`][crate::bar::Panel::default_id]), which
+/// is logged at the debug level. A panel can be sent a message with
+/// `<id>.<message>`, and shown or hidden with `#<id>.show`, `#<id>.hide`, or
+/// `#<id>.toggle`.
+///
+/// Panels that set `click_through = true` let clicks pass through them to the
+/// windows below, instead of receiving them. This is useful for decorative
+/// panels such as spacers.
+///
+/// Panels that set `priority` to a non-negative integer can be collapsed
+/// when a row doesn't have room for all of its panels. The panels with the
+/// lowest priority are hidden first, and a `»` expander is drawn after the
+/// left panels in their place. Clicking the expander shows the hidden
+/// panels, and clicking it again hides them. Panels without a priority are
+/// never collapsed.
+///
+/// Panels that set `group` to the same name form a group, which can be shown
+/// or hidden as a unit with `#group:<name>.show`, `#group:<name>.hide`, or
+/// `#group:<name>.toggle`. Toggling a group shows it if all of its panels are
+/// hidden, and hides it otherwise. A panel that sets `toggle_group` to the
+/// name of a group toggles it when left-clicked instead of receiving the
+/// click, e.g. an icon that the group collapses behind. Setting `visible =
+/// false` on the group's panels starts it collapsed.
+///
+/// The `attrs`, `ramps`, `bgs`, `consts`, and `highlights` tables can be
+/// reloaded without restarting the bar by sending `restyle` to any bar over
+/// IPC, which redraws every bar. See [`style::reload`].
+///
+/// If `monitor` is set, it takes precedence over the `monitor` option in the
+/// config file. If neither is set to a single monitor, the first of
+/// [`monitors`] is used.
+pub fn parse(
+    bar_name: &str,
+    config: &Path,
+    monitor: Option<String>,
+) -> Result<BarConfig> {
+    style::set_path(config);
+    let config = read_config(config);
     log::info!("Read config file");
 
-    ATTRS
-        .set(config.get_table("attrs").unwrap_or_default())
-        .unwrap();
-
-    RAMPS
-        .set(config.get_table("ramps").unwrap_or_default())
-        .unwrap();
+    style::load(&config);
 
-    BGS.set(config.get_table("bgs").unwrap_or_default())
-        .unwrap();
+    // these are the same for every bar, so only the first one parsed sets them
+    let _ = IMAGES.set(config.get_table("images").unwrap_or_default());
 
-    CONSTS
-        .set(config.get_table("consts").unwrap_or_default())
-        .unwrap();
-
-    IMAGES
-        .set(config.get_table("images").unwrap_or_default())
-        .unwrap();
+    let _ = RAMP_PALETTE.set(config.get_string("ramp_palette").map_or_else(
+        |_| RampPalette::default(),
+        |name| {
+            RampPalette::parse(name.as_str()).unwrap_or_else(|| {
+                log::warn!("Unknown ramp palette {name}");
+                RampPalette::default()
+            })
+        },
+    ));
 
-    HIGHLIGHTS
-        .set(config.get_table("highlights").unwrap_or_default())
-        .unwrap();
+    let _ = ICON_THEME.set(
+        config
+            .get_string("icon_theme")
+            .unwrap_or_else(|_| icon::default_theme_name()),
+    );
 
     let mut bars_table = config
         .get_table("bars")
@@ -157,6 +390,23 @@ pub fn parse(bar_name: &str, config: &Path) -> Result<BarConfig> {
         .with_context(|| format!("`{bar_name}` isn't a table"))?;
     log::trace!("got bar table {bar_name} from config");
 
+    let monitor = match monitor {
+        Some(monitor) => Some(monitor),
+        None => expand_monitor(bar_table.get("monitor"))?
+            .into_iter()
+            .next()
+            .flatten(),
+    };
+    match monitor {
+        Some(monitor) => {
+            bar_table.insert(String::from("monitor"), Value::from(monitor));
+        }
+        None => {
+            bar_table.remove("monitor");
+        }
+    }
+    apply_monitor_overrides(&mut bar_table);
+
     let mut bar = BarConfigBuilder::default()
         .name(bar_name.to_owned())
         .position({
@@ -169,6 +419,8 @@ pub fn parse(bar_name: &str, config: &Path) -> Result<BarConfig> {
             {
                 "top" => Position::Top,
                 "bottom" => Position::Bottom,
+                "left" => Position::Left,
+                "right" => Position::Right,
                 _ => Position::Top,
             };
             log::trace!("got bar position: {val:?}");
@@ -183,6 +435,13 @@ pub fn parse(bar_name: &str, config: &Path) -> Result<BarConfig> {
             log::trace!("got bar height: {val}");
             val
         })
+        .rows({
+            let val = remove_uint_from_config("rows", &mut bar_table)
+                .unwrap_or(1)
+                .max(1) as usize;
+            log::trace!("got bar rows: {val}");
+            val
+        })
         .transparent({
             let val = bar_table
                 .remove("transparent")
@@ -193,14 +452,8 @@ pub fn parse(bar_name: &str, config: &Path) -> Result<BarConfig> {
             val
         })
         .bg({
-            let val = bar_table
-                .remove("bg")
-                .unwrap_or_default()
-                .into_string()
-                .unwrap_or_default()
-                .parse()
-                .unwrap_or_default();
-            log::trace!("got bar background: {val}");
+            let val = BarBg::parse(&mut bar_table);
+            log::trace!("got bar background: {val:?}");
             val
         })
         .margins({
@@ -254,90 +507,507 @@ pub fn parse(bar_name: &str, config: &Path) -> Result<BarConfig> {
             log::trace!("got bar monitor: {val:?}");
             val
         })
+        .zen_panels({
+            let val = remove_array_from_config("zen_panels", &mut bar_table)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|p| p.into_string().ok())
+                .collect::<Vec<_>>();
+            log::trace!("got bar zen panels: {val:?}");
+            val
+        })
+        .zen_height({
+            let val = remove_uint_from_config("zen_height", &mut bar_table)
+                .map(|h| h as u16);
+            log::trace!("got bar zen height: {val:?}");
+            val
+        })
+        .privacy_classes({
+            let val =
+                remove_array_from_config("privacy_classes", &mut bar_table)
+                    .map_or_else(
+                        || vec![String::from("obs")],
+                        |a| {
+                            a.into_iter()
+                                .filter_map(|c| c.into_string().ok())
+                                .collect()
+                        },
+                    );
+            log::trace!("got bar privacy classes: {val:?}");
+            val
+        })
+        .accessibility({
+            let val = remove_bool_from_config("accessibility", &mut bar_table)
+                .unwrap_or_default();
+            log::trace!("got bar accessibility: {val}");
+            val
+        })
+        .high_contrast({
+            let val = remove_bool_from_config("high_contrast", &mut bar_table)
+                .unwrap_or_default();
+            log::trace!("got bar high contrast: {val}");
+            val
+        })
+        .focus_accent({
+            let val = remove_string_from_config("focus_accent", &mut bar_table)
+                .and_then(|color| {
+                    color
+                        .parse()
+                        .map_err(|e| log::warn!("Invalid focus accent: {e}"))
+                        .ok()
+                });
+            log::trace!("got bar focus accent: {val:?}");
+            val
+        })
+        .focus_accent_height({
+            let val =
+                remove_uint_from_config("focus_accent_height", &mut bar_table)
+                    .map_or(2, |h| h as u16);
+            log::trace!("got bar focus accent height: {val}");
+            val
+        })
+        .autohide({
+            let val = remove_bool_from_config("autohide", &mut bar_table)
+                .unwrap_or_default()
+                .then(|| {
+                    Duration::from_millis(
+                        remove_uint_from_config(
+                            "autohide_delay",
+                            &mut bar_table,
+                        )
+                        .unwrap_or(1000),
+                    )
+                });
+            log::trace!("got bar autohide: {val:?}");
+            val
+        })
+        .hide_on_fullscreen({
+            let val =
+                remove_bool_from_config("hide_on_fullscreen", &mut bar_table)
+                    .unwrap_or_default();
+            log::trace!("got bar hide on fullscreen: {val}");
+            val
+        })
+        .tooltip_delay({
+            let val = Duration::from_millis(
+                remove_uint_from_config("tooltip_delay", &mut bar_table)
+                    .unwrap_or(500),
+            );
+            log::trace!("got bar tooltip delay: {val:?}");
+            val
+        })
+        .double_click_time({
+            let val = Duration::from_millis(
+                remove_uint_from_config("double_click_time", &mut bar_table)
+                    .unwrap_or(400),
+            );
+            log::trace!("got bar double click time: {val:?}");
+            val
+        })
+        .hold_time({
+            let val = Duration::from_millis(
+                remove_uint_from_config("hold_time", &mut bar_table)
+                    .unwrap_or(500),
+            );
+            log::trace!("got bar hold time: {val:?}");
+            val
+        })
+        .transition({
+            let val = remove_string_from_config("transition", &mut bar_table)
+                .map_or_else(TransitionStyle::default, |style| {
+                    TransitionStyle::parse(style.to_lowercase().as_str())
+                        .unwrap_or_else(|| {
+                            log::warn!("Invalid transition {style}");
+                            TransitionStyle::default()
+                        })
+                });
+            log::trace!("got bar transition: {val:?}");
+            val
+        })
+        .transition_duration({
+            let val = Duration::from_millis(
+                remove_uint_from_config("transition_duration", &mut bar_table)
+                    .unwrap_or(200),
+            );
+            log::trace!("got bar transition duration: {val:?}");
+            val
+        })
+        .animation_duration({
+            let val = Duration::from_millis(
+                remove_uint_from_config("animation_duration", &mut bar_table)
+                    .unwrap_or_default(),
+            );
+            log::trace!("got bar animation duration: {val:?}");
+            val
+        })
+        .corner_radius({
+            let val = remove_float_from_config("corner_radius", &mut bar_table)
+                .unwrap_or_default()
+                .max(0.0);
+            log::trace!("got bar corner radius: {val}");
+            val
+        })
+        .border_width({
+            let val = remove_float_from_config("border_width", &mut bar_table)
+                .unwrap_or_default()
+                .max(0.0);
+            log::trace!("got bar border width: {val}");
+            val
+        })
+        .border_color({
+            let val = remove_color_from_config("border_color", &mut bar_table)
+                .unwrap_or_default();
+            log::trace!("got bar border color: {val:?}");
+            val
+        })
+        .blur({
+            let val = remove_bool_from_config("blur", &mut bar_table)
+                .unwrap_or_default();
+            log::trace!("got bar blur: {val}");
+            val
+        })
+        .shadow_radius({
+            let val = remove_float_from_config("shadow_radius", &mut bar_table)
+                .unwrap_or_default()
+                .max(0.0);
+            log::trace!("got bar shadow radius: {val}");
+            val
+        })
+        .click_through_gaps({
+            let val =
+                remove_bool_from_config("click_through_gaps", &mut bar_table)
+                    .unwrap_or_default();
+            log::trace!("got bar click through gaps: {val}");
+            val
+        })
+        .layer({
+            let val = remove_string_from_config("layer", &mut bar_table)
+                .map_or(Layer::Normal, |name| {
+                    Layer::parse(name.as_str()).unwrap_or_else(|| {
+                        log::warn!("Unknown layer {name}, using normal");
+                        Layer::Normal
+                    })
+                });
+            log::trace!("got bar layer: {val:?}");
+            val
+        })
+        .shadow_opacity({
+            let val =
+                remove_float_from_config("shadow_opacity", &mut bar_table)
+                    .unwrap_or(0.5)
+                    .clamp(0.0, 1.0);
+            log::trace!("got bar shadow opacity: {val}");
+            val
+        })
+        .input({
+            let val = remove_array_from_config("input", &mut bar_table)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|device| {
+                    device
+                        .into_table()
+                        .map_err(anyhow::Error::from)
+                        .and_then(InputDevice::parse)
+                        .map_err(|e| log::warn!("Invalid input device: {e}"))
+                        .ok()
+                })
+                .collect::<Vec<_>>();
+            log::trace!("got bar input devices: {val:?}");
+            val
+        })
         .left(Vec::new())
         .center(Vec::new())
         .right(Vec::new())
         .build()?;
 
-    let mut left_final = Vec::new();
-    let mut center_final = Vec::new();
-    let mut right_final = Vec::new();
-
-    let panels_left = bar_table.remove("panels_left");
-    if let Some(pl) = panels_left {
-        let panel_list =
-            pl.into_array().context("`panels_left` isn't an array")?;
-        for p in panel_list {
-            if let Ok(name) = p.clone().into_string() {
-                log::debug!("Adding left panel {name}");
-                left_final.push(name);
-            } else {
-                log::warn!("Ignoring non-string value {p:?} in `panels_left`");
+    let panels_table = config
+        .get_table("panels")
+        .context("`panels` doesn't exist or isn't a table")?;
+    log::trace!("got panels table");
+
+    let mut sensitive = HashSet::new();
+    let mut click_through = HashSet::new();
+    let mut priorities = HashMap::new();
+    let mut ids = HashMap::new();
+    let mut groups = HashMap::new();
+
+    let separator = remove_string_from_config("separator", &mut bar_table)
+        .map(|name| &*name.leak());
+    log::trace!("got bar separator: {separator:?}");
+
+    for row in 0..bar.rows {
+        let suffix = if row == 0 {
+            String::new()
+        } else {
+            format!("_{}", row + 1)
+        };
+
+        for alignment in [Alignment::Left, Alignment::Center, Alignment::Right]
+        {
+            let key = format!("panels_{alignment}{suffix}");
+            let Some(panels) = bar_table.remove(key.as_str()) else {
+                continue;
+            };
+            let panel_list = panels
+                .into_array()
+                .with_context(|| format!("`{key}` isn't an array"))?;
+
+            // leak panel names so that we can use &'static str instead of
+            // String
+            let mut first = true;
+            for p in panel_list {
+                if let Ok(name) = p.clone().into_string() {
+                    log::debug!("Adding {alignment} panel {name} to row {row}");
+                    if let Some(panel) = parse_panel(
+                        name.leak(),
+                        &panels_table,
+                        &config,
+                        &mut sensitive,
+                        &mut click_through,
+                        &mut priorities,
+                        &mut ids,
+                        &mut groups,
+                    ) {
+                        if let Some(separator) =
+                            separator.filter(|_| !first).and_then(|s| {
+                                parse_panel(
+                                    s,
+                                    &panels_table,
+                                    &config,
+                                    &mut sensitive,
+                                    &mut click_through,
+                                    &mut priorities,
+                                    &mut ids,
+                                    &mut groups,
+                                )
+                            })
+                        {
+                            bar.add_panel_to_row(separator, alignment, row);
+                        }
+                        first = false;
+                        bar.add_panel_to_row(panel, alignment, row);
+                    }
+                } else {
+                    log::warn!("Ignoring non-string value {p:?} in `{key}`");
+                }
             }
+            log::debug!("{alignment} panels added to row {row}");
         }
     }
 
-    let panels_center = bar_table.remove("panels_center");
-    if let Some(pc) = panels_center {
-        let panel_list =
-            pc.into_array().context("`panels_center` isn't an array")?;
-        for p in panel_list {
-            if let Ok(name) = p.clone().into_string() {
-                log::debug!("Adding center panel {name}");
-                center_final.push(name);
-            } else {
-                log::warn!(
-                    "Ignoring non-string value {p:?} in `panels_center`"
-                );
-            }
-        }
+    bar.sensitive = sensitive;
+    bar.click_through = click_through;
+    bar.priorities = priorities;
+    bar.ids = ids;
+    bar.groups = groups;
+
+    Ok(bar)
+}
+
+/// Returns the monitors that a bar with a given name should be created on,
+/// each of which can be passed to [`parse`]. `None` means the primary monitor.
+///
+/// If `monitor` is set, it is the only one. Otherwise, this depends on the
+/// bar's `monitor` option:
+/// - unset: the primary monitor
+/// - a monitor name: that monitor
+/// - `"all"`: every connected monitor, with the primary monitor first
+/// - an array of monitor names: each of those monitors
+pub fn monitors(
+    bar_name: &str,
+    config: &Path,
+    monitor: Option<String>,
+) -> Result<Vec<Option<String>>> {
+    if let Some(monitor) = monitor {
+        return Ok(vec![Some(monitor)]);
     }
 
-    let panels_right = bar_table.remove("panels_right");
-    if let Some(pr) = panels_right {
-        let panel_list =
-            pr.into_array().context("`panels_right` isn't an array")?;
-        for p in panel_list {
-            if let Ok(name) = p.clone().into_string() {
-                log::debug!("Adding right panel {name}");
-                right_final.push(name);
-            } else {
-                log::warn!("Ignoring non-string value {p:?} in `panels_right`");
-            }
+    let bar_table = read_config(config)
+        .get_table("bars")
+        .context("`bars` doesn't exist or isn't a table")?
+        .remove(bar_name)
+        .with_context(|| format!("`{bar_name}` doesn't exist"))?
+        .into_table()
+        .with_context(|| format!("`{bar_name}` isn't a table"))?;
+
+    expand_monitor(bar_table.get("monitor"))
+}
+
+/// Returns the names of all of the bars in a config file, in alphabetical
+/// order.
+///
+/// # Errors
+///
+/// If the config file doesn't have a `bars` table.
+pub fn bar_names(config: &Path) -> Result<Vec<String>> {
+    let mut names = read_config(config)
+        .get_table("bars")
+        .context("`bars` doesn't exist or isn't a table")?
+        .into_keys()
+        .collect::<Vec<_>>();
+    names.sort();
+    Ok(names)
+}
+
+fn read_config(path: &Path) -> Config {
+    Config::builder()
+        .add_source(
+            File::new(
+                path.to_str().unwrap_or_else(|| {
+                    log::error!("Invalid config path");
+                    executor::block_on(cleanup::exit(None, false, 101))
+                }),
+                FileFormat::Toml,
+            )
+            .required(true),
+        )
+        .build()
+        .unwrap_or_else(|e| {
+            log::error!("Error parsing config file: {e}");
+            executor::block_on(cleanup::exit(None, false, 101))
+        })
+}
+
+fn expand_monitor(monitor: Option<&Value>) -> Result<Vec<Option<String>>> {
+    let Some(monitor) = monitor else {
+        return Ok(vec![None]);
+    };
+    if let Ok(monitors) = monitor.clone().into_array() {
+        let monitors = monitors
+            .into_iter()
+            .filter_map(|m| m.into_string().ok())
+            .map(Some)
+            .collect::<Vec<_>>();
+        if monitors.is_empty() {
+            return Err(anyhow!("`monitor` is an empty array"));
         }
+        return Ok(monitors);
     }
 
-    let panels_table = config
-        .get_table("panels")
-        .context("`panels` doesn't exist or isn't a table")?;
-    log::trace!("got panels table");
+    let monitor = monitor
+        .clone()
+        .into_string()
+        .context("`monitor` isn't a string or an array")?;
+    if monitor == "all" {
+        let monitors = x::monitor_names()?;
+        log::debug!("Found monitors {monitors:?}");
+        Ok(monitors.into_iter().map(Some).collect())
+    } else {
+        Ok(vec![Some(monitor)])
+    }
+}
 
-    // leak panel names so that we can use &'static str instead of String
-    left_final
-        .into_iter()
-        .filter_map(|p| parse_panel(p.leak(), &panels_table, &config))
-        .for_each(|p| bar.add_panel(p, Alignment::Left));
-    log::debug!("left panels added");
-    center_final
+fn apply_monitor_overrides(bar_table: &mut HashMap<String, Value>) {
+    let Some(overrides) = bar_table.remove("monitor_overrides") else {
+        return;
+    };
+    let Ok(overrides) = overrides.into_table() else {
+        log::warn!("Ignoring `monitor_overrides` because it isn't a table");
+        return;
+    };
+
+    let monitor = match bar_table
+        .get("monitor")
+        .and_then(|m| m.clone().into_string().ok())
+    {
+        Some(monitor) => monitor,
+        None => match x::primary_monitor_name() {
+            Ok(monitor) => monitor,
+            Err(e) => {
+                log::warn!("Failed to find the primary monitor: {e}");
+                return;
+            }
+        },
+    };
+
+    let Some(mut table) = get_table_from_config(monitor.as_str(), &overrides)
+    else {
+        log::debug!("No overrides for monitor {monitor}");
+        return;
+    };
+    log::debug!("Applying overrides for monitor {monitor}");
+
+    let remove = remove_array_from_config("remove_panels", &mut table)
+        .unwrap_or_default()
         .into_iter()
-        .filter_map(|p| parse_panel(p.leak(), &panels_table, &config))
-        .for_each(|p| bar.add_panel(p, Alignment::Center));
-    log::debug!("center panels added");
-    right_final
+        .filter_map(|p| p.into_string().ok())
+        .collect::<Vec<_>>();
+    let add_keys = table
+        .keys()
+        .filter(|key| key.starts_with("add_panels_"))
+        .cloned()
+        .collect::<Vec<_>>();
+    let add = add_keys
         .into_iter()
-        .filter_map(|p| parse_panel(p.leak(), &panels_table, &config))
-        .for_each(|p| bar.add_panel(p, Alignment::Right));
-    log::debug!("right panels added");
+        .filter_map(|key| {
+            let panels = remove_array_from_config(key.as_str(), &mut table)?;
+            Some((key["add_".len()..].to_string(), panels))
+        })
+        .collect::<Vec<_>>();
 
-    Ok(bar)
+    // anything that's left replaces the bar's value
+    bar_table.extend(table);
+
+    if !remove.is_empty() {
+        for (key, value) in bar_table.iter_mut() {
+            if !key.starts_with("panels_") {
+                continue;
+            }
+            if let Ok(panels) = value.clone().into_array() {
+                *value = Value::from(
+                    panels
+                        .into_iter()
+                        .filter(|p| {
+                            p.clone()
+                                .into_string()
+                                .map_or(true, |name| !remove.contains(&name))
+                        })
+                        .collect::<Vec<_>>(),
+                );
+            }
+        }
+    }
+
+    for (key, panels) in add {
+        let mut list = remove_array_from_config(key.as_str(), bar_table)
+            .unwrap_or_default();
+        list.extend(panels);
+        bar_table.insert(key, Value::from(list));
+    }
 }
 
 fn parse_panel(
     p: &'static str,
     panels_table: &HashMap<String, Value>,
     config: &Config,
+    sensitive: &mut HashSet<&'static str>,
+    click_through: &mut HashSet<&'static str>,
+    priorities: &mut HashMap<&'static str, u64>,
+    ids: &mut HashMap<&'static str, String>,
+    groups: &mut HashMap<&'static str, (Option<String>, Option<String>)>,
 ) -> Option<Box<dyn PanelConfig>> {
     if let Some(mut table) = get_table_from_config(p, panels_table) {
+        if remove_bool_from_config("sensitive", &mut table).unwrap_or_default()
+        {
+            sensitive.insert(p);
+        }
+        if remove_bool_from_config("click_through", &mut table)
+            .unwrap_or_default()
+        {
+            click_through.insert(p);
+        }
+        if let Some(priority) = remove_uint_from_config("priority", &mut table)
+        {
+            priorities.insert(p, priority);
+        }
+        if let Some(id) = remove_string_from_config("id", &mut table) {
+            ids.insert(p, id);
+        }
+        let group = remove_string_from_config("group", &mut table);
+        let toggles = remove_string_from_config("toggle_group", &mut table);
+        if group.is_some() || toggles.is_some() {
+            groups.insert(p, (group, toggles));
+        }
         if let Some(s) = remove_string_from_config("type", &mut table) {
             log::debug!("parsing {s} panel");
             return match s.as_str() {
@@ -346,34 +1016,85 @@ fn parse_panel(
                     Battery::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "bluetooth")]
+                "bluetooth" => Bluetooth::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "calendar")]
+                "calendar" | "agenda" => Calendar::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "cgroup")]
+                "cgroup" => {
+                    Cgroup::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "clock")]
                 "clock" => {
                     Clock::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "composite")]
+                "composite" => Composite::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
                 #[cfg(feature = "cpu")]
                 "cpu" => Cpu::parse(p, &mut table, config)
                     .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "cpu_cores")]
+                "cpu_cores" | "cores" => CpuCores::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
                 #[cfg(feature = "custom")]
                 "custom" => {
                     Custom::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "dunst")]
+                "dunst" => {
+                    Dunst::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "fanotify")]
                 "fanotify" => {
                     Fanotify::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "feed")]
+                "feed" => {
+                    Feed::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "github")]
                 "github" => {
                     Github::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "gpu")]
+                "gpu" => Gpu::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "idle")]
+                "idle" | "idle_countdown" => {
+                    IdleCountdown::parse(p, &mut table, config).map::<Box<
+                        dyn PanelConfig,
+                    >, _>(
+                        |p| Box::new(p)
+                    )
+                }
+                #[cfg(feature = "imap")]
+                "imap" => {
+                    Imap::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "inotify")]
                 "inotify" => {
                     Inotify::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "locks")]
+                "locks" => {
+                    Locks::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "mail")]
+                "mail" | "maildir" => Mail::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
                 #[cfg(feature = "memory")]
                 "memory" => {
                     Memory::parse(p, &mut table, config)
@@ -387,27 +1108,109 @@ fn parse_panel(
                     Network::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "nightlight")]
+                "nightlight" | "night_light" => {
+                    NightLight::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "ping")]
                 "ping" => {
                     Ping::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "pomodoro")]
+                "pomodoro" => {
+                    Pomodoro::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "powerprofile")]
+                "powerprofile" | "power_profile" => {
+                    PowerProfile::parse(p, &mut table, config).map::<Box<
+                        dyn PanelConfig,
+                    >, _>(
+                        |p| Box::new(p)
+                    )
+                }
+                #[cfg(feature = "privacy")]
+                "privacy" => {
+                    Privacy::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "publicip")]
+                "publicip" | "public_ip" => {
+                    PublicIp::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "pulseaudio")]
                 "pulseaudio" => Pulseaudio::parse(p, &mut table, config)
                     .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "runner")]
+                "runner" => {
+                    Runner::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "selfstats")]
+                "selfstats" | "self_stats" => {
+                    SelfStats::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "separator")]
                 "separator" => Separator::parse(p, &mut table, config)
                     .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
-                #[cfg(feature = "systray")]
-                "systray" => {
-                    Systray::parse(p, &mut table, config)
+                #[cfg(feature = "spacer")]
+                "spacer" => {
+                    Spacer::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "sni")]
+                "sni" => Sni::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "storage")]
+                "storage" => {
+                    Storage::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "sun")]
+                "sun" | "sunrise" | "sunset" => {
+                    Sun::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "systray")]
+                "systray" | "tray" => Systray::parse(p, &mut table, config)
+                    .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p)),
+                #[cfg(feature = "taskwarrior")]
+                "taskwarrior" | "task" => {
+                    Taskwarrior::parse(p, &mut table, config).map::<Box<
+                        dyn PanelConfig,
+                    >, _>(
+                        |p| Box::new(p)
+                    )
+                }
                 #[cfg(feature = "temp")]
                 "temp" => {
                     Temp::parse(p, &mut table, config)
                         .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
                 }
+                #[cfg(feature = "ticker")]
+                "ticker" => {
+                    Ticker::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "timer")]
+                "timer" => {
+                    Timer::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "updates")]
+                "updates" => {
+                    Updates::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
+                #[cfg(feature = "uptime")]
+                "uptime" => {
+                    Uptime::parse(p, &mut table, config)
+                        .map::<Box<dyn PanelConfig>, _>(|p| Box::new(p))
+                }
                 #[cfg(feature = "xwindow")]
                 "xwindow" => {
                     XWindow::parse(p, &mut table, config)
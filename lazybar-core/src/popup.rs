@@ -0,0 +1,215 @@
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
+use anyhow::{anyhow, Result};
+use csscolorparser::Color;
+use derive_debug::Dbg;
+use x11rb::{
+    connection::Connection,
+    protocol::xproto::{
+        ConfigureWindowAux, ConnectionExt, CreateWindowAux, EventMask,
+        GrabMode, GrabStatus, Window, WindowClass,
+    },
+    xcb_ffi::XCBConnection,
+    CURRENT_TIME, NONE,
+};
+
+use crate::{
+    high_contrast,
+    x::{create_surface, find_visual},
+    PanelDrawFn,
+};
+
+/// The contents of a popup window that a panel can ask the bar to show next
+/// to it, like a calendar for a clock or a mixer for a volume panel.
+///
+/// The popup is shown while `open` is set. A panel usually sets it in
+/// response to a click and redraws, and the bar clears it when the popup is
+/// dismissed by a click outside of it or a change in focus.
+#[derive(Dbg)]
+pub struct PanelPopup {
+    /// The width in pixels of the popup.
+    pub width: i32,
+    /// The height in pixels of the popup.
+    pub height: i32,
+    /// A [`Fn`] that draws the popup to the [`cairo::Context`], starting at
+    /// (0, 0). The second argument is always zero.
+    #[dbg(placeholder = "..")]
+    pub draw_fn: PanelDrawFn,
+    /// Whether the popup should be shown.
+    pub open: Arc<AtomicBool>,
+}
+
+impl PanelPopup {
+    /// Creates a new [`PanelPopup`] from its components.
+    #[must_use]
+    pub const fn new(
+        dims: (i32, i32),
+        draw_fn: PanelDrawFn,
+        open: Arc<AtomicBool>,
+    ) -> Self {
+        Self {
+            width: dims.0,
+            height: dims.1,
+            draw_fn,
+            open,
+        }
+    }
+
+    /// Whether the popup should be shown.
+    #[must_use]
+    pub fn is_open(&self) -> bool {
+        self.open.load(Ordering::Relaxed)
+    }
+}
+
+/// A popup window shown by the bar. While it's open, the pointer is grabbed
+/// so that a click anywhere else can dismiss it. The window is destroyed when
+/// this is dropped, and the panel's `open` flag is cleared.
+pub(crate) struct Popup {
+    conn: Arc<XCBConnection>,
+    pub(crate) window: Window,
+    surface: cairo::XCBSurface,
+    /// The id of the panel that the popup belongs to
+    pub(crate) owner: String,
+    open: Arc<AtomicBool>,
+    geometry: (i32, i32, i32, i32),
+    bg: Color,
+}
+
+impl Popup {
+    /// Maps a popup window with the geometry `(x, y, width, height)` and
+    /// grabs the pointer.
+    pub(crate) fn show(
+        conn: Arc<XCBConnection>,
+        screen: usize,
+        owner: String,
+        content: &PanelPopup,
+        geometry: (i32, i32, i32, i32),
+        bg: &Color,
+    ) -> Result<Self> {
+        let screen = conn
+            .setup()
+            .roots
+            .get(screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?;
+        let root = screen.root;
+        let visual = *find_visual(screen, 24)
+            .ok_or_else(|| anyhow!("Failed to find visual"))?;
+        let (x, y, width, height) = geometry;
+
+        let window = conn.generate_id()?;
+        conn.create_window(
+            24,
+            window,
+            root,
+            x as i16,
+            y as i16,
+            width.max(1) as u16,
+            height.max(1) as u16,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            visual.visual_id,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .event_mask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS),
+        )?;
+        let surface = create_surface(
+            window,
+            visual,
+            width.max(1),
+            height.max(1),
+            conn.as_ref(),
+        )?;
+        conn.map_window(window)?;
+
+        // clicks outside of the bar's windows are reported to the popup
+        let grab = conn
+            .grab_pointer(
+                true,
+                window,
+                EventMask::BUTTON_PRESS,
+                GrabMode::ASYNC,
+                GrabMode::ASYNC,
+                NONE,
+                NONE,
+                CURRENT_TIME,
+            )?
+            .reply()?;
+        if grab.status != GrabStatus::SUCCESS {
+            log::warn!(
+                "Failed to grab the pointer for a popup: {:?}; it will only \
+                 close when its panel closes it",
+                grab.status
+            );
+        }
+
+        let popup = Self {
+            conn,
+            window,
+            surface,
+            owner,
+            open: content.open.clone(),
+            geometry,
+            bg: bg.clone(),
+        };
+        popup.draw(content)?;
+
+        Ok(popup)
+    }
+
+    /// Moves or resizes the popup if needed, then redraws it.
+    pub(crate) fn update(
+        &mut self,
+        content: &PanelPopup,
+        geometry: (i32, i32, i32, i32),
+    ) -> Result<()> {
+        if geometry != self.geometry {
+            let (x, y, width, height) = geometry;
+            self.conn.configure_window(
+                self.window,
+                &ConfigureWindowAux::new()
+                    .x(x)
+                    .y(y)
+                    .width(width.max(1) as u32)
+                    .height(height.max(1) as u32),
+            )?;
+            self.surface.set_size(width.max(1), height.max(1))?;
+            self.geometry = geometry;
+        }
+        self.open = content.open.clone();
+        self.draw(content)
+    }
+
+    /// Whether a point relative to the popup is inside of it.
+    pub(crate) const fn contains(&self, x: i16, y: i16) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as i32) < self.geometry.2
+            && (y as i32) < self.geometry.3
+    }
+
+    /// Draws the popup's contents over the bar's background.
+    pub(crate) fn draw(&self, content: &PanelPopup) -> Result<()> {
+        let cr = cairo::Context::new(&self.surface)?;
+        let bg = high_contrast::or(&self.bg, &high_contrast::BG);
+        cr.set_source_rgba(bg.r.into(), bg.g.into(), bg.b.into(), 1.0);
+        cr.paint()?;
+        (content.draw_fn)(&cr, 0.0)?;
+
+        self.surface.flush();
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Popup {
+    fn drop(&mut self) {
+        self.open.store(false, Ordering::Relaxed);
+        let _ = self.conn.ungrab_pointer(CURRENT_TIME);
+        let _ = self.conn.destroy_window(self.window);
+        let _ = self.conn.flush();
+    }
+}
@@ -0,0 +1,27 @@
+pub use async_trait::async_trait;
+pub use config::{Config, Value};
+
+pub use crate::{
+    array_to_struct,
+    attrs::Attrs,
+    bar::{
+        ClickSpan, Dependence, Event, EventResponse, MouseButton, MouseEvent,
+        PanelDrawInfo, TooltipSpan,
+    },
+    common::{
+        draw_common, draw_gauge, draw_graph, draw_panel, Display, PanelCommon,
+        ShowHide,
+    },
+    gauge::Gauge,
+    graph::Graph,
+    ipc::ChannelEndpoint,
+    markup_escape_text,
+    menu::MenuEntry,
+    popup::PanelPopup,
+    remove_array_from_config, remove_bool_from_config,
+    remove_color_from_config, remove_float_from_config,
+    remove_string_from_config, remove_uint_from_config, state,
+    threshold::Thresholds,
+    ColorRamp, Highlight, ManagedIntervalStream, PanelConfig, PanelStream,
+    Ramp,
+};
@@ -0,0 +1,332 @@
+use std::{collections::HashMap, hash::BuildHasher};
+
+use config::Value;
+use csscolorparser::Color;
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+
+use crate::{
+    remove_array_from_config, remove_color_from_config,
+    remove_string_from_config,
+};
+
+lazy_static! {
+    static ref TOKEN: Regex = Regex::new(r"%bar(?::(?<width>\d+))?%").unwrap();
+    static ref STACK_TOKEN: Regex =
+        Regex::new(r"%stack(?::(?<width>\d+))?%").unwrap();
+}
+
+/// How many characters wide a bar is if its token doesn't say
+const DEFAULT_WIDTH: usize = 10;
+
+/// A horizontal bar that shows where a value lies in a range, drawn with
+/// characters so that it can sit anywhere in a format string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProgressBar {
+    /// The character for the part of the bar up to the value
+    pub fill: String,
+    /// The character for the rest of the bar
+    pub empty: String,
+    /// The character drawn at each marker
+    pub marker: String,
+    /// The color of the filled part, or the panel's foreground if unset
+    pub fill_color: Option<Color>,
+    /// The color of the empty part, or the panel's foreground if unset
+    pub empty_color: Option<Color>,
+    /// The color of the markers, or the panel's foreground if unset
+    pub marker_color: Option<Color>,
+    /// Values in the panel's range to mark on the bar, e.g. a battery's
+    /// low level
+    pub markers: Vec<f64>,
+}
+
+impl Default for ProgressBar {
+    fn default() -> Self {
+        Self {
+            fill: String::from("━"),
+            empty: String::from("─"),
+            marker: String::from("┃"),
+            fill_color: None,
+            empty_color: None,
+            marker_color: None,
+            markers: Vec::new(),
+        }
+    }
+}
+
+/// A horizontal bar split into segments that add up to a whole, such as
+/// used, cached, and free memory, drawn with characters so that it can sit
+/// anywhere in a format string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackedBar {
+    /// The character for each segment, repeated if there are more segments
+    pub chars: Vec<String>,
+    /// The color of each segment. Segments without one use the panel's
+    /// foreground.
+    pub colors: Vec<Color>,
+}
+
+impl Default for StackedBar {
+    fn default() -> Self {
+        Self {
+            chars: vec![
+                String::from("█"),
+                String::from("▓"),
+                String::from("░"),
+            ],
+            colors: Vec::new(),
+        }
+    }
+}
+
+impl StackedBar {
+    /// Parses a stacked bar from a panel's table. Every option has a
+    /// default, so this always succeeds.
+    ///
+    /// Configuration options:
+    /// - `stack_chars`: an array with the character for each segment, in the
+    ///   order the panel lists them. Defaults to `["█", "▓", "░"]`, so that
+    ///   the segments can be told apart without colors.
+    /// - `stack_colors`: an array with the color of each segment. Defaults to
+    ///   the panel's foreground color. See [`csscolorparser::parse`].
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let strings = |key, table: &mut HashMap<String, Value, S>| {
+            remove_array_from_config(key, table).map(|array| {
+                array
+                    .into_iter()
+                    .filter_map(|value| {
+                        value
+                            .into_string()
+                            .map_err(|e| log::warn!("Invalid {key}: {e}"))
+                            .ok()
+                    })
+                    .collect::<Vec<_>>()
+            })
+        };
+        let chars = strings("stack_chars", table)
+            .filter(|chars| !chars.is_empty())
+            .unwrap_or_else(|| Self::default().chars);
+        let colors = strings("stack_colors", table)
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|color| {
+                csscolorparser::parse(color.as_str())
+                    .map_err(|e| log::warn!("Invalid stack color: {e}"))
+                    .ok()
+            })
+            .collect();
+        Self { chars, colors }
+    }
+
+    /// Replaces each `%stack%` or `%stack:<width>%` token in a format string
+    /// with a bar `width` characters wide (10 by default) split in
+    /// proportion to `parts`.
+    #[must_use]
+    pub fn replace(&self, text: &str, parts: &[f64]) -> String {
+        STACK_TOKEN
+            .replace_all(text, |caps: &Captures| {
+                let width = caps
+                    .name("width")
+                    .and_then(|width| width.as_str().parse().ok())
+                    .unwrap_or(DEFAULT_WIDTH);
+                self.render(parts, width)
+            })
+            .into_owned()
+    }
+
+    /// Returns the markup for a bar `width` characters wide split in
+    /// proportion to `parts`.
+    #[must_use]
+    pub fn render(&self, parts: &[f64], width: usize) -> String {
+        let parts = parts
+            .iter()
+            .map(|part| if part.is_finite() { part.max(0.0) } else { 0.0 })
+            .collect::<Vec<_>>();
+        let total = parts.iter().sum::<f64>();
+        if width == 0 || total <= 0.0 {
+            return String::new();
+        }
+
+        // give each segment its whole cells, then hand out the rest to the
+        // segments that were rounded down the most
+        let exact = parts
+            .iter()
+            .map(|part| part / total * width as f64)
+            .collect::<Vec<_>>();
+        let mut cells = exact
+            .iter()
+            .map(|cells| *cells as usize)
+            .collect::<Vec<_>>();
+        let mut order = (0..parts.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| {
+            (exact[b] - cells[b] as f64)
+                .total_cmp(&(exact[a] - cells[a] as f64))
+        });
+        let remaining = width.saturating_sub(cells.iter().sum());
+        for idx in order.into_iter().take(remaining) {
+            cells[idx] += 1;
+        }
+
+        cells
+            .into_iter()
+            .enumerate()
+            .filter(|(_, count)| *count > 0)
+            .map(|(idx, count)| {
+                let text = glib::markup_escape_text(
+                    self.chars[idx % self.chars.len()].repeat(count).as_str(),
+                );
+                match self.colors.get(idx) {
+                    Some(color) => format!(
+                        "<span foreground=\"{}\">{text}</span>",
+                        color.to_hex_string()
+                    ),
+                    None => text.to_string(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Fill,
+    Empty,
+    Marker,
+}
+
+impl ProgressBar {
+    /// Parses a progress bar from a panel's table. Every option has a
+    /// default, so this always succeeds.
+    ///
+    /// Configuration options:
+    /// - `bar_fill`: the character for the filled part of the bar. Defaults
+    ///   to `━`.
+    /// - `bar_empty`: the character for the empty part of the bar. Defaults
+    ///   to `─`.
+    /// - `bar_fill_color` and `bar_empty_color`: the colors of each part.
+    ///   Default to the panel's foreground color. See
+    ///   [`csscolorparser::parse`].
+    /// - `bar_markers`: an array of values to mark on the bar, in the same
+    ///   units as the value the bar shows (e.g. a percentage for a battery).
+    ///   Defaults to none.
+    /// - `bar_marker`: the character drawn at each marker. Defaults to `┃`.
+    /// - `bar_marker_color`: the color of the markers. Defaults to the
+    ///   panel's foreground color.
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let default = Self::default();
+        Self {
+            fill: remove_string_from_config("bar_fill", table)
+                .unwrap_or(default.fill),
+            empty: remove_string_from_config("bar_empty", table)
+                .unwrap_or(default.empty),
+            marker: remove_string_from_config("bar_marker", table)
+                .unwrap_or(default.marker),
+            fill_color: remove_color_from_config("bar_fill_color", table),
+            empty_color: remove_color_from_config("bar_empty_color", table),
+            marker_color: remove_color_from_config("bar_marker_color", table),
+            markers: remove_array_from_config("bar_markers", table)
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|marker| {
+                    marker
+                        .into_float()
+                        .map_err(|e| log::warn!("Invalid bar marker: {e}"))
+                        .ok()
+                })
+                .collect(),
+        }
+    }
+
+    /// Replaces each `%bar%` or `%bar:<width>%` token in a format string
+    /// with a bar `width` characters wide (10 by default) showing where
+    /// `value` lies between `min` and `max`.
+    #[must_use]
+    pub fn replace(
+        &self,
+        text: &str,
+        value: f64,
+        min: f64,
+        max: f64,
+    ) -> String {
+        TOKEN
+            .replace_all(text, |caps: &Captures| {
+                let width = caps
+                    .name("width")
+                    .and_then(|width| width.as_str().parse().ok())
+                    .unwrap_or(DEFAULT_WIDTH);
+                self.render(value, min, max, width)
+            })
+            .into_owned()
+    }
+
+    /// Returns the markup for a bar `width` characters wide showing where
+    /// `value` lies between `min` and `max`.
+    #[must_use]
+    pub fn render(
+        &self,
+        value: f64,
+        min: f64,
+        max: f64,
+        width: usize,
+    ) -> String {
+        if width == 0 {
+            return String::new();
+        }
+        let range = max - min;
+        let position = |value: f64| {
+            if range > 0.0 {
+                ((value - min) / range).clamp(0.0, 1.0) * width as f64
+            } else {
+                0.0
+            }
+        };
+        let filled = position(value).round() as usize;
+        let markers = self
+            .markers
+            .iter()
+            .map(|&marker| (position(marker) as usize).min(width - 1))
+            .collect::<Vec<_>>();
+
+        let cells = (0..width).map(|idx| {
+            if markers.contains(&idx) {
+                Cell::Marker
+            } else if idx < filled {
+                Cell::Fill
+            } else {
+                Cell::Empty
+            }
+        });
+
+        // group neighboring cells of the same kind into one span each
+        let mut runs: Vec<(Cell, usize)> = Vec::new();
+        for cell in cells {
+            match runs.last_mut() {
+                Some((last, count)) if *last == cell => *count += 1,
+                _ => runs.push((cell, 1)),
+            }
+        }
+
+        runs.into_iter()
+            .map(|(cell, count)| {
+                let (text, color) = match cell {
+                    Cell::Fill => (&self.fill, &self.fill_color),
+                    Cell::Empty => (&self.empty, &self.empty_color),
+                    Cell::Marker => (&self.marker, &self.marker_color),
+                };
+                let text =
+                    glib::markup_escape_text(text.repeat(count).as_str());
+                match color {
+                    Some(color) => format!(
+                        "<span foreground=\"{}\">{text}</span>",
+                        color.to_hex_string()
+                    ),
+                    None => text.to_string(),
+                }
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,151 @@
 use std::ops::Sub;
 
-use crate::{parser, remove_string_from_config};
+use csscolorparser::Color;
+
+use crate::{
+    parser, remove_array_from_config, remove_bool_from_config,
+    remove_string_from_config, replace_consts, style,
+};
+
+/// A set of colors for the named constants `%{ok}`, `%{warn}`, and `%{crit}`,
+/// which can be used in any string in the config, e.g. in ramps or colors.
+/// Constants with the same names in the `consts` table take precedence.
+///
+/// The palette is chosen with the top-level `ramp_palette` key. The
+/// colorblind-safe palettes are taken from the Okabe-Ito palette.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub enum RampPalette {
+    /// Green, yellow, and red
+    #[default]
+    Default,
+    /// Safe for red-green color blindness (deuteranopia and protanopia)
+    Deuteranopia,
+    /// Safe for blue-yellow color blindness
+    Tritanopia,
+    /// Shades of gray that differ in lightness
+    Monochrome,
+}
+
+impl RampPalette {
+    /// Parses a palette from its name. `protanopia` is accepted as an alias
+    /// for `deuteranopia`.
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "default" => Some(Self::Default),
+            "deuteranopia" | "protanopia" => Some(Self::Deuteranopia),
+            "tritanopia" => Some(Self::Tritanopia),
+            "monochrome" => Some(Self::Monochrome),
+            _ => None,
+        }
+    }
+
+    /// Returns the `ok`, `warn`, and `crit` colors as hex strings.
+    #[must_use]
+    pub const fn colors(self) -> [&'static str; 3] {
+        match self {
+            Self::Default => ["#4caf50", "#ffc107", "#f44336"],
+            Self::Deuteranopia => ["#56b4e9", "#f0e442", "#d55e00"],
+            Self::Tritanopia => ["#009e73", "#cc79a7", "#d55e00"],
+            Self::Monochrome => ["#ffffff", "#aaaaaa", "#666666"],
+        }
+    }
+
+    /// Returns the color with the given name (`ok`, `warn`, or `crit`).
+    #[must_use]
+    pub const fn color(self, name: &str) -> Option<&'static str> {
+        let [ok, warn, crit] = self.colors();
+        match name.as_bytes() {
+            b"ok" => Some(ok),
+            b"warn" => Some(warn),
+            b"crit" => Some(crit),
+            _ => None,
+        }
+    }
+}
+
+/// What a [`Ramp`] does with values outside of its range.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+#[non_exhaustive]
+pub enum OutOfRange {
+    /// Use the first or last icon.
+    #[default]
+    Clamp,
+    /// Start over from the other end of the ramp, as if the range repeated.
+    Wrap,
+    /// Show nothing.
+    Hide,
+}
+
+impl OutOfRange {
+    /// Parses an instance from its name (`clamp`, `wrap`, or `hide`).
+    #[must_use]
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "clamp" => Some(Self::Clamp),
+            "wrap" => Some(Self::Wrap),
+            "hide" => Some(Self::Hide),
+            _ => None,
+        }
+    }
+}
+
+/// Chooses which of `len` icons to show for `value` in the range `[min, max]`.
+/// The range is split into `len` equal parts, and `max` itself belongs to the
+/// last one.
+///
+/// Returns [`None`] if there are no icons, or if `value` is out of range and
+/// `out_of_range` is [`OutOfRange::Hide`]. If `inverted` is true, the first
+/// icon is used for the top of the range instead of the bottom.
+#[must_use]
+pub fn ramp_index(
+    value: f64,
+    min: f64,
+    max: f64,
+    len: usize,
+    out_of_range: OutOfRange,
+    inverted: bool,
+) -> Option<usize> {
+    if len == 0 || value.is_nan() {
+        return None;
+    }
+    let range = max - min;
+    let mut prop = if range.abs() < f64::EPSILON {
+        // every value other than `min` is out of range
+        if value < min {
+            -1.0
+        } else if value > max {
+            2.0
+        } else {
+            0.0
+        }
+    } else {
+        (value - min) / range
+    };
+
+    if !(0.0..=1.0).contains(&prop) {
+        match out_of_range {
+            OutOfRange::Clamp => prop = prop.clamp(0.0, 1.0),
+            OutOfRange::Wrap => prop = prop.rem_euclid(1.0),
+            OutOfRange::Hide => return None,
+        }
+    }
+
+    let idx = ((prop * len as f64).trunc() as usize).min(len - 1);
+    Some(if inverted { len - 1 - idx } else { idx })
+}
 
 /// Utility data structure to display one of several strings based on a value in
 /// a range, like a volume icon.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Default)]
 pub struct Ramp {
     icons: Vec<String>,
+    out_of_range: OutOfRange,
+    inverted: bool,
+    /// The name this instance was parsed from, used to look it up again when
+    /// the styles are reloaded.
+    name: Option<String>,
 }
 
 impl Ramp {
@@ -16,33 +155,49 @@ impl Ramp {
     /// an empty string.
     #[must_use]
     pub const fn empty() -> Self {
-        Self { icons: Vec::new() }
+        Self {
+            icons: Vec::new(),
+            out_of_range: OutOfRange::Clamp,
+            inverted: false,
+            name: None,
+        }
     }
 
-    /// Given a value and a range, chooses the appropriate icon.
+    /// Given a value and a range, chooses the appropriate icon. See
+    /// [`ramp_index`] for details.
+    ///
+    /// If the styles have been reloaded (see [`style::reload`]), the ramp is
+    /// looked up again by name first.
     pub fn choose<T>(&self, value: T, min: T, max: T) -> String
     where
         T: Sub + Copy,
         f64: From<T>,
     {
-        // prevent division by zero
-        if self.icons.is_empty() {
-            return String::new();
-        }
-        let min = f64::from(min);
-        let max = f64::from(max);
-        let mut prop = (f64::from(value) - min) / (max - min);
-        if prop < min {
-            prop = min;
-        }
-        if prop > max {
-            prop = max;
-        }
-        let idx = prop * (self.icons.len()) as f64;
-        self.icons
-            .get((idx.trunc() as usize).min(self.icons.len() - 1))
-            .unwrap()
-            .clone()
+        let restyled;
+        let ramp = match self.name.as_ref().filter(|_| style::reloaded()) {
+            Some(name) => {
+                restyled = style::cached(name, |name| Self::parse(name));
+                restyled.as_ref().unwrap_or(self)
+            }
+            None => self,
+        };
+        ramp.choose_icon(value, min, max)
+    }
+
+    fn choose_icon<T>(&self, value: T, min: T, max: T) -> String
+    where
+        T: Sub + Copy,
+        f64: From<T>,
+    {
+        ramp_index(
+            f64::from(value),
+            f64::from(min),
+            f64::from(max),
+            self.icons.len(),
+            self.out_of_range,
+            self.inverted,
+        )
+        .map_or_else(String::new, |idx| self.icons[idx].clone())
     }
 
     /// Parses a new instance with a given name from the global
@@ -51,11 +206,37 @@ impl Ramp {
     /// Ramps should be defined in a table called `[ramps]`. Each ramp should be
     /// a table with keys ranging from 0 to any number. The values should be
     /// [pango] markup strings.
+    ///
+    /// Each ramp table may also contain:
+    /// - `out_of_range`: `clamp`, `wrap`, or `hide`. See [`OutOfRange`].
+    ///   Defaults to `clamp`.
+    /// - `inverted`: `true` or `false`. Whether the icons are listed from the
+    ///   top of the range to the bottom. Defaults to `false`.
+    ///
+    /// Two ramps are built in, and are used if no ramp with the same name is
+    /// defined. Both show a colored dot using the colors of the
+    /// [`RampPalette`]:
+    /// - `ok_warn_crit`: for values where low is good, like temperature
+    /// - `crit_warn_ok`: for values where high is good, like battery charge
     #[must_use]
     pub fn parse(name: impl AsRef<str>) -> Option<Self> {
-        let ramps_table = parser::RAMPS.get().unwrap();
-        let mut ramp_table =
-            ramps_table.get(name.as_ref())?.clone().into_table().ok()?;
+        let ramps_table = parser::RAMPS.read().unwrap();
+        let Some(ramp) = ramps_table.get(name.as_ref()) else {
+            return Self::builtin(name.as_ref());
+        };
+        let mut ramp_table = ramp.clone().into_table().ok()?;
+        let out_of_range = remove_string_from_config(
+            "out_of_range",
+            &mut ramp_table,
+        )
+        .map_or(OutOfRange::Clamp, |name| {
+            OutOfRange::parse(name.as_str()).unwrap_or_else(|| {
+                log::warn!("Invalid out_of_range value {name}");
+                OutOfRange::Clamp
+            })
+        });
+        let inverted = remove_bool_from_config("inverted", &mut ramp_table)
+            .unwrap_or_default();
         let mut key = 0;
         let mut icons = Vec::new();
         while let Some(icon) =
@@ -64,13 +245,37 @@ impl Ramp {
             icons.push(icon);
             key += 1;
         }
-        Some(Self { icons })
+        Some(Self {
+            icons,
+            out_of_range,
+            inverted,
+            name: Some(name.as_ref().to_owned()),
+        })
+    }
+
+    fn builtin(name: &str) -> Option<Self> {
+        let palette = parser::RAMP_PALETTE.get().copied().unwrap_or_default();
+        let mut colors = palette.colors();
+        match name {
+            "ok_warn_crit" => {}
+            "crit_warn_ok" => colors.reverse(),
+            _ => return None,
+        }
+        Some(
+            colors
+                .iter()
+                .map(|color| format!("<span foreground='{color}'>●</span>"))
+                .collect(),
+        )
     }
 }
 
 impl From<Vec<String>> for Ramp {
     fn from(icons: Vec<String>) -> Self {
-        Self { icons }
+        Self {
+            icons,
+            ..Self::empty()
+        }
     }
 }
 
@@ -78,6 +283,7 @@ impl FromIterator<String> for Ramp {
     fn from_iter<T: IntoIterator<Item = String>>(iter: T) -> Self {
         Self {
             icons: iter.into_iter().collect(),
+            ..Self::empty()
         }
     }
 }
@@ -87,3 +293,282 @@ impl Extend<String> for Ramp {
         self.icons.extend(iter);
     }
 }
+
+/// Maps a value in a range to a color, blending smoothly between a list of
+/// stops. Unlike a [`Ramp`], which snaps between a few icons, this is meant
+/// for colors that should shift gradually, such as a panel's foreground (see
+/// [`Attrs::parse`][crate::Attrs::parse]).
+#[derive(Clone, Debug, PartialEq, PartialOrd, Default)]
+pub struct ColorRamp {
+    /// Each stop's position in the range, from 0 to 1, and its color, in
+    /// increasing order of position
+    stops: Vec<(f64, Color)>,
+    inverted: bool,
+}
+
+impl ColorRamp {
+    /// Creates an instance with the given colors spread evenly across the
+    /// range.
+    #[must_use]
+    pub fn new(colors: Vec<Color>) -> Self {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        Self {
+            stops: colors
+                .into_iter()
+                .enumerate()
+                .map(|(idx, color)| (idx as f64 / last, color))
+                .collect(),
+            inverted: false,
+        }
+    }
+
+    /// Parses a new instance with a given name from the global
+    /// [`Config`][config::Config].
+    ///
+    /// Color ramps are defined in the `[ramps]` table alongside other ramps.
+    /// A ramp is a color ramp if it has a `colors` key:
+    /// - `colors`: an array of colors, from the bottom of the range to the
+    ///   top. See [`csscolorparser::parse`]. Constants like `%{ok}` can be
+    ///   used.
+    /// - `positions`: an array with the position of each color in the range,
+    ///   from 0 to 1. Defaults to spreading the colors evenly.
+    /// - `inverted`: `true` or `false`. Whether the colors are listed from
+    ///   the top of the range to the bottom. Defaults to `false`.
+    ///
+    /// Values between two stops get a color between them, and values out of
+    /// range get the first or last color. The built-in `ok_warn_crit` and
+    /// `crit_warn_ok` ramps (see [`Ramp::parse`]) can also be used as color
+    /// ramps.
+    #[must_use]
+    pub fn parse(name: impl AsRef<str>) -> Option<Self> {
+        let ramps_table = parser::RAMPS.read().unwrap();
+        let Some(ramp) = ramps_table.get(name.as_ref()) else {
+            return Self::builtin(name.as_ref());
+        };
+        let mut ramp_table = ramp.clone().into_table().ok()?;
+        let consts = parser::CONSTS.read().unwrap();
+        let colors = remove_array_from_config("colors", &mut ramp_table)?
+            .into_iter()
+            .filter_map(|color| {
+                let color = color.into_string().ok()?;
+                replace_consts(color.as_str(), &consts)
+                    .parse::<Color>()
+                    .map_err(|e| log::warn!("Invalid color {color}: {e}"))
+                    .ok()
+            })
+            .collect::<Vec<_>>();
+        let positions = remove_array_from_config("positions", &mut ramp_table)
+            .map(|positions| {
+                positions
+                    .into_iter()
+                    .filter_map(|position| position.into_float().ok())
+                    .collect::<Vec<_>>()
+            });
+
+        let mut ramp = match positions {
+            Some(positions) if positions.len() == colors.len() => {
+                let mut stops = positions
+                    .into_iter()
+                    .map(|position| position.clamp(0.0, 1.0))
+                    .zip(colors)
+                    .collect::<Vec<_>>();
+                stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+                Self {
+                    stops,
+                    inverted: false,
+                }
+            }
+            Some(_) => {
+                log::warn!(
+                    "Color ramp {} has a different number of positions and \
+                     colors",
+                    name.as_ref()
+                );
+                Self::new(colors)
+            }
+            None => Self::new(colors),
+        };
+        ramp.inverted = remove_bool_from_config("inverted", &mut ramp_table)
+            .unwrap_or_default();
+        Some(ramp)
+    }
+
+    fn builtin(name: &str) -> Option<Self> {
+        let palette = parser::RAMP_PALETTE.get().copied().unwrap_or_default();
+        let mut colors = palette.colors();
+        match name {
+            "ok_warn_crit" => {}
+            "crit_warn_ok" => colors.reverse(),
+            _ => return None,
+        }
+        Some(Self::new(
+            colors
+                .iter()
+                .filter_map(|color| color.parse().ok())
+                .collect(),
+        ))
+    }
+
+    /// Returns the color for `value` in the range `[min, max]`, or [`None`]
+    /// if the ramp has no colors.
+    #[must_use]
+    pub fn color(&self, value: f64, min: f64, max: f64) -> Option<Color> {
+        let range = max - min;
+        let mut prop = if range.abs() < f64::EPSILON || value.is_nan() {
+            0.0
+        } else {
+            ((value - min) / range).clamp(0.0, 1.0)
+        };
+        if self.inverted {
+            prop = 1.0 - prop;
+        }
+
+        let after = self.stops.iter().position(|(pos, _)| *pos >= prop);
+        let (start, end) = match after {
+            Some(0) => return self.stops.first().map(|(_, c)| c.clone()),
+            Some(idx) => (&self.stops[idx - 1], &self.stops[idx]),
+            None => return self.stops.last().map(|(_, c)| c.clone()),
+        };
+        let span = end.0 - start.0;
+        let t = if span > 0.0 {
+            ((prop - start.0) / span) as f32
+        } else {
+            1.0
+        };
+        let lerp = |a: f32, b: f32| (b - a).mul_add(t, a);
+        Some(Color::new(
+            lerp(start.1.r, end.1.r),
+            lerp(start.1.g, end.1.g),
+            lerp(start.1.b, end.1.b),
+            lerp(start.1.a, end.1.a),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ramp_index, OutOfRange};
+
+    fn index(value: f64, min: f64, max: f64, len: usize) -> Option<usize> {
+        ramp_index(value, min, max, len, OutOfRange::Clamp, false)
+    }
+
+    #[test]
+    fn splits_range_evenly() {
+        assert_eq!(index(0.0, 0.0, 1.0, 4), Some(0));
+        assert_eq!(index(0.24, 0.0, 1.0, 4), Some(0));
+        assert_eq!(index(0.25, 0.0, 1.0, 4), Some(1));
+        assert_eq!(index(0.5, 0.0, 1.0, 4), Some(2));
+        assert_eq!(index(0.99, 0.0, 1.0, 4), Some(3));
+        // the top of the range belongs to the last icon
+        assert_eq!(index(1.0, 0.0, 1.0, 4), Some(3));
+    }
+
+    #[test]
+    fn other_ranges() {
+        assert_eq!(index(20.0, 20.0, 100.0, 4), Some(0));
+        assert_eq!(index(39.0, 20.0, 100.0, 4), Some(0));
+        assert_eq!(index(40.0, 20.0, 100.0, 4), Some(1));
+        assert_eq!(index(80.0, 20.0, 100.0, 4), Some(3));
+        assert_eq!(index(100.0, 20.0, 100.0, 4), Some(3));
+        assert_eq!(index(-5.0, -10.0, 10.0, 2), Some(0));
+        assert_eq!(index(0.0, -10.0, 10.0, 2), Some(1));
+        // a range from high to low runs the other way
+        assert_eq!(index(100.0, 100.0, 0.0, 4), Some(0));
+        assert_eq!(index(25.0, 100.0, 0.0, 4), Some(3));
+    }
+
+    #[test]
+    fn clamp() {
+        assert_eq!(index(-1.0, 0.0, 1.0, 4), Some(0));
+        assert_eq!(index(2.0, 0.0, 1.0, 4), Some(3));
+        assert_eq!(index(10.0, 20.0, 100.0, 4), Some(0));
+        assert_eq!(index(150.0, 20.0, 100.0, 4), Some(3));
+    }
+
+    #[test]
+    fn wrap() {
+        let wrap = |value, min, max| {
+            ramp_index(value, min, max, 4, OutOfRange::Wrap, false)
+        };
+        assert_eq!(wrap(1.25, 0.0, 1.0), Some(1));
+        assert_eq!(wrap(-0.25, 0.0, 1.0), Some(3));
+        assert_eq!(wrap(2.0, 0.0, 1.0), Some(0));
+        assert_eq!(wrap(120.0, 20.0, 100.0), Some(1));
+        // values in range aren't wrapped, so the top is still the last icon
+        assert_eq!(wrap(1.0, 0.0, 1.0), Some(3));
+    }
+
+    #[test]
+    fn hide() {
+        let hide = |value, min, max| {
+            ramp_index(value, min, max, 4, OutOfRange::Hide, false)
+        };
+        assert_eq!(hide(-0.1, 0.0, 1.0), None);
+        assert_eq!(hide(1.5, 0.0, 1.0), None);
+        assert_eq!(hide(0.0, 0.0, 1.0), Some(0));
+        assert_eq!(hide(1.0, 0.0, 1.0), Some(3));
+    }
+
+    #[test]
+    fn inverted() {
+        let inverted = |value, out_of_range| {
+            ramp_index(value, 0.0, 1.0, 4, out_of_range, true)
+        };
+        assert_eq!(inverted(0.0, OutOfRange::Clamp), Some(3));
+        assert_eq!(inverted(0.5, OutOfRange::Clamp), Some(1));
+        assert_eq!(inverted(1.0, OutOfRange::Clamp), Some(0));
+        assert_eq!(inverted(2.0, OutOfRange::Clamp), Some(0));
+        assert_eq!(inverted(1.25, OutOfRange::Wrap), Some(2));
+        assert_eq!(inverted(2.0, OutOfRange::Hide), None);
+    }
+
+    #[test]
+    fn empty_range() {
+        assert_eq!(index(5.0, 5.0, 5.0, 3), Some(0));
+        assert_eq!(index(4.0, 5.0, 5.0, 3), Some(0));
+        assert_eq!(index(6.0, 5.0, 5.0, 3), Some(2));
+        assert_eq!(
+            ramp_index(5.0, 5.0, 5.0, 3, OutOfRange::Hide, false),
+            Some(0)
+        );
+        assert_eq!(ramp_index(6.0, 5.0, 5.0, 3, OutOfRange::Hide, false), None);
+        assert_eq!(
+            ramp_index(6.0, 5.0, 5.0, 3, OutOfRange::Wrap, false),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn nan() {
+        for out_of_range in
+            [OutOfRange::Clamp, OutOfRange::Wrap, OutOfRange::Hide]
+        {
+            assert_eq!(
+                ramp_index(f64::NAN, 0.0, 1.0, 4, out_of_range, false),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn small_ramps() {
+        assert_eq!(index(0.5, 0.0, 1.0, 0), None);
+        assert_eq!(index(0.0, 0.0, 1.0, 1), Some(0));
+        assert_eq!(index(1.0, 0.0, 1.0, 1), Some(0));
+        assert_eq!(index(2.0, 0.0, 1.0, 1), Some(0));
+        assert_eq!(
+            ramp_index(0.5, 0.0, 1.0, 1, OutOfRange::Clamp, true),
+            Some(0)
+        );
+        assert_eq!(ramp_index(2.0, 0.0, 1.0, 1, OutOfRange::Hide, false), None);
+    }
+
+    #[test]
+    fn parse_out_of_range() {
+        assert_eq!(OutOfRange::parse("clamp"), Some(OutOfRange::Clamp));
+        assert_eq!(OutOfRange::parse("wrap"), Some(OutOfRange::Wrap));
+        assert_eq!(OutOfRange::parse("hide"), Some(OutOfRange::Hide));
+        assert_eq!(OutOfRange::parse("Clamp"), None);
+    }
+}
@@ -0,0 +1,209 @@
+use std::{
+    collections::HashMap, os::unix::process::CommandExt, process::Command,
+};
+
+use anyhow::Result;
+use config::Value;
+use derive_builder::Builder;
+use nix::sys::prctl;
+
+use crate::{
+    remove_array_from_config, remove_bool_from_config,
+    remove_string_from_config, remove_uint_from_config,
+};
+
+/// A program that a sandboxed command is run inside of.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Wrapper {
+    /// Run the command in a transient systemd scope with `systemd-run --user
+    /// --scope`.
+    SystemdRun,
+    /// Run the command with `bwrap`, using a read-only view of the
+    /// filesystem and new namespaces for everything but the network.
+    Bubblewrap,
+}
+
+/// Restrictions applied to commands spawned by a panel.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Default, Builder)]
+pub struct Sandbox {
+    /// Whether to set `PR_SET_NO_NEW_PRIVS` before running the command
+    #[builder(default)]
+    pub no_new_privs: bool,
+    /// Whether to clear the environment before running the command
+    #[builder(default)]
+    pub clear_env: bool,
+    /// The environment variables to keep if `clear_env` is set
+    #[builder(default = r#"vec![
+        String::from("PATH"),
+        String::from("HOME"),
+        String::from("DISPLAY"),
+    ]"#)]
+    pub keep_env: Vec<String>,
+    /// The niceness to run the command with
+    #[builder(default, setter(strip_option))]
+    pub nice: Option<u64>,
+    /// The arguments passed to `ionice`, e.g. `["-c", "3"]`
+    #[builder(default, setter(strip_option))]
+    pub ionice: Option<Vec<String>>,
+    /// The program the command should be run inside of
+    #[builder(default, setter(strip_option))]
+    pub wrapper: Option<Wrapper>,
+}
+
+impl Sandbox {
+    /// Attempts to parse an instance of this type from a subset of the global
+    /// [`Config`][config::Config].
+    ///
+    /// Configuration options:
+    /// - `no_new_privs`: Prevent the command from gaining privileges, e.g.
+    ///   through setuid binaries.
+    ///   - type: bool
+    ///   - default: false
+    /// - `clear_env`: Run the command with an empty environment, except for
+    ///   the variables listed in `keep_env`.
+    ///   - type: bool
+    ///   - default: false
+    /// - `keep_env`: The environment variables to keep when `clear_env` is
+    ///   set.
+    ///   - type: Array of Strings
+    ///   - default: `["PATH", "HOME", "DISPLAY"]`
+    /// - `nice`: Run the command with `nice -n <nice>`.
+    ///   - type: u64
+    ///   - default: none
+    /// - `ionice`: Run the command with `ionice`. The value is the scheduling
+    ///   class (`idle`, `best-effort`, or `realtime`), optionally followed by
+    ///   a colon and a priority level, e.g. `best-effort:7`.
+    ///   - type: String
+    ///   - default: none
+    /// - `wrapper`: Run the command inside of another program.
+    ///   - type: String - `systemd-run` or `bwrap`
+    ///   - default: none
+    pub fn parse<S: std::hash::BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Result<Self> {
+        let mut builder = SandboxBuilder::default();
+
+        if let Some(no_new_privs) =
+            remove_bool_from_config("no_new_privs", table)
+        {
+            builder.no_new_privs(no_new_privs);
+        }
+        if let Some(clear_env) = remove_bool_from_config("clear_env", table) {
+            builder.clear_env(clear_env);
+        }
+        if let Some(keep_env) = remove_array_from_config("keep_env", table) {
+            builder.keep_env(
+                keep_env
+                    .into_iter()
+                    .filter_map(|v| v.into_string().ok())
+                    .collect(),
+            );
+        }
+        if let Some(nice) = remove_uint_from_config("nice", table) {
+            builder.nice(nice);
+        }
+        if let Some(ionice) = remove_string_from_config("ionice", table) {
+            let (class, level) = ionice
+                .split_once(':')
+                .map_or((ionice.as_str(), None), |(c, l)| (c, Some(l)));
+            let class = match class {
+                "realtime" => "1",
+                "best-effort" => "2",
+                "idle" => "3",
+                c => {
+                    log::warn!("Unknown ionice class {c}, using best-effort");
+                    "2"
+                }
+            };
+            let mut args = vec![String::from("-c"), String::from(class)];
+            if let Some(level) = level {
+                args.push(String::from("-n"));
+                args.push(String::from(level));
+            }
+            builder.ionice(args);
+        }
+        if let Some(wrapper) = remove_string_from_config("wrapper", table) {
+            match wrapper.as_str() {
+                "systemd-run" => {
+                    builder.wrapper(Wrapper::SystemdRun);
+                }
+                "bwrap" => {
+                    builder.wrapper(Wrapper::Bubblewrap);
+                }
+                w => log::warn!("Ignoring unknown wrapper {w}"),
+            }
+        }
+
+        Ok(builder.build()?)
+    }
+
+    /// Creates a [`Command`] that runs `sh -c <command>` with the
+    /// restrictions described by this sandbox.
+    #[must_use]
+    pub fn command(&self, command: &str) -> Command {
+        let mut args: Vec<String> = Vec::new();
+
+        match self.wrapper {
+            Some(Wrapper::SystemdRun) => {
+                args.extend(
+                    ["systemd-run", "--user", "--scope", "--quiet", "--"]
+                        .map(String::from),
+                );
+            }
+            Some(Wrapper::Bubblewrap) => {
+                args.extend(
+                    [
+                        "bwrap",
+                        "--ro-bind",
+                        "/",
+                        "/",
+                        "--dev",
+                        "/dev",
+                        "--proc",
+                        "/proc",
+                        "--tmpfs",
+                        "/tmp",
+                        "--unshare-all",
+                        "--share-net",
+                        "--die-with-parent",
+                        "--",
+                    ]
+                    .map(String::from),
+                );
+            }
+            None => {}
+        }
+        if let Some(nice) = self.nice {
+            args.extend([String::from("nice"), String::from("-n")]);
+            args.push(nice.to_string());
+        }
+        if let Some(ref ionice) = self.ionice {
+            args.push(String::from("ionice"));
+            args.extend(ionice.iter().cloned());
+        }
+        args.extend(["sh", "-c", command].map(String::from));
+
+        let mut cmd = Command::new(args.remove(0));
+        cmd.args(args);
+
+        if self.clear_env {
+            cmd.env_clear();
+            for key in &self.keep_env {
+                if let Ok(value) = std::env::var(key) {
+                    cmd.env(key, value);
+                }
+            }
+        }
+
+        if self.no_new_privs {
+            // SAFETY: prctl is async-signal-safe
+            unsafe {
+                cmd.pre_exec(|| {
+                    prctl::set_no_new_privs().map_err(std::io::Error::from)
+                });
+            }
+        }
+
+        cmd
+    }
+}
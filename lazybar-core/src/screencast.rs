@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+use tokio::sync::mpsc::{
+    unbounded_channel, UnboundedReceiver, UnboundedSender,
+};
+use tokio_stream::StreamExt;
+use zbus::{
+    fdo::MonitoringProxy,
+    message::Type,
+    zvariant::{OwnedObjectPath, OwnedValue},
+    Connection, MatchRule, Message, MessageStream,
+};
+
+const SCREENCAST: &str = "org.freedesktop.portal.ScreenCast";
+const REQUEST: &str = "org.freedesktop.portal.Request";
+const SESSION: &str = "org.freedesktop.portal.Session";
+
+/// Watches the session bus for xdg-desktop-portal screen casts. The
+/// receiver gets `true` when the first screen cast starts and `false` when
+/// the last one ends.
+///
+/// Screen casts are found by monitoring calls to
+/// `org.freedesktop.portal.ScreenCast.Start` and the portal's replies, so
+/// this needs a bus that allows `BecomeMonitor` (dbus-daemon and
+/// dbus-broker both do for the bus's own user). If monitoring fails, the
+/// sender is dropped and nothing is ever received.
+#[must_use]
+pub fn watch() -> UnboundedReceiver<bool> {
+    let (send, recv) = unbounded_channel();
+    tokio::spawn(async move {
+        if let Err(e) = run(send).await {
+            log::warn!("Failed to watch for screen casts: {e}");
+        }
+    });
+    recv
+}
+
+/// Screen cast sessions, by the object path of the session
+#[derive(Debug, Default)]
+struct Sessions {
+    /// Sessions waiting for the user to allow the screen cast, keyed by the
+    /// path of the portal request
+    pending: HashMap<String, (String, String)>,
+    /// Sessions that are casting, with the unique name of their client
+    active: HashMap<String, String>,
+}
+
+impl Sessions {
+    /// Updates the sessions from a monitored message.
+    fn handle(&mut self, msg: &Message) -> Result<()> {
+        let header = msg.header();
+        let (Some(interface), Some(member), Some(path)) =
+            (header.interface(), header.member(), header.path())
+        else {
+            return Ok(());
+        };
+        let path = path.as_str();
+        match (interface.as_str(), member.as_str()) {
+            (SCREENCAST, "Start") => {
+                let Some(sender) = header.sender() else {
+                    return Ok(());
+                };
+                let (session, _, options): (
+                    OwnedObjectPath,
+                    String,
+                    HashMap<String, OwnedValue>,
+                ) = msg.body().deserialize()?;
+                let token = options
+                    .get("handle_token")
+                    .and_then(|token| token.downcast_ref::<&str>().ok());
+                if let Some(token) = token {
+                    // the request path is fixed by the portal spec, which
+                    // lets the response be matched without tracking replies
+                    let request = format!(
+                        "/org/freedesktop/portal/desktop/request/{}/{token}",
+                        sender.trim_start_matches(':').replace('.', "_")
+                    );
+                    self.pending.insert(
+                        request,
+                        (session.to_string(), sender.to_string()),
+                    );
+                } else {
+                    // without a token, the request can't be found, so the
+                    // session counts as soon as it's started
+                    self.active.insert(session.to_string(), sender.to_string());
+                }
+            }
+            (REQUEST, "Response") => {
+                if let Some((session, sender)) = self.pending.remove(path) {
+                    let (response, _): (u32, HashMap<String, OwnedValue>) =
+                        msg.body().deserialize()?;
+                    // 0 means the user allowed the screen cast
+                    if response == 0 {
+                        self.active.insert(session, sender);
+                    }
+                }
+            }
+            (SESSION, "Close" | "Closed") => {
+                self.active.remove(path);
+            }
+            ("org.freedesktop.DBus", "NameOwnerChanged") => {
+                let (name, _, new_owner): (String, String, String) =
+                    msg.body().deserialize()?;
+                // the portal closes the sessions of clients that leave the
+                // bus without telling anyone
+                if new_owner.is_empty() {
+                    self.pending.retain(|_, (_, sender)| *sender != name);
+                    self.active.retain(|_, sender| *sender != name);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+async fn run(send: UnboundedSender<bool>) -> Result<()> {
+    let conn = Connection::session().await?;
+    let rules = [
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(SCREENCAST)?
+            .member("Start")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(REQUEST)?
+            .member("Response")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::MethodCall)
+            .interface(SESSION)?
+            .member("Close")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .interface(SESSION)?
+            .member("Closed")?
+            .build(),
+        MatchRule::builder()
+            .msg_type(Type::Signal)
+            .sender("org.freedesktop.DBus")?
+            .interface("org.freedesktop.DBus")?
+            .member("NameOwnerChanged")?
+            .build(),
+    ];
+    MonitoringProxy::new(&conn)
+        .await?
+        .become_monitor(&rules, 0)
+        .await?;
+    log::debug!("Watching for screen casts");
+
+    let mut stream = MessageStream::from(conn);
+    let mut sessions = Sessions::default();
+    let mut casting = false;
+    while let Some(msg) = stream.next().await {
+        if let Err(e) = sessions.handle(&msg?) {
+            log::debug!("Error reading a screen cast message: {e}");
+            continue;
+        }
+        if sessions.active.is_empty() == casting {
+            casting = !casting;
+            log::debug!(
+                "Screen cast {}",
+                if casting { "started" } else { "stopped" }
+            );
+            if send.send(casting).is_err() {
+                break;
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,36 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use lazy_static::lazy_static;
+use tokio::sync::watch;
+
+lazy_static! {
+    static ref VALUES: Mutex<HashMap<String, String>> =
+        Mutex::new(HashMap::new());
+    static ref CHANGED: watch::Sender<()> = watch::channel(()).0;
+}
+
+/// Publishes a value under the key `<panel>.<key>`, notifying subscribers if
+/// it differs from the previous value.
+pub fn publish(panel: &str, key: &str, value: impl ToString) {
+    let value = value.to_string();
+    let mut values = VALUES.lock().unwrap();
+    let old = values.insert(format!("{panel}.{key}"), value.clone());
+    drop(values);
+
+    if old.as_ref() != Some(&value) {
+        CHANGED.send_replace(());
+    }
+}
+
+/// Returns the value most recently published under `key`, which should be of
+/// the form `<panel>.<key>`.
+#[must_use]
+pub fn get(key: &str) -> Option<String> {
+    VALUES.lock().unwrap().get(key).cloned()
+}
+
+/// Returns a receiver that is notified whenever any published value changes.
+#[must_use]
+pub fn subscribe() -> watch::Receiver<()> {
+    CHANGED.subscribe()
+}
@@ -0,0 +1,127 @@
+use std::{
+    any::{Any, TypeId},
+    cell::RefCell,
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
+
+use anyhow::{anyhow, Result};
+use config::{Config, File, FileFormat};
+use lazy_static::lazy_static;
+use tokio::sync::watch;
+
+use crate::parser;
+
+static GENERATION: AtomicUsize = AtomicUsize::new(0);
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+type Cache = HashMap<TypeId, HashMap<String, (usize, Box<dyn Any>)>>;
+
+lazy_static! {
+    static ref RELOADS: watch::Sender<usize> = watch::Sender::new(0);
+}
+
+thread_local! {
+    static CACHE: RefCell<Cache> = RefCell::new(HashMap::new());
+}
+
+/// Returns the number of times the styles have been reloaded.
+#[must_use]
+pub fn generation() -> usize {
+    GENERATION.load(Ordering::Relaxed)
+}
+
+/// Returns whether the styles have been reloaded since the config was first
+/// parsed.
+#[must_use]
+pub fn reloaded() -> bool {
+    generation() > 0
+}
+
+/// Returns the style of type `T` named `name` as `parse` reads it from the
+/// current styles. Each style is parsed at most once per reload on each
+/// thread, so restyling on every draw doesn't have to take the config locks.
+pub(crate) fn cached<T: Clone + 'static>(
+    name: &str,
+    parse: impl FnOnce(&str) -> Option<T>,
+) -> Option<T> {
+    let generation = generation();
+    let hit = CACHE.with_borrow(|cache| {
+        cache
+            .get(&TypeId::of::<T>())
+            .and_then(|styles| styles.get(name))
+            .filter(|(parsed, _)| *parsed == generation)
+            .and_then(|(_, style)| style.downcast_ref::<Option<T>>().cloned())
+    });
+    if let Some(style) = hit {
+        return style;
+    }
+
+    // parse without holding the cache, in case parsing restyles something
+    let style = parse(name);
+    CACHE.with_borrow_mut(|cache| {
+        cache
+            .entry(TypeId::of::<T>())
+            .or_default()
+            .insert(name.to_owned(), (generation, Box::new(style.clone())));
+    });
+    style
+}
+
+/// Returns a receiver that is notified each time the styles are reloaded, so
+/// that every bar can redraw itself.
+pub(crate) fn subscribe() -> watch::Receiver<usize> {
+    RELOADS.subscribe()
+}
+
+pub(crate) fn set_path(path: &Path) {
+    let _ = CONFIG_PATH.set(path.to_path_buf());
+}
+
+/// Fills in the style tables in [`parser`] from `config`.
+pub(crate) fn load(config: &Config) {
+    *parser::ATTRS.write().unwrap() =
+        config.get_table("attrs").unwrap_or_default();
+    *parser::RAMPS.write().unwrap() =
+        config.get_table("ramps").unwrap_or_default();
+    *parser::BGS.write().unwrap() = config.get_table("bgs").unwrap_or_default();
+    *parser::CONSTS.write().unwrap() =
+        config.get_table("consts").unwrap_or_default();
+    *parser::HIGHLIGHTS.write().unwrap() =
+        config.get_table("highlights").unwrap_or_default();
+}
+
+/// Re-reads the `attrs`, `ramps`, `bgs`, `consts`, and `highlights` tables
+/// from the config file. If the file can't be parsed, the current styles are
+/// kept.
+///
+/// Panels keep running, and the [`Attrs`][crate::Attrs],
+/// [`Ramp`][crate::Ramp]s, and [`Highlight`][crate::Highlight]s they were
+/// configured with are looked up again by name when they're next drawn. Every
+/// bar is redrawn once the styles are reloaded, but panels keep their current
+/// size until they next update. Styles that aren't referenced by name, like
+/// the bar's `bg`, aren't reloaded.
+pub fn reload() -> Result<()> {
+    let path = CONFIG_PATH
+        .get()
+        .ok_or_else(|| anyhow!("The config file hasn't been parsed yet"))?;
+    let config = Config::builder()
+        .add_source(
+            File::new(
+                path.to_str()
+                    .ok_or_else(|| anyhow!("Invalid config path"))?,
+                FileFormat::Toml,
+            )
+            .required(true),
+        )
+        .build()?;
+
+    load(&config);
+    RELOADS.send_replace(GENERATION.fetch_add(1, Ordering::Relaxed) + 1);
+    log::info!("Reloaded styles from {}", path.display());
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{borrow::Cow, collections::HashMap, hash::BuildHasher};
+
+use config::Value;
+use csscolorparser::Color;
+
+use crate::{
+    attrs::AttrsBuilder, parser, remove_float_from_config,
+    remove_string_from_config, Attrs,
+};
+
+/// How far past its thresholds a panel's value is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum Level {
+    /// The value hasn't passed any threshold.
+    #[default]
+    Ok,
+    /// The value has passed `warn_above` or `warn_below`.
+    Warn,
+    /// The value has passed `crit_above` or `crit_below`.
+    Crit,
+}
+
+/// Values at which a panel switches to a different set of attrs, e.g. to
+/// turn red when a CPU is busy or a battery is low. Panels that show a
+/// number check it against these every time they're drawn.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Thresholds {
+    /// The value above which the panel is at [`Level::Warn`]
+    pub warn_above: Option<f64>,
+    /// The value above which the panel is at [`Level::Crit`]
+    pub crit_above: Option<f64>,
+    /// The value below which the panel is at [`Level::Warn`]
+    pub warn_below: Option<f64>,
+    /// The value below which the panel is at [`Level::Crit`]
+    pub crit_below: Option<f64>,
+    /// The attrs used at [`Level::Warn`], or the `%{warn}` color if unset
+    pub warn: Option<Attrs>,
+    /// The attrs used at [`Level::Crit`], or the `%{crit}` color if unset
+    pub crit: Option<Attrs>,
+}
+
+impl Thresholds {
+    /// Parses thresholds from a panel's table. Every option is optional, and
+    /// a panel without any thresholds always uses its own attrs.
+    ///
+    /// Configuration options:
+    /// - `warn_above` and `crit_above`: the values above which the panel
+    ///   switches to its warning or critical attrs, e.g. 80 and 95 for a CPU.
+    /// - `warn_below` and `crit_below`: the values below which it does, e.g.
+    ///   20 and 10 for a battery.
+    /// - `attrs_warn` and `attrs_crit`: the names of the attrs to switch to.
+    ///   Options they don't set are taken from the panel's attrs. Default to
+    ///   the panel's attrs with the `%{warn}` or `%{crit}` color of the
+    ///   palette (see [`RampPalette`][crate::RampPalette]) as the foreground.
+    ///
+    /// The value is in the same units as the panel shows it, e.g. a
+    /// percentage for a CPU or degrees for a temperature.
+    pub fn parse<S: BuildHasher>(
+        table: &mut HashMap<String, Value, S>,
+    ) -> Self {
+        let attrs = |key: &str, table: &mut HashMap<String, Value, S>| {
+            remove_string_from_config(key, table).and_then(|name| {
+                Attrs::parse(name.as_str())
+                    .map_err(|e| log::warn!("Invalid {key} {name}: {e}"))
+                    .ok()
+            })
+        };
+        Self {
+            warn_above: remove_float_from_config("warn_above", table),
+            crit_above: remove_float_from_config("crit_above", table),
+            warn_below: remove_float_from_config("warn_below", table),
+            crit_below: remove_float_from_config("crit_below", table),
+            warn: attrs("attrs_warn", table),
+            crit: attrs("attrs_crit", table),
+        }
+    }
+
+    /// Returns how far past its thresholds `value` is. A NaN value is always
+    /// [`Level::Ok`].
+    #[must_use]
+    pub fn level(&self, value: f64) -> Level {
+        let above = |threshold: Option<f64>| {
+            threshold.is_some_and(|threshold| value > threshold)
+        };
+        let below = |threshold: Option<f64>| {
+            threshold.is_some_and(|threshold| value < threshold)
+        };
+        if above(self.crit_above) || below(self.crit_below) {
+            Level::Crit
+        } else if above(self.warn_above) || below(self.warn_below) {
+            Level::Warn
+        } else {
+            Level::Ok
+        }
+    }
+
+    /// Returns the attrs to draw a panel with when its value is `value`:
+    /// `attrs` itself, or the warning or critical attrs layered over it.
+    ///
+    /// The result is already restyled (see [`Attrs::restyled`]).
+    #[must_use]
+    pub fn attrs<'a>(&self, attrs: &'a Attrs, value: f64) -> Cow<'a, Attrs> {
+        let (level, name) = match self.level(value) {
+            Level::Ok => return attrs.restyled(),
+            Level::Warn => (&self.warn, "warn"),
+            Level::Crit => (&self.crit, "crit"),
+        };
+        let level = level.as_ref().map_or_else(
+            || {
+                let palette =
+                    parser::RAMP_PALETTE.get().copied().unwrap_or_default();
+                let mut builder = AttrsBuilder::default();
+                if let Some(color) = palette
+                    .color(name)
+                    .and_then(|color| color.parse::<Color>().ok())
+                {
+                    builder.fg(color);
+                }
+                Cow::Owned(builder.build().unwrap_or_default())
+            },
+            Attrs::restyled,
+        );
+        Cow::Owned(level.layered_over(attrs.restyled().as_ref()))
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use csscolorparser::Color;
+use pangocairo::functions::{create_layout, show_layout};
+use x11rb::{
+    connection::Connection,
+    protocol::{
+        randr::MonitorInfo,
+        xproto::{
+            ConfigureWindowAux, ConnectionExt, CreateWindowAux, EventMask,
+            Window, WindowClass,
+        },
+    },
+    xcb_ffi::XCBConnection,
+};
+
+use crate::{
+    format, high_contrast,
+    x::{beside_bar, create_surface, find_visual},
+    Attrs, Position,
+};
+
+/// The space in pixels between the text of a tooltip and its edges
+const PADDING: i32 = 4;
+
+/// A window next to the bar showing a panel's tooltip. The window is
+/// destroyed when this is dropped.
+pub(crate) struct Tooltip {
+    conn: Arc<XCBConnection>,
+    pub(crate) window: Window,
+    surface: cairo::XCBSurface,
+    /// The markup being shown
+    pub(crate) markup: String,
+    attrs: Attrs,
+    bg: Color,
+}
+
+impl Tooltip {
+    /// Shows a tooltip next to the bar, as close to the pointer as the
+    /// monitor allows. `bar` is the geometry of the bar window and `pointer`
+    /// is the position of the pointer, both relative to the root window.
+    pub(crate) fn show(
+        conn: Arc<XCBConnection>,
+        screen: usize,
+        markup: String,
+        attrs: &Attrs,
+        bg: &Color,
+        position: Position,
+        bar: (i16, i16, u16, u16),
+        mon: &MonitorInfo,
+        pointer: (i16, i16),
+    ) -> Result<Self> {
+        let screen = conn
+            .setup()
+            .roots
+            .get(screen)
+            .ok_or_else(|| anyhow!("Screen not found"))?;
+        let root = screen.root;
+        let visual = *find_visual(screen, 24)
+            .ok_or_else(|| anyhow!("Failed to find visual"))?;
+
+        // the size isn't known until the text has been laid out
+        let window = conn.generate_id()?;
+        conn.create_window(
+            24,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            visual.visual_id,
+            &CreateWindowAux::new()
+                .override_redirect(1)
+                .event_mask(EventMask::EXPOSURE),
+        )?;
+        let surface = create_surface(window, visual, 1, 1, conn.as_ref())?;
+        let tooltip = Self {
+            conn,
+            window,
+            surface,
+            markup,
+            attrs: attrs.restyled().into_owned(),
+            bg: bg.clone(),
+        };
+
+        let layout = tooltip.layout(&cairo::Context::new(&tooltip.surface)?);
+        let (text_width, text_height) = layout.pixel_size();
+        let (width, height) =
+            (text_width + 2 * PADDING, text_height + 2 * PADDING);
+
+        let along = if position.is_vertical() {
+            i32::from(pointer.1) - height / 2
+        } else {
+            i32::from(pointer.0) - width / 2
+        };
+        let (x, y) = beside_bar(position, bar, mon, along, (width, height));
+
+        tooltip.conn.configure_window(
+            window,
+            &ConfigureWindowAux::new()
+                .x(x)
+                .y(y)
+                .width(width as u32)
+                .height(height as u32),
+        )?;
+        tooltip.surface.set_size(width, height)?;
+        tooltip.conn.map_window(window)?;
+        tooltip.draw()?;
+
+        Ok(tooltip)
+    }
+
+    fn layout(&self, cr: &cairo::Context) -> pango::Layout {
+        let layout = create_layout(cr);
+        layout.set_markup(format::process(self.markup.as_str()).as_ref());
+        self.attrs.apply_font(&layout);
+        layout
+    }
+
+    /// Draws the tooltip, e.g. after it has been exposed.
+    pub(crate) fn draw(&self) -> Result<()> {
+        let cr = cairo::Context::new(&self.surface)?;
+        let bg = high_contrast::or(&self.bg, &high_contrast::BG);
+        cr.set_source_rgba(bg.r.into(), bg.g.into(), bg.b.into(), 1.0);
+        cr.paint()?;
+
+        let layout = self.layout(&cr);
+        self.attrs.apply_fg(&cr);
+        cr.move_to(f64::from(PADDING), f64::from(PADDING));
+        show_layout(&cr, &layout);
+
+        self.surface.flush();
+        self.conn.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Tooltip {
+    fn drop(&mut self) {
+        let _ = self.conn.destroy_window(self.window);
+        let _ = self.conn.flush();
+    }
+}
@@ -180,7 +180,7 @@ pub fn remove_string_from_config<S: std::hash::BuildHasher>(
             },
             |s| {
                 Some(
-                    replace_consts(s.as_str(), parser::CONSTS.get().unwrap())
+                    replace_consts(s.as_str(), &parser::CONSTS.read().unwrap())
                         .to_string(),
                 )
             },
@@ -211,7 +211,7 @@ pub fn remove_array_from_config<S: std::hash::BuildHasher>(
                                     ValueKind::String(
                                         replace_consts(
                                             val.as_str(),
-                                            parser::CONSTS.get().unwrap(),
+                                            &parser::CONSTS.read().unwrap(),
                                         )
                                         .to_string(),
                                     ),
@@ -289,7 +289,7 @@ pub fn remove_color_from_config<S: std::hash::BuildHasher>(
                 None
             },
             |val| {
-                replace_consts(val.as_str(), parser::CONSTS.get().unwrap())
+                replace_consts(val.as_str(), &parser::CONSTS.read().unwrap())
                     .parse()
                     .map_or_else(
                         |_| {
@@ -304,16 +304,34 @@ pub fn remove_color_from_config<S: std::hash::BuildHasher>(
 }
 
 /// Replace references to constants (of the form `%{const_name}`) with their
-/// respective constants
+/// respective constants. `%{else}` and `%{endif}` are left for conditional
+/// blocks (see [`format::process`][crate::format::process]), and
+/// `%{endhighlight}` and `%{endspan}` for blocks of a panel's text (see
+/// [`draw_panel`][crate::common::draw_panel]). A reference followed by `%` is
+/// an expression like `%{x}%` and is left alone as well.
 pub fn replace_consts<'a, S: std::hash::BuildHasher>(
     format: &'a str,
     consts: &HashMap<String, Value, S>,
 ) -> Cow<'a, str> {
     REGEX.replace_all(format, |caps: &Captures| {
         let con = &caps["const"];
+        let end = caps.get(0).map_or(0, |m| m.end());
+        if matches!(con, "else" | "endif" | "endhighlight" | "endspan")
+            || format[end..].starts_with('%')
+        {
+            return caps[0].to_string();
+        }
         consts
             .get(con)
             .and_then(|c| c.clone().into_string().ok())
+            .or_else(|| {
+                parser::RAMP_PALETTE
+                    .get()
+                    .copied()
+                    .unwrap_or_default()
+                    .color(con)
+                    .map(String::from)
+            })
             .map_or_else(
                 || {
                     log::warn!("Invalid constant: {con}");
@@ -323,3 +341,19 @@ pub fn replace_consts<'a, S: std::hash::BuildHasher>(
             )
     })
 }
+
+/// Formats a number of bytes with a binary unit suffix (`B`, `K`, `M`, `G`,
+/// `T`, or `P`) and one decimal place, e.g. `1.5G`.
+#[must_use]
+pub fn human_readable(bytes: u64) -> String {
+    const UNITS: [&str; 6] = ["B", "K", "M", "G", "T", "P"];
+
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    format!("{value:.1}{}", UNITS[unit])
+}
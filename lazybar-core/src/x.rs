@@ -1,25 +1,25 @@
 use std::{
-    pin::Pin,
-    sync::{Arc, Mutex},
-    task::{self, Poll},
+    sync::{Arc, Mutex, Once},
+    thread,
 };
 
 use anyhow::{Context, Result};
 use cairo::XCBSurface;
 use csscolorparser::Color;
-use futures::FutureExt;
 use lazy_static::lazy_static;
 use nix::unistd::gethostname;
-use tokio::task::JoinHandle;
-use tokio_stream::Stream;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedSender};
+use tokio_stream::wrappers::UnboundedReceiverStream;
 use x11rb::{
     connection::Connection,
     protocol::{
         randr::{ConnectionExt as _, MonitorInfo},
+        shape::{self, ConnectionExt as _},
         xproto::{
-            Atom, AtomEnum, Colormap, ColormapAlloc, ConnectionExt,
-            CreateWindowAux, EventMask, PropMode, Screen, VisualClass,
-            Visualtype, Window, WindowClass,
+            Atom, AtomEnum, ClientMessageEvent, ClipOrdering, Colormap,
+            ColormapAlloc, ConfigureWindowAux, ConnectionExt, CreateWindowAux,
+            Cursor, EventMask, PropMode, Rectangle, Screen, StackMode,
+            VisualClass, Visualtype, Window, WindowClass,
         },
         Event,
     },
@@ -27,13 +27,21 @@ use x11rb::{
     xcb_ffi::XCBConnection,
 };
 
-use crate::{interned_atoms, Position};
+use crate::{interned_atoms, Layer, Position};
 
 lazy_static! {
     static ref ATOMS: Arc<Mutex<InternedAtoms>> =
         Arc::new(Mutex::new(InternedAtoms::new()));
 }
 
+/// The connection that every bar shares, and the index of its default screen
+static CONNECTION: Mutex<Option<(Arc<XCBConnection>, usize)>> =
+    Mutex::new(None);
+/// One sender for each stream returned by [`events`]
+static LISTENERS: Mutex<Vec<UnboundedSender<Event>>> = Mutex::new(Vec::new());
+/// Starts the thread that reads events from [`CONNECTION`]
+static READER: Once = Once::new();
+
 interned_atoms!(
     InternedAtoms,
     ATOMS,
@@ -50,6 +58,8 @@ interned_atoms!(
     _NET_ACTIVE_WINDOW,
     _NET_DESKTOP_NAMES,
     _NET_WM_WINDOW_TYPE,
+    _NET_WM_STATE_ABOVE,
+    _NET_WM_STATE_BELOW,
     _NET_WM_STATE_STICKY,
     _NET_CURRENT_DESKTOP,
     _NET_WM_STRUT_PARTIAL,
@@ -59,48 +69,59 @@ interned_atoms!(
     _NET_WM_WINDOW_TYPE_DOCK,
     _NET_WM_WINDOW_TYPE_NORMAL,
     _NET_SYSTEM_TRAY_ORIENTATION,
+    _NET_WM_STATE_DEMANDS_ATTENTION,
 );
 
 pub fn intern_named_atom(conn: &impl Connection, atom: &[u8]) -> Result<Atom> {
     Ok(conn.intern_atom(true, atom)?.reply()?.atom)
 }
 
-#[derive(Debug)]
-pub struct XStream {
-    conn: Arc<XCBConnection>,
-    handle: Option<JoinHandle<Result<Event>>>,
-}
-
-impl XStream {
-    pub const fn new(conn: Arc<XCBConnection>) -> Self {
-        Self { conn, handle: None }
+/// Returns the X connection that every bar shares and the index of its
+/// default screen, connecting on first use.
+pub fn connection() -> Result<(Arc<XCBConnection>, usize)> {
+    let mut shared = CONNECTION.lock().unwrap();
+    if let Some(shared) = shared.as_ref() {
+        return Ok(shared.clone());
     }
+    let (conn, screen) = XCBConnection::connect(None)?;
+    let conn = Arc::new(conn);
+    *shared = Some((conn.clone(), screen));
+    Ok((conn, screen))
 }
 
-impl Stream for XStream {
-    type Item = Result<Event>;
-
-    fn poll_next(
-        mut self: Pin<&mut Self>,
-        cx: &mut task::Context<'_>,
-    ) -> Poll<Option<Self::Item>> {
-        if let Some(handle) = &mut self.handle {
-            let value = handle.poll_unpin(cx).map(Result::ok);
-            if handle.is_finished() {
-                self.handle = None;
+/// Returns a stream of the events on the shared connection. Every stream
+/// gets every event, including those for other bars' windows. The stream
+/// ends if the connection is lost.
+///
+/// Events that arrive before this is called are only sent to the streams
+/// that already exist, so a bar should call this before creating its
+/// windows.
+pub fn events() -> Result<UnboundedReceiverStream<Event>> {
+    let (conn, _) = connection()?;
+    let (send, recv) = unbounded_channel();
+    LISTENERS.lock().unwrap().push(send);
+    READER.call_once(|| {
+        // waiting blocks, so this gets its own thread rather than a
+        // blocking task that the runtime would wait on at shutdown
+        thread::spawn(move || loop {
+            match conn.wait_for_event() {
+                Ok(event) => LISTENERS
+                    .lock()
+                    .unwrap()
+                    .retain(|listener| listener.send(event.clone()).is_ok()),
+                Err(e) => {
+                    log::warn!(
+                        "X connection error (this probably points to an \
+                         issue external to lazybar): {e}"
+                    );
+                    // ending every stream tells the bars to shut down
+                    LISTENERS.lock().unwrap().clear();
+                    break;
+                }
             }
-            value
-        } else {
-            let conn = self.conn.clone();
-            let waker = cx.waker().clone();
-            self.handle = Some(tokio::task::spawn_blocking(move || {
-                let event = conn.wait_for_event();
-                waker.wake();
-                Ok(event?)
-            }));
-            Poll::Pending
-        }
-    }
+        });
+    });
+    Ok(UnboundedReceiverStream::new(recv))
 }
 
 pub fn find_visual(screen: &Screen, depth: u8) -> Option<&Visualtype> {
@@ -116,36 +137,108 @@ pub fn find_visual(screen: &Screen, depth: u8) -> Option<&Visualtype> {
     None
 }
 
+/// Returns the position and size of the window of a bar with a given height
+/// (its thickness, for bars on the side of the screen) on a monitor, as
+/// `(x, y, width, height)`.
+pub const fn bar_geometry(
+    position: Position,
+    height: u16,
+    mon: &MonitorInfo,
+) -> (i16, i16, u16, u16) {
+    match position {
+        Position::Top => (mon.x, mon.y, mon.width, height),
+        Position::Bottom => (
+            mon.x,
+            mon.y + mon.height.saturating_sub(height) as i16,
+            mon.width,
+            height,
+        ),
+        Position::Left => (mon.x, mon.y, height, mon.height),
+        Position::Right => (
+            mon.x + mon.width.saturating_sub(height) as i16,
+            mon.y,
+            height,
+            mon.height,
+        ),
+    }
+}
+
+/// Grows the geometry of a bar window by `extent` pixels on the side facing
+/// the rest of the monitor, which leaves room to draw a shadow.
+pub const fn with_shadow(
+    position: Position,
+    (x, y, width, height): (i16, i16, u16, u16),
+    extent: u16,
+) -> (i16, i16, u16, u16) {
+    match position {
+        Position::Top => (x, y, width, height + extent),
+        Position::Bottom => (x, y - extent as i16, width, height + extent),
+        Position::Left => (x, y, width + extent, height),
+        Position::Right => (x - extent as i16, y, width + extent, height),
+    }
+}
+
+/// Returns the position of a window of a given size placed against the
+/// inner edge of a bar whose window has the geometry `bar`. `along` is where
+/// the window should start along the length of the bar, relative to the root
+/// window, and is moved as needed to keep the window on the monitor.
+pub fn beside_bar(
+    position: Position,
+    bar: (i16, i16, u16, u16),
+    mon: &MonitorInfo,
+    along: i32,
+    (width, height): (i32, i32),
+) -> (i32, i32) {
+    let (bar_x, bar_y) = (i32::from(bar.0), i32::from(bar.1));
+    let (bar_width, bar_height) = (i32::from(bar.2), i32::from(bar.3));
+    let (mon_x, mon_y) = (i32::from(mon.x), i32::from(mon.y));
+    let along_x =
+        along.clamp(mon_x, (mon_x + i32::from(mon.width) - width).max(mon_x));
+    let along_y =
+        along.clamp(mon_y, (mon_y + i32::from(mon.height) - height).max(mon_y));
+    match position {
+        Position::Top => (along_x, bar_y + bar_height),
+        Position::Bottom => (along_x, bar_y - height),
+        Position::Left => (bar_x + bar_width, along_y),
+        Position::Right => (bar_x - width, along_y),
+    }
+}
+
 pub fn create_window(
     position: Position,
     height: u16,
     transparent: bool,
     background: &Color,
     monitor: Option<String>,
-) -> Result<(XCBConnection, usize, Window, u16, Visualtype, MonitorInfo)> {
-    let (conn, screen_idx) = XCBConnection::connect(None)?;
+) -> Result<(
+    Arc<XCBConnection>,
+    usize,
+    Window,
+    u16,
+    Visualtype,
+    MonitorInfo,
+)> {
+    let (conn, screen_idx) = connection()?;
     let window: Window = conn.generate_id()?;
     let colormap: Colormap = conn.generate_id()?;
     let screen = conn.setup().roots.get(screen_idx).unwrap();
 
-    let monitors = conn.randr_get_monitors(screen.root, true)?.reply()?;
-    let mut iter = monitors.monitors.iter();
-    let mon = if let Some(monitor) = monitor {
-        iter.find(|info| {
-            conn.get_atom_name(info.name).map_or(false, |cookie| {
-                cookie.reply().map_or(false, |reply| {
-                    String::from_utf8_lossy(reply.name.as_slice()) == monitor
-                })
-            })
-        })
-        .with_context(|| format!("No monitor found with name {monitor}"))?
+    let mon = find_monitor(&conn, screen.root, monitor.as_deref())?
+        .with_context(|| {
+            monitor.map_or_else(
+                || String::from("No monitors found"),
+                |monitor| format!("No monitor found with name {monitor}"),
+            )
+        })?;
+
+    // the length of the bar, which is its width before any rotation
+    let width = if position.is_vertical() {
+        mon.height
     } else {
-        iter.find(|info| info.primary)
-            .or_else(|| monitors.monitors.first())
-            .context("No monitors found")?
+        mon.width
     };
-
-    let width = mon.width;
+    let (x, y, window_width, window_height) =
+        bar_geometry(position, height, &mon);
 
     let depth = if transparent { 32 } else { 24 };
     let visual = *find_visual(screen, depth).expect("Failed to find visual");
@@ -176,25 +269,88 @@ pub fn create_window(
         depth,
         window,
         screen.root,
-        mon.x,
-        if position == Position::Top {
-            mon.y
-        } else {
-            mon.y + (mon.height - height) as i16
-        },
-        width,
-        height,
+        x,
+        y,
+        window_width,
+        window_height,
         0,
         WindowClass::INPUT_OUTPUT,
         visual.visual_id,
         &CreateWindowAux::new()
             .backing_pixel(bg)
             .border_pixel(bg)
-            .event_mask(EventMask::EXPOSURE | EventMask::BUTTON_PRESS)
+            .event_mask(
+                EventMask::EXPOSURE
+                    | EventMask::BUTTON_PRESS
+                    | EventMask::BUTTON_RELEASE
+                    | EventMask::ENTER_WINDOW
+                    | EventMask::LEAVE_WINDOW
+                    | EventMask::POINTER_MOTION,
+            )
             .colormap(colormap),
     )?;
 
-    Ok((conn, screen_idx, window, width, visual, mon.clone()))
+    Ok((conn, screen_idx, window, width, visual, mon))
+}
+
+/// Returns the name of the primary monitor, or the first monitor if none is
+/// marked as primary.
+pub fn primary_monitor_name() -> Result<String> {
+    let (conn, screen_idx) = XCBConnection::connect(None)?;
+    let screen = &conn.setup().roots[screen_idx];
+
+    let monitors = conn.randr_get_monitors(screen.root, true)?.reply()?;
+    let mon = monitors
+        .monitors
+        .iter()
+        .find(|info| info.primary)
+        .or_else(|| monitors.monitors.first())
+        .context("No monitors found")?;
+    let name = conn.get_atom_name(mon.name)?.reply()?.name;
+
+    Ok(String::from_utf8_lossy(name.as_slice()).into_owned())
+}
+
+/// Finds the monitor with a given name, or the primary monitor (failing
+/// that, the first monitor) if `monitor` is `None`. Returns `None` if there
+/// is no such monitor, e.g. because it has been disconnected.
+pub fn find_monitor(
+    conn: &impl Connection,
+    root: Window,
+    monitor: Option<&str>,
+) -> Result<Option<MonitorInfo>> {
+    let monitors = conn.randr_get_monitors(root, true)?.reply()?.monitors;
+    let mon = if let Some(monitor) = monitor {
+        monitors.into_iter().find(|info| {
+            conn.get_atom_name(info.name).map_or(false, |cookie| {
+                cookie.reply().map_or(false, |reply| {
+                    String::from_utf8_lossy(reply.name.as_slice()) == monitor
+                })
+            })
+        })
+    } else {
+        let primary = monitors.iter().position(|info| info.primary);
+        monitors.into_iter().nth(primary.unwrap_or(0))
+    };
+    Ok(mon)
+}
+
+/// Returns the names of every connected monitor, with the primary monitor
+/// first.
+pub fn monitor_names() -> Result<Vec<String>> {
+    let (conn, screen_idx) = XCBConnection::connect(None)?;
+    let screen = &conn.setup().roots[screen_idx];
+
+    let mut monitors = conn.randr_get_monitors(screen.root, true)?.reply()?;
+    monitors.monitors.sort_by_key(|info| !info.primary);
+    monitors
+        .monitors
+        .iter()
+        .map(|info| -> Result<String> {
+            let name = conn.get_atom_name(info.name)?.reply()?.name;
+            Ok(String::from_utf8_lossy(name.as_slice()).into_owned())
+        })
+        .collect()
 }
 
 pub fn set_wm_properties(
@@ -205,6 +361,8 @@ pub fn set_wm_properties(
     height: u32,
     bar_name: &str,
     mon: &MonitorInfo,
+    reserve_space: bool,
+    layer: Layer,
 ) {
     if let Ok(window_type_atom) =
         InternedAtoms::get(conn, "_NET_WM_WINDOW_TYPE")
@@ -222,36 +380,18 @@ pub fn set_wm_properties(
         }
     }
 
-    let strut = if position == Position::Top {
-        &[
-            0,
-            0,
-            height,
-            0,
-            0,
-            0,
-            0,
-            0,
-            mon.x as u32,
-            mon.x as u32 + width - 1,
-            0,
-            0,
-        ]
-    } else {
-        &[
-            0,
-            0,
-            0,
-            height,
-            0,
-            0,
-            0,
-            0,
-            0,
-            0,
-            mon.x as u32,
-            mon.x as u32 + width - 1,
-        ]
+    let (x, y) = (mon.x as u32, mon.y as u32);
+    let strut = match position {
+        // bars that hide themselves shouldn't leave a gap behind
+        _ if !reserve_space => [0; 12],
+        Position::Top => [0, 0, height, 0, 0, 0, 0, 0, x, x + width - 1, 0, 0],
+        Position::Bottom => {
+            [0, 0, 0, height, 0, 0, 0, 0, 0, 0, x, x + width - 1]
+        }
+        Position::Left => [height, 0, 0, 0, y, y + width - 1, 0, 0, 0, 0, 0, 0],
+        Position::Right => {
+            [0, height, 0, 0, 0, 0, y, y + width - 1, 0, 0, 0, 0]
+        }
     };
     if let Ok(strut_partial_atom) =
         InternedAtoms::get(conn, "_NET_WM_STRUT_PARTIAL")
@@ -261,7 +401,7 @@ pub fn set_wm_properties(
             window,
             strut_partial_atom,
             AtomEnum::CARDINAL,
-            strut,
+            &strut,
         );
     }
     if let Ok(strut_atom) = InternedAtoms::get(conn, "_NET_WM_STRUT") {
@@ -278,41 +418,29 @@ pub fn set_wm_properties(
         if let Ok(wm_state_sticky_atom) =
             InternedAtoms::get(conn, "_NET_WM_STATE_STICKY")
         {
+            let mut states = vec![wm_state_sticky_atom];
+            states.extend(layer_atom(conn, layer));
             let _ = conn.change_property32(
                 PropMode::REPLACE,
                 window,
                 wm_state_atom,
                 AtomEnum::ATOM,
-                &[wm_state_sticky_atom],
+                states.as_slice(),
             );
         }
     }
 
+    let (x, y, width, height) = bar_geometry(position, height as u16, mon);
+    let (x, y, width, height) =
+        (x as u32, y as u32, u32::from(width), u32::from(height));
     let _ = conn.change_property32(
         PropMode::REPLACE,
         window,
         AtomEnum::WM_NORMAL_HINTS,
         AtomEnum::WM_SIZE_HINTS,
         &[
-            0x3c,
-            mon.x as u32,
-            if position == Position::Top {
-                mon.y as u32
-            } else {
-                mon.y as u32 + mon.height as u32 - height
-            },
-            width,
-            height,
-            width,
-            height,
-            width,
-            height,
-            0,
-            0,
-            0,
-            0,
-            width,
-            height,
+            0x3c, x, y, width, height, width, height, width, height, 0, 0, 0,
+            0, width, height,
         ],
     );
 
@@ -404,6 +532,219 @@ impl From<Visualtype> for xcb_visualtype_t {
     }
 }
 
+/// Creates the hand cursor from the core X cursor font.
+pub fn hand_cursor(conn: &impl Connection) -> Result<Cursor> {
+    // `XC_hand2` in X11/cursorfont.h, followed by its mask
+    const HAND2: u16 = 60;
+
+    let font = conn.generate_id()?;
+    conn.open_font(font, b"cursor")?;
+    let cursor = conn.generate_id()?;
+    conn.create_glyph_cursor(
+        cursor,
+        font,
+        font,
+        HAND2,
+        HAND2 + 1,
+        0,
+        0,
+        0,
+        0xffff,
+        0xffff,
+        0xffff,
+    )?;
+    conn.close_font(font)?;
+    Ok(cursor)
+}
+
+/// Returns the `_NET_WM_STATE` atom that keeps a window in a layer, if the
+/// layer needs one.
+fn layer_atom(conn: &impl Connection, layer: Layer) -> Option<Atom> {
+    let name = match layer {
+        Layer::Above => "_NET_WM_STATE_ABOVE",
+        Layer::Normal => return None,
+        Layer::Below => "_NET_WM_STATE_BELOW",
+    };
+    InternedAtoms::get(conn, name).ok()
+}
+
+/// Moves a window that is already mapped to another layer by asking the
+/// window manager to change its `_NET_WM_STATE`, and restacks it for window
+/// managers that don't support the state. Windows that aren't mapped get
+/// their layer from [`set_wm_properties`] when they are.
+pub fn set_layer(
+    conn: &impl Connection,
+    root: Window,
+    window: Window,
+    layer: Layer,
+) -> Result<()> {
+    const REMOVE: u32 = 0;
+    const ADD: u32 = 1;
+
+    let wm_state_atom = InternedAtoms::get(conn, "_NET_WM_STATE")?;
+    let new = layer_atom(conn, layer);
+    for old in [Layer::Above, Layer::Below]
+        .into_iter()
+        .filter_map(|l| layer_atom(conn, l))
+        .filter(|&atom| Some(atom) != new)
+    {
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            ClientMessageEvent::new(
+                32,
+                window,
+                wm_state_atom,
+                [REMOVE, old, 0, 1, 0],
+            ),
+        )?;
+    }
+    if let Some(new) = new {
+        conn.send_event(
+            false,
+            root,
+            EventMask::SUBSTRUCTURE_NOTIFY | EventMask::SUBSTRUCTURE_REDIRECT,
+            ClientMessageEvent::new(
+                32,
+                window,
+                wm_state_atom,
+                [ADD, new, 0, 1, 0],
+            ),
+        )?;
+    }
+
+    match layer {
+        Layer::Above => {
+            conn.configure_window(
+                window,
+                &ConfigureWindowAux::new().stack_mode(StackMode::ABOVE),
+            )?;
+        }
+        Layer::Normal => {}
+        Layer::Below => {
+            conn.configure_window(
+                window,
+                &ConfigureWindowAux::new().stack_mode(StackMode::BELOW),
+            )?;
+        }
+    }
+    conn.flush()?;
+    Ok(())
+}
+
+/// Asks the compositor to blur whatever is behind a window. KWin reads
+/// `_KDE_NET_WM_BLUR_BEHIND_REGION`, where an empty region covers the whole
+/// window, and other compositors such as picom can match the property in
+/// their blur rules.
+pub fn set_blur(conn: &impl Connection, window: Window) -> Result<()> {
+    // unlike the other atoms, create this one if the compositor hasn't
+    let blur_atom = conn
+        .intern_atom(false, b"_KDE_NET_WM_BLUR_BEHIND_REGION")?
+        .reply()?
+        .atom;
+    conn.change_property32(
+        PropMode::REPLACE,
+        window,
+        blur_atom,
+        AtomEnum::CARDINAL,
+        &[],
+    )?;
+    Ok(())
+}
+
+/// Limits the part of a window that receives pointer events to a rectangle,
+/// so that clicks elsewhere fall through to the windows below.
+pub fn shape_input(
+    conn: &impl Connection,
+    window: Window,
+    (x, y, width, height): (i16, i16, u16, u16),
+) -> Result<()> {
+    conn.shape_rectangles(
+        shape::SO::SET,
+        shape::SK::INPUT,
+        ClipOrdering::UNSORTED,
+        window,
+        0,
+        0,
+        &[Rectangle {
+            x,
+            y,
+            width,
+            height,
+        }],
+    )?;
+    Ok(())
+}
+
+/// Removes rectangles from the part of a window that receives pointer
+/// events, so that clicks on them fall through to the windows below.
+pub fn subtract_input(
+    conn: &impl Connection,
+    window: Window,
+    holes: &[Rectangle],
+) -> Result<()> {
+    conn.shape_rectangles(
+        shape::SO::SUBTRACT,
+        shape::SK::INPUT,
+        ClipOrdering::UNSORTED,
+        window,
+        0,
+        0,
+        holes,
+    )?;
+    Ok(())
+}
+
+/// Shapes a window into a rectangle with rounded corners. This is only
+/// needed when the window can't be transparent, and the corners are jagged
+/// because each pixel is either in the window or not.
+pub fn round_corners(
+    conn: &impl Connection,
+    window: Window,
+    (width, height): (u16, u16),
+    radius: f64,
+) -> Result<()> {
+    let radius = radius.min(f64::from(width.min(height)) / 2.0).max(0.0);
+    let rows = radius.ceil() as u16;
+    let mut rectangles = Vec::with_capacity(2 * usize::from(rows) + 1);
+    for row in 0..rows {
+        let dy = radius - f64::from(row) - 0.5;
+        let inset = (radius - dy.mul_add(-dy, radius * radius).max(0.0).sqrt())
+            .round() as u16;
+        let width = width.saturating_sub(2 * inset);
+        rectangles.push(Rectangle {
+            x: inset as i16,
+            y: row as i16,
+            width,
+            height: 1,
+        });
+        rectangles.push(Rectangle {
+            x: inset as i16,
+            y: (height - 1 - row) as i16,
+            width,
+            height: 1,
+        });
+    }
+    rectangles.push(Rectangle {
+        x: 0,
+        y: rows as i16,
+        width,
+        height: height.saturating_sub(2 * rows),
+    });
+
+    conn.shape_rectangles(
+        shape::SO::SET,
+        shape::SK::BOUNDING,
+        ClipOrdering::UNSORTED,
+        window,
+        0,
+        0,
+        &rectangles,
+    )?;
+    Ok(())
+}
+
 pub fn create_surface(
     window: Window,
     visual: Visualtype,
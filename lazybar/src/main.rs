@@ -1,11 +1,11 @@
 use std::{io, path::PathBuf};
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use clap::{
     crate_name, crate_version, value_parser, Arg, ArgAction, Command, ValueHint,
 };
 use clap_complete::{generate, Generator, Shell};
-use lazybar_core::parser;
+use lazybar_core::{parser, BarConfig};
 use log::LevelFilter;
 use simple_logger::SimpleLogger;
 
@@ -52,10 +52,51 @@ fn main() -> Result<()> {
                 .help(
                     "specify the name of the bar to read from the config file",
                 )
+                .long_help(
+                    "specify the names of the bars to read from the config \
+                     file\nEvery bar runs on one thread and shares one X \
+                     connection",
+                )
                 .value_name("BAR")
                 .value_hint(ValueHint::Other)
-                .action(ArgAction::Set)
-                .required(true),
+                .num_args(1..)
+                .action(ArgAction::Append)
+                .required_unless_present("all"),
+        )
+        .arg(
+            Arg::new("all")
+                .long("all")
+                .help("run every bar in the config file")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("bar"),
+        )
+        .arg(
+            Arg::new("monitor")
+                .short('m')
+                .long("monitor")
+                .help("specify the monitor to display the bar on")
+                .long_help(
+                    "specify the monitor to display the bar on\nThis \
+                     overrides the bar's `monitor` option and selects which \
+                     of its `monitor_overrides` are applied, so the same bar \
+                     can be run on several monitors",
+                )
+                .value_name("MONITOR")
+                .value_hint(ValueHint::Other)
+                .action(ArgAction::Set),
+        )
+        .arg(
+            Arg::new("demo")
+                .long("demo")
+                .help("run the bar with simulated data")
+                .long_help(
+                    "run the bar with simulated data\nThe battery, network, \
+                     and xworkspaces panels show a draining and charging \
+                     battery, a connection that drops periodically, and \
+                     scripted workspace changes instead of reading the real \
+                     system, so themes and layouts can be designed anywhere",
+                )
+                .action(ArgAction::SetTrue),
         )
         .arg(
             Arg::new("verbosity")
@@ -85,6 +126,10 @@ fn main() -> Result<()> {
         .init()
         .unwrap();
 
+    if args.get_flag("demo") {
+        lazybar_core::demo::enable();
+    }
+
     // the provided path, failing that
     // $XDG_CONFIG_HOME/lazybar/config.toml, failing that
     // $HOME/.config/lazybar/config.toml, failing that
@@ -102,12 +147,35 @@ fn main() -> Result<()> {
         &PathBuf::from("/etc/lazybar/config.toml")
     };
 
-    let config = parser::parse(
-        args.get_one::<String>("bar").unwrap().as_str(),
-        path.as_path(),
-    )?;
+    let bars = if args.get_flag("all") {
+        parser::bar_names(path.as_path())?
+    } else {
+        args.get_many::<String>("bar")
+            .unwrap_or_default()
+            .cloned()
+            .collect()
+    };
+    let mut instances = Vec::new();
+    for bar in bars {
+        for monitor in parser::monitors(
+            bar.as_str(),
+            path.as_path(),
+            args.get_one::<String>("monitor").cloned(),
+        )? {
+            instances.push((bar.clone(), monitor));
+        }
+    }
 
-    config.run()?;
+    if instances.is_empty() {
+        return Err(anyhow!("No bars to run"));
+    }
+    let configs = instances
+        .into_iter()
+        .map(|(bar, monitor)| {
+            parser::parse(bar.as_str(), path.as_path(), monitor)
+        })
+        .collect::<Result<Vec<_>>>()?;
 
-    Ok(())
+    // panels aren't Send, so every bar runs on this thread
+    BarConfig::run_all(configs)
 }